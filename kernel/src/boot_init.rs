@@ -4,13 +4,18 @@
 //!   1. `init_interrupt_system` — GDT, IDT, PIC, keyboard, timer, SYSCALL, SMEP
 //!   2. `init_memory_and_fs`    — heap, RamFS, FAT, ext2, env, network
 //!   3. `test_paging_allocation` — allocator smoke-test (debug build helper)
+//!   4. `start_init`            — spawn `/sbin/init` as PID 1
+//!
+//! `run_text_mode_kernel` replaces the GUI loop when there is no framebuffer
+//! or the kernel command line says `headless`.
 //!
 //! Diagnostic helpers (`check_system_tables_64bit`, `verify_idt_entries_64bit`,
 //! `test_64bit_interrupts`) are called internally from `init_interrupt_system`.
 
 use core::arch::asm;
 use crate::kernel::serial::SERIAL_PORT;
-use crate::kernel::{gdt, idt, interrupts, timer, pic, keyboard,
+use crate::gui::{fonts, graphics::Graphics};
+use crate::kernel::{gdt, idt, timer, pic, keyboard, vt,
                     syscall_handler, paging_allocator};
 
 // ── Interrupt system ──────────────────────────────────────────────────────────
@@ -42,6 +47,17 @@ pub unsafe fn init_interrupt_system() {
     timer::init(100);
     SERIAL_PORT.write_str("  ✓ Timer at 100Hz\n");
 
+    unsafe { SERIAL_PORT.write_str("Step 6.5: Seeding kernel RNG...\n"); }
+    crate::kernel::rng::init();
+    let seeded = if crate::kernel::rng::has_seed_instruction() {
+        "  ✓ ChaCha20 RNG seeded from RDSEED + RDTSC jitter\n"
+    } else if crate::kernel::rng::hardware_backed() {
+        "  ✓ ChaCha20 RNG seeded from RDRAND + RDTSC jitter\n"
+    } else {
+        "  ✓ ChaCha20 RNG seeded from RDTSC jitter (no RDRAND)\n"
+    };
+    unsafe { SERIAL_PORT.write_str(seeded); }
+
     SERIAL_PORT.write_str("Step 7: Testing interrupt system...\n");
     test_64bit_interrupts();
     SERIAL_PORT.write_str("✓ 64-bit interrupt system fully operational\n");
@@ -67,7 +83,7 @@ pub unsafe fn init_interrupt_system() {
 pub unsafe fn init_memory_and_fs(
     memory_map: &limine::request::MemoryMapRequest,
 ) {
-    use crate::kernel::{fs::ramfs::RAMFS, procfs, env, ata, disk_store, diskfs, mbr, fat, ext2, net};
+    use crate::kernel::{fs::ramfs::RAMFS, procfs, devfs, env, ata, disk_store, diskfs, mbr, fat, ext2, net};
     use crate::kernel::mbr::PTYPE_LINUX;
 
     paging_allocator::init_paging_heap(memory_map);
@@ -79,6 +95,12 @@ pub unsafe fn init_memory_and_fs(
     procfs::populate();
     SERIAL_PORT.write_str("✓ procfs initialized\n");
 
+    devfs::init();
+    crate::kernel::tty::register_devices();
+    crate::kernel::pty::register_devices();
+    crate::kernel::evdev::register_devices();
+    unsafe { SERIAL_PORT.write_str("✓ devfs initialized\n"); }
+
     env::init_defaults();
     SERIAL_PORT.write_str("✓ Environment initialized\n");
 
@@ -89,6 +111,13 @@ pub unsafe fn init_memory_and_fs(
     diskfs::populate();
     SERIAL_PORT.write_str("✓ diskfs populated\n");
 
+    unsafe {
+        crate::kernel::keymap::restore();
+        SERIAL_PORT.write_str("✓ Keyboard layout: ");
+        SERIAL_PORT.write_str(crate::kernel::keyboard::layout().name());
+        SERIAL_PORT.write_str("\n");
+    }
+
     mbr::init();
     fat::init();
 
@@ -109,7 +138,11 @@ pub unsafe fn init_memory_and_fs(
         ext2::init(part_lba);
     }
 
+    // Auto-enable the first MBR swap partition (type 0x82), if any.
+    unsafe { crate::kernel::swap::init(); }
+
     net::init();
+    unsafe { crate::kernel::ac97::init(); }
 }
 
 // ── Allocator smoke-test ──────────────────────────────────────────────────────
@@ -198,20 +231,68 @@ unsafe fn test_64bit_interrupts() {
 
     pic::unmask_irq(1);
     SERIAL_PORT.write_str("  ✓ Keyboard interrupts enabled\n");
+
+    unsafe {
+        SERIAL_PORT.enable_rx_interrupt();
+        pic::unmask_irq(4);
+        SERIAL_PORT.write_str("  ✓ Serial (ttyS0) interrupts enabled\n");
+    }
 }
 
-// ── Text-mode fallback ────────────────────────────────────────────────────────
+// ── init (PID 1) ──────────────────────────────────────────────────────────────
 
-pub unsafe fn run_text_mode_kernel() -> ! {
-    SERIAL_PORT.write_str("Running in text mode - no GUI available\n");
-    let mut counter = 0u64;
-    loop {
-        counter += 1;
-        if counter % 10_000_000 == 0 {
-            SERIAL_PORT.write_str("Heartbeat: ");
-            SERIAL_PORT.write_decimal(counter as u32);
+/// Spawn `/sbin/init` before anything else so it gets PID 1.  It runs the
+/// boot scripts and starts every other long-lived process.
+pub unsafe fn start_init() {
+    match unsafe { crate::kernel::scheduler::spawn_init(crate::kernel::programs::INIT) } {
+        Ok(_)  => unsafe { SERIAL_PORT.write_str("✓ /sbin/init started as pid 1\n") },
+        Err(e) => unsafe {
+            SERIAL_PORT.write_str("✗ /sbin/init: ");
+            SERIAL_PORT.write_str(e);
             SERIAL_PORT.write_str("\n");
         }
+    }
+}
+
+// ── Text mode ─────────────────────────────────────────────────────────────────
+
+/// No GUI loop — either there is no framebuffer or the kernel was booted
+/// with `headless`: keep init and its services (the gettys on ttyS0 and the
+/// virtual consoles) running.  With a framebuffer the active virtual
+/// console is drawn on it; Alt+F1..F6 switches consoles.
+pub unsafe fn run_text_mode_kernel(graphics: Option<Graphics>) -> ! {
+    unsafe { SERIAL_PORT.write_str("Running in text mode - no GUI\n"); }
+    crate::kernel::vt::enable();
+    loop {
+        unsafe { keyboard::poll(); }
+        let _ = unsafe { crate::kernel::scheduler::tick() };
+        unsafe { crate::kernel::net::poll(); }
+        if let Some(g) = &graphics {
+            if vt::take_dirty() { draw_console(g); }
+        }
         unsafe { core::arch::asm!("hlt"); }
     }
 }
+
+/// Draw the active virtual console, centred, with a block cursor.
+fn draw_console(g: &Graphics) {
+    let (w, h) = g.get_dimensions();
+    let x0 = w.saturating_sub(vt::COLS as u64 * 8) / 2;
+    let y0 = h.saturating_sub(vt::ROWS as u64 * 16) / 2;
+    vt::with_active(|cells, (crow, ccol)| {
+        for (r, line) in cells.iter().enumerate() {
+            for (c, cell) in line.iter().enumerate() {
+                let (fg, bg) = match (r, c) == (crow, ccol) {
+                    true  => (0, 7),
+                    false => (cell.attr & 0x0F, cell.attr >> 4),
+                };
+                let (x, y) = (x0 + c as u64 * 8, y0 + r as u64 * 16);
+                g.fill_rect(x, y, 8, 16, vt::PALETTE[bg as usize]);
+                if cell.ch > b' ' && cell.ch < 0x7F {
+                    fonts::draw_char(g, x, y, cell.ch as char, vt::PALETTE[fg as usize]);
+                }
+            }
+        }
+    });
+    g.present();
+}
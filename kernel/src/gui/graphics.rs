@@ -90,8 +90,13 @@ impl Graphics {
         let back_ptr = back_vec.as_mut_ptr();
         core::mem::forget(back_vec);
 
-        let fb_addr = framebuffer.addr();
         let fb_pitch = framebuffer.pitch();
+        // Move the framebuffer onto 2 MiB write-combining pages if possible.
+        let fb_addr = unsafe {
+            crate::kernel::paging_allocator::remap_framebuffer(
+                framebuffer.addr() as u64, (fb_pitch * height) as usize)
+        } as *mut u8;
+        crate::kernel::fbdev::register(&framebuffer, fb_addr as u64);
 
         // Publish for the panic handler.
         unsafe {
@@ -291,6 +291,13 @@ impl PS2Mouse {
         let dx: i16 = if (flags & 0x10) != 0 { dx_raw as i16 - 256 } else { dx_raw as i16 };
         let dy: i16 = if (flags & 0x20) != 0 { dy_raw as i16 - 256 } else { dy_raw as i16 };
 
+        // /dev/input/event1 sees every packet (screen orientation: y down);
+        // while a client has grabbed it the desktop sees none.
+        unsafe {
+            crate::kernel::evdev::mouse(dx as i32, -(dy as i32), flags & 0x07);
+            if crate::kernel::evdev::grabbed(crate::kernel::evdev::MOUSE) { return; }
+        }
+
         // Update button states
         self.left_button   = (flags & 0x01) != 0;
         self.right_button  = (flags & 0x02) != 0;
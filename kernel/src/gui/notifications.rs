@@ -44,7 +44,8 @@ impl NotificationManager {
         Self { slots: [None; MAX_NOTIFS] }
     }
 
-    /// Push a new notification. Oldest is evicted if all slots are full.
+    /// Push a new notification and sound the event chime. Oldest is evicted
+    /// if all slots are full.
     pub fn push(&mut self, title: &str, body: &str, icon_color: u32) {
         let mut n = Notification::empty();
         n.icon_color = icon_color;
@@ -60,6 +61,8 @@ impl NotificationManager {
         n.body[..bl].copy_from_slice(&bb[..bl]);
         n.body_len = bl;
 
+        crate::kernel::speaker::event();
+
         // Find a free slot
         for slot in self.slots.iter_mut() {
             if slot.is_none() {
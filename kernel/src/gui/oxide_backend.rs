@@ -45,7 +45,7 @@ const CAP: usize = 64;
 
 #[derive(Copy, Clone)]
 enum RawEv {
-    Byte(u8),
+    Char(char),
     Arrow(Dir),
 }
 
@@ -75,8 +75,8 @@ unsafe fn pop() -> Option<RawEv> {
 // `register_gui_key_callback` is the *secondary* slot — it fires alongside
 // whatever the main GUI loop registered as the primary callback.
 
-unsafe fn on_char(byte: u8) {
-    push(RawEv::Byte(byte));
+unsafe fn on_char(ch: char) {
+    push(RawEv::Char(ch));
 }
 
 unsafe fn on_arrow(k: KbArrow) {
@@ -171,7 +171,7 @@ impl<'a> Backend for OxideBackend<'a> {
         // 2. Drain the keyboard ring buffer.
         if let Some(raw) = unsafe { pop() } {
             return Some(match raw {
-                RawEv::Byte(b)  => Event::KeyDown(byte_to_key(b)),
+                RawEv::Char(c)  => Event::KeyDown(char_to_key(c)),
                 RawEv::Arrow(d) => Event::KeyDown(dir_to_key(d)),
             });
         }
@@ -225,15 +225,15 @@ impl<'a> Backend for OxideBackend<'a> {
 
 // ── Key translation ───────────────────────────────────────────────────────────
 
-fn byte_to_key(b: u8) -> Key {
-    match b {
-        0x08 | 0x7F => Key::Backspace,
-        0x09        => Key::Tab,
-        0x0A | 0x0D => Key::Enter,
-        0x1B        => Key::Escape,
-        0x20        => Key::Space,
-        0x21..=0x7E => Key::Char(b as char),
-        _           => Key::Unknown,
+fn char_to_key(c: char) -> Key {
+    match c {
+        '\x08' | '\x7F'       => Key::Backspace,
+        '\t'                  => Key::Tab,
+        '\n' | '\r'           => Key::Enter,
+        '\x1B'                => Key::Escape,
+        ' '                   => Key::Space,
+        c if !c.is_control()  => Key::Char(c),
+        _                     => Key::Unknown,
     }
 }
 
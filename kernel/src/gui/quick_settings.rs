@@ -3,6 +3,8 @@
 
 use crate::gui::graphics::Graphics;
 use crate::gui::fonts;
+use crate::kernel::keymap::LAYOUTS;
+use core::sync::atomic::{AtomicBool, Ordering};
 
 // ── Panel geometry ────────────────────────────────────────────────────────────
 const PANEL_W:    u64 = 380;
@@ -54,9 +56,9 @@ pub struct QuickSettings {
     pub visible: bool,
     pub tab: QsTab,
     pub brightness: u8,   // 0–100
-    pub volume:     u8,   // 0–100
     dragging_brightness: bool,
     dragging_volume:     bool,
+    dragging_pcm:        bool,
     hovered_shutdown: bool,
     hovered_reboot:   bool,
 }
@@ -67,9 +69,9 @@ impl QuickSettings {
             visible: false,
             tab: QsTab::Display,
             brightness: 80,
-            volume: 65,
             dragging_brightness: false,
             dragging_volume:     false,
+            dragging_pcm:        false,
             hovered_shutdown: false,
             hovered_reboot:   false,
         }
@@ -80,6 +82,7 @@ impl QuickSettings {
         self.visible = false;
         self.dragging_brightness = false;
         self.dragging_volume = false;
+        self.dragging_pcm = false;
     }
 
     /// Open directly on the Time tab (e.g. when the clock is clicked).
@@ -137,6 +140,24 @@ impl QuickSettings {
         (bx, by, Self::TZ_BTN_W, Self::TZ_BTN_H)
     }
 
+    // ── Sound tab: the PCM slider sits one section below Master ──────────────
+    const PCM_OFF_Y: u64 = 62;
+    /// First of the System/Event sounds switches, below the Output section.
+    const SOUND_TOGGLES_OFF_Y: u64 = Self::PCM_OFF_Y * 2 + 46;
+
+    // ── Keyboard layout buttons (Display tab, 3 columns × 2 rows) ─────────────
+    const KB_SECTION_OFF_Y: u64 = 144;
+    const KB_GRID_OFF_Y:    u64 = Self::KB_SECTION_OFF_Y + 24;
+    const KB_BTN_W:         u64 = (PANEL_W - CONTENT_PAD * 2 - 16) / 3;
+
+    fn layout_button_rect(px: u64, content_y: u64, idx: usize) -> (u64, u64, u64, u64) {
+        let row = (idx / 3) as u64;
+        let col = (idx % 3) as u64;
+        let bx = px + CONTENT_PAD + col * (Self::KB_BTN_W + 8);
+        let by = content_y + Self::KB_GRID_OFF_Y + row * Self::TZ_BTN_ROW_H;
+        (bx, by, Self::KB_BTN_W, Self::TZ_BTN_H)
+    }
+
     fn current_tz_index() -> usize {
         let offset = crate::kernel::rtc::get_tz_offset();
         for (i, &(_, _, ofs)) in TIMEZONES.iter().enumerate() {
@@ -173,16 +194,28 @@ impl QuickSettings {
         }
         if left_held && self.tab == QsTab::Sound {
             let content_y = PANEL_TOP + TAB_H + 12;
-            let vcy = content_y + 44;
-            let (sx, sy, sw) = Self::slider_rect(px, vcy);
-            if my >= sy && my < sy + SLIDER_H && mx >= sx && mx <= sx + sw {
-                self.volume = Self::value_from_click(sx, sw, mx);
-                self.dragging_volume = true;
-            }
+            self.drag_sound_sliders(px, content_y, mx, my);
         }
         if !left_held {
             self.dragging_brightness = false;
             self.dragging_volume     = false;
+            self.dragging_pcm        = false;
+        }
+    }
+
+    /// Master and PCM sliders on the Sound tab set the sound card's mixer.
+    fn drag_sound_sliders(&mut self, px: u64, content_y: u64, mx: u64, my: u64) {
+        use crate::kernel::ac97::{self, Control};
+        for (off, control) in [(44, Control::Master), (44 + Self::PCM_OFF_Y, Control::Pcm)] {
+            let (sx, sy, sw) = Self::slider_rect(px, content_y + off);
+            if my >= sy && my < sy + SLIDER_H && mx >= sx && mx <= sx + sw {
+                let v = Self::value_from_click(sx, sw, mx);
+                unsafe { ac97::set_volume(control, v, v); }
+                match control {
+                    Control::Master => self.dragging_volume = true,
+                    Control::Pcm    => self.dragging_pcm    = true,
+                }
+            }
         }
     }
 
@@ -234,11 +267,30 @@ impl QuickSettings {
             }
         }
         if self.tab == QsTab::Sound {
-            let vcy = content_y + 44;
-            let (sx, sy, sw) = Self::slider_rect(px, vcy);
-            if my >= sy && my < sy + SLIDER_H && mx >= sx && mx <= sx + sw {
-                self.volume = Self::value_from_click(sx, sw, mx);
-                self.dragging_volume = true;
+            self.drag_sound_sliders(px, content_y, mx, my);
+        }
+
+        // Sound toggles — a switch turned on plays the sound it enables
+        if self.tab == QsTab::Sound {
+            use crate::kernel::speaker::{self, BELL, EVENTS};
+            let cy3 = content_y + Self::SOUND_TOGGLES_OFF_Y;
+            let rows: [(&AtomicBool, fn()); 2] = [(&BELL, speaker::bell), (&EVENTS, speaker::event)];
+            for (i, (flag, sample)) in rows.into_iter().enumerate() {
+                let ry = cy3 + i as u64 * 28;
+                if my + 1 >= ry && my < ry + 17 && mx >= px + CONTENT_PAD && mx < px + PANEL_W - CONTENT_PAD {
+                    if !flag.fetch_xor(true, Ordering::Relaxed) { sample(); }
+                }
+            }
+        }
+
+        // Keyboard layout buttons — the choice is saved to disk
+        if self.tab == QsTab::Display {
+            for (i, layout) in LAYOUTS.iter().enumerate() {
+                let (bx, by, bw, bh) = Self::layout_button_rect(px, content_y, i);
+                if mx >= bx && mx < bx + bw && my >= by && my < by + bh {
+                    unsafe { crate::kernel::keymap::load(layout.name()); }
+                    break;
+                }
             }
         }
 
@@ -323,20 +375,49 @@ impl QuickSettings {
         let cy3 = cy2 + 26;
         toggle_row(graphics, px, cy3,      "Dark mode",  true);
         toggle_row(graphics, px, cy3 + 28, "Animations", true);
+
+        section_label(graphics, px, cy + Self::KB_SECTION_OFF_Y, "Keyboard layout", C_ACCENT);
+        let current = crate::kernel::keyboard::layout();
+        for (i, &layout) in LAYOUTS.iter().enumerate() {
+            let (bx, by, bw, bh) = Self::layout_button_rect(px, cy, i);
+            let selected = layout == current;
+            if selected {
+                graphics.fill_rounded_rect(bx, by, bw, bh, 5, 0xFF1A3A5E);
+                graphics.draw_rounded_rect(bx, by, bw, bh, 5, C_ACCENT, 1);
+            } else {
+                graphics.fill_rounded_rect(bx, by, bw, bh, 5, C_SURFACE);
+                graphics.draw_rounded_rect(bx, by, bw, bh, 5, C_BORDER, 1);
+            }
+            let label = layout.label();
+            let lx = bx + bw.saturating_sub(label.len() as u64 * 9) / 2;
+            let col = if selected { 0xFF88C8FFu32 } else { C_TEXT };
+            fonts::draw_string(graphics, lx, by + 8, label, col);
+        }
     }
 
     fn draw_sound_tab(&self, graphics: &Graphics, px: u64, cy: u64) {
-        section_label(graphics, px, cy, "Master Volume", C_ACCENT);
-        let scy = cy + 26;
-        let (sx, _, sw) = Self::slider_rect(px, scy);
-        draw_slider(graphics, sx, scy, sw, self.volume as u64, C_ACCENT, self.dragging_volume);
-        draw_value_pill(graphics, px + PANEL_W - CONTENT_PAD - 36, scy - 2, self.volume);
+        use crate::kernel::ac97::{self, Control};
+        use crate::kernel::speaker::{BELL, EVENTS};
+        let sliders = [
+            ("Master Volume", Control::Master, self.dragging_volume, 0),
+            ("PCM",           Control::Pcm,    self.dragging_pcm,    Self::PCM_OFF_Y),
+        ];
+        for (label, control, dragging, off) in sliders {
+            section_label(graphics, px, cy + off, label, C_ACCENT);
+            let scy = cy + off + 26;
+            let (sx, _, sw) = Self::slider_rect(px, scy);
+            let value = ac97::volume(control).0;
+            draw_slider(graphics, sx, scy, sw, value as u64, C_ACCENT, dragging);
+            draw_value_pill(graphics, px + PANEL_W - CONTENT_PAD - 36, scy - 2, value);
+        }
 
-        let cy2 = scy + 36;
+        let cy2 = cy + Self::PCM_OFF_Y * 2;
         section_label(graphics, px, cy2, "Output", C_ACCENT);
-        let cy3 = cy2 + 26;
-        toggle_row(graphics, px, cy3,      "System sounds", true);
-        toggle_row(graphics, px, cy3 + 28, "Event sounds",  false);
+        let dev_col = if ac97::is_present() { C_TEXT } else { C_DIM };
+        fonts::draw_string(graphics, px + CONTENT_PAD, cy2 + 24, ac97::name(), dev_col);
+        let cy3 = cy + Self::SOUND_TOGGLES_OFF_Y;
+        toggle_row(graphics, px, cy3,      "System sounds", BELL.load(Ordering::Relaxed));
+        toggle_row(graphics, px, cy3 + 28, "Event sounds",  EVENTS.load(Ordering::Relaxed));
     }
 
     fn draw_network_tab(&self, graphics: &Graphics, px: u64, cy: u64) {
@@ -1000,7 +1000,7 @@ impl TerminalApp {
 
             "pid" => {
                 let r = unsafe {
-                    syscall::handle_syscall(syscall::Syscall::GetPid as u64, 0, 0, 0, 0, 0)
+                    syscall::handle_syscall(syscall::Syscall::GetPid as u64, 0, 0, 0, 0, 0, 0)
                 };
                 self.push_line(&format!("pid: {}", r.value));
             }
@@ -1044,12 +1044,12 @@ impl TerminalApp {
 
             "shutdown" | "poweroff" => {
                 self.push_line("Shutting down...");
-                crate::kernel::shutdown::poweroff();
+                crate::kernel::shutdown::request_poweroff();
             }
 
             "reboot" => {
                 self.push_line("Rebooting...");
-                crate::kernel::shutdown::reboot();
+                crate::kernel::shutdown::request_reboot();
             }
 
             "run" => {
@@ -1115,8 +1115,12 @@ impl TerminalApp {
                         TaskState::Ready              => "ready",
                         TaskState::Running            => "running",
                         TaskState::Sleeping(_)        => "sleeping",
-                        TaskState::Waiting(_)         => "waiting",
+                        TaskState::Waiting(..)        => "waiting",
                         TaskState::WaitingForMsg(_,_) => "ipc-wait",
+                        TaskState::WaitingForFd(..)   => "fd-wait",
+                        TaskState::WaitingForEvents(..) => "epoll",
+                        TaskState::Traced             => "traced",
+                        TaskState::Stopped            => "stopped",
                         TaskState::Dead(_)            => "dead",
                     };
                     self.push_line(&format!("  [{}] {} ({})", info.pid, name, state_str));
@@ -1145,16 +1149,19 @@ impl TerminalApp {
                               "disk2 (secondary master)","disk3 (secondary slave)"];
                 let mounts = ["/disk (FAT16)", "(unmounted)", "/ext2 (ext2)", "(unmounted)"];
                 self.push_line("ATA Disks:");
-                for i in 0..4usize {
+                for i in 0..ata::MAX_DISKS {
+                    // Positions past the IDE four are listed only when filled.
+                    if i >= labels.len() && !ata::is_present_at(i) { continue; }
+                    let label = labels.get(i).map_or_else(|| format!("disk{}", i), |l| String::from(*l));
                     if ata::is_present_at(i) {
                         if let Some((secs, _slave, lba48)) = ata::disk_info(i) {
                             let mb = secs / 2048;
                             let mode = if lba48 { "LBA48" } else { "LBA28" };
                             self.push_line(&format!("  {} : {} MB  {}  mount={}",
-                                labels[i], mb, mode, mounts[i]));
+                                label, mb, mode, mounts.get(i).copied().unwrap_or("(unmounted)")));
                         }
                     } else {
-                        self.push_line(&format!("  {} : not present", labels[i]));
+                        self.push_line(&format!("  {} : not present", label));
                     }
                 }
                 self.push_line("Disk Record Store (/store):");
@@ -1333,30 +1340,36 @@ impl TerminalApp {
 
     // ── Line buffer ───────────────────────────────────────────────────────────
 
-    /// Strip ANSI escape sequences (e.g. `\x1b[31m`) from `text`.
-    fn strip_ansi(text: &str) -> String {
-        let mut out = String::with_capacity(text.len());
-        let bytes   = text.as_bytes();
-        let mut i   = 0;
+    /// Strip ANSI escape sequences (e.g. `\x1b[31m`) and BEL from `text`.
+    /// Returns the text and whether it rang the bell.
+    fn strip_ansi(text: &str) -> (String, bool) {
+        let mut out  = String::with_capacity(text.len());
+        let mut bell = false;
+        let bytes    = text.as_bytes();
+        let mut i    = 0;
         while i < bytes.len() {
             if bytes[i] == 0x1b && i + 1 < bytes.len() && bytes[i + 1] == b'[' {
                 // Skip CSI sequence: ESC [ ... <letter>
                 i += 2;
                 while i < bytes.len() && !bytes[i].is_ascii_alphabetic() { i += 1; }
                 if i < bytes.len() { i += 1; }
+            } else if bytes[i] == 0x07 {
+                bell = true;
+                i += 1;
             } else {
                 out.push(bytes[i] as char);
                 i += 1;
             }
         }
-        out
+        (out, bell)
     }
 
     fn push_line(&mut self, raw: &str) {
         const MAX_CHARS: usize = 72;
 
-        // 1. Strip ANSI escape codes
-        let cleaned = Self::strip_ansi(raw);
+        // 1. Strip ANSI escape codes; a BEL beeps the PC speaker
+        let (cleaned, bell) = Self::strip_ansi(raw);
+        if bell { crate::kernel::speaker::bell(); }
 
         // 2. Handle carriage return: keep only the last segment after \r
         //    e.g. "Loading...\rDone!" → "Done!"
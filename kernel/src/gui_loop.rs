@@ -25,7 +25,7 @@ use crate::gui::notifications::NotificationManager;
 use crate::gui::calendar::CalendarPanel;
 use crate::gui::menu::MenuAction;
 
-use crate::kernel::{compositor, gui_proc, scheduler, timer, keyboard, programs, shutdown, interrupts};
+use crate::kernel::{compositor, gui_proc, scheduler, session, timer, keyboard, programs, shutdown, interrupts};
 use crate::net_probe::{NetProbe, NetProbePhase};
 use crate::sysinfo::draw_sysinfo_panel;
 
@@ -224,8 +224,8 @@ pub unsafe fn run_gui_with_mouse(
                     needs_redraw = true;
                 } else if quick_settings.visible {
                     match quick_settings.handle_click(mx64, my64, screen_w) {
-                        QsAction::Shutdown => shutdown::poweroff(),
-                        QsAction::Reboot   => shutdown::reboot(),
+                        QsAction::Shutdown => shutdown::request_poweroff(),
+                        QsAction::Reboot   => shutdown::request_reboot(),
                         _                  => {}
                     }
                     needs_redraw = true;
@@ -242,8 +242,8 @@ pub unsafe fn run_gui_with_mouse(
                         spawn_program(name, &mut terminals, &mut notepads, graphics, unsafe { &mut *wm });
                         notifications.push(name, "Application started", 0xFF26A269);
                         needs_redraw = true;
-                    } else if sm_action == 1 { shutdown::poweroff();
-                    } else if sm_action == 2 { shutdown::reboot();
+                    } else if sm_action == 1 { shutdown::request_poweroff();
+                    } else if sm_action == 2 { shutdown::request_reboot();
                     } else if sm_consumed { needs_redraw = true;
                     } else if launcher_app.visible {
                         if let Some(prog) = launcher_app.handle_click(mx64, my64, screen_h) {
@@ -397,6 +397,12 @@ pub unsafe fn run_gui_with_mouse(
             unsafe { gui_proc::on_process_exit(pid as u32); }
             terminal_dirty = true; needs_redraw = true;
         }
+        // Programs the desktop session started for a terminal window.
+        while let Some((wid, pid)) = unsafe { session::poll_launched() } {
+            if let Some(term) = terminals.iter_mut().find(|t| t.window_id() as u32 == wid) {
+                term.attach_foreground(pid);
+            }
+        }
         for term in terminals.iter_mut() {
             if term.poll_task_outputs() { terminal_dirty = true; }
         }
@@ -474,10 +480,13 @@ fn spawn_program(
         if let Some(wid) = wm.add_window(widgets::Window::new(30 + offset, 60 + offset, win_w, win_h, title)) {
             wm.set_focused(Some(wid));
             let mut term = terminal::TerminalApp::new(wid);
-            if let Ok(pid) = unsafe { scheduler::spawn(code, name) } { term.attach_foreground(pid); }
+            // With a session running, the pid arrives later via poll_launched.
+            if !unsafe { session::launch(name, wid as u32) } {
+                if let Ok(pid) = unsafe { scheduler::spawn(code, name) } { term.attach_foreground(pid); }
+            }
             terminals.push(term);
         }
-    } else {
+    } else if !unsafe { session::launch(name, session::NO_WINDOW) } {
         let _ = unsafe { scheduler::spawn(code, name) };
     }
 }
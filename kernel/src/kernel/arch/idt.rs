@@ -133,7 +133,7 @@ pub fn init() {
         IDT[0].set_handler(isr0, kernel_selector, 0x8E);    // Interrupt gate
         IDT[1].set_handler(isr1, kernel_selector, 0x8E);
         IDT[2].set_handler(isr2, kernel_selector, 0x8E);
-        IDT[3].set_handler(isr3, kernel_selector, 0x8E);
+        IDT[3].set_handler(isr3, kernel_selector, 0xEE);   // DPL=3: user `int3` breakpoints
         IDT[4].set_handler(isr4, kernel_selector, 0x8E);
         IDT[5].set_handler(isr5, kernel_selector, 0x8E);
         IDT[6].set_handler(isr6, kernel_selector, 0x8E);
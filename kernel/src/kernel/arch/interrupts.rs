@@ -7,6 +7,7 @@ use crate::kernel::paging_allocator;
 use crate::gui::mouse::{PS2Mouse, MouseCursor};
 use crate::kernel::keyboard::handle_keyboard_interrupt;
 use crate::kernel::user_mode::TaskContext;
+use crate::kernel::scheduler::SigInfo;
 
 // ============================================================================
 // GLOBAL STATE
@@ -95,6 +96,16 @@ pub extern "C" fn isr_common_handler(frame: *mut InterruptFrame) {
         // Dispatch to specific handlers
         match int_no {
             0..=31 => {
+                // Not-present page fault on a swapped-out user page (from
+                // user code, or the kernel touching a user buffer): read the
+                // page back in and retry.
+                if int_no == 14 && err_code & 0x1 == 0 {
+                    let cr2: u64;
+                    asm!("mov {}, cr2", out(reg) cr2, options(nomem, nostack, preserves_flags));
+                    if crate::kernel::swap::handle_fault(cr2) {
+                        return; // retry the faulting instruction
+                    }
+                }
                 // Page fault (#PF) on a user-mode write to a present page may
                 // be a copy-on-write fault from fork(). Resolve it and retry
                 // the faulting instruction instead of killing the task.
@@ -121,6 +132,11 @@ pub extern "C" fn isr_common_handler(frame: *mut InterruptFrame) {
                 handle_keyboard_interrupt();
                 pic::send_eoi(1);
             },
+            36 => {
+                // COM1 (IRQ4) - /dev/ttyS0 input
+                crate::kernel::serial::handle_interrupt();
+                pic::send_eoi(4);
+            },
             34..=43 => {
                 // Other hardware IRQs (IRQ2-11) - EXCLUDE IRQ12
                 handle_hardware_irq(int_no);
@@ -174,6 +190,8 @@ pub extern "C" fn isr_common_handler(frame: *mut InterruptFrame) {
 /// returns.  PIC EOI is sent inside preempt() so it is not skipped.
 unsafe fn handle_timer_interrupt(frame: *mut InterruptFrame) {
     TIMER_TICKS += 1;
+    unsafe { crate::kernel::timer::calibrate_tsc(); }
+    unsafe { crate::kernel::speaker::tick(); }
 
     // Minimal early-boot logging; suppress noisy per-tick serial spam.
     if TIMER_TICKS <= 5 {
@@ -294,9 +312,37 @@ pub unsafe fn poll_mouse_data() -> bool {
 
     processed
 }
+// ============================================================================
+// PCI DEVICE INTERRUPTS
+// ============================================================================
+
+const MAX_IRQ_HANDLERS: usize = 8;
+
+/// Handlers for the interrupt lines PCI drivers asked for.  PCI lines are
+/// shared, so every handler on the line runs and reports whether its
+/// device had something pending.
+static mut IRQ_HANDLERS: [Option<(u8, unsafe fn() -> bool)>; MAX_IRQ_HANDLERS] =
+    [None; MAX_IRQ_HANDLERS];
+
+/// Run `handler` whenever IRQ `irq` fires and unmask the line.  Returns
+/// `false` if the table is full or `irq` is not a PIC line a device can use.
+pub unsafe fn register_irq(irq: u8, handler: unsafe fn() -> bool) -> bool {
+    if !(3..16).contains(&irq) || irq == 4 || irq == 12 { return false; }
+    let handlers = unsafe { &mut *(&raw mut IRQ_HANDLERS) };
+    let Some(slot) = handlers.iter_mut().find(|h| h.is_none()) else { return false };
+    *slot = Some((irq, handler));
+    unsafe { pic::unmask_irq(irq); }
+    true
+}
+
 /// Handle other hardware IRQs
 unsafe fn handle_hardware_irq(int_no: u64) {
     let irq_num = int_no - 32;
+    let mut claimed = false;
+    for &(irq, handler) in unsafe { &*(&raw const IRQ_HANDLERS) }.iter().flatten() {
+        if irq as u64 == irq_num { claimed |= unsafe { handler() }; }
+    }
+    if claimed { return; }
     SERIAL_PORT.write_str("HW-IRQ:");
     SERIAL_PORT.write_decimal(irq_num as u32);
     SERIAL_PORT.write_str(" ");
@@ -324,14 +370,17 @@ unsafe fn handle_system_call(frame: *mut InterruptFrame) {
     // to yield (e.g. sleep). Cleared by exit_to_kernel or after dispatch.
     crate::kernel::user_mode::CURRENT_SYSCALL_CTX = Some(frame_to_ctx(&*frame));
 
-    let result = syscall::handle_syscall(
-        (*frame).rax,
-        (*frame).rdi,
-        (*frame).rsi,
-        (*frame).rdx,
-        (*frame).r10,
-        (*frame).r8,
-    );
+    let result = unsafe {
+        syscall::handle_syscall(
+            (*frame).rax,
+            (*frame).rdi,
+            (*frame).rsi,
+            (*frame).rdx,
+            (*frame).r10,
+            (*frame).r8,
+            (*frame).r9,
+        )
+    };
 
     // Clear context after normal (non-yielding) dispatch.
     crate::kernel::user_mode::CURRENT_SYSCALL_CTX = None;
@@ -376,6 +425,41 @@ pub unsafe fn init_mouse_system(screen_width: u64, screen_height: u64) {
 // CPU EXCEPTION HANDLER - 64-bit version
 // ============================================================================
 
+/// Map a user-mode CPU exception to its signal and `siginfo_t` (Linux rules).
+fn fault_signal(int_no: u64, err_code: u64, cr2: u64, rip: u64) -> (u8, SigInfo) {
+    use crate::kernel::scheduler::{SIGFPE, SIGTRAP, SIGILL, SIGBUS, SIGSEGV, SI_KERNEL,
+                                   FPE_INTDIV, FPE_FLTINV, TRAP_TRACE, ILL_ILLOPN,
+                                   BUS_ADRALN, SEGV_MAPERR, SEGV_ACCERR};
+    let info = |code, addr| SigInfo::fault(code, addr, int_no, err_code);
+    match int_no {
+        0       => (SIGFPE,  info(FPE_INTDIV, rip)),
+        1       => (SIGTRAP, info(TRAP_TRACE, rip)),
+        3       => (SIGTRAP, info(SI_KERNEL, 0)),
+        6       => (SIGILL,  info(ILL_ILLOPN, rip)),
+        16      => (SIGFPE,  info(FPE_FLTINV, rip)),
+        19      => (SIGFPE,  info(simd_fpe_code(), rip)),
+        11 | 12 => (SIGBUS,  info(SI_KERNEL, 0)),
+        17      => (SIGBUS,  info(BUS_ADRALN, 0)),
+        // P bit clear: nothing mapped there; set: a protection violation.
+        14      => (SIGSEGV, info(if err_code & 1 == 0 { SEGV_MAPERR } else { SEGV_ACCERR }, cr2)),
+        _       => (SIGSEGV, info(SI_KERNEL, 0)),
+    }
+}
+
+/// `si_code` for a #XM fault: the first unmasked exception flag in MXCSR.
+fn simd_fpe_code() -> i32 {
+    use crate::kernel::scheduler::{FPE_FLTINV, FPE_FLTDIV, FPE_FLTOVF, FPE_FLTUND, FPE_FLTRES};
+    let mut mxcsr: u32 = 0;
+    unsafe { asm!("stmxcsr [{}]", in(reg) &raw mut mxcsr, options(nostack, preserves_flags)); }
+    let raised = mxcsr & !(mxcsr >> 7) & 0x3F;
+    if raised & 0x01 != 0 { FPE_FLTINV }       // invalid operation
+    else if raised & 0x04 != 0 { FPE_FLTDIV }  // divide by zero
+    else if raised & 0x08 != 0 { FPE_FLTOVF }  // overflow
+    else if raised & 0x10 != 0 { FPE_FLTUND }  // underflow
+    else if raised & 0x20 != 0 { FPE_FLTRES }  // precision
+    else { FPE_FLTINV }
+}
+
 /// Handle CPU exceptions with detailed 64-bit information
 fn handle_cpu_exception_64(int_no: u64, err_code: u64, frame: *mut InterruptFrame) -> ! {
     unsafe {
@@ -391,6 +475,19 @@ fn handle_cpu_exception_64(int_no: u64, err_code: u64, frame: *mut InterruptFram
             let idx   = CURRENT_TASK_IDX;
             let pid   = (*sched).tasks[idx].pid;
 
+            // Give the task's own handler a chance first (SIGSEGV, SIGFPE, ...).
+            let (signum, info) = fault_signal(int_no, err_code, fault_addr, (*frame).rip);
+            if crate::kernel::scheduler::raise_fault(signum, info, frame_to_ctx(&*frame)) {
+                SERIAL_PORT.write_str("user fault #");
+                SERIAL_PORT.write_decimal(int_no as u32);
+                SERIAL_PORT.write_str(" pid=");
+                SERIAL_PORT.write_decimal(pid as u32);
+                SERIAL_PORT.write_str(" -> signal ");
+                SERIAL_PORT.write_decimal(signum as u32);
+                SERIAL_PORT.write_str("\n");
+                crate::kernel::user_mode::exit_to_kernel(crate::kernel::scheduler::EXIT_PREEMPTED);
+            }
+
             SERIAL_PORT.write_str("user fault #");
             SERIAL_PORT.write_decimal(int_no as u32);
             SERIAL_PORT.write_str(" pid=");
@@ -426,8 +523,13 @@ fn handle_cpu_exception_64(int_no: u64, err_code: u64, frame: *mut InterruptFram
             SERIAL_PORT.write_hex((*frame).rdx as u32);
             SERIAL_PORT.write_str("\n");
 
-            // Dump [rsp+0..+48] to see what argv/envp look like
-            if user_rsp >= 0x1000 && user_rsp < 0x800000 {
+            // Dump [rsp+0..+48] to see what argv/envp look like.  Both
+            // pages it can touch must be mapped: the fault being reported
+            // may well be a stack overflow.
+            if user_rsp >= 0x1000 && user_rsp < 0x0000_8000_0000_0000 - 48
+                && crate::kernel::paging_allocator::is_page_mapped_current(user_rsp)
+                && crate::kernel::paging_allocator::is_page_mapped_current(user_rsp + 47)
+            {
                 SERIAL_PORT.write_str("  stack dump [rsp+0..+48]:\n");
                 let base = user_rsp as *const u64;
                 for i in 0..6usize {
@@ -441,15 +543,11 @@ fn handle_cpu_exception_64(int_no: u64, err_code: u64, frame: *mut InterruptFram
                 }
             }
             SERIAL_PORT.write_str("  — killed\n");
+            crate::kernel::coredump::dump(idx, signum, &frame_to_ctx(&*frame));
 
-            // exit_to_kernel(-signal) triggers the normal Dead path in tick()
-            let sig: i64 = match int_no {
-                0 | 16 | 19 => -8,  // SIGFPE
-                11 | 12     => -11, // SIGSEGV (stack fault)
-                _           => -11, // SIGSEGV (default)
-            };
+            // No handler: exit_to_kernel(-signal) triggers the normal Dead path in tick()
             crate::kernel::user_mode::CURRENT_SYSCALL_CTX = None;
-            crate::kernel::user_mode::exit_to_kernel(sig);
+            crate::kernel::user_mode::exit_to_kernel(-(signum as i64));
         }
 
         SERIAL_PORT.write_str("\n=== 64-BIT CPU EXCEPTION ===\n");
@@ -0,0 +1,386 @@
+//! Intel AC'97 audio controller (ICH family, QEMU `-device AC97`).
+//!
+//! The controller has two I/O BARs: the codec's mixer registers (NAM) and
+//! the bus-master DMA engine (NABM).  Only the PCM-out channel is used.  It
+//! walks a ring of 32 buffer descriptors, each pointing at a 4 KiB DMA
+//! buffer of 16-bit stereo samples, from CIV (the buffer playing) up to LVI
+//! (the last valid one) and halts when it gets there.
+//!
+//! `push` fills the buffer after LVI and moves LVI onto it once it is full;
+//! a halted engine picks up again at the next LVI write.  Every buffer
+//! raises an interrupt on completion, which only wakes writers — how much
+//! of the ring is free is always read back from CIV and LVI.
+//!
+//! The codec runs at 48 kHz unless it implements variable rate audio, in
+//! which case `set_rate` programs the DAC.  `/dev/dsp` and `/dev/mixer`
+//! live in `drivers::dsp`.
+
+use core::arch::asm;
+use crate::kernel::net::pci::{self, PciDevice};
+use crate::kernel::serial::SERIAL_PORT;
+
+// Mixer (NAM) registers.
+const NAM_RESET:      u16 = 0x00;
+const NAM_MASTER:     u16 = 0x02;
+const NAM_PCM_OUT:    u16 = 0x18;
+const NAM_EXT_ID:     u16 = 0x28;
+const NAM_EXT_CTRL:   u16 = 0x2A;
+const NAM_FRONT_RATE: u16 = 0x2C;
+
+// Bus master (NABM) registers: the PCM-out box, then the global ones.
+const PO_BDBAR: u16 = 0x10;
+const PO_CIV:   u16 = 0x14;
+const PO_LVI:   u16 = 0x15;
+const PO_SR:    u16 = 0x16;
+const PO_PICB:  u16 = 0x18;
+const PO_CR:    u16 = 0x1B;
+const GLOB_CNT: u16 = 0x2C;
+const GLOB_STA: u16 = 0x30;
+
+const CR_RPBM:  u8 = 1 << 0; // run
+const CR_RR:    u8 = 1 << 1; // reset the box
+const CR_LVBIE: u8 = 1 << 2;
+const CR_FEIE:  u8 = 1 << 3;
+const CR_IOCE:  u8 = 1 << 4;
+
+const SR_DCH:   u16 = 1 << 0; // DMA halted
+const SR_LVBCI: u16 = 1 << 2;
+const SR_BCIS:  u16 = 1 << 3;
+const SR_FIFOE: u16 = 1 << 4;
+
+const GLOB_CNT_COLD: u32 = 1 << 1;
+const GLOB_STA_PCR:  u32 = 1 << 8; // primary codec ready
+const EXT_VRA:  u16 = 1 << 0;
+const MUTE:     u16 = 1 << 15;
+const BD_IOC:   u16 = 1 << 15;
+
+pub const BUFFERS:  usize = 32;
+pub const BUF_SIZE: usize = 4096;
+/// Bytes per frame in the device format: 16-bit little-endian stereo.
+pub const FRAME: usize = 4;
+pub const DEFAULT_RATE: u32 = 48_000;
+
+/// Mixer controls, as percentages per side.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Control { Master, Pcm }
+
+/// One buffer descriptor list entry.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct BufferDesc {
+    addr:    u32,
+    /// Length in 16-bit samples.
+    samples: u16,
+    flags:   u16,
+}
+
+struct Ac97 {
+    nam:     u16,
+    nabm:    u16,
+    pci:     PciDevice,
+    vra:     bool,
+    rate:    u32,
+    /// Physical addresses of the descriptor list and of the buffers.
+    bdl:     u64,
+    bufs:    u64,
+    /// Bytes submitted in each buffer.
+    len:     [usize; BUFFERS],
+    /// Buffer being filled, and the bytes already in it.
+    fill:     usize,
+    fill_len: usize,
+    /// The engine has been started since the last reset.
+    started: bool,
+}
+
+static mut DEV: Option<Ac97> = None;
+
+/// Master and PCM volume, (left, right) in percent.  Kept without a
+/// device too, so the settings panel has something to show.
+static mut VOLUME: [(u8, u8); 2] = [(65, 65), (100, 100)];
+
+#[inline] fn inb(port: u16) -> u8 {
+    let v: u8;
+    unsafe { asm!("in al, dx", out("al") v, in("dx") port, options(nomem, nostack)); }
+    v
+}
+#[inline] fn inw(port: u16) -> u16 {
+    let v: u16;
+    unsafe { asm!("in ax, dx", out("ax") v, in("dx") port, options(nomem, nostack)); }
+    v
+}
+#[inline] fn inl(port: u16) -> u32 {
+    let v: u32;
+    unsafe { asm!("in eax, dx", out("eax") v, in("dx") port, options(nomem, nostack)); }
+    v
+}
+#[inline] fn outb(port: u16, val: u8) {
+    unsafe { asm!("out dx, al", in("dx") port, in("al") val, options(nomem, nostack)); }
+}
+#[inline] fn outw(port: u16, val: u16) {
+    unsafe { asm!("out dx, ax", in("dx") port, in("ax") val, options(nomem, nostack)); }
+}
+#[inline] fn outl(port: u16, val: u32) {
+    unsafe { asm!("out dx, eax", in("dx") port, in("eax") val, options(nomem, nostack)); }
+}
+
+/// Roughly `us` microseconds: each write to the POST port takes about one.
+fn io_delay(us: u32) {
+    for _ in 0..us { outb(0x80, 0); }
+}
+
+fn hhdm(phys: u64) -> u64 {
+    phys + crate::kernel::paging_allocator::get_hhdm_offset()
+}
+
+unsafe fn dev() -> Option<&'static mut Ac97> {
+    unsafe { (*(&raw mut DEV)).as_mut() }
+}
+
+impl Ac97 {
+    fn running(&self) -> bool {
+        self.started && inw(self.nabm + PO_SR) & SR_DCH == 0
+    }
+
+    /// Buffers the engine still has to play, CIV through LVI.
+    fn in_flight(&self) -> usize {
+        if !self.running() { return 0; }
+        let civ = inb(self.nabm + PO_CIV) as usize;
+        let lvi = inb(self.nabm + PO_LVI) as usize;
+        (lvi.wrapping_sub(civ) & (BUFFERS - 1)) + 1
+    }
+
+    /// Hand the fill buffer to the engine and move on to the next one.
+    fn submit(&mut self) {
+        let bd = hhdm(self.bdl) as *mut BufferDesc;
+        unsafe {
+            core::ptr::write_volatile(bd.add(self.fill), BufferDesc {
+                addr:    (self.bufs + (self.fill * BUF_SIZE) as u64) as u32,
+                samples: (self.fill_len / 2) as u16,
+                flags:   BD_IOC,
+            });
+        }
+        self.len[self.fill] = self.fill_len;
+        outb(self.nabm + PO_LVI, self.fill as u8);
+        if !self.started {
+            outb(self.nabm + PO_CR, CR_RPBM | CR_LVBIE | CR_FEIE | CR_IOCE);
+            self.started = true;
+        }
+        self.fill = (self.fill + 1) % BUFFERS;
+        self.fill_len = 0;
+    }
+
+    /// Stop the engine and empty the ring.
+    fn reset(&mut self) {
+        outb(self.nabm + PO_CR, 0);
+        outb(self.nabm + PO_CR, CR_RR);
+        for _ in 0..1000 {
+            if inb(self.nabm + PO_CR) & CR_RR == 0 { break; }
+            io_delay(1);
+        }
+        outl(self.nabm + PO_BDBAR, self.bdl as u32);
+        outw(self.nabm + PO_SR, SR_LVBCI | SR_BCIS | SR_FIFOE);
+        self.fill = 0;
+        self.fill_len = 0;
+        self.started = false;
+    }
+}
+
+// ── Initialisation ─────────────────────────────────────────────────────────
+
+/// Find the controller (PCI class 04:01), reset the codec, set up the
+/// descriptor ring and register `/dev/dsp` and `/dev/mixer`.
+pub unsafe fn init() -> bool {
+    let Some(pci) = pci::find_class(0x04, 0x01) else {
+        unsafe { SERIAL_PORT.write_str("[ac97] No audio controller found\n"); }
+        return false;
+    };
+    let (Some(nam), Some(nabm)) = (pci.io_bar(0), pci.io_bar(1)) else {
+        unsafe { SERIAL_PORT.write_str("[ac97] Controller has no I/O BARs\n"); }
+        return false;
+    };
+    pci.enable_bus_mastering();
+
+    // Cold reset, then wait up to 100 ms for the primary codec.
+    outl(nabm + GLOB_CNT, GLOB_CNT_COLD);
+    let mut ready = false;
+    for _ in 0..1000 {
+        if inl(nabm + GLOB_STA) & GLOB_STA_PCR != 0 { ready = true; break; }
+        io_delay(100);
+    }
+    if !ready {
+        unsafe { SERIAL_PORT.write_str("[ac97] Codec not ready\n"); }
+        return false;
+    }
+    outw(nam + NAM_RESET, 0);
+
+    let (Some(bdl), Some(bufs)) = (
+        unsafe { crate::kernel::paging_allocator::alloc_dma_frames(1) },
+        unsafe { crate::kernel::paging_allocator::alloc_dma_frames(BUFFERS * BUF_SIZE / 4096) },
+    ) else {
+        unsafe { SERIAL_PORT.write_str("[ac97] Out of DMA memory\n"); }
+        return false;
+    };
+
+    let vra = inw(nam + NAM_EXT_ID) & EXT_VRA != 0;
+    if vra { outw(nam + NAM_EXT_CTRL, inw(nam + NAM_EXT_CTRL) | EXT_VRA); }
+
+    let mut ac = Ac97 {
+        nam, nabm, pci, vra, rate: DEFAULT_RATE, bdl, bufs,
+        len: [0; BUFFERS], fill: 0, fill_len: 0, started: false,
+    };
+    ac.reset();
+    unsafe { *(&raw mut DEV) = Some(ac); }
+    unsafe { set_rate(DEFAULT_RATE); }
+    for c in [Control::Master, Control::Pcm] {
+        let (l, r) = volume(c);
+        unsafe { set_volume(c, l, r); }
+    }
+
+    let irq = pci.irq_line();
+    let irq_ok = unsafe { crate::kernel::interrupts::register_irq(irq, interrupt) };
+
+    unsafe {
+        SERIAL_PORT.write_str("[ac97] ");
+        SERIAL_PORT.write_hex(pci.vendor as u32);
+        SERIAL_PORT.write_str(":");
+        SERIAL_PORT.write_hex(pci.device as u32);
+        SERIAL_PORT.write_str(" nam=0x");
+        SERIAL_PORT.write_hex(nam as u32);
+        SERIAL_PORT.write_str(" nabm=0x");
+        SERIAL_PORT.write_hex(nabm as u32);
+        SERIAL_PORT.write_str(if vra { " VRA" } else { " 48kHz" });
+        if irq_ok {
+            SERIAL_PORT.write_str(" irq=");
+            SERIAL_PORT.write_decimal(irq as u32);
+        }
+        SERIAL_PORT.write_str("\n");
+    }
+
+    crate::kernel::dsp::register_devices();
+    true
+}
+
+pub fn is_present() -> bool {
+    unsafe { (*(&raw const DEV)).is_some() }
+}
+
+/// Name for settings screens.
+pub fn name() -> &'static str {
+    match unsafe { (*(&raw const DEV)).as_ref() } {
+        Some(d) if d.pci.vendor == 0x8086 => "Intel AC'97",
+        Some(_) => "AC'97",
+        None    => "No sound card",
+    }
+}
+
+// ── Interrupt ──────────────────────────────────────────────────────────────
+
+/// PCM-out interrupt: acknowledge it and wake the writer.  Returns `false`
+/// when the (shared) line was raised by some other device.
+unsafe fn interrupt() -> bool {
+    let Some(d) = (unsafe { dev() }) else { return false };
+    let sr = inw(d.nabm + PO_SR) & (SR_LVBCI | SR_BCIS | SR_FIFOE);
+    if sr == 0 { return false; }
+    outw(d.nabm + PO_SR, sr);
+    unsafe { crate::kernel::dsp::wake(); }
+    true
+}
+
+// ── Playback ───────────────────────────────────────────────────────────────
+
+/// Sample rate the DAC runs at.
+pub fn rate() -> u32 {
+    unsafe { (*(&raw const DEV)).as_ref().map_or(DEFAULT_RATE, |d| d.rate) }
+}
+
+/// Ask for `hz`; returns the rate the DAC actually runs at, which without
+/// variable rate audio is always 48 kHz.
+pub unsafe fn set_rate(hz: u32) -> u32 {
+    let Some(d) = (unsafe { dev() }) else { return DEFAULT_RATE };
+    if d.vra {
+        outw(d.nam + NAM_FRONT_RATE, hz.clamp(8000, 48_000) as u16);
+        d.rate = inw(d.nam + NAM_FRONT_RATE) as u32;
+    }
+    d.rate
+}
+
+/// Bytes `push` would take right now.
+pub unsafe fn space() -> usize {
+    let Some(d) = (unsafe { dev() }) else { return 0 };
+    (BUFFERS - 1).saturating_sub(d.in_flight()) * BUF_SIZE - d.fill_len
+}
+
+/// Bytes queued and not played yet, including a partly filled buffer.
+pub unsafe fn queued() -> usize {
+    let Some(d) = (unsafe { dev() }) else { return 0 };
+    let mut bytes = d.fill_len;
+    if d.running() {
+        let civ = inb(d.nabm + PO_CIV) as usize;
+        let lvi = inb(d.nabm + PO_LVI) as usize;
+        bytes += inw(d.nabm + PO_PICB) as usize * 2;
+        let mut i = civ;
+        while i != lvi {
+            i = (i + 1) % BUFFERS;
+            bytes += d.len[i];
+        }
+    }
+    bytes
+}
+
+/// Whether the engine is still playing.
+pub unsafe fn playing() -> bool {
+    unsafe { dev() }.is_some_and(|d| d.running())
+}
+
+/// Copy device-format samples into the ring; returns how many bytes fit.
+/// With nothing playing, a partly filled buffer is started at once so
+/// short sounds are not held back.
+pub unsafe fn push(data: &[u8]) -> usize {
+    let Some(d) = (unsafe { dev() }) else { return 0 };
+    let mut done = 0;
+    while done < data.len() && d.in_flight() < BUFFERS - 1 {
+        let n = (BUF_SIZE - d.fill_len).min(data.len() - done);
+        let dst = hhdm(d.bufs + (d.fill * BUF_SIZE + d.fill_len) as u64) as *mut u8;
+        unsafe { core::ptr::copy_nonoverlapping(data[done..].as_ptr(), dst, n); }
+        d.fill_len += n;
+        done += n;
+        if d.fill_len == BUF_SIZE { d.submit(); }
+    }
+    if d.fill_len > 0 && !d.running() { d.submit(); }
+    done
+}
+
+/// Start whatever is sitting in the fill buffer.
+pub unsafe fn flush() {
+    if let Some(d) = unsafe { dev() } {
+        if d.fill_len > 0 && d.in_flight() < BUFFERS - 1 { d.submit(); }
+    }
+}
+
+/// Stop playback and drop everything queued.
+pub unsafe fn reset() {
+    if let Some(d) = unsafe { dev() } { d.reset(); }
+}
+
+// ── Mixer ──────────────────────────────────────────────────────────────────
+
+pub fn volume(c: Control) -> (u8, u8) {
+    unsafe { (*(&raw const VOLUME))[c as usize] }
+}
+
+/// Set a control to `left`/`right` percent (clamped to 100).  Zero on both
+/// sides mutes it.
+pub unsafe fn set_volume(c: Control, left: u8, right: u8) {
+    let (left, right) = (left.min(100), right.min(100));
+    unsafe { (*(&raw mut VOLUME))[c as usize] = (left, right); }
+    let Some(d) = (unsafe { dev() }) else { return };
+    // Master is attenuation in 1.5 dB steps (0..31 on every codec); PCM
+    // out is gain with 8 meaning 0 dB, so 100% never amplifies.
+    let field = |p: u8| match c {
+        Control::Master => (100 - p as u16) * 31 / 100,
+        Control::Pcm    => 8 + (100 - p as u16) * 23 / 100,
+    };
+    let reg = match c { Control::Master => NAM_MASTER, Control::Pcm => NAM_PCM_OUT };
+    let mute = if left == 0 && right == 0 { MUTE } else { 0 };
+    outw(d.nam + reg, mute | field(left) << 8 | field(right));
+}
@@ -0,0 +1,610 @@
+//! AHCI SATA host controller (PCI class 01:06: QEMU `-device ahci` and the
+//! ICH9 controller built into the q35 machine).
+//!
+//! BAR 5 (ABAR) holds the HBA's global registers followed by one 128-byte
+//! register block per port.  Each port with a SATA disk gets, in DMA memory
+//! below 4 GiB, a command list of 32 headers, a received-FIS area and 32
+//! command tables.  A command is a host-to-device register FIS plus a PRDT:
+//! a scatter-gather list built straight from the physical pages behind the
+//! caller's buffer, or a per-port bounce buffer when the buffer cannot be
+//! described that way (odd address, above 4 GiB on a 32-bit HBA, too
+//! fragmented).
+//!
+//! Disks that support NCQ get READ/WRITE FPDMA QUEUED: a transfer is split
+//! into 32 KiB commands, one per slot (the slot is the NCQ tag), all issued
+//! at once and completed by the drive in whatever order suits it.  Other
+//! disks use READ/WRITE DMA (EXT), one command at a time.  Completion raises
+//! the controller's INTx line; the waiter sleeps in `hlt` until its slots
+//! clear from PxCI and PxSACT, or polls them when it runs with interrupts
+//! masked (inside a syscall).
+//!
+//! Disks are added to `ata::DISKS`, so `/dev/hdX`, FAT, ext2, swap and the
+//! record store reach them through the same `ata::read_sector` calls as IDE
+//! disks.  Port n takes slot n when that is free, which matches QEMU's
+//! `if=ide,index=n` numbering on q35.
+
+use core::arch::asm;
+use core::ptr::{read_volatile, write_volatile};
+use crate::kernel::ata::{self, AtaDisk, Dma};
+use crate::kernel::net::pci;
+use crate::kernel::paging_allocator::{
+    alloc_dma_frames, free_phys_frames, get_hhdm_offset, kernel_virt_to_phys, map_mmio,
+};
+use crate::kernel::serial::SERIAL_PORT;
+use crate::kernel::timer;
+
+// HBA registers.
+const CAP:  u64 = 0x00;
+const GHC:  u64 = 0x04;
+const IS:   u64 = 0x08;
+const PI:   u64 = 0x0C;
+const VS:   u64 = 0x10;
+const CAP2: u64 = 0x24;
+const BOHC: u64 = 0x28;
+
+const CAP_S64A: u32 = 1 << 31;
+const CAP_SNCQ: u32 = 1 << 30;
+const GHC_IE:   u32 = 1 << 1;
+const GHC_AE:   u32 = 1 << 31;
+const CAP2_BOH: u32 = 1 << 0;
+const BOHC_BOS: u32 = 1 << 0;
+const BOHC_OOS: u32 = 1 << 1;
+
+// Port registers, at 0x100 + 0x80 * port.
+const PX_CLB:  u64 = 0x00;
+const PX_CLBU: u64 = 0x04;
+const PX_FB:   u64 = 0x08;
+const PX_FBU:  u64 = 0x0C;
+const PX_IS:   u64 = 0x10;
+const PX_IE:   u64 = 0x14;
+const PX_CMD:  u64 = 0x18;
+const PX_TFD:  u64 = 0x20;
+const PX_SIG:  u64 = 0x24;
+const PX_SSTS: u64 = 0x28;
+const PX_SCTL: u64 = 0x2C;
+const PX_SERR: u64 = 0x30;
+const PX_SACT: u64 = 0x34;
+const PX_CI:   u64 = 0x38;
+
+const CMD_ST:  u32 = 1 << 0;
+const CMD_SUD: u32 = 1 << 1;
+const CMD_POD: u32 = 1 << 2;
+const CMD_FRE: u32 = 1 << 4;
+const CMD_FR:  u32 = 1 << 14;
+const CMD_CR:  u32 = 1 << 15;
+
+const TFD_DRQ: u32 = 1 << 3;
+const TFD_BSY: u32 = 1 << 7;
+
+const SSTS_DET_PRESENT: u32 = 3;
+
+// PxIS / PxIE: completions (D2H register FIS, PIO setup, DMA setup, set
+// device bits) and the error conditions that stop the port.
+const IS_DONE:   u32 = 0x0F;
+const IS_ERRORS: u32 = (1 << 30) | (1 << 29) | (1 << 28) | (1 << 27) | (1 << 26) | (1 << 24) | (1 << 23);
+
+const SIG_ATA: u32 = 0x0000_0101;
+
+// ATA commands.
+const ATA_IDENTIFY:    u8 = 0xEC;
+const ATA_READ_DMA:    u8 = 0xC8;
+const ATA_WRITE_DMA:   u8 = 0xCA;
+const ATA_READ_DMA48:  u8 = 0x25;
+const ATA_WRITE_DMA48: u8 = 0x35;
+const ATA_READ_FPDMA:  u8 = 0x60;
+const ATA_WRITE_FPDMA: u8 = 0x61;
+const ATA_FLUSH:       u8 = 0xE7;
+const ATA_FLUSH48:     u8 = 0xEA;
+
+const FIS_TYPE_H2D: u8 = 0x27;
+const FIS_COMMAND:  u8 = 0x80;
+const DEV_LBA:      u8 = 0x40;
+
+const MAX_PORTS: usize = 32;
+const SECTOR:    usize = 512;
+/// PRDT entries per command table.
+const PRDT_MAX:  usize = 16;
+/// Command FIS area (64) + ATAPI command (16) + reserved (48), then the PRDT.
+const TABLE_SIZE: usize = 0x80 + PRDT_MAX * 16;
+/// Largest byte count one PRDT entry can carry.
+const PRD_MAX_BYTES: usize = 4 << 20;
+/// Sectors per command: 32 KiB.
+const CHUNK_SECTORS: usize = 64;
+/// Page 0: command list (1 KiB) and received FIS (256 B); then the tables.
+const CMD_PAGES:    usize = 1 + (32 * TABLE_SIZE).div_ceil(4096);
+const BOUNCE_PAGES: usize = CHUNK_SECTORS * SECTOR / 4096;
+const FIS_OFFSET:   u64 = 1024;
+
+/// 5 s with interrupts on, or about as long spinning with them off.
+const TIMEOUT_TICKS: u64 = 500;
+const TIMEOUT_SPINS: u64 = 100_000_000;
+
+#[derive(Clone, Copy)]
+struct Port {
+    /// Virtual address of the port's register block.
+    regs:   u64,
+    /// Physical base of the command list, received FIS and command tables.
+    mem:    u64,
+    /// Physical base of the bounce buffer (`BOUNCE_PAGES`).
+    bounce: u64,
+    /// Commands that may be in flight at once: the NCQ depth, or 1.
+    depth:  u32,
+    ncq:    bool,
+    lba48:  bool,
+}
+
+static mut ABAR: u64 = 0;
+static mut S64A: bool = false;
+static mut PORTS: [Option<Port>; MAX_PORTS] = [const { None }; MAX_PORTS];
+/// Error bits the interrupt handler found in PxIS, until the waiter looks.
+static mut ERRORS: [u32; MAX_PORTS] = [0; MAX_PORTS];
+
+// ── Register access ───────────────────────────────────────────────────────
+
+unsafe fn rd(addr: u64) -> u32 {
+    unsafe { read_volatile(addr as *const u32) }
+}
+
+unsafe fn wr(addr: u64, v: u32) {
+    unsafe { write_volatile(addr as *mut u32, v) }
+}
+
+fn port_regs(abar: u64, p: usize) -> u64 {
+    abar + 0x100 + 0x80 * p as u64
+}
+
+/// About `us` microseconds: each write to the POST port takes ~1 µs.
+fn udelay(us: u32) {
+    for _ in 0..us {
+        unsafe { asm!("out 0x80, al", in("al") 0u8, options(nomem, nostack)); }
+    }
+}
+
+/// Wait up to `ms` milliseconds for `(reg & mask) == want`.
+unsafe fn wait_reg(addr: u64, mask: u32, want: u32, ms: u32) -> bool {
+    for _ in 0..ms * 10 {
+        if unsafe { rd(addr) } & mask == want { return true; }
+        udelay(100);
+    }
+    false
+}
+
+// ── Port engine ───────────────────────────────────────────────────────────
+
+/// Stop command processing and FIS reception.
+unsafe fn stop(regs: u64) {
+    unsafe {
+        wr(regs + PX_CMD, rd(regs + PX_CMD) & !CMD_ST);
+        wait_reg(regs + PX_CMD, CMD_CR, 0, 500);
+        wr(regs + PX_CMD, rd(regs + PX_CMD) & !CMD_FRE);
+        wait_reg(regs + PX_CMD, CMD_FR, 0, 500);
+    }
+}
+
+/// Start FIS reception and command processing once the device is idle.
+unsafe fn start(regs: u64) -> bool {
+    unsafe {
+        wr(regs + PX_CMD, rd(regs + PX_CMD) | CMD_SUD | CMD_POD | CMD_FRE);
+        if !wait_reg(regs + PX_TFD, TFD_BSY | TFD_DRQ, 0, 1000) { return false; }
+        wr(regs + PX_CMD, rd(regs + PX_CMD) | CMD_ST);
+    }
+    true
+}
+
+/// Bring a port back after an error or timeout: stopping it drops every
+/// outstanding command; a device still busy gets a COMRESET.
+unsafe fn recover(regs: u64) {
+    unsafe {
+        stop(regs);
+        if rd(regs + PX_TFD) & (TFD_BSY | TFD_DRQ) != 0 {
+            wr(regs + PX_SCTL, (rd(regs + PX_SCTL) & !0xF) | 1);
+            udelay(1000);
+            wr(regs + PX_SCTL, rd(regs + PX_SCTL) & !0xF);
+            wait_reg(regs + PX_SSTS, 0xF, SSTS_DET_PRESENT, 1000);
+        }
+        wr(regs + PX_SERR, !0);
+        wr(regs + PX_IS, !0);
+        start(regs);
+    }
+}
+
+// ── Commands ──────────────────────────────────────────────────────────────
+
+fn virt(phys: u64) -> u64 {
+    phys + get_hhdm_offset()
+}
+
+fn table(port: &Port, slot: u32) -> u64 {
+    virt(port.mem) + 4096 + slot as u64 * TABLE_SIZE as u64
+}
+
+/// Append one PRDT entry, if there is room.
+unsafe fn put_prd(table: u64, n: &mut usize, phys: u64, bytes: usize) -> Option<()> {
+    if *n == PRDT_MAX || (phys + bytes as u64 > 1 << 32 && !unsafe { S64A }) { return None; }
+    let e = (table + 0x80 + *n as u64 * 16) as *mut u32;
+    unsafe {
+        write_volatile(e,        phys as u32);
+        write_volatile(e.add(1), (phys >> 32) as u32);
+        write_volatile(e.add(2), 0);
+        write_volatile(e.add(3), (bytes - 1) as u32);
+    }
+    *n += 1;
+    Some(())
+}
+
+/// Describe the `len` bytes at kernel address `addr` in the PRDT of
+/// `table`, one entry per physically contiguous run.  Returns the entry
+/// count, or `None` if the buffer cannot be described.
+unsafe fn fill_prdt(table: u64, addr: u64, len: usize) -> Option<u16> {
+    if addr & 1 != 0 { return None; }
+    let mut n = 0;
+    let mut run: Option<(u64, usize)> = None;
+    let mut off = 0;
+    while off < len {
+        let v = addr + off as u64;
+        let phys = unsafe { kernel_virt_to_phys(v)? };
+        let step = (4096 - (v & 0xFFF) as usize).min(len - off);
+        run = match run {
+            Some((start, bytes)) if start + bytes as u64 == phys && bytes + step <= PRD_MAX_BYTES =>
+                Some((start, bytes + step)),
+            Some((start, bytes)) => {
+                unsafe { put_prd(table, &mut n, start, bytes)?; }
+                Some((phys, step))
+            }
+            None => Some((phys, step)),
+        };
+        off += step;
+    }
+    if let Some((start, bytes)) = run {
+        unsafe { put_prd(table, &mut n, start, bytes)?; }
+    }
+    Some(n as u16)
+}
+
+/// Write the command header and FIS for `slot` and hand it to the port.
+/// The PRDT (`prdtl` entries) must already be in place.
+unsafe fn issue(port: &Port, slot: u32, command: u8, lba: u64, count: u16, prdtl: u16, write: bool) {
+    let queued = matches!(command, ATA_READ_FPDMA | ATA_WRITE_FPDMA);
+    let hdr = (virt(port.mem) + slot as u64 * 32) as *mut u32;
+    unsafe {
+        write_volatile(hdr, 5 | (write as u32) << 6 | (prdtl as u32) << 16);
+        write_volatile(hdr.add(1), 0);
+    }
+
+    let mut fis = [0u8; 20];
+    fis[0] = FIS_TYPE_H2D;
+    fis[1] = FIS_COMMAND;
+    fis[2] = command;
+    fis[4] = lba as u8;
+    fis[5] = (lba >> 8) as u8;
+    fis[6] = (lba >> 16) as u8;
+    fis[7] = DEV_LBA;
+    fis[8] = (lba >> 24) as u8;
+    fis[9] = (lba >> 32) as u8;
+    fis[10] = (lba >> 40) as u8;
+    if queued {
+        // FPDMA: the sector count goes in FEATURES, the tag in COUNT.
+        fis[3]  = count as u8;
+        fis[11] = (count >> 8) as u8;
+        fis[12] = (slot as u8) << 3;
+    } else {
+        fis[12] = count as u8;
+        fis[13] = (count >> 8) as u8;
+        if !port.lba48 { fis[7] |= ((lba >> 24) & 0xF) as u8; }
+    }
+    let cfis = table(port, slot) as *mut u8;
+    unsafe {
+        core::ptr::write_bytes(cfis, 0, 64);
+        core::ptr::copy_nonoverlapping(fis.as_ptr(), cfis, fis.len());
+        if queued { wr(port.regs + PX_SACT, 1 << slot); }
+        wr(port.regs + PX_CI, 1 << slot);
+    }
+}
+
+/// Wait for the commands in `mask` on port `p` to finish.  On an error or
+/// timeout the port is reset and every command in flight is lost.
+unsafe fn wait(p: usize, mask: u32) -> bool {
+    let Some(port) = (unsafe { (*(&raw const PORTS))[p].as_ref() }) else { return false };
+    let rflags: u64;
+    unsafe { asm!("pushfq; pop {}", out(reg) rflags, options(nomem, preserves_flags)); }
+    let irqs = rflags & (1 << 9) != 0;
+    if irqs { unsafe { asm!("cli", options(nomem, nostack)); } }
+
+    let start = unsafe { timer::get_ticks() };
+    let mut spins = 0u64;
+    let ok = loop {
+        let err = unsafe {
+            core::mem::take(&mut (*(&raw mut ERRORS))[p]) | (rd(port.regs + PX_IS) & IS_ERRORS)
+        };
+        if err != 0 { break false; }
+        if unsafe { rd(port.regs + PX_CI) | rd(port.regs + PX_SACT) } & mask == 0 { break true; }
+        if irqs {
+            if unsafe { timer::get_ticks() } - start > TIMEOUT_TICKS { break false; }
+            // `sti` takes effect after `hlt` starts: no lost wakeup.
+            unsafe { asm!("sti; hlt; cli", options(nomem, nostack)); }
+        } else {
+            spins += 1;
+            if spins > TIMEOUT_SPINS { break false; }
+            core::hint::spin_loop();
+        }
+    };
+    if irqs { unsafe { asm!("sti", options(nomem, nostack)); } }
+
+    if !ok {
+        unsafe {
+            SERIAL_PORT.write_str("[ahci] port ");
+            SERIAL_PORT.write_decimal(p as u32);
+            SERIAL_PORT.write_str(": command failed, tfd=0x");
+            SERIAL_PORT.write_hex(rd(port.regs + PX_TFD));
+            SERIAL_PORT.write_str(" serr=0x");
+            SERIAL_PORT.write_hex(rd(port.regs + PX_SERR));
+            SERIAL_PORT.write_str("\n");
+            recover(port.regs);
+        }
+    }
+    ok
+}
+
+/// Run one command on slot 0 through the bounce buffer (`count` sectors
+/// copied in for a write, out for a read).
+unsafe fn bounced(p: usize, command: u8, lba: u64, count: usize, buf: *mut u8, write: bool) -> bool {
+    let Some(port) = (unsafe { (*(&raw const PORTS))[p].as_ref() }) else { return false };
+    let bounce = virt(port.bounce) as *mut u8;
+    let len = count * SECTOR;
+    if write && len > 0 { unsafe { core::ptr::copy_nonoverlapping(buf, bounce, len); } }
+    let prdtl = if len > 0 {
+        match unsafe { fill_prdt(table(port, 0), bounce as u64, len) } {
+            Some(n) => n,
+            None    => return false,
+        }
+    } else { 0 };
+    unsafe { issue(port, 0, command, lba, count as u16, prdtl, write); }
+    if !unsafe { wait(p, 1) } { return false; }
+    if !write && len > 0 { unsafe { core::ptr::copy_nonoverlapping(bounce, buf, len); } }
+    true
+}
+
+fn rw_command(port: &Port, write: bool) -> u8 {
+    match (port.ncq, port.lba48, write) {
+        (true,  _,     false) => ATA_READ_FPDMA,
+        (true,  _,     true)  => ATA_WRITE_FPDMA,
+        (false, true,  false) => ATA_READ_DMA48,
+        (false, true,  true)  => ATA_WRITE_DMA48,
+        (false, false, false) => ATA_READ_DMA,
+        (false, false, true)  => ATA_WRITE_DMA,
+    }
+}
+
+/// Move `count` sectors between the disk on port `p` and `buf`, starting
+/// at `lba`.  Writes are flushed from the drive's cache before returning.
+pub unsafe fn transfer(p: usize, lba: u64, count: usize, buf: *mut u8, write: bool) -> bool {
+    let Some(port) = (unsafe { (*(&raw const PORTS))[p].as_ref() }) else { return false };
+    let command = rw_command(port, write);
+    let mut done = 0;
+    while done < count {
+        // Fill as many slots as the port takes, then wait for all of them.
+        let mut mask = 0u32;
+        let mut slot = 0;
+        while done < count && slot < port.depth {
+            let n = (count - done).min(CHUNK_SECTORS);
+            let ptr = unsafe { buf.add(done * SECTOR) };
+            let Some(prdtl) = (unsafe { fill_prdt(table(port, slot), ptr as u64, n * SECTOR) }) else {
+                // Not describable: run it alone through the bounce buffer.
+                if mask != 0 { break; }
+                if !unsafe { bounced(p, command, lba + done as u64, n, ptr, write) } { return false; }
+                done += n;
+                continue;
+            };
+            unsafe { issue(port, slot, command, lba + done as u64, n as u16, prdtl, write); }
+            mask |= 1 << slot;
+            slot += 1;
+            done += n;
+        }
+        if mask != 0 && !unsafe { wait(p, mask) } { return false; }
+    }
+    if write {
+        let flush = if port.lba48 { ATA_FLUSH48 } else { ATA_FLUSH };
+        return unsafe { bounced(p, flush, 0, 0, core::ptr::null_mut(), false) };
+    }
+    true
+}
+
+// ── Interrupt ─────────────────────────────────────────────────────────────
+
+/// Acknowledge every port that raised the HBA's line.  Waiters notice on
+/// their own that their slots have cleared; errors are handed over in
+/// `ERRORS`.
+unsafe fn interrupt() -> bool {
+    let abar = unsafe { ABAR };
+    if abar == 0 { return false; }
+    let pending = unsafe { rd(abar + IS) };
+    if pending == 0 { return false; }
+    for p in 0..MAX_PORTS {
+        if pending & (1 << p) == 0 { continue; }
+        let regs = port_regs(abar, p);
+        unsafe {
+            let is = rd(regs + PX_IS);
+            wr(regs + PX_IS, is);
+            (*(&raw mut ERRORS))[p] |= is & IS_ERRORS;
+        }
+    }
+    unsafe { wr(abar + IS, pending); }
+    true
+}
+
+// ── Probing ───────────────────────────────────────────────────────────────
+
+/// Set up port `p` and, if a SATA disk answers IDENTIFY, add it to the
+/// ata disk table.
+unsafe fn probe_port(abar: u64, p: usize, slots: u32, sncq: bool) -> bool {
+    let regs = port_regs(abar, p);
+    if unsafe { rd(regs + PX_SSTS) } & 0xF != SSTS_DET_PRESENT { return false; }
+
+    unsafe { stop(regs); }
+    let (Some(mem), Some(bounce)) = (
+        unsafe { alloc_dma_frames(CMD_PAGES) },
+        unsafe { alloc_dma_frames(BOUNCE_PAGES) },
+    ) else {
+        unsafe { SERIAL_PORT.write_str("[ahci] Out of DMA memory\n"); }
+        return false;
+    };
+    let release = || unsafe {
+        free_phys_frames(mem, CMD_PAGES);
+        free_phys_frames(bounce, BOUNCE_PAGES);
+    };
+
+    // Command headers point at their tables once and for all.
+    for slot in 0..32u64 {
+        let hdr = (virt(mem) + slot * 32) as *mut u32;
+        let ctba = mem + 4096 + slot * TABLE_SIZE as u64;
+        unsafe {
+            write_volatile(hdr.add(2), ctba as u32);
+            write_volatile(hdr.add(3), (ctba >> 32) as u32);
+        }
+    }
+    unsafe {
+        wr(regs + PX_CLB,  mem as u32);
+        wr(regs + PX_CLBU, (mem >> 32) as u32);
+        wr(regs + PX_FB,   (mem + FIS_OFFSET) as u32);
+        wr(regs + PX_FBU,  ((mem + FIS_OFFSET) >> 32) as u32);
+        wr(regs + PX_SERR, !0);
+        wr(regs + PX_IS,   !0);
+    }
+    if !unsafe { start(regs) } {
+        unsafe { stop(regs); }
+        release();
+        return false;
+    }
+    let sig = unsafe { rd(regs + PX_SIG) };
+    if sig != SIG_ATA {
+        // ATAPI, port multiplier or enclosure: not a disk we drive.
+        unsafe {
+            SERIAL_PORT.write_str("[ahci] port ");
+            SERIAL_PORT.write_decimal(p as u32);
+            SERIAL_PORT.write_str(": signature 0x");
+            SERIAL_PORT.write_hex(sig);
+            SERIAL_PORT.write_str(", skipped\n");
+            stop(regs);
+        }
+        release();
+        return false;
+    }
+    unsafe { wr(regs + PX_IE, IS_DONE | IS_ERRORS); }
+
+    let mut port = Port { regs, mem, bounce, depth: 1, ncq: false, lba48: false };
+    unsafe { (*(&raw mut PORTS))[p] = Some(port); }
+
+    let mut id = [0u16; 256];
+    if !unsafe { bounced(p, ATA_IDENTIFY, 0, 1, id.as_mut_ptr() as *mut u8, false) } {
+        unsafe {
+            (*(&raw mut PORTS))[p] = None;
+            stop(regs);
+        }
+        release();
+        return false;
+    }
+
+    port.lba48 = id[83] & (1 << 10) != 0;
+    let sectors = if port.lba48 {
+        (id[100] as u64) | (id[101] as u64) << 16 | (id[102] as u64) << 32 | (id[103] as u64) << 48
+    } else {
+        (id[60] as u64) | (id[61] as u64) << 16
+    };
+    port.ncq = sncq && id[76] & (1 << 8) != 0;
+    if port.ncq { port.depth = ((id[75] & 0x1F) as u32 + 1).min(slots); }
+
+    let mut model = [b' '; 40];
+    for i in 0..20 {
+        model[i * 2]     = (id[27 + i] >> 8) as u8;
+        model[i * 2 + 1] = id[27 + i] as u8;
+    }
+    let end = model.iter().rposition(|&c| c != b' ').map_or(0, |i| i + 1);
+
+    let depth = port.depth;
+    let (ncq, lba48) = (port.ncq, port.lba48);
+    unsafe { (*(&raw mut PORTS))[p] = Some(port); }
+    let disk = AtaDisk { io_base: 0, ctrl: 0, slave: false, sectors, lba48, model, dma: Some(Dma::Ahci(p as u8)) };
+    let Some(idx) = (unsafe { ata::add_disk(p, disk) }) else {
+        unsafe {
+            SERIAL_PORT.write_str("[ahci] No free disk slot\n");
+            (*(&raw mut PORTS))[p] = None;
+            stop(regs);
+        }
+        release();
+        return false;
+    };
+
+    unsafe {
+        SERIAL_PORT.write_str("[ahci] port ");
+        SERIAL_PORT.write_decimal(p as u32);
+        SERIAL_PORT.write_str(" -> disk");
+        SERIAL_PORT.write_decimal(idx as u32);
+        SERIAL_PORT.write_str(": ");
+        SERIAL_PORT.write_decimal((sectors / 2048) as u32);
+        SERIAL_PORT.write_str(" MB");
+        if lba48 { SERIAL_PORT.write_str(" LBA48"); }
+        if ncq {
+            SERIAL_PORT.write_str(" NCQ depth ");
+            SERIAL_PORT.write_decimal(depth);
+        }
+        SERIAL_PORT.write_str(" model=");
+        SERIAL_PORT.write_str(core::str::from_utf8(&model[..end]).unwrap_or("?"));
+        SERIAL_PORT.write_str("\n");
+    }
+    true
+}
+
+/// Find the first AHCI controller, take it over from the firmware and add
+/// its disks to the ata disk table.  Returns how many disks were found.
+/// Called from `ata::init_all`.
+pub unsafe fn init() -> usize {
+    let Some(pci) = pci::find_class(0x01, 0x06) else { return 0 };
+    let Some(phys) = pci.mem_bar(5) else {
+        unsafe { SERIAL_PORT.write_str("[ahci] Controller has no ABAR\n"); }
+        return 0;
+    };
+    let Some(abar) = (unsafe { map_mmio(phys, 0x1100) }) else {
+        unsafe { SERIAL_PORT.write_str("[ahci] Cannot map ABAR\n"); }
+        return 0;
+    };
+    pci.enable_bus_mastering();
+
+    unsafe {
+        // BIOS/OS handoff, where the firmware supports it.
+        if rd(abar + CAP2) & CAP2_BOH != 0 {
+            wr(abar + BOHC, rd(abar + BOHC) | BOHC_OOS);
+            wait_reg(abar + BOHC, BOHC_BOS, 0, 2000);
+        }
+        wr(abar + GHC, rd(abar + GHC) | GHC_AE);
+        wr(abar + IS, !0);
+
+        let cap = rd(abar + CAP);
+        S64A = cap & CAP_S64A != 0;
+        ABAR = abar;
+        let irq = pci.irq_line();
+        let irq_ok = crate::kernel::interrupts::register_irq(irq, interrupt);
+        wr(abar + GHC, rd(abar + GHC) | GHC_IE);
+
+        let vs = rd(abar + VS);
+        SERIAL_PORT.write_str("[ahci] ");
+        SERIAL_PORT.write_hex(pci.vendor as u32);
+        SERIAL_PORT.write_str(":");
+        SERIAL_PORT.write_hex(pci.device as u32);
+        SERIAL_PORT.write_str(" AHCI ");
+        SERIAL_PORT.write_decimal(vs >> 16);
+        SERIAL_PORT.write_str(".");
+        SERIAL_PORT.write_decimal((vs >> 8) & 0xFF);
+        SERIAL_PORT.write_str(" ports=0x");
+        SERIAL_PORT.write_hex(rd(abar + PI));
+        if irq_ok {
+            SERIAL_PORT.write_str(" irq=");
+            SERIAL_PORT.write_decimal(irq as u32);
+        }
+        SERIAL_PORT.write_str("\n");
+
+        let slots = ((cap >> 8) & 0x1F) + 1;
+        let sncq = cap & CAP_SNCQ != 0;
+        let pi = rd(abar + PI);
+        (0..MAX_PORTS).filter(|&p| pi & (1 << p) != 0 && probe_port(abar, p, slots, sncq)).count()
+    }
+}
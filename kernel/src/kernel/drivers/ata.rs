@@ -9,6 +9,11 @@
 //! Each position is probed independently. Backward-compatible wrapper
 //! functions keep the old callers (installer, terminal, main) unchanged.
 //!
+//! `DISKS` is also the block-device table for SATA, virtio and NVMe disks:
+//! `ahci::init`, `virtio_blk::init` and `nvme::init` fill the positions IDE
+//! left empty and those above 3, up to [`MAX_DISKS`], and the read/write functions below hand those disks to their
+//! DMA driver instead of the PIO path.
+//!
 //! Works with: QEMU -device ide-hd, VirtualBox IDE controller,
 //!             VMware IDE adapter (set controller to IDE in VM settings).
 
@@ -58,11 +63,28 @@ pub struct AtaDisk {
     pub sectors:  u64,        // total LBA sector count (LBA48 allows >2G)
     pub lba48:    bool,
     pub model:    [u8; 40],
+    /// DMA controller behind the disk; `None` for a legacy IDE disk.
+    pub dma:      Option<Dma>,
+}
+
+/// Controller that moves a disk's sectors by DMA.
+#[derive(Clone, Copy)]
+pub enum Dma {
+    /// AHCI port.
+    Ahci(u8),
+    /// virtio-blk disk number.
+    Virtio(u8),
+    /// NVMe namespace number (driver-wide, not the NSID).
+    Nvme(u8),
 }
 
 // ── Global disk table ─────────────────────────────────────────────────────
 
-pub static mut DISKS: [Option<AtaDisk>; 4] = [None, None, None, None];
+/// Size of `DISKS`: the four IDE positions plus room for disks found by the
+/// other controller drivers.
+pub const MAX_DISKS: usize = 16;
+
+pub static mut DISKS: [Option<AtaDisk>; MAX_DISKS] = [const { None }; MAX_DISKS];
 
 // ── Port helpers ──────────────────────────────────────────────────────────
 
@@ -116,6 +138,9 @@ unsafe fn wait_drq(io: u16, timeout: u32) -> bool {
 unsafe fn probe_disk(slot: usize, io: u16, ctrl: u16, slave: bool) {
     let dev_sel: u8 = if slave { 0xB0 } else { 0xA0 };
 
+    // No controller at all (q35 has no legacy IDE): the bus floats high.
+    if unsafe { disk_inb(io, OFF_CMD) } == 0xFF { return; }
+
     // Soft reset the bus (affects both master and slave).
     if !slave {
         unsafe {
@@ -230,13 +255,15 @@ unsafe fn probe_disk(slot: usize, io: u16, ctrl: u16, slave: bool) {
         }
         SERIAL_PORT.write_str("\n");
 
-        DISKS[slot] = Some(AtaDisk { io_base: io, ctrl, slave, sectors, lba48, model });
+        DISKS[slot] = Some(AtaDisk { io_base: io, ctrl, slave, sectors, lba48, model, dma: None });
     }
 }
 
 // ── Public init ───────────────────────────────────────────────────────────
 
-/// Probe all four ATA positions and populate `DISKS[0..4]`.
+/// Probe all four ATA positions, the AHCI controller, virtio and NVMe disks,
+/// populate `DISKS` and add a `/dev/hdX` node for each disk found
+/// (NVMe namespaces get `/dev/nvmeXnY` from their driver instead).
 /// Call once during boot after memory is set up.
 pub unsafe fn init_all() {
     unsafe {
@@ -244,7 +271,37 @@ pub unsafe fn init_all() {
         probe_disk(1, PRIMARY_IO,   PRIMARY_CTRL,   true);  // primary slave
         probe_disk(2, SECONDARY_IO, SECONDARY_CTRL, false); // secondary master
         probe_disk(3, SECONDARY_IO, SECONDARY_CTRL, true);  // secondary slave
+        crate::kernel::ahci::init();
+        crate::kernel::virtio_blk::init();
+        crate::kernel::nvme::init();
     }
+    for idx in 0..MAX_DISKS {
+        if is_present_at(idx) && !matches!(dma(idx), Some(Dma::Nvme(_))) {
+            crate::kernel::devfs::register_disk(idx);
+        }
+    }
+}
+
+/// Put a disk found by another controller driver into `DISKS`: at
+/// `preferred` if that position is free, else the first free one.
+/// Returns the position, or `None` (with a warning on the serial port) when
+/// the table is full.
+pub unsafe fn add_disk(preferred: usize, disk: AtaDisk) -> Option<usize> {
+    let disks = unsafe { &mut *(&raw mut DISKS) };
+    let idx = if preferred < disks.len() && disks[preferred].is_none() {
+        preferred
+    } else if let Some(idx) = disks.iter().position(|d| d.is_none()) {
+        idx
+    } else {
+        unsafe {
+            SERIAL_PORT.write_str("[ata] WARNING: disk table full, dropping a ");
+            SERIAL_PORT.write_decimal((disk.sectors / 2048) as u32);
+            SERIAL_PORT.write_str(" MB disk\n");
+        }
+        return None;
+    };
+    disks[idx] = Some(disk);
+    Some(idx)
 }
 
 // ── Backward-compatible wrappers (callers: main.rs, installer, terminal) ──
@@ -269,10 +326,12 @@ pub fn sector_count_sec() -> u32 { unsafe { DISKS[3].as_ref().map(|d| d.sectors
 
 // ── Unified read / write ──────────────────────────────────────────────────
 
-/// Read one 512-byte sector from disk `idx` (0-3) at the given LBA.
+/// Read one 512-byte sector from disk `idx` at the given LBA.
 pub unsafe fn read_sector(idx: usize, lba: u32, buf: &mut [u8; 512]) -> bool {
     let (io, ctrl, slave, lba48) = unsafe {
         match DISKS[idx].as_ref() {
+            Some(AtaDisk { dma: Some(dma), .. }) =>
+                return dma_transfer(*dma, lba as u64, 1, buf.as_mut_ptr(), false),
             Some(d) => (d.io_base, d.ctrl, d.slave, d.lba48),
             None    => return false,
         }
@@ -280,10 +339,12 @@ pub unsafe fn read_sector(idx: usize, lba: u32, buf: &mut [u8; 512]) -> bool {
     unsafe { do_read(io, ctrl, slave, lba as u64, buf, lba48) }
 }
 
-/// Write one 512-byte sector to disk `idx` (0-3) at the given LBA.
+/// Write one 512-byte sector to disk `idx` at the given LBA.
 pub unsafe fn write_sector(idx: usize, lba: u32, buf: &[u8; 512]) -> bool {
     let (io, ctrl, slave, lba48) = unsafe {
         match DISKS[idx].as_ref() {
+            Some(AtaDisk { dma: Some(dma), .. }) =>
+                return dma_transfer(*dma, lba as u64, 1, buf.as_ptr() as *mut u8, true),
             Some(d) => (d.io_base, d.ctrl, d.slave, d.lba48),
             None    => return false,
         }
@@ -398,9 +459,28 @@ unsafe fn do_write(io: u16, ctrl: u16, slave: bool, lba: u64, buf: &[u8; 512], l
 
 // ── Convenience: multi-sector read/write ─────────────────────────────────
 
+/// DMA controller of disk `idx`, for the multi-sector calls that can go
+/// to it as one transfer.
+fn dma(idx: usize) -> Option<Dma> {
+    unsafe { (*(&raw const DISKS)).get(idx)?.as_ref()?.dma }
+}
+
+/// Move `count` sectors at `lba` through the DMA controller `dma`.
+unsafe fn dma_transfer(dma: Dma, lba: u64, count: usize, buf: *mut u8, write: bool) -> bool {
+    match dma {
+        Dma::Ahci(port) => unsafe { crate::kernel::ahci::transfer(port as usize, lba, count, buf, write) },
+        Dma::Virtio(d)  => unsafe { crate::kernel::virtio_blk::transfer(d as usize, lba, count, buf, write) },
+        Dma::Nvme(n)    => unsafe { crate::kernel::nvme::transfer(n as usize, lba, count, buf, write) },
+    }
+}
+
 /// Read `count` consecutive sectors from disk `idx` into `buf`.
 /// `buf` must be at least `count * 512` bytes.
 pub unsafe fn read_sectors(idx: usize, lba: u32, count: u32, buf: &mut [u8]) -> bool {
+    if let Some(dma) = dma(idx) {
+        if buf.len() < count as usize * 512 { return false; }
+        return unsafe { dma_transfer(dma, lba as u64, count as usize, buf.as_mut_ptr(), false) };
+    }
     for i in 0..count {
         let offset = (i as usize) * 512;
         let chunk = unsafe { &mut *(buf[offset..offset + 512].as_mut_ptr() as *mut [u8; 512]) };
@@ -411,6 +491,10 @@ pub unsafe fn read_sectors(idx: usize, lba: u32, count: u32, buf: &mut [u8]) ->
 
 /// Write `count` consecutive sectors to disk `idx` from `buf`.
 pub unsafe fn write_sectors(idx: usize, lba: u32, count: u32, buf: &[u8]) -> bool {
+    if let Some(dma) = dma(idx) {
+        if buf.len() < count as usize * 512 { return false; }
+        return unsafe { dma_transfer(dma, lba as u64, count as usize, buf.as_ptr() as *mut u8, true) };
+    }
     for i in 0..count {
         let offset = (i as usize) * 512;
         let chunk = unsafe { &*(buf[offset..offset + 512].as_ptr() as *const [u8; 512]) };
@@ -419,13 +503,13 @@ pub unsafe fn write_sectors(idx: usize, lba: u32, count: u32, buf: &[u8]) -> boo
     true
 }
 
-/// Returns `true` if disk `idx` (0-3) was detected.
+/// Returns `true` if disk `idx` was detected.
 pub fn is_present_at(idx: usize) -> bool {
-    if idx >= 4 { return false; }
+    if idx >= MAX_DISKS { return false; }
     unsafe { DISKS[idx].is_some() }
 }
 
-/// Returns how many disks were detected across all four positions.
+/// Returns how many disks were detected across all positions.
 pub fn disk_count() -> usize {
     unsafe { (*core::ptr::addr_of!(DISKS)).iter().filter(|d| d.is_some()).count() }
 }
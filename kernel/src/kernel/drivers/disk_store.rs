@@ -54,7 +54,7 @@ const _: () = assert!(core::mem::size_of::<RecordSlot>()  == 512);
 
 // ── Store state ───────────────────────────────────────────────────────────
 
-static mut STORE_MOUNTED: [bool; 4] = [false; 4];
+static mut STORE_MOUNTED: [bool; ata::MAX_DISKS] = [false; ata::MAX_DISKS];
 
 // ── Internal helpers ──────────────────────────────────────────────────────
 
@@ -0,0 +1,387 @@
+//! OSS sound devices — `/dev/dsp` (PCM playback) and `/dev/mixer`.
+//!
+//! `/dev/dsp` takes samples in the format the opener picked with the OSS
+//! ioctls (`SNDCTL_DSP_SPEED`, `_SETFMT`, `_CHANNELS`; 8 kHz unsigned 8-bit
+//! mono until then), scales them by the stream's own volume
+//! (`SNDCTL_DSP_SETPLAYVOL`) and converts them to the 16-bit stereo the
+//! AC'97 ring holds, resampling when the codec cannot run at the requested
+//! rate.  Writes block while the ring is full.  There is one playback
+//! stream: a second open fails with `EBUSY`, as on OSS without vmix.
+//!
+//! `/dev/mixer` exposes the codec's master and PCM volume as
+//! `SOUND_MIXER_VOLUME` and `SOUND_MIXER_PCM`; the mixer ioctls work on a
+//! `/dev/dsp` fd too.
+//!
+//! The stream is reference-counted across dup and fork like evdev clients.
+
+use crate::kernel::ac97::{self, Control};
+use crate::kernel::anonfd::EAGAIN;
+use crate::kernel::epoll::{self, Source};
+use crate::kernel::fs::devfs::{self, DevNode};
+use crate::kernel::fs::ramfs::{FdBackend, FdEntry, FdTable, MAX_FD};
+use crate::kernel::scheduler::{SCHED, CURRENT_TASK_IDX, wait_for_fd};
+use crate::kernel::syscall_core::validate_user_range;
+use crate::kernel::user_mode::CURRENT_SYSCALL_CTX;
+
+// Sample formats (sys/soundcard.h).
+pub const AFMT_U8:     u32 = 0x0008;
+pub const AFMT_S16_LE: u32 = 0x0010;
+pub const AFMT_S8:     u32 = 0x0040;
+
+// /dev/dsp ioctls.
+const SNDCTL_DSP_RESET:       u64 = 0x0000_5000;
+const SNDCTL_DSP_SYNC:        u64 = 0x0000_5001;
+const SNDCTL_DSP_SPEED:       u64 = 0xC004_5002;
+const SNDCTL_DSP_STEREO:      u64 = 0xC004_5003;
+const SNDCTL_DSP_GETBLKSIZE:  u64 = 0xC004_5004;
+const SNDCTL_DSP_SETFMT:      u64 = 0xC004_5005;
+const SNDCTL_DSP_CHANNELS:    u64 = 0xC004_5006;
+const SNDCTL_DSP_POST:        u64 = 0x0000_5008;
+const SNDCTL_DSP_SETFRAGMENT: u64 = 0xC004_500A;
+const SNDCTL_DSP_GETFMTS:     u64 = 0x8004_500B;
+const SNDCTL_DSP_GETOSPACE:   u64 = 0x8010_500C;
+const SNDCTL_DSP_NONBLOCK:    u64 = 0x0000_500E;
+const SNDCTL_DSP_GETODELAY:   u64 = 0x8004_5017;
+const SNDCTL_DSP_GETPLAYVOL:  u64 = 0x8004_5018;
+const SNDCTL_DSP_SETPLAYVOL:  u64 = 0xC004_5018;
+
+// Mixer ioctls.
+const SOUND_MIXER_VOLUME: u64 = 0;
+const SOUND_MIXER_PCM:    u64 = 4;
+const MIXER_READ:  u64 = 0x8004_4D00;
+const MIXER_WRITE: u64 = 0xC004_4D00;
+const SOUND_MIXER_READ_RECSRC:     u64 = 0x8004_4DFF;
+const SOUND_MIXER_READ_DEVMASK:    u64 = 0x8004_4DFE;
+const SOUND_MIXER_READ_RECMASK:    u64 = 0x8004_4DFD;
+const SOUND_MIXER_READ_CAPS:       u64 = 0x8004_4DFC;
+const SOUND_MIXER_READ_STEREODEVS: u64 = 0x8004_4DFB;
+const OSS_GETVERSION:              u64 = 0x8004_4D76;
+const SOUND_VERSION: i32 = 0x03_0802;
+
+const O_ACCMODE:  u32 = 0x3;
+const O_NONBLOCK: u32 = 0x800;
+
+const ENODEV: i64 = -19;
+const EFAULT: i64 = -14;
+const EBUSY:  i64 = -16;
+const EINVAL: i64 = -22;
+const ENOTTY: i64 = -25;
+
+const MIN_RATE: u32 = 8000;
+const MAX_RATE: u32 = 48_000;
+
+struct Stream {
+    used:     bool,
+    refs:     u32,
+    nonblock: bool,
+    rate:     u32,
+    format:   u32,
+    channels: u32,
+    /// Stream volume, (left, right) in percent.
+    volume:   (u8, u8),
+    /// Position in the input between writes when resampling, 16.16 frames.
+    phase:    u32,
+    /// `SNDCTL_DSP_SYNC` is waiting for the ring to drain.
+    draining: bool,
+}
+
+impl Stream {
+    const FREE: Self = Self {
+        used: false, refs: 0, nonblock: false,
+        rate: MIN_RATE, format: AFMT_U8, channels: 1,
+        volume: (100, 100), phase: 0, draining: false,
+    };
+
+    fn sample_bytes(&self) -> usize {
+        if self.format == AFMT_S16_LE { 2 } else { 1 }
+    }
+
+    fn frame_bytes(&self) -> usize {
+        self.sample_bytes() * self.channels as usize
+    }
+
+    /// Frame `i` of `data` as scaled 16-bit left and right samples.
+    fn frame(&self, data: &[u8], i: usize) -> (i16, i16) {
+        let at = |off: usize| -> i32 {
+            match self.format {
+                AFMT_U8 => (data[off] as i32 - 128) << 8,
+                AFMT_S8 => (data[off] as i8 as i32) << 8,
+                _       => i16::from_le_bytes([data[off], data[off + 1]]) as i32,
+            }
+        };
+        let base = i * self.frame_bytes();
+        let left  = at(base);
+        let right = if self.channels == 2 { at(base + self.sample_bytes()) } else { left };
+        ((left  * self.volume.0 as i32 / 100) as i16,
+         (right * self.volume.1 as i32 / 100) as i16)
+    }
+
+    /// Device-format byte count `bytes` expressed in this stream's format.
+    fn stream_bytes(&self, bytes: usize) -> usize {
+        let frames = (bytes / ac97::FRAME) as u64 * self.rate as u64 / ac97::rate() as u64;
+        frames as usize * self.frame_bytes()
+    }
+}
+
+static mut STREAM: Stream = Stream::FREE;
+
+unsafe fn stream() -> &'static mut Stream {
+    unsafe { &mut *(&raw mut STREAM) }
+}
+
+/// Add `/dev/dsp` and `/dev/mixer` (major 14).  Called by the AC'97
+/// driver once the codec is up.
+pub fn register_devices() {
+    for (name, backend, minor) in [("mixer", FdBackend::Mixer, 0), ("dsp", FdBackend::Dsp, 3)] {
+        devfs::register(DevNode {
+            name, backend, minor: 0, rdev: (14, minor), block: false,
+            mode: 0o660, uid: 0, gid: devfs::GID_AUDIO,
+        });
+    }
+}
+
+// ── Opening and closing ───────────────────────────────────────────────────
+
+/// Open `/dev/dsp` for playback and install it in `fdt`.
+pub unsafe fn open(fdt: &mut FdTable, flags: u32) -> i64 {
+    if flags & O_ACCMODE == 0 { return ENODEV; } // no capture channel
+    let s = unsafe { stream() };
+    if s.used { return EBUSY; }
+    let fd = fdt.open_dev(FdBackend::Dsp, 0, true);
+    if fd < 0 { return fd; }
+    *s = Stream::FREE;
+    s.used     = true;
+    s.refs     = 1;
+    s.nonblock = flags & O_NONBLOCK != 0;
+    unsafe {
+        ac97::reset();
+        ac97::set_rate(s.rate);
+    }
+    fd
+}
+
+/// A new fd refers to the stream behind `e` (dup, fork).
+pub unsafe fn addref(e: &FdEntry) {
+    if e.backend == FdBackend::Dsp {
+        unsafe { stream().refs += 1; }
+    }
+}
+
+/// An fd referring to the stream behind `e` went away.  The last one lets
+/// what is queued play out.
+pub unsafe fn release(e: &FdEntry) {
+    if e.backend != FdBackend::Dsp { return; }
+    let s = unsafe { stream() };
+    s.refs = s.refs.saturating_sub(1);
+    if s.refs == 0 {
+        unsafe { ac97::flush(); }
+        epoll::forget(Source::Dsp);
+        *s = Stream::FREE;
+    }
+}
+
+/// The task at `idx` is exiting: release the stream if it holds it.
+pub unsafe fn on_exit(idx: usize) {
+    let fdt = unsafe { &raw mut (*(&raw mut SCHED)).tasks[idx].fd_table };
+    for slot in unsafe { (*fdt).entries.iter_mut() } {
+        if let Some(e) = slot {
+            if e.backend == FdBackend::Dsp {
+                unsafe { release(e); }
+                *slot = None;
+            }
+        }
+    }
+}
+
+/// A buffer finished playing (called from the AC'97 interrupt).
+pub unsafe fn wake() {
+    if unsafe { stream().used } {
+        epoll::notify(Source::Dsp);
+    }
+}
+
+// ── File operations ───────────────────────────────────────────────────────
+
+/// `write(2)`: whole frames only; `EAGAIN` when the ring is full.
+pub unsafe fn write(buf: &[u8]) -> i64 {
+    let s = unsafe { stream() };
+    if buf.is_empty() { return 0; }
+    let fb = s.frame_bytes();
+    if buf.len() < fb { return EINVAL; }
+    let frames = buf.len() / fb;
+    let step = ((s.rate as u64) << 16) / ac97::rate() as u64;
+
+    // Convert a chunk at a time, never more than the ring has room for.
+    let mut chunk = [0u8; 1024];
+    let mut done = 0;
+    while done < frames {
+        let room = unsafe { ac97::space() }.min(chunk.len()) / ac97::FRAME;
+        if room == 0 { break; }
+        let mut n = 0;
+        while n < room {
+            let i = done + (s.phase >> 16) as usize;
+            if i >= frames { break; }
+            let (l, r) = s.frame(buf, i);
+            chunk[n * 4..n * 4 + 2].copy_from_slice(&l.to_le_bytes());
+            chunk[n * 4 + 2..n * 4 + 4].copy_from_slice(&r.to_le_bytes());
+            s.phase += step as u32;
+            n += 1;
+        }
+        let used = ((s.phase >> 16) as usize).min(frames - done);
+        done += used;
+        s.phase -= (used as u32) << 16;
+        unsafe { ac97::push(&chunk[..n * ac97::FRAME]); }
+    }
+    if done == 0 { return EAGAIN; }
+    (done * fb) as i64
+}
+
+/// `(readable, writable)`: writable while the ring has room, or, during
+/// `SNDCTL_DSP_SYNC`, once it has drained.
+pub unsafe fn ready() -> (bool, bool) {
+    let s = unsafe { stream() };
+    let w = match s.draining {
+        true  => unsafe { !ac97::playing() },
+        false => unsafe { ac97::space() > 0 },
+    };
+    (false, w)
+}
+
+/// Park the current task on `fd` until it is writable.
+unsafe fn wait(fd: i32) {
+    if let Some(ctx) = unsafe { core::ptr::replace(&raw mut CURRENT_SYSCALL_CTX, None) } {
+        unsafe { wait_for_fd(fd, true, ctx); }
+    }
+}
+
+/// Called when a write on the current task's `fd` returned `EAGAIN`:
+/// unless the stream is non-blocking, park the task until there is room.
+pub unsafe fn block(fd: i32) {
+    if fd < 0 || fd as usize >= MAX_FD { return; }
+    match unsafe { (*(&raw const SCHED)).tasks[CURRENT_TASK_IDX].fd_table.entries[fd as usize] } {
+        Some(e) if e.backend == FdBackend::Dsp => {}
+        _ => return,
+    }
+    if unsafe { stream().nonblock } { return; }
+    unsafe { wait(fd); }
+}
+
+// ── ioctls ────────────────────────────────────────────────────────────────
+
+unsafe fn get_int(arg: u64) -> Result<i32, i64> {
+    if validate_user_range(arg, 4).is_err() { return Err(EFAULT); }
+    Ok(unsafe { core::ptr::read_unaligned(arg as *const i32) })
+}
+
+unsafe fn put_ints(arg: u64, vals: &[i32]) -> i64 {
+    if validate_user_range(arg, vals.len() as u64 * 4).is_err() { return EFAULT; }
+    unsafe { core::ptr::copy_nonoverlapping(vals.as_ptr(), arg as *mut i32, vals.len()); }
+    0
+}
+
+/// OSS packs stereo levels as `left | right << 8`.
+fn pack((left, right): (u8, u8)) -> i32 {
+    left as i32 | (right as i32) << 8
+}
+
+fn unpack(v: i32) -> (u8, u8) {
+    ((v & 0xFF).min(100) as u8, ((v >> 8) & 0xFF).min(100) as u8)
+}
+
+/// `/dev/dsp` ioctls on the stream behind `fd`.
+pub unsafe fn ioctl(fd: i32, request: u64, arg: u64) -> i64 {
+    let s = unsafe { stream() };
+    match request {
+        SNDCTL_DSP_RESET => {
+            unsafe { ac97::reset(); }
+            s.phase = 0;
+            0
+        }
+        SNDCTL_DSP_POST => { unsafe { ac97::flush(); } 0 }
+        SNDCTL_DSP_SYNC => {
+            unsafe { ac97::flush(); }
+            s.draining = unsafe { ac97::playing() };
+            if s.draining { unsafe { wait(fd); } }
+            0
+        }
+        SNDCTL_DSP_NONBLOCK => { s.nonblock = true; 0 }
+        SNDCTL_DSP_SPEED => {
+            let hz = match unsafe { get_int(arg) } { Ok(v) => v, Err(e) => return e };
+            if hz > 0 {
+                s.rate = (hz as u32).clamp(MIN_RATE, MAX_RATE);
+                s.phase = 0;
+                unsafe { ac97::set_rate(s.rate); }
+            }
+            unsafe { put_ints(arg, &[s.rate as i32]) }
+        }
+        SNDCTL_DSP_STEREO | SNDCTL_DSP_CHANNELS => {
+            let v = match unsafe { get_int(arg) } { Ok(v) => v, Err(e) => return e };
+            let stereo = if request == SNDCTL_DSP_STEREO { v != 0 } else { v >= 2 };
+            if request == SNDCTL_DSP_STEREO || v > 0 {
+                s.channels = if stereo { 2 } else { 1 };
+            }
+            let out = if request == SNDCTL_DSP_STEREO { s.channels as i32 - 1 } else { s.channels as i32 };
+            unsafe { put_ints(arg, &[out]) }
+        }
+        SNDCTL_DSP_SETFMT => {
+            let f = match unsafe { get_int(arg) } { Ok(v) => v as u32, Err(e) => return e };
+            if matches!(f, AFMT_U8 | AFMT_S8 | AFMT_S16_LE) { s.format = f; }
+            unsafe { put_ints(arg, &[s.format as i32]) }
+        }
+        SNDCTL_DSP_GETFMTS => unsafe { put_ints(arg, &[(AFMT_U8 | AFMT_S8 | AFMT_S16_LE) as i32]) },
+        SNDCTL_DSP_GETBLKSIZE => unsafe { put_ints(arg, &[s.stream_bytes(ac97::BUF_SIZE) as i32]) },
+        // Fragments are the AC'97 buffers; the request is only a hint.
+        SNDCTL_DSP_SETFRAGMENT => 0,
+        SNDCTL_DSP_GETOSPACE => {
+            // struct audio_buf_info { fragments, fragstotal, fragsize, bytes }
+            let space = unsafe { ac97::space() };
+            unsafe {
+                put_ints(arg, &[
+                    (space / ac97::BUF_SIZE) as i32,
+                    (ac97::BUFFERS - 1) as i32,
+                    s.stream_bytes(ac97::BUF_SIZE) as i32,
+                    s.stream_bytes(space) as i32,
+                ])
+            }
+        }
+        SNDCTL_DSP_GETODELAY => unsafe { put_ints(arg, &[s.stream_bytes(ac97::queued()) as i32]) },
+        SNDCTL_DSP_GETPLAYVOL => unsafe { put_ints(arg, &[pack(s.volume)]) },
+        SNDCTL_DSP_SETPLAYVOL => {
+            let v = match unsafe { get_int(arg) } { Ok(v) => v, Err(e) => return e };
+            s.volume = unpack(v);
+            unsafe { put_ints(arg, &[pack(s.volume)]) }
+        }
+        _ => unsafe { mixer_ioctl(request, arg) },
+    }
+}
+
+/// `/dev/mixer` ioctls.
+pub unsafe fn mixer_ioctl(request: u64, arg: u64) -> i64 {
+    let control = |channel: u64| match channel {
+        SOUND_MIXER_VOLUME => Some(Control::Master),
+        SOUND_MIXER_PCM    => Some(Control::Pcm),
+        _                  => None,
+    };
+    match request {
+        SOUND_MIXER_READ_DEVMASK | SOUND_MIXER_READ_STEREODEVS => unsafe {
+            put_ints(arg, &[(1 << SOUND_MIXER_VOLUME | 1 << SOUND_MIXER_PCM) as i32])
+        },
+        SOUND_MIXER_READ_RECMASK | SOUND_MIXER_READ_RECSRC | SOUND_MIXER_READ_CAPS => unsafe {
+            put_ints(arg, &[0])
+        },
+        OSS_GETVERSION => unsafe { put_ints(arg, &[SOUND_VERSION]) },
+        r if r & !0xFF == MIXER_READ => match control(r & 0xFF) {
+            Some(c) => unsafe { put_ints(arg, &[pack(ac97::volume(c))]) },
+            None    => EINVAL,
+        },
+        r if r & !0xFF == MIXER_WRITE => match control(r & 0xFF) {
+            Some(c) => {
+                let (l, r) = match unsafe { get_int(arg) } { Ok(v) => unpack(v), Err(e) => return e };
+                unsafe { ac97::set_volume(c, l, r); }
+                unsafe { put_ints(arg, &[pack(ac97::volume(c))]) }
+            }
+            None => EINVAL,
+        },
+        _ => ENOTTY,
+    }
+}
@@ -0,0 +1,502 @@
+//! Linux evdev input devices — `/dev/input/event0` (keyboard) and
+//! `/dev/input/event1` (mouse).
+//!
+//! The PS/2 drivers hand every key transition and mouse packet here before
+//! decoding them for the console and the GUI.  Each open file is a client
+//! with its own queue of `struct input_event`s: key presses are `EV_KEY`
+//! with value 1, releases 0 and typematic repeats 2; mouse motion is
+//! `EV_REL` (`REL_X`/`REL_Y`, y growing downwards) plus `BTN_*` keys; every
+//! packet ends with `SYN_REPORT`.  A client that falls behind loses its
+//! queue and gets `SYN_DROPPED`.
+//!
+//! `EVIOCGRAB` gives one client the device exclusively: other clients stop
+//! receiving events and so do the console and the desktop, as on Linux.
+//! The grab goes away with the last fd of the grabbing client.
+//!
+//! Clients are reference-counted across dup and fork like pty ends;
+//! `FdEntry::raw_fd` is the client slot.
+
+use pc_keyboard::KeyCode;
+use crate::kernel::anonfd::EAGAIN;
+use crate::kernel::epoll::{self, Source};
+use crate::kernel::fs::devfs::{self, DevNode};
+use crate::kernel::fs::ramfs::{FdBackend, FdEntry, FdTable, MAX_FD};
+use crate::kernel::scheduler::{SCHED, CURRENT_TASK_IDX, wait_for_fd};
+use crate::kernel::syscall_core::validate_user_range;
+use crate::kernel::user_mode::CURRENT_SYSCALL_CTX;
+
+pub const KEYBOARD: usize = 0;
+pub const MOUSE:    usize = 1;
+const NR_DEVICES: usize = 2;
+
+const NR_CLIENTS: usize = 16;
+/// Events queued per client before it overflows.
+const QUEUE_LEN:  usize = 128;
+
+// Event types and codes (linux/input-event-codes.h).
+pub const EV_SYN: u16 = 0x00;
+pub const EV_KEY: u16 = 0x01;
+pub const EV_REL: u16 = 0x02;
+pub const EV_LED: u16 = 0x11;
+pub const EV_REP: u16 = 0x14;
+const EV_MAX: u16 = 0x1f;
+
+pub const SYN_REPORT:  u16 = 0;
+pub const SYN_DROPPED: u16 = 3;
+pub const REL_X: u16 = 0x00;
+pub const REL_Y: u16 = 0x01;
+pub const BTN_LEFT:   u16 = 0x110;
+pub const BTN_RIGHT:  u16 = 0x111;
+pub const BTN_MIDDLE: u16 = 0x112;
+const KEY_MAX: usize = 0x2ff;
+const INPUT_PROP_POINTER: usize = 0;
+
+const EV_VERSION: u32 = 0x01_0001;
+const BUS_I8042:  u16 = 0x11;
+
+// ioctl numbers, less the direction and size bits (see `ioctl`).
+const EVIOCGVERSION: u8 = 0x01;
+const EVIOCGID:      u8 = 0x02;
+const EVIOCGREP:     u8 = 0x03;
+const EVIOCSREP:     u8 = 0x03;
+const EVIOCGNAME:    u8 = 0x06;
+const EVIOCGPHYS:    u8 = 0x07;
+const EVIOCGUNIQ:    u8 = 0x08;
+const EVIOCGPROP:    u8 = 0x09;
+const EVIOCGKEY:     u8 = 0x18;
+const EVIOCGLED:     u8 = 0x19;
+const EVIOCGSND:     u8 = 0x1a;
+const EVIOCGSW:      u8 = 0x1b;
+const EVIOCGBIT:     u8 = 0x20;
+const EVIOCGRAB:     u8 = 0x90;
+const EVIOCSCLOCKID: u8 = 0xa0;
+
+const IOC_WRITE: u64 = 1;
+const IOC_READ:  u64 = 2;
+
+const O_NONBLOCK: u32 = 0x800;
+
+const ENOENT: i64 = -2;
+const EFAULT: i64 = -14;
+const EBUSY:  i64 = -16;
+const EINVAL: i64 = -22;
+const ENOTTY: i64 = -25;
+const ENOSPC: i64 = -28;
+
+/// `struct input_event` on x86_64 (24 bytes).
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct InputEvent {
+    pub sec:   i64,
+    pub usec:  i64,
+    pub type_: u16,
+    pub code:  u16,
+    pub value: i32,
+}
+
+const EVENT_SIZE: usize = core::mem::size_of::<InputEvent>();
+
+struct Device {
+    name: &'static str,
+    phys: &'static str,
+    /// `struct input_id`: bustype, vendor, product, version.
+    id:   [u16; 4],
+    /// Keys and buttons currently down, as an `EVIOCGKEY` bitmap.
+    keys: [u8; KEY_MAX / 8 + 1],
+    /// Client holding `EVIOCGRAB`.
+    grab: Option<usize>,
+}
+
+static mut DEVICES: [Device; NR_DEVICES] = [
+    Device {
+        name: "AT Translated Set 2 keyboard", phys: "isa0060/serio0/input0",
+        id: [BUS_I8042, 0x0001, 0x0001, 0xab41], keys: [0; KEY_MAX / 8 + 1], grab: None,
+    },
+    Device {
+        name: "PS/2 Generic Mouse", phys: "isa0060/serio1/input0",
+        id: [BUS_I8042, 0x0002, 0x0001, 0x0000], keys: [0; KEY_MAX / 8 + 1], grab: None,
+    },
+];
+
+struct Client {
+    used:     bool,
+    dev:      usize,
+    refs:     u32,
+    nonblock: bool,
+    queue:    [InputEvent; QUEUE_LEN],
+    head:     usize,
+    len:      usize,
+}
+
+impl Client {
+    const FREE: Self = Self {
+        used: false, dev: 0, refs: 0, nonblock: false,
+        queue: [InputEvent { sec: 0, usec: 0, type_: 0, code: 0, value: 0 }; QUEUE_LEN],
+        head: 0, len: 0,
+    };
+
+    fn push(&mut self, ev: InputEvent) {
+        if self.len == QUEUE_LEN {
+            // Overflow: drop everything and tell the reader to resync.
+            self.head = 0;
+            self.len  = 0;
+            self.push(InputEvent { type_: EV_SYN, code: SYN_DROPPED, ..ev });
+            return;
+        }
+        self.queue[(self.head + self.len) % QUEUE_LEN] = ev;
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> Option<InputEvent> {
+        if self.len == 0 { return None; }
+        let ev = self.queue[self.head];
+        self.head = (self.head + 1) % QUEUE_LEN;
+        self.len -= 1;
+        Some(ev)
+    }
+}
+
+static mut CLIENTS: [Client; NR_CLIENTS] = [const { Client::FREE }; NR_CLIENTS];
+
+/// Typematic delay and period in milliseconds, as `EVIOCGREP` reports them.
+static mut REPEAT: [u32; 2] = [500, 92];
+
+unsafe fn device(dev: usize) -> &'static mut Device {
+    unsafe { &mut (*(&raw mut DEVICES))[dev] }
+}
+
+unsafe fn client(n: usize) -> &'static mut Client {
+    unsafe { &mut (*(&raw mut CLIENTS))[n] }
+}
+
+/// Add `/dev/input/event0` and `/dev/input/event1` (major 13, minors 64+).
+pub fn register_devices() {
+    for (dev, name) in [(KEYBOARD, "input/event0"), (MOUSE, "input/event1")] {
+        devfs::register(DevNode {
+            name, backend: FdBackend::Evdev, minor: dev as i32,
+            rdev: (13, 64 + dev as u32), block: false,
+            mode: 0o660, uid: 0, gid: devfs::GID_INPUT,
+        });
+    }
+}
+
+// ── Opening and closing ───────────────────────────────────────────────────
+
+/// Open device `dev`: allocate a client and install it in `fdt`.
+pub unsafe fn open(fdt: &mut FdTable, dev: usize, flags: u32) -> i64 {
+    let Some(n) = (0..NR_CLIENTS).find(|&n| unsafe { !client(n).used }) else { return ENOSPC };
+    let fd = fdt.open_dev(FdBackend::Evdev, n as i32, false);
+    if fd < 0 { return fd; }
+    unsafe {
+        *client(n) = Client::FREE;
+        let c = client(n);
+        c.used     = true;
+        c.dev      = dev;
+        c.refs     = 1;
+        c.nonblock = flags & O_NONBLOCK != 0;
+    }
+    fd
+}
+
+/// The device behind client `n` (for `fstat`).
+pub fn device_of(n: i32) -> i32 {
+    unsafe { client(n as usize).dev as i32 }
+}
+
+/// A new fd refers to the client behind `e` (dup, fork).
+pub unsafe fn addref(e: &FdEntry) {
+    if e.backend == FdBackend::Evdev {
+        unsafe { client(e.raw_fd as usize).refs += 1; }
+    }
+}
+
+/// An fd referring to the client behind `e` went away.
+pub unsafe fn release(e: &FdEntry) {
+    if e.backend != FdBackend::Evdev { return; }
+    let n = e.raw_fd as usize;
+    let c = unsafe { client(n) };
+    c.refs = c.refs.saturating_sub(1);
+    if c.refs == 0 {
+        let d = unsafe { device(c.dev) };
+        if d.grab == Some(n) { d.grab = None; }
+        epoll::forget(Source::Evdev(n));
+        *c = Client::FREE;
+    }
+}
+
+/// The task at `idx` is exiting: release the clients it holds.
+pub unsafe fn on_exit(idx: usize) {
+    let fdt = unsafe { &raw mut (*(&raw mut SCHED)).tasks[idx].fd_table };
+    for slot in unsafe { (*fdt).entries.iter_mut() } {
+        if let Some(e) = slot {
+            if e.backend == FdBackend::Evdev {
+                unsafe { release(e); }
+                *slot = None;
+            }
+        }
+    }
+}
+
+// ── Event delivery (called by the PS/2 drivers) ───────────────────────────
+
+/// Queue `events` on every client of `dev`, or only on the grabbing one.
+unsafe fn deliver(dev: usize, events: &[(u16, u16, i32)]) {
+    let ns  = unsafe { crate::kernel::timer::get_ticks() } * crate::kernel::timers::NS_PER_TICK;
+    let sec = (ns / 1_000_000_000) as i64;
+    let usec = (ns % 1_000_000_000 / 1000) as i64;
+    let grab = unsafe { device(dev).grab };
+    for n in 0..NR_CLIENTS {
+        let c = unsafe { client(n) };
+        if !c.used || c.dev != dev || grab.is_some_and(|g| g != n) { continue; }
+        for &(type_, code, value) in events {
+            c.push(InputEvent { sec, usec, type_, code, value });
+        }
+        epoll::notify(Source::Evdev(n));
+    }
+}
+
+/// True if some client has grabbed `dev`; the caller then keeps the input
+/// to itself.
+pub fn grabbed(dev: usize) -> bool {
+    unsafe { device(dev).grab.is_some() }
+}
+
+/// Record key `code` going down or up and report it.  A press of a key
+/// that is already down is a typematic repeat.
+unsafe fn key_event(dev: usize, code: u16, down: bool) {
+    let d = unsafe { device(dev) };
+    let (byte, bit) = (code as usize / 8, 1u8 << (code % 8));
+    let was_down = d.keys[byte] & bit != 0;
+    if !down && !was_down { return; }
+    if down { d.keys[byte] |= bit; } else { d.keys[byte] &= !bit; }
+    let value = match (down, was_down) { (true, true) => 2, (true, false) => 1, _ => 0 };
+    unsafe { deliver(dev, &[(EV_KEY, code, value), (EV_SYN, SYN_REPORT, 0)]); }
+}
+
+/// A key transition decoded by the keyboard driver.
+pub unsafe fn keyboard(code: KeyCode, down: bool) {
+    if let Some(&(_, linux)) = KEYMAP.iter().find(|(k, _)| *k == code) {
+        unsafe { key_event(KEYBOARD, linux, down); }
+    }
+}
+
+/// The keyboard LEDs changed (`num`, `caps`, `scroll`).
+pub unsafe fn leds(num: bool, caps: bool, scroll: bool) {
+    unsafe {
+        deliver(KEYBOARD, &[
+            (EV_LED, 0, num as i32), (EV_LED, 1, caps as i32), (EV_LED, 2, scroll as i32),
+            (EV_SYN, SYN_REPORT, 0),
+        ]);
+    }
+}
+
+/// A complete mouse packet: motion (screen orientation, y down) and the
+/// left/right/middle button bits.
+pub unsafe fn mouse(dx: i32, dy: i32, buttons: u8) {
+    let d = unsafe { device(MOUSE) };
+    let mut events = [(0u16, 0u16, 0i32); 6];
+    let mut n = 0;
+    for (i, btn) in [BTN_LEFT, BTN_RIGHT, BTN_MIDDLE].into_iter().enumerate() {
+        let (byte, bit) = (btn as usize / 8, 1u8 << (btn % 8));
+        let down = buttons & (1 << i) != 0;
+        if (d.keys[byte] & bit != 0) != down {
+            if down { d.keys[byte] |= bit; } else { d.keys[byte] &= !bit; }
+            events[n] = (EV_KEY, btn, down as i32);
+            n += 1;
+        }
+    }
+    if dx != 0 { events[n] = (EV_REL, REL_X, dx); n += 1; }
+    if dy != 0 { events[n] = (EV_REL, REL_Y, dy); n += 1; }
+    if n == 0 { return; }
+    events[n] = (EV_SYN, SYN_REPORT, 0);
+    unsafe { deliver(MOUSE, &events[..=n]); }
+}
+
+// ── File operations ───────────────────────────────────────────────────────
+
+/// `read(2)`: whole events only; `EAGAIN` if none are queued.
+pub unsafe fn read(n: usize, buf: &mut [u8]) -> i64 {
+    if buf.len() < EVENT_SIZE { return EINVAL; }
+    let c = unsafe { client(n) };
+    if c.len == 0 { return EAGAIN; }
+    let mut count = 0;
+    while count + EVENT_SIZE <= buf.len() {
+        let Some(ev) = c.pop() else { break };
+        let bytes = unsafe {
+            core::slice::from_raw_parts(&ev as *const InputEvent as *const u8, EVENT_SIZE)
+        };
+        buf[count..count + EVENT_SIZE].copy_from_slice(bytes);
+        count += EVENT_SIZE;
+    }
+    count as i64
+}
+
+/// `(readable, writable)` for client `n`.
+pub unsafe fn ready(n: usize) -> (bool, bool) {
+    (unsafe { client(n).len } > 0, false)
+}
+
+/// Called when a read on the current task's `fd` returned `EAGAIN`: unless
+/// it was opened `O_NONBLOCK`, park the task until an event arrives.
+pub unsafe fn block(fd: i32) {
+    if fd < 0 || fd as usize >= MAX_FD { return; }
+    let e = match unsafe { (*(&raw const SCHED)).tasks[CURRENT_TASK_IDX].fd_table.entries[fd as usize] } {
+        Some(e) if e.backend == FdBackend::Evdev => e,
+        _ => return,
+    };
+    if unsafe { client(e.raw_fd as usize).nonblock } { return; }
+    if let Some(ctx) = unsafe { core::ptr::replace(&raw mut CURRENT_SYSCALL_CTX, None) } {
+        unsafe { wait_for_fd(fd, false, ctx); }
+    }
+}
+
+/// Copy `data` to the user buffer `arg` of `size` bytes; returns the
+/// number of bytes copied, as the variable-length evdev ioctls do.
+unsafe fn copy_out(arg: u64, size: usize, data: &[u8]) -> i64 {
+    let len = size.min(data.len());
+    if validate_user_range(arg, len as u64).is_err() { return EFAULT; }
+    unsafe { core::ptr::copy_nonoverlapping(data.as_ptr(), arg as *mut u8, len); }
+    len as i64
+}
+
+/// Bitmap of the event codes of type `ev` that `dev` can produce.
+fn capabilities(dev: usize, ev: u16) -> [u8; KEY_MAX / 8 + 1] {
+    let mut bits = [0u8; KEY_MAX / 8 + 1];
+    let mut set = |code: u16| bits[code as usize / 8] |= 1 << (code % 8);
+    match (dev, ev) {
+        (KEYBOARD, 0) => for t in [EV_SYN, EV_KEY, EV_LED, EV_REP] { set(t) },
+        (MOUSE, 0)    => for t in [EV_SYN, EV_KEY, EV_REL] { set(t) },
+        (KEYBOARD, EV_KEY) => for &(_, code) in KEYMAP { set(code) },
+        (MOUSE, EV_KEY)    => for b in [BTN_LEFT, BTN_RIGHT, BTN_MIDDLE] { set(b) },
+        (MOUSE, EV_REL)    => { set(REL_X); set(REL_Y); }
+        (KEYBOARD, EV_LED) => for l in 0..3 { set(l) },
+        _ => {}
+    }
+    bits
+}
+
+/// evdev ioctls on client `n`.  `arg` is a user pointer (an int for
+/// `EVIOCGRAB` and `EVIOCSCLOCKID`).
+pub unsafe fn ioctl(n: usize, request: u64, arg: u64) -> i64 {
+    let dir  = request >> 30;
+    let size = ((request >> 16) & 0x3fff) as usize;
+    if (request >> 8) & 0xff != b'E' as u64 { return ENOTTY; }
+    let nr  = (request & 0xff) as u8;
+    let dev = unsafe { client(n).dev };
+    let d   = unsafe { device(dev) };
+    match (dir, nr) {
+        (IOC_READ, EVIOCGVERSION) => unsafe { copy_out(arg, 4, &EV_VERSION.to_ne_bytes()).min(0) },
+        (IOC_READ, EVIOCGID) => {
+            let mut id = [0u8; 8];
+            for (i, v) in d.id.iter().enumerate() { id[i * 2..i * 2 + 2].copy_from_slice(&v.to_ne_bytes()); }
+            unsafe { copy_out(arg, 8, &id).min(0) }
+        }
+        (IOC_READ, EVIOCGREP) if dev == KEYBOARD => {
+            let rep = unsafe { *(&raw const REPEAT) };
+            let mut out = [0u8; 8];
+            out[..4].copy_from_slice(&rep[0].to_ne_bytes());
+            out[4..].copy_from_slice(&rep[1].to_ne_bytes());
+            unsafe { copy_out(arg, 8, &out).min(0) }
+        }
+        (IOC_WRITE, EVIOCSREP) if dev == KEYBOARD => {
+            if validate_user_range(arg, 8).is_err() { return EFAULT; }
+            let delay  = unsafe { core::ptr::read_unaligned(arg as *const u32) };
+            let period = unsafe { core::ptr::read_unaligned((arg + 4) as *const u32) };
+            let applied = unsafe { crate::kernel::keyboard::set_typematic(delay, period) };
+            unsafe { *(&raw mut REPEAT) = [applied.0, applied.1]; }
+            0
+        }
+        (IOC_READ, EVIOCGNAME) => {
+            let mut name = [0u8; 64];
+            name[..d.name.len()].copy_from_slice(d.name.as_bytes());
+            unsafe { copy_out(arg, size, &name[..=d.name.len()]) }
+        }
+        (IOC_READ, EVIOCGPHYS) => {
+            let mut phys = [0u8; 64];
+            phys[..d.phys.len()].copy_from_slice(d.phys.as_bytes());
+            unsafe { copy_out(arg, size, &phys[..=d.phys.len()]) }
+        }
+        (IOC_READ, EVIOCGUNIQ) => ENOENT,
+        (IOC_READ, EVIOCGPROP) => {
+            let props = if dev == MOUSE { 1u8 << INPUT_PROP_POINTER } else { 0 };
+            unsafe { copy_out(arg, size, &[props]) }
+        }
+        (IOC_READ, EVIOCGKEY) => { let keys = d.keys; unsafe { copy_out(arg, size, &keys) } }
+        (IOC_READ, EVIOCGLED) => {
+            let leds = if dev == KEYBOARD {
+                let (num, caps, scroll) = crate::kernel::keyboard::leds();
+                num as u8 | (caps as u8) << 1 | (scroll as u8) << 2
+            } else { 0 };
+            unsafe { copy_out(arg, size, &[leds]) }
+        }
+        (IOC_READ, EVIOCGSND | EVIOCGSW) => unsafe { copy_out(arg, size, &[0u8; 8]) },
+        (IOC_READ, nr) if (EVIOCGBIT..=EVIOCGBIT + EV_MAX as u8).contains(&nr) => {
+            let ev = (nr - EVIOCGBIT) as u16;
+            let bits = capabilities(dev, ev);
+            // Bitmap length for the type's *_MAX: EV, KEY, ABS, SW, FF; the
+            // rest fit in two bytes.
+            let len = match ev { 0 => 4, EV_KEY => bits.len(), 0x03 => 8, 0x05 => 3, 0x15 => 16, _ => 2 };
+            unsafe { copy_out(arg, size, &bits[..len]) }
+        }
+        (IOC_WRITE, EVIOCGRAB) => {
+            if arg != 0 {
+                if d.grab.is_some() { return EBUSY; }
+                d.grab = Some(n);
+            } else {
+                if d.grab != Some(n) { return EINVAL; }
+                d.grab = None;
+            }
+            0
+        }
+        // Timestamps come from the tick counter, which serves every clock.
+        (IOC_WRITE, EVIOCSCLOCKID) => {
+            if validate_user_range(arg, 4).is_err() { return EFAULT; }
+            match unsafe { core::ptr::read_unaligned(arg as *const i32) } {
+                0 | 1 | 7 => 0, // REALTIME, MONOTONIC, BOOTTIME
+                _ => EINVAL,
+            }
+        }
+        _ => EINVAL,
+    }
+}
+
+// ── Keycodes ──────────────────────────────────────────────────────────────
+
+/// pc-keyboard key codes → Linux `KEY_*` codes.
+const KEYMAP: &[(KeyCode, u16)] = &[
+    (KeyCode::Escape, 1),
+    (KeyCode::Key1, 2), (KeyCode::Key2, 3), (KeyCode::Key3, 4), (KeyCode::Key4, 5),
+    (KeyCode::Key5, 6), (KeyCode::Key6, 7), (KeyCode::Key7, 8), (KeyCode::Key8, 9),
+    (KeyCode::Key9, 10), (KeyCode::Key0, 11),
+    (KeyCode::OemMinus, 12), (KeyCode::OemPlus, 13), (KeyCode::Backspace, 14), (KeyCode::Tab, 15),
+    (KeyCode::Q, 16), (KeyCode::W, 17), (KeyCode::E, 18), (KeyCode::R, 19), (KeyCode::T, 20),
+    (KeyCode::Y, 21), (KeyCode::U, 22), (KeyCode::I, 23), (KeyCode::O, 24), (KeyCode::P, 25),
+    (KeyCode::Oem4, 26), (KeyCode::Oem6, 27), (KeyCode::Return, 28), (KeyCode::LControl, 29),
+    (KeyCode::A, 30), (KeyCode::S, 31), (KeyCode::D, 32), (KeyCode::F, 33), (KeyCode::G, 34),
+    (KeyCode::H, 35), (KeyCode::J, 36), (KeyCode::K, 37), (KeyCode::L, 38),
+    (KeyCode::Oem1, 39), (KeyCode::Oem3, 40), (KeyCode::Oem8, 41), (KeyCode::LShift, 42),
+    (KeyCode::Oem7, 43),
+    (KeyCode::Z, 44), (KeyCode::X, 45), (KeyCode::C, 46), (KeyCode::V, 47), (KeyCode::B, 48),
+    (KeyCode::N, 49), (KeyCode::M, 50),
+    (KeyCode::OemComma, 51), (KeyCode::OemPeriod, 52), (KeyCode::Oem2, 53), (KeyCode::RShift, 54),
+    (KeyCode::NumpadMultiply, 55), (KeyCode::LAlt, 56), (KeyCode::Spacebar, 57), (KeyCode::CapsLock, 58),
+    (KeyCode::F1, 59), (KeyCode::F2, 60), (KeyCode::F3, 61), (KeyCode::F4, 62), (KeyCode::F5, 63),
+    (KeyCode::F6, 64), (KeyCode::F7, 65), (KeyCode::F8, 66), (KeyCode::F9, 67), (KeyCode::F10, 68),
+    (KeyCode::NumpadLock, 69), (KeyCode::ScrollLock, 70),
+    (KeyCode::Numpad7, 71), (KeyCode::Numpad8, 72), (KeyCode::Numpad9, 73), (KeyCode::NumpadSubtract, 74),
+    (KeyCode::Numpad4, 75), (KeyCode::Numpad5, 76), (KeyCode::Numpad6, 77), (KeyCode::NumpadAdd, 78),
+    (KeyCode::Numpad1, 79), (KeyCode::Numpad2, 80), (KeyCode::Numpad3, 81),
+    (KeyCode::Numpad0, 82), (KeyCode::NumpadPeriod, 83),
+    (KeyCode::Oem5, 86), (KeyCode::F11, 87), (KeyCode::F12, 88),
+    (KeyCode::Oem12, 89), (KeyCode::Oem10, 92), (KeyCode::Oem11, 93), (KeyCode::Oem9, 94),
+    (KeyCode::NumpadEnter, 96), (KeyCode::RControl, 97), (KeyCode::NumpadDivide, 98),
+    (KeyCode::PrintScreen, 99), (KeyCode::SysRq, 99), (KeyCode::RAltGr, 100),
+    (KeyCode::Home, 102), (KeyCode::ArrowUp, 103), (KeyCode::PageUp, 104),
+    (KeyCode::ArrowLeft, 105), (KeyCode::ArrowRight, 106), (KeyCode::End, 107),
+    (KeyCode::ArrowDown, 108), (KeyCode::PageDown, 109), (KeyCode::Insert, 110), (KeyCode::Delete, 111),
+    (KeyCode::Mute, 113), (KeyCode::VolumeDown, 114), (KeyCode::VolumeUp, 115),
+    (KeyCode::PauseBreak, 119), (KeyCode::Oem13, 124),
+    (KeyCode::LWin, 125), (KeyCode::RWin, 126), (KeyCode::Apps, 127),
+    (KeyCode::Calculator, 140), (KeyCode::NextTrack, 163), (KeyCode::Play, 164),
+    (KeyCode::PrevTrack, 165), (KeyCode::Stop, 166), (KeyCode::WWWHome, 172),
+];
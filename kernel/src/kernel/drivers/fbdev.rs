@@ -0,0 +1,218 @@
+//! `/dev/fb0` — the Limine framebuffer as a Linux fbdev device.
+//!
+//! The bootloader hands over a single linear framebuffer at a fixed mode;
+//! this driver exposes it unchanged.  `FBIOGET_VSCREENINFO` and
+//! `FBIOGET_FSCREENINFO` describe it, `FBIOPUT_VSCREENINFO` only accepts
+//! the current geometry (there is no mode setting), and `mmap` maps the
+//! physical pixels straight into the caller.  `read`/`write` at the file
+//! offset go through the kernel's write-combining window.
+//!
+//! The GUI compositor draws to the same memory, so a program that takes the
+//! framebuffer over while the desktop is running competes with it — as on
+//! Linux with a display server up.
+
+use crate::kernel::fs::devfs::{self, DevNode};
+use crate::kernel::fs::ramfs::FdBackend;
+
+pub const FBIOGET_VSCREENINFO: u64 = 0x4600;
+pub const FBIOPUT_VSCREENINFO: u64 = 0x4601;
+pub const FBIOGET_FSCREENINFO: u64 = 0x4602;
+pub const FBIOPAN_DISPLAY:     u64 = 0x4606;
+pub const FBIOBLANK:           u64 = 0x4611;
+
+const FB_TYPE_PACKED_PIXELS: u32 = 0;
+const FB_VISUAL_TRUECOLOR:   u32 = 2;
+
+const EINVAL: i64 = -22;
+const ENOTTY: i64 = -25;
+const EFAULT: i64 = -14;
+const ENOSPC: i64 = -28;
+
+const PAGE_SIZE: u64 = 4096;
+
+/// `struct fb_bitfield`.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct FbBitfield { pub offset: u32, pub length: u32, pub msb_right: u32 }
+
+/// `struct fb_var_screeninfo` (160 bytes).
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct FbVarScreeninfo {
+    pub xres: u32, pub yres: u32,
+    pub xres_virtual: u32, pub yres_virtual: u32,
+    pub xoffset: u32, pub yoffset: u32,
+    pub bits_per_pixel: u32, pub grayscale: u32,
+    pub red: FbBitfield, pub green: FbBitfield, pub blue: FbBitfield, pub transp: FbBitfield,
+    pub nonstd: u32, pub activate: u32,
+    /// Physical size in millimetres; unknown (`u32::MAX`).
+    pub height: u32, pub width: u32,
+    pub accel_flags: u32,
+    pub pixclock: u32,
+    pub left_margin: u32, pub right_margin: u32,
+    pub upper_margin: u32, pub lower_margin: u32,
+    pub hsync_len: u32, pub vsync_len: u32,
+    pub sync: u32, pub vmode: u32, pub rotate: u32, pub colorspace: u32,
+    pub reserved: [u32; 4],
+}
+
+/// `struct fb_fix_screeninfo` (80 bytes on x86_64).
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct FbFixScreeninfo {
+    pub id: [u8; 16],
+    pub smem_start: u64,
+    pub smem_len: u32,
+    pub type_: u32,
+    pub type_aux: u32,
+    pub visual: u32,
+    pub xpanstep: u16, pub ypanstep: u16, pub ywrapstep: u16,
+    pub line_length: u32,
+    pub mmio_start: u64,
+    pub mmio_len: u32,
+    pub accel: u32,
+    pub capabilities: u16,
+    pub reserved: [u16; 2],
+}
+
+/// The framebuffer as the bootloader set it up.
+#[derive(Clone, Copy)]
+struct Fb {
+    /// Kernel address of the first pixel.
+    virt:   u64,
+    /// Physical address of the first pixel.
+    phys:   u64,
+    width:  u32,
+    height: u32,
+    /// Bytes per scanline.
+    pitch:  u32,
+    bpp:    u32,
+    /// (shift, size) of the red, green and blue channels.
+    masks:  [(u8, u8); 3],
+}
+
+impl Fb {
+    fn len(&self) -> u64 { self.pitch as u64 * self.height as u64 }
+}
+
+static mut FB: Option<Fb> = None;
+
+/// Record the framebuffer and add `/dev/fb0`.  `virt` is where the kernel
+/// maps it (see `remap_framebuffer`); `fb` still carries the HHDM address
+/// the physical one is derived from.
+pub fn register(fb: &limine::framebuffer::Framebuffer, virt: u64) {
+    let phys = (fb.addr() as u64).wrapping_sub(crate::kernel::paging_allocator::get_hhdm_offset());
+    unsafe {
+        FB = Some(Fb {
+            virt,
+            phys,
+            width:  fb.width() as u32,
+            height: fb.height() as u32,
+            pitch:  fb.pitch() as u32,
+            bpp:    fb.bpp() as u32,
+            masks:  [
+                (fb.red_mask_shift(),   fb.red_mask_size()),
+                (fb.green_mask_shift(), fb.green_mask_size()),
+                (fb.blue_mask_shift(),  fb.blue_mask_size()),
+            ],
+        });
+    }
+    devfs::register(DevNode {
+        name: "fb0", backend: FdBackend::Fb, minor: 0,
+        rdev: (29, 0), block: false, mode: 0o660, uid: 0, gid: devfs::GID_VIDEO,
+    });
+}
+
+fn fb() -> Option<Fb> { unsafe { *(&raw const FB) } }
+
+/// Size of the framebuffer in bytes (0 if there is none).
+pub fn size() -> u64 { fb().map_or(0, |f| f.len()) }
+
+fn var_info(f: &Fb) -> FbVarScreeninfo {
+    let field = |(shift, size): (u8, u8)| FbBitfield { offset: shift as u32, length: size as u32, msb_right: 0 };
+    FbVarScreeninfo {
+        xres: f.width, yres: f.height,
+        xres_virtual: f.width, yres_virtual: f.height,
+        bits_per_pixel: f.bpp,
+        red: field(f.masks[0]), green: field(f.masks[1]), blue: field(f.masks[2]),
+        height: u32::MAX, width: u32::MAX,
+        ..FbVarScreeninfo::default()
+    }
+}
+
+fn fix_info(f: &Fb) -> FbFixScreeninfo {
+    let mut id = [0u8; 16];
+    id[..6].copy_from_slice(b"limine");
+    FbFixScreeninfo {
+        id,
+        smem_start: f.phys,
+        smem_len: f.len() as u32,
+        type_: FB_TYPE_PACKED_PIXELS,
+        visual: FB_VISUAL_TRUECOLOR,
+        line_length: f.pitch,
+        ..FbFixScreeninfo::default()
+    }
+}
+
+/// fbdev ioctls.  `arg` is a user pointer.
+pub unsafe fn ioctl(request: u64, arg: u64) -> i64 {
+    use crate::kernel::syscall_core::validate_user_range;
+    let Some(f) = fb() else { return -19 }; // ENODEV
+    match request {
+        FBIOGET_VSCREENINFO => {
+            if validate_user_range(arg, size_of::<FbVarScreeninfo>() as u64).is_err() { return EFAULT; }
+            unsafe { core::ptr::write_unaligned(arg as *mut FbVarScreeninfo, var_info(&f)); }
+            0
+        }
+        FBIOGET_FSCREENINFO => {
+            if validate_user_range(arg, size_of::<FbFixScreeninfo>() as u64).is_err() { return EFAULT; }
+            unsafe { core::ptr::write_unaligned(arg as *mut FbFixScreeninfo, fix_info(&f)); }
+            0
+        }
+        // No mode setting: the current mode is the only one accepted.
+        FBIOPUT_VSCREENINFO | FBIOPAN_DISPLAY => {
+            if validate_user_range(arg, size_of::<FbVarScreeninfo>() as u64).is_err() { return EFAULT; }
+            let want = unsafe { core::ptr::read_unaligned(arg as *const FbVarScreeninfo) };
+            let ok = want.xoffset == 0 && want.yoffset == 0
+                && (request == FBIOPAN_DISPLAY
+                    || (want.xres == f.width && want.yres == f.height
+                        && want.xres_virtual <= f.width && want.yres_virtual <= f.height
+                        && (want.bits_per_pixel == 0 || want.bits_per_pixel == f.bpp)));
+            if !ok { return EINVAL; }
+            if request == FBIOPUT_VSCREENINFO {
+                unsafe { core::ptr::write_unaligned(arg as *mut FbVarScreeninfo, var_info(&f)); }
+            }
+            0
+        }
+        FBIOBLANK => 0,
+        _ => ENOTTY,
+    }
+}
+
+/// Read framebuffer bytes at `offset`.
+pub unsafe fn read(offset: u64, buf: &mut [u8]) -> i64 {
+    let Some(f) = fb() else { return -19 };
+    if offset >= f.len() { return 0; }
+    let n = (f.len() - offset).min(buf.len() as u64) as usize;
+    unsafe { core::ptr::copy_nonoverlapping((f.virt + offset) as *const u8, buf.as_mut_ptr(), n); }
+    n as i64
+}
+
+/// Write framebuffer bytes at `offset`; ENOSPC at the end, like Linux.
+pub unsafe fn write(offset: u64, buf: &[u8]) -> i64 {
+    let Some(f) = fb() else { return -19 };
+    if offset >= f.len() { return if buf.is_empty() { 0 } else { ENOSPC }; }
+    let n = (f.len() - offset).min(buf.len() as u64) as usize;
+    unsafe { core::ptr::copy_nonoverlapping(buf.as_ptr(), (f.virt + offset) as *mut u8, n); }
+    n as i64
+}
+
+/// Physical address backing `pages` pages of a mapping at `offset`, or
+/// `None` if they would run past the (page-rounded) framebuffer.
+pub fn mmap_phys(offset: u64, pages: u64) -> Option<u64> {
+    let f = fb()?;
+    if f.phys & (PAGE_SIZE - 1) != 0 { return None; }
+    let limit = f.len().div_ceil(PAGE_SIZE) * PAGE_SIZE;
+    let end = pages.checked_mul(PAGE_SIZE).and_then(|len| offset.checked_add(len))?;
+    (end <= limit).then(|| f.phys + offset)
+}
@@ -5,17 +5,24 @@
 use core::arch::asm;
 use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 use crate::kernel::serial::SERIAL_PORT;
-use pc_keyboard::{layouts, DecodedKey, HandleControl, Keyboard, KeyCode, KeyState, ScancodeSet1};
+use crate::kernel::keymap::{Composer, Layout, Output};
+use pc_keyboard::{layouts, DecodedKey, HandleControl, Keyboard, KeyCode, KeyEvent, KeyState, ScancodeSet1};
 
 const KEYBOARD_DEBUG_LOGGING: bool = false;
 
 // ============================================================================
-// PC-KEYBOARD DECODER  (scancode-set-1, US QWERTY)
+// PC-KEYBOARD DECODER  (scancode-set-1, layout chosen through `keymap`)
 // ============================================================================
 
-/// The pc-keyboard state machine.  Initialised in `init()`.
-/// `const fn new` means we can store it as a static directly.
-static mut KB: Option<Keyboard<layouts::Us104Key, ScancodeSet1>> = None;
+/// The pc-keyboard state machine.  Initialised in `init()`, rebuilt by
+/// `set_layout()`.
+static mut KB: Option<Keyboard<layouts::AnyLayout, ScancodeSet1>> = None;
+
+/// Layout `KB` decodes with.
+static mut LAYOUT: Layout = Layout::Us;
+
+/// Dead-key / Compose state between keystrokes.
+static mut COMPOSER: Composer = Composer::new();
 
 /// Modifier state tracked independently (pc-keyboard 0.7 has no public accessor).
 #[derive(Clone, Copy, Default)]
@@ -63,7 +70,26 @@ static CALLBACK_ENABLED:       AtomicBool               = AtomicBool::new(false)
 static ARROW_CALLBACK_ENABLED: AtomicBool               = AtomicBool::new(false);
 
 /// Secondary callback for GUI proc key routing (set by `gui_proc::init`).
-static mut GUI_KEY_CALLBACK: Option<unsafe fn(u8)> = None;
+/// Receives whole characters; `KEY_CALLBACK` gets their UTF-8 bytes.
+static mut GUI_KEY_CALLBACK: Option<unsafe fn(char)> = None;
+
+/// Text-console mode: input goes to the active virtual console instead of
+/// the stdin ring, and Alt+F1..F6 switches consoles.
+static CONSOLE_MODE: AtomicBool = AtomicBool::new(false);
+
+/// Route keyboard input to the virtual consoles (`vt::enable`).
+pub fn route_to_vt(on: bool) {
+    CONSOLE_MODE.store(on, Ordering::Relaxed);
+}
+
+/// Deliver one input byte to whoever reads the keyboard.
+unsafe fn emit(byte: u8) {
+    if CONSOLE_MODE.load(Ordering::Relaxed) {
+        unsafe { crate::kernel::vt::key(byte); }
+    } else {
+        crate::kernel::stdin::push(byte);
+    }
+}
 
 pub unsafe fn register_key_callback(callback: KeyCallback) {
     unsafe { KEY_CALLBACK = Some(callback); }
@@ -77,7 +103,7 @@ pub unsafe fn register_arrow_key_callback(callback: ArrowKeyCallback) {
 
 /// Register a secondary key callback that is always called alongside `KEY_CALLBACK`.
 /// Used by `gui_proc` to buffer chars for GUI window event routing.
-pub unsafe fn register_gui_key_callback(callback: unsafe fn(u8)) {
+pub unsafe fn register_gui_key_callback(callback: unsafe fn(char)) {
     unsafe { GUI_KEY_CALLBACK = Some(callback); }
 }
 
@@ -148,12 +174,31 @@ unsafe fn process_scancode(scancode: u8) {
 
         match kb.add_byte(scancode) {
             Ok(Some(key_event)) => {
+                let code = key_event.code;
+                // Raw transitions go to /dev/input/event0 first.  Keys
+                // without a break code (Pause) are pressed and released.
+                crate::kernel::evdev::keyboard(key_event.code, key_event.state != KeyState::Up);
+                if key_event.state == KeyState::SingleShot {
+                    crate::kernel::evdev::keyboard(key_event.code, false);
+                }
+
                 // Update our local modifier tracking from the raw event.
                 update_modifiers(key_event.code, key_event.state);
 
-                if let Some(decoded) = kb.process_keyevent(key_event) {
+                // The decoder still tracks modifiers while an evdev client
+                // holds the keyboard, but nothing reaches the console.
+                let decoded = kb.process_keyevent(key_event);
+                if crate::kernel::evdev::grabbed(crate::kernel::evdev::KEYBOARD) { return; }
+                if let Some(decoded) = decoded {
                     match decoded {
-                        DecodedKey::Unicode(c) => dispatch_unicode(c),
+                        DecodedKey::Unicode(c) => {
+                            let layout = *core::ptr::addr_of!(LAYOUT);
+                            match (*core::ptr::addr_of_mut!(COMPOSER)).feed(layout, code, c) {
+                                Output::None      => {}
+                                Output::One(c)    => dispatch_unicode(c),
+                                Output::Two(a, b) => { dispatch_unicode(a); dispatch_unicode(b); }
+                            }
+                        }
                         DecodedKey::RawKey(kc) => dispatch_raw_key(kc),
                     }
                 }
@@ -195,12 +240,19 @@ unsafe fn update_leds(m: &ModState) {
     let prev = LAST_LED.load(Ordering::Relaxed);
     if led != prev {
         LAST_LED.store(led, Ordering::Relaxed);
-        unsafe { send_led_command(led); }
+        unsafe {
+            send_led_command(led);
+            crate::kernel::evdev::leds(m.num, m.caps, m.scroll);
+        }
     }
 }
 
 /// Handle a decoded Unicode character.
 unsafe fn dispatch_unicode(c: char) {
+    if !c.is_ascii() {
+        unsafe { dispatch_utf8(c); }
+        return;
+    }
     let byte: u8 = match c as u32 {
         0x08 => 8,              // Backspace
         0x09 => b'\t',          // Tab
@@ -209,7 +261,6 @@ unsafe fn dispatch_unicode(c: char) {
         n @ 0x01..=0x1F => n as u8,
         // Printable ASCII
         n @ 0x20..=0x7E => n as u8,
-        // Drop anything outside ASCII for now
         _ => return,
     };
 
@@ -222,7 +273,7 @@ unsafe fn dispatch_unicode(c: char) {
     }
 
     unsafe {
-        crate::kernel::stdin::push(byte);
+        emit(byte);
 
         if CALLBACK_ENABLED.load(Ordering::Relaxed) {
             if let Some(cb) = KEY_CALLBACK {
@@ -231,7 +282,28 @@ unsafe fn dispatch_unicode(c: char) {
         }
         // Secondary GUI-proc routing callback
         if let Some(gui_cb) = GUI_KEY_CALLBACK {
-            gui_cb(byte);
+            gui_cb(byte as char);
+        }
+    }
+}
+
+/// Deliver a non-ASCII character: the tty and the key callback get its
+/// UTF-8 encoding, GUI windows the character itself.
+unsafe fn dispatch_utf8(c: char) {
+    let mut buf = [0u8; 4];
+    for &byte in c.encode_utf8(&mut buf).as_bytes() {
+        unsafe {
+            emit(byte);
+            if CALLBACK_ENABLED.load(Ordering::Relaxed) {
+                if let Some(cb) = KEY_CALLBACK {
+                    cb(byte);
+                }
+            }
+        }
+    }
+    unsafe {
+        if let Some(gui_cb) = GUI_KEY_CALLBACK {
+            gui_cb(c);
         }
     }
 }
@@ -242,6 +314,30 @@ unsafe fn dispatch_raw_key(kc: KeyCode) {
         unsafe { SERIAL_PORT.write_str("[RAWKEY]\n"); }
     }
 
+    // Menu is the Compose key; any other non-modifier key abandons a
+    // half-typed dead-key or Compose sequence.
+    let composer = unsafe { &mut *core::ptr::addr_of_mut!(COMPOSER) };
+    match kc {
+        KeyCode::Apps => { composer.start_compose(); return; }
+        KeyCode::LShift | KeyCode::RShift | KeyCode::LControl | KeyCode::RControl
+        | KeyCode::RControl2 | KeyCode::LAlt | KeyCode::RAltGr
+        | KeyCode::CapsLock | KeyCode::NumpadLock => {}
+        _ => composer.cancel(),
+    }
+
+    // Alt+F1..F6 → switch virtual console
+    if CONSOLE_MODE.load(Ordering::Relaxed) && unsafe { is_alt_pressed() } {
+        let vt = match kc {
+            KeyCode::F1 => Some(0), KeyCode::F2 => Some(1), KeyCode::F3 => Some(2),
+            KeyCode::F4 => Some(3), KeyCode::F5 => Some(4), KeyCode::F6 => Some(5),
+            _ => None,
+        };
+        if let Some(vt) = vt {
+            crate::kernel::vt::switch(vt);
+            return;
+        }
+    }
+
     // Some raw keys have ASCII equivalents
     let ascii: Option<u8> = match kc {
         KeyCode::Backspace => Some(8),
@@ -253,7 +349,7 @@ unsafe fn dispatch_raw_key(kc: KeyCode) {
 
     if let Some(byte) = ascii {
         unsafe {
-            crate::kernel::stdin::push(byte);
+            emit(byte);
             if CALLBACK_ENABLED.load(Ordering::Relaxed) {
                 if let Some(cb) = KEY_CALLBACK {
                     cb(byte);
@@ -280,7 +376,7 @@ unsafe fn dispatch_raw_key(kc: KeyCode) {
     };
     if let Some(seq) = vt100 {
         for &byte in seq {
-            crate::kernel::stdin::push(byte);
+            unsafe { emit(byte); }
         }
     }
 
@@ -324,6 +420,59 @@ pub unsafe fn is_caps_lock_on() -> bool {
     unsafe { (*core::ptr::addr_of!(MODS)).caps }
 }
 
+/// Current (num, caps, scroll) lock LEDs.
+pub fn leds() -> (bool, bool, bool) {
+    let led = LAST_LED.load(Ordering::Relaxed);
+    (led & 0x02 != 0, led & 0x04 != 0, led & 0x01 != 0)
+}
+
+/// Layout the decoder currently uses.
+pub fn layout() -> Layout {
+    unsafe { *core::ptr::addr_of!(LAYOUT) }
+}
+
+/// Switch the decoder to `layout`.  Shift/Ctrl/AltGr held at the time and
+/// the Caps/Num Lock states carry over; a pending dead key is dropped.
+/// Use `keymap::load` so the tunable and the saved choice follow.
+pub unsafe fn set_layout(layout: Layout) {
+    unsafe {
+        // The IRQ1 handler owns KB; keep it out while the decoder is rebuilt.
+        let flags: u64;
+        asm!("pushfq; pop {}; cli", out(reg) flags);
+
+        let mut kb = Keyboard::new(ScancodeSet1::new(), layout.decoder(), HandleControl::MapLettersToUnicode);
+        let m = *core::ptr::addr_of!(MODS);
+        for (held, code) in [(m.lshift, KeyCode::LShift), (m.rshift, KeyCode::RShift),
+                             (m.lctrl, KeyCode::LControl), (m.rctrl, KeyCode::RControl),
+                             (m.ralt, KeyCode::RAltGr), (m.caps, KeyCode::CapsLock),
+                             (!m.num, KeyCode::NumpadLock)] {
+            if held { kb.process_keyevent(KeyEvent::new(code, KeyState::Down)); }
+        }
+        *core::ptr::addr_of_mut!(KB) = Some(kb);
+        *core::ptr::addr_of_mut!(LAYOUT) = layout;
+        (*core::ptr::addr_of_mut!(COMPOSER)).cancel();
+
+        if flags & 0x200 != 0 { asm!("sti"); }
+    }
+}
+
+/// Program the typematic delay and repeat period (milliseconds) with the
+/// nearest rates the keyboard supports, which are returned.
+pub unsafe fn set_typematic(delay_ms: u32, period_ms: u32) -> (u32, u32) {
+    // Delay: 250 ms steps from 250 to 1000.  Period: (8 + A) * 2^B * 4.17 ms
+    // for the low three bits A and the next two B.
+    let delay = (delay_ms.clamp(250, 1000) + 125) / 250 - 1;
+    let period = |rate: u32| (8 + (rate & 7)) * (1 << ((rate >> 3) & 3)) * 417 / 100;
+    let rate = (0..32u32).min_by_key(|&r| period(r).abs_diff(period_ms)).unwrap_or(0);
+    unsafe {
+        ctrl_data(0xF3);
+        ctrl_read_fast(); // consume ACK
+        ctrl_data(((delay << 5) | rate) as u8);
+        ctrl_read_fast(); // consume ACK
+    }
+    ((delay + 1) * 250, period(rate))
+}
+
 // ============================================================================
 // 8042 CONTROLLER HELPERS
 // ============================================================================
@@ -409,11 +558,12 @@ pub unsafe fn init() {
     unsafe {
         SERIAL_PORT.write_str("Initializing keyboard driver (pc-keyboard + 8042)...\n");
 
-        // Set up the pc-keyboard decoder (scancode set 1, US layout).
+        // Set up the pc-keyboard decoder (scancode set 1, US layout until
+        // `keymap::restore` loads the saved one).
         // HandleControl::MapLettersToUnicode: Ctrl+C → '\x03', Ctrl+D → '\x04', etc.
         *core::ptr::addr_of_mut!(KB) = Some(Keyboard::new(
             ScancodeSet1::new(),
-            layouts::Us104Key,
+            Layout::Us.decoder(),
             HandleControl::MapLettersToUnicode,
         ));
 
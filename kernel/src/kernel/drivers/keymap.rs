@@ -0,0 +1,259 @@
+//! Keyboard layouts, dead keys and Compose sequences.
+//!
+//! The PS/2 driver decodes scancodes with whichever [`Layout`] is active and
+//! hands every character to its [`Composer`], which folds dead-key and
+//! Compose (the Menu key) sequences into single characters.
+//!
+//! The layout is selected with the `loadkeys` syscall, by writing its name
+//! to `/proc/sys/kernel/keymap`, or from the Quick Settings panel, and is
+//! kept across boots as a record in the disk store.
+
+use pc_keyboard::{layouts, KeyCode};
+
+/// Writable tunable holding the active layout name.
+pub const KEYMAP_PATH: &str = "/proc/sys/kernel/keymap";
+
+/// Disk-store record (disk 0) with the saved layout name — "KMAP", well
+/// clear of the small ids handed out by the shell's `store` command.
+const RECORD_ID: u32 = 0x4B4D_4150;
+
+// ── Layouts ────────────────────────────────────────────────────────────────
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Layout { Us, Uk, De, Fr, Dvorak, Colemak }
+
+/// Every selectable layout, in the order the settings panel shows them.
+pub const LAYOUTS: [Layout; 6] =
+    [Layout::Us, Layout::Uk, Layout::De, Layout::Fr, Layout::Dvorak, Layout::Colemak];
+
+impl Layout {
+    pub const fn name(self) -> &'static str {
+        match self {
+            Layout::Us      => "us",
+            Layout::Uk      => "uk",
+            Layout::De      => "de",
+            Layout::Fr      => "fr",
+            Layout::Dvorak  => "dvorak",
+            Layout::Colemak => "colemak",
+        }
+    }
+
+    /// Short human-readable name for settings screens.
+    pub const fn label(self) -> &'static str {
+        match self {
+            Layout::Us      => "US",
+            Layout::Uk      => "UK",
+            Layout::De      => "German",
+            Layout::Fr      => "French",
+            Layout::Dvorak  => "Dvorak",
+            Layout::Colemak => "Colemak",
+        }
+    }
+
+    /// Look a layout up by name; `gb`, `azerty` and `qwertz` are accepted
+    /// as the names the console-setup tools use.
+    pub fn by_name(name: &str) -> Option<Layout> {
+        match name.trim() {
+            "us"            => Some(Layout::Us),
+            "uk" | "gb"     => Some(Layout::Uk),
+            "de" | "qwertz" => Some(Layout::De),
+            "fr" | "azerty" => Some(Layout::Fr),
+            "dvorak"        => Some(Layout::Dvorak),
+            "colemak"       => Some(Layout::Colemak),
+            _               => None,
+        }
+    }
+
+    /// The pc-keyboard table that decodes this layout.
+    pub fn decoder(self) -> layouts::AnyLayout {
+        match self {
+            Layout::Us      => layouts::AnyLayout::Us104Key(layouts::Us104Key),
+            Layout::Uk      => layouts::AnyLayout::Uk105Key(layouts::Uk105Key),
+            Layout::De      => layouts::AnyLayout::De105Key(layouts::De105Key),
+            Layout::Fr      => layouts::AnyLayout::Azerty(layouts::Azerty),
+            Layout::Dvorak  => layouts::AnyLayout::Dvorak104Key(layouts::Dvorak104Key),
+            Layout::Colemak => layouts::AnyLayout::Colemak(layouts::Colemak),
+        }
+    }
+
+    /// The accent `ch` stands for if `code` is a dead key on this layout.
+    /// The same characters on AltGr (e.g. `^` on AZERTY AltGr+9) stay live.
+    fn dead(self, code: KeyCode, ch: char) -> Option<char> {
+        match (self, code, ch) {
+            (Layout::De, KeyCode::Oem8,    '^')
+          | (Layout::De, KeyCode::OemPlus, '´' | '`')
+          | (Layout::Fr, KeyCode::Oem4,    '^' | '¨') => Some(ch),
+            _ => None,
+        }
+    }
+}
+
+// ── Composition tables ─────────────────────────────────────────────────────
+
+/// (accent, base letters, composed letters) — position i of the second
+/// string composes to position i of the third.
+const ACCENTS: &[(char, &str, &str)] = &[
+    ('`', "aeiouAEIOU",   "àèìòùÀÈÌÒÙ"),
+    ('´', "aeiouyAEIOUY", "áéíóúýÁÉÍÓÚÝ"),
+    ('^', "aeiouAEIOU",   "âêîôûÂÊÎÔÛ"),
+    ('¨', "aeiouyAEIOU",  "äëïöüÿÄËÏÖÜ"),
+    ('~', "anoANO",       "ãñõÃÑÕ"),
+    ('¸', "cC",           "çÇ"),
+];
+
+/// Compose pairs that are not an accent on a letter.  Either order works.
+const SPECIALS: &[(char, char, char)] = &[
+    ('s', 's', 'ß'), ('a', 'e', 'æ'), ('A', 'E', 'Æ'), ('o', 'e', 'œ'), ('O', 'E', 'Œ'),
+    ('o', '/', 'ø'), ('O', '/', 'Ø'), ('o', 'a', 'å'), ('O', 'A', 'Å'),
+    ('E', '=', '€'), ('L', '-', '£'), ('Y', '=', '¥'), ('c', '/', '¢'), ('s', 'o', '§'),
+    ('!', '!', '¡'), ('?', '?', '¿'), ('<', '<', '«'), ('>', '>', '»'),
+    ('1', '2', '½'), ('1', '4', '¼'), ('3', '4', '¾'), ('o', 'o', '°'),
+    ('+', '-', '±'), ('x', 'x', '×'), (':', '-', '÷'), ('m', 'u', 'µ'),
+    ('c', 'o', '©'), ('r', 'o', '®'), ('t', 'm', '™'),
+];
+
+/// `base` with `accent` on it, if that letter exists.
+fn accented(accent: char, base: char) -> Option<char> {
+    let &(_, bases, results) = ACCENTS.iter().find(|(a, _, _)| *a == accent)?;
+    let i = bases.chars().position(|c| c == base)?;
+    results.chars().nth(i)
+}
+
+/// The accent an ASCII (or spacing) character introduces after Compose.
+fn accent_of(c: char) -> Option<char> {
+    match c {
+        '`'        => Some('`'),
+        '\'' | '´' => Some('´'),
+        '^'        => Some('^'),
+        '"' | '¨'  => Some('¨'),
+        '~'        => Some('~'),
+        ',' | '¸'  => Some('¸'),
+        _          => None,
+    }
+}
+
+/// Result of Compose `a` `b`.
+fn compose(a: char, b: char) -> Option<char> {
+    accent_of(a).and_then(|acc| accented(acc, b))
+        .or_else(|| accent_of(b).and_then(|acc| accented(acc, a)))
+        .or_else(|| SPECIALS.iter()
+            .find(|&&(x, y, _)| (x, y) == (a, b) || (y, x) == (a, b))
+            .map(|&(_, _, r)| r))
+}
+
+// ── Composer ───────────────────────────────────────────────────────────────
+
+#[derive(Clone, Copy)]
+enum Pending {
+    None,
+    /// A dead key carrying this accent was pressed.
+    Dead(char),
+    /// Compose was pressed; waiting for the first character.
+    Compose,
+    /// Compose and one character; waiting for the second.
+    Compose2(char),
+}
+
+/// What a keystroke produces once dead keys and Compose are resolved.
+pub enum Output {
+    None,
+    One(char),
+    Two(char, char),
+}
+
+pub struct Composer {
+    pending: Pending,
+}
+
+impl Composer {
+    pub const fn new() -> Self { Self { pending: Pending::None } }
+
+    /// The Compose key was pressed.
+    pub fn start_compose(&mut self) { self.pending = Pending::Compose; }
+
+    /// Forget a half-typed sequence (layout switch, navigation keys).
+    pub fn cancel(&mut self) { self.pending = Pending::None; }
+
+    /// Feed the character `ch` decoded from `code` on `layout`.
+    pub fn feed(&mut self, layout: Layout, code: KeyCode, ch: char) -> Output {
+        let control = (ch as u32) < 0x20 || ch == '\x7F';
+        match core::mem::replace(&mut self.pending, Pending::None) {
+            Pending::None => match layout.dead(code, ch) {
+                Some(acc) => { self.pending = Pending::Dead(acc); Output::None }
+                None      => Output::One(ch),
+            },
+            // Backspace and Escape abandon the accent; other control
+            // characters (Enter, Tab, ^C, ...) still go through.
+            Pending::Dead(_) if ch == '\x08' || ch == '\x1B' => Output::None,
+            Pending::Dead(_) if control => Output::One(ch),
+            // Space, or the same dead key twice, gives the accent itself.
+            Pending::Dead(acc) if ch == ' ' => Output::One(acc),
+            Pending::Dead(acc) => match layout.dead(code, ch) {
+                Some(next) if next == acc => Output::One(acc),
+                Some(next) => { self.pending = Pending::Dead(next); Output::One(acc) }
+                None => match accented(acc, ch) {
+                    Some(c) => Output::One(c),
+                    None    => Output::Two(acc, ch),
+                },
+            },
+            Pending::Compose | Pending::Compose2(_) if control => Output::None,
+            Pending::Compose => { self.pending = Pending::Compose2(ch); Output::None }
+            // Unknown sequences are dropped, as X does.
+            Pending::Compose2(first) => match compose(first, ch) {
+                Some(c) => Output::One(c),
+                None    => Output::None,
+            },
+        }
+    }
+}
+
+// ── Selection, tunable and persistence ─────────────────────────────────────
+
+/// Make `name` the active layout, reflect it in the tunable and save it.
+pub unsafe fn load(name: &str) -> i64 {
+    match Layout::by_name(name) {
+        Some(layout) => { unsafe { apply(layout, true); } 0 }
+        None         => -22, // EINVAL
+    }
+}
+
+unsafe fn apply(layout: Layout, save: bool) {
+    unsafe { crate::kernel::keyboard::set_layout(layout); }
+    unsafe { write_tunable(layout); }
+    if save && crate::kernel::disk_store::is_mounted(0) {
+        crate::kernel::diskfs::write_record(RECORD_ID, layout.name().as_bytes());
+    }
+}
+
+unsafe fn write_tunable(layout: Layout) {
+    if let Some(fs) = unsafe { crate::kernel::fs::ramfs::RAMFS.get() } {
+        let _ = fs.write_file(KEYMAP_PATH, layout.name().as_bytes());
+        let _ = fs.append_file(KEYMAP_PATH, b"\n");
+    }
+}
+
+/// Pick up a layout written to the tunable.  Called by `procfs::written`;
+/// a name that is not a layout is replaced by the active one.
+pub unsafe fn reload() {
+    let Some(fs) = (unsafe { crate::kernel::fs::ramfs::RAMFS.get() }) else { return };
+    let current = crate::kernel::keyboard::layout();
+    let wanted = fs.read_file(KEYMAP_PATH)
+        .and_then(|text| core::str::from_utf8(text).ok())
+        .map(Layout::by_name);
+    match wanted {
+        Some(Some(layout)) if layout == current => {}
+        Some(Some(layout)) => unsafe { apply(layout, true) },
+        _                  => unsafe { write_tunable(current) },
+    }
+}
+
+/// Boot: create the tunable and switch to the layout saved on disk 0.
+/// Runs after `disk_store::mount`.
+pub unsafe fn restore() {
+    let mut buf = [0u8; 16];
+    let saved = match unsafe { crate::kernel::disk_store::read_record(0, RECORD_ID, &mut buf) } {
+        Some(len) => core::str::from_utf8(&buf[..len.min(buf.len())]).ok().and_then(Layout::by_name),
+        None      => None,
+    };
+    unsafe { apply(saved.unwrap_or(Layout::Us), false); }
+}
@@ -4,17 +4,41 @@
 //!   serial   — UART serial port (COM1)
 //!   pic      — 8259A Programmable Interrupt Controller
 //!   timer    — 8253/8254 Programmable Interval Timer
+//!   speaker  — PC speaker on PIT channel 2 (bell, KDMKTONE/KIOCSOUND)
 //!   keyboard — PS/2 keyboard controller
-//!   ata      — ATA/IDE disk controller
+//!   keymap   — keyboard layouts, dead keys and Compose
+//!   evdev    — /dev/input/event* for the PS/2 keyboard and mouse
+//!   vt       — text virtual consoles tty1..6
+//!   ata      — ATA/IDE disk controller and the disk table
+//!   ahci     — AHCI SATA controller (DMA, NCQ) feeding the ata disk table
+//!   virtio   — virtio-pci transport (legacy + modern) and split virtqueues
+//!   virtio_blk — virtio block devices feeding the ata disk table
+//!   nvme     — NVMe controllers (admin + I/O queue, PRPs) feeding the ata disk table
+//!   fbdev    — /dev/fb0 over the bootloader framebuffer
+//!   ac97     — Intel AC'97 audio controller (PCM-out DMA ring, codec mixer)
+//!   dsp      — OSS /dev/dsp and /dev/mixer on top of ac97
+//!   rng      — ChaCha20 CSPRNG (RDSEED / RDRAND / RDTSC jitter seeded)
 //!   shutdown — ACPI power management
 //!   net/     — network subsystem (PCI, NIC drivers, IP stack)
 
 pub mod serial;
 pub mod pic;
 pub mod timer;
+pub mod speaker;
 pub mod rtc;
+pub mod rng;
 pub mod keyboard;
+pub mod keymap;
+pub mod evdev;
+pub mod vt;
 pub mod ata;
+pub mod ahci;
+pub mod virtio;
+pub mod virtio_blk;
+pub mod nvme;
+pub mod fbdev;
+pub mod ac97;
+pub mod dsp;
 pub mod disk_store;
 pub mod shutdown;
 pub mod net;
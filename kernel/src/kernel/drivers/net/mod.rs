@@ -5,6 +5,7 @@
 //!   rtl8139.rs — RTL8139 Ethernet driver (QEMU)
 //!   e1000.rs   — Intel e1000 driver (VirtualBox/VMware, I/O port)
 //!   pcnet.rs   — AMD PCnet driver (VirtualBox PCnet-FAST III)
+//!   virtio.rs  — virtio-net driver (QEMU virtio-net-pci, legacy or modern)
 //!   stack.rs   — smoltcp integration (Interface + SocketSet)
 //!   socket.rs  — per-process socket table + syscall implementations
 
@@ -12,18 +13,20 @@ pub mod pci;
 pub mod rtl8139;
 pub mod e1000;
 pub mod pcnet;
+pub mod virtio;
 pub mod stack;
 pub mod socket;
 pub mod dns;
 
 /// Initialise the full networking subsystem.
-/// Tries RTL8139 → e1000 → PCnet in order.
+/// Tries RTL8139 → e1000 → PCnet → virtio-net in order.
 pub unsafe fn init() {
     pci::enumerate_to_serial();
 
     let found = unsafe { rtl8139::init() }
              || unsafe { e1000::init()  }
-             || unsafe { pcnet::init()  };
+             || unsafe { pcnet::init()  }
+             || unsafe { virtio::init() };
 
     if !found {
         crate::kernel::serial::SERIAL_PORT.write_str("[net] No supported NIC found\n");
@@ -45,6 +48,7 @@ pub fn is_present() -> bool {
     rtl8139::PRESENT.load(Ordering::Relaxed)
     || e1000::PRESENT.load(Ordering::Relaxed)
     || pcnet::PRESENT.load(Ordering::Relaxed)
+    || virtio::PRESENT.load(Ordering::Relaxed)
 }
 
 /// Name of the active NIC for display purposes.
@@ -53,6 +57,7 @@ pub fn nic_name() -> &'static str {
     if rtl8139::PRESENT.load(Ordering::Relaxed) { return "RTL8139"; }
     if e1000::PRESENT.load(Ordering::Relaxed)   { return "e1000";   }
     if pcnet::PRESENT.load(Ordering::Relaxed)   { return "PCnet";   }
+    if virtio::PRESENT.load(Ordering::Relaxed)  { return "virtio-net"; }
     "None"
 }
 
@@ -90,5 +95,12 @@ pub fn get_mac() -> Option<[u8; 6]> {
             }
         }
     }
+    if virtio::PRESENT.load(Ordering::Relaxed) {
+        unsafe {
+            if let Some(d) = &*core::ptr::addr_of!(virtio::DRIVER) {
+                return Some(d.mac);
+            }
+        }
+    }
     None
 }
@@ -78,12 +78,66 @@ impl PciDevice {
         if val & 1 != 0 { Some((val & 0xFFFC) as u16) } else { None }
     }
 
+    /// Read a BAR and return its physical base (if it is a memory BAR).
+    /// A 64-bit BAR takes its high half from the next register.
+    pub fn mem_bar(&self, bar: u8) -> Option<u64> {
+        let reg = 0x10 + bar * 4;
+        let val = read32(self.bus, self.dev, self.func, reg);
+        if val & 1 != 0 { return None; }
+        let mut base = (val & 0xFFFF_FFF0) as u64;
+        if (val >> 1) & 3 == 2 {
+            base |= (read32(self.bus, self.dev, self.func, reg + 4) as u64) << 32;
+        }
+        (base != 0).then_some(base)
+    }
+
     /// Enable PCI bus mastering (needed for DMA).
     pub fn enable_bus_mastering(&self) {
         let cmd = read16(self.bus, self.dev, self.func, 0x04);
         write16(self.bus, self.dev, self.func, 0x04, cmd | 0x0007);
         // bits: I/O space (0), memory space (1), bus master (2)
     }
+
+    /// The legacy interrupt line the firmware routed the device to.
+    pub fn irq_line(&self) -> u8 {
+        read32(self.bus, self.dev, self.func, 0x3C) as u8
+    }
+
+    /// Config-space offsets of the device's capabilities with ID `id`.
+    pub fn capabilities(self, id: u8) -> impl Iterator<Item = u8> {
+        let status = read16(self.bus, self.dev, self.func, 0x06);
+        let mut ptr = if status & 0x10 != 0 {
+            read32(self.bus, self.dev, self.func, 0x34) as u8 & 0xFC
+        } else { 0 };
+        // A malformed list could loop; config space holds at most 48.
+        let mut left = 48;
+        core::iter::from_fn(move || {
+            while ptr != 0 && left > 0 {
+                left -= 1;
+                let cur = ptr;
+                let hdr = read32(self.bus, self.dev, self.func, cur);
+                ptr = (hdr >> 8) as u8 & 0xFC;
+                if hdr as u8 == id { return Some(cur); }
+            }
+            None
+        })
+    }
+}
+
+/// Call `f` for every function on every bus.
+pub fn for_each_device(mut f: impl FnMut(PciDevice)) {
+    for bus in 0..=255u8 {
+        for dev in 0..32u8 {
+            let hdr = read32(bus, dev, 0, 0x0C);
+            let max_func: u8 = if (hdr >> 23) & 1 != 0 { 8 } else { 1 };
+            for func in 0..max_func {
+                let id = read32(bus, dev, func, 0x00);
+                let vendor = id as u16;
+                if vendor == 0xFFFF { continue; }
+                f(PciDevice { bus, dev, func, vendor, device: (id >> 16) as u16 });
+            }
+        }
+    }
 }
 
 /// Scan all buses for a device matching `vendor_id:device_id`.
@@ -107,6 +161,27 @@ pub fn find_device(vendor_id: u16, device_id: u16) -> Option<PciDevice> {
     None
 }
 
+/// Scan all buses for the first device of `class:subclass` (e.g. 04:01
+/// for an AC'97 audio controller).
+pub fn find_class(class: u8, subclass: u8) -> Option<PciDevice> {
+    for bus in 0..=255u8 {
+        for dev in 0..32u8 {
+            let hdr = read32(bus, dev, 0, 0x0C);
+            let max_func: u8 = if (hdr >> 23) & 1 != 0 { 8 } else { 1 };
+            for func in 0..max_func {
+                let id = read32(bus, dev, func, 0x00);
+                let vendor = id as u16;
+                if vendor == 0xFFFF { continue; }
+                let class_dword = read32(bus, dev, func, 0x08);
+                if (class_dword >> 24) as u8 == class && (class_dword >> 16) as u8 == subclass {
+                    return Some(PciDevice { bus, dev, func, vendor, device: (id >> 16) as u16 });
+                }
+            }
+        }
+    }
+    None
+}
+
 /// Print every detected PCI device (vendor:device, bus/dev/func) to serial.
 /// Call this once at boot to help diagnose missing hardware.
 pub fn enumerate_to_serial() {
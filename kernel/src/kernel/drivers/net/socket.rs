@@ -414,6 +414,48 @@ pub unsafe fn socket_read_ready(sfd: i64) -> bool {
     }
 }
 
+/// Returns true if `sfd` names an open socket.
+pub unsafe fn is_socket(sfd: i64) -> bool {
+    slot_from_fd(sfd).is_some()
+}
+
+/// `(readable, writable)` for poll/epoll.  A listener is readable once a
+/// connection can be accepted; a closed TCP connection is readable (EOF).
+pub unsafe fn socket_ready(sfd: i64) -> (bool, bool) {
+    let slot = match slot_from_fd(sfd) { Some(s) => s, None => return (false, false) };
+    let (handle, sock_type, listening) = unsafe {
+        let table = &*core::ptr::addr_of!(SOCK_TABLE);
+        match table[slot].as_ref() {
+            Some(e) => (e.handle, e.sock_type, e.listening),
+            None    => return (false, false),
+        }
+    };
+    unsafe {
+        let net_ptr = core::ptr::addr_of_mut!(stack::NET);
+        let state   = match &mut *net_ptr { Some(s) => s, None => return (false, false) };
+        if sock_type == SOCK_STREAM {
+            let sock = state.sockets.get_mut::<TcpSocket>(handle);
+            if listening {
+                (sock.is_active(), false)
+            } else {
+                (sock.can_recv() || !sock.is_open(), sock.can_send())
+            }
+        } else {
+            let sock = state.sockets.get_mut::<UdpSocket>(handle);
+            (sock.can_recv(), sock.can_send())
+        }
+    }
+}
+
+/// Readiness callback after the stack processed traffic: any open socket
+/// may have become readable or writable.
+pub unsafe fn notify_all() {
+    for slot in 0..MAX_SOCKETS {
+        let open = unsafe { (*core::ptr::addr_of!(SOCK_TABLE))[slot].is_some() };
+        if open { crate::kernel::epoll::notify(crate::kernel::epoll::Source::Socket(slot)); }
+    }
+}
+
 /// Returns `true` once the TCP handshake is complete (socket can send data).
 /// Used by the kernel-side connectivity probe — does not consume or send any data.
 pub unsafe fn tcp_is_connected(sfd: i64) -> bool {
@@ -449,5 +491,6 @@ pub unsafe fn sys_close_socket(sfd: i64) -> i64 {
         let table = &mut *core::ptr::addr_of_mut!(SOCK_TABLE);
         table[slot] = None;
     }
+    crate::kernel::epoll::forget(crate::kernel::epoll::Source::Socket(slot));
     0
 }
@@ -17,10 +17,12 @@ use smoltcp::socket::tcp::{self, Socket as TcpSocket};
 use smoltcp::socket::udp::{self, Socket as UdpSocket};
 use smoltcp::time::Instant;
 use smoltcp::wire::{EthernetAddress, IpAddress, IpCidr, Ipv4Address, Ipv4Cidr};
+use crate::kernel::slab::{zeroed_vec, SOCK_BUF};
 
 use super::rtl8139;
 use super::e1000;
 use super::pcnet;
+use super::virtio;
 
 // ── Resolved network configuration ────────────────────────────────────────
 
@@ -62,7 +64,7 @@ impl Device for NicDevice {
     fn receive(&mut self, _ts: Instant) -> Option<(RtlRxToken, RtlTxToken)> {
         let mut buf = [0u8; 1514];
         let n = unsafe {
-            // Try RTL8139 first, then e1000.
+            // Try RTL8139 first, then e1000, PCnet and virtio-net.
             let n = {
                 let ptr = core::ptr::addr_of_mut!(rtl8139::DRIVER);
                 match &mut *ptr { Some(nic) => nic.recv(&mut buf), None => 0 }
@@ -73,8 +75,14 @@ impl Device for NicDevice {
                     match &mut *ptr { Some(nic) => nic.recv(&mut buf), None => 0 }
                 };
                 if n2 > 0 { n2 } else {
-                    let ptr = core::ptr::addr_of_mut!(pcnet::DRIVER);
-                    match &mut *ptr { Some(nic) => nic.recv(&mut buf), None => 0 }
+                    let n3 = {
+                        let ptr = core::ptr::addr_of_mut!(pcnet::DRIVER);
+                        match &mut *ptr { Some(nic) => nic.recv(&mut buf), None => 0 }
+                    };
+                    if n3 > 0 { n3 } else {
+                        let ptr = core::ptr::addr_of_mut!(virtio::DRIVER);
+                        match &mut *ptr { Some(nic) => nic.recv(&mut buf), None => 0 }
+                    }
                 }
             }
         };
@@ -86,7 +94,8 @@ impl Device for NicDevice {
         let rtl_ok = rtl8139::PRESENT.load(Ordering::Relaxed);
         let e1k_ok = e1000::PRESENT.load(Ordering::Relaxed);
         let pcn_ok = pcnet::PRESENT.load(Ordering::Relaxed);
-        if rtl_ok || e1k_ok || pcn_ok { Some(RtlTxToken) } else { None }
+        let vio_ok = virtio::PRESENT.load(Ordering::Relaxed);
+        if rtl_ok || e1k_ok || pcn_ok || vio_ok { Some(RtlTxToken) } else { None }
     }
 
     fn capabilities(&self) -> DeviceCapabilities {
@@ -124,6 +133,11 @@ impl TxToken for RtlTxToken {
                     let ptr = core::ptr::addr_of_mut!(pcnet::DRIVER);
                     if let Some(nic) = &mut *ptr {
                         nic.send(&buf[..len]);
+                    } else {
+                        let ptr = core::ptr::addr_of_mut!(virtio::DRIVER);
+                        if let Some(nic) = &mut *ptr {
+                            nic.send(&buf[..len]);
+                        }
                     }
                 }
             }
@@ -146,6 +160,9 @@ pub unsafe fn init() {
         } else if pcnet::PRESENT.load(Ordering::Relaxed) {
             let ptr = core::ptr::addr_of!(pcnet::DRIVER);
             match &*ptr { Some(d) => d.mac, None => return }
+        } else if virtio::PRESENT.load(Ordering::Relaxed) {
+            let ptr = core::ptr::addr_of!(virtio::DRIVER);
+            match &*ptr { Some(d) => d.mac, None => return }
         } else {
             return;
         }
@@ -249,7 +266,9 @@ pub unsafe fn poll() {
     if let Some(state) = &mut *ptr {
         let now = timestamp();
         let mut nic = NicDevice;
-        state.iface.poll(now, &mut nic, &mut state.sockets);
+        if state.iface.poll(now, &mut nic, &mut state.sockets) {
+            unsafe { super::socket::notify_all(); }
+        }
 
         // Keep processing DHCP events (renewal, reconfiguration).
         if let Some(h) = state.dhcp_handle {
@@ -280,8 +299,8 @@ pub unsafe fn tcp_socket_new() -> Option<SocketHandle> {
         let ptr = core::ptr::addr_of_mut!(NET);
         let state = match &mut *ptr { Some(s) => s, None => return None };
 
-        let rx = tcp::SocketBuffer::new(alloc::vec![0u8; 4096]);
-        let tx = tcp::SocketBuffer::new(alloc::vec![0u8; 4096]);
+        let rx = tcp::SocketBuffer::new(zeroed_vec(SOCK_BUF));
+        let tx = tcp::SocketBuffer::new(zeroed_vec(SOCK_BUF));
         Some(state.sockets.add(TcpSocket::new(rx, tx)))
     }
 }
@@ -293,11 +312,11 @@ pub unsafe fn udp_socket_new() -> Option<SocketHandle> {
 
         let rx = udp::PacketBuffer::new(
             alloc::vec![udp::PacketMetadata::EMPTY; 16],
-            alloc::vec![0u8; 4096],
+            zeroed_vec(SOCK_BUF),
         );
         let tx = udp::PacketBuffer::new(
             alloc::vec![udp::PacketMetadata::EMPTY; 16],
-            alloc::vec![0u8; 4096],
+            zeroed_vec(SOCK_BUF),
         );
         Some(state.sockets.add(UdpSocket::new(rx, tx)))
     }
@@ -0,0 +1,153 @@
+//! virtio-net driver (QEMU `-device virtio-net-pci`), legacy or modern,
+//! on the virtio-pci transport in `drivers/virtio.rs`.
+//!
+//! Queue 0 receives, queue 1 transmits.  Each receive descriptor owns a
+//! 2 KiB DMA buffer that goes straight back to the device once its frame
+//! is copied out; transmit buffers are reclaimed from the used ring before
+//! the next send.  Every frame carries a virtio_net_hdr in front (10 bytes,
+//! 12 with VERSION_1), left zero: no checksum or segmentation offload is
+//! negotiated.  Received frames raise the INTx line, which the handler only
+//! acknowledges — `net::poll` picks the frames up.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use crate::kernel::paging_allocator::{alloc_dma_frames, get_hhdm_offset};
+use crate::kernel::virtio::{self, Device, Virtqueue};
+
+const F_MAC: u64 = 1 << 5;
+
+const RX_QUEUE: u16 = 0;
+const TX_QUEUE: u16 = 1;
+
+const RX_N: usize = 32;
+const TX_N: usize = 32;
+const BUF_SZ: usize = 2048; // header + max Ethernet frame
+const BUF_PAGES: usize = (RX_N + TX_N) * BUF_SZ / 4096;
+
+pub struct VirtioNet {
+    dev:     Device,
+    rx:      Virtqueue,
+    tx:      Virtqueue,
+    /// Physical base of RX_N receive buffers followed by TX_N transmit ones.
+    bufs:    u64,
+    hdr_len: usize,
+    /// Bit n set: transmit buffer n is ours to fill.
+    tx_free: u32,
+    pub mac: [u8; 6],
+}
+
+pub static mut DRIVER: Option<VirtioNet> = None;
+pub static PRESENT: AtomicBool = AtomicBool::new(false);
+
+fn virt(phys: u64) -> u64 {
+    phys + get_hhdm_offset()
+}
+
+unsafe fn interrupt() -> bool {
+    match unsafe { &*core::ptr::addr_of!(DRIVER) } {
+        Some(nic) => nic.dev.ack_interrupt(),
+        None      => false,
+    }
+}
+
+pub unsafe fn init() -> bool {
+    let Some(&pci) = virtio::devices(virtio::TYPE_NET).first() else { return false };
+    let sp = &crate::kernel::serial::SERIAL_PORT;
+
+    let Some(dev) = (unsafe { Device::new(pci) }) else {
+        unsafe { sp.write_str("[virtio-net] Device has no usable BARs\n"); }
+        return false;
+    };
+    let Some(features) = dev.negotiate(F_MAC) else {
+        unsafe { sp.write_str("[virtio-net] Feature negotiation failed\n"); }
+        return false;
+    };
+    let (Some(mut rx), Some(mut tx)) = (unsafe { dev.queue(RX_QUEUE) }, unsafe { dev.queue(TX_QUEUE) }) else {
+        unsafe { sp.write_str("[virtio-net] Missing RX/TX queue\n"); }
+        dev.fail();
+        return false;
+    };
+    let Some(bufs) = (unsafe { alloc_dma_frames(BUF_PAGES) }) else {
+        dev.fail();
+        return false;
+    };
+
+    let mut mac = [0x52, 0x54, 0x00, 0x12, 0x34, 0x56];
+    if features & F_MAC != 0 {
+        for (i, b) in mac.iter_mut().enumerate() { *b = dev.config8(i as u16); }
+    }
+    let hdr_len = if dev.is_modern() { 12 } else { 10 };
+
+    for slot in 0..RX_N.min(rx.size() as usize) {
+        rx.push(&[(bufs + (slot * BUF_SZ) as u64, BUF_SZ as u32, true)], slot as u16);
+    }
+    // Sent buffers are reclaimed lazily; no need to hear about them.
+    tx.disable_interrupts();
+    dev.driver_ok();
+    rx.kick();
+
+    let irq = pci.irq_line();
+    let irq_ok = unsafe { crate::kernel::interrupts::register_irq(irq, interrupt) };
+    let modern = dev.is_modern();
+
+    unsafe {
+        let ptr = core::ptr::addr_of_mut!(DRIVER);
+        *ptr = Some(VirtioNet { dev, rx, tx, bufs, hdr_len, tx_free: u32::MAX, mac });
+    }
+    PRESENT.store(true, Ordering::SeqCst);
+
+    unsafe {
+        sp.write_str("[virtio-net] ");
+        sp.write_str(if modern { "modern" } else { "legacy" });
+        sp.write_str(" device, MAC ");
+        for (i, b) in mac.iter().enumerate() {
+            if i > 0 { sp.write_str(":"); }
+            sp.write_hex(*b as u32);
+        }
+        if irq_ok {
+            sp.write_str(" irq=");
+            sp.write_decimal(irq as u32);
+        }
+        sp.write_str("\n");
+    }
+    true
+}
+
+impl VirtioNet {
+    fn buf(&self, n: usize) -> u64 {
+        self.bufs + (n * BUF_SZ) as u64
+    }
+
+    pub fn send(&mut self, frame: &[u8]) -> bool {
+        let len = self.hdr_len + frame.len();
+        if len > BUF_SZ { return false; }
+
+        while let Some((slot, _)) = self.tx.pop() { self.tx_free |= 1 << slot; }
+        if self.tx_free == 0 { return false; }
+        let slot = self.tx_free.trailing_zeros() as usize;
+
+        let phys = self.buf(RX_N + slot);
+        let p = virt(phys) as *mut u8;
+        unsafe {
+            core::ptr::write_bytes(p, 0, self.hdr_len);
+            core::ptr::copy_nonoverlapping(frame.as_ptr(), p.add(self.hdr_len), frame.len());
+        }
+        if !self.tx.push(&[(phys, len as u32, false)], slot as u16) { return false; }
+        self.tx_free &= !(1 << slot);
+        self.tx.kick();
+        true
+    }
+
+    pub fn recv(&mut self, out: &mut [u8]) -> usize {
+        let Some((slot, len)) = self.rx.pop() else { return 0 };
+        let phys = self.buf(slot as usize);
+        let n = (len as usize).min(BUF_SZ).saturating_sub(self.hdr_len).min(out.len());
+        unsafe {
+            core::ptr::copy_nonoverlapping((virt(phys) as *const u8).add(self.hdr_len), out.as_mut_ptr(), n);
+        }
+
+        // Hand the buffer back to the device.
+        self.rx.push(&[(phys, BUF_SZ as u32, true)], slot);
+        self.rx.kick();
+        n
+    }
+}
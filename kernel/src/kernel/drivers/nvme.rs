@@ -0,0 +1,658 @@
+//! NVMe SSDs (PCI class 01:08, QEMU `-device nvme`).
+//!
+//! BAR 0 holds the controller registers and, from 0x1000, the doorbells.
+//! Bring-up disables the controller, hands it a 32-entry admin queue pair,
+//! enables it, IDENTIFYs the controller and creates one I/O queue pair.
+//! Namespaces come from the active namespace list (IDENTIFY CNS 2), or
+//! 1..NN on controllers that predate it.
+//!
+//! Data is described with PRPs: PRP1 is the first, possibly partial, page
+//! of the caller's buffer and PRP2 the second page or a PRP list of the
+//! rest, all taken from the pages behind the buffer.  A transfer is cut
+//! into 64 KiB commands (less if MDTS says so) and up to `MAX_REQS` are
+//! queued before the waiter reaps the completion queue.  Namespaces with
+//! blocks larger than 512 bytes, and buffers PRPs cannot describe, go
+//! through a bounce buffer, read-modify-write for partly covered blocks.
+//!
+//! Completions raise the controller's INTx line (interrupt vector 0).  The
+//! handler masks it through INTMS so the level-triggered line drops; the
+//! waiter reaps the queue and unmasks before sleeping in `hlt` again.  With
+//! interrupts off (inside a syscall) it polls the phase tag instead.
+//!
+//! Namespaces join `ata::DISKS`, so FAT, ext2, swap and the `fs::mbr`
+//! partition scan reach them like any other disk.  They show up as
+//! `/dev/nvmeXnY`, with `/dev/nvmeXnYpN` per MBR partition, rather than
+//! `/dev/hdX`.  The n-th namespace takes slot n when that is free, so on a
+//! machine with only an NVMe drive its first namespace is disk 0.
+
+extern crate alloc;
+
+use alloc::format;
+use core::arch::asm;
+use core::ptr::{read_volatile, write_volatile};
+use core::sync::atomic::{fence, Ordering};
+use crate::kernel::ata::{self, AtaDisk, Dma};
+use crate::kernel::net::pci::{self, PciDevice};
+use crate::kernel::paging_allocator::{
+    alloc_dma_frames, free_phys_frames, get_hhdm_offset, kernel_virt_to_phys, map_mmio,
+};
+use crate::kernel::serial::SERIAL_PORT;
+use crate::kernel::timer;
+
+// Controller registers.
+const CAP:   u64 = 0x00;
+const VS:    u64 = 0x08;
+const INTMS: u64 = 0x0C;
+const INTMC: u64 = 0x10;
+const CC:    u64 = 0x14;
+const CSTS:  u64 = 0x1C;
+const AQA:   u64 = 0x24;
+const ASQ:   u64 = 0x28;
+const ACQ:   u64 = 0x30;
+const DOORBELLS: u64 = 0x1000;
+
+const CC_EN:     u32 = 1 << 0;
+/// I/O submission and completion entries: 2^6 = 64 and 2^4 = 16 bytes.
+const CC_IOSQES: u32 = 6 << 16;
+const CC_IOCQES: u32 = 4 << 20;
+const CSTS_RDY:  u32 = 1 << 0;
+const CSTS_CFS:  u32 = 1 << 1;
+
+// Admin commands.
+const ADM_CREATE_SQ:    u8 = 0x01;
+const ADM_CREATE_CQ:    u8 = 0x05;
+const ADM_IDENTIFY:     u8 = 0x06;
+const ADM_SET_FEATURES: u8 = 0x09;
+const FEAT_NUM_QUEUES:  u32 = 0x07;
+const CNS_NAMESPACE:    u32 = 0;
+const CNS_CONTROLLER:   u32 = 1;
+const CNS_ACTIVE_NS:    u32 = 2;
+
+// NVM commands.
+const IO_FLUSH: u8 = 0x00;
+const IO_WRITE: u8 = 0x01;
+const IO_READ:  u8 = 0x02;
+
+const MAX_CTRLS: usize = 4;
+const MAX_NS:    usize = 4;
+const ADMIN_DEPTH: u16 = 32;
+const IO_DEPTH:    u16 = 64;
+const SECTOR: usize = 512;
+const PAGE:   usize = 4096;
+/// Bytes per I/O command, unless MDTS is smaller.
+const CHUNK_BYTES: usize = 64 * 1024;
+/// PRP list entries per command: every page after the first.
+const PRP_ENTRIES: usize = CHUNK_BYTES / PAGE;
+const PRP_LIST_BYTES: usize = PRP_ENTRIES * 8;
+/// Commands in flight per transfer, each with its own PRP list.
+const MAX_REQS: usize = 16;
+const BOUNCE_PAGES: usize = CHUNK_BYTES / PAGE;
+
+/// Timeout for a batch, in timer ticks (100 Hz) or polling iterations.
+const TIMEOUT_TICKS: u64 = 500;
+const TIMEOUT_SPINS: u64 = 100_000_000;
+
+/// A submission / completion queue pair.
+struct Queue {
+    /// Physical bases of the submission and completion rings.
+    sq:    u64,
+    cq:    u64,
+    depth: u16,
+    tail:  u16,
+    head:  u16,
+    /// Phase tag a new completion entry carries.
+    phase: bool,
+    /// Kernel addresses of the SQ tail and CQ head doorbells.
+    sq_db: u64,
+    cq_db: u64,
+}
+
+struct Ctrl {
+    /// Virtual address of the register BAR.
+    regs:      u64,
+    admin:     Queue,
+    io:        Queue,
+    /// Physical page of PRP lists, `PRP_LIST_BYTES` per request slot.
+    prps:      u64,
+    /// Physical base of the bounce buffer (`BOUNCE_PAGES`).
+    bounce:    u64,
+    /// Largest transfer per command.
+    max_bytes: usize,
+    /// Volatile write cache: flush after writes.
+    vwc:       bool,
+    /// A command timed out; the queues are in an unknown state.
+    dead:      bool,
+}
+
+#[derive(Clone, Copy)]
+struct Namespace {
+    ctrl:   usize,
+    nsid:   u32,
+    /// log2 of the block size.
+    shift:  u32,
+}
+
+static mut CTRLS: [Option<Ctrl>; MAX_CTRLS] = [const { None }; MAX_CTRLS];
+static mut NAMESPACES: [Option<Namespace>; MAX_NS] = [None; MAX_NS];
+/// IRQ lines `interrupt` is registered on.
+static mut IRQS: [u8; MAX_CTRLS] = [0; MAX_CTRLS];
+
+// ── Register access ───────────────────────────────────────────────────────
+
+unsafe fn rd(addr: u64) -> u32 {
+    unsafe { read_volatile(addr as *const u32) }
+}
+
+unsafe fn wr(addr: u64, v: u32) {
+    unsafe { write_volatile(addr as *mut u32, v) }
+}
+
+unsafe fn rd64(addr: u64) -> u64 {
+    unsafe { rd(addr) as u64 | (rd(addr + 4) as u64) << 32 }
+}
+
+unsafe fn wr64(addr: u64, v: u64) {
+    unsafe {
+        wr(addr, v as u32);
+        wr(addr + 4, (v >> 32) as u32);
+    }
+}
+
+fn virt(phys: u64) -> u64 {
+    phys + get_hhdm_offset()
+}
+
+/// About `us` microseconds: each write to the POST port takes ~1 µs.
+fn udelay(us: u32) {
+    for _ in 0..us {
+        unsafe { asm!("out 0x80, al", in("al") 0u8, options(nomem, nostack)); }
+    }
+}
+
+/// Wait up to `ms` milliseconds for `(reg & mask) == want`.
+unsafe fn wait_reg(addr: u64, mask: u32, want: u32, ms: u32) -> bool {
+    for _ in 0..ms * 10 {
+        if unsafe { rd(addr) } & mask == want { return true; }
+        udelay(100);
+    }
+    false
+}
+
+// ── Queues ────────────────────────────────────────────────────────────────
+
+impl Queue {
+    /// Allocate queue pair `qid` with `depth` entries; `stride` is the
+    /// doorbell stride from CAP.DSTRD.
+    unsafe fn new(regs: u64, stride: u64, qid: u16, depth: u16) -> Option<Queue> {
+        let sq_pages = (depth as usize * 64).div_ceil(PAGE);
+        let sq = unsafe { alloc_dma_frames(sq_pages)? };
+        let Some(cq) = (unsafe { alloc_dma_frames((depth as usize * 16).div_ceil(PAGE)) }) else {
+            unsafe { free_phys_frames(sq, sq_pages); }
+            return None;
+        };
+        Some(Queue {
+            sq, cq, depth,
+            tail:  0,
+            head:  0,
+            phase: true,
+            sq_db: regs + DOORBELLS + 2 * qid as u64 * stride,
+            cq_db: regs + DOORBELLS + (2 * qid as u64 + 1) * stride,
+        })
+    }
+
+    unsafe fn submit(&mut self, cmd: &[u32; 16]) {
+        let e = (virt(self.sq) + self.tail as u64 * 64) as *mut u32;
+        for (i, &w) in cmd.iter().enumerate() {
+            unsafe { write_volatile(e.add(i), w); }
+        }
+        self.tail = (self.tail + 1) % self.depth;
+        fence(Ordering::SeqCst);
+        unsafe { wr(self.sq_db, self.tail as u32); }
+    }
+
+    /// Whether a new completion entry waits at the head.
+    fn ready(&self) -> bool {
+        let dw3 = unsafe { rd(virt(self.cq) + self.head as u64 * 16 + 12) };
+        (dw3 >> 16) & 1 == self.phase as u32
+    }
+
+    /// Take the next completion: command ID and status (0 = success).
+    unsafe fn reap(&mut self) -> Option<(u16, u16)> {
+        if !self.ready() { return None; }
+        fence(Ordering::SeqCst);
+        let dw3 = unsafe { rd(virt(self.cq) + self.head as u64 * 16 + 12) };
+        self.head += 1;
+        if self.head == self.depth {
+            self.head = 0;
+            self.phase = !self.phase;
+        }
+        unsafe { wr(self.cq_db, self.head as u32); }
+        Some((dw3 as u16, (dw3 >> 17) as u16 & 0x7FFF))
+    }
+}
+
+fn command(opcode: u8, cid: u16, nsid: u32, prp1: u64, prp2: u64) -> [u32; 16] {
+    let mut c = [0u32; 16];
+    c[0] = opcode as u32 | (cid as u32) << 16;
+    c[1] = nsid;
+    c[6] = prp1 as u32;
+    c[7] = (prp1 >> 32) as u32;
+    c[8] = prp2 as u32;
+    c[9] = (prp2 >> 32) as u32;
+    c
+}
+
+// ── Commands ──────────────────────────────────────────────────────────────
+
+impl Ctrl {
+    /// Wait for `pending` commands on the admin or I/O queue.  True if all
+    /// of them succeeded.  A timeout leaves the controller dead.
+    unsafe fn wait(&mut self, c: usize, admin: bool, mut pending: usize) -> bool {
+        let rflags: u64;
+        unsafe { asm!("pushfq; pop {}", out(reg) rflags, options(nomem, preserves_flags)); }
+        let irqs = rflags & (1 << 9) != 0;
+        if irqs { unsafe { asm!("cli", options(nomem, nostack)); } }
+
+        let start = unsafe { timer::get_ticks() };
+        let mut spins = 0u64;
+        let mut ok = true;
+        let done = loop {
+            let q = if admin { &mut self.admin } else { &mut self.io };
+            while let Some((_, status)) = unsafe { q.reap() } {
+                ok &= status == 0;
+                pending -= 1;
+            }
+            if pending == 0 { break true; }
+            if irqs {
+                if unsafe { timer::get_ticks() } - start > TIMEOUT_TICKS { break false; }
+                // Unmask: the line comes back up if more is already queued.
+                // `sti` takes effect after `hlt` starts: no lost wakeup.
+                unsafe {
+                    wr(self.regs + INTMC, 1);
+                    asm!("sti; hlt; cli", options(nomem, nostack));
+                }
+            } else {
+                spins += 1;
+                if spins > TIMEOUT_SPINS { break false; }
+                core::hint::spin_loop();
+            }
+        };
+        unsafe { wr(self.regs + INTMC, 1); }
+        if irqs { unsafe { asm!("sti", options(nomem, nostack)); } }
+
+        if !done {
+            self.dead = true;
+            unsafe {
+                SERIAL_PORT.write_str("[nvme] nvme");
+                SERIAL_PORT.write_decimal(c as u32);
+                SERIAL_PORT.write_str(": command timed out, csts=0x");
+                SERIAL_PORT.write_hex(rd(self.regs + CSTS));
+                SERIAL_PORT.write_str(", controller disabled\n");
+            }
+        }
+        done && ok
+    }
+
+    /// Run one admin command to completion.
+    unsafe fn admin(&mut self, c: usize, cmd: [u32; 16]) -> bool {
+        if self.dead { return false; }
+        unsafe {
+            self.admin.submit(&cmd);
+            self.wait(c, true, 1)
+        }
+    }
+
+    unsafe fn identify(&mut self, c: usize, cns: u32, nsid: u32, buf: u64) -> bool {
+        let mut cmd = command(ADM_IDENTIFY, 0, nsid, buf, 0);
+        cmd[10] = cns;
+        unsafe { self.admin(c, cmd) }
+    }
+
+    /// PRP1 and PRP2 for the `len` bytes at kernel address `addr`, using
+    /// the PRP list of request `slot` when more than two pages are needed.
+    /// `None` if the buffer is not dword-aligned or not mapped.
+    unsafe fn prps(&self, slot: usize, addr: u64, len: usize) -> Option<(u64, u64)> {
+        if addr & 3 != 0 { return None; }
+        let first = unsafe { kernel_virt_to_phys(addr)? };
+        let first_len = (PAGE - (addr & 0xFFF) as usize).min(len);
+        let pages = (len - first_len).div_ceil(PAGE);
+        let next = |i: usize| unsafe { kernel_virt_to_phys(addr + (first_len + i * PAGE) as u64) };
+        match pages {
+            0 => Some((first, 0)),
+            1 => Some((first, next(0)?)),
+            n if n <= PRP_ENTRIES => {
+                let list = self.prps + (slot * PRP_LIST_BYTES) as u64;
+                let entries = virt(list) as *mut u64;
+                for i in 0..n {
+                    unsafe { write_volatile(entries.add(i), next(i)?); }
+                }
+                Some((first, list))
+            }
+            _ => None,
+        }
+    }
+
+    /// Queue a read or write of `blocks` blocks at `lba` of namespace
+    /// `nsid` as request `slot`, with the `len` bytes at `addr` as data.
+    /// False if the buffer cannot be described with PRPs.
+    unsafe fn queue_rw(&mut self, slot: usize, opcode: u8, nsid: u32, lba: u64, blocks: usize,
+                       addr: u64, len: usize) -> bool {
+        let Some((prp1, prp2)) = (unsafe { self.prps(slot, addr, len) }) else { return false };
+        let mut cmd = command(opcode, slot as u16, nsid, prp1, prp2);
+        cmd[10] = lba as u32;
+        cmd[11] = (lba >> 32) as u32;
+        cmd[12] = (blocks - 1) as u32;
+        unsafe { self.io.submit(&cmd); }
+        true
+    }
+
+    /// Move `count` sectors at `lba` through the bounce buffer, whole
+    /// blocks at a time.  A write reads partly covered blocks first.
+    unsafe fn bounced(&mut self, c: usize, ns: &Namespace, lba: u64, count: usize,
+                      buf: *mut u8, write: bool) -> bool {
+        let per = 1usize << (ns.shift - 9);
+        let bounce = virt(self.bounce);
+        let max_blocks = (BOUNCE_PAGES * PAGE).min(self.max_bytes) >> ns.shift;
+        let mut done = 0;
+        while done < count {
+            let sector = lba + done as u64;
+            let block = sector / per as u64;
+            let skip = (sector % per as u64) as usize;
+            let n = (count - done).min(max_blocks * per - skip);
+            let blocks = (skip + n).div_ceil(per);
+            let bytes = blocks << ns.shift;
+            if !write || skip != 0 || n % per != 0 {
+                if !unsafe { self.queue_rw(0, IO_READ, ns.nsid, block, blocks, bounce, bytes) }
+                    || !unsafe { self.wait(c, false, 1) } {
+                    return false;
+                }
+            }
+            let at = (bounce + (skip * SECTOR) as u64) as *mut u8;
+            let data = unsafe { buf.add(done * SECTOR) };
+            if write {
+                unsafe { core::ptr::copy_nonoverlapping(data, at, n * SECTOR); }
+                if !unsafe { self.queue_rw(0, IO_WRITE, ns.nsid, block, blocks, bounce, bytes) }
+                    || !unsafe { self.wait(c, false, 1) } {
+                    return false;
+                }
+            } else {
+                unsafe { core::ptr::copy_nonoverlapping(at, data, n * SECTOR); }
+            }
+            done += n;
+        }
+        true
+    }
+}
+
+/// Move `count` sectors between NVMe namespace `n` and `buf`, starting at
+/// `lba`.  Writes are flushed from a volatile write cache before returning.
+pub unsafe fn transfer(n: usize, lba: u64, count: usize, buf: *mut u8, write: bool) -> bool {
+    let Some(ns) = (unsafe { (*(&raw const NAMESPACES)).get(n).copied().flatten() }) else {
+        return false;
+    };
+    let c = ns.ctrl;
+    let Some(ctrl) = (unsafe { (*(&raw mut CTRLS))[c].as_mut() }) else { return false };
+    if ctrl.dead { return false; }
+
+    if ns.shift != 9 {
+        if !unsafe { ctrl.bounced(c, &ns, lba, count, buf, write) } { return false; }
+    } else {
+        let opcode = if write { IO_WRITE } else { IO_READ };
+        let chunk = ctrl.max_bytes / SECTOR;
+        let mut done = 0;
+        while done < count {
+            let mut queued = 0;
+            while done < count && queued < MAX_REQS {
+                let sectors = (count - done).min(chunk);
+                let data = unsafe { buf.add(done * SECTOR) };
+                let sector = lba + done as u64;
+                if unsafe { ctrl.queue_rw(queued, opcode, ns.nsid, sector, sectors, data as u64, sectors * SECTOR) } {
+                    queued += 1;
+                } else if queued > 0 {
+                    break; // let the batch finish, then bounce this one
+                } else if !unsafe { ctrl.bounced(c, &ns, sector, sectors, data, write) } {
+                    return false;
+                }
+                done += sectors;
+            }
+            if queued > 0 && !unsafe { ctrl.wait(c, false, queued) } { return false; }
+        }
+    }
+
+    if write && ctrl.vwc {
+        unsafe { ctrl.io.submit(&command(IO_FLUSH, 0, ns.nsid, 0, 0)); }
+        return unsafe { ctrl.wait(c, false, 1) };
+    }
+    true
+}
+
+unsafe fn interrupt() -> bool {
+    let mut claimed = false;
+    for ctrl in unsafe { (*(&raw const CTRLS)).iter().flatten() } {
+        if ctrl.admin.ready() || ctrl.io.ready() {
+            // Mask until the waiter has reaped the queue.
+            unsafe { wr(ctrl.regs + INTMS, 1); }
+            claimed = true;
+        }
+    }
+    claimed
+}
+
+// ── Probing ───────────────────────────────────────────────────────────────
+
+/// IDENTIFY namespace `nsid` of controller `c` into `ident` and, if it is
+/// usable, add it to the ata disk table and `/dev`.
+unsafe fn add_namespace(c: usize, nsid: u32, ident: u64, model: [u8; 40]) -> bool {
+    let Some(ctrl) = (unsafe { (*(&raw mut CTRLS))[c].as_mut() }) else { return false };
+    if !unsafe { ctrl.identify(c, CNS_NAMESPACE, nsid, ident) } { return false; }
+    let id = virt(ident) as *const u8;
+    let blocks = unsafe { read_volatile(id as *const u64) };
+    let flbas = unsafe { read_volatile(id.add(26)) } & 0xF;
+    let lbaf = unsafe { read_volatile(id.add(128 + 4 * flbas as usize) as *const u32) };
+    let (metadata, shift) = (lbaf & 0xFFFF, (lbaf >> 16) & 0xFF);
+
+    if blocks == 0 { return false; }
+    if metadata != 0 || !(9..=16).contains(&shift) || 1 << shift > ctrl.max_bytes {
+        unsafe {
+            SERIAL_PORT.write_str("[nvme] nvme");
+            SERIAL_PORT.write_decimal(c as u32);
+            SERIAL_PORT.write_str("n");
+            SERIAL_PORT.write_decimal(nsid);
+            SERIAL_PORT.write_str(": unsupported LBA format, skipped\n");
+        }
+        return false;
+    }
+
+    let namespaces = unsafe { &mut *(&raw mut NAMESPACES) };
+    let Some(n) = namespaces.iter().position(|ns| ns.is_none()) else { return false };
+    namespaces[n] = Some(Namespace { ctrl: c, nsid, shift });
+
+    let sectors = blocks << (shift - 9);
+    let disk = AtaDisk {
+        io_base: 0, ctrl: 0, slave: false, sectors, lba48: true, model, dma: Some(Dma::Nvme(n as u8)),
+    };
+    let Some(idx) = (unsafe { ata::add_disk(n, disk) }) else {
+        unsafe { SERIAL_PORT.write_str("[nvme] No free disk slot\n"); }
+        namespaces[n] = None;
+        return false;
+    };
+
+    let name: &'static str = format!("nvme{c}n{nsid}").leak();
+    crate::kernel::devfs::register_nvme(idx, name);
+
+    let end = model.iter().rposition(|&ch| ch != b' ').map_or(0, |i| i + 1);
+    unsafe {
+        SERIAL_PORT.write_str("[nvme] ");
+        SERIAL_PORT.write_str(name);
+        SERIAL_PORT.write_str(" -> disk");
+        SERIAL_PORT.write_decimal(idx as u32);
+        SERIAL_PORT.write_str(": ");
+        SERIAL_PORT.write_decimal((sectors / 2048) as u32);
+        SERIAL_PORT.write_str(" MB, ");
+        SERIAL_PORT.write_decimal(1 << shift);
+        SERIAL_PORT.write_str("-byte blocks, model=");
+        SERIAL_PORT.write_str(core::str::from_utf8(&model[..end]).unwrap_or("?"));
+        SERIAL_PORT.write_str("\n");
+    }
+    true
+}
+
+/// Reset controller `pci`, set up its queues as controller `c` and add its
+/// namespaces.  Returns how many namespaces were added.
+unsafe fn probe(c: usize, pci: PciDevice) -> usize {
+    let fail = |msg: &str| {
+        unsafe {
+            SERIAL_PORT.write_str("[nvme] ");
+            SERIAL_PORT.write_str(msg);
+            SERIAL_PORT.write_str("\n");
+        }
+        0
+    };
+    let Some(phys) = pci.mem_bar(0) else { return fail("Controller has no BAR0") };
+    let Some(regs) = (unsafe { map_mmio(phys, PAGE) }) else { return fail("Cannot map BAR0") };
+    pci.enable_bus_mastering();
+
+    let cap = unsafe { rd64(regs + CAP) };
+    let stride = 4u64 << ((cap >> 32) & 0xF);
+    let max_entries = (cap & 0xFFFF) as u32 + 1;
+    let timeout_ms = (((cap >> 24) & 0xFF) as u32).max(1) * 500;
+    if (cap >> 48) & 0xF != 0 { return fail("4 KiB pages unsupported"); }
+    if unsafe { map_mmio(phys, (DOORBELLS + 4 * stride) as usize) }.is_none() {
+        return fail("Cannot map doorbells");
+    }
+
+    unsafe {
+        wr(regs + CC, rd(regs + CC) & !CC_EN);
+        if !wait_reg(regs + CSTS, CSTS_RDY, 0, timeout_ms) { return fail("Controller will not reset"); }
+    }
+
+    let io_depth = IO_DEPTH.min(max_entries.min(u16::MAX as u32) as u16);
+    let (Some(admin), Some(io)) = (unsafe { Queue::new(regs, stride, 0, ADMIN_DEPTH) },
+                                   unsafe { Queue::new(regs, stride, 1, io_depth) }) else {
+        return fail("Out of DMA memory");
+    };
+    let (Some(prps), Some(bounce), Some(ident)) = (unsafe { alloc_dma_frames(1) },
+                                                   unsafe { alloc_dma_frames(BOUNCE_PAGES) },
+                                                   unsafe { alloc_dma_frames(1) }) else {
+        return fail("Out of DMA memory");
+    };
+
+    unsafe {
+        let depth = (ADMIN_DEPTH - 1) as u32;
+        wr(regs + AQA, depth | depth << 16);
+        wr64(regs + ASQ, admin.sq);
+        wr64(regs + ACQ, admin.cq);
+        wr(regs + CC, CC_EN | CC_IOSQES | CC_IOCQES);
+        if !wait_reg(regs + CSTS, CSTS_RDY | CSTS_CFS, CSTS_RDY, timeout_ms) {
+            return fail("Controller did not become ready");
+        }
+    }
+
+    let (io_sq, io_cq) = (io.sq, io.cq);
+    let ctrls = unsafe { &mut *(&raw mut CTRLS) };
+    ctrls[c] = Some(Ctrl { regs, admin, io, prps, bounce, max_bytes: CHUNK_BYTES, vwc: false, dead: false });
+    let ctrl = ctrls[c].as_mut().unwrap();
+
+    let irq = pci.irq_line();
+    let irq_ok = unsafe {
+        let irqs = &mut *(&raw mut IRQS);
+        if irqs.contains(&irq) {
+            true
+        } else if crate::kernel::interrupts::register_irq(irq, interrupt) {
+            irqs[c] = irq;
+            true
+        } else { false }
+    };
+
+    let disable = |ctrl: &mut Ctrl| {
+        unsafe {
+            wr(ctrl.regs + CC, rd(ctrl.regs + CC) & !CC_EN);
+            free_phys_frames(ident, 1);
+        }
+        ctrl.dead = true;
+        0
+    };
+    if !unsafe { ctrl.identify(c, CNS_CONTROLLER, 0, ident) } {
+        fail("IDENTIFY controller failed");
+        return disable(ctrl);
+    }
+    let id = virt(ident) as *const u8;
+    let mut model = [b' '; 40];
+    unsafe { core::ptr::copy_nonoverlapping(id.add(24), model.as_mut_ptr(), 40); }
+    let mdts = unsafe { read_volatile(id.add(77)) };
+    let nn = unsafe { read_volatile(id.add(516) as *const u32) };
+    ctrl.vwc = unsafe { read_volatile(id.add(525)) } & 1 != 0;
+    if mdts != 0 { ctrl.max_bytes = CHUNK_BYTES.min(PAGE << mdts.min(16)); }
+
+    // One I/O queue pair (both counts are 0-based), then create it.
+    let mut cmd = command(ADM_SET_FEATURES, 0, 0, 0, 0);
+    cmd[10] = FEAT_NUM_QUEUES;
+    unsafe { ctrl.admin(c, cmd); }
+    let mut cq = command(ADM_CREATE_CQ, 0, 0, io_cq, 0);
+    cq[10] = ((io_depth - 1) as u32) << 16 | 1;
+    cq[11] = 1 << 1 | 1; // interrupts on, vector 0; physically contiguous
+    let mut sq = command(ADM_CREATE_SQ, 0, 0, io_sq, 0);
+    sq[10] = ((io_depth - 1) as u32) << 16 | 1;
+    sq[11] = 1 << 16 | 1; // completions to CQ 1; physically contiguous
+    if !unsafe { ctrl.admin(c, cq) } || !unsafe { ctrl.admin(c, sq) } {
+        fail("Cannot create I/O queues");
+        return disable(ctrl);
+    }
+
+    unsafe {
+        let vs = rd(regs + VS);
+        SERIAL_PORT.write_str("[nvme] nvme");
+        SERIAL_PORT.write_decimal(c as u32);
+        SERIAL_PORT.write_str(": ");
+        SERIAL_PORT.write_hex(pci.vendor as u32);
+        SERIAL_PORT.write_str(":");
+        SERIAL_PORT.write_hex(pci.device as u32);
+        SERIAL_PORT.write_str(" NVMe ");
+        SERIAL_PORT.write_decimal(vs >> 16);
+        SERIAL_PORT.write_str(".");
+        SERIAL_PORT.write_decimal((vs >> 8) & 0xFF);
+        SERIAL_PORT.write_str(" queue depth ");
+        SERIAL_PORT.write_decimal(io_depth as u32);
+        SERIAL_PORT.write_str(" namespaces=");
+        SERIAL_PORT.write_decimal(nn);
+        if irq_ok {
+            SERIAL_PORT.write_str(" irq=");
+            SERIAL_PORT.write_decimal(irq as u32);
+        }
+        SERIAL_PORT.write_str("\n");
+    }
+
+    // Active namespace IDs, ascending and zero-terminated; NVMe 1.0
+    // controllers lack the list, and every ID up to NN is valid there.
+    let mut nsids = [0u32; MAX_NS];
+    if unsafe { ctrl.identify(c, CNS_ACTIVE_NS, 0, ident) } {
+        for (i, id) in nsids.iter_mut().enumerate() {
+            *id = unsafe { read_volatile((virt(ident) as *const u32).add(i)) };
+        }
+    } else {
+        for (i, id) in nsids.iter_mut().enumerate() {
+            if (i as u32) < nn { *id = i as u32 + 1; }
+        }
+    }
+
+    let added = nsids.iter().take_while(|&&id| id != 0)
+        .filter(|&&id| unsafe { add_namespace(c, id, ident, model) })
+        .count();
+    unsafe { free_phys_frames(ident, 1); }
+    added
+}
+
+/// Find NVMe controllers and add their namespaces to the ata disk table.
+/// Returns how many namespaces were added.  Called from `ata::init_all`.
+pub unsafe fn init() -> usize {
+    let mut found = [None; MAX_CTRLS];
+    let mut n = 0;
+    pci::for_each_device(|d| {
+        let class = pci::read32(d.bus, d.dev, d.func, 0x08);
+        if class >> 16 == 0x0108 && n < MAX_CTRLS {
+            found[n] = Some(d);
+            n += 1;
+        }
+    });
+    found.iter().flatten().enumerate().map(|(c, &pci)| unsafe { probe(c, pci) }).sum()
+}
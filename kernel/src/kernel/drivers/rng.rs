@@ -0,0 +1,294 @@
+//! Kernel random number generator.
+//!
+//! A ChaCha20 CSPRNG with fast key erasure: every refill runs the block
+//! function under the current 256-bit key, keeps the first half of the
+//! output as the next key and hands out the second half, so a later
+//! compromise of the state does not reveal earlier output.  Bulk requests
+//! (`getrandom`, `/dev/urandom`) take whole blocks under the old key before
+//! it is replaced.
+//!
+//! The key is seeded at boot from RDSEED (CPUID.07H:EBX[18]) or RDRAND
+//! (CPUID.01H:ECX[30]) when the CPU has them, always mixed with RDTSC jitter
+//! around port I/O, the RTC and the PIT tick count.  Fresh seed material is
+//! folded in every `RESEED_INTERVAL` refills, and anything written to
+//! `/dev/random` or `/dev/urandom` goes in through `add_entropy`.
+
+use core::arch::asm;
+use crate::kernel::timer;
+
+/// Refills between automatic reseeds from the hardware/jitter sources.
+const RESEED_INTERVAL: u32 = 1024;
+
+static mut KEY:    [u32; 8]  = [0; 8];
+/// Unused output from the last refill; consumed bytes are zeroed.
+static mut BUF:    [u8; 32]  = [0; 32];
+static mut AVAIL:  usize     = 0;
+static mut REFILLS: u32      = 0;
+static mut SEEDED: bool      = false;
+static mut HW_RNG: bool      = false;
+static mut HW_SEED: bool     = false;
+
+// ── Hardware entropy ──────────────────────────────────────────────────────
+
+/// CPUID `leaf`/`subleaf` → (eax, ebx, ecx).
+fn cpuid(leaf: u32, subleaf: u32) -> (u32, u32, u32) {
+    let eax: u32;
+    let ebx: u64;
+    let ecx: u32;
+    unsafe {
+        // rbx is reserved by LLVM, so save it around CPUID by hand.
+        asm!(
+            "mov {tmp}, rbx",
+            "cpuid",
+            "xchg {tmp}, rbx",
+            tmp = out(reg) ebx,
+            inout("eax") leaf => eax,
+            inout("ecx") subleaf => ecx,
+            out("edx") _,
+            options(nostack, preserves_flags),
+        );
+    }
+    (eax, ebx as u32, ecx)
+}
+
+/// True if the CPU advertises the RDRAND instruction.
+fn has_rdrand() -> bool { cpuid(1, 0).2 & (1 << 30) != 0 }
+
+/// True if the CPU advertises the RDSEED instruction.
+fn has_rdseed() -> bool { cpuid(0, 0).0 >= 7 && cpuid(7, 0).1 & (1 << 18) != 0 }
+
+/// One RDRAND draw, retried a few times as Intel recommends.
+fn rdrand() -> Option<u64> {
+    for _ in 0..10 {
+        let val: u64;
+        let ok:  u8;
+        unsafe {
+            asm!(
+                "rdrand {val}",
+                "setc {ok}",
+                val = out(reg) val,
+                ok  = out(reg_byte) ok,
+                options(nomem, nostack),
+            );
+        }
+        if ok != 0 { return Some(val); }
+    }
+    None
+}
+
+/// One RDSEED draw.  RDSEED runs dry far more easily than RDRAND, so it
+/// gets more retries with a pause between them.
+fn rdseed() -> Option<u64> {
+    for _ in 0..100 {
+        let val: u64;
+        let ok:  u8;
+        unsafe {
+            asm!(
+                "rdseed {val}",
+                "setc {ok}",
+                val = out(reg) val,
+                ok  = out(reg_byte) ok,
+                options(nomem, nostack),
+            );
+        }
+        if ok != 0 { return Some(val); }
+        core::hint::spin_loop();
+    }
+    None
+}
+
+/// TSC deltas around port I/O — noisy enough to be useful on their own and
+/// always mixed in, even when the CPU has a hardware generator.
+fn jitter_word() -> u64 {
+    let mut acc = unsafe { timer::rdtsc() };
+    for _ in 0..8 {
+        let t0 = unsafe { timer::rdtsc() };
+        unsafe { asm!("in al, 0x80", out("al") _, options(nomem, nostack)); }
+        let dt = unsafe { timer::rdtsc() }.wrapping_sub(t0);
+        acc = acc.rotate_left(7) ^ dt;
+    }
+    acc ^ unsafe { timer::get_ticks() }.rotate_left(32)
+}
+
+/// Gather a seed word from whatever entropy the machine offers.
+fn seed_word() -> u64 {
+    let hw = unsafe {
+        if HW_SEED { rdseed().or_else(rdrand) }
+        else if HW_RNG { rdrand() }
+        else { None }
+    };
+    hw.unwrap_or(0) ^ jitter_word()
+}
+
+// ── ChaCha20 ──────────────────────────────────────────────────────────────
+
+#[inline(always)]
+fn quarter(s: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    s[a] = s[a].wrapping_add(s[b]); s[d] ^= s[a]; s[d] = s[d].rotate_left(16);
+    s[c] = s[c].wrapping_add(s[d]); s[b] ^= s[c]; s[b] = s[b].rotate_left(12);
+    s[a] = s[a].wrapping_add(s[b]); s[d] ^= s[a]; s[d] = s[d].rotate_left(8);
+    s[c] = s[c].wrapping_add(s[d]); s[b] ^= s[c]; s[b] = s[b].rotate_left(7);
+}
+
+/// The ChaCha20 block function (RFC 8439 layout: 32-bit block counter,
+/// 96-bit nonce; the nonce is always zero here since every key is used for
+/// a single request).
+fn chacha_block(key: &[u32; 8], counter: u32, out: &mut [u8; 64]) {
+    let mut init = [0u32; 16];
+    init[..4].copy_from_slice(&[0x6170_7865, 0x3320_646E, 0x7962_2D32, 0x6B20_6574]);
+    init[4..12].copy_from_slice(key);
+    init[12] = counter;
+    let mut s = init;
+    for _ in 0..10 {
+        quarter(&mut s, 0, 4,  8, 12);
+        quarter(&mut s, 1, 5,  9, 13);
+        quarter(&mut s, 2, 6, 10, 14);
+        quarter(&mut s, 3, 7, 11, 15);
+        quarter(&mut s, 0, 5, 10, 15);
+        quarter(&mut s, 1, 6, 11, 12);
+        quarter(&mut s, 2, 7,  8, 13);
+        quarter(&mut s, 3, 4,  9, 14);
+    }
+    for i in 0..16 {
+        out[i * 4..i * 4 + 4].copy_from_slice(&s[i].wrapping_add(init[i]).to_le_bytes());
+    }
+}
+
+/// Replace the key with the first half of block 0 under the current key
+/// and return the second half.
+unsafe fn rekey() -> [u8; 32] {
+    let mut block = [0u8; 64];
+    let key = &raw mut KEY;
+    chacha_block(unsafe { &*key }, 0, &mut block);
+    for i in 0..8 {
+        unsafe { (*key)[i] = u32::from_le_bytes(block[i * 4..i * 4 + 4].try_into().unwrap()); }
+    }
+    let mut rest = [0u8; 32];
+    rest.copy_from_slice(&block[32..]);
+    block.fill(0);
+    rest
+}
+
+/// Fold `words` into the key and rekey so they diffuse through all of it.
+unsafe fn absorb(words: &[u32]) {
+    let key = &raw mut KEY;
+    for (i, w) in words.iter().enumerate() {
+        unsafe { (*key)[i % 8] ^= w.rotate_left((i / 8) as u32 * 13); }
+    }
+    unsafe {
+        let _ = rekey();
+        AVAIL = 0;
+        (*(&raw mut BUF)).fill(0);
+    }
+}
+
+/// Refill `BUF`, reseeding first when the interval is up.
+unsafe fn refill() {
+    unsafe {
+        REFILLS = REFILLS.wrapping_add(1);
+        if REFILLS % RESEED_INTERVAL == 0 { reseed(); }
+        BUF = rekey();
+        AVAIL = 32;
+    }
+}
+
+/// Mix one fresh seed word per key word into the generator.
+unsafe fn reseed() {
+    let mut words = [0u32; 8];
+    for pair in words.chunks_mut(2) {
+        let w = seed_word();
+        pair[0] = w as u32;
+        pair[1] = (w >> 32) as u32;
+    }
+    unsafe { absorb(&words); }
+}
+
+// ── Public interface ──────────────────────────────────────────────────────
+
+/// Seed the generator.  Called once from boot; later callers seed lazily.
+pub fn init() {
+    unsafe {
+        HW_RNG  = has_rdrand();
+        HW_SEED = has_rdseed();
+        let (_, min, sec) = crate::kernel::rtc::read_time();
+        let mut words = [0u32; 16];
+        for pair in words.chunks_mut(2) {
+            let w = seed_word();
+            pair[0] = w as u32;
+            pair[1] = (w >> 32) as u32;
+        }
+        words[15] ^= ((min as u32) << 8) | sec as u32;
+        absorb(&words);
+        SEEDED = true;
+    }
+}
+
+/// True if boot seeding found a hardware RNG.
+pub fn hardware_backed() -> bool { unsafe { HW_RNG || HW_SEED } }
+
+/// True if the CPU has RDSEED (reported at boot).
+pub fn has_seed_instruction() -> bool { unsafe { HW_SEED } }
+
+/// Mix caller-supplied bytes (writes to `/dev/random`) into the key.  They
+/// can only add entropy, never replace what is already there.
+pub fn add_entropy(data: &[u8]) {
+    unsafe { if !SEEDED { init(); } }
+    for chunk in data.chunks(32) {
+        let mut words = [0u32; 9];
+        for (i, b) in chunk.iter().enumerate() {
+            words[i / 4] |= (*b as u32) << ((i % 4) * 8);
+        }
+        words[8] = unsafe { timer::rdtsc() } as u32;
+        unsafe { absorb(&words); }
+    }
+}
+
+/// Fill `buf` with random bytes.
+pub fn fill(buf: &mut [u8]) {
+    unsafe {
+        if !SEEDED { init(); }
+        let mut done = 0;
+        // Bulk: whole blocks 1.. under the current key, then erase it.
+        if buf.len() - done >= 64 {
+            let key = *(&raw const KEY);
+            let mut block = [0u8; 64];
+            let mut counter = 1u32;
+            while buf.len() - done >= 64 {
+                chacha_block(&key, counter, &mut block);
+                buf[done..done + 64].copy_from_slice(&block);
+                done += 64;
+                counter = counter.wrapping_add(1);
+                if counter == 0 { break; }
+            }
+            block.fill(0);
+            refill();
+        }
+        while done < buf.len() {
+            if AVAIL == 0 { refill(); }
+            let b   = &mut *(&raw mut BUF);
+            let off = 32 - AVAIL;
+            let n   = AVAIL.min(buf.len() - done);
+            buf[done..done + n].copy_from_slice(&b[off..off + n]);
+            b[off..off + n].fill(0);
+            AVAIL -= n;
+            done  += n;
+        }
+    }
+}
+
+/// Next 64 random bits.
+pub fn next_u64() -> u64 {
+    let mut b = [0u8; 8];
+    fill(&mut b);
+    u64::from_le_bytes(b)
+}
+
+/// Uniform value in `0..bound` (`bound` must be non-zero).
+pub fn below(bound: u64) -> u64 {
+    // Rejection sampling keeps the distribution flat for non-power-of-two bounds.
+    let zone = u64::MAX - (u64::MAX % bound);
+    loop {
+        let v = next_u64();
+        if v < zone { return v % bound; }
+    }
+}
@@ -146,6 +146,11 @@ impl SerialPort {
         }
     }
 
+    /// Raise IRQ 4 whenever received data is available.
+    pub unsafe fn enable_rx_interrupt(&self) {
+        unsafe { self.outb(INT_ENABLE_REG, 0x01); }
+    }
+
     /// Low-level port I/O functions
     #[inline]
     unsafe fn outb(&self, reg: u16, value: u8) {
@@ -186,6 +191,15 @@ impl SerialPort {
 // Global serial port instance
 pub static SERIAL_PORT: SerialPort = SerialPort::new(SERIAL_PORT_BASE);
 
+/// COM1 interrupt (IRQ 4): drain the receive FIFO into `/dev/ttyS0`.
+pub unsafe fn handle_interrupt() {
+    unsafe {
+        while let Some(b) = SERIAL_PORT.read_byte() {
+            crate::kernel::tty::input(crate::kernel::tty::TTY_S0, b);
+        }
+    }
+}
+
 // Convenience macros for logging
 #[macro_export]
 macro_rules! serial_print {
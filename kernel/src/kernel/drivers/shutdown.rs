@@ -6,6 +6,10 @@
 //!   2. QEMU / Bochs ISA debug exit port (0x604 / 0xB004)
 //!   3. VirtualBox ACPI control port (0x4004)
 //!   4. Fallback: disable interrupts and halt
+//!
+//! The desktop's shutdown and reboot actions go through init instead
+//! (`request_poweroff` / `request_reboot`), so services are stopped in order
+//! before init itself calls `reboot(2)`.
 
 use core::arch::asm;
 use crate::kernel::serial::SERIAL_PORT;
@@ -140,6 +144,7 @@ unsafe fn outb(port: u16, val: u8) {
 pub fn poweroff() -> ! {
     unsafe {
         SERIAL_PORT.write_str("OxideOS: shutting down...\n");
+        crate::kernel::slab::report_leaks();
 
         // Try hypervisor-specific ports first — these are safe I/O writes and
         // don't touch ACPI tables in physical memory that may not be HHDM-mapped.
@@ -165,6 +170,7 @@ pub fn poweroff() -> ! {
 pub fn reboot() -> ! {
     unsafe {
         SERIAL_PORT.write_str("OxideOS: rebooting...\n");
+        crate::kernel::slab::report_leaks();
         for _ in 0..0xFF_FFFFu32 {
             let status: u8;
             asm!("in al, 0x64", out("al") status, options(nostack, nomem));
@@ -175,3 +181,32 @@ pub fn reboot() -> ! {
         loop { asm!("hlt"); }
     }
 }
+
+/// Stop the machine without powering it off (`reboot(LINUX_REBOOT_CMD_HALT)`).
+pub fn halt() -> ! {
+    unsafe {
+        SERIAL_PORT.write_str("OxideOS: system halted\n");
+        crate::kernel::slab::report_leaks();
+        asm!("cli");
+        loop { asm!("hlt"); }
+    }
+}
+
+// ── Orderly shutdown through init ─────────────────────────────────────────────
+
+/// Ask init to stop every service and power off (SIGUSR2, as BusyBox init
+/// takes it).  Without an init that handles it, power off straight away.
+pub fn request_poweroff() {
+    if !signal_init(crate::kernel::scheduler::SIGUSR2) { poweroff(); }
+}
+
+/// Ask init to stop every service and reboot (SIGTERM).
+pub fn request_reboot() {
+    if !signal_init(crate::kernel::scheduler::SIGTERM) { reboot(); }
+}
+
+fn signal_init(signum: u8) -> bool {
+    use crate::kernel::scheduler::{init_ignores, reaper, send_signal};
+    let pid = reaper();
+    pid != 0 && !init_ignores(pid, signum) && unsafe { send_signal(pid, signum) }
+}
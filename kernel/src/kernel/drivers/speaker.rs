@@ -0,0 +1,137 @@
+//! PC speaker driven by PIT channel 2.
+//!
+//! Channel 2 of the 8254 (see `timer`) runs as a square-wave generator
+//! (mode 3) whose output is gated onto the speaker through bits 0 (gate)
+//! and 1 (speaker data) of port 0x61.  A tone is just a divisor of the
+//! 1.193182 MHz PIT clock; `beep` arms a deadline in timer ticks and the
+//! timer interrupt calls `tick()` to silence the speaker when it passes.
+//!
+//! Two switches, flipped from Quick Settings, decide which alerts are
+//! audible: `BELL` covers the terminal bell (`\a` on a console), `EVENTS`
+//! covers GUI notifications.  Tones asked for explicitly through
+//! `KIOCSOUND` / `KDMKTONE` always sound.
+//!
+//! Under QEMU the speaker needs an audio backend:
+//! `-audiodev pa,id=snd0 -machine pcspk-audiodev=snd0`.
+
+use core::arch::asm;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use crate::kernel::timer;
+
+const PIT_CH2:  u16 = 0x42;
+const PIT_CMD:  u16 = 0x43;
+const PORT_B:   u16 = 0x61;
+
+/// Channel 2, lo/hi byte, mode 3 (square wave), binary.
+const CMD_CH2_SQUARE: u8 = 0xB6;
+/// Port 0x61: timer 2 gate and speaker data enable.
+const SPEAKER_ON: u8 = 0x03;
+
+pub const PIT_HZ: u32 = 1_193_182;
+
+/// Console bell, as on Linux: 750 Hz for 125 ms.
+const BELL_HZ: u32 = 750;
+const BELL_MS: u32 = 125;
+/// Notification chime.
+const EVENT_HZ: u32 = 1000;
+const EVENT_MS: u32 = 60;
+
+/// Terminal bell enabled ("System sounds").
+pub static BELL: AtomicBool = AtomicBool::new(true);
+/// Notification beeps enabled ("Event sounds").
+pub static EVENTS: AtomicBool = AtomicBool::new(true);
+
+/// Tick at which the current tone stops; 0 = none pending.
+static STOP_AT: AtomicU64 = AtomicU64::new(0);
+
+unsafe fn inb(port: u16) -> u8 {
+    let v: u8;
+    unsafe { asm!("in al, dx", out("al") v, in("dx") port, options(nomem, nostack)); }
+    v
+}
+
+unsafe fn outb(port: u16, v: u8) {
+    unsafe { asm!("out dx, al", in("dx") port, in("al") v, options(nomem, nostack)); }
+}
+
+/// Run `f` with interrupts off so `tick()` cannot interleave with a
+/// read-modify-write of port 0x61.
+fn without_interrupts<T>(f: impl FnOnce() -> T) -> T {
+    let flags: u64;
+    unsafe { asm!("pushfq; pop {}; cli", out(reg) flags, options(nomem)); }
+    let r = f();
+    if flags & (1 << 9) != 0 {
+        unsafe { asm!("sti", options(nomem, nostack)); }
+    }
+    r
+}
+
+unsafe fn start(divisor: u16) {
+    unsafe {
+        outb(PIT_CMD, CMD_CH2_SQUARE);
+        outb(PIT_CH2, divisor as u8);
+        outb(PIT_CH2, (divisor >> 8) as u8);
+        let b = inb(PORT_B);
+        if b & SPEAKER_ON != SPEAKER_ON { outb(PORT_B, b | SPEAKER_ON); }
+    }
+}
+
+unsafe fn stop() {
+    unsafe {
+        let b = inb(PORT_B);
+        outb(PORT_B, b & !SPEAKER_ON);
+    }
+}
+
+/// Sound the speaker with PIT divisor `count` until told otherwise
+/// (`KIOCSOUND`).  A count of 0 silences it.
+pub fn sound(count: u32) {
+    without_interrupts(|| unsafe {
+        STOP_AT.store(0, Ordering::Relaxed);
+        match count {
+            0 => stop(),
+            c => start(c.min(0xFFFF) as u16),
+        }
+    });
+}
+
+/// Sound divisor `count` for `ms` milliseconds (`KDMKTONE`).  Either being
+/// 0 silences the speaker.  A new tone replaces one still playing.
+pub fn tone(count: u32, ms: u32) {
+    if count == 0 || ms == 0 {
+        sound(0);
+        return;
+    }
+    without_interrupts(|| unsafe {
+        // Round up to whole ticks (100 Hz) so short tones are not lost.
+        let ticks = (ms as u64).div_ceil(10).max(1);
+        STOP_AT.store(timer::get_ticks() + ticks, Ordering::Relaxed);
+        start(count.min(0xFFFF) as u16);
+    });
+}
+
+/// Beep at `hz` for `ms` milliseconds.
+pub fn beep(hz: u32, ms: u32) {
+    if hz == 0 { return; }
+    tone(PIT_HZ / hz, ms);
+}
+
+/// The terminal bell, unless system sounds are off.
+pub fn bell() {
+    if BELL.load(Ordering::Relaxed) { beep(BELL_HZ, BELL_MS); }
+}
+
+/// The notification chime, unless event sounds are off.
+pub fn event() {
+    if EVENTS.load(Ordering::Relaxed) { beep(EVENT_HZ, EVENT_MS); }
+}
+
+/// Called from the timer interrupt: end a timed tone.
+pub unsafe fn tick() {
+    let at = STOP_AT.load(Ordering::Relaxed);
+    if at != 0 && unsafe { timer::get_ticks() } >= at {
+        STOP_AT.store(0, Ordering::Relaxed);
+        unsafe { stop(); }
+    }
+}
@@ -95,4 +95,27 @@ pub unsafe fn rdtsc() -> u64 {
         options(nostack, nomem)
     );
     ((high as u64) << 32) | (low as u64)
-}
\ No newline at end of file
+}
+/// `(tick, TSC)` at the first timer interrupt; the base for [`tsc_hz`].
+static mut TSC_BASE: (u64, u64) = (0, 0);
+
+/// Called by the timer ISR on every tick; the first call fixes the TSC
+/// calibration base.
+pub unsafe fn calibrate_tsc() {
+    let base = unsafe { &mut *(&raw mut TSC_BASE) };
+    if base.1 == 0 {
+        *base = unsafe { (TIMER_TICKS, rdtsc()) };
+    }
+}
+
+/// TSC frequency in Hz, measured against the PIT since the first tick
+/// (assumes 100Hz).  Reports 1 GHz until 100 ms have been measured.
+pub unsafe fn tsc_hz() -> u64 {
+    let (tick0, tsc0) = unsafe { TSC_BASE };
+    let ticks = unsafe { TIMER_TICKS } - tick0;
+    if tsc0 == 0 || ticks < 10 {
+        return 1_000_000_000;
+    }
+    let hz = (unsafe { rdtsc() } - tsc0) as u128 * 100 / ticks as u128;
+    (hz as u64).max(1)
+}
@@ -0,0 +1,471 @@
+//! virtio-pci transport and split virtqueues, shared by the virtio-net
+//! (`net::virtio`) and virtio-blk (`virtio_blk`) drivers.
+//!
+//! A virtio device is reached through one of two register layouts:
+//!   legacy — transitional devices (0x1000 + type - 1): the virtio 0.9.5
+//!            header in I/O BAR 0, queues placed by page frame number.
+//!   modern — virtio 1.x (0x1040 + type, and transitional devices that
+//!            carry the capabilities too): vendor-specific PCI capabilities
+//!            point at the common, notify, ISR and device-config windows
+//!            in memory BARs.  Requires VIRTIO_F_VERSION_1.
+//! Modern is used whenever the capabilities are there.
+//!
+//! Each queue is a split ring in DMA memory below 4 GiB, laid out the
+//! legacy way — descriptor table, available ring, used ring on the next
+//! page boundary — which also meets the modern alignment rules.  Devices
+//! signal completions on their INTx line; reading the ISR status register
+//! acknowledges it.  MSI-X is never enabled.
+
+extern crate alloc;
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::arch::asm;
+use core::ptr::{read_volatile, write_volatile};
+use core::sync::atomic::{fence, Ordering};
+use crate::kernel::net::pci::{self, PciDevice};
+use crate::kernel::paging_allocator::{alloc_dma_frames, get_hhdm_offset, map_mmio};
+
+pub const VENDOR: u16 = 0x1AF4;
+
+/// Device types (modern device ID = 0x1040 + type).
+pub const TYPE_NET: u16 = 1;
+pub const TYPE_BLK: u16 = 2;
+
+pub const F_VERSION_1: u64 = 1 << 32;
+
+// Device status.
+const STATUS_ACKNOWLEDGE: u8 = 1;
+const STATUS_DRIVER:      u8 = 2;
+const STATUS_DRIVER_OK:   u8 = 4;
+const STATUS_FEATURES_OK: u8 = 8;
+const STATUS_FAILED:      u8 = 0x80;
+
+// Legacy header in I/O BAR 0.
+const L_HOST_FEATURES:  u16 = 0x00;
+const L_GUEST_FEATURES: u16 = 0x04;
+const L_QUEUE_PFN:      u16 = 0x08;
+const L_QUEUE_SIZE:     u16 = 0x0C;
+const L_QUEUE_SELECT:   u16 = 0x0E;
+const L_QUEUE_NOTIFY:   u16 = 0x10;
+const L_STATUS:         u16 = 0x12;
+const L_ISR:            u16 = 0x13;
+/// Device-specific config (this offset holds while MSI-X is off).
+const L_CONFIG:         u16 = 0x14;
+
+// Modern common configuration window.
+const C_DEVICE_FEATURE_SELECT: u64 = 0x00;
+const C_DEVICE_FEATURE:        u64 = 0x04;
+const C_DRIVER_FEATURE_SELECT: u64 = 0x08;
+const C_DRIVER_FEATURE:        u64 = 0x0C;
+const C_STATUS:                u64 = 0x14;
+const C_QUEUE_SELECT:          u64 = 0x16;
+const C_QUEUE_SIZE:            u64 = 0x18;
+const C_QUEUE_ENABLE:          u64 = 0x1C;
+const C_QUEUE_NOTIFY_OFF:      u64 = 0x1E;
+const C_QUEUE_DESC:            u64 = 0x20;
+const C_QUEUE_DRIVER:          u64 = 0x28;
+const C_QUEUE_DEVICE:          u64 = 0x30;
+
+// virtio_pci_cap.cfg_type
+const CAP_VENDOR: u8 = 0x09;
+const CFG_COMMON: u8 = 1;
+const CFG_NOTIFY: u8 = 2;
+const CFG_ISR:    u8 = 3;
+const CFG_DEVICE: u8 = 4;
+
+const DESC_NEXT:  u16 = 1;
+const DESC_WRITE: u16 = 2;
+/// Available-ring flag: don't interrupt when buffers are used.
+const AVAIL_NO_INTERRUPT: u16 = 1;
+
+/// Largest ring set up on a modern device; legacy devices fix their own.
+const MAX_QUEUE: u16 = 256;
+
+// ── Port I/O ──────────────────────────────────────────────────────────────
+
+unsafe fn inb(port: u16) -> u8 {
+    let v: u8;
+    unsafe { asm!("in al, dx", out("al") v, in("dx") port, options(nomem, nostack)); }
+    v
+}
+unsafe fn inw(port: u16) -> u16 {
+    let v: u16;
+    unsafe { asm!("in ax, dx", out("ax") v, in("dx") port, options(nomem, nostack)); }
+    v
+}
+unsafe fn inl(port: u16) -> u32 {
+    let v: u32;
+    unsafe { asm!("in eax, dx", out("eax") v, in("dx") port, options(nomem, nostack)); }
+    v
+}
+unsafe fn outb(port: u16, v: u8) {
+    unsafe { asm!("out dx, al", in("dx") port, in("al") v, options(nomem, nostack)); }
+}
+unsafe fn outw(port: u16, v: u16) {
+    unsafe { asm!("out dx, ax", in("dx") port, in("ax") v, options(nomem, nostack)); }
+}
+unsafe fn outl(port: u16, v: u32) {
+    unsafe { asm!("out dx, eax", in("dx") port, in("eax") v, options(nomem, nostack)); }
+}
+
+unsafe fn rd8(addr: u64) -> u8   { unsafe { read_volatile(addr as *const u8) } }
+unsafe fn rd16(addr: u64) -> u16 { unsafe { read_volatile(addr as *const u16) } }
+unsafe fn rd32(addr: u64) -> u32 { unsafe { read_volatile(addr as *const u32) } }
+unsafe fn wr8(addr: u64, v: u8)   { unsafe { write_volatile(addr as *mut u8, v) } }
+unsafe fn wr16(addr: u64, v: u16) { unsafe { write_volatile(addr as *mut u16, v) } }
+unsafe fn wr32(addr: u64, v: u32) { unsafe { write_volatile(addr as *mut u32, v) } }
+unsafe fn wr64(addr: u64, v: u64) {
+    unsafe {
+        wr32(addr, v as u32);
+        wr32(addr + 4, (v >> 32) as u32);
+    }
+}
+
+fn virt(phys: u64) -> u64 {
+    phys + get_hhdm_offset()
+}
+
+// ── Discovery ─────────────────────────────────────────────────────────────
+
+/// Every virtio function of device type `ty`, legacy or modern.
+pub fn devices(ty: u16) -> Vec<PciDevice> {
+    let mut found = Vec::new();
+    pci::for_each_device(|d| {
+        if d.vendor == VENDOR && (d.device == 0x0FFF + ty || d.device == 0x1040 + ty) {
+            found.push(d);
+        }
+    });
+    found
+}
+
+// ── Transport ─────────────────────────────────────────────────────────────
+
+#[derive(Clone, Copy)]
+enum Regs {
+    Legacy { io: u16 },
+    /// Kernel addresses of the mapped capability windows.
+    Modern { common: u64, notify: u64, notify_mul: u32, isr: u64, device: u64 },
+}
+
+/// Where a queue's notifications go.
+#[derive(Clone, Copy)]
+enum Doorbell {
+    Port(u16),
+    Mmio(u64),
+}
+
+/// Locate and map the virtio 1.x capability windows, if the device has them.
+unsafe fn modern_regs(pci: PciDevice) -> Option<Regs> {
+    let (mut common, mut notify, mut isr, mut device) = (None, None, None, None);
+    let mut notify_mul = 0;
+    for cap in pci.capabilities(CAP_VENDOR) {
+        let rd = |off: u8| pci::read32(pci.bus, pci.dev, pci.func, cap + off);
+        let cfg_type = (rd(0) >> 24) as u8;
+        let bar = rd(4) as u8;
+        if bar > 5 { continue; }
+        let (offset, length) = (rd(8) as u64, rd(12) as usize);
+        let window = || unsafe { map_mmio(pci.mem_bar(bar)? + offset, length.max(1)) };
+        match cfg_type {
+            CFG_COMMON if common.is_none() => common = window(),
+            CFG_NOTIFY if notify.is_none() => {
+                notify = window();
+                notify_mul = rd(16);
+            }
+            CFG_ISR    if isr.is_none()    => isr = window(),
+            CFG_DEVICE if device.is_none() => device = window(),
+            _ => {}
+        }
+    }
+    Some(Regs::Modern { common: common?, notify: notify?, notify_mul, isr: isr?, device: device.unwrap_or(0) })
+}
+
+/// One virtio function and its register layout.
+pub struct Device {
+    regs: Regs,
+}
+
+impl Device {
+    /// Take over `pci`, through the modern interface when it has one.
+    pub unsafe fn new(pci: PciDevice) -> Option<Device> {
+        let regs = unsafe { modern_regs(pci) }
+            .or_else(|| pci.io_bar(0).map(|io| Regs::Legacy { io }))?;
+        pci.enable_bus_mastering();
+        Some(Device { regs })
+    }
+
+    pub fn is_modern(&self) -> bool {
+        matches!(self.regs, Regs::Modern { .. })
+    }
+
+    fn status(&self) -> u8 {
+        unsafe {
+            match self.regs {
+                Regs::Legacy { io }         => inb(io + L_STATUS),
+                Regs::Modern { common, .. } => rd8(common + C_STATUS),
+            }
+        }
+    }
+
+    fn set_status(&self, s: u8) {
+        unsafe {
+            match self.regs {
+                Regs::Legacy { io }         => outb(io + L_STATUS, s),
+                Regs::Modern { common, .. } => wr8(common + C_STATUS, s),
+            }
+        }
+    }
+
+    fn device_features(&self) -> u64 {
+        unsafe {
+            match self.regs {
+                Regs::Legacy { io } => inl(io + L_HOST_FEATURES) as u64,
+                Regs::Modern { common, .. } => {
+                    wr32(common + C_DEVICE_FEATURE_SELECT, 0);
+                    let lo = rd32(common + C_DEVICE_FEATURE) as u64;
+                    wr32(common + C_DEVICE_FEATURE_SELECT, 1);
+                    lo | (rd32(common + C_DEVICE_FEATURE) as u64) << 32
+                }
+            }
+        }
+    }
+
+    fn set_driver_features(&self, f: u64) {
+        unsafe {
+            match self.regs {
+                Regs::Legacy { io } => outl(io + L_GUEST_FEATURES, f as u32),
+                Regs::Modern { common, .. } => {
+                    wr32(common + C_DRIVER_FEATURE_SELECT, 0);
+                    wr32(common + C_DRIVER_FEATURE, f as u32);
+                    wr32(common + C_DRIVER_FEATURE_SELECT, 1);
+                    wr32(common + C_DRIVER_FEATURE, (f >> 32) as u32);
+                }
+            }
+        }
+    }
+
+    /// Reset the device and agree on the features in `wanted` it offers
+    /// (plus VERSION_1 on the modern interface).  Returns the agreed set,
+    /// or `None` if the device turns it down.  Queues come next.
+    pub fn negotiate(&self, wanted: u64) -> Option<u64> {
+        self.set_status(0);
+        for _ in 0..1_000_000 {
+            if self.status() == 0 { break; }
+            core::hint::spin_loop();
+        }
+        self.set_status(STATUS_ACKNOWLEDGE);
+        self.set_status(STATUS_ACKNOWLEDGE | STATUS_DRIVER);
+
+        let offered = self.device_features();
+        let mut features = offered & wanted;
+        if self.is_modern() {
+            if offered & F_VERSION_1 == 0 {
+                self.fail();
+                return None;
+            }
+            features |= F_VERSION_1;
+        }
+        self.set_driver_features(features);
+
+        if self.is_modern() {
+            self.set_status(STATUS_ACKNOWLEDGE | STATUS_DRIVER | STATUS_FEATURES_OK);
+            if self.status() & STATUS_FEATURES_OK == 0 {
+                self.fail();
+                return None;
+            }
+        }
+        Some(features)
+    }
+
+    /// Set up queues first: the device may use them from here on.
+    pub fn driver_ok(&self) {
+        self.set_status(self.status() | STATUS_DRIVER_OK);
+    }
+
+    /// Give up on the device.
+    pub fn fail(&self) {
+        self.set_status(self.status() | STATUS_FAILED);
+    }
+
+    pub fn config8(&self, off: u16) -> u8 {
+        unsafe {
+            match self.regs {
+                Regs::Legacy { io }         => inb(io + L_CONFIG + off),
+                Regs::Modern { device, .. } => rd8(device + off as u64),
+            }
+        }
+    }
+
+    pub fn config32(&self, off: u16) -> u32 {
+        unsafe {
+            match self.regs {
+                Regs::Legacy { io }         => inl(io + L_CONFIG + off),
+                Regs::Modern { device, .. } => rd32(device + off as u64),
+            }
+        }
+    }
+
+    pub fn config64(&self, off: u16) -> u64 {
+        self.config32(off) as u64 | (self.config32(off + 4) as u64) << 32
+    }
+
+    /// Read (and so clear) the ISR status.  True if the device interrupted.
+    pub fn ack_interrupt(&self) -> bool {
+        unsafe {
+            match self.regs {
+                Regs::Legacy { io }      => inb(io + L_ISR) != 0,
+                Regs::Modern { isr, .. } => rd8(isr) != 0,
+            }
+        }
+    }
+
+    /// Allocate and enable queue `index`.  `None` if the device has no
+    /// such queue or memory runs out.
+    pub unsafe fn queue(&self, index: u16) -> Option<Virtqueue> {
+        match self.regs {
+            Regs::Legacy { io } => unsafe {
+                outw(io + L_QUEUE_SELECT, index);
+                let size = inw(io + L_QUEUE_SIZE);
+                if size == 0 || inl(io + L_QUEUE_PFN) != 0 { return None; }
+                let q = Virtqueue::new(index, size, Doorbell::Port(io + L_QUEUE_NOTIFY))?;
+                outl(io + L_QUEUE_PFN, (q.phys >> 12) as u32);
+                Some(q)
+            },
+            Regs::Modern { common, notify, notify_mul, .. } => unsafe {
+                wr16(common + C_QUEUE_SELECT, index);
+                let max = rd16(common + C_QUEUE_SIZE);
+                if max == 0 { return None; }
+                let size = max.min(MAX_QUEUE);
+                let off = rd16(common + C_QUEUE_NOTIFY_OFF) as u64 * notify_mul as u64;
+                let q = Virtqueue::new(index, size, Doorbell::Mmio(notify + off))?;
+                wr16(common + C_QUEUE_SIZE, size);
+                wr64(common + C_QUEUE_DESC, q.phys);
+                wr64(common + C_QUEUE_DRIVER, q.phys + q.avail_off);
+                wr64(common + C_QUEUE_DEVICE, q.phys + q.used_off);
+                wr16(common + C_QUEUE_ENABLE, 1);
+                Some(q)
+            },
+        }
+    }
+}
+
+// ── Split virtqueue ───────────────────────────────────────────────────────
+
+/// A split ring.  Requests are descriptor chains tagged with a caller
+/// token, returned by `pop` when the device is done with them.
+pub struct Virtqueue {
+    index:     u16,
+    size:      u16,
+    /// Physical base: descriptor table, then the two rings.
+    phys:      u64,
+    avail_off: u64,
+    used_off:  u64,
+    doorbell:  Doorbell,
+    free_head: u16,
+    num_free:  u16,
+    avail_idx: u16,
+    last_used: u16,
+    /// Caller token per chain head.
+    tokens:    Vec<u16>,
+}
+
+impl Virtqueue {
+    unsafe fn new(index: u16, size: u16, doorbell: Doorbell) -> Option<Virtqueue> {
+        let n = size as u64;
+        let avail_off = 16 * n;
+        let used_off = (avail_off + 6 + 2 * n).next_multiple_of(4096);
+        let pages = (used_off + 6 + 8 * n).div_ceil(4096) as usize;
+        let phys = unsafe { alloc_dma_frames(pages)? };
+
+        // Thread every descriptor onto the free list.
+        let desc = virt(phys);
+        for i in 0..size {
+            unsafe { wr16(desc + i as u64 * 16 + 14, i.wrapping_add(1)); }
+        }
+        Some(Virtqueue {
+            index, size, phys, avail_off, used_off, doorbell,
+            free_head: 0,
+            num_free:  size,
+            avail_idx: 0,
+            last_used: 0,
+            tokens:    vec![0; size as usize],
+        })
+    }
+
+    pub fn size(&self) -> u16 { self.size }
+
+    /// Descriptors not tied up in requests.
+    pub fn num_free(&self) -> u16 { self.num_free }
+
+    /// Ask the device not to interrupt when it uses this queue's buffers
+    /// (it may anyway; the hint only saves interrupts).
+    pub fn disable_interrupts(&mut self) {
+        unsafe { wr16(virt(self.phys + self.avail_off), AVAIL_NO_INTERRUPT); }
+    }
+
+    /// Chain `bufs` — (physical address, length, device writes it) — into
+    /// one request tagged `token` and make it available.  The device only
+    /// looks once `kick` is called.  False if descriptors run short.
+    pub fn push(&mut self, bufs: &[(u64, u32, bool)], token: u16) -> bool {
+        if bufs.is_empty() || bufs.len() > self.num_free as usize { return false; }
+        let desc = virt(self.phys);
+        let head = self.free_head;
+        let mut i = head;
+        for (n, &(addr, len, writable)) in bufs.iter().enumerate() {
+            let d = desc + i as u64 * 16;
+            let next = unsafe { rd16(d + 14) };
+            let mut flags = if writable { DESC_WRITE } else { 0 };
+            if n + 1 < bufs.len() { flags |= DESC_NEXT; }
+            unsafe {
+                wr64(d, addr);
+                wr32(d + 8, len);
+                wr16(d + 12, flags);
+            }
+            if n + 1 < bufs.len() { i = next; } else { self.free_head = next; }
+        }
+        self.num_free -= bufs.len() as u16;
+        self.tokens[head as usize] = token;
+
+        let avail = virt(self.phys + self.avail_off);
+        unsafe { wr16(avail + 4 + (self.avail_idx % self.size) as u64 * 2, head); }
+        self.avail_idx = self.avail_idx.wrapping_add(1);
+        fence(Ordering::SeqCst);
+        unsafe { wr16(avail + 2, self.avail_idx); }
+        true
+    }
+
+    /// Notify the device of new requests.
+    pub fn kick(&self) {
+        fence(Ordering::SeqCst);
+        unsafe {
+            match self.doorbell {
+                Doorbell::Port(port) => outw(port, self.index),
+                Doorbell::Mmio(addr) => wr16(addr, self.index),
+            }
+        }
+    }
+
+    /// Take the next request the device has finished: its token and the
+    /// number of bytes the device wrote.
+    pub fn pop(&mut self) -> Option<(u16, u32)> {
+        let used = virt(self.phys + self.used_off);
+        if unsafe { rd16(used + 2) } == self.last_used { return None; }
+        fence(Ordering::SeqCst);
+        let e = used + 4 + (self.last_used % self.size) as u64 * 8;
+        let (head, len) = unsafe { (rd32(e) as u16, rd32(e + 4)) };
+        self.last_used = self.last_used.wrapping_add(1);
+
+        // Return the chain to the free list.
+        let desc = virt(self.phys);
+        let mut i = head;
+        let mut count = 1;
+        while unsafe { rd16(desc + i as u64 * 16 + 12) } & DESC_NEXT != 0 {
+            i = unsafe { rd16(desc + i as u64 * 16 + 14) };
+            count += 1;
+        }
+        unsafe { wr16(desc + i as u64 * 16 + 14, self.free_head); }
+        self.free_head = head;
+        self.num_free += count;
+        Some((self.tokens[head as usize], len))
+    }
+}
@@ -0,0 +1,329 @@
+//! virtio-blk disks (QEMU `-drive if=virtio` or `-device virtio-blk-pci`),
+//! on the virtio-pci transport in `virtio`.
+//!
+//! A request is a descriptor chain: a 16-byte header (type, sector), the
+//! data, and a status byte the device writes last.  The data descriptors
+//! come straight from the physical pages behind the caller's buffer, one
+//! per contiguous run; a per-disk bounce buffer covers buffers that cannot
+//! be translated or are too fragmented for the device's segment limit.
+//!
+//! A transfer is cut into 64 KiB requests and up to `MAX_REQS` of them are
+//! queued before a single notify.  The device raises its INTx line as they
+//! complete; the waiter sleeps in `hlt` until the used ring has them all,
+//! or polls it when running with interrupts masked (inside a syscall).
+//!
+//! Disks are added to `ata::DISKS`, so `/dev/hdX`, FAT, ext2, swap and the
+//! record store reach them through `ata::read_sector` like any other disk.
+//! The n-th virtio disk takes slot n when that is free.
+
+use core::arch::asm;
+use core::ptr::{read_volatile, write_volatile};
+use crate::kernel::ata::{self, AtaDisk, Dma};
+use crate::kernel::paging_allocator::{
+    alloc_dma_frames, free_phys_frames, get_hhdm_offset, kernel_virt_to_phys,
+};
+use crate::kernel::serial::SERIAL_PORT;
+use crate::kernel::timer;
+use crate::kernel::virtio::{self, Device, Virtqueue};
+
+// Request types.
+const T_IN:     u32 = 0;
+const T_OUT:    u32 = 1;
+const T_FLUSH:  u32 = 4;
+const T_GET_ID: u32 = 8;
+
+const S_OK: u8 = 0;
+
+// Feature bits.
+const F_SEG_MAX: u64 = 1 << 2;
+const F_RO:      u64 = 1 << 5;
+const F_FLUSH:   u64 = 1 << 9;
+
+// Device config.
+const CFG_CAPACITY: u16 = 0;
+const CFG_SEG_MAX:  u16 = 12;
+
+const MAX_DISKS: usize = 4;
+const SECTOR: usize = 512;
+/// Sectors per request.
+const CHUNK_SECTORS: usize = 128;
+/// Data descriptors per request: 64 KiB of 4 KiB pages, plus one for a
+/// buffer that does not start on a page.
+const SEG_MAX: usize = CHUNK_SECTORS * SECTOR / 4096 + 1;
+/// Requests in flight per transfer.
+const MAX_REQS: usize = 16;
+/// Bytes of the request page per request: header, then status at +16.
+const REQ_SIZE: u64 = 32;
+const BOUNCE_PAGES: usize = CHUNK_SECTORS * SECTOR / 4096;
+const ID_LEN: usize = 20;
+
+/// Timeout for a batch, in timer ticks (100 Hz) or polling iterations.
+const TIMEOUT_TICKS: u64 = 500;
+const TIMEOUT_SPINS: u64 = 100_000_000;
+
+struct Disk {
+    dev:       Device,
+    queue:     Virtqueue,
+    /// Physical page of request headers and status bytes, `REQ_SIZE` each.
+    reqs:      u64,
+    /// Physical base of the bounce buffer (`BOUNCE_PAGES`).
+    bounce:    u64,
+    /// Data descriptors the device accepts per request.
+    seg_max:   usize,
+    flush:     bool,
+    read_only: bool,
+    /// A request timed out; the queue state is unknown, so stop using it.
+    dead:      bool,
+}
+
+static mut DISKS: [Option<Disk>; MAX_DISKS] = [const { None }; MAX_DISKS];
+/// IRQ lines `interrupt` is registered on.
+static mut IRQS: [u8; MAX_DISKS] = [0; MAX_DISKS];
+
+fn virt(phys: u64) -> u64 {
+    phys + get_hhdm_offset()
+}
+
+/// Append the `len` bytes at kernel address `addr` to `segs`, one entry
+/// per physically contiguous run, keeping to `max` entries.
+unsafe fn describe(segs: &mut [(u64, u32, bool)], n: &mut usize, max: usize,
+                   addr: u64, len: usize, writable: bool) -> Option<()> {
+    let first = *n;
+    let mut off = 0;
+    while off < len {
+        let v = addr + off as u64;
+        let phys = unsafe { kernel_virt_to_phys(v)? };
+        let step = (4096 - (v & 0xFFF) as usize).min(len - off);
+        match segs[*n - 1] {
+            (start, bytes, _) if *n > first && start + bytes as u64 == phys =>
+                segs[*n - 1].1 += step as u32,
+            _ => {
+                if *n - first == max { return None; }
+                segs[*n] = (phys, step as u32, writable);
+                *n += 1;
+            }
+        }
+        off += step;
+    }
+    Some(())
+}
+
+impl Disk {
+    /// Queue request `slot` of type `ty` at `sector` with `len` bytes of
+    /// data at kernel address `data` (read into it unless `ty` is OUT).
+    /// False if the data cannot be described or descriptors run short.
+    unsafe fn queue_request(&mut self, slot: usize, ty: u32, sector: u64, data: u64, len: usize) -> bool {
+        let hdr = self.reqs + slot as u64 * REQ_SIZE;
+        unsafe {
+            let h = virt(hdr) as *mut u32;
+            write_volatile(h, ty);
+            write_volatile(h.add(1), 0);
+            write_volatile(h.add(2) as *mut u64, sector);
+            write_volatile((virt(hdr) + 16) as *mut u8, 0xFF);
+        }
+        let mut segs = [(0u64, 0u32, false); SEG_MAX + 2];
+        segs[0] = (hdr, 16, false);
+        let mut n = 1;
+        if len > 0 && unsafe { describe(&mut segs, &mut n, self.seg_max, data, len, ty != T_OUT) }.is_none() {
+            return false;
+        }
+        segs[n] = (hdr + 16, 1, true);
+        n += 1;
+        self.queue.push(&segs[..n], slot as u16)
+    }
+
+    fn status(&self, slot: u16) -> u8 {
+        unsafe { read_volatile((virt(self.reqs) + slot as u64 * REQ_SIZE + 16) as *const u8) }
+    }
+
+    /// Notify the device and wait for `pending` queued requests.  True if
+    /// all of them completed with status OK.
+    unsafe fn complete(&mut self, d: usize, mut pending: usize) -> bool {
+        self.queue.kick();
+        let rflags: u64;
+        unsafe { asm!("pushfq; pop {}", out(reg) rflags, options(nomem, preserves_flags)); }
+        let irqs = rflags & (1 << 9) != 0;
+        if irqs { unsafe { asm!("cli", options(nomem, nostack)); } }
+
+        let start = unsafe { timer::get_ticks() };
+        let mut spins = 0u64;
+        let mut ok = true;
+        let done = loop {
+            while let Some((slot, _)) = self.queue.pop() {
+                ok &= self.status(slot) == S_OK;
+                pending -= 1;
+            }
+            if pending == 0 { break true; }
+            if irqs {
+                if unsafe { timer::get_ticks() } - start > TIMEOUT_TICKS { break false; }
+                // `sti` takes effect after `hlt` starts: no lost wakeup.
+                unsafe { asm!("sti; hlt; cli", options(nomem, nostack)); }
+            } else {
+                spins += 1;
+                if spins > TIMEOUT_SPINS { break false; }
+                core::hint::spin_loop();
+            }
+        };
+        if irqs { unsafe { asm!("sti", options(nomem, nostack)); } }
+
+        if !done {
+            self.dead = true;
+            unsafe {
+                SERIAL_PORT.write_str("[virtio-blk] disk ");
+                SERIAL_PORT.write_decimal(d as u32);
+                SERIAL_PORT.write_str(": request timed out, disk disabled\n");
+            }
+        }
+        done && ok
+    }
+
+    /// Run one request through the bounce buffer.
+    unsafe fn bounced(&mut self, d: usize, ty: u32, sector: u64, buf: *mut u8, len: usize) -> bool {
+        let bounce = virt(self.bounce) as *mut u8;
+        if ty == T_OUT { unsafe { core::ptr::copy_nonoverlapping(buf, bounce, len); } }
+        if !unsafe { self.queue_request(0, ty, sector, bounce as u64, len) } { return false; }
+        if !unsafe { self.complete(d, 1) } { return false; }
+        if ty != T_OUT { unsafe { core::ptr::copy_nonoverlapping(bounce, buf, len); } }
+        true
+    }
+}
+
+/// Move `count` sectors between virtio disk `d` and `buf`, starting at
+/// `lba`.  Writes are flushed from the device's cache before returning.
+pub unsafe fn transfer(d: usize, lba: u64, count: usize, buf: *mut u8, write: bool) -> bool {
+    let Some(disk) = (unsafe { (*(&raw mut DISKS)).get_mut(d) }).and_then(Option::as_mut) else {
+        return false;
+    };
+    if disk.dead || (write && disk.read_only) { return false; }
+    let ty = if write { T_OUT } else { T_IN };
+
+    let mut done = 0;
+    while done < count {
+        let mut queued = 0;
+        while done < count && queued < MAX_REQS && disk.queue.num_free() as usize >= disk.seg_max + 2 {
+            let sectors = (count - done).min(CHUNK_SECTORS);
+            let data = unsafe { buf.add(done * SECTOR) };
+            let sector = lba + done as u64;
+            if unsafe { disk.queue_request(queued, ty, sector, data as u64, sectors * SECTOR) } {
+                queued += 1;
+            } else if queued > 0 {
+                break; // let the batch finish, then bounce this one
+            } else if !unsafe { disk.bounced(d, ty, sector, data, sectors * SECTOR) } {
+                return false;
+            }
+            done += sectors;
+        }
+        if queued > 0 && !unsafe { disk.complete(d, queued) } { return false; }
+    }
+
+    if write && disk.flush {
+        if !unsafe { disk.queue_request(0, T_FLUSH, 0, 0, 0) } { return false; }
+        return unsafe { disk.complete(d, 1) };
+    }
+    true
+}
+
+unsafe fn interrupt() -> bool {
+    let mut claimed = false;
+    for disk in unsafe { (*(&raw const DISKS)).iter().flatten() } {
+        claimed |= disk.dev.ack_interrupt();
+    }
+    claimed
+}
+
+// ── Probing ───────────────────────────────────────────────────────────────
+
+/// Set up the virtio-blk function `pci` as virtio disk `d` and add it to
+/// the ata disk table.
+unsafe fn probe(d: usize, pci: crate::kernel::net::pci::PciDevice) -> bool {
+    let Some(dev) = (unsafe { Device::new(pci) }) else {
+        unsafe { SERIAL_PORT.write_str("[virtio-blk] Device has no usable BARs\n"); }
+        return false;
+    };
+    let Some(features) = dev.negotiate(F_SEG_MAX | F_RO | F_FLUSH) else {
+        unsafe { SERIAL_PORT.write_str("[virtio-blk] Feature negotiation failed\n"); }
+        return false;
+    };
+    let Some(queue) = (unsafe { dev.queue(0) }) else {
+        unsafe { SERIAL_PORT.write_str("[virtio-blk] No request queue\n"); }
+        dev.fail();
+        return false;
+    };
+    let Some(reqs) = (unsafe { alloc_dma_frames(1) }) else { dev.fail(); return false };
+    let Some(bounce) = (unsafe { alloc_dma_frames(BOUNCE_PAGES) }) else {
+        unsafe { free_phys_frames(reqs, 1); }
+        dev.fail();
+        return false;
+    };
+
+    let sectors = dev.config64(CFG_CAPACITY);
+    let mut seg_max = SEG_MAX;
+    if features & F_SEG_MAX != 0 { seg_max = seg_max.min(dev.config32(CFG_SEG_MAX) as usize); }
+    seg_max = seg_max.min(queue.size() as usize - 2).max(1);
+    let irq = pci.irq_line();
+    let modern = dev.is_modern();
+    dev.driver_ok();
+
+    unsafe {
+        let irqs = &mut *(&raw mut IRQS);
+        if !irqs.contains(&irq) && crate::kernel::interrupts::register_irq(irq, interrupt) {
+            irqs[d] = irq;
+        }
+        (*(&raw mut DISKS))[d] = Some(Disk {
+            dev, queue, reqs, bounce, seg_max,
+            flush:     features & F_FLUSH != 0,
+            read_only: features & F_RO != 0,
+            dead:      false,
+        });
+    }
+
+    // The serial number stands in for an ATA model string.
+    let mut model = [b' '; 40];
+    model[..11].copy_from_slice(b"VirtIO disk");
+    let mut id = [0u8; ID_LEN];
+    let got_id = unsafe {
+        let disk = (*(&raw mut DISKS))[d].as_mut().unwrap();
+        disk.bounced(d, T_GET_ID, 0, id.as_mut_ptr(), ID_LEN)
+    };
+    let id_len = id.iter().position(|&c| c == 0).unwrap_or(ID_LEN);
+    if got_id && id_len > 0 {
+        model[12..12 + id_len].copy_from_slice(&id[..id_len]);
+    }
+    let end = model.iter().rposition(|&c| c != b' ').map_or(0, |i| i + 1);
+
+    let disk = AtaDisk {
+        io_base: 0, ctrl: 0, slave: false, sectors, lba48: true, model, dma: Some(Dma::Virtio(d as u8)),
+    };
+    let Some(idx) = (unsafe { ata::add_disk(d, disk) }) else {
+        unsafe {
+            SERIAL_PORT.write_str("[virtio-blk] No free disk slot\n");
+            (*(&raw mut DISKS))[d] = None;
+        }
+        return false;
+    };
+
+    unsafe {
+        SERIAL_PORT.write_str("[virtio-blk] ");
+        SERIAL_PORT.write_str(if modern { "modern" } else { "legacy" });
+        SERIAL_PORT.write_str(" device -> disk");
+        SERIAL_PORT.write_decimal(idx as u32);
+        SERIAL_PORT.write_str(": ");
+        SERIAL_PORT.write_decimal((sectors / 2048) as u32);
+        SERIAL_PORT.write_str(" MB");
+        if features & F_RO != 0 { SERIAL_PORT.write_str(" read-only"); }
+        SERIAL_PORT.write_str(" model=");
+        SERIAL_PORT.write_str(core::str::from_utf8(&model[..end]).unwrap_or("?"));
+        SERIAL_PORT.write_str("\n");
+    }
+    true
+}
+
+/// Find virtio block devices and add them to the ata disk table.
+/// Returns how many disks were added.  Called from `ata::init_all`.
+pub unsafe fn init() -> usize {
+    let mut found = 0;
+    for pci in virtio::devices(virtio::TYPE_BLK) {
+        if found < MAX_DISKS && unsafe { probe(found, pci) } { found += 1; }
+    }
+    found
+}
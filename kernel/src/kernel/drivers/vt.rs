@@ -0,0 +1,289 @@
+//! Text virtual consoles `/dev/tty1`..`/dev/tty6`.
+//!
+//! Each console is an 80×25 grid of character cells driven by a VT100
+//! subset — enough for a shell, `login` and full-screen programs that use
+//! cursor addressing and SGR colours.  Only the active console is shown;
+//! Alt+F1..F6 switches between them.  Nothing here touches the framebuffer:
+//! the text-mode loop calls `take_dirty` and redraws from `with_active`.
+//!
+//! Keyboard input reaches the consoles once `enable` has been called (the
+//! `headless` boot option); until then the keyboard feeds the GUI as before.
+
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use crate::kernel::tty::{self, Winsize};
+
+pub const NR_VTS: usize = 6;
+pub const COLS:   usize = 80;
+pub const ROWS:   usize = 25;
+
+/// Size reported by TIOCGWINSZ on a console (8×16 font).
+pub const WINSIZE: Winsize = Winsize {
+    ws_row: ROWS as u16, ws_col: COLS as u16,
+    ws_xpixel: (COLS * 8) as u16, ws_ypixel: (ROWS * 16) as u16,
+};
+
+/// ARGB of the 16 ANSI colours, in SGR order.
+pub const PALETTE: [u32; 16] = [
+    0xFF000000, 0xFFAA0000, 0xFF00AA00, 0xFFAA5500, 0xFF0000AA, 0xFFAA00AA, 0xFF00AAAA, 0xFFAAAAAA,
+    0xFF555555, 0xFFFF5555, 0xFF55FF55, 0xFFFFFF55, 0xFF5555FF, 0xFFFF55FF, 0xFF55FFFF, 0xFFFFFFFF,
+];
+
+/// Light grey on black.
+const DEFAULT_ATTR: u8 = 0x07;
+
+/// One character cell: the byte and its colours (foreground in the low
+/// nibble, background in the high one, both `PALETTE` indices).
+#[derive(Clone, Copy)]
+pub struct Cell {
+    pub ch:   u8,
+    pub attr: u8,
+}
+
+const BLANK: Cell = Cell { ch: b' ', attr: DEFAULT_ATTR };
+
+#[derive(Clone, Copy, PartialEq)]
+enum Parse { Ground, Esc, Csi }
+
+const MAX_PARAMS: usize = 8;
+
+struct Vt {
+    cells:   [[Cell; COLS]; ROWS],
+    row:     usize,
+    col:     usize,
+    saved:   (usize, usize),
+    attr:    u8,
+    bold:    bool,
+    reverse: bool,
+    /// The last write filled the right-most column; the next printable
+    /// character wraps first.
+    wrap:    bool,
+    parse:   Parse,
+    params:  [u16; MAX_PARAMS],
+    nparams: usize,
+    private: bool,
+    /// A BEL arrived since the last `write` returned.
+    bell:    bool,
+}
+
+impl Vt {
+    const fn new() -> Self {
+        Self {
+            cells: [[BLANK; COLS]; ROWS],
+            row: 0, col: 0, saved: (0, 0),
+            attr: DEFAULT_ATTR, bold: false, reverse: false, wrap: false,
+            parse: Parse::Ground,
+            params: [0; MAX_PARAMS], nparams: 0, private: false,
+            bell: false,
+        }
+    }
+
+    fn blank(&self) -> Cell {
+        Cell { ch: b' ', attr: self.attr & 0xF0 }
+    }
+
+    fn param(&self, i: usize, default: u16) -> usize {
+        let v = if i < self.nparams { self.params[i] } else { 0 };
+        (if v == 0 { default } else { v }) as usize
+    }
+
+    fn scroll_up(&mut self, top: usize, n: usize) {
+        let n = n.min(ROWS - top);
+        self.cells.copy_within(top + n..ROWS, top);
+        let b = self.blank();
+        for r in ROWS - n..ROWS { self.cells[r] = [b; COLS]; }
+    }
+
+    fn scroll_down(&mut self, top: usize, n: usize) {
+        let n = n.min(ROWS - top);
+        self.cells.copy_within(top..ROWS - n, top + n);
+        let b = self.blank();
+        for r in top..top + n { self.cells[r] = [b; COLS]; }
+    }
+
+    fn newline(&mut self) {
+        if self.row + 1 == ROWS { self.scroll_up(0, 1); } else { self.row += 1; }
+    }
+
+    fn put(&mut self, c: u8) {
+        if self.wrap {
+            self.col = 0;
+            self.newline();
+            self.wrap = false;
+        }
+        self.cells[self.row][self.col] = Cell { ch: c, attr: self.attr };
+        if self.col + 1 == COLS { self.wrap = true; } else { self.col += 1; }
+    }
+
+    fn erase(&mut self, row: usize, from: usize, to: usize) {
+        let b = self.blank();
+        for c in &mut self.cells[row][from..to] { *c = b; }
+    }
+
+    fn sgr(&mut self) {
+        for i in 0..self.nparams.max(1) {
+            let p = if i < self.nparams { self.params[i] } else { 0 };
+            let (mut fg, mut bg) = (self.attr & 0x07, self.attr >> 4);
+            if self.reverse { core::mem::swap(&mut fg, &mut bg); }
+            match p {
+                0       => { fg = 7; bg = 0; self.bold = false; self.reverse = false; }
+                1       => self.bold = true,
+                22      => self.bold = false,
+                7       => self.reverse = true,
+                27      => self.reverse = false,
+                30..=37 => fg = (p - 30) as u8,
+                39      => fg = 7,
+                40..=47 => bg = (p - 40) as u8,
+                49      => bg = 0,
+                90..=97 => { fg = (p - 90) as u8; self.bold = true; }
+                _ => {}
+            }
+            if self.reverse { core::mem::swap(&mut fg, &mut bg); }
+            self.attr = (bg << 4) | fg | if self.bold { 8 } else { 0 };
+        }
+    }
+
+    fn csi(&mut self, fin: u8) {
+        let (row, col) = (self.row, self.col);
+        self.wrap = false;
+        match fin {
+            b'A' => self.row = row.saturating_sub(self.param(0, 1)),
+            b'B' => self.row = (row + self.param(0, 1)).min(ROWS - 1),
+            b'C' => self.col = (col + self.param(0, 1)).min(COLS - 1),
+            b'D' => self.col = col.saturating_sub(self.param(0, 1)),
+            b'G' => self.col = (self.param(0, 1) - 1).min(COLS - 1),
+            b'd' => self.row = (self.param(0, 1) - 1).min(ROWS - 1),
+            b'H' | b'f' => {
+                self.row = (self.param(0, 1) - 1).min(ROWS - 1);
+                self.col = (self.param(1, 1) - 1).min(COLS - 1);
+            }
+            b'J' => match self.param(0, 0) {
+                0 => { self.erase(row, col, COLS); for r in row + 1..ROWS { self.erase(r, 0, COLS); } }
+                1 => { for r in 0..row { self.erase(r, 0, COLS); } self.erase(row, 0, col + 1); }
+                _ => for r in 0..ROWS { self.erase(r, 0, COLS); },
+            },
+            b'K' => match self.param(0, 0) {
+                0 => self.erase(row, col, COLS),
+                1 => self.erase(row, 0, col + 1),
+                _ => self.erase(row, 0, COLS),
+            },
+            b'P' => {
+                let n = self.param(0, 1).min(COLS - col);
+                self.cells[row].copy_within(col + n..COLS, col);
+                self.erase(row, COLS - n, COLS);
+            }
+            b'@' => {
+                let n = self.param(0, 1).min(COLS - col);
+                self.cells[row].copy_within(col..COLS - n, col + n);
+                self.erase(row, col, col + n);
+            }
+            b'L' => self.scroll_down(row, self.param(0, 1)),
+            b'M' => self.scroll_up(row, self.param(0, 1)),
+            b'm' => self.sgr(),
+            b's' => self.saved = (row, col),
+            b'u' => (self.row, self.col) = self.saved,
+            // Modes (cursor visibility, wrap, ...) are accepted and ignored.
+            _ => {}
+        }
+    }
+
+    fn feed(&mut self, c: u8) {
+        match self.parse {
+            Parse::Ground => match c {
+                0x1B  => self.parse = Parse::Esc,
+                b'\r' => { self.col = 0; self.wrap = false; }
+                b'\n' | 0x0B | 0x0C => { self.newline(); self.wrap = false; }
+                0x08  => { self.col = self.col.saturating_sub(1); self.wrap = false; }
+                b'\t' => { self.col = ((self.col / 8 + 1) * 8).min(COLS - 1); }
+                0x07  => self.bell = true,
+                // A UTF-8 character takes one cell: its continuation bytes
+                // are not drawn.
+                0x80..=0xBF => {}
+                0x20..=0x7E | 0xC0..=0xFF => self.put(c),
+                _ => {}
+            },
+            Parse::Esc => {
+                self.parse = Parse::Ground;
+                match c {
+                    b'[' => {
+                        self.parse = Parse::Csi;
+                        self.params = [0; MAX_PARAMS];
+                        self.nparams = 0;
+                        self.private = false;
+                    }
+                    b'c' => *self = Self::new(),
+                    b'7' => self.saved = (self.row, self.col),
+                    b'8' => (self.row, self.col) = self.saved,
+                    _ => {}
+                }
+            }
+            Parse::Csi => match c {
+                b'0'..=b'9' => {
+                    if self.nparams == 0 { self.nparams = 1; }
+                    let p = &mut self.params[self.nparams - 1];
+                    *p = p.saturating_mul(10).saturating_add((c - b'0') as u16);
+                }
+                b';' => if self.nparams < MAX_PARAMS {
+                    if self.nparams == 0 { self.nparams = 1; }
+                    self.nparams += 1;
+                },
+                b'?' | b'>' | b'=' => self.private = true,
+                0x40..=0x7E => {
+                    self.parse = Parse::Ground;
+                    if !self.private { self.csi(c); }
+                }
+                _ => self.parse = Parse::Ground,
+            },
+        }
+    }
+}
+
+// ── Global state ──────────────────────────────────────────────────────────
+
+static mut VTS: [Vt; NR_VTS] = [const { Vt::new() }; NR_VTS];
+static ACTIVE:  AtomicUsize = AtomicUsize::new(0);
+static DIRTY:   AtomicBool  = AtomicBool::new(true);
+
+/// Give the keyboard to the consoles (headless boot).
+pub fn enable() {
+    crate::kernel::keyboard::route_to_vt(true);
+    DIRTY.store(true, Ordering::Relaxed);
+}
+
+/// Index (0-based) of the console on screen.
+pub fn active() -> usize {
+    ACTIVE.load(Ordering::Relaxed)
+}
+
+/// Bring console `vt` (0-based) to the screen.
+pub fn switch(vt: usize) {
+    if vt < NR_VTS && vt != active() {
+        ACTIVE.store(vt, Ordering::Relaxed);
+        DIRTY.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Output to console `vt` (0-based).  Returns true if the bytes rang the
+/// bell (a BEL outside an escape sequence); sounding it is up to the tty.
+pub unsafe fn write(vt: usize, bytes: &[u8]) -> bool {
+    if vt >= NR_VTS { return false; }
+    let v = unsafe { &mut (*(&raw mut VTS))[vt] };
+    for &b in bytes { v.feed(b); }
+    if vt == active() { DIRTY.store(true, Ordering::Relaxed); }
+    core::mem::take(&mut v.bell)
+}
+
+/// A byte typed on the keyboard, for the console on screen.
+pub unsafe fn key(byte: u8) {
+    unsafe { tty::input(tty::VT_BASE + active(), byte); }
+}
+
+/// True (once) when the screen needs redrawing.
+pub fn take_dirty() -> bool {
+    DIRTY.swap(false, Ordering::Relaxed)
+}
+
+/// Run `f` over the active console's cells and cursor `(row, col)`.
+pub fn with_active<R>(f: impl FnOnce(&[[Cell; COLS]; ROWS], (usize, usize)) -> R) -> R {
+    let v = unsafe { &(*(&raw const VTS))[active()] };
+    f(&v.cells, (v.row, v.col))
+}
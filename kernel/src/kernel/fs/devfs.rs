@@ -0,0 +1,440 @@
+//! devfs — the `/dev` directory.
+//!
+//! `/dev` is a table of device nodes that drivers fill in as they come up;
+//! `vfs::resolve` looks names up here, and a name nobody registered does not
+//! exist.  Each node names the fd backend that serves it, the minor passed
+//! to that backend in `FdEntry::raw_fd`, and the Linux device number, mode
+//! and owner `stat` reports.
+//!
+//! | Node                  | Backend     | Registered by          |
+//! |-----------------------|-------------|------------------------|
+//! | null zero full        | DevNull/Mem | `devfs::init`          |
+//! | random urandom        | Mem         | `devfs::init`          |
+//! | tty ttyS0 tty1..6     | DevTty/Tty  | `tty::register_devices`|
+//! | ptmx, pts/N           | PtyMaster/Tty | `pty::register_devices`, `tty::lookup` |
+//! | hda..hdd              | Block       | `ata::init_all`        |
+//! | nvmeXnY, nvmeXnYpN    | Block       | `nvme::init`           |
+//! | fb0                   | Fb          | `fbdev::register`      |
+//! | input/event0..1       | Evdev       | `evdev::register_devices` |
+//! | dsp, mixer            | Dsp/Mixer   | `dsp::register_devices` (from `ac97::init`) |
+//!
+//! The memory devices and the raw block devices are implemented here; the
+//! framebuffer lives in `drivers::fbdev`.
+
+extern crate alloc;
+
+use alloc::format;
+use core::sync::atomic::{AtomicU32, Ordering};
+use crate::kernel::fs::ramfs::{FdBackend, FdEntry};
+use crate::kernel::vfs::{LinuxStat, S_IFBLK, S_IFCHR};
+
+/// Group owning the terminal lines (`tty` in `/etc/group`).
+pub const GID_TTY:   u32 = 5;
+/// Group owning the raw disks (`disk` in `/etc/group`).
+pub const GID_DISK:  u32 = 6;
+/// Group owning the sound devices (`audio` in `/etc/group`).
+pub const GID_AUDIO: u32 = 29;
+/// Group owning the framebuffer (`video` in `/etc/group`).
+pub const GID_VIDEO: u32 = 44;
+/// Group owning the input devices (`input` in `/etc/group`).
+pub const GID_INPUT: u32 = 104;
+
+// Minors of the Linux memory devices (major 1).
+pub const MEM_NULL:    i32 = 3;
+pub const MEM_ZERO:    i32 = 5;
+pub const MEM_FULL:    i32 = 7;
+pub const MEM_RANDOM:  i32 = 8;
+pub const MEM_URANDOM: i32 = 9;
+
+// Block device ioctls.
+pub const BLKGETSIZE:   u64 = 0x1260;
+pub const BLKFLSBUF:    u64 = 0x1261;
+pub const BLKSSZGET:    u64 = 0x1268;
+pub const BLKGETSIZE64: u64 = 0x8008_1272;
+
+const EPERM:  i64 = -1;
+const EIO:    i64 = -5;
+const EBADF:  i64 = -9;
+const EFAULT: i64 = -14;
+const ENODEV: i64 = -19;
+const EINVAL: i64 = -22;
+const ENOTTY: i64 = -25;
+const ENOSPC: i64 = -28;
+const EOVERFLOW: i64 = -75;
+
+const MAX_NODES: usize = 48;
+const SECTOR: u64 = 512;
+
+/// One entry of `/dev`.
+#[derive(Clone, Copy)]
+pub struct DevNode {
+    pub name:    &'static str,
+    /// `Dir` for `/dev` itself, `/dev/pts` and `/dev/input`.  Other nodes
+    /// may live one directory down (`input/event0`).
+    pub backend: FdBackend,
+    /// Goes into `FdEntry::raw_fd`: the tty index, disk index (plus
+    /// partition number, see `extent`), memory device minor, …
+    pub minor:   i32,
+    /// Linux (major, minor) reported as `st_rdev`.
+    pub rdev:    (u32, u32),
+    /// Block rather than character device.
+    pub block:   bool,
+    pub mode:    u16,
+    pub uid:     u32,
+    pub gid:     u32,
+}
+
+impl DevNode {
+    /// A world-accessible character device owned by root.
+    pub const fn chr(name: &'static str, backend: FdBackend, minor: i32, rdev: (u32, u32)) -> Self {
+        Self { name, backend, minor, rdev, block: false, mode: 0o666, uid: 0, gid: 0 }
+    }
+
+    /// A terminal line served by tty `index`: 0620, group tty.  The owner
+    /// is the tty's own (`tty::owner`), filled in on lookup.
+    pub const fn line(name: &'static str, index: usize, rdev: (u32, u32)) -> Self {
+        Self { name, backend: FdBackend::Tty, minor: index as i32, rdev, block: false, mode: 0o620, uid: 0, gid: GID_TTY }
+    }
+
+    /// This node with its current owner: terminal lines change hands at
+    /// login.
+    fn current(mut self) -> Self {
+        if self.backend == FdBackend::Tty {
+            self.uid = crate::kernel::tty::owner(self.minor as usize);
+        }
+        self
+    }
+
+    const fn dir(name: &'static str) -> Self {
+        Self { name, backend: FdBackend::Dir, minor: 0, rdev: (0, 0), block: false, mode: 0o755, uid: 0, gid: 0 }
+    }
+}
+
+static mut NODES: [Option<DevNode>; MAX_NODES] = [None; MAX_NODES];
+
+// ── Registry ──────────────────────────────────────────────────────────────
+
+/// Add `node` to `/dev`, replacing any node of the same name.  Returns
+/// `false` if the table is full.
+pub fn register(node: DevNode) -> bool {
+    let nodes = unsafe { &mut *(&raw mut NODES) };
+    let slot = nodes.iter().position(|n| n.is_some_and(|n| n.name == node.name))
+        .or_else(|| nodes.iter().position(|n| n.is_none()));
+    match slot {
+        Some(i) => { nodes[i] = Some(node); true }
+        None    => false,
+    }
+}
+
+/// Register the memory devices.  Called once at boot, before any driver
+/// adds its own nodes.
+pub fn init() {
+    for (name, backend, minor) in [
+        ("null",    FdBackend::DevNull, MEM_NULL),
+        ("zero",    FdBackend::Mem,     MEM_ZERO),
+        ("full",    FdBackend::Mem,     MEM_FULL),
+        ("random",  FdBackend::Mem,     MEM_RANDOM),
+        ("urandom", FdBackend::Mem,     MEM_URANDOM),
+    ] {
+        register(DevNode::chr(name, backend, minor, (1, minor as u32)));
+    }
+}
+
+/// Node for `name`, relative to `/dev` (`""` is `/dev` itself).  Pty
+/// slaves come and go, so `pts/N` is answered by the tty layer; like the
+/// other lines they are 0620, owned by whoever opened the master.
+pub fn lookup(name: &str) -> Option<DevNode> {
+    match name {
+        ""    => return Some(DevNode::dir("")),
+        "pts" => return Some(DevNode::dir("pts")),
+        _ => {}
+    }
+    if let Some(n) = name.strip_prefix("pts/") {
+        let t = crate::kernel::tty::lookup(name)?;
+        let n: u32 = n.parse().ok()?;
+        return Some(DevNode::line("pts", t, (136, n)).current());
+    }
+    let nodes = unsafe { &*(&raw const NODES) };
+    if let Some(n) = nodes.iter().flatten().find(|n| n.name == name) {
+        return Some(n.current());
+    }
+    // A directory exists while some node lives in it.
+    let in_dir = |n: &&DevNode| n.name.split_once('/').is_some_and(|(d, _)| d == name);
+    nodes.iter().flatten().find(in_dir).map(|n| DevNode::dir(n.name.split_once('/').unwrap().0))
+}
+
+/// The node an open fd refers to (for `fstat`).
+pub fn node_of(e: &FdEntry) -> Option<DevNode> {
+    let nodes = unsafe { &*(&raw const NODES) };
+    let minor = match e.backend {
+        FdBackend::Evdev => crate::kernel::evdev::device_of(e.raw_fd),
+        _ => e.raw_fd,
+    };
+    let found = nodes.iter().flatten().find(|n| {
+        n.backend == e.backend && (n.minor == minor || e.backend == FdBackend::PtyMaster)
+    });
+    match found {
+        Some(n) => Some(n.current()),
+        None if e.backend == FdBackend::Tty => {
+            let n = e.raw_fd as usize - crate::kernel::tty::PTY_BASE;
+            Some(DevNode::line("pts", e.raw_fd as usize, (136, n as u32)).current())
+        }
+        None => None,
+    }
+}
+
+/// Listing of `/dev` (`dir` empty) or one of its subdirectories, one name
+/// per line.  Subdirectories of `/dev` are listed once.
+pub fn list(dir: &str, buf: &mut [u8]) -> usize {
+    let nodes = unsafe { &*(&raw const NODES) };
+    let mut len = 0;
+    let mut push = |name: &str, buf: &mut [u8]| {
+        if len + name.len() + 1 > buf.len() { return; }
+        buf[len..len + name.len()].copy_from_slice(name.as_bytes());
+        buf[len + name.len()] = b'\n';
+        len += name.len() + 1;
+    };
+    for (i, n) in nodes.iter().enumerate() {
+        let Some(n) = n else { continue };
+        let (parent, leaf) = n.name.split_once('/').unwrap_or(("", n.name));
+        if dir.is_empty() && !parent.is_empty() {
+            // First node in the subdirectory stands for it.
+            let first = nodes[..i].iter().flatten()
+                .all(|m| m.name.split_once('/').map(|(d, _)| d) != Some(parent));
+            if first { push(parent, buf); }
+        } else if parent == dir {
+            push(leaf, buf);
+        }
+    }
+    if dir.is_empty() { push("pts", buf); }
+    len
+}
+
+/// `chown` of `node`.  Only terminal lines can change hands; the other
+/// nodes keep the owner their driver gave them.
+pub fn chown(node: &DevNode, uid: u32, gid: u32) -> i64 {
+    match node.backend {
+        FdBackend::Tty => unsafe { crate::kernel::tty::chown(node.minor as usize, uid, gid) },
+        _ => EPERM,
+    }
+}
+
+/// Whether the current task may open `node` with `flags`.
+pub fn may_open(node: &DevNode, flags: u32) -> bool {
+    use crate::kernel::cred::{self, MAY_READ, MAY_WRITE};
+    use crate::kernel::fs::{O_RDWR, O_WRONLY};
+    let want = if flags & O_WRONLY != 0 { MAY_WRITE }
+               else if flags & O_RDWR != 0 { MAY_READ | MAY_WRITE }
+               else { MAY_READ };
+    cred::get().may_access(want, node.mode, node.uid, node.gid)
+}
+
+/// `stat` of `node`.
+pub fn stat(node: &DevNode) -> LinuxStat {
+    if node.backend == FdBackend::Dir {
+        let mut s = LinuxStat::fill_dir(800 + node.name.len() as u64);
+        s.st_uid = 0;
+        s.st_gid = 0;
+        return s;
+    }
+    let mut s = LinuxStat::fill_chardev(800 + slot_of(node) as u64);
+    let (major, minor) = node.rdev;
+    s.st_mode = if node.block { S_IFBLK } else { S_IFCHR } | node.mode as u32;
+    s.st_uid  = node.uid;
+    s.st_gid  = node.gid;
+    s.st_rdev = ((major as u64 & 0xFFF) << 8) | (minor as u64 & 0xFF) | ((minor as u64 & !0xFF) << 12);
+    s.st_size = size(node.backend, node.minor) as i64;
+    s.st_blksize = SECTOR as i64;
+    s
+}
+
+fn slot_of(node: &DevNode) -> usize {
+    let nodes = unsafe { &*(&raw const NODES) };
+    nodes.iter().position(|n| n.is_some_and(|n| n.name == node.name)).unwrap_or(MAX_NODES)
+}
+
+/// Size in bytes of a seekable device (0 for the rest).
+fn size(backend: FdBackend, minor: i32) -> u64 {
+    match backend {
+        FdBackend::Block => extent(minor).map_or(0, |(_, sectors)| sectors * SECTOR),
+        FdBackend::Fb    => crate::kernel::fbdev::size(),
+        _ => 0,
+    }
+}
+
+// ── I/O for the Mem, Block and Fb backends ────────────────────────────────
+
+/// Read from `e` at its offset; the caller advances the offset.
+pub unsafe fn read(e: &FdEntry, buf: &mut [u8]) -> i64 {
+    match e.backend {
+        FdBackend::Mem => match e.raw_fd {
+            MEM_RANDOM | MEM_URANDOM => { crate::kernel::rng::fill(buf); buf.len() as i64 }
+            _ => { buf.fill(0); buf.len() as i64 }
+        },
+        FdBackend::Block => unsafe { block_read(e.raw_fd, e.offset as u64, buf) },
+        FdBackend::Fb    => unsafe { crate::kernel::fbdev::read(e.offset as u64, buf) },
+        _ => EBADF,
+    }
+}
+
+/// Write to `e` at its offset; the caller advances the offset.
+pub unsafe fn write(e: &FdEntry, buf: &[u8]) -> i64 {
+    if !e.writable { return EBADF; }
+    match e.backend {
+        FdBackend::Mem => match e.raw_fd {
+            MEM_FULL => ENOSPC,
+            MEM_RANDOM | MEM_URANDOM => { crate::kernel::rng::add_entropy(buf); buf.len() as i64 }
+            _ => buf.len() as i64,
+        },
+        FdBackend::Block => unsafe { block_write(e.raw_fd, e.offset as u64, buf) },
+        FdBackend::Fb    => unsafe { crate::kernel::fbdev::write(e.offset as u64, buf) },
+        _ => EBADF,
+    }
+}
+
+/// New offset for `lseek` on `e`.  The memory devices always report 0.
+pub fn lseek(e: &FdEntry, offset: i64, whence: u32) -> i64 {
+    if e.backend == FdBackend::Mem { return 0; }
+    let base = match whence {
+        0 => 0,
+        1 => e.offset as u64,
+        2 => size(e.backend, e.raw_fd),
+        _ => return EINVAL,
+    };
+    let Some(new) = i64::try_from(base).ok().and_then(|b| b.checked_add(offset)) else {
+        return EOVERFLOW;
+    };
+    if new < 0 { EINVAL } else { new }
+}
+
+/// ioctl on a Mem, Block or Fb fd.
+pub unsafe fn ioctl(e: &FdEntry, request: u64, arg: u64) -> i64 {
+    use crate::kernel::syscall_core::validate_user_range;
+    match e.backend {
+        FdBackend::Fb => unsafe { crate::kernel::fbdev::ioctl(request, arg) },
+        FdBackend::Block => {
+            let bytes = size(FdBackend::Block, e.raw_fd);
+            match request {
+                BLKGETSIZE64 | BLKGETSIZE => {
+                    if validate_user_range(arg, 8).is_err() { return EFAULT; }
+                    let v = if request == BLKGETSIZE64 { bytes } else { bytes / SECTOR };
+                    unsafe { core::ptr::write_unaligned(arg as *mut u64, v); }
+                    0
+                }
+                BLKSSZGET => {
+                    if validate_user_range(arg, 4).is_err() { return EFAULT; }
+                    unsafe { core::ptr::write_unaligned(arg as *mut i32, SECTOR as i32); }
+                    0
+                }
+                BLKFLSBUF => 0, // writes go straight to the disk
+                _ => ENOTTY,
+            }
+        }
+        _ => ENOTTY,
+    }
+}
+
+// ── Raw block devices ─────────────────────────────────────────────────────
+
+const HD_NAMES: [&str; crate::kernel::ata::MAX_DISKS] = [
+    "hda", "hdb", "hdc", "hdd", "hde", "hdf", "hdg", "hdh",
+    "hdi", "hdj", "hdk", "hdl", "hdm", "hdn", "hdo", "hdp",
+];
+
+/// Linux block major of each IDE channel (two disks per channel).
+const HD_MAJORS: [u32; crate::kernel::ata::MAX_DISKS / 2] = [3, 22, 33, 34, 56, 57, 88, 89];
+
+/// Add `/dev/hda`..`/dev/hdp` for the disk at `idx` (0-3 are primary
+/// master, primary slave, secondary master, secondary slave).
+pub fn register_disk(idx: usize) {
+    // One IDE major per channel; the slave is minor 64.
+    let rdev = (HD_MAJORS[idx / 2], if idx % 2 == 1 { 64 } else { 0 });
+    register(DevNode {
+        name: HD_NAMES[idx], backend: FdBackend::Block, minor: idx as i32,
+        rdev, block: true, mode: 0o660, uid: 0, gid: GID_DISK,
+    });
+}
+
+/// Add `/dev/<name>` for the NVMe namespace at disk `idx`, and `<name>pN`
+/// for each partition its MBR lists.  Linux puts these on the extended
+/// block major, minors handed out in order.
+pub fn register_nvme(idx: usize, name: &'static str) {
+    static NEXT_MINOR: AtomicU32 = AtomicU32::new(0);
+    let node = |name, minor| DevNode {
+        name, backend: FdBackend::Block, minor,
+        rdev: (259, NEXT_MINOR.fetch_add(1, Ordering::Relaxed)),
+        block: true, mode: 0o660, uid: 0, gid: GID_DISK,
+    };
+    register(node(name, idx as i32));
+    let parts = unsafe { crate::kernel::mbr::scan(idx) };
+    for (i, _) in parts.iter().enumerate().filter(|(_, p)| p.is_some()) {
+        let n = i as i32 + 1;
+        register(node(format!("{name}p{n}").leak(), idx as i32 | n << PART_SHIFT));
+    }
+}
+
+/// Block minors carry the MBR partition number above the disk index.
+const PART_SHIFT: i32 = 8;
+
+/// First sector and length in sectors of the block device `minor`: the
+/// whole of disk `minor & 0xFF` for partition 0, else that partition as
+/// the MBR lists it now.
+fn extent(minor: i32) -> Option<(u64, u64)> {
+    let disk = (minor & 0xFF) as usize;
+    match minor >> PART_SHIFT {
+        0 => crate::kernel::ata::disk_info(disk).map(|(sectors, _, _)| (0, sectors)),
+        n => unsafe { crate::kernel::mbr::read_partition(disk, n as usize) }
+            .map(|p| (p.start_lba as u64, p.size_sectors as u64)),
+    }
+}
+
+/// Read `buf.len()` bytes at byte `offset` of block device `minor`, short
+/// at the end of the device.
+unsafe fn block_read(minor: i32, offset: u64, buf: &mut [u8]) -> i64 {
+    let Some((start, sectors)) = extent(minor) else { return ENODEV };
+    let (idx, total) = ((minor & 0xFF) as usize, sectors * SECTOR);
+    if total == 0 { return ENODEV; }
+    if offset >= total { return 0; }
+    let len = (total - offset).min(buf.len() as u64) as usize;
+    let mut sector = [0u8; SECTOR as usize];
+    let mut done = 0;
+    while done < len {
+        let pos = offset + done as u64;
+        let lba = start + pos / SECTOR;
+        let at  = (pos % SECTOR) as usize;
+        let n   = (SECTOR as usize - at).min(len - done);
+        if !unsafe { crate::kernel::ata::read_sector(idx, lba as u32, &mut sector) } {
+            return if done > 0 { done as i64 } else { EIO };
+        }
+        buf[done..done + n].copy_from_slice(&sector[at..at + n]);
+        done += n;
+    }
+    done as i64
+}
+
+/// Write `buf` at byte `offset` of block device `minor`.  Partial sectors
+/// are read, patched and written back.  ENOSPC at the end of the device.
+unsafe fn block_write(minor: i32, offset: u64, buf: &[u8]) -> i64 {
+    let Some((start, sectors)) = extent(minor) else { return ENODEV };
+    let (idx, total) = ((minor & 0xFF) as usize, sectors * SECTOR);
+    if total == 0 { return ENODEV; }
+    if offset >= total { return if buf.is_empty() { 0 } else { ENOSPC }; }
+    let len = (total - offset).min(buf.len() as u64) as usize;
+    let mut sector = [0u8; SECTOR as usize];
+    let mut done = 0;
+    while done < len {
+        let pos = offset + done as u64;
+        let lba = (start + pos / SECTOR) as u32;
+        let at  = (pos % SECTOR) as usize;
+        let n   = (SECTOR as usize - at).min(len - done);
+        if n < SECTOR as usize && !unsafe { crate::kernel::ata::read_sector(idx, lba, &mut sector) } {
+            return if done > 0 { done as i64 } else { EIO };
+        }
+        sector[at..at + n].copy_from_slice(&buf[done..done + n]);
+        if !unsafe { crate::kernel::ata::write_sector(idx, lba, &sector) } {
+            return if done > 0 { done as i64 } else { EIO };
+        }
+        done += n;
+    }
+    done as i64
+}
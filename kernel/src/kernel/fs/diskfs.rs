@@ -40,9 +40,14 @@ pub fn populate() {
     let labels = ["disk0 (primary master)",  "disk1 (primary slave)",
                   "disk2 (secondary master)","disk3 (secondary slave)"];
     let mounts = ["/disk", "(no mount)", "/ext2", "(no mount)"];
-    for i in 0..4usize {
+    for i in 0..crate::kernel::ata::MAX_DISKS {
+        // Positions past the IDE four are listed only when filled.
+        if i >= labels.len() && !crate::kernel::ata::is_present_at(i) { continue; }
         push_str(&mut info, "  ");
-        push_str(&mut info, labels[i]);
+        match labels.get(i) {
+            Some(label) => push_str(&mut info, label),
+            None        => { push_str(&mut info, "disk"); push_u64(&mut info, i as u64); }
+        }
         push_str(&mut info, ": ");
         if crate::kernel::ata::is_present_at(i) {
             if let Some((secs, slave, lba48)) = crate::kernel::ata::disk_info(i) {
@@ -50,7 +55,7 @@ pub fn populate() {
                 push_str(&mut info, " MB");
                 if lba48 { push_str(&mut info, " LBA48"); }
                 push_str(&mut info, "  mount=");
-                push_str(&mut info, mounts[i]);
+                push_str(&mut info, mounts.get(i).copied().unwrap_or("(no mount)"));
             }
         } else {
             push_str(&mut info, "not present");
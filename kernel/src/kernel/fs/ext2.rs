@@ -12,9 +12,10 @@
 //!
 //! # Limitations
 //! - 1024 / 2048 / 4096 byte blocks supported
-//! - Inode direct blocks only (12 × block_size ≤ 48 KB per file) — no
-//!   indirect, double-indirect, or triple-indirect blocks; writes/creates
-//!   that would need a 13th block fail with `EFBIG`
+//! - Reads follow direct, indirect, double- and triple-indirect blocks, so
+//!   large files installed by `mke2fs`/`debugfs` (e.g. shared libraries) load
+//! - Writes use direct blocks only (12 × block_size ≤ 48 KB per file);
+//!   writes/creates that would need a 13th block fail with `EFBIG`
 //! - No symbolic links, hard links, or file locking
 //! - Up to 8 block groups, each bitmap must fit in a single block
 //! - Up to 16 simultaneously open files
@@ -104,6 +105,38 @@ impl Inode {
     fn size(&self)    -> u64  { self.size_lo as u64 | ((self.size_hi as u64) << 32) }
 }
 
+// ── Indirect block lookup ───────────────────────────────────────────────────
+
+/// Read entry `idx` of the pointer block `table`.  A zero `table` (hole)
+/// or a failed read yields 0, which callers treat as a sparse block.
+unsafe fn pointer_at(state: &Ext2State, table: u32, idx: usize) -> u32 {
+    if table == 0 || !unsafe { read_block_into_scratch(state, table) } { return 0; }
+    let s = unsafe { &*(&raw const SCRATCH) };
+    let off = idx * 4;
+    u32::from_le_bytes([s[off], s[off + 1], s[off + 2], s[off + 3]])
+}
+
+/// Map logical block `idx` (counted from the first block past the 12 direct
+/// ones) through the single/double/triple-indirect chain.  Clobbers SCRATCH.
+unsafe fn indirect_block(state: &Ext2State, ind: &[u32; 3], mut idx: usize) -> u32 {
+    let per = state.block_size as usize / 4;
+    if idx < per {
+        return unsafe { pointer_at(state, ind[0], idx) };
+    }
+    idx -= per;
+    if idx < per * per {
+        let l1 = unsafe { pointer_at(state, ind[1], idx / per) };
+        return unsafe { pointer_at(state, l1, idx % per) };
+    }
+    idx -= per * per;
+    if idx < per * per * per {
+        let l1 = unsafe { pointer_at(state, ind[2], idx / (per * per)) };
+        let l2 = unsafe { pointer_at(state, l1, (idx / per) % per) };
+        return unsafe { pointer_at(state, l2, idx % per) };
+    }
+    0
+}
+
 // ── Open file descriptor ────────────────────────────────────────────────────
 #[derive(Clone, Copy)]
 struct Ext2Fd {
@@ -112,6 +145,8 @@ struct Ext2Fd {
     file_size:   u32,
     file_offset: u32,
     direct_blocks: [u32; 12],
+    /// Single-, double- and triple-indirect block pointers (`i_block[12..15]`).
+    indirect:    [u32; 3],
     writable:    bool,
     append:      bool,
 }
@@ -120,7 +155,7 @@ impl Ext2Fd {
     const fn empty() -> Self {
         Self {
             active: false, inode_no: 0, file_size: 0, file_offset: 0,
-            direct_blocks: [0u32; 12], writable: false, append: false,
+            direct_blocks: [0u32; 12], indirect: [0u32; 3], writable: false, append: false,
         }
     }
 }
@@ -1010,13 +1045,15 @@ pub unsafe fn open(path: &[u8], flags: u32) -> i64 {
     for i in 0..EXT2_FD_COUNT {
         let slot = &raw mut (*fds)[i];
         if !(*slot).active {
-            (*slot).active      = true;
-            (*slot).inode_no    = ino;
-            (*slot).file_size   = inode.size_lo;
-            (*slot).file_offset = if flags & O_APPEND != 0 { inode.size_lo } else { 0 };
-            (*slot).direct_blocks.copy_from_slice(&inode.block[..12]);
-            (*slot).writable    = writable;
-            (*slot).append      = flags & O_APPEND != 0;
+            let slot = unsafe { &mut *slot };
+            slot.active      = true;
+            slot.inode_no    = ino;
+            slot.file_size   = inode.size_lo;
+            slot.file_offset = if flags & O_APPEND != 0 { inode.size_lo } else { 0 };
+            slot.direct_blocks.copy_from_slice(&inode.block[..12]);
+            slot.indirect.copy_from_slice(&inode.block[12..15]);
+            slot.writable    = writable;
+            slot.append      = flags & O_APPEND != 0;
             return (EXT2_FD_BASE + i as i32) as i64;
         }
     }
@@ -1044,8 +1081,11 @@ pub unsafe fn read_fd(fd: i32, buf: &mut [u8]) -> i64 {
         let block_idx   = file_offset / block_size;
         let byte_in_blk = file_offset % block_size;
 
-        if block_idx >= 12 { break; } // no indirect block support yet
-        let blk = (*slot).direct_blocks[block_idx];
+        let blk = if block_idx < 12 {
+            unsafe { (*slot).direct_blocks[block_idx] }
+        } else {
+            unsafe { indirect_block(&*state, &(*slot).indirect, block_idx - 12) }
+        };
         if blk == 0 {
             // Sparse hole (e.g. left by a truncate-grow): zero-fill rather
             // than stopping, since file_offset is still < file_size here.
@@ -1142,6 +1182,63 @@ pub unsafe fn close(fd: i32) -> i64 {
     0
 }
 
+/// Duplicate an open ext2 FD into a fresh slot with its own file offset.
+/// Used by `mmap` so a mapping keeps its file open after the caller closes
+/// the original descriptor.  Returns the new raw FD or a negative error.
+pub unsafe fn dup_fd(fd: i32) -> i64 {
+    unsafe {
+        let state = &raw mut EXT2;
+        if !(*state).ready || !is_ext2_fd(fd) { return -9; } // EBADF
+        let src = (fd - EXT2_FD_BASE) as usize;
+        if !(*state).fds[src].active { return -9; }
+        match (0..EXT2_FD_COUNT).find(|&i| !(*state).fds[i].active) {
+            None    => -24, // EMFILE
+            Some(i) => {
+                (*state).fds[i] = (*state).fds[src];
+                (EXT2_FD_BASE + i as i32) as i64
+            }
+        }
+    }
+}
+
+/// Size in bytes of the file open on `fd`, or 0 if `fd` is invalid.
+pub fn file_size(fd: i32) -> u32 {
+    if !is_ext2_fd(fd) { return 0; }
+    unsafe {
+        let state = &raw const EXT2;
+        let slot  = &(*state).fds[(fd - EXT2_FD_BASE) as usize];
+        if slot.active { slot.file_size } else { 0 }
+    }
+}
+
+/// Read from `fd` at absolute `offset` without disturbing its file position.
+pub unsafe fn pread(fd: i32, offset: u32, buf: &mut [u8]) -> i64 {
+    unsafe {
+        let state = &raw mut EXT2;
+        if !(*state).ready || !is_ext2_fd(fd) { return -5; }
+        let slot  = &raw mut (*state).fds[(fd - EXT2_FD_BASE) as usize];
+        let saved = (*slot).file_offset;
+        (*slot).file_offset = offset;
+        let n = read_fd(fd, buf);
+        (*slot).file_offset = saved;
+        n
+    }
+}
+
+/// Write to `fd` at absolute `offset` without disturbing its file position.
+pub unsafe fn pwrite(fd: i32, offset: u32, buf: &[u8]) -> i64 {
+    unsafe {
+        let state = &raw mut EXT2;
+        if !(*state).ready || !is_ext2_fd(fd) { return -5; }
+        let slot  = &raw mut (*state).fds[(fd - EXT2_FD_BASE) as usize];
+        let saved = (*slot).file_offset;
+        (*slot).file_offset = offset;
+        let n = write_fd(fd, buf);
+        (*slot).file_offset = saved;
+        n
+    }
+}
+
 /// List directory entries at `path` into `out` as `<name>\n` lines.
 /// Directories are suffixed with `/`.  Returns bytes written.
 pub unsafe fn list_dir_raw(path: &[u8], out: &mut [u8]) -> i64 {
@@ -1214,6 +1311,19 @@ pub unsafe fn is_dir(path: &[u8]) -> bool {
     inode.is_dir()
 }
 
+/// Permission bits, owner and group of the inode at `path`, or `None` if
+/// it does not exist.  Only the low 16 bits of the ids are stored.
+pub unsafe fn owner(path: &[u8]) -> Option<(u16, u32, u32)> {
+    let state = &raw const EXT2;
+    if !unsafe { (*state).ready } { return None; }
+    let path = strip_ext2_prefix(path);
+    let ino = unsafe { lookup_path(&*state, path) };
+    if ino == 0 { return None; }
+    let mut inode = Inode::zero();
+    if !unsafe { read_inode(&*state, ino, &mut inode) } { return None; }
+    Some((inode.mode & 0o7777, inode.uid as u32, inode.gid as u32))
+}
+
 /// Create a directory at `path`. Returns 0 on success, or a negative error.
 pub unsafe fn mkdir(path: &[u8]) -> i64 {
     let state = &raw mut EXT2;
@@ -38,6 +38,7 @@ struct Bpb {
 
 // ── Open file descriptor ───────────────────────────────────────────────────
 
+#[derive(Clone, Copy)]
 struct FatFd {
     active:        bool,
     writable:      bool,
@@ -1038,6 +1039,75 @@ pub unsafe fn close(fd: i32) -> i64 {
     0
 }
 
+/// Duplicate an open FAT FD into a fresh slot with its own file offset.
+/// Used by `mmap` so a mapping keeps its file open after the caller closes
+/// the original descriptor.  Returns the new raw FD or a negative error.
+pub unsafe fn dup_fd(fd: i32) -> i64 {
+    unsafe {
+        let fs = &raw mut FAT_FS;
+        if !(*fs).ready || !is_fat_fd(fd) { return -9; } // EBADF
+        let src = (fd - FAT_FD_BASE) as usize;
+        if !(*fs).fds[src].active { return -9; }
+        match (0..FAT_FD_COUNT).find(|&i| !(*fs).fds[i].active) {
+            None    => -24, // EMFILE
+            Some(i) => {
+                (*fs).fds[i] = (*fs).fds[src];
+                (FAT_FD_BASE + i as i32) as i64
+            }
+        }
+    }
+}
+
+/// Position an FD at absolute byte `offset` by walking the cluster chain
+/// from the first cluster (`file_seek` only moves `file_offset`).
+unsafe fn seek_slot(fs: *mut FatFs, slot: *mut FatFd, offset: u32) {
+    unsafe {
+        let spc           = (*fs).bpb.sectors_per_cluster as u32;
+        let cluster_bytes = spc * 512;
+        let mut cluster   = (*slot).first_cluster;
+        for _ in 0..offset / cluster_bytes {
+            let next = fat_next(&(*fs).bpb, cluster);
+            if next >= 0xFFF8 || next < 2 { break; }
+            cluster = next;
+        }
+        (*slot).cur_cluster = cluster;
+        (*slot).cur_sector  = ((offset % cluster_bytes) / 512) as u8;
+        (*slot).file_offset = offset;
+    }
+}
+
+/// Read from `fd` at absolute `offset` without disturbing its file position.
+pub unsafe fn pread(fd: i32, offset: u32, buf: &mut [u8]) -> i64 {
+    unsafe {
+        let fs = &raw mut FAT_FS;
+        if !(*fs).ready || !is_fat_fd(fd) { return -5; }
+        let slot  = &raw mut (*fs).fds[(fd - FAT_FD_BASE) as usize];
+        let saved = *slot;
+        seek_slot(fs, slot, offset);
+        let n = read_fd(fd, buf);
+        (*slot).cur_cluster = saved.cur_cluster;
+        (*slot).cur_sector  = saved.cur_sector;
+        (*slot).file_offset = saved.file_offset;
+        n
+    }
+}
+
+/// Write to `fd` at absolute `offset` without disturbing its file position.
+pub unsafe fn pwrite(fd: i32, offset: u32, buf: &[u8]) -> i64 {
+    unsafe {
+        let fs = &raw mut FAT_FS;
+        if !(*fs).ready || !is_fat_fd(fd) { return -5; }
+        let slot  = &raw mut (*fs).fds[(fd - FAT_FD_BASE) as usize];
+        let saved = *slot;
+        seek_slot(fs, slot, offset);
+        let n = write_fd(fd, buf);
+        (*slot).cur_cluster = saved.cur_cluster;
+        (*slot).cur_sector  = saved.cur_sector;
+        (*slot).file_offset = saved.file_offset;
+        n
+    }
+}
+
 /// Convert an 8.3 FAT name pair (name bytes, ext bytes) into a lowercase String.
 fn fat83_to_string(name: &[u8], ext: &[u8]) -> String {
     let mut s = String::new();
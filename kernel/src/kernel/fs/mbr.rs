@@ -17,6 +17,8 @@ pub const PTYPE_FAT16_SMALL: u8 = 0x04;
 pub const PTYPE_FAT16_LARGE: u8 = 0x06;
 /// FAT16 with LBA addressing
 pub const PTYPE_FAT16_LBA:   u8 = 0x0E;
+/// Linux swap
+pub const PTYPE_LINUX_SWAP:  u8 = 0x82;
 /// Linux ext2/ext3/ext4
 pub const PTYPE_LINUX:       u8 = 0x83;
 
@@ -155,3 +157,34 @@ pub unsafe fn ext2_lba_offset() -> Option<u32> {
     }
     None
 }
+
+/// Read the four primary partitions straight from LBA 0 of ATA disk `disk`.
+/// Unlike the cached `MBR` state this works for every disk, not just the
+/// primary master.  All `None` for unreadable disks and missing partition
+/// tables; empty entries are `None` too.
+pub unsafe fn scan(disk: usize) -> [Option<PartEntry>; 4] {
+    let mut parts = [None; 4];
+    if !ata::is_present_at(disk) { return parts; }
+    let mut buf = [0u8; 512];
+    if !unsafe { ata::read_sector(disk, 0, &mut buf) } { return parts; }
+    if buf[510] != 0x55 || buf[511] != 0xAA || buf[0] == 0xEB || buf[0] == 0xE9 {
+        return parts;
+    }
+    for (i, part) in parts.iter_mut().enumerate() {
+        let off = 446 + i * 16;
+        let e = PartEntry {
+            status:         buf[off],
+            partition_type: buf[off + 4],
+            start_lba:      u32::from_le_bytes([buf[off+8],  buf[off+9],  buf[off+10], buf[off+11]]),
+            size_sectors:   u32::from_le_bytes([buf[off+12], buf[off+13], buf[off+14], buf[off+15]]),
+        };
+        if !e.is_empty() { *part = Some(e); }
+    }
+    parts
+}
+
+/// Primary partition `n` (1–4) of ATA disk `disk`, read as by `scan`.
+pub unsafe fn read_partition(disk: usize, n: usize) -> Option<PartEntry> {
+    if !(1..=4).contains(&n) { return None; }
+    unsafe { scan(disk)[n - 1] }
+}
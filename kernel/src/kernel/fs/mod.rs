@@ -12,6 +12,7 @@ pub mod mbr;
 pub mod vfs;
 pub mod procfs;
 pub mod diskfs;
+pub mod devfs;
 
 pub use ramfs::RAMFS;
 
@@ -20,6 +21,7 @@ pub const O_RDONLY: u32 = 0;
 pub const O_WRONLY: u32 = 1;
 pub const O_RDWR:   u32 = 2;
 pub const O_CREAT:  u32 = 0x40;
+pub const O_NOCTTY: u32 = 0x100;
 pub const O_TRUNC:  u32 = 0x200;
 pub const O_APPEND: u32 = 0x400;
 
@@ -2,7 +2,8 @@
 //!
 //! `populate()` is called once at boot to create the /proc directory tree in
 //! RamFS and write the static files.  `refresh(path)` is called by vfs_open
-//! every time a dynamic file is opened so its contents are up-to-date.
+//! every time a dynamic file is opened so its contents are up-to-date, and
+//! `written(inode)` after every write to a RamFS file so tunables take effect.
 
 extern crate alloc;
 use alloc::vec::Vec;
@@ -21,6 +22,20 @@ fn push_u64(v: &mut Vec<u8>, mut n: u64) {
     v.extend_from_slice(&buf[i..]);
 }
 
+fn push_hex(v: &mut Vec<u8>, mut n: u64) {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    push_str(v, "0x");
+    let mut buf = [0u8; 16];
+    let mut i = 16usize;
+    loop {
+        i -= 1;
+        buf[i] = DIGITS[(n & 0xf) as usize];
+        n >>= 4;
+        if n == 0 { break; }
+    }
+    v.extend_from_slice(&buf[i..]);
+}
+
 // ── populate (called once at boot) ───────────────────────────────────────────
 
 pub fn populate() {
@@ -50,6 +65,24 @@ model name\t: OxideOS Virtual CPU @ 100Hz\narch\t\t: x86_64\n",
     // placeholder content for the dynamic files (will be refreshed on open)
     let _ = fs.write_file("/proc/uptime",  b"0.00 0.00\n");
     let _ = fs.write_file("/proc/meminfo", b"MemTotal: 0 kB\n");
+    let _ = fs.write_file("/proc/buddyinfo", b"");
+    let _ = fs.write_file("/proc/slabinfo",  b"slabinfo - version: 2.1\n");
+    let _ = fs.write_file("/proc/swaps",   b"Filename\tType\tSize\tUsed\tPriority\n");
+    let _ = fs.write_file("/proc/trace",   b"");
+    let _ = fs.create_dir("/proc/net");
+    let _ = fs.write_file("/proc/net/dhcp", b"");
+
+    // /proc/sys/kernel — writable tunables, read back by the kernel on use
+    // or applied by `written`
+    let _ = fs.create_dir("/proc/sys");
+    let _ = fs.create_dir("/proc/sys/kernel");
+    let _ = fs.write_file(
+        "/proc/sys/kernel/core_pattern",
+        crate::kernel::coredump::DEFAULT_PATTERN.as_bytes(),
+    );
+    let _ = fs.append_file("/proc/sys/kernel/core_pattern", b"\n");
+    let _ = fs.write_file(crate::kernel::systrace::TRACE_PATH, b"0\n");
+    let _ = fs.write_file(crate::kernel::keymap::KEYMAP_PATH, b"us\n");
 }
 
 // ── refresh (called on every vfs_open for /proc/* dynamic files) ─────────────
@@ -58,10 +91,28 @@ pub fn refresh(path: &str) {
     match path {
         "/proc/uptime"  => refresh_uptime(),
         "/proc/meminfo" => refresh_meminfo(),
+        "/proc/swaps"   => refresh_swaps(),
+        "/proc/buddyinfo" => refresh_buddyinfo(),
+        "/proc/slabinfo"  => refresh_slabinfo(),
+        "/proc/trace"     => refresh_trace(),
+        "/proc/net/dhcp"  => refresh_net_dhcp(),
         _ => {}
     }
 }
 
+// ── written (called after every write to a RamFS file) ───────────────────────
+
+/// Apply the tunable stored in RamFS inode `inode_idx`, if it is one.
+pub unsafe fn written(inode_idx: usize) {
+    let Some(fs) = (unsafe { crate::kernel::fs::ramfs::RAMFS.get() }) else { return };
+    let node = Some(inode_idx);
+    if fs.resolve(crate::kernel::systrace::TRACE_PATH) == node {
+        unsafe { crate::kernel::systrace::reload(); }
+    } else if fs.resolve(crate::kernel::keymap::KEYMAP_PATH) == node {
+        unsafe { crate::kernel::keymap::reload(); }
+    }
+}
+
 fn refresh_uptime() {
     let ticks  = unsafe { crate::kernel::timer::get_ticks() };
     let secs   = ticks / 100;
@@ -90,9 +141,144 @@ fn refresh_meminfo() {
     push_str(&mut buf, "MemAvailable: "); push_u64(&mut buf, free_kb);  push_str(&mut buf, " kB\n");
     push_str(&mut buf, "MemUsed:      "); push_u64(&mut buf, used_kb);  push_str(&mut buf, " kB\n");
 
+    let (swap_pages, swap_used) = crate::kernel::swap::stats();
+    let swap_total_kb = (swap_pages * 4096 / 1024) as u64;
+    let swap_free_kb  = ((swap_pages - swap_used) * 4096 / 1024) as u64;
+    push_str(&mut buf, "SwapTotal:    "); push_u64(&mut buf, swap_total_kb); push_str(&mut buf, " kB\n");
+    push_str(&mut buf, "SwapFree:     "); push_u64(&mut buf, swap_free_kb);  push_str(&mut buf, " kB\n");
+
     write_proc_file("/proc/meminfo", &buf);
 }
 
+/// One line per zone: free block counts for orders 0 (4 KiB) .. 10 (4 MiB).
+fn refresh_buddyinfo() {
+    let mut buf: Vec<u8> = Vec::new();
+    for (name, managed, nr_free) in crate::kernel::paging_allocator::buddy_info() {
+        if managed == 0 { continue; }
+        push_str(&mut buf, "Node 0, zone ");
+        for _ in name.len()..8 { buf.push(b' '); }
+        push_str(&mut buf, name);
+        for n in nr_free {
+            let start = buf.len();
+            push_u64(&mut buf, n as u64);
+            let width = buf.len() - start;
+            for _ in width..7 { buf.insert(start, b' '); }
+        }
+        buf.push(b'\n');
+    }
+
+    write_proc_file("/proc/buddyinfo", &buf);
+}
+
+/// Linux `slabinfo` 2.1 layout, plus lifetime alloc/free counts per cache.
+/// With `slab_debug`, every read also sweeps all caches for corruption.
+fn refresh_slabinfo() {
+    crate::kernel::slab::check_all();
+
+    let mut buf: Vec<u8> = Vec::new();
+    push_str(&mut buf, "slabinfo - version: 2.1\n");
+    push_str(&mut buf, "# name            <active_objs> <num_objs> <objsize> <objperslab> <pagesperslab>");
+    push_str(&mut buf, " : slabdata <active_slabs> <num_slabs> : stats <allocs> <frees>\n");
+    for (name, active, total, size, per_slab, slabs, allocs, frees) in crate::kernel::slab::stats() {
+        push_str(&mut buf, name);
+        for _ in name.len()..18 { buf.push(b' '); }
+        for n in [active, total, size, per_slab, crate::kernel::slab::PAGES_PER_SLAB] {
+            push_u64(&mut buf, n as u64);
+            buf.push(b' ');
+        }
+        push_str(&mut buf, ": slabdata ");
+        push_u64(&mut buf, slabs as u64); buf.push(b' ');
+        push_u64(&mut buf, slabs as u64);
+        push_str(&mut buf, " : stats ");
+        push_u64(&mut buf, allocs); buf.push(b' ');
+        push_u64(&mut buf, frees);
+        buf.push(b'\n');
+    }
+
+    write_proc_file("/proc/slabinfo", &buf);
+}
+
+/// One line per traced syscall, oldest first:
+/// `seq pid tick name(arg1, ..., arg6) = result <ticks>`.  A call still in
+/// progress ends in `...`; one that never returned shows `= ?`.
+fn refresh_trace() {
+    use crate::kernel::systrace::Outcome;
+
+    let mut buf: Vec<u8> = Vec::new();
+    push_str(&mut buf, "# seq pid tick call = result <ticks>\n");
+    for rec in crate::kernel::systrace::records() {
+        push_u64(&mut buf, rec.seq);   buf.push(b' ');
+        push_u64(&mut buf, rec.pid as u64); buf.push(b' ');
+        push_u64(&mut buf, rec.start); buf.push(b' ');
+        push_str(&mut buf, rec.name());
+        buf.push(b'(');
+        for (i, a) in rec.args.iter().enumerate() {
+            if i > 0 { push_str(&mut buf, ", "); }
+            push_hex(&mut buf, *a);
+        }
+        buf.push(b')');
+        match rec.outcome {
+            Outcome::Running => push_str(&mut buf, " ..."),
+            Outcome::Unfinished => push_str(&mut buf, " = ?"),
+            Outcome::Returned(v) => {
+                push_str(&mut buf, " = ");
+                if v < 0 { buf.push(b'-'); }
+                push_u64(&mut buf, v.unsigned_abs());
+            }
+        }
+        if rec.outcome != Outcome::Running {
+            push_str(&mut buf, " <");
+            push_u64(&mut buf, rec.ticks);
+            buf.push(b'>');
+        }
+        buf.push(b'\n');
+    }
+
+    write_proc_file("/proc/trace", &buf);
+}
+
+fn push_ipv4(v: &mut Vec<u8>, ip: [u8; 4]) {
+    for (i, b) in ip.iter().enumerate() {
+        if i > 0 { v.push(b'.'); }
+        push_u64(v, *b as u64);
+    }
+}
+
+/// `/proc/net/dhcp` — the interface and its lease, one `key value` per line.
+/// `bound 0` means the static QEMU fallback addresses are in use.
+fn refresh_net_dhcp() {
+    use crate::kernel::net::{self, stack::NET_CONFIG};
+
+    let mut buf: Vec<u8> = Vec::new();
+    push_str(&mut buf, "nic "); push_str(&mut buf, net::nic_name()); buf.push(b'\n');
+    if net::is_present() {
+        let nc = unsafe { &*core::ptr::addr_of!(NET_CONFIG) };
+        push_str(&mut buf, "bound ");   push_u64(&mut buf, nc.dhcp_ok as u64); buf.push(b'\n');
+        push_str(&mut buf, "ip ");      push_ipv4(&mut buf, nc.ip);
+        buf.push(b'/'); push_u64(&mut buf, nc.prefix_len as u64); buf.push(b'\n');
+        push_str(&mut buf, "gateway "); push_ipv4(&mut buf, nc.gateway); buf.push(b'\n');
+        push_str(&mut buf, "dns ");     push_ipv4(&mut buf, nc.dns); buf.push(b'\n');
+    }
+
+    write_proc_file("/proc/net/dhcp", &buf);
+}
+
+fn refresh_swaps() {
+    let mut buf: Vec<u8> = Vec::new();
+    push_str(&mut buf, "Filename\t\t\t\tType\t\tSize\t\tUsed\t\tPriority\n");
+    if let Some((path, is_file)) = crate::kernel::swap::active() {
+        let (pages, used) = crate::kernel::swap::stats();
+        push_str(&mut buf, &path);
+        push_str(&mut buf, if is_file { "\t\t\t\tfile\t\t" } else { "\t\t\t\tpartition\t" });
+        push_u64(&mut buf, (pages * 4096 / 1024) as u64);
+        push_str(&mut buf, "\t\t");
+        push_u64(&mut buf, (used * 4096 / 1024) as u64);
+        push_str(&mut buf, "\t\t-2\n");
+    }
+
+    write_proc_file("/proc/swaps", &buf);
+}
+
 fn write_proc_file(path: &str, data: &[u8]) {
     let Some(fs) = (unsafe { crate::kernel::fs::ramfs::RAMFS.get() }) else { return };
     if let Some(idx) = fs.resolve(path) {
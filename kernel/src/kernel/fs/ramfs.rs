@@ -1,8 +1,9 @@
 // src/kernel/fs/ramfs.rs
 //! In-memory filesystem (RamFS) for OxideOS.
 //!
-//! Stores files and directories as a flat Vec<INode>.  Each inode knows its
-//! parent inode index so the tree can be walked without a hash-map.
+//! Stores files and directories as a flat Vec of inodes, each allocated from
+//! the `ramfs_inode` slab cache.  Each inode knows its parent inode index so
+//! the tree can be walked without a hash-map.
 //!
 //! The global singleton `RAMFS` is an `UnsafeCell<Option<RamFs>>` that is
 //! initialised once by `RAMFS.init()` after the heap allocator is ready.
@@ -14,9 +15,11 @@
 
 extern crate alloc;
 
+use alloc::boxed::Box;
 use alloc::string::String;
 use alloc::vec::Vec;
 use core::cell::UnsafeCell;
+use crate::kernel::slab::{self, RAMFS_INODE};
 
 use super::{
     O_WRONLY, O_RDWR, O_CREAT, O_TRUNC, O_APPEND,
@@ -65,10 +68,30 @@ pub enum FdBackend {
     Ext2,
     /// Anonymous pipe end; `raw_fd` is the pipe's raw fd, `writable` tells direction.
     Pipe,
+    /// eventfd, timerfd or signalfd; `raw_fd` is the `anonfd` slot.
+    Anon,
     /// /dev/null — writes discard, reads return EOF.
     DevNull,
     /// /dev/tty  — reads come from stdin ring; writes go to console.
     DevTty,
+    /// /dev/ttyS0, /dev/tty1..6, /dev/pts/N — `raw_fd` is the `tty` index.
+    Tty,
+    /// Pseudo-terminal master from /dev/ptmx; `raw_fd` is the pty number.
+    PtyMaster,
+    /// /dev/zero, /dev/full, /dev/random, /dev/urandom — `raw_fd` is the
+    /// Linux memory-device minor (see `devfs`).
+    Mem,
+    /// /dev/hda..hdd raw disk — `raw_fd` is the ATA disk index, `offset`
+    /// the byte position.
+    Block,
+    /// /dev/fb0 — `offset` is the byte position in the framebuffer.
+    Fb,
+    /// /dev/input/eventN — `raw_fd` is the `evdev` client slot.
+    Evdev,
+    /// /dev/dsp — the OSS playback stream in `dsp`.
+    Dsp,
+    /// /dev/mixer — stateless; only takes ioctls.
+    Mixer,
     /// Open directory; `raw_fd` unused, `inode_idx` encodes filesystem+path hash.
     /// `offset` tracks how many getdents64 entries have been returned.
     Dir,
@@ -81,7 +104,9 @@ pub struct FdEntry {
     pub backend:   FdBackend,
     /// RamFS: inode index.
     pub inode_idx: usize,
-    /// Fat16: internal FAT raw fd.  Pipe: raw pipe fd.
+    /// Fat16: internal FAT raw fd.  Pipe: raw pipe fd.  Anon: anonfd slot.
+    /// Tty: tty index.  PtyMaster: pty number.  Evdev: client slot.
+    /// Mem/Block: see `FdBackend`.
     pub raw_fd:    i32,
     pub offset:    usize,
     pub writable:  bool,
@@ -202,15 +227,16 @@ impl FdTable {
         }
     }
 
-    /// Allocate one FD slot for a /dev file.
-    pub fn open_dev(&mut self, backend: FdBackend) -> i64 {
+    /// Allocate one FD slot for a /dev file; `minor` selects the device
+    /// within `backend` (the tty index for `Tty`).
+    pub fn open_dev(&mut self, backend: FdBackend, minor: i32, writable: bool) -> i64 {
         match self.alloc_fd() {
             None   => -24, // EMFILE
             Some(fd) => {
                 self.entries[fd] = Some(FdEntry {
                     backend,
-                    inode_idx: 0, raw_fd: 0, offset: 0,
-                    writable: backend != FdBackend::DevNull,
+                    inode_idx: 0, raw_fd: minor, offset: 0,
+                    writable,
                     append: false,
                     dir_path: [0u8; 64], dir_path_len: 0,
                 });
@@ -219,6 +245,22 @@ impl FdTable {
         }
     }
 
+    /// Allocate one FD slot for an eventfd/timerfd/signalfd in `anonfd` slot `slot`.
+    pub fn open_anon(&mut self, slot: i32) -> i64 {
+        match self.alloc_fd() {
+            None     => EMFILE,
+            Some(fd) => {
+                self.entries[fd] = Some(FdEntry {
+                    backend: FdBackend::Anon,
+                    inode_idx: 0, raw_fd: slot, offset: 0,
+                    writable: true, append: false,
+                    dir_path: [0u8; 64], dir_path_len: 0,
+                });
+                fd as i64
+            }
+        }
+    }
+
     /// Allocate one FD slot for an open directory.
     pub fn open_dir(&mut self, path: &[u8]) -> i64 {
         match self.alloc_fd() {
@@ -246,8 +288,12 @@ impl FdTable {
             Some(e) => {
                 match e.backend {
                     FdBackend::Pipe  => unsafe { crate::kernel::pipe::close(e.raw_fd); }
+                    FdBackend::Anon  => unsafe { crate::kernel::anonfd::close(e.raw_fd); }
                     FdBackend::Fat16 => unsafe { crate::kernel::fat::close(e.raw_fd); }
                     FdBackend::Ext2  => unsafe { crate::kernel::ext2::close(e.raw_fd); }
+                    FdBackend::PtyMaster | FdBackend::Tty => unsafe { crate::kernel::pty::release(&e); }
+                    FdBackend::Evdev => unsafe { crate::kernel::evdev::release(&e); }
+                    FdBackend::Dsp   => unsafe { crate::kernel::dsp::release(&e); }
                     _ => {}
                 }
                 0
@@ -266,6 +312,9 @@ impl FdTable {
             FdBackend::Pipe => {
                 return unsafe { crate::kernel::pipe::read(entry.raw_fd, buf) };
             }
+            FdBackend::Anon => {
+                return unsafe { crate::kernel::anonfd::read(entry.raw_fd, buf) };
+            }
             FdBackend::Fat16 => {
                 return unsafe { crate::kernel::fat::read_fd(entry.raw_fd, buf) };
             }
@@ -280,6 +329,22 @@ impl FdTable {
                     None     => -6,
                 };
             }
+            FdBackend::Tty => {
+                return unsafe { crate::kernel::tty::read(entry.raw_fd as usize, buf) };
+            }
+            FdBackend::PtyMaster => {
+                return unsafe { crate::kernel::pty::read(entry.raw_fd as usize, buf) };
+            }
+            FdBackend::Evdev => {
+                return unsafe { crate::kernel::evdev::read(entry.raw_fd as usize, buf) };
+            }
+            // Playback only.
+            FdBackend::Dsp | FdBackend::Mixer => return -22, // EINVAL
+            FdBackend::Mem | FdBackend::Block | FdBackend::Fb => {
+                let r = unsafe { crate::kernel::devfs::read(&entry, buf) };
+                if r > 0 { if let Some(e) = &mut self.entries[fd as usize] { e.offset += r as usize; } }
+                return r;
+            }
             FdBackend::Dir => return EISDIR,
             FdBackend::RamFS => {}
         }
@@ -307,6 +372,9 @@ impl FdTable {
             FdBackend::Pipe  => {
                 return unsafe { crate::kernel::pipe::write(entry.raw_fd, buf) };
             }
+            FdBackend::Anon  => {
+                return unsafe { crate::kernel::anonfd::write(entry.raw_fd, buf) };
+            }
             FdBackend::Fat16 => {
                 return unsafe { crate::kernel::fat::write_fd(entry.raw_fd, buf) };
             }
@@ -321,6 +389,20 @@ impl FdTable {
                 }
                 return buf.len() as i64;
             }
+            FdBackend::Tty => {
+                return unsafe { crate::kernel::tty::write(entry.raw_fd as usize, buf) };
+            }
+            FdBackend::PtyMaster => {
+                return unsafe { crate::kernel::pty::write(entry.raw_fd as usize, buf) };
+            }
+            // Events cannot be injected.
+            FdBackend::Evdev | FdBackend::Mixer => return -22, // EINVAL
+            FdBackend::Dsp => return unsafe { crate::kernel::dsp::write(buf) },
+            FdBackend::Mem | FdBackend::Block | FdBackend::Fb => {
+                let r = unsafe { crate::kernel::devfs::write(&entry, buf) };
+                if r > 0 { if let Some(e) = &mut self.entries[fd as usize] { e.offset += r as usize; } }
+                return r;
+            }
             FdBackend::Dir => return EISDIR,
             FdBackend::RamFS => {}
         }
@@ -353,13 +435,21 @@ impl FdTable {
                 // Addref the resource being duplicated.
                 match e.backend {
                     FdBackend::Pipe  => unsafe { crate::kernel::pipe::addref(e.raw_fd); }
+                    FdBackend::Anon  => unsafe { crate::kernel::anonfd::addref(e.raw_fd); }
+                    FdBackend::PtyMaster | FdBackend::Tty => unsafe { crate::kernel::pty::addref(&e); }
+                    FdBackend::Evdev => unsafe { crate::kernel::evdev::addref(&e); }
+                    FdBackend::Dsp   => unsafe { crate::kernel::dsp::addref(&e); }
                     _ => {}
                 }
                 // Close whatever is currently at new_fd.
                 if let Some(old) = self.entries[new_fd as usize] {
                     match old.backend {
                         FdBackend::Pipe  => unsafe { crate::kernel::pipe::close(old.raw_fd); }
+                        FdBackend::Anon  => unsafe { crate::kernel::anonfd::close(old.raw_fd); }
                         FdBackend::Fat16 => unsafe { crate::kernel::fat::close(old.raw_fd); }
+                        FdBackend::PtyMaster | FdBackend::Tty => unsafe { crate::kernel::pty::release(&old); }
+                        FdBackend::Evdev => unsafe { crate::kernel::evdev::release(&old); }
+                        FdBackend::Dsp   => unsafe { crate::kernel::dsp::release(&old); }
                         _ => {}
                     }
                 }
@@ -390,7 +480,7 @@ impl FdTable {
 /// The filesystem tree.  Owns only inodes — FD state lives in each task's
 /// `FdTable` so it is naturally per-process.
 pub struct RamFs {
-    pub inodes: Vec<INode>,
+    pub inodes: Vec<Box<INode>>,
 }
 
 impl RamFs {
@@ -399,7 +489,7 @@ impl RamFs {
         let mut fs = Self { inodes: Vec::new() };
 
         // inode 0 = root directory
-        fs.inodes.push(INode {
+        fs.inodes.push(slab::boxed(RAMFS_INODE, INode {
             name:       String::from("/"),
             parent_idx: ROOT_PARENT,
             kind:       NodeKind::Directory,
@@ -407,7 +497,7 @@ impl RamFs {
             mode:       0o755,
             uid:        0,
             gid:        0,
-        });
+        }));
 
         // Standard directories
         let _ = fs.create_dir("/etc");
@@ -418,14 +508,68 @@ impl RamFs {
         // Pre-populated files
         let _ = fs.write_file("/etc/hostname", b"oxideos\n");
         let _ = fs.write_file("/etc/version",  crate::version::ETC_VERSION.as_bytes());
+        // Library search path for musl's dynamic linker.
+        let _ = fs.write_file("/etc/ld-musl-x86_64.path", b"/lib\n/usr/lib\n");
         let _ = fs.write_file(
             "/etc/motd",
             b"Welcome to OxideOS!\nType 'help' in the terminal for commands.\n",
         );
 
+        // Accounts.  Passwords are "root" and "user"; the shadow format is
+        // `$oxide$<salt>$<hex>`, verified by oxide_rt::passwd.
+        let _ = fs.write_file(
+            "/etc/passwd",
+            b"root:x:0:0:root:/root:/bin/sh\n\
+              user:x:1000:1000:OxideOS user:/home/user:/bin/sh\n",
+        );
+        let _ = fs.write_file(
+            "/etc/group",
+            b"root:x:0:\ntty:x:5:\ndisk:x:6:\nwheel:x:10:user\nshadow:x:42:\naudio:x:29:user\nvideo:x:44:user\ninput:x:104:user\nuser:x:1000:\n",
+        );
+        let _ = fs.write_file(
+            "/etc/shadow",
+            b"root:$oxide$Xq3vR8tZ$9f2d70f4fa7b2a6a16708191da00da098f7eed8bb185d7198a7c977509e7e017:0:0:99999:7:::\n\
+              user:$oxide$p2Lk9sWm$1b16d70d7908652b514f4a1f0dca33d6db9841ee17ba3df93454f80366c75f1a:0:0:99999:7:::\n",
+        );
+        fs.set_owner("/etc/shadow", 0o640, 0, 42);
+        let _ = fs.create_dir("/root");
+        fs.set_owner("/root", 0o700, 0, 0);
+        let _ = fs.create_dir("/home/user");
+        fs.set_owner("/home/user", 0o755, 1000, 1000);
+        fs.set_owner("/tmp", 0o1777, 0, 0);
+
+        // Boot configuration read by /sbin/init.
+        let _ = fs.create_dir("/sbin");
+        let _ = fs.create_dir("/var");
+        let _ = fs.create_dir("/var/run");
+        let _ = fs.create_dir("/var/log");
+        let _ = fs.write_file(
+            "/etc/inittab",
+            b"# id::action:command [args]\n\
+              # actions: sysinit, wait, once, respawn, shutdown\n\
+              getty::respawn:/sbin/getty ttyS0\n\
+              tty1::respawn:/sbin/getty tty1\n\
+              tty2::respawn:/sbin/getty tty2\n\
+              netd::respawn:/sbin/netd\n\
+              desktop::respawn:/bin/session\n",
+        );
+        let _ = fs.create_dir("/etc/rc.d");
+        let _ = fs.write_file("/etc/rc.d/S10banner", b"# Print the release on the console.\ncat /etc/version\n");
+        let _ = fs.write_file("/etc/rc.d/K90goodbye", b"echo rc: stopping services\n");
+
         fs
     }
 
+    /// Set mode and owner of an existing inode (used while seeding).
+    fn set_owner(&mut self, path: &str, mode: u16, uid: u32, gid: u32) {
+        if let Some(idx) = self.resolve(path) {
+            let node = &mut self.inodes[idx];
+            node.mode = mode;
+            node.uid  = uid;
+            node.gid  = gid;
+        }
+    }
+
     // ── Path helpers ──────────────────────────────────────────────────────
 
     /// Resolve an absolute path to an inode index.
@@ -446,7 +590,30 @@ impl RamFs {
         self.inodes.iter().position(|n| n.parent_idx == parent_idx && n.name == name)
     }
 
+    /// Absolute path of inode `idx` (the inverse of `resolve`).
+    pub fn path_of(&self, mut idx: usize) -> Option<String> {
+        let mut parts: Vec<&str> = Vec::new();
+        while idx != 0 {
+            let node = self.inodes.get(idx)?;
+            parts.push(&node.name);
+            idx = node.parent_idx;
+            if idx == ROOT_PARENT { return None; }
+        }
+        let mut path = String::new();
+        for part in parts.iter().rev() {
+            path.push('/');
+            path.push_str(part);
+        }
+        if path.is_empty() { path.push('/'); }
+        Some(path)
+    }
+
     /// Split "/foo/bar" → ("/foo", "bar").  Returns None for root.
+    /// Inode of the directory that holds (or would hold) `path`.
+    pub fn parent(&self, path: &str) -> Option<usize> {
+        Self::split_path(path).and_then(|(dir, _)| self.resolve(dir))
+    }
+
     fn split_path(path: &str) -> Option<(&str, &str)> {
         let path = path.trim_end_matches('/');
         if path.is_empty() || path == "/" { return None; }
@@ -466,7 +633,7 @@ impl RamFs {
         let parent_idx = self.resolve(parent_path).ok_or(ENOENT)?;
         if self.inodes[parent_idx].kind != NodeKind::Directory { return Err(ENOTDIR); }
         let idx = self.inodes.len();
-        self.inodes.push(INode {
+        self.inodes.push(slab::boxed(RAMFS_INODE, INode {
             name:       String::from(name),
             parent_idx,
             kind:       NodeKind::Directory,
@@ -474,7 +641,7 @@ impl RamFs {
             mode:       0o755,
             uid:        0,
             gid:        0,
-        });
+        }));
         Ok(idx)
     }
 
@@ -524,7 +691,7 @@ impl RamFs {
         let parent_idx = self.resolve(parent_path).ok_or(ENOENT)?;
         if self.inodes[parent_idx].kind != NodeKind::Directory { return Err(ENOTDIR); }
         let idx = self.inodes.len();
-        self.inodes.push(INode {
+        self.inodes.push(slab::boxed(RAMFS_INODE, INode {
             name:       String::from(name),
             parent_idx,
             kind:       NodeKind::File,
@@ -532,7 +699,7 @@ impl RamFs {
             mode:       0o644,
             uid:        0,
             gid:        0,
-        });
+        }));
         Ok(idx)
     }
 
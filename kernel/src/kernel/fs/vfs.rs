@@ -7,11 +7,17 @@
 //! | `/dev/*`     | DevFS         |
 //! | `/disk/*`    | FAT16         |
 //! | `/ext2/*`    | ext2          |
+//! | `/lib/*`     | ext2 `/lib`   |
 //! | `/proc/*`    | procfs        |
 //! | `/store/*`   | DiskStore     |
 //! | `/`          | RamFS         |
 
-use crate::kernel::fs::ramfs::FdBackend;
+extern crate alloc;
+
+use alloc::vec::Vec;
+use crate::kernel::fs::devfs::DevNode;
+use crate::kernel::fs::ramfs::{FdBackend, RamFs};
+use crate::kernel::cred::{self, MAY_EXEC, MAY_READ, MAY_WRITE, S_ISVTX};
 
 // ── Resolved ──────────────────────────────────────────────────────────────
 
@@ -19,7 +25,9 @@ pub enum Resolved<'a> {
     RamFS     { path: &'a str },
     Fat16     { fat_path: &'a [u8] },
     Ext2      { path: &'a [u8] },
-    Dev       { backend: FdBackend },
+    /// A `/dev` node from the devfs registry; `None` if there is no such
+    /// device.
+    Dev       { node: Option<DevNode> },
     Proc      { path: &'a str },
     /// `/store` or `/store/<id>` — backed by the on-disk record store.
     DiskStore { path: &'a str },
@@ -27,12 +35,8 @@ pub enum Resolved<'a> {
 
 pub fn resolve<'a>(path: &'a str) -> Resolved<'a> {
     if path.starts_with("/dev/") || path == "/dev" {
-        let dev_name = if path.len() > 5 { &path[5..] } else { "" };
-        let backend = match dev_name {
-            "tty" => FdBackend::DevTty,
-            _     => FdBackend::DevNull,
-        };
-        return Resolved::Dev { backend };
+        let dev_name = path[4..].trim_matches('/');
+        return Resolved::Dev { node: crate::kernel::devfs::lookup(dev_name) };
     }
     if path == "/disk" || path.starts_with("/disk/") {
         return Resolved::Fat16 { fat_path: path.as_bytes() };
@@ -40,6 +44,11 @@ pub fn resolve<'a>(path: &'a str) -> Resolved<'a> {
     if path == "/ext2" || path.starts_with("/ext2/") {
         return Resolved::Ext2 { path: path.as_bytes() };
     }
+    // The dynamic linker and shared libraries live on ext2; `/lib` reaches
+    // them in place (ext2 paths without the `/ext2` prefix are root-relative).
+    if (path == "/lib" || path.starts_with("/lib/")) && crate::kernel::ext2::is_ready() {
+        return Resolved::Ext2 { path: path.as_bytes() };
+    }
     if path == "/proc" || path.starts_with("/proc/") {
         return Resolved::Proc { path };
     }
@@ -58,7 +67,41 @@ pub unsafe fn vfs_open(path: &str, flags: u32) -> i64 {
     let fdt   = &raw mut (*sched).tasks[idx].fd_table;
 
     match resolve(path) {
-        Resolved::Dev { backend } => (*fdt).open_dev(backend),
+        Resolved::Dev { node: None } => -2,
+        Resolved::Dev { node: Some(node) } if node.backend == FdBackend::Dir => {
+            unsafe { (*fdt).open_dir(path.as_bytes()) }
+        }
+        Resolved::Dev { node: Some(node) } if node.backend == FdBackend::PtyMaster => {
+            unsafe { crate::kernel::pty::open_master(&mut *fdt, flags) }
+        }
+        Resolved::Dev { node: Some(node) } if node.backend == FdBackend::Evdev => {
+            if !crate::kernel::devfs::may_open(&node, flags) { return -13; } // EACCES
+            unsafe { crate::kernel::evdev::open(&mut *fdt, node.minor as usize, flags) }
+        }
+        Resolved::Dev { node: Some(node) } if node.backend == FdBackend::Dsp => {
+            if !crate::kernel::devfs::may_open(&node, flags) { return -13; } // EACCES
+            unsafe { crate::kernel::dsp::open(&mut *fdt, flags) }
+        }
+        Resolved::Dev { node: Some(node) } => {
+            let (backend, minor) = (node.backend, node.minor);
+            if !crate::kernel::devfs::may_open(&node, flags) { return -13; } // EACCES
+            if !crate::kernel::pty::may_open(backend, minor) { return -5; } // EIO
+            // Terminals ignore the access mode, as they always have.
+            let writable = match backend {
+                FdBackend::Mem | FdBackend::Block | FdBackend::Fb => {
+                    flags & (crate::kernel::fs::O_WRONLY | crate::kernel::fs::O_RDWR) != 0
+                }
+                b => b != FdBackend::DevNull,
+            };
+            let fd = unsafe { (*fdt).open_dev(backend, minor, writable) };
+            if let Some(e) = usize::try_from(fd).ok().and_then(|fd| unsafe { (*fdt).entries[fd] }) {
+                unsafe { crate::kernel::pty::addref(&e); }
+                if backend == FdBackend::Tty && flags & crate::kernel::fs::O_NOCTTY == 0 {
+                    unsafe { crate::kernel::tty::opened(minor as usize); }
+                }
+            }
+            fd
+        }
 
         Resolved::Fat16 { fat_path } => {
             if !crate::kernel::ata::is_present() { return -19; }
@@ -89,10 +132,17 @@ pub unsafe fn vfs_open(path: &str, flags: u32) -> i64 {
         Resolved::RamFS { path } => {
             match crate::kernel::fs::ramfs::RAMFS.get() {
                 Some(fs) => {
+                    let err = ramfs_open_check(fs, path, flags);
+                    if err < 0 { return err; }
                     if fs.list_dir(path).is_some() {
                         return (*fdt).open_dir(path.as_bytes());
                     }
-                    (*fdt).open(fs, path, flags)
+                    let created = fs.resolve(path).is_none();
+                    let fd = unsafe { (*fdt).open(fs, path, flags) };
+                    if fd >= 0 && created {
+                        if let Some(i) = fs.resolve(path) { ramfs_set_creator(fs, i); }
+                    }
+                    fd
                 }
                 None => -2,
             }
@@ -136,16 +186,177 @@ pub unsafe fn vfs_open(path: &str, flags: u32) -> i64 {
     }
 }
 
+// ── RamFS permissions ─────────────────────────────────────────────────────
+//
+// RamFS is the only backend with real ownership; FAT16 and ext2 files are
+// open to everyone.  Checks use the caller's filesystem uid/gid.
+
+/// May the current task access inode `idx` for the `MAY_*` bits in `want`?
+/// `None` (no such inode) is never allowed.
+pub fn ramfs_may(fs: &RamFs, idx: Option<usize>, want: u32) -> bool {
+    let Some(idx) = idx else { return false };
+    let node = &fs.inodes[idx];
+    cred::get().may_access(want, node.mode, node.uid, node.gid)
+}
+
+/// `EACCES` if the current task may not open RamFS `path` with `flags`.
+/// Creating a file needs write and search permission on its directory.
+fn ramfs_open_check(fs: &RamFs, path: &str, flags: u32) -> i64 {
+    use crate::kernel::fs::{O_CREAT, O_RDWR, O_TRUNC, O_WRONLY};
+    let want = match fs.resolve(path) {
+        None if flags & O_CREAT != 0 => {
+            return if ramfs_may(fs, fs.parent(path), MAY_WRITE | MAY_EXEC) { 0 } else { -13 };
+        }
+        None => return 0, // the open itself reports ENOENT
+        Some(_) if flags & O_WRONLY != 0 => MAY_WRITE,
+        Some(_) if flags & O_RDWR   != 0 => MAY_READ | MAY_WRITE,
+        Some(_) if flags & O_TRUNC  != 0 => MAY_READ | MAY_WRITE,
+        Some(_) => MAY_READ,
+    };
+    if ramfs_may(fs, fs.resolve(path), want) { 0 } else { -13 } // EACCES
+}
+
+/// `0` if the current task may unlink or rename away RamFS `path`: it needs
+/// write and search permission on the directory, and in a sticky directory
+/// (`/tmp`) must also own the entry or the directory.
+pub fn ramfs_may_remove(fs: &RamFs, path: &str) -> i64 {
+    let dir = fs.parent(path);
+    if !ramfs_may(fs, dir, MAY_WRITE | MAY_EXEC) { return -13; } // EACCES
+    let (Some(dir), Some(idx)) = (dir, fs.resolve(path)) else { return 0 };
+    let c = cred::get();
+    let sticky = fs.inodes[dir].mode & S_ISVTX != 0;
+    if sticky && c.fsuid != 0 && c.fsuid != fs.inodes[idx].uid && c.fsuid != fs.inodes[dir].uid {
+        return -1; // EPERM
+    }
+    0
+}
+
+/// New inodes belong to the creating task's filesystem uid and gid.
+fn ramfs_set_creator(fs: &mut RamFs, idx: usize) {
+    let c = cred::get();
+    fs.inodes[idx].uid = c.fsuid;
+    fs.inodes[idx].gid = c.fsgid;
+}
+
+// ── vfs_read_file ─────────────────────────────────────────────────────────
+
+/// Read a whole regular file into memory without touching any fd table.
+/// Used by the ELF loader (program interpreters) and exec.  Returns `None`
+/// if the file is missing, empty, or lives on a backend without plain files.
+pub unsafe fn vfs_read_file(path: &str) -> Option<Vec<u8>> {
+    let data = match resolve(path) {
+        Resolved::RamFS { path } | Resolved::Proc { path } => {
+            unsafe { crate::kernel::fs::ramfs::RAMFS.get() }?.read_file(path)?.to_vec()
+        }
+        Resolved::Fat16 { fat_path } => {
+            if !crate::kernel::ata::is_present() { return None; }
+            let fd = unsafe { crate::kernel::fat::open(fat_path, 0) };
+            if fd < 0 { return None; }
+            let mut buf = Vec::new();
+            let mut tmp = [0u8; 512];
+            loop {
+                let n = unsafe { crate::kernel::fat::read_fd(fd as i32, &mut tmp) };
+                if n <= 0 { break; }
+                buf.extend_from_slice(&tmp[..n as usize]);
+            }
+            let _ = unsafe { crate::kernel::fat::close(fd as i32) };
+            buf
+        }
+        Resolved::Ext2 { path: ext2_path } => {
+            if !crate::kernel::ext2::is_ready() { return None; }
+            let fd = unsafe { crate::kernel::ext2::open(ext2_path, 0) };
+            if fd < 0 { return None; }
+            let mut buf = Vec::new();
+            let mut tmp = [0u8; 4096];
+            loop {
+                let n = unsafe { crate::kernel::ext2::read_fd(fd as i32, &mut tmp) };
+                if n <= 0 { break; }
+                buf.extend_from_slice(&tmp[..n as usize]);
+            }
+            let _ = unsafe { crate::kernel::ext2::close(fd as i32) };
+            buf
+        }
+        Resolved::Dev { .. } | Resolved::DiskStore { .. } => return None,
+    };
+    if data.is_empty() { None } else { Some(data) }
+}
+
+// ── vfs_write_file ────────────────────────────────────────────────────────
+
+/// Create or truncate `path` and write `data` to it, without touching any
+/// fd table.  Used for kernel-generated files such as core dumps, on behalf
+/// of a task with credentials `cred` (not necessarily the running one).  On
+/// RamFS a new file is created with `mode`, owned by the filesystem ids,
+/// which need write access to the directory; an existing file is replaced
+/// only if it is a regular file they own.  Returns the number of bytes
+/// written or a negative errno.
+pub unsafe fn vfs_write_file(path: &str, data: &[u8], cred: &cred::Credentials, mode: u16) -> i64 {
+    use crate::kernel::fs::{O_CREAT, O_TRUNC, O_WRONLY};
+    let flags = O_WRONLY | O_CREAT | O_TRUNC;
+    match resolve(path) {
+        Resolved::RamFS { path } => {
+            let Some(fs) = (unsafe { crate::kernel::fs::ramfs::RAMFS.get() }) else { return -5 }; // EIO
+            let existing = fs.resolve(path);
+            match existing {
+                Some(idx) => {
+                    let node = &fs.inodes[idx];
+                    if node.kind != crate::kernel::fs::ramfs::NodeKind::File { return -21; } // EISDIR
+                    if node.uid != cred.fsuid { return -13; } // EACCES
+                }
+                None => {
+                    let Some(dir) = fs.parent(path) else { return -2 }; // ENOENT
+                    let d = &fs.inodes[dir];
+                    if !cred.may_access(MAY_WRITE | MAY_EXEC, d.mode, d.uid, d.gid) { return -13; }
+                }
+            }
+            match fs.create_file(path) {
+                Ok(idx) => {
+                    let node = &mut fs.inodes[idx];
+                    if existing.is_none() {
+                        node.mode = mode;
+                        node.uid  = cred.fsuid;
+                        node.gid  = cred.fsgid;
+                    }
+                    node.data.extend_from_slice(data);
+                    unsafe { crate::kernel::procfs::written(idx); }
+                    data.len() as i64
+                }
+                Err(e) => e,
+            }
+        }
+        Resolved::Fat16 { fat_path } => {
+            if !crate::kernel::ata::is_present() { return -5; } // EIO
+            let fd = unsafe { crate::kernel::fat::open(fat_path, flags) };
+            if fd < 0 { return fd; }
+            let n = unsafe { crate::kernel::fat::write_fd(fd as i32, data) };
+            let _ = unsafe { crate::kernel::fat::close(fd as i32) };
+            n
+        }
+        Resolved::Ext2 { path: ext2_path } => {
+            if !crate::kernel::ext2::is_ready() { return -5; } // EIO
+            let fd = unsafe { crate::kernel::ext2::open(ext2_path, flags) };
+            if fd < 0 { return fd; }
+            let n = unsafe { crate::kernel::ext2::write_fd(fd as i32, data) };
+            let _ = unsafe { crate::kernel::ext2::close(fd as i32) };
+            n
+        }
+        Resolved::Dev { .. } | Resolved::Proc { .. } | Resolved::DiskStore { .. } => -30, // EROFS
+    }
+}
+
 // ── vfs_readdir ───────────────────────────────────────────────────────────
 
 pub fn vfs_readdir(path: &str, buf: &mut [u8]) -> i64 {
     match resolve(path) {
-        Resolved::Dev { .. } => {
-            let listing = b"null\ntty\n";
-            let n = listing.len().min(buf.len());
-            buf[..n].copy_from_slice(&listing[..n]);
-            n as i64
+        Resolved::Dev { node: Some(node) } if node.backend == FdBackend::Dir => {
+            if node.name == "pts" {
+                crate::kernel::pty::list(buf) as i64
+            } else {
+                crate::kernel::devfs::list(node.name, buf) as i64
+            }
         }
+        Resolved::Dev { node: Some(_) } => -20, // ENOTDIR
+        Resolved::Dev { node: None }    => -2,
         Resolved::Fat16 { fat_path } => {
             match unsafe { crate::kernel::fat::resolve_dir(fat_path) } {
                 Some(loc) => unsafe { crate::kernel::fat::list_dir_raw(loc, buf) },
@@ -191,7 +402,13 @@ pub unsafe fn vfs_mkdir(path: &str) -> i64 {
         Resolved::DiskStore { .. }   => -1, // EPERM
         Resolved::RamFS { path } => {
             match unsafe { crate::kernel::fs::ramfs::RAMFS.get() } {
-                Some(fs) => fs.create_dir(path).map(|_| 0i64).unwrap_or(-1),
+                Some(fs) => {
+                    if !ramfs_may(fs, fs.parent(path), MAY_WRITE | MAY_EXEC) { return -13; } // EACCES
+                    match fs.create_dir(path) {
+                        Ok(i)  => { ramfs_set_creator(fs, i); 0 }
+                        Err(_) => -1,
+                    }
+                }
                 None     => -2,
             }
         }
@@ -220,7 +437,7 @@ pub unsafe fn vfs_chdir(path: &str) -> i64 {
                    .and_then(|fs| fs.list_dir(rpath))
                    .is_some()
         }
-        Resolved::Dev       { .. } => false,
+        Resolved::Dev { node } => node.is_some_and(|n| n.backend == FdBackend::Dir),
         Resolved::Proc { path: rpath } => {
             rpath == "/proc"
             || unsafe { crate::kernel::fs::ramfs::RAMFS.get() }
@@ -298,6 +515,13 @@ impl LinuxStat {
         s.st_mode = S_IFDIR | 0o755; s.st_uid = 1000; s.st_gid = 1000;
         s.st_blksize = 512; s
     }
+    /// Mode bits and owner of a RamFS inode, replacing the defaults.
+    pub fn with_owner(mut self, node: &crate::kernel::fs::ramfs::INode) -> Self {
+        self.st_mode = (self.st_mode & !0o7777) | node.mode as u32;
+        self.st_uid  = node.uid;
+        self.st_gid  = node.gid;
+        self
+    }
     pub fn fill_chardev(ino: u64) -> Self {
         let mut s = Self::zeroed();
         s.st_dev = 1; s.st_ino = ino; s.st_nlink = 1;
@@ -310,10 +534,11 @@ impl LinuxStat {
 pub unsafe fn vfs_stat_linux(path: &str, out: *mut LinuxStat) -> i64 {
     unsafe { *out = LinuxStat::zeroed(); }
     match resolve(path) {
-        Resolved::Dev { .. } => {
-            unsafe { *out = LinuxStat::fill_chardev(1); }
+        Resolved::Dev { node: Some(node) } => {
+            unsafe { *out = crate::kernel::devfs::stat(&node); }
             0
         }
+        Resolved::Dev { node: None } => -2,
         Resolved::Fat16 { fat_path } => {
             if !crate::kernel::ata::is_present() { return -19; }
             if fat_path == b"/disk" || fat_path == b"/disk/"
@@ -353,11 +578,13 @@ pub unsafe fn vfs_stat_linux(path: &str, out: *mut LinuxStat) -> i64 {
             match unsafe { crate::kernel::fs::ramfs::RAMFS.get() } {
                 None => -2,
                 Some(fs) => {
+                    let Some(idx) = fs.resolve(rpath) else { return -2 };
+                    let node = &fs.inodes[idx];
                     if let Some(data) = fs.read_file(rpath) {
-                        unsafe { *out = LinuxStat::fill_file(data.len() as u64, 300 + rpath.len() as u64); }
+                        unsafe { *out = LinuxStat::fill_file(data.len() as u64, 300 + rpath.len() as u64).with_owner(node); }
                         0
                     } else if fs.list_dir(rpath).is_some() {
-                        unsafe { *out = LinuxStat::fill_dir(400 + rpath.len() as u64); }
+                        unsafe { *out = LinuxStat::fill_dir(400 + rpath.len() as u64).with_owner(node); }
                         0
                     } else { -2 }
                 }
@@ -406,10 +633,13 @@ pub unsafe fn vfs_stat_linux(path: &str, out: *mut LinuxStat) -> i64 {
 pub unsafe fn vfs_stat(path: &str, out: *mut FileStat) -> i64 {
     unsafe {
         match resolve(path) {
-            Resolved::Dev { .. } => {
-                (*out) = FileStat { size: 0, kind: StatKind::Device as u32, _pad: 0 };
+            Resolved::Dev { node: Some(node) } => {
+                let st = crate::kernel::devfs::stat(&node);
+                let kind = if node.backend == FdBackend::Dir { StatKind::Directory } else { StatKind::Device };
+                (*out) = FileStat { size: st.st_size as u64, kind: kind as u32, _pad: 0 };
                 0
             }
+            Resolved::Dev { node: None } => -7,
             Resolved::Fat16 { fat_path } => {
                 if !crate::kernel::ata::is_present() { return -19; }
                 if fat_path == b"/disk" || fat_path == b"/disk/"
@@ -51,9 +51,12 @@ pub struct GuiEventRaw {
 impl GuiEventRaw {
     const fn zero() -> Self { Self { kind: 0, data: [0; 12] } }
 
-    pub fn key(ch: u8) -> Self {
+    /// `data[0]` is the character if it is ASCII (0 otherwise) and
+    /// `data[4..8]` its Unicode scalar value, u32 LE.
+    pub fn key(ch: char) -> Self {
         let mut d = [0u8; 12];
-        d[0] = ch;
+        if ch.is_ascii() { d[0] = ch as u8; }
+        d[4..8].copy_from_slice(&(ch as u32).to_le_bytes());
         Self { kind: GUI_EVENT_KEY, data: d }
     }
 
@@ -172,7 +175,7 @@ static mut GFX_VALID: bool                                           = false;
 // Pending keyboard chars forwarded from the ISR into the main-loop buffer
 // so the loop can route them to the focused window's event queue.
 const KEY_RING_SIZE: usize = 128;
-static mut KEY_RING: [u8; KEY_RING_SIZE] = [0; KEY_RING_SIZE];
+static mut KEY_RING: [char; KEY_RING_SIZE] = ['\0'; KEY_RING_SIZE];
 static mut KEY_HEAD:  usize = 0;
 static mut KEY_TAIL:  usize = 0;
 
@@ -192,7 +195,7 @@ pub unsafe fn init(
 }
 
 /// Called from the keyboard ISR to buffer a char for GUI routing.
-unsafe fn gui_key_isr_callback(ch: u8) {
+unsafe fn gui_key_isr_callback(ch: char) {
     unsafe {
         let next = (KEY_TAIL + 1) % KEY_RING_SIZE;
         if next != KEY_HEAD {
@@ -203,7 +206,7 @@ unsafe fn gui_key_isr_callback(ch: u8) {
 }
 
 /// Drain one pending key from the ISR ring.  Returns None if empty.
-pub unsafe fn pop_pending_key() -> Option<u8> {
+pub unsafe fn pop_pending_key() -> Option<char> {
     unsafe {
         if KEY_HEAD == KEY_TAIL { return None; }
         let ch = KEY_RING[KEY_HEAD];
@@ -523,7 +526,7 @@ pub unsafe fn get_size(pid: u32, window_id: u32, w_ptr: u64, h_ptr: u64) -> i64
 // ── Main-loop event injection ─────────────────────────────────────────────────
 
 /// Push a keyboard char to the event queue of the window with `window_id`.
-pub unsafe fn push_key_event(window_id: u32, ch: u8) {
+pub unsafe fn push_key_event(window_id: u32, ch: char) {
     if let Some(slot) = slot_by_win(window_id) {
         unsafe { entries()[slot].push(GuiEventRaw::key(ch)); }
     }
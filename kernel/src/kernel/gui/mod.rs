@@ -1,3 +1,5 @@
-//! Kernel-side GUI support: compositor and GUI process management.
+//! Kernel-side GUI support: compositor, GUI process management and the
+//! desktop session hand-off.
 pub mod compositor;
 pub mod gui_proc;
+pub mod session;
@@ -0,0 +1,79 @@
+//! Desktop session hand-off.
+//!
+//! init supervises `/bin/session`, the desktop session.  While it runs, the
+//! desktop asks it to start programs instead of spawning them from the
+//! kernel, so they run under the session's user and are its children.
+//!
+//! # Protocol
+//!
+//! Two well-known IPC queues, both created by the session.  Values are
+//! little-endian u32s.
+//!
+//! | queue | type_id | direction         | payload                          |
+//! |-------|---------|-------------------|----------------------------------|
+//! | 2     | 1       | desktop → session | window u32, <program name>       |
+//! | 3     | 2       | session → desktop | pid u32  (hello, sent at start)  |
+//! | 3     | 3       | session → desktop | window u32, pid i32 (< 0: failed)|
+//!
+//! `window` is the terminal window that should show the program, or
+//! `NO_WINDOW`.
+
+use crate::kernel::ipc;
+use crate::kernel::scheduler::{self, TaskState};
+
+pub const LAUNCH_QUEUE_ID:   u32 = 2;
+pub const LAUNCHED_QUEUE_ID: u32 = 3;
+
+const MSG_LAUNCH:   u32 = 1;
+const MSG_HELLO:    u32 = 2;
+const MSG_LAUNCHED: u32 = 3;
+
+/// `window` value for a program that is not shown in a terminal.
+pub const NO_WINDOW: u32 = u32::MAX;
+
+/// PID of the running session, from its last hello (0 = none yet).
+static mut SESSION_PID: u8 = 0;
+
+/// Whether a session is running to take launch requests.
+pub fn available() -> bool {
+    let pid = unsafe { SESSION_PID };
+    pid != 0 && scheduler::task_infos().iter().any(|t| {
+        t.pid == pid && !matches!(t.state, TaskState::Empty | TaskState::Dead(_))
+    })
+}
+
+/// Ask the session to start `name` for `window`.  Returns `false` — the
+/// caller spawns the program itself — when no session is running or its
+/// queue is full.
+pub unsafe fn launch(name: &str, window: u32) -> bool {
+    if !available() { return false; }
+    let mut data = [0u8; ipc::MAX_MSG_SIZE];
+    let len = name.len().min(ipc::MAX_MSG_SIZE - 4);
+    data[..4].copy_from_slice(&window.to_le_bytes());
+    data[4..4 + len].copy_from_slice(&name.as_bytes()[..len]);
+    unsafe { ipc::msgsnd(LAUNCH_QUEUE_ID, MSG_LAUNCH, &data[..4 + len]) == 0 }
+}
+
+/// Drain the session's replies.  Returns the next `(window, pid)` of a
+/// program it started, or `None` once the queue is empty.
+pub unsafe fn poll_launched() -> Option<(u32, u8)> {
+    loop {
+        let mut msg = ipc::Message::empty();
+        if unsafe { ipc::msgrcv(LAUNCHED_QUEUE_ID, &mut msg) } != 0 { return None; }
+        match msg.type_id {
+            MSG_HELLO if msg.size >= 4 => unsafe {
+                SESSION_PID = read_u32(&msg.data, 0) as u8;
+            },
+            MSG_LAUNCHED if msg.size >= 8 => {
+                let window = read_u32(&msg.data, 0);
+                let pid    = read_u32(&msg.data, 4) as i32;
+                if pid > 0 { return Some((window, pid as u8)); }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
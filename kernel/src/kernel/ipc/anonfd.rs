@@ -0,0 +1,418 @@
+//! Anonymous-inode file descriptors: eventfd, timerfd, signalfd and epoll.
+//!
+//! The objects live in a pool of `ANON_COUNT` slots.  An `FdEntry` with
+//! backend `FdBackend::Anon` holds the slot index in `raw_fd`; like pipes,
+//! slots are reference-counted across dup2 and fork and freed on the last
+//! close.  `O_NONBLOCK` belongs to the object, as it does to an open file
+//! description on Linux.
+//!
+//! A read (or an eventfd write) that cannot complete returns `EAGAIN` on a
+//! non-blocking object; otherwise the task is parked in
+//! `TaskState::WaitingForFd` and the call restarted once `fd_ready` says so.
+//! All of them are pollable; state changes are reported to `epoll::notify`.
+//! The interest list of an epoll instance lives in `epoll.rs`.
+
+use crate::kernel::epoll::{self, Source};
+use crate::kernel::fs::ramfs::{FdBackend, MAX_FD};
+use crate::kernel::scheduler::{
+    SCHED, CURRENT_TASK_IDX, SIGKILL, SIGSTOP, SI_TIMER, wait_for_fd,
+};
+use crate::kernel::timers::{self, Clock, Timer, TIMER_ABSTIME};
+use crate::kernel::user_mode::CURRENT_SYSCALL_CTX;
+
+pub const ANON_COUNT: usize = 16;
+
+// Creation flags shared by eventfd2, timerfd_create and signalfd4.
+// syscall_core refuses any other flag bits; `O_CLOEXEC` is accepted and
+// ignored, as is `TFD_TIMER_CANCEL_ON_SET` (the clock is never set).
+pub use crate::kernel::syscall_core::{EFD_SEMAPHORE, O_NONBLOCK};
+
+pub const EAGAIN: i64 = -11;
+const EBADF:  i64 = -9;
+const EINVAL: i64 = -22;
+const ENFILE: i64 = -23;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Kind {
+    Free,
+    Event,
+    Timer,
+    Signal,
+    Epoll,
+}
+
+struct AnonFile {
+    kind:      Kind,
+    refs:      u8,
+    nonblock:  bool,
+    /// eventfd: the counter.  timerfd: expirations not yet read.
+    count:     u64,
+    semaphore: bool,
+    timer:     Timer,
+    /// signalfd: signals it accepts (bit N = signal N+1).
+    mask:      u32,
+}
+
+impl AnonFile {
+    const FREE: Self = Self {
+        kind: Kind::Free, refs: 0, nonblock: false, count: 0, semaphore: false,
+        timer: Timer::off(Clock::Monotonic), mask: 0,
+    };
+}
+
+static mut FILES: [AnonFile; ANON_COUNT] = [const { AnonFile::FREE }; ANON_COUNT];
+
+/// `struct signalfd_siginfo`, one per signal read from a signalfd.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct SignalfdSiginfo {
+    signo:     u32,
+    errno:     i32,
+    code:      i32,
+    pid:       u32,
+    uid:       u32,
+    fd:        i32,
+    tid:       u32,
+    band:      u32,
+    overrun:   u32,
+    trapno:    u32,
+    status:    i32,
+    int:       i32,
+    ptr:       u64,
+    utime:     u64,
+    stime:     u64,
+    addr:      u64,
+    addr_lsb:  u16,
+    _pad2:     u16,
+    syscall:   i32,
+    call_addr: u64,
+    arch:      u32,
+    _pad:      [u8; 28],
+}
+
+const _: () = assert!(core::mem::size_of::<SignalfdSiginfo>() == 128);
+
+fn file(slot: i32) -> Option<*mut AnonFile> {
+    if slot < 0 || slot as usize >= ANON_COUNT { return None; }
+    let f = unsafe { &raw mut FILES[slot as usize] };
+    if unsafe { (*f).kind } == Kind::Free { None } else { Some(f) }
+}
+
+/// Kind of the live object in `slot`.
+pub fn kind(slot: i32) -> Option<Kind> {
+    file(slot).map(|f| unsafe { (*f).kind })
+}
+
+// ── Reference counting ─────────────────────────────────────────────────────
+
+/// Take a free slot for a new object with one reference.
+unsafe fn alloc(kind: Kind, nonblock: bool) -> Option<usize> {
+    let files = unsafe { &mut *(&raw mut FILES) };
+    let slot  = files.iter().position(|f| f.kind == Kind::Free)?;
+    files[slot] = AnonFile { kind, refs: 1, nonblock, ..AnonFile::FREE };
+    Some(slot)
+}
+
+/// Increment the reference count (dup2, fork).
+pub unsafe fn addref(slot: i32) {
+    if let Some(f) = file(slot) {
+        let f = unsafe { &mut *f };
+        f.refs = f.refs.saturating_add(1);
+    }
+}
+
+/// Drop one reference; the object is freed with the last.
+pub unsafe fn close(slot: i32) {
+    if let Some(f) = file(slot) {
+        let f = unsafe { &mut *f };
+        f.refs = f.refs.saturating_sub(1);
+        if f.refs == 0 {
+            if f.kind == Kind::Epoll { unsafe { epoll::release(slot as usize); } }
+            *f = AnonFile::FREE;
+            epoll::forget(Source::Anon(slot));
+        }
+    }
+}
+
+/// Task-exit hook: drop the task's references.
+pub unsafe fn on_exit(idx: usize) {
+    let fdt = unsafe { &mut (*(&raw mut SCHED)).tasks[idx].fd_table };
+    for slot in fdt.entries.iter_mut() {
+        if let Some(e) = slot {
+            if e.backend == FdBackend::Anon {
+                unsafe { close(e.raw_fd); }
+                *slot = None;
+            }
+        }
+    }
+}
+
+/// Install `slot` in the current task's fd table, releasing it on failure.
+unsafe fn install(slot: usize) -> i64 {
+    let fdt = unsafe { &mut (*(&raw mut SCHED)).tasks[CURRENT_TASK_IDX].fd_table };
+    let fd  = fdt.open_anon(slot as i32);
+    if fd < 0 { unsafe { close(slot as i32); } }
+    fd
+}
+
+/// Slot of the object behind `fd` of the task at `idx`, which must be of
+/// `kind`.
+pub unsafe fn slot_of(idx: usize, fd: i32, kind: Kind) -> Result<usize, i64> {
+    if fd < 0 || fd as usize >= MAX_FD { return Err(EBADF); }
+    let task = unsafe { &(*(&raw const SCHED)).tasks[idx] };
+    let e = match task.fd_table.entries[fd as usize] {
+        Some(e) => e,
+        None    => return Err(EBADF),
+    };
+    if e.backend != FdBackend::Anon { return Err(EINVAL); }
+    match file(e.raw_fd) {
+        Some(f) if unsafe { (*f).kind } == kind => Ok(e.raw_fd as usize),
+        _ => Err(EINVAL),
+    }
+}
+
+/// The object behind the current task's `fd`, which must be of `kind`.
+unsafe fn lookup(fd: i32, kind: Kind) -> Result<*mut AnonFile, i64> {
+    unsafe { slot_of(CURRENT_TASK_IDX, fd, kind) }.map(|slot| unsafe { &raw mut FILES[slot] })
+}
+
+// ── I/O ────────────────────────────────────────────────────────────────────
+
+/// Pending signals of the task at `idx` that a signalfd with `mask` takes.
+unsafe fn signals_for(idx: usize, mask: u32) -> u32 {
+    unsafe { (*(&raw const SCHED)).tasks[idx].pending_signals & mask }
+}
+
+/// Bring a timerfd's expiration count up to date.
+unsafe fn update_timer(f: *mut AnonFile) {
+    let f = unsafe { &mut *f };
+    if f.kind == Kind::Timer {
+        let now = unsafe { f.timer.clock.now(CURRENT_TASK_IDX) };
+        f.count = f.count.saturating_add(f.timer.expire(now));
+    }
+}
+
+/// Read from the object in `slot` on behalf of the current task.
+pub unsafe fn read(slot: i32, buf: &mut [u8]) -> i64 {
+    let f = match file(slot) { Some(f) => unsafe { &mut *f }, None => return EBADF };
+    match f.kind {
+        Kind::Event | Kind::Timer => {
+            if buf.len() < 8 { return EINVAL; }
+            unsafe { update_timer(f); }
+            if f.count == 0 { return EAGAIN; }
+            let value = if f.semaphore { 1 } else { f.count };
+            f.count -= value;
+            buf[..8].copy_from_slice(&value.to_ne_bytes());
+            // An eventfd that was full is writable again.
+            if f.kind == Kind::Event { epoll::notify(Source::Anon(slot)); }
+            8
+        }
+        Kind::Signal => {
+            const REC: usize = core::mem::size_of::<SignalfdSiginfo>();
+            if buf.len() < REC { return EINVAL; }
+            let idx  = unsafe { CURRENT_TASK_IDX };
+            let task = unsafe { &raw mut (*(&raw mut SCHED)).tasks[idx] };
+            let mut n = 0;
+            while n + REC <= buf.len() {
+                let pending = unsafe { signals_for(idx, f.mask) };
+                if pending == 0 { break; }
+                let bit    = pending.trailing_zeros();
+                let signum = bit as usize + 1;
+                let info = unsafe {
+                    (*task).pending_signals &= !(1u32 << bit);
+                    (*task).signal_info[signum]
+                };
+                let timer = info.code == SI_TIMER;
+                let rec = SignalfdSiginfo {
+                    signo: signum as u32, errno: 0, code: info.code,
+                    pid: info.pid, uid: info.uid, fd: 0,
+                    tid: if timer { info.timerid as u32 } else { 0 },
+                    band: 0,
+                    overrun: if timer { info.overrun as u32 } else { 0 },
+                    trapno: info.trapno as u32, status: info.status,
+                    int: info.value as i32, ptr: info.value,
+                    utime: 0, stime: 0, addr: info.addr, addr_lsb: 0, _pad2: 0,
+                    syscall: 0, call_addr: 0, arch: 0, _pad: [0; 28],
+                };
+                let bytes = unsafe {
+                    core::slice::from_raw_parts(&rec as *const SignalfdSiginfo as *const u8, REC)
+                };
+                buf[n..n + REC].copy_from_slice(bytes);
+                n += REC;
+            }
+            if n == 0 { EAGAIN } else { n as i64 }
+        }
+        Kind::Epoll => EINVAL,
+        Kind::Free  => EBADF,
+    }
+}
+
+/// Write to the object in `slot`; only eventfds accept writes.
+pub unsafe fn write(slot: i32, buf: &[u8]) -> i64 {
+    let f = match file(slot) { Some(f) => unsafe { &mut *f }, None => return EBADF };
+    if f.kind != Kind::Event || buf.len() < 8 { return EINVAL; }
+    let mut raw = [0u8; 8];
+    raw.copy_from_slice(&buf[..8]);
+    let value = u64::from_ne_bytes(raw);
+    if value == u64::MAX { return EINVAL; }
+    if f.count > u64::MAX - 1 - value { return EAGAIN; }
+    f.count += value;
+    epoll::notify(Source::Anon(slot));
+    8
+}
+
+/// `(readable, writable)` for poll, as seen by the task at `idx`.
+pub unsafe fn ready(idx: usize, slot: i32) -> (bool, bool) {
+    let f = match file(slot) { Some(f) => unsafe { &mut *f }, None => return (true, true) };
+    match f.kind {
+        Kind::Event  => (f.count > 0, f.count < u64::MAX - 1),
+        Kind::Timer  => { unsafe { update_timer(f); } (f.count > 0, false) }
+        Kind::Signal => (unsafe { signals_for(idx, f.mask) } != 0, false),
+        Kind::Epoll  => (unsafe { epoll::ready(idx, slot as usize) }, false),
+        Kind::Free   => (true, true),
+    }
+}
+
+/// Timer-tick hook: report timerfds that expired since the last check.
+pub unsafe fn expire_timers() {
+    for slot in 0..ANON_COUNT {
+        let f = unsafe { &mut *(&raw mut FILES[slot]) };
+        if f.kind != Kind::Timer { continue; }
+        let before = f.count;
+        unsafe { update_timer(f); }
+        if f.count != before { epoll::notify(Source::Anon(slot as i32)); }
+    }
+}
+
+/// `signum` was queued for some task: signalfds accepting it may be readable.
+pub fn signal_queued(signum: usize) {
+    for slot in 0..ANON_COUNT {
+        let f = unsafe { &raw const FILES[slot] };
+        if unsafe { (*f).kind } == Kind::Signal && unsafe { (*f).mask } & (1u32 << (signum - 1)) != 0 {
+            epoll::notify(Source::Anon(slot as i32));
+        }
+    }
+}
+
+/// True once the task at `idx`, blocked on `fd`, can retry its read (or
+/// write): the object is ready, or the fd is gone.
+pub unsafe fn fd_ready(idx: usize, fd: i32, write: bool) -> bool {
+    if fd < 0 || fd as usize >= MAX_FD { return true; }
+    match unsafe { (*(&raw const SCHED)).tasks[idx].fd_table.entries[fd as usize] } {
+        Some(e) if e.backend == FdBackend::Anon => {
+            let (r, w) = unsafe { ready(idx, e.raw_fd) };
+            if write { w } else { r }
+        }
+        Some(e) if e.backend == FdBackend::Tty => {
+            let t = e.raw_fd as usize;
+            if write { crate::kernel::tty::writable(t) } else { unsafe { crate::kernel::tty::readable(t) } }
+        }
+        Some(e) if e.backend == FdBackend::PtyMaster => {
+            let (r, w) = unsafe { crate::kernel::pty::ready(e.raw_fd as usize) };
+            if write { w } else { r }
+        }
+        Some(e) if e.backend == FdBackend::Evdev && !write => unsafe {
+            crate::kernel::evdev::ready(e.raw_fd as usize).0
+        },
+        Some(e) if e.backend == FdBackend::Dsp && write => unsafe {
+            crate::kernel::dsp::ready().1
+        },
+        _ => true,
+    }
+}
+
+/// Called when a read or write on the current task's `fd` returned
+/// `EAGAIN`: unless the object is non-blocking, park the task until it is
+/// ready.  Returns only if the caller should report `EAGAIN`.
+pub unsafe fn block(fd: i32, write: bool) {
+    if fd < 0 || fd as usize >= MAX_FD { return; }
+    let e = match unsafe { (*(&raw const SCHED)).tasks[CURRENT_TASK_IDX].fd_table.entries[fd as usize] } {
+        Some(e) if e.backend == FdBackend::Anon => e,
+        _ => return,
+    };
+    match file(e.raw_fd) {
+        Some(f) if unsafe { !(*f).nonblock } => {}
+        _ => return,
+    }
+    if let Some(ctx) = unsafe { core::ptr::replace(&raw mut CURRENT_SYSCALL_CTX, None) } {
+        unsafe { wait_for_fd(fd, write, ctx); }
+    }
+}
+
+// ── Syscalls ───────────────────────────────────────────────────────────────
+
+/// `eventfd2(initval, flags)`.
+pub unsafe fn eventfd2(initval: u64, flags: u64) -> i64 {
+    let slot = match unsafe { alloc(Kind::Event, flags & O_NONBLOCK != 0) } {
+        Some(s) => s,
+        None    => return ENFILE,
+    };
+    let f = unsafe { &mut *(&raw mut FILES[slot]) };
+    f.count     = initval as u32 as u64;
+    f.semaphore = flags & EFD_SEMAPHORE != 0;
+    unsafe { install(slot) }
+}
+
+/// `timerfd_create(clockid, flags)`.
+pub unsafe fn timerfd_create(_clock_id: u64, flags: u64) -> i64 {
+    match unsafe { alloc(Kind::Timer, flags & O_NONBLOCK != 0) } {
+        Some(slot) => unsafe { install(slot) },
+        None       => ENFILE,
+    }
+}
+
+/// `timerfd_gettime(fd, curr)`.
+pub unsafe fn timerfd_gettime(fd: i32, curr_ptr: u64) -> i64 {
+    let f = match unsafe { lookup(fd, Kind::Timer) } { Ok(v) => unsafe { &mut *v }, Err(e) => return e };
+    unsafe { update_timer(f); }
+    let now = unsafe { f.timer.clock.now(CURRENT_TASK_IDX) };
+    unsafe { timers::write_itimerspec(curr_ptr, f.timer.remaining(now), f.timer.interval) }
+}
+
+/// `timerfd_settime(fd, flags, new, old)`.  Re-arming discards expirations
+/// not yet read.
+pub unsafe fn timerfd_settime(fd: i32, flags: u64, new_ptr: u64, old_ptr: u64) -> i64 {
+    let f = match unsafe { lookup(fd, Kind::Timer) } { Ok(v) => v, Err(e) => return e };
+    let (value, interval) = match unsafe { timers::read_itimerspec(new_ptr) } {
+        Ok(v)  => v,
+        Err(e) => return e,
+    };
+    if old_ptr != 0 {
+        let r = unsafe { timerfd_gettime(fd, old_ptr) };
+        if r < 0 { return r; }
+    }
+    let f = unsafe { &mut *f };
+    let now = unsafe { f.timer.clock.now(CURRENT_TASK_IDX) };
+    f.timer.set(now, value, interval, flags & TIMER_ABSTIME != 0);
+    f.count = 0;
+    0
+}
+
+/// New epoll instance for `epoll_create1`.
+pub unsafe fn epoll_create() -> i64 {
+    match unsafe { alloc(Kind::Epoll, false) } {
+        Some(slot) => { unsafe { epoll::init(slot); } unsafe { install(slot) } }
+        None       => ENFILE,
+    }
+}
+
+/// `signalfd4(fd, mask, sizemask, flags)`: `fd` -1 creates a signalfd,
+/// otherwise replaces the mask of an existing one.  syscall_core has
+/// already checked `sizemask` and the mask pointer.
+pub unsafe fn signalfd4(fd: i32, mask_ptr: u64, _sizemask: u64, flags: u64) -> i64 {
+    let unblockable = (1u32 << (SIGKILL as u32 - 1)) | (1u32 << (SIGSTOP as u32 - 1));
+    let mask = unsafe { core::ptr::read_unaligned(mask_ptr as *const u64) } as u32 & !unblockable;
+
+    if fd != -1 {
+        return match unsafe { lookup(fd, Kind::Signal) } {
+            Ok(f)  => { unsafe { (*f).mask = mask; } fd as i64 }
+            Err(e)     => e,
+        };
+    }
+    let slot = match unsafe { alloc(Kind::Signal, flags & O_NONBLOCK != 0) } {
+        Some(s) => s,
+        None    => return ENFILE,
+    };
+    unsafe { (*(&raw mut FILES[slot])).mask = mask; }
+    unsafe { install(slot) }
+}
@@ -0,0 +1,442 @@
+//! epoll: readiness notification for pipes, ttys, sockets and the
+//! anonymous fds (eventfd, timerfd, signalfd and epoll itself).
+//!
+//! An epoll instance is an `anonfd` object of kind `Epoll`; its interest
+//! list lives here in `SETS`, indexed by the same slot.  Each watch names the
+//! object it follows as a `Source`.  Backends call `notify(source)` whenever
+//! the object may have become readable or writable (pipe write/read/close,
+//! a key pushed into stdin, a smoltcp poll that touched sockets, an eventfd
+//! write, a timerfd expiry, a queued signal).  That puts matching watches on
+//! the set's ready list and raises its `wake` flag; `epoll_wait` then only
+//! re-checks watches on the ready list instead of every fd.
+//!
+//! Level-triggered watches stay on the ready list while the object is ready;
+//! `EPOLLET` watches leave it once reported and return on the next
+//! notification; `EPOLLONESHOT` watches are disabled until `EPOLL_CTL_MOD`.
+//!
+//! A waiter with nothing to report is parked in
+//! `TaskState::WaitingForEvents`; `tick()` restarts the call when the set's
+//! `wake` flag is up, or completes it with 0 at the deadline.
+
+use crate::kernel::anonfd::{self, Kind, ANON_COUNT};
+use crate::kernel::fs::ramfs::{FdBackend, MAX_FD};
+use crate::kernel::pipe;
+use crate::kernel::scheduler::{SCHED, CURRENT_TASK_IDX, SIGKILL, SIGSTOP, wait_for_events};
+use crate::kernel::stdin;
+use crate::kernel::syscall_core::TIMER_HZ;
+use crate::kernel::{pty, tty};
+use crate::kernel::user_mode::CURRENT_SYSCALL_CTX;
+
+pub const EPOLLIN:        u32 = 0x001;
+pub const EPOLLOUT:       u32 = 0x004;
+pub const EPOLLERR:       u32 = 0x008;
+pub const EPOLLHUP:       u32 = 0x010;
+pub const EPOLLEXCLUSIVE: u32 = 1 << 28;
+pub const EPOLLWAKEUP:    u32 = 1 << 29;
+pub const EPOLLONESHOT:   u32 = 1 << 30;
+pub const EPOLLET:        u32 = 1 << 31;
+
+/// Mode bits of a watch, as opposed to the events it asks for.
+const CONTROL_BITS: u32 = EPOLLEXCLUSIVE | EPOLLWAKEUP | EPOLLONESHOT | EPOLLET;
+
+pub use crate::kernel::syscall_core::{EPOLL_CTL_ADD, EPOLL_CTL_DEL, EPOLL_CTL_MOD};
+use crate::kernel::syscall_core::EPOLL_EVENT_SIZE as EVENT_SIZE;
+
+/// Watches per epoll instance.
+pub const MAX_WATCHES: usize = 32;
+/// Deepest chain of epoll sets watching each other (Linux's EP_MAX_NESTS).
+const MAX_NESTS: usize = 4;
+
+const ENOENT: i64 = -2;
+const EBADF:  i64 = -9;
+const EEXIST: i64 = -17;
+const EINVAL: i64 = -22;
+const ENOSPC: i64 = -28;
+const ELOOP:  i64 = -40;
+const EPERM:  i64 = -1;
+
+/// The kernel object behind a watched fd.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Source {
+    /// The console (`/dev/tty`, stdin).
+    Tty,
+    /// A terminal with a line discipline, by its `tty` index.
+    Terminal(usize),
+    /// A pty master, by its pty number.
+    PtyMaster(usize),
+    /// One end of a pipe, by its raw pipe fd.
+    Pipe(i32),
+    /// A socket, by its socket-table slot.
+    Socket(usize),
+    /// An anonymous fd, by its `anonfd` slot.
+    Anon(i32),
+    /// An input device client, by its `evdev` slot.
+    Evdev(usize),
+    /// The `/dev/dsp` playback stream.
+    Dsp,
+}
+
+#[derive(Clone, Copy)]
+struct Watch {
+    fd:     i32,
+    source: Source,
+    events: u32,
+    data:   u64,
+    /// On the ready list: notified since it was last found not ready (or,
+    /// for `EPOLLET`, last reported).
+    queued: bool,
+}
+
+struct EpollSet {
+    watches: [Option<Watch>; MAX_WATCHES],
+    /// Raised by `notify`, lowered when `epoll_wait` scans the ready list.
+    wake:    bool,
+}
+
+impl EpollSet {
+    const EMPTY: Self = Self { watches: [None; MAX_WATCHES], wake: false };
+
+    fn find(&mut self, fd: i32) -> Option<&mut Watch> {
+        self.watches.iter_mut().flatten().find(|w| w.fd == fd)
+    }
+}
+
+static mut SETS: [EpollSet; ANON_COUNT] = [const { EpollSet::EMPTY }; ANON_COUNT];
+
+// ── Readiness ──────────────────────────────────────────────────────────────
+
+/// Readiness callback: `source` may have changed state.  Queues every watch
+/// on it and wakes the sets' waiters, including sets watching those sets.
+pub fn notify(source: Source) {
+    unsafe { notify_depth(source, 0); }
+}
+
+unsafe fn notify_depth(source: Source, depth: usize) {
+    if depth > MAX_NESTS { return; }
+    for slot in 0..ANON_COUNT {
+        let set = unsafe { &mut *(&raw mut SETS[slot]) };
+        let mut hit = false;
+        for w in set.watches.iter_mut().flatten() {
+            if w.source == source {
+                w.queued = true;
+                hit = true;
+            }
+        }
+        if hit {
+            set.wake = true;
+            unsafe { notify_depth(Source::Anon(slot as i32), depth + 1); }
+        }
+    }
+}
+
+/// The object is gone: drop every watch on it.
+pub fn forget(source: Source) {
+    unsafe {
+        for set in (*(&raw mut SETS)).iter_mut() {
+            for w in set.watches.iter_mut() {
+                if w.is_some_and(|w| w.source == source) { *w = None; }
+            }
+        }
+    }
+}
+
+/// Current `EPOLL*` state of `source`, as seen by the task at `idx`.
+unsafe fn events(idx: usize, source: Source) -> u32 {
+    match source {
+        Source::Tty => {
+            if stdin::available() > 0 { EPOLLIN | EPOLLOUT } else { EPOLLOUT }
+        }
+        Source::Terminal(t) => {
+            (if unsafe { tty::readable(t) } { EPOLLIN } else { 0 })
+                | (if tty::writable(t) { EPOLLOUT } else { 0 })
+                | (if pty::hung_up(t) { EPOLLHUP } else { 0 })
+        }
+        Source::PtyMaster(n) => {
+            let (r, w) = unsafe { pty::ready(n) };
+            (if r { EPOLLIN } else { 0 }) | (if w { EPOLLOUT } else { 0 })
+                | (if pty::slave_closed(n) { EPOLLHUP } else { 0 })
+        }
+        Source::Pipe(raw) => {
+            let mut ev = 0;
+            if pipe::is_read_fd(raw) {
+                if pipe::read_ready(raw) { ev |= EPOLLIN; }
+                if pipe::peer_closed(raw) { ev |= EPOLLHUP; }
+            } else {
+                if pipe::write_ready(raw) { ev |= EPOLLOUT; }
+                if pipe::peer_closed(raw) { ev |= EPOLLERR; }
+            }
+            ev
+        }
+        Source::Socket(slot) => {
+            let (r, w) = unsafe { crate::kernel::net::socket::socket_ready(slot as i64 + 200) };
+            (if r { EPOLLIN } else { 0 }) | (if w { EPOLLOUT } else { 0 })
+        }
+        Source::Anon(slot) => {
+            let (r, w) = unsafe { anonfd::ready(idx, slot) };
+            (if r { EPOLLIN } else { 0 }) | (if w { EPOLLOUT } else { 0 })
+        }
+        Source::Evdev(n) => {
+            if unsafe { crate::kernel::evdev::ready(n) }.0 { EPOLLIN } else { 0 }
+        }
+        Source::Dsp => {
+            if unsafe { crate::kernel::dsp::ready() }.1 { EPOLLOUT } else { 0 }
+        }
+    }
+}
+
+/// The object behind `fd` of the task at `idx`.  Regular files, directories
+/// and `/dev/null` are always ready and cannot be watched (`EPERM`).
+unsafe fn source(idx: usize, fd: i32) -> Result<Source, i64> {
+    if fd >= 200 {
+        return if unsafe { crate::kernel::net::socket::is_socket(fd as i64) } {
+            Ok(Source::Socket(fd as usize - 200))
+        } else {
+            Err(EBADF)
+        };
+    }
+    if fd < 0 || fd as usize >= MAX_FD { return Err(EBADF); }
+    let e = match unsafe { (*(&raw const SCHED)).tasks[idx].fd_table.entries[fd as usize] } {
+        Some(e) => e,
+        None    => return Err(EBADF),
+    };
+    match e.backend {
+        FdBackend::DevTty => Ok(Source::Tty),
+        FdBackend::Tty    => Ok(Source::Terminal(e.raw_fd as usize)),
+        FdBackend::PtyMaster => Ok(Source::PtyMaster(e.raw_fd as usize)),
+        FdBackend::Pipe   => Ok(Source::Pipe(e.raw_fd)),
+        FdBackend::Anon   => Ok(Source::Anon(e.raw_fd)),
+        FdBackend::Evdev  => Ok(Source::Evdev(e.raw_fd as usize)),
+        FdBackend::Dsp    => Ok(Source::Dsp),
+        FdBackend::DevNull | FdBackend::RamFS | FdBackend::Fat16
+        | FdBackend::Ext2 | FdBackend::Dir
+        | FdBackend::Mem | FdBackend::Block | FdBackend::Fb | FdBackend::Mixer => Err(EPERM),
+    }
+}
+
+/// `EPOLL*` readiness of `fd` for poll/select: `None` if the fd is closed.
+pub unsafe fn fd_events(idx: usize, fd: i32) -> Option<u32> {
+    match unsafe { source(idx, fd) } {
+        Ok(src)    => Some(unsafe { events(idx, src) }),
+        Err(EPERM) => Some(EPOLLIN | EPOLLOUT),
+        Err(_)     => None,
+    }
+}
+
+/// True if the epoll instance in `slot` is readable: some watch on its
+/// ready list has an event to report.
+pub unsafe fn ready(idx: usize, slot: usize) -> bool {
+    let set = unsafe { &*(&raw const SETS[slot]) };
+    set.watches.iter().flatten().any(|w| {
+        w.queued && w.events & !CONTROL_BITS != 0
+            && unsafe { events(idx, w.source) } & (w.events | EPOLLERR | EPOLLHUP) != 0
+    })
+}
+
+/// True once a task parked in `epoll_wait` on `epfd` should retry: the set
+/// was notified, or the fd no longer names an epoll instance.
+pub unsafe fn woken(idx: usize, epfd: i32) -> bool {
+    match unsafe { anonfd::slot_of(idx, epfd, Kind::Epoll) } {
+        Ok(slot) => unsafe { (*(&raw const SETS[slot])).wake },
+        Err(_)   => true,
+    }
+}
+
+// ── Instance lifetime (called by anonfd) ───────────────────────────────────
+
+/// A fresh instance took `slot`.
+pub unsafe fn init(slot: usize) {
+    unsafe { SETS[slot] = EpollSet::EMPTY; }
+}
+
+/// The instance in `slot` was freed.
+pub unsafe fn release(slot: usize) {
+    unsafe { SETS[slot] = EpollSet::EMPTY; }
+}
+
+// ── Syscalls ───────────────────────────────────────────────────────────────
+
+/// `epoll_create1(flags)`; `epoll_create(size)` passes 0 after checking size.
+/// syscall_core has already refused flags other than `O_CLOEXEC`.
+pub unsafe fn epoll_create1(_flags: u64) -> i64 {
+    unsafe { anonfd::epoll_create() }
+}
+
+/// Does the set in `slot` (or a set nested inside it) watch `target`?
+unsafe fn reaches(slot: usize, target: Source, depth: usize) -> bool {
+    if depth > MAX_NESTS { return true; }
+    let set = unsafe { &*(&raw const SETS[slot]) };
+    set.watches.iter().flatten().any(|w| {
+        w.source == target || match w.source {
+            Source::Anon(inner) if anonfd::kind(inner) == Some(Kind::Epoll) =>
+                unsafe { reaches(inner as usize, target, depth + 1) },
+            _ => false,
+        }
+    })
+}
+
+/// `epoll_ctl(epfd, op, fd, event)`.  syscall_core has checked `op` and,
+/// except for `EPOLL_CTL_DEL`, the event pointer.
+pub unsafe fn epoll_ctl(epfd: i32, op: u64, fd: i32, event_ptr: u64) -> i64 {
+    let idx  = unsafe { CURRENT_TASK_IDX };
+    let slot = match unsafe { anonfd::slot_of(idx, epfd, Kind::Epoll) } {
+        Ok(s)  => s,
+        Err(e) => return e,
+    };
+    let src = match unsafe { source(idx, fd) } {
+        Ok(s)  => s,
+        Err(e) => return e,
+    };
+    if src == Source::Anon(slot as i32) { return EINVAL; }
+
+    let (events, data) = if op == EPOLL_CTL_DEL {
+        (0, 0)
+    } else {
+        unsafe {
+            (core::ptr::read_unaligned(event_ptr as *const u32),
+             core::ptr::read_unaligned((event_ptr + 4) as *const u64))
+        }
+    };
+
+    let set = unsafe { &mut *(&raw mut SETS[slot]) };
+    match op {
+        EPOLL_CTL_ADD => {
+            if set.find(fd).is_some() { return EEXIST; }
+            if let Source::Anon(inner) = src {
+                if anonfd::kind(inner) == Some(Kind::Epoll)
+                    && unsafe { reaches(inner as usize, Source::Anon(slot as i32), 1) }
+                {
+                    return ELOOP;
+                }
+            }
+            let free = match set.watches.iter_mut().find(|w| w.is_none()) {
+                Some(w) => w,
+                None    => return ENOSPC,
+            };
+            *free = Some(Watch { fd, source: src, events, data, queued: true });
+        }
+        EPOLL_CTL_MOD => {
+            if events & EPOLLEXCLUSIVE != 0 { return EINVAL; }
+            match set.find(fd) {
+                Some(w) if w.events & EPOLLEXCLUSIVE != 0 => return EINVAL,
+                Some(w) => {
+                    w.events = events;
+                    w.data   = data;
+                    w.queued = true;
+                }
+                None => return ENOENT,
+            }
+        }
+        EPOLL_CTL_DEL => {
+            match set.watches.iter_mut().find(|w| w.is_some_and(|w| w.fd == fd)) {
+                Some(w) => *w = None,
+                None    => return ENOENT,
+            }
+            return 0;
+        }
+        _ => return EINVAL,
+    }
+    // Let the current state be picked up by a waiter (and enclosing sets).
+    set.wake = true;
+    notify(Source::Anon(slot as i32));
+    0
+}
+
+/// Report up to `max` events from the set in `slot` to `out`.
+unsafe fn collect(idx: usize, slot: usize, out: u64, max: usize) -> usize {
+    let set = unsafe { &mut *(&raw mut SETS[slot]) };
+    set.wake = false;
+    let mut n = 0;
+    for w in set.watches.iter_mut().flatten() {
+        if n == max { break; }
+        if !w.queued { continue; }
+        if w.events & !CONTROL_BITS == 0 {
+            // Disabled EPOLLONESHOT watch.
+            w.queued = false;
+            continue;
+        }
+        let ev = unsafe { events(idx, w.source) } & (w.events | EPOLLERR | EPOLLHUP);
+        if ev == 0 {
+            w.queued = false;
+            continue;
+        }
+        let rec = out + n as u64 * EVENT_SIZE;
+        unsafe {
+            core::ptr::write_unaligned(rec as *mut u32, ev);
+            core::ptr::write_unaligned((rec + 4) as *mut u64, w.data);
+        }
+        n += 1;
+        if w.events & EPOLLONESHOT != 0 {
+            w.events &= CONTROL_BITS;
+            w.queued = false;
+        } else if w.events & EPOLLET != 0 {
+            w.queued = false;
+        }
+    }
+    n
+}
+
+/// `epoll_pwait(epfd, events, maxevents, timeout, sigmask, sigsetsize)`;
+/// `epoll_wait` passes a null `sigmask`.
+///
+/// With a mask, it is installed for the duration of the wait as sigsuspend
+/// does (`saved_signal_mask` / `in_sigsuspend`), so a signal handler that
+/// interrupts the wait runs under it and sigreturn restores the caller's.
+/// syscall_core has checked `maxevents`, the event buffer and the mask.
+pub unsafe fn epoll_pwait(
+    epfd: i32, events_ptr: u64, maxevents: i64, timeout_ms: i64,
+    sigmask_ptr: u64, _sigsetsize: u64,
+) -> i64 {
+    let idx  = unsafe { CURRENT_TASK_IDX };
+    let slot = match unsafe { anonfd::slot_of(idx, epfd, Kind::Epoll) } {
+        Ok(s)  => s,
+        Err(e) => return e,
+    };
+
+    if sigmask_ptr != 0 {
+        let task = unsafe { &mut (*(&raw mut SCHED)).tasks[idx] };
+        let unblockable = (1u32 << (SIGKILL as u32 - 1)) | (1u32 << (SIGSTOP as u32 - 1));
+        let mask = unsafe { core::ptr::read_unaligned(sigmask_ptr as *const u64) } as u32 & !unblockable;
+        // A restarted wait already holds the caller's mask.
+        if !task.in_sigsuspend {
+            task.saved_signal_mask = task.signal_mask;
+            task.in_sigsuspend     = true;
+        }
+        task.signal_mask = mask;
+    }
+
+    let n = unsafe { collect(idx, slot, events_ptr, maxevents as usize) };
+    if n > 0 || timeout_ms == 0 {
+        unsafe { restore_mask(idx); }
+        return n as i64;
+    }
+
+    let deadline = if timeout_ms < 0 {
+        u64::MAX
+    } else {
+        // u64::MAX means "no timeout"; a huge finite one stops just short.
+        let now = unsafe { crate::kernel::timer::get_ticks() };
+        now.saturating_add((timeout_ms as u64).saturating_mul(TIMER_HZ).div_ceil(1000).max(1))
+            .min(u64::MAX - 1)
+    };
+    if let Some(ctx) = unsafe { core::ptr::replace(&raw mut CURRENT_SYSCALL_CTX, None) } {
+        unsafe { wait_for_events(epfd, deadline, ctx); }
+    }
+    unsafe { restore_mask(idx); }
+    0
+}
+
+/// Undo the temporary `epoll_pwait` mask of the task at `idx` when the wait
+/// finishes without a signal (sigreturn handles the other case).
+pub unsafe fn restore_mask(idx: usize) {
+    let task = unsafe { &mut (*(&raw mut SCHED)).tasks[idx] };
+    if task.in_sigsuspend {
+        task.signal_mask   = task.saved_signal_mask;
+        task.in_sigsuspend = false;
+    }
+}
+
+/// Milliseconds left before `deadline`, for a restarted `epoll_wait`.
+/// At most `i64::MAX`, which the restarted call still reads as finite.
+pub fn remaining_ms(deadline: u64, now: u64) -> u64 {
+    (deadline.saturating_sub(now).saturating_mul(1000) / TIMER_HZ).min(i64::MAX as u64)
+}
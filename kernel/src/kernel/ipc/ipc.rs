@@ -2,7 +2,13 @@
 //! Message-passing IPC for OxideOS.
 //!
 //! Implements a fixed-size message queue system for use by the window manager
-//! and GUI applications.
+//! and GUI applications.  Queued messages live in the `ipc_msg` slab cache
+//! and are freed as they are received.
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use crate::kernel::slab::{self, IPC_MSG};
 
 const MAX_QUEUES: usize = 16;
 pub const MAX_MSG_SIZE: usize = 256;
@@ -15,6 +21,11 @@ pub struct Message {
     pub data: [u8; MAX_MSG_SIZE],
 }
 
+// syscall_core validates msgrcv buffers without seeing this type.
+const _: () = assert!(
+    core::mem::size_of::<Message>() as u64 == crate::kernel::syscall_core::IPC_MESSAGE_SIZE
+);
+
 impl Message {
     pub const fn empty() -> Self {
         Self { type_id: 0, size: 0, data: [0; MAX_MSG_SIZE] }
@@ -24,7 +35,7 @@ impl Message {
 struct MessageQueue {
     id: u32,
     in_use: bool,
-    messages: [Message; MSG_QUEUE_DEPTH],
+    messages: [Option<Box<Message>>; MSG_QUEUE_DEPTH],
     head: usize,
     tail: usize,
 }
@@ -34,7 +45,7 @@ impl MessageQueue {
         Self {
             id: 0,
             in_use: false,
-            messages: [Message::empty(); MSG_QUEUE_DEPTH],
+            messages: [const { None }; MSG_QUEUE_DEPTH],
             head: 0,
             tail: 0,
         }
@@ -78,10 +89,10 @@ pub unsafe fn msgsnd(id: u32, type_id: u32, data: &[u8]) -> i64 {
             if (*q).is_full() { return -6; } // EAGAIN
             
             let tail = (*q).tail;
-            (*q).messages[tail].type_id = type_id;
-            (*q).messages[tail].size = data.len() as u32;
-            (&mut (*q).messages[tail].data)[..data.len()].copy_from_slice(data);
-            
+            let mut msg = Message { type_id, size: data.len() as u32, data: [0; MAX_MSG_SIZE] };
+            msg.data[..data.len()].copy_from_slice(data);
+            (*q).messages[tail] = Some(slab::boxed(IPC_MSG, msg));
+
             (*q).tail = (tail + 1) % MSG_QUEUE_DEPTH;
             return 0; // Success
         }
@@ -98,7 +109,7 @@ pub unsafe fn msgrcv(id: u32, msg_out: &mut Message) -> i64 {
             if (*q).is_empty() { return -6; } // EAGAIN
 
             let head = (*q).head;
-            *msg_out = (*q).messages[head];
+            if let Some(msg) = (*q).messages[head].take() { *msg_out = *msg; }
 
             (*q).head = (head + 1) % MSG_QUEUE_DEPTH;
             return 0; // Success
@@ -113,6 +124,7 @@ pub unsafe fn msgq_destroy(id: u32) -> i64 {
     for i in 0..MAX_QUEUES {
         if (*queues)[i].in_use && (*queues)[i].id == id {
             (*queues)[i].in_use = false;
+            (*queues)[i].messages.iter_mut().for_each(|m| *m = None);
             (*queues)[i].head   = 0;
             (*queues)[i].tail   = 0;
             return 0;
@@ -1,4 +1,5 @@
-//! Inter-process communication: IPC message queues, pipes, shared memory, stdin.
+//! Inter-process communication: IPC message queues, pipes, shared memory, stdin,
+//! eventfd/timerfd/signalfd, epoll.
 
 // Re-export everything from ipc.rs at this level so callers can still write
 // `crate::kernel::ipc::Message`, `crate::kernel::ipc::msgq_create`, etc.
@@ -6,5 +7,7 @@ mod ipc;
 pub use self::ipc::*;
 
 pub mod pipe;
+pub mod anonfd;
+pub mod epoll;
 pub mod shm;
 pub mod stdin;
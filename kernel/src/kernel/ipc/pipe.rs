@@ -5,6 +5,10 @@
 //!   write end of pipe N → FD  81 + N*2
 //!
 //! Range is therefore FDs 80-95 (16 FDs for 8 pipes).
+//!
+//! Reads, writes and closes report the other end to `epoll::notify`.
+
+use crate::kernel::epoll::{self, Source};
 
 pub const PIPE_FD_BASE: i32 = 80;
 const PIPE_COUNT: usize = 8;
@@ -62,6 +66,26 @@ pub fn read_ready(raw_fd: i32) -> bool {
     unsafe { !PIPES[idx].is_empty() }
 }
 
+/// Returns true if the write end of a pipe has buffer space (for poll).
+pub fn write_ready(raw_fd: i32) -> bool {
+    if !is_pipe_fd(raw_fd) || is_read_fd(raw_fd) { return false; }
+    let idx = pipe_index(raw_fd);
+    unsafe { PIPES[idx].available_write() > 0 }
+}
+
+/// Returns true once every holder of the other end has closed it: EOF for
+/// the reader, EPIPE for the writer.
+pub fn peer_closed(raw_fd: i32) -> bool {
+    if !is_pipe_fd(raw_fd) { return false; }
+    let p = unsafe { &*(&raw const PIPES[pipe_index(raw_fd)]) };
+    if is_read_fd(raw_fd) { p.write_refs == 0 } else { p.read_refs == 0 }
+}
+
+/// The fd of the other end of the same pipe.
+fn peer(fd: i32) -> i32 {
+    if is_read_fd(fd) { fd + 1 } else { fd - 1 }
+}
+
 /// Allocate a new pipe. Returns `(read_fd, write_fd)` on success.
 pub unsafe fn alloc() -> Option<(i32, i32)> {
     let pipes = &raw mut PIPES;
@@ -109,6 +133,7 @@ pub unsafe fn write(fd: i32, data: &[u8]) -> i64 {
         (*p).tail = next;
         written += 1;
     }
+    if written > 0 { epoll::notify(Source::Pipe(peer(fd))); }
     written as i64
 }
 
@@ -130,6 +155,7 @@ pub unsafe fn read(fd: i32, buf: &mut [u8]) -> i64 {
         (*p).head = ((*p).head + 1) % PIPE_BUF;
         read += 1;
     }
+    epoll::notify(Source::Pipe(peer(fd)));
     read as i64
 }
 
@@ -137,13 +163,19 @@ pub unsafe fn read(fd: i32, buf: &mut [u8]) -> i64 {
 pub unsafe fn close(fd: i32) -> i64 {
     if !is_pipe_fd(fd) { return -5; }
     let pipes = &raw mut PIPES;
-    let p = &raw mut (*pipes)[pipe_index(fd)];
-    if is_read_fd(fd) {
-        if (*p).read_refs > 0 { (*p).read_refs -= 1; }
-        if (*p).read_refs == 0 { (*p).read_open = false; }
+    let p = unsafe { &mut (*pipes)[pipe_index(fd)] };
+    let closed = if is_read_fd(fd) {
+        if p.read_refs > 0 { p.read_refs -= 1; }
+        if p.read_refs == 0 { p.read_open = false; }
+        !p.read_open
     } else {
-        if (*p).write_refs > 0 { (*p).write_refs -= 1; }
-        if (*p).write_refs == 0 { (*p).write_open = false; }
+        if p.write_refs > 0 { p.write_refs -= 1; }
+        if p.write_refs == 0 { p.write_open = false; }
+        !p.write_open
+    };
+    if closed {
+        epoll::forget(Source::Pipe(fd));
+        epoll::notify(Source::Pipe(peer(fd)));
     }
     0
 }
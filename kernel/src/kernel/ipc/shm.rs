@@ -10,10 +10,10 @@
 //! # Address layout (per process, per segment)
 //!
 //! ```
-//! SHM_VIRT_BASE + shmid * SHM_SLOT_SIZE
+//! layout.shm_base + shmid * SHM_SLOT_SIZE
 //! ```
-//! Default: `0x2000_0000 + id × 1 MB`.  The region is above `USER_MMAP_BASE`
-//! (0x0800_0000) and the user heap, so it does not collide with brk or mmap.
+//! `shm_base` is drawn at random per exec (see `scheduler::UserLayout`) in a
+//! window of its own, away from the stack, heap and mmap area.
 
 use crate::kernel::paging_allocator as pa;
 
@@ -28,8 +28,6 @@ pub const MAX_ATTACH:   usize = 8;
 
 const PAGE_SIZE: u64 = 4096;
 
-/// Virtual base in each process where shm segments are mapped.
-const SHM_VIRT_BASE: u64 = 0x2000_0000;
 /// Each slot occupies 1 MB of virtual address space.
 const SHM_SLOT_SIZE: u64 = 0x0010_0000;
 
@@ -106,12 +104,14 @@ pub unsafe fn shmget(key: u32, size: u64, _flags: u32) -> i64 {
 
 /// Map segment `shmid` into the calling process.
 ///
-/// `attaches` is the calling task's attachment table; `cr3` is its page table.
+/// `attaches` is the calling task's attachment table; `cr3` is its page table
+/// and `base` its randomised shm window.
 /// Returns the virtual address (as a positive i64) or a negative error.
 pub unsafe fn shmat(
     shmid:   u32,
     attaches: &mut [ShmAttach; MAX_ATTACH],
     cr3:     u64,
+    base:    u64,
 ) -> i64 {
     let segtab = &raw mut SEGTAB;
     let id     = shmid as usize;
@@ -129,7 +129,7 @@ pub unsafe fn shmat(
         None    => return -24, // EMFILE
     };
 
-    let vaddr = SHM_VIRT_BASE + id as u64 * SHM_SLOT_SIZE;
+    let vaddr = base + id as u64 * SHM_SLOT_SIZE;
     let pages = (*segtab)[id].pages;
     let phys  = (*segtab)[id].phys_base;
 
@@ -17,6 +17,7 @@ pub fn push(ch: u8) {
         if next != HEAD {          // only if not full
             BUF[TAIL] = ch;
             TAIL = next;
+            crate::kernel::epoll::notify(crate::kernel::epoll::Source::Tty);
         }
     }
 }
@@ -0,0 +1,401 @@
+// src/kernel/mem/buddy.rs
+//! Buddy allocator for physical frames.
+//!
+//! # Layout
+//!
+//! Every frame from 0 up to the end of the highest USABLE entry in the Limine
+//! memory map has a 4-byte [`FrameInfo`] (refcount, order, flags) in a flat
+//! array carved out of usable RAM at boot and accessed through the HHDM.
+//! There is no fixed size limit — 4 GiB of RAM costs 4 MiB of metadata.
+//!
+//! Free memory is kept in blocks of 2^order frames (order 0 ..= `MAX_ORDER`,
+//! i.e. 4 KiB .. 4 MiB), each aligned to its own size.  Every order has a
+//! doubly linked free list whose `next`/`prev` links live in the first 16
+//! bytes of the free block itself, so allocation and free are O(1) apart
+//! from the at most `MAX_ORDER` split/merge steps.
+//!
+//! # Zones
+//!
+//! | Zone     | Range           | Used for                                  |
+//! |----------|-----------------|-------------------------------------------|
+//! | `Dma32`  | below 4 GiB     | devices with 32-bit DMA addressing        |
+//! | `Normal` | 4 GiB and above | everything else (falls back to `Dma32`)   |
+//!
+//! The first 32 MiB are never handed out: they hold the kernel image and
+//! Limine's boot structures.  Blocks never straddle a zone boundary.
+//!
+//! # Ownership
+//!
+//! Allocated frames are individually reference counted (COW fork shares
+//! them); a multi-frame allocation is returned to the allocator one frame at
+//! a time via [`PhysicalFrameAllocator::free_frame`], and neighbouring free
+//! buddies merge back into larger blocks.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use limine::memory_map::EntryType;
+use limine::request::MemoryMapRequest;
+use crate::kernel::serial::SERIAL_PORT;
+
+/// Largest block order: 2^10 frames = 4 MiB.
+pub const MAX_ORDER: usize = 10;
+/// Order of a 2 MiB huge page.
+pub const HUGE_ORDER: usize = 9;
+
+const HHDM: u64 = 0xFFFF_8000_0000_0000;
+const ADDR_MASK: u64 = 0x000F_FFFF_FFFF_F000;
+/// End-of-list marker for the free-list links.
+const NIL: u64 = u64::MAX;
+/// Frames below 32 MiB hold the kernel binary and boot structures.
+const RESERVED_LOW_FRAMES: usize = 8192;
+/// First frame of the `Normal` zone (4 GiB).
+const DMA32_END_FRAME: usize = 0x1_0000_0000 / 4096;
+
+// ── Per-frame metadata ────────────────────────────────────────────────────────
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct FrameInfo {
+    /// 0 = free or unmanaged, 1 = owned, >1 = shared (COW).
+    refcount: u16,
+    /// Block order; only meaningful on the head frame of a free block.
+    order:    u8,
+    flags:    u8,
+}
+
+/// Head frame of a free block of `order`.
+const FRAME_FREE: u8 = 1 << 0;
+/// Never handed out (boot page tables, the metadata array itself).
+const FRAME_RESERVED: u8 = 1 << 1;
+
+// ── Zones ─────────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Zone {
+    /// Physical addresses below 4 GiB.
+    Dma32  = 0,
+    /// Physical addresses at or above 4 GiB.
+    Normal = 1,
+}
+
+pub const NR_ZONES: usize = 2;
+
+impl Zone {
+    pub fn name(self) -> &'static str {
+        match self {
+            Zone::Dma32  => "DMA32",
+            Zone::Normal => "Normal",
+        }
+    }
+
+    fn of(pfn: usize) -> Self {
+        if pfn < DMA32_END_FRAME { Zone::Dma32 } else { Zone::Normal }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct BuddyZone {
+    /// Physical address of the first free block of each order, or `NIL`.
+    free_head:   [u64; MAX_ORDER + 1],
+    /// Number of free blocks of each order (for `/proc/buddyinfo`).
+    nr_free:     [usize; MAX_ORDER + 1],
+    /// Frames handed to this zone at boot.
+    managed:     usize,
+}
+
+impl BuddyZone {
+    const fn new() -> Self {
+        Self { free_head: [NIL; MAX_ORDER + 1], nr_free: [0; MAX_ORDER + 1], managed: 0 }
+    }
+}
+
+// ── Allocator ─────────────────────────────────────────────────────────────────
+
+pub struct PhysicalFrameAllocator {
+    /// `FrameInfo` array (HHDM address) covering frames `0..nframes`.
+    frames:               *mut FrameInfo,
+    nframes:              usize,
+    zones:                [BuddyZone; NR_ZONES],
+    pub total_frames:     usize,
+    pub allocated_frames: AtomicUsize,
+}
+
+impl PhysicalFrameAllocator {
+    pub const fn new() -> Self {
+        Self {
+            frames:           core::ptr::null_mut(),
+            nframes:          0,
+            zones:            [BuddyZone::new(); NR_ZONES],
+            total_frames:     0,
+            allocated_frames: AtomicUsize::new(0),
+        }
+    }
+
+    pub unsafe fn init(&mut self, memory_map: &MemoryMapRequest) {
+        unsafe { SERIAL_PORT.write_str("=== INITIALIZING PHYSICAL FRAME ALLOCATOR (buddy) ===\n") };
+
+        let Some(map) = memory_map.get_response() else { return };
+        let usable = || map.entries().iter().filter(|e| e.entry_type == EntryType::USABLE);
+
+        // ── 1. Size and place the metadata array ─────────────────────────────
+        let nframes = usable()
+            .map(|e| ((e.base + e.length) / 4096) as usize)
+            .max()
+            .unwrap_or(0);
+        let meta_pages = (nframes * core::mem::size_of::<FrameInfo>()).div_ceil(4096);
+
+        let meta_pfn = usable().find_map(|e| {
+            let start = core::cmp::max((e.base / 4096) as usize, RESERVED_LOW_FRAMES);
+            let end   = ((e.base + e.length) / 4096) as usize;
+            (end > start && end - start >= meta_pages).then_some(start)
+        });
+        let Some(meta_pfn) = meta_pfn else {
+            unsafe { SERIAL_PORT.write_str("  No room for frame metadata!\n") };
+            return;
+        };
+
+        self.frames  = (meta_pfn as u64 * 4096 + HHDM) as *mut FrameInfo;
+        self.nframes = nframes;
+        unsafe {
+            core::ptr::write_bytes(self.frames, 0, nframes);
+            for pfn in meta_pfn..meta_pfn + meta_pages {
+                (*self.frames.add(pfn)).flags = FRAME_RESERVED;
+            }
+        }
+
+        // ── 2. Keep Limine's live page tables out of the free lists ──────────
+        unsafe { self.protect_page_table_frames(); }
+
+        // ── 3. Hand every usable run to its zone as aligned blocks ───────────
+        for e in usable() {
+            let start = core::cmp::max((e.base / 4096) as usize, RESERVED_LOW_FRAMES);
+            let end   = ((e.base + e.length) / 4096) as usize;
+            let mut pfn = start;
+            while pfn < end {
+                if unsafe { (*self.frames.add(pfn)).flags } & FRAME_RESERVED != 0 {
+                    pfn += 1;
+                    continue;
+                }
+                let mut run_end = pfn;
+                while run_end < end
+                    && unsafe { (*self.frames.add(run_end)).flags } & FRAME_RESERVED == 0
+                    && Zone::of(run_end) == Zone::of(pfn)
+                {
+                    run_end += 1;
+                }
+                unsafe { self.add_range(pfn, run_end); }
+                pfn = run_end;
+            }
+        }
+
+        unsafe {
+            for z in [Zone::Dma32, Zone::Normal] {
+                let managed = self.zones[z as usize].managed;
+                if managed == 0 { continue; }
+                SERIAL_PORT.write_str("  Zone ");
+                SERIAL_PORT.write_str(z.name());
+                SERIAL_PORT.write_str(": ");
+                SERIAL_PORT.write_decimal(managed as u32);
+                SERIAL_PORT.write_str(" frames\n");
+            }
+            SERIAL_PORT.write_str("Total trackable frames: ");
+            SERIAL_PORT.write_decimal(self.total_frames as u32);
+            SERIAL_PORT.write_str(" (");
+            SERIAL_PORT.write_decimal((self.total_frames * 4) as u32);
+            SERIAL_PORT.write_str(" KB), metadata ");
+            SERIAL_PORT.write_decimal((meta_pages * 4) as u32);
+            SERIAL_PORT.write_str(" KB\n");
+        }
+    }
+
+    /// Walk the four-level page table rooted at CR3 (HHDM-mapped) and mark
+    /// every frame that holds an intermediate table as reserved.
+    unsafe fn protect_page_table_frames(&mut self) {
+        const PRESENT: u64 = 1;
+        const HUGE: u64 = 1 << 7;
+
+        let cr3: u64;
+        unsafe { core::arch::asm!("mov {}, cr3", out(reg) cr3); }
+        let l4_phys = cr3 & ADDR_MASK;
+        self.reserve(l4_phys);
+
+        let l4 = unsafe { &*((l4_phys + HHDM) as *const [u64; 512]) };
+        for &l4e in l4.iter() {
+            if l4e & PRESENT == 0 || l4e & HUGE != 0 { continue; }
+            let l3_phys = l4e & ADDR_MASK;
+            self.reserve(l3_phys);
+
+            let l3 = unsafe { &*((l3_phys + HHDM) as *const [u64; 512]) };
+            for &l3e in l3.iter() {
+                if l3e & PRESENT == 0 || l3e & HUGE != 0 { continue; }
+                let l2_phys = l3e & ADDR_MASK;
+                self.reserve(l2_phys);
+
+                let l2 = unsafe { &*((l2_phys + HHDM) as *const [u64; 512]) };
+                for &l2e in l2.iter() {
+                    if l2e & PRESENT == 0 || l2e & HUGE != 0 { continue; }
+                    self.reserve(l2e & ADDR_MASK);
+                }
+            }
+        }
+    }
+
+    /// Boot-time only: mark a frame as never allocatable.
+    fn reserve(&mut self, phys: u64) {
+        let pfn = (phys / 4096) as usize;
+        if pfn < self.nframes {
+            unsafe { (*self.frames.add(pfn)).flags |= FRAME_RESERVED; }
+        }
+    }
+
+    /// Add frames `start..end` (one zone, none reserved) as the largest
+    /// naturally aligned blocks that fit.
+    unsafe fn add_range(&mut self, start: usize, end: usize) {
+        let mut pfn = start;
+        while pfn < end {
+            let mut order = MAX_ORDER;
+            while order > 0 && (pfn & ((1 << order) - 1) != 0 || pfn + (1 << order) > end) {
+                order -= 1;
+            }
+            unsafe { self.push_free(pfn, order); }
+            self.zones[Zone::of(pfn) as usize].managed += 1 << order;
+            self.total_frames += 1 << order;
+            pfn += 1 << order;
+        }
+    }
+
+    #[inline]
+    unsafe fn info(&self, pfn: usize) -> *mut FrameInfo {
+        unsafe { self.frames.add(pfn) }
+    }
+
+    /// Free-list link words stored at the start of the free block `pfn`.
+    #[inline]
+    fn links(pfn: usize) -> *mut [u64; 2] {
+        (pfn as u64 * 4096 + HHDM) as *mut [u64; 2]
+    }
+
+    unsafe fn push_free(&mut self, pfn: usize, order: usize) {
+        let zone = &mut self.zones[Zone::of(pfn) as usize];
+        let head = zone.free_head[order];
+        unsafe {
+            *Self::links(pfn) = [head, NIL];
+            if head != NIL { (*Self::links((head / 4096) as usize))[1] = pfn as u64 * 4096; }
+        }
+        zone.free_head[order] = pfn as u64 * 4096;
+        zone.nr_free[order] += 1;
+        let info = unsafe { &mut *self.info(pfn) };
+        info.flags |= FRAME_FREE;
+        info.order  = order as u8;
+    }
+
+    unsafe fn remove_free(&mut self, pfn: usize, order: usize) {
+        let zone = &mut self.zones[Zone::of(pfn) as usize];
+        unsafe {
+            let [next, prev] = *Self::links(pfn);
+            if prev != NIL { (*Self::links((prev / 4096) as usize))[0] = next; }
+            else           { zone.free_head[order] = next; }
+            if next != NIL { (*Self::links((next / 4096) as usize))[1] = prev; }
+        }
+        zone.nr_free[order] -= 1;
+        unsafe { (*self.info(pfn)).flags &= !FRAME_FREE; }
+    }
+
+    /// Take a block of exactly `order` from `zone`, splitting a larger one
+    /// if needed.
+    unsafe fn take_block(&mut self, zone: Zone, order: usize) -> Option<usize> {
+        let z = zone as usize;
+        let found = (order..=MAX_ORDER).find(|&k| self.zones[z].free_head[k] != NIL)?;
+        let pfn = (self.zones[z].free_head[found] / 4096) as usize;
+        unsafe { self.remove_free(pfn, found); }
+        // Return the upper halves of the split block to the lower orders.
+        for k in (order..found).rev() {
+            unsafe { self.push_free(pfn + (1 << k), k); }
+        }
+        Some(pfn)
+    }
+
+    /// Allocate a naturally aligned block of 2^`order` frames from `zone`
+    /// (`Normal` requests fall back to `Dma32`).  Every frame gets a
+    /// refcount of 1.  Returns the physical address.
+    pub fn alloc_block(&mut self, order: usize, zone: Zone) -> Option<u64> {
+        if order > MAX_ORDER || self.frames.is_null() { return None; }
+        let pfn = match zone {
+            Zone::Normal => unsafe {
+                self.take_block(Zone::Normal, order)
+                    .or_else(|| self.take_block(Zone::Dma32, order))?
+            },
+            Zone::Dma32 => unsafe { self.take_block(Zone::Dma32, order)? },
+        };
+        for f in pfn..pfn + (1 << order) {
+            unsafe { (*self.info(f)).refcount = 1; }
+        }
+        self.allocated_frames.fetch_add(1 << order, Ordering::Relaxed);
+        Some(pfn as u64 * 4096)
+    }
+
+    /// Allocate `num_pages` physically contiguous frames from `zone`.  The
+    /// block is rounded up to a power of two and the unused tail is given
+    /// back immediately.
+    pub fn alloc_contiguous(&mut self, num_pages: usize, zone: Zone) -> Option<u64> {
+        if num_pages == 0 { return None; }
+        let order = num_pages.next_power_of_two().trailing_zeros() as usize;
+        let base = self.alloc_block(order, zone)?;
+        for i in num_pages..(1 << order) {
+            self.free_frame(base + i as u64 * 4096);
+        }
+        Some(base)
+    }
+
+    pub fn allocate_frame(&mut self) -> Option<u64> {
+        self.alloc_block(0, Zone::Normal)
+    }
+
+    /// Drop one reference to `addr`'s frame.  Only when the refcount reaches
+    /// zero is the frame returned to its zone (merging with free buddies) and
+    /// the allocation count decremented — shared (COW) frames stay alive for
+    /// their other owners.  Frames that were never handed out (MMIO, boot
+    /// memory, double frees) are ignored.
+    pub fn free_frame(&mut self, addr: u64) {
+        let mut pfn = (addr / 4096) as usize;
+        if pfn >= self.nframes { return; }
+        let info = unsafe { &mut *self.info(pfn) };
+        if info.refcount == 0 || info.flags & (FRAME_FREE | FRAME_RESERVED) != 0 { return; }
+        info.refcount -= 1;
+        if info.refcount != 0 { return; }
+        self.allocated_frames.fetch_sub(1, Ordering::Relaxed);
+
+        let zone = Zone::of(pfn);
+        let mut order = 0;
+        while order < MAX_ORDER {
+            let buddy = pfn ^ (1 << order);
+            if buddy >= self.nframes || Zone::of(buddy) != zone { break; }
+            let b = unsafe { &*self.info(buddy) };
+            if b.flags & FRAME_FREE == 0 || b.order as usize != order { break; }
+            unsafe { self.remove_free(buddy, order); }
+            pfn &= !(1 << order);
+            order += 1;
+        }
+        unsafe { self.push_free(pfn, order); }
+    }
+
+    /// Add one more reference to `addr`'s frame (used when a fork shares a
+    /// page between parent and child).
+    pub fn inc_refcount(&mut self, addr: u64) {
+        let pfn = (addr / 4096) as usize;
+        if pfn < self.nframes {
+            let info = unsafe { &mut *self.info(pfn) };
+            info.refcount = info.refcount.saturating_add(1);
+        }
+    }
+
+    pub fn get_refcount(&self, addr: u64) -> u16 {
+        let pfn = (addr / 4096) as usize;
+        if pfn < self.nframes { unsafe { (*self.info(pfn)).refcount } } else { 0 }
+    }
+
+    /// `(managed_frames, free blocks per order)` of `zone`.
+    pub fn zone_info(&self, zone: Zone) -> (usize, [usize; MAX_ORDER + 1]) {
+        let z = &self.zones[zone as usize];
+        (z.managed, z.nr_free)
+    }
+}
@@ -0,0 +1,705 @@
+// src/kernel/mem/mmap.rs
+//! Memory-mapped regions for OxideOS: mmap, munmap, mprotect, mremap,
+//! msync, mincore and madvise.
+//!
+//! # Design
+//!
+//! Every mapping is tracked as an `MmapRegion` in the owning task; regions
+//! that touch and agree on protection and backing are kept merged, so
+//! memory handed out piecemeal (malloc arenas) takes one slot.  Pages are
+//! populated eagerly when the mapping is created: anonymous regions get
+//! zeroed frames, file regions get a copy of the file contents.
+//!
+//! - `MAP_PRIVATE`: the copy belongs to the task and becomes copy-on-write
+//!   across `fork()` like any other private page.
+//! - `MAP_SHARED`: `fork()` shares the frames (refcounted) instead of COW,
+//!   and pages the MMU marked dirty are written back to the file on `msync`,
+//!   `munmap`, `exec` and exit.  Write-back never grows the file.
+//!
+//! FAT16 and ext2 mappings own a private duplicate of the caller's raw FD so
+//! the mapping survives `close()`; RamFS mappings reference the inode index.
+//! `/dev/fb0` mappings point straight at the framebuffer's physical pages
+//! and are always shared; they have nothing to populate or write back.
+//!
+//! All functions operate on the currently running task and return Linux
+//! errno values as negative numbers.
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use crate::kernel::paging_allocator as pa;
+use crate::kernel::scheduler::{
+    SCHED, CURRENT_TASK_IDX, MAX_TASKS, MAX_MMAP_REGIONS, MmapBacking, MmapRegion, Task,
+};
+
+// ── Linux ABI constants ───────────────────────────────────────────────────────
+
+pub const PROT_READ:  u32 = 0x1;
+pub const PROT_WRITE: u32 = 0x2;
+pub const PROT_EXEC:  u32 = 0x4;
+
+pub use crate::kernel::syscall_core::{
+    MAP_SHARED, MAP_PRIVATE, MAP_SHARED_VALIDATE, MAP_TYPE, MAP_FIXED, MAP_ANONYMOUS,
+};
+
+pub const MREMAP_MAYMOVE: u32 = 1;
+pub const MREMAP_FIXED:   u32 = 2;
+
+pub const MS_ASYNC:      u32 = 1;
+pub const MS_INVALIDATE: u32 = 2;
+pub const MS_SYNC:       u32 = 4;
+
+pub const MADV_DONTNEED: u32 = 4;
+
+/// First address past the canonical lower half.
+const USER_SPACE_END: u64 = 0x0000_8000_0000_0000;
+const PAGE_SIZE: u64 = 4096;
+
+fn pages_for(len: u64) -> u64 { len.div_ceil(PAGE_SIZE) }
+
+/// Pages covering `len` bytes at `addr`, and the end of those pages, if
+/// they lie below `USER_SPACE_END`.  `addr` and `len` come straight from
+/// user space, so the sums are checked.
+fn user_range(addr: u64, len: u64) -> Option<(u64, u64)> {
+    let pages = pages_for(len);
+    let end   = pages.checked_mul(PAGE_SIZE).and_then(|b| addr.checked_add(b))?;
+    (end <= USER_SPACE_END).then_some((pages, end))
+}
+
+/// Largest mapping, in pages: all of user space.
+const MAX_PAGES: u64 = USER_SPACE_END / PAGE_SIZE;
+
+unsafe fn current_task() -> *mut Task {
+    let sched = &raw mut SCHED;
+    unsafe { &raw mut (*sched).tasks[CURRENT_TASK_IDX] }
+}
+
+// ── Backing-file I/O ──────────────────────────────────────────────────────────
+
+/// Length of the backing file in bytes (0 for anonymous memory).
+unsafe fn file_len(backing: MmapBacking) -> u64 {
+    match backing {
+        MmapBacking::Anon | MmapBacking::Fb => 0,
+        MmapBacking::RamFS(idx) => match unsafe { crate::kernel::fs::ramfs::RAMFS.get() } {
+            Some(fs) if idx < fs.inodes.len() => fs.inodes[idx].data.len() as u64,
+            _ => 0,
+        },
+        MmapBacking::Fat16(fd) => crate::kernel::fat::file_size(fd) as u64,
+        MmapBacking::Ext2(fd)  => crate::kernel::ext2::file_size(fd) as u64,
+    }
+}
+
+/// Read file bytes at `offset` into `buf`.  Short reads leave the rest of
+/// `buf` untouched (callers pass pre-zeroed frames).
+unsafe fn file_read(backing: MmapBacking, offset: u64, buf: &mut [u8]) {
+    match backing {
+        MmapBacking::Anon | MmapBacking::Fb => {}
+        MmapBacking::RamFS(idx) => {
+            if let Some(fs) = unsafe { crate::kernel::fs::ramfs::RAMFS.get() } {
+                if idx >= fs.inodes.len() { return; }
+                let data = &fs.inodes[idx].data;
+                let start = (offset as usize).min(data.len());
+                let n = (data.len() - start).min(buf.len());
+                buf[..n].copy_from_slice(&data[start..start + n]);
+            }
+        }
+        MmapBacking::Fat16(fd) => { let _ = unsafe { crate::kernel::fat::pread(fd, offset as u32, buf) }; }
+        MmapBacking::Ext2(fd)  => { let _ = unsafe { crate::kernel::ext2::pread(fd, offset as u32, buf) }; }
+    }
+}
+
+/// Write `data` to the backing file at `offset` (never past the current EOF).
+unsafe fn file_write(backing: MmapBacking, offset: u64, data: &[u8]) {
+    match backing {
+        MmapBacking::Anon | MmapBacking::Fb => {}
+        MmapBacking::RamFS(idx) => {
+            if let Some(fs) = unsafe { crate::kernel::fs::ramfs::RAMFS.get() } {
+                if idx >= fs.inodes.len() { return; }
+                let file = &mut fs.inodes[idx].data;
+                let start = offset as usize;
+                if start >= file.len() { return; }
+                let n = (file.len() - start).min(data.len());
+                file[start..start + n].copy_from_slice(&data[..n]);
+            }
+        }
+        MmapBacking::Fat16(fd) => { let _ = unsafe { crate::kernel::fat::pwrite(fd, offset as u32, data) }; }
+        MmapBacking::Ext2(fd)  => { let _ = unsafe { crate::kernel::ext2::pwrite(fd, offset as u32, data) }; }
+    }
+}
+
+/// Kernel (HHDM) view of the frame backing user page `virt`.
+unsafe fn page_slice(cr3: u64, virt: u64) -> Option<&'static mut [u8]> {
+    let phys = unsafe { pa::user_page_phys_in(cr3, virt)? };
+    let ptr  = (phys + pa::get_hhdm_offset()) as *mut u8;
+    Some(unsafe { core::slice::from_raw_parts_mut(ptr, PAGE_SIZE as usize) })
+}
+
+/// Copy file contents into `pages` freshly mapped (zeroed) pages at `virt`.
+unsafe fn fill_from_file(cr3: u64, virt: u64, pages: u64, backing: MmapBacking, offset: u64) {
+    if backing == MmapBacking::Anon { return; }
+    let len = unsafe { file_len(backing) };
+    for p in 0..pages {
+        let file_off = offset + p * PAGE_SIZE;
+        if file_off >= len { break; }
+        if let Some(buf) = unsafe { page_slice(cr3, virt + p * PAGE_SIZE) } {
+            let n = (len - file_off).min(PAGE_SIZE) as usize;
+            unsafe { file_read(backing, file_off, &mut buf[..n]); }
+        }
+    }
+}
+
+/// Write the dirty pages of `r` inside `[start, end)` back to its file.
+unsafe fn write_back(cr3: u64, r: &MmapRegion, start: u64, end: u64) {
+    if !r.shared || r.backing == MmapBacking::Anon { return; }
+    let len = unsafe { file_len(r.backing) };
+    let mut virt = start.max(r.virt);
+    while virt < end.min(r.end()) {
+        let file_off = r.offset + (virt - r.virt);
+        if file_off < len && unsafe { pa::take_dirty_in(cr3, virt) } {
+            if let Some(buf) = unsafe { page_slice(cr3, virt) } {
+                let n = (len - file_off).min(PAGE_SIZE) as usize;
+                unsafe { file_write(r.backing, file_off, &buf[..n]); }
+            }
+        }
+        virt += PAGE_SIZE;
+    }
+}
+
+/// Close a mapping-owned raw FD once no region of `task` refers to it.
+unsafe fn release_backing(task: *mut Task, backing: MmapBacking) {
+    let n = unsafe { (*task).mmap_nregions };
+    let still_used = (0..n).any(|i| unsafe { (*task).mmap_regions[i].backing } == backing);
+    if still_used { return; }
+    match backing {
+        MmapBacking::Fat16(fd) => { let _ = unsafe { crate::kernel::fat::close(fd) }; }
+        MmapBacking::Ext2(fd)  => { let _ = unsafe { crate::kernel::ext2::close(fd) }; }
+        _ => {}
+    }
+}
+
+// ── Region bookkeeping ────────────────────────────────────────────────────────
+
+/// Ensure no region straddles `addr`, splitting one in two if necessary.
+/// Returns `false` if the region table is full.
+unsafe fn split_at(task: *mut Task, addr: u64) -> bool {
+    let n = unsafe { (*task).mmap_nregions };
+    for i in 0..n {
+        let r = unsafe { (*task).mmap_regions[i] };
+        if addr <= r.virt || addr >= r.end() { continue; }
+        if n >= MAX_MMAP_REGIONS { return false; }
+        let head_pages = ((addr - r.virt) / PAGE_SIZE) as u32;
+        let mut tail = r;
+        tail.virt   = addr;
+        tail.pages  = r.pages - head_pages;
+        if tail.backing != MmapBacking::Anon { tail.offset += addr - r.virt; }
+        unsafe {
+            (*task).mmap_regions[i].pages = head_pages;
+            (*task).mmap_regions[n]       = tail;
+            (*task).mmap_nregions         = n + 1;
+        }
+        return true;
+    }
+    true
+}
+
+/// Can `b` continue `a` as one region?  It must start where `a` ends and
+/// agree on protection, sharing and backing — for a file, at the next
+/// offset.
+fn mergeable(a: &MmapRegion, b: &MmapRegion) -> bool {
+    a.end() == b.virt && a.prot == b.prot && a.shared == b.shared
+        && a.file_writable == b.file_writable && a.backing == b.backing
+        && (a.backing == MmapBacking::Anon || a.offset + a.pages as u64 * PAGE_SIZE == b.offset)
+        && a.pages.checked_add(b.pages).is_some()
+}
+
+/// Merge every pair of regions `mergeable` allows.
+unsafe fn coalesce(task: *mut Task) {
+    let regions = unsafe { &mut (*task).mmap_regions };
+    let n       = unsafe { &mut (*task).mmap_nregions };
+    let mut i = 0;
+    while i < *n {
+        let Some(j) = (0..*n).find(|&j| mergeable(&regions[i], &regions[j])) else {
+            i += 1;
+            continue;
+        };
+        regions[i].pages += regions[j].pages;
+        let last = *n - 1;
+        regions[j]    = regions[last];
+        regions[last] = MmapRegion::empty();
+        *n = last;
+        // The swap may have moved region `i`; start over.
+        i = 0;
+    }
+}
+
+/// Record a new mapping `r`, growing a neighbour when it can.  Returns
+/// `false` if it needs a slot and the table is full.
+unsafe fn insert(task: *mut Task, r: MmapRegion) -> bool {
+    let regions = unsafe { &mut (*task).mmap_regions };
+    let n       = unsafe { (*task).mmap_nregions };
+    if let Some(j) = (0..n).find(|&j| mergeable(&regions[j], &r)) {
+        regions[j].pages += r.pages;
+    } else if let Some(j) = (0..n).find(|&j| mergeable(&r, &regions[j])) {
+        regions[j].pages += r.pages;
+        regions[j].virt   = r.virt;
+        regions[j].offset = r.offset;
+    } else if n < MAX_MMAP_REGIONS {
+        regions[n] = r;
+        unsafe { (*task).mmap_nregions = n + 1; }
+    } else {
+        return false;
+    }
+    // `r` may have closed the gap between two regions.
+    unsafe { coalesce(task); }
+    true
+}
+
+/// Index of the region containing `virt`, if any.
+unsafe fn region_at(task: *const Task, virt: u64) -> Option<usize> {
+    let n = unsafe { (*task).mmap_nregions };
+    (0..n).find(|&i| {
+        let r = unsafe { &(*task).mmap_regions[i] };
+        virt >= r.virt && virt < r.end()
+    })
+}
+
+/// `true` if every page of `[start, start + pages*4K)` is mapped.
+unsafe fn range_mapped(cr3: u64, start: u64, pages: u64) -> bool {
+    (0..pages).all(|p| unsafe { pa::user_page_phys_in(cr3, start + p * PAGE_SIZE) }.is_some())
+}
+
+/// First address at or above `start` with `pages` consecutive unmapped pages.
+unsafe fn find_free(cr3: u64, start: u64, pages: u64) -> Option<u64> {
+    let mut base = start;
+    'search: while base + pages * PAGE_SIZE <= USER_SPACE_END {
+        for p in 0..pages {
+            let virt = base + p * PAGE_SIZE;
+            if unsafe { pa::user_page_phys_in(cr3, virt) }.is_some() {
+                base = virt + PAGE_SIZE;
+                continue 'search;
+            }
+        }
+        return Some(base);
+    }
+    None
+}
+
+/// Apply `prot` to already-mapped pages (PROT_NONE clears user access).
+/// Returns `false` if a page could not be brought back in from swap.
+unsafe fn apply_prot(cr3: u64, virt: u64, pages: u64, prot: u32, shared: bool) -> bool {
+    unsafe {
+        pa::protect_user_region_in(
+            cr3, virt, pages as usize,
+            prot & (PROT_READ | PROT_WRITE | PROT_EXEC) != 0,
+            prot & PROT_WRITE != 0,
+            prot & PROT_EXEC  != 0,
+            shared,
+        )
+    }.is_ok()
+}
+
+/// Map fresh pages for (part of) a region and populate them.  Framebuffer
+/// regions map the device's own pages instead.
+unsafe fn populate(
+    cr3: u64, virt: u64, pages: u64, prot: u32, shared: bool,
+    backing: MmapBacking, offset: u64,
+) -> bool {
+    let ok = if backing == MmapBacking::Fb {
+        match crate::kernel::fbdev::mmap_phys(offset, pages) {
+            Some(phys) => unsafe {
+                pa::map_phys_pages_in(cr3, virt, phys, pages as usize, prot & PROT_WRITE != 0)
+            }.is_ok(),
+            None => false,
+        }
+    } else {
+        unsafe {
+            pa::map_user_region_in(cr3, virt, pages as usize,
+                                   prot & PROT_WRITE != 0, prot & PROT_EXEC != 0)
+        }.is_ok()
+    };
+    if !ok {
+        unsafe { pa::unmap_user_region_in(cr3, virt, pages as usize); }
+        return false;
+    }
+    unsafe { fill_from_file(cr3, virt, pages, backing, offset); }
+    // Shared pages are written back via their dirty bits; keep them resident.
+    if shared { unsafe { pa::pin_user_region_in(cr3, virt, pages as usize); } }
+    if prot & (PROT_READ | PROT_WRITE | PROT_EXEC) == 0
+        && !unsafe { apply_prot(cr3, virt, pages, prot, shared) }
+    {
+        unsafe { pa::unmap_user_region_in(cr3, virt, pages as usize); }
+        return false;
+    }
+    true
+}
+
+/// Unmap `[start, start + pages*4K)` from `task`: write back shared pages,
+/// drop the covered regions and free the frames.
+unsafe fn unmap_range(task: *mut Task, start: u64, pages: u64) -> i64 {
+    let Some((_, end)) = user_range(start, pages * PAGE_SIZE) else { return -22 };
+    let cr3 = unsafe { (*task).cr3 };
+    if !unsafe { split_at(task, start) && split_at(task, end) } { return -12; } // ENOMEM
+
+    let mut i = 0;
+    while i < unsafe { (*task).mmap_nregions } {
+        let r = unsafe { (*task).mmap_regions[i] };
+        if r.virt >= start && r.end() <= end {
+            unsafe { write_back(cr3, &r, r.virt, r.end()); }
+            let last = unsafe { (*task).mmap_nregions } - 1;
+            unsafe {
+                (*task).mmap_regions[i]    = (*task).mmap_regions[last];
+                (*task).mmap_regions[last] = MmapRegion::empty();
+                (*task).mmap_nregions      = last;
+                release_backing(task, r.backing);
+            }
+        } else {
+            i += 1;
+        }
+    }
+    unsafe { pa::unmap_user_region_in(cr3, start, pages as usize); }
+    0
+}
+
+// ── Syscall entry points ──────────────────────────────────────────────────────
+
+/// mmap(addr, len, prot, flags, fd, offset).
+pub unsafe fn mmap(addr: u64, len: u64, prot: u32, flags: u32, fd: i32, offset: u64) -> i64 {
+    // syscall_core::sys_mmap has already refused a zero or oversized length,
+    // a misaligned or wrapping offset and a bad MAP_FIXED address.
+    let mut shared = match flags & MAP_TYPE {
+        MAP_SHARED | MAP_SHARED_VALIDATE => true,
+        MAP_PRIVATE                      => false,
+        _                                => return -22, // EINVAL
+    };
+    let pages = pages_for(len);
+    let task  = unsafe { current_task() };
+    let cr3   = unsafe { (*task).cr3 };
+
+    // Resolve the backing object.
+    let (backing, file_writable) = if flags & MAP_ANONYMOUS != 0 {
+        (MmapBacking::Anon, true)
+    } else {
+        use crate::kernel::fs::ramfs::{FdBackend, MAX_FD};
+        if fd < 0 || fd as usize >= MAX_FD { return -9; } // EBADF
+        let Some(e) = (unsafe { (*task).fd_table.entries[fd as usize] }) else { return -9 };
+        if shared && prot & PROT_WRITE != 0 && !e.writable { return -13; } // EACCES
+        let backing = match e.backend {
+            FdBackend::RamFS => MmapBacking::RamFS(e.inode_idx),
+            FdBackend::Fat16 => {
+                let raw = unsafe { crate::kernel::fat::dup_fd(e.raw_fd) };
+                if raw < 0 { return raw; }
+                MmapBacking::Fat16(raw as i32)
+            }
+            FdBackend::Ext2 => {
+                let raw = unsafe { crate::kernel::ext2::dup_fd(e.raw_fd) };
+                if raw < 0 { return raw; }
+                MmapBacking::Ext2(raw as i32)
+            }
+            FdBackend::Fb => {
+                if crate::kernel::fbdev::mmap_phys(offset, pages).is_none() { return -22; }
+                if prot & PROT_WRITE != 0 && !e.writable { return -13; }
+                // Device memory: even a private mapping sees the screen.
+                shared = true;
+                MmapBacking::Fb
+            }
+            _ => return -19, // ENODEV
+        };
+        (backing, e.writable)
+    };
+
+    // Pick the virtual range.
+    let mmap_end = unsafe {
+        if (*task).mmap_end == 0 { (*task).layout.mmap_base } else { (*task).mmap_end }
+    };
+    let base = if flags & MAP_FIXED != 0 {
+        let r = unsafe { unmap_range(task, addr, pages) };
+        if r < 0 {
+            unsafe { release_backing(task, backing); }
+            return r;
+        }
+        addr
+    } else {
+        match unsafe { find_free(cr3, mmap_end, pages) } {
+            Some(b) => b,
+            None => {
+                unsafe { release_backing(task, backing); }
+                return -12;
+            }
+        }
+    };
+    let end = base + pages * PAGE_SIZE;
+
+    if !unsafe { populate(cr3, base, pages, prot, shared, backing, offset) } {
+        unsafe { release_backing(task, backing); }
+        return -12;
+    }
+
+    let region = MmapRegion {
+        virt: base, pages: pages as u32, prot, shared, file_writable, backing, offset,
+    };
+    if !unsafe { insert(task, region) } {
+        unsafe {
+            pa::unmap_user_region_in(cr3, base, pages as usize);
+            release_backing(task, backing);
+        }
+        return -12; // ENOMEM
+    }
+    // Fixed mappings only move the bump pointer if they straddle it.
+    if flags & MAP_FIXED == 0 || (base <= mmap_end && end > mmap_end) {
+        unsafe { (*task).mmap_end = end; }
+    }
+    base as i64
+}
+
+/// munmap(addr, len).
+pub unsafe fn munmap(addr: u64, len: u64) -> i64 {
+    if len == 0 || addr & (PAGE_SIZE - 1) != 0 { return -22; }
+    let Some((pages, _)) = user_range(addr, len) else { return -22 };
+    unsafe { unmap_range(current_task(), addr, pages) }
+}
+
+/// mprotect(addr, len, prot).
+pub unsafe fn mprotect(addr: u64, len: u64, prot: u32) -> i64 {
+    if addr & (PAGE_SIZE - 1) != 0 { return -22; }
+    if len == 0 { return 0; }
+    let Some((pages, end)) = user_range(addr, len) else { return -12 }; // ENOMEM
+    let task  = unsafe { current_task() };
+    let cr3   = unsafe { (*task).cr3 };
+
+    if !unsafe { range_mapped(cr3, addr, pages) } { return -12; } // ENOMEM
+    if !unsafe { split_at(task, addr) && split_at(task, end) } { return -12; }
+
+    // Shared file mappings opened read-only can never become writable.
+    let n = unsafe { (*task).mmap_nregions };
+    for i in 0..n {
+        let r = unsafe { (*task).mmap_regions[i] };
+        if r.virt >= addr && r.end() <= end && r.shared
+            && r.backing != MmapBacking::Anon && prot & PROT_WRITE != 0 && !r.file_writable
+        {
+            return -13; // EACCES
+        }
+    }
+
+    let mut virt = addr;
+    while virt < end {
+        let shared = match unsafe { region_at(task, virt) } {
+            Some(i) => unsafe {
+                (*task).mmap_regions[i].prot = prot;
+                (*task).mmap_regions[i].shared
+            },
+            None => false,
+        };
+        if !unsafe { apply_prot(cr3, virt, 1, prot, shared) } { return -12; } // ENOMEM
+        virt += PAGE_SIZE;
+    }
+    unsafe { coalesce(task); }
+    0
+}
+
+/// mremap(old_addr, old_len, new_len, flags).  Supports shrinking, growing
+/// in place, and moving with `MREMAP_MAYMOVE`.
+pub unsafe fn mremap(old_addr: u64, old_len: u64, new_len: u64, flags: u32) -> i64 {
+    if old_addr & (PAGE_SIZE - 1) != 0 || new_len == 0 { return -22; }
+    if flags & MREMAP_FIXED != 0 || flags & !(MREMAP_MAYMOVE | MREMAP_FIXED) != 0 { return -22; }
+    let Some((old_pages, old_end)) = user_range(old_addr, old_len) else { return -22 };
+    let new_pages = pages_for(new_len);
+    if new_pages > MAX_PAGES { return -12; } // ENOMEM
+    let task      = unsafe { current_task() };
+    let cr3       = unsafe { (*task).cr3 };
+
+    if !unsafe { split_at(task, old_addr) && split_at(task, old_end) } { return -12; }
+    let Some(i) = (unsafe { region_at(task, old_addr) }) else { return -14 }; // EFAULT
+    let r = unsafe { (*task).mmap_regions[i] };
+    if r.end() != old_end { return -14; }
+
+    if new_pages <= old_pages {
+        if new_pages < old_pages {
+            let r = unsafe { unmap_range(task, old_addr + new_pages * PAGE_SIZE, old_pages - new_pages) };
+            if r < 0 { return r; }
+        }
+        return old_addr as i64;
+    }
+
+    let extra     = new_pages - old_pages;
+    let mmap_end  = unsafe {
+        if (*task).mmap_end == 0 { (*task).layout.mmap_base } else { (*task).mmap_end }
+    };
+    let tail_free = old_end + extra * PAGE_SIZE <= USER_SPACE_END
+        && (0..extra).all(|p| unsafe { pa::user_page_phys_in(cr3, old_end + p * PAGE_SIZE) }.is_none());
+
+    let new_base = if tail_free {
+        old_addr
+    } else if flags & MREMAP_MAYMOVE != 0 {
+        let Some(dst) = (unsafe { find_free(cr3, mmap_end, new_pages) }) else { return -12 };
+        if unsafe { pa::move_user_region_in(cr3, old_addr, dst, old_pages as usize) }.is_err() {
+            return -12;
+        }
+        // The pages now live at `dst`; record that before growing so a
+        // failed populate below leaves the region table matching them.
+        unsafe {
+            (*task).mmap_regions[i].virt = dst;
+            let moved_end = dst + old_pages * PAGE_SIZE;
+            if moved_end > mmap_end { (*task).mmap_end = moved_end; }
+        }
+        dst
+    } else {
+        return -12; // ENOMEM
+    };
+
+    let grow_at  = new_base + old_pages * PAGE_SIZE;
+    let Some(file_off) = r.offset.checked_add(old_pages * PAGE_SIZE)
+        .filter(|off| off.checked_add(extra * PAGE_SIZE).is_some())
+    else { return -75 }; // EOVERFLOW
+    if !unsafe { populate(cr3, grow_at, extra, r.prot, r.shared, r.backing, file_off) } {
+        return -12;
+    }
+
+    let new_end = new_base + new_pages * PAGE_SIZE;
+    unsafe {
+        (*task).mmap_regions[i].virt  = new_base;
+        (*task).mmap_regions[i].pages = new_pages as u32;
+        if new_end > mmap_end { (*task).mmap_end = new_end; }
+        coalesce(task);
+    }
+    new_base as i64
+}
+
+/// msync(addr, len, flags) — write dirty MAP_SHARED pages back to their files.
+pub unsafe fn msync(addr: u64, len: u64, flags: u32) -> i64 {
+    if addr & (PAGE_SIZE - 1) != 0 { return -22; }
+    if flags & !(MS_ASYNC | MS_INVALIDATE | MS_SYNC) != 0 { return -22; }
+    if flags & MS_ASYNC != 0 && flags & MS_SYNC != 0 { return -22; }
+    let Some((pages, end)) = user_range(addr, len) else { return -12 }; // ENOMEM
+    let task  = unsafe { current_task() };
+    let cr3   = unsafe { (*task).cr3 };
+    if !unsafe { range_mapped(cr3, addr, pages) } { return -12; }
+
+    // Every backend is write-through, so MS_ASYNC and MS_SYNC behave alike
+    // and MS_INVALIDATE has no cached copies to drop.
+    let n = unsafe { (*task).mmap_nregions };
+    for i in 0..n {
+        let r = unsafe { (*task).mmap_regions[i] };
+        if r.virt < end && r.end() > addr {
+            unsafe { write_back(cr3, &r, addr, end); }
+        }
+    }
+    0
+}
+
+/// mincore(addr, len, vec) — one byte per page, bit 0 set if resident.
+pub unsafe fn mincore(addr: u64, len: u64, vec_ptr: u64) -> i64 {
+    if addr & (PAGE_SIZE - 1) != 0 { return -22; }
+    let Some((pages, _)) = user_range(addr, len) else { return -12 }; // ENOMEM
+    if crate::kernel::syscall_core::validate_user_range(vec_ptr, pages).is_err() { return -14; }
+    let cr3 = unsafe { (*current_task()).cr3 };
+    if !unsafe { pa::prepare_user_write_in(cr3, vec_ptr, pages as usize) } { return -14; } // EFAULT
+    for p in 0..pages {
+        if unsafe { pa::user_page_phys_in(cr3, addr + p * PAGE_SIZE) }.is_none() { return -12; }
+        unsafe { core::ptr::write_volatile((vec_ptr + p) as *mut u8, 1); }
+    }
+    0
+}
+
+/// madvise(addr, len, advice).  Only `MADV_DONTNEED` has an effect: private
+/// pages are dropped and re-populated (zeroes, or fresh file contents).
+/// Ranges not covered by an mmap region (ELF image, stack) are refused.
+pub unsafe fn madvise(addr: u64, len: u64, advice: u32) -> i64 {
+    if addr & (PAGE_SIZE - 1) != 0 { return -22; }
+    let Some((pages, _)) = user_range(addr, len) else { return -22 };
+    if advice != MADV_DONTNEED { return 0; }
+    let task  = unsafe { current_task() };
+    let cr3   = unsafe { (*task).cr3 };
+    if !unsafe { range_mapped(cr3, addr, pages) } { return -12; }
+    if (0..pages).any(|p| unsafe { region_at(task, addr + p * PAGE_SIZE) }.is_none()) {
+        return -22;
+    }
+
+    for p in 0..pages {
+        let virt = addr + p * PAGE_SIZE;
+        let Some(i) = (unsafe { region_at(task, virt) }) else { return -22 };
+        let r = unsafe { (*task).mmap_regions[i] };
+        if r.shared { continue; }
+        let (prot, backing, file_off) = (r.prot, r.backing, r.offset + (virt - r.virt));
+        unsafe { pa::unmap_user_region_in(cr3, virt, 1); }
+        if !unsafe { populate(cr3, virt, 1, prot, false, backing, file_off) } { return -12; }
+    }
+    0
+}
+
+// ── Task lifecycle hooks ──────────────────────────────────────────────────────
+
+/// Write back and drop every mapping of task `idx`.  Called on exit and
+/// exec while the task's page table is still alive.
+pub unsafe fn release_task(idx: usize) {
+    let sched = &raw mut SCHED;
+    let task  = unsafe { &raw mut (*sched).tasks[idx] };
+    let cr3   = unsafe { (*task).cr3 };
+    while unsafe { (*task).mmap_nregions } > 0 {
+        let last = unsafe { (*task).mmap_nregions } - 1;
+        let r    = unsafe { (*task).mmap_regions[last] };
+        if cr3 != 0 { unsafe { write_back(cr3, &r, r.virt, r.end()); } }
+        unsafe {
+            (*task).mmap_regions[last] = MmapRegion::empty();
+            (*task).mmap_nregions      = last;
+            release_backing(task, r.backing);
+        }
+    }
+    unsafe { (*task).mmap_end = 0; }
+}
+
+/// Virtual ranges of `task`'s MAP_SHARED regions, for `fork()`.
+pub fn shared_ranges(task: &Task) -> Vec<(u64, u64)> {
+    task.mmap_regions[..task.mmap_nregions].iter()
+        .filter(|r| r.shared)
+        .map(|r| (r.virt, r.end()))
+        .collect()
+}
+
+/// Give a freshly forked child its own duplicates of the raw FDs its
+/// inherited file mappings refer to.
+pub unsafe fn on_fork(child_idx: usize) {
+    let sched = &raw mut SCHED;
+    let child = unsafe { &raw mut (*sched).tasks[child_idx] };
+    let n     = unsafe { (*child).mmap_nregions };
+    let mut done = [false; MAX_MMAP_REGIONS];
+    for i in 0..n {
+        if done[i] { continue; }
+        let old = unsafe { (*child).mmap_regions[i].backing };
+        let new = match old {
+            MmapBacking::Fat16(fd) => {
+                let r = unsafe { crate::kernel::fat::dup_fd(fd) };
+                if r < 0 { MmapBacking::Anon } else { MmapBacking::Fat16(r as i32) }
+            }
+            MmapBacking::Ext2(fd) => {
+                let r = unsafe { crate::kernel::ext2::dup_fd(fd) };
+                if r < 0 { MmapBacking::Anon } else { MmapBacking::Ext2(r as i32) }
+            }
+            _ => continue,
+        };
+        for j in i..n {
+            if unsafe { (*child).mmap_regions[j].backing } == old {
+                unsafe { (*child).mmap_regions[j].backing = new; }
+                done[j] = true;
+            }
+        }
+    }
+}
+
+/// Keep RamFS-backed mappings consistent after `RamFs::remove_file`.
+/// Mappings of the removed inode keep their pages but lose write-back.
+pub unsafe fn on_ramfs_inode_removed(removed_idx: usize) {
+    let sched = &raw mut SCHED;
+    for t in 0..MAX_TASKS {
+        let task = unsafe { &raw mut (*sched).tasks[t] };
+        for i in 0..unsafe { (*task).mmap_nregions } {
+            let r = unsafe { &mut (*task).mmap_regions[i] };
+            if let MmapBacking::RamFS(idx) = r.backing {
+                if idx == removed_idx {
+                    r.backing = MmapBacking::Anon;
+                } else if idx > removed_idx {
+                    r.backing = MmapBacking::RamFS(idx - 1);
+                }
+            }
+        }
+    }
+}
@@ -1,3 +1,7 @@
 //! Memory management: frame allocator and paging.
+pub mod buddy;
 pub mod paging_allocator;
+pub mod slab;
+pub mod mmap;
+pub mod swap;
 // pub mod allocator; // alternative bump allocator (unused)
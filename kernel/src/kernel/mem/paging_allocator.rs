@@ -10,10 +10,10 @@ use core::alloc::{GlobalAlloc, Layout};
 use core::ptr::{self, NonNull};
 use core::sync::atomic::{AtomicUsize, Ordering, AtomicBool};
 use core::cell::UnsafeCell;
-use limine::memory_map::EntryType;
 use limine::request::MemoryMapRequest;
 use linked_list_allocator::Heap;
 use crate::kernel::serial::SERIAL_PORT;
+use super::buddy::{PhysicalFrameAllocator, Zone, HUGE_ORDER, MAX_ORDER};
 
 // ============================================================================
 // PAGE TABLE STRUCTURES (x86_64)
@@ -27,10 +27,23 @@ impl PageTableFlags {
     const PRESENT:      u64 = 1 << 0;
     const WRITABLE:     u64 = 1 << 1;
     const USER:         u64 = 1 << 2;
+    /// Page Write-Through; with PCD and PAT selects the memory type.
+    const WRITE_THROUGH: u64 = 1 << 3;
+    /// Page Cache Disable: with PWT, selects UC for device registers.
+    const CACHE_DISABLE: u64 = 1 << 4;
+    /// In an L2 entry: maps a 2 MiB page directly instead of an L1 table.
+    const HUGE:         u64 = 1 << 7;
     /// Software-defined "copy-on-write" marker. Bit 9 is one of the AVL bits
     /// (9-11), architecturally ignored by the MMU for all paging-structure
     /// entries, so it is safe to repurpose for OS bookkeeping.
     const COW:          u64 = 1 << 9;
+    /// Software "never swap out" marker (AVL bit 10) for frames owned by
+    /// someone other than the page table: shared memory, MAP_SHARED file
+    /// pages and physical mappings.
+    const PINNED:       u64 = 1 << 10;
+    /// Software marker (AVL bit 11) on a *non-present* leaf entry: the page
+    /// lives in swap slot `entry >> 12`, the other low bits keep its flags.
+    const SWAPPED:      u64 = 1 << 11;
     const NO_EXECUTE:   u64 = 1 << 63;
 
     fn kernel_flags() -> Self { Self(Self::PRESENT | Self::WRITABLE) }
@@ -85,179 +98,6 @@ impl PageTable {
     fn zero(&mut self) { for e in &mut self.entries { e.clear(); } }
 }
 
-// ============================================================================
-// PHYSICAL FRAME ALLOCATOR  (bitmap, 256 MB range)
-// ============================================================================
-
-struct PhysicalFrameAllocator {
-    bitmap:           [u64; 1024],   // 1024×64 = 65536 frames = 256 MB
-    /// Reference count per frame. 0 = free, 1 = normally owned, >1 = shared
-    /// (e.g. COW pages shared between a forked parent and child).
-    refcount:         [u16; 65536],
-    next_frame:       AtomicUsize,
-    total_frames:     usize,
-    allocated_frames: AtomicUsize,
-}
-
-impl PhysicalFrameAllocator {
-    const fn new() -> Self {
-        Self {
-            bitmap:           [0; 1024],
-            refcount:         [0; 65536],
-            next_frame:       AtomicUsize::new(0),
-            total_frames:     0,
-            allocated_frames: AtomicUsize::new(0),
-        }
-    }
-
-    unsafe fn init(&mut self, memory_map: &MemoryMapRequest) {
-        unsafe { SERIAL_PORT.write_str("=== INITIALIZING PHYSICAL FRAME ALLOCATOR ===\n") };
-
-        if let Some(map) = memory_map.get_response() {
-            for word in &mut self.bitmap { *word = u64::MAX; } // all used initially
-
-            for entry in map.entries() {
-                if entry.entry_type == EntryType::USABLE {
-                    let start = (entry.base   as usize) / 4096;
-                    let count = (entry.length as usize) / 4096;
-                    // Skip the first 32 MB to protect the kernel binary,
-                    // Limine's page-table frames, and other boot structures
-                    // that Limine may have placed below that boundary.
-                    let safe  = core::cmp::max(start, 8192); // 8192 * 4096 = 32 MB
-
-                    if safe < 65536 {
-                        let end = core::cmp::min(start + count, 65536);
-                        for frame in safe..end {
-                            self.mark_free(frame);
-                            self.total_frames += 1;
-                        }
-                        unsafe {
-                            SERIAL_PORT.write_str("  Tracked frames ");
-                            SERIAL_PORT.write_decimal(safe as u32);
-                            SERIAL_PORT.write_str(" - ");
-                            SERIAL_PORT.write_decimal(end as u32);
-                            SERIAL_PORT.write_str("\n");
-                        }
-                    }
-                }
-            }
-
-            // Walk the existing Limine page-table hierarchy from CR3 and
-            // mark every intermediate table frame as used.  This prevents
-            // the allocator from handing out a frame that Limine already
-            // uses for an L4/L3/L2/L1 entry and corrupting kernel mappings.
-            unsafe { self.protect_page_table_frames(); }
-
-            unsafe {
-                SERIAL_PORT.write_str("Total trackable frames: ");
-                SERIAL_PORT.write_decimal(self.total_frames as u32);
-                SERIAL_PORT.write_str(" (");
-                SERIAL_PORT.write_decimal((self.total_frames * 4) as u32);
-                SERIAL_PORT.write_str(" KB)\n");
-            }
-        }
-    }
-
-    /// Walk the four-level page table rooted at CR3 (HHDM-mapped) and mark
-    /// every frame that holds an intermediate table as used in the bitmap.
-    unsafe fn protect_page_table_frames(&mut self) {
-        const HHDM: u64 = 0xFFFF_8000_0000_0000;
-        const PRESENT: u64 = 1;
-        const HUGE: u64 = 1 << 7;
-        const ADDR_MASK: u64 = 0x000F_FFFF_FFFF_F000;
-
-        let cr3: u64;
-        unsafe { core::arch::asm!("mov {}, cr3", out(reg) cr3); }
-        let l4_phys = cr3 & ADDR_MASK;
-        self.mark_frame_used(l4_phys);
-
-        let l4 = unsafe { &*((l4_phys + HHDM) as *const [u64; 512]) };
-        for &l4e in l4.iter() {
-            if l4e & PRESENT == 0 || l4e & HUGE != 0 { continue; }
-            let l3_phys = l4e & ADDR_MASK;
-            self.mark_frame_used(l3_phys);
-
-            let l3 = unsafe { &*((l3_phys + HHDM) as *const [u64; 512]) };
-            for &l3e in l3.iter() {
-                if l3e & PRESENT == 0 || l3e & HUGE != 0 { continue; }
-                let l2_phys = l3e & ADDR_MASK;
-                self.mark_frame_used(l2_phys);
-
-                let l2 = unsafe { &*((l2_phys + HHDM) as *const [u64; 512]) };
-                for &l2e in l2.iter() {
-                    if l2e & PRESENT == 0 || l2e & HUGE != 0 { continue; }
-                    let l1_phys = l2e & ADDR_MASK;
-                    self.mark_frame_used(l1_phys);
-                }
-            }
-        }
-    }
-
-    fn mark_frame_used(&mut self, phys: u64) {
-        let frame = (phys / 4096) as usize;
-        if frame < 65536 {
-            // If this frame was previously marked free, remove it from the count.
-            if self.is_free(frame) {
-                self.total_frames = self.total_frames.saturating_sub(1);
-            }
-            self.mark_used(frame);
-        }
-    }
-
-    fn mark_free(&mut self, frame: usize) {
-        if frame < 65536 { self.bitmap[frame / 64] &= !(1u64 << (frame % 64)); }
-    }
-    fn mark_used(&mut self, frame: usize) {
-        if frame < 65536 { self.bitmap[frame / 64] |=   1u64 << (frame % 64);  }
-    }
-    fn is_free(&self, frame: usize) -> bool {
-        frame < 65536 && (self.bitmap[frame / 64] & (1u64 << (frame % 64))) == 0
-    }
-
-    fn allocate_frame(&mut self) -> Option<u64> {
-        let start = self.next_frame.load(Ordering::Relaxed);
-        for off in 0..self.total_frames {
-            let frame = (start + off) % 65536;
-            if self.is_free(frame) {
-                self.mark_used(frame);
-                self.refcount[frame] = 1;
-                self.next_frame.store((frame + 1) % 65536, Ordering::Relaxed);
-                self.allocated_frames.fetch_add(1, Ordering::Relaxed);
-                return Some((frame * 4096) as u64);
-            }
-        }
-        None
-    }
-
-    /// Drop one reference to `addr`'s frame. Only when the refcount reaches
-    /// zero is the frame actually marked free and the allocation count
-    /// decremented — shared (COW) frames stay alive for their other owners.
-    fn free_frame(&mut self, addr: u64) {
-        let frame = (addr / 4096) as usize;
-        if frame < 65536 {
-            self.refcount[frame] = self.refcount[frame].saturating_sub(1);
-            if self.refcount[frame] == 0 {
-                self.mark_free(frame);
-                self.allocated_frames.fetch_sub(1, Ordering::Relaxed);
-            }
-        }
-    }
-
-    /// Add one more reference to `addr`'s frame (used when a fork shares a
-    /// page between parent and child).
-    fn inc_refcount(&mut self, addr: u64) {
-        let frame = (addr / 4096) as usize;
-        if frame < 65536 {
-            self.refcount[frame] = self.refcount[frame].saturating_add(1);
-        }
-    }
-
-    fn get_refcount(&self, addr: u64) -> u16 {
-        let frame = (addr / 4096) as usize;
-        if frame < 65536 { self.refcount[frame] } else { 0 }
-    }
-}
-
 // ============================================================================
 // PAGE TABLE MANAGER
 // ============================================================================
@@ -323,6 +163,7 @@ impl PageTableManager {
 
             // L2 → L1
             let l2 = self.get_table(l2_phys);
+            if l2.entries[l2i].0 & PageTableFlags::HUGE != 0 { return Err("Inside a 2 MiB page"); }
             let l1_phys = if l2.entries[l2i].is_present() {
                 let mut f = l2.entries[l2i].flags(); f.merge(pf);
                 l2.entries[l2i].set(l2.entries[l2i].addr(), f);
@@ -336,12 +177,60 @@ impl PageTableManager {
             // Leaf
             let l1 = self.get_table(l1_phys);
             if l1.entries[l1i].is_present() { return Err("Page already mapped"); }
+            if is_swap_entry(l1.entries[l1i].0) {
+                crate::kernel::swap::release_entry(l1.entries[l1i].0);
+            }
             l1.entries[l1i].set(phys, flags);
             core::arch::asm!("invlpg [{}]", in(reg) virt);
         }
         Ok(())
     }
 
+    /// Install a 2 MiB leaf in the L2 table for `virt` (both addresses must
+    /// be 2 MiB aligned).  `pat` sets the PAT bit (bit 12 of a huge entry),
+    /// which [`PageTableEntry::set`] would mask off.
+    unsafe fn map_huge(
+        &mut self,
+        virt:        u64,
+        phys:        u64,
+        flags:       PageTableFlags,
+        pat:         bool,
+        frame_alloc: &mut PhysicalFrameAllocator,
+    ) -> Result<(), &'static str> {
+        if (virt | phys) & (HUGE_PAGE_SIZE - 1) != 0 { return Err("Unaligned 2 MiB page"); }
+        let pf = flags.parent_table_flags();
+        let l4i = ((virt >> 39) & 0x1FF) as usize;
+        let l3i = ((virt >> 30) & 0x1FF) as usize;
+        let l2i = ((virt >> 21) & 0x1FF) as usize;
+
+        unsafe {
+            let l4 = self.get_table(self.l4_table_phys);
+            let l3_phys = if l4.entries[l4i].is_present() {
+                l4.entries[l4i].addr()
+            } else {
+                let t = frame_alloc.allocate_frame().ok_or("OOM: L3 table")?;
+                l4.entries[l4i].set(t, pf);
+                self.get_table(t).zero(); t
+            };
+            let l3 = self.get_table(l3_phys);
+            let l2_phys = if l3.entries[l3i].is_present() {
+                l3.entries[l3i].addr()
+            } else {
+                let t = frame_alloc.allocate_frame().ok_or("OOM: L2 table")?;
+                l3.entries[l3i].set(t, pf);
+                self.get_table(t).zero(); t
+            };
+            let l2 = self.get_table(l2_phys);
+            if l2.entries[l2i].is_present() { return Err("Page already mapped"); }
+            l2.entries[l2i].0 = phys
+                | (flags.0 & (0xFFF | PageTableFlags::NO_EXECUTE))
+                | PageTableFlags::HUGE
+                | if pat { 1 << 12 } else { 0 };
+            core::arch::asm!("invlpg [{}]", in(reg) virt);
+        }
+        Ok(())
+    }
+
     /// Remove a leaf mapping and return the freed physical address.
     unsafe fn unmap(
         &mut self,
@@ -362,9 +251,17 @@ impl PageTableManager {
 
             let l2 = self.get_table(l3.entries[l3i].addr());
             if !l2.entries[l2i].is_present() { return Err("Not mapped (L2)"); }
+            if l2.entries[l2i].0 & PageTableFlags::HUGE != 0 { return Err("2 MiB page (L2)"); }
 
             let l1 = self.get_table(l2.entries[l2i].addr());
-            if !l1.entries[l1i].is_present() { return Err("Not mapped (L1)"); }
+            if !l1.entries[l1i].is_present() {
+                let e = l1.entries[l1i].0;
+                if is_swap_entry(e) {
+                    l1.entries[l1i].clear();
+                    crate::kernel::swap::release_entry(e);
+                }
+                return Err("Not mapped (L1)");
+            }
 
             let phys = l1.entries[l1i].addr();
             l1.entries[l1i].clear();
@@ -382,6 +279,10 @@ impl PageTableManager {
 const FREE_LIST_CAPACITY: usize = 256;
 /// 16 MB pre-mapped slab for fine-grained kernel heap allocations.
 const SLAB_PAGES: usize = 4096;
+/// Map the kernel slab and the framebuffer with 2 MiB pages where possible
+/// (fewer page-table frames and TLB entries).  Turn off to use 4 KiB pages only.
+const HUGE_PAGES: bool = true;
+const HUGE_PAGE_SIZE: u64 = 2 * 1024 * 1024;
 
 struct PagingAllocatorInner {
     frame_allocator:    PhysicalFrameAllocator,
@@ -393,7 +294,8 @@ struct PagingAllocatorInner {
     /// Recycled virtual ranges: (virt_start, num_pages)
     free_list:     [(usize, usize); FREE_LIST_CAPACITY],
     free_list_len: usize,
-    /// Fine-grained allocator for sub-page kernel heap objects.
+    /// Fine-grained allocator for kernel heap objects over 4 KiB (smaller
+    /// ones come from `slab`'s caches).
     slab:     Heap,
     slab_end: usize,
 }
@@ -433,18 +335,40 @@ impl PagingAllocator {
         // ── Map slab pages eagerly so linked_list_allocator can manage them ──
         const SLAB_START: usize = 0xFFFFFF0000000000;
         const SLAB_SIZE:  usize = SLAB_PAGES * 4096; // 16 MB
+        // Each 2 MiB chunk uses a single huge page when a 2 MiB block is
+        // available, and falls back to 512 small pages otherwise.
         {
             let ptm = inner.page_table_manager.as_mut().unwrap();
-            for i in 0..SLAB_PAGES {
-                let virt = (SLAB_START + i * 4096) as u64;
-                let phys = inner.frame_allocator.allocate_frame()
-                    .expect("OOM during slab init");
-                unsafe {
-                    ptm.map(virt, phys, PageTableFlags::kernel_flags(),
-                            &mut inner.frame_allocator)
-                        .expect("map failed during slab init");
+            let mut huge_chunks = 0u32;
+            for chunk in 0..SLAB_PAGES / 512 {
+                let chunk_virt = (SLAB_START + chunk * HUGE_PAGE_SIZE as usize) as u64;
+                if HUGE_PAGES {
+                    if let Some(phys) = inner.frame_allocator.alloc_block(HUGE_ORDER, Zone::Normal) {
+                        unsafe {
+                            ptm.map_huge(chunk_virt, phys, PageTableFlags::kernel_flags(), false,
+                                         &mut inner.frame_allocator)
+                                .expect("map failed during slab init");
+                        }
+                        huge_chunks += 1;
+                        continue;
+                    }
+                }
+                for i in 0..512 {
+                    let virt = chunk_virt + i * 4096;
+                    let phys = inner.frame_allocator.allocate_frame()
+                        .expect("OOM during slab init");
+                    unsafe {
+                        ptm.map(virt, phys, PageTableFlags::kernel_flags(),
+                                &mut inner.frame_allocator)
+                            .expect("map failed during slab init");
+                    }
                 }
             }
+            unsafe {
+                SERIAL_PORT.write_str("Slab: ");
+                SERIAL_PORT.write_decimal(huge_chunks);
+                SERIAL_PORT.write_str(" x 2 MiB pages\n");
+            }
         }
         unsafe { inner.slab.init(SLAB_START as *mut u8, SLAB_SIZE); }
         inner.slab_end = SLAB_START + SLAB_SIZE;
@@ -539,8 +463,12 @@ unsafe impl GlobalAlloc for PagingAllocator {
             return ptr::null_mut();
         }
 
-        // Fast path: slab handles all small/medium kernel allocations in O(1)
-        // and actually frees memory on dealloc.
+        // Fast path: objects up to 4 KiB come from the size-class caches.
+        if let Some(ptr) = unsafe { crate::kernel::slab::kmalloc(layout) } {
+            if !ptr.is_null() { return ptr; }
+        }
+
+        // Linked-list slab for larger or over-aligned objects.
         if let Ok(ptr) = inner.slab.allocate_first_fit(layout) {
             return ptr.as_ptr();
         }
@@ -561,6 +489,11 @@ unsafe impl GlobalAlloc for PagingAllocator {
         let inner = unsafe { &mut *self.inner.get() };
         if !inner.initialized.load(Ordering::Relaxed) { return; }
 
+        if crate::kernel::slab::owns(ptr) {
+            unsafe { crate::kernel::slab::kfree(ptr); }
+            return;
+        }
+
         let addr = ptr as usize;
 
         // Slab range: [heap_start, slab_end)
@@ -664,6 +597,7 @@ pub unsafe fn map_user_region(
     writable:   bool,
     executable: bool,
 ) -> Result<(), &'static str> {
+    unsafe { crate::kernel::swap::ensure_free(num_pages); }
     let inner = unsafe { &mut *ALLOCATOR.inner.get() };
     if !inner.initialized.load(Ordering::Relaxed) {
         return Err("Paging allocator not initialized");
@@ -696,6 +630,7 @@ pub unsafe fn copy_to_region(dest: u64, bytes: &[u8]) {
 /// accessible while the task runs.  Returns the physical address of the new
 /// L4 table (to be loaded into CR3).
 pub unsafe fn create_user_page_table() -> Option<u64> {
+    unsafe { crate::kernel::swap::ensure_free(1); }
     let inner = unsafe { &mut *ALLOCATOR.inner.get() };
     if !inner.initialized.load(Ordering::Relaxed) { return None; }
 
@@ -730,6 +665,7 @@ pub unsafe fn map_user_region_in(
     writable:   bool,
     executable: bool,
 ) -> Result<(), &'static str> {
+    unsafe { crate::kernel::swap::ensure_free(num_pages); }
     let inner = unsafe { &mut *ALLOCATOR.inner.get() };
     if !inner.initialized.load(Ordering::Relaxed) {
         return Err("Paging allocator not initialized");
@@ -821,7 +757,10 @@ pub unsafe fn free_user_page_table(cr3_phys: u64) {
                 let l1 = (l1_phys + hho) as *const u64;
                 for l1i in 0..512usize {
                     let l1e = unsafe { *l1.add(l1i) };
-                    if l1e & 1 == 0 { continue; }
+                    if l1e & 1 == 0 {
+                        if is_swap_entry(l1e) { unsafe { crate::kernel::swap::release_entry(l1e); } }
+                        continue;
+                    }
                     fa.free_frame(l1e & 0x000F_FFFF_FFFF_F000);
                 }
                 fa.free_frame(l1_phys); // free the L1 table frame
@@ -841,10 +780,14 @@ pub unsafe fn free_user_page_table(cr3_phys: u64) {
 ///
 /// - Inside `shm_ranges`: shared verbatim (same frame, same flags, no
 ///   refcount change) — preserves shared-memory semantics across fork.
+/// - Inside `shared_ranges` (MAP_SHARED mmaps): shared verbatim with the
+///   frame refcount bumped, so either task may exit or unmap first.
 /// - Inside `stack_range`: deep-copied into a fresh frame, exactly as
 ///   `copy_user_page_table` does. This keeps the user stack always privately
 ///   writable so kernel-side writes (e.g. signal-frame delivery via
 ///   `copy_to_region_in`) never hit a read-only COW page.
+/// - Swapped-out pages: the swap entry is copied and the slot's reference
+///   count bumped.
 /// - Everything else: shared with the child via `inc_refcount`. If the page
 ///   was writable, both the parent's and child's PTE are rewritten to be
 ///   read-only with the COW bit set (the parent's live mapping is flushed
@@ -856,7 +799,9 @@ pub unsafe fn cow_fork_user_page_table(
     src_cr3:     u64,
     stack_range: (u64, u64),
     shm_ranges:  &[(u64, u64)],
+    shared_ranges: &[(u64, u64)],
 ) -> Option<u64> {
+    unsafe { crate::kernel::swap::ensure_free(0); }
     let inner = unsafe { &mut *ALLOCATOR.inner.get() };
     if !inner.initialized.load(Ordering::Relaxed) { return None; }
     let hho        = inner.page_table_manager.as_ref()?.higher_half_offset;
@@ -910,7 +855,17 @@ pub unsafe fn cow_fork_user_page_table(
 
                 for l1i in 0..512usize {
                     let l1e = unsafe { *src_l1.add(l1i) };
-                    if l1e & 1 == 0 { continue; }
+                    if l1e & 1 == 0 {
+                        // Swapped-out page: the child shares the swap slot and
+                        // gets its own private copy when it faults it back in.
+                        if is_swap_entry(l1e) {
+                            unsafe {
+                                crate::kernel::swap::dup_entry(l1e);
+                                *dst_l1.add(l1i) = l1e;
+                            }
+                        }
+                        continue;
+                    }
 
                     let virt = ((l4i as u64) << 39)
                              | ((l3i as u64) << 30)
@@ -923,6 +878,11 @@ pub unsafe fn cow_fork_user_page_table(
                         // Shared memory: share the frame verbatim, untouched
                         // by COW refcounting.
                         unsafe { *dst_l1.add(l1i) = l1e; }
+                    } else if shared_ranges.iter().any(|&r| in_range(virt, r)) {
+                        // MAP_SHARED mapping: both tasks keep writing to the
+                        // same frame; each holds its own reference.
+                        fa.inc_refcount(src_frame);
+                        unsafe { *dst_l1.add(l1i) = l1e; }
                     } else if in_range(virt, stack_range) {
                         // Stack: deep-copy so kernel-side writes (signal
                         // frames) never land on a read-only COW page.
@@ -971,42 +931,110 @@ pub unsafe fn copy_to_region_in(cr3_phys: u64, dest: u64, bytes: &[u8]) {
     }
 }
 
-/// Allocate `num_pages` zeroed physical frames.
-///
+/// Allocate `num_pages` zeroed, physically contiguous frames.
 /// Returns the physical address of the first frame, or 0 on failure.
 /// The frames are HHDM-mapped so the kernel can access them via
-/// `phys + 0xFFFF800000000000`.  They are never individually freed
-/// (suitable for kernel/shm lifetime objects).
+/// `phys + 0xFFFF800000000000`.  Release them with [`free_phys_frames`].
 pub unsafe fn alloc_phys_frames(num_pages: usize) -> u64 {
+    unsafe { alloc_contiguous_in(num_pages, Zone::Normal) }.unwrap_or(0)
+}
+
+/// Allocate `num_pages` zeroed, physically contiguous frames below 4 GiB
+/// for a device that can only address 32 bits of DMA.  Returns the
+/// physical address of the first frame.
+pub unsafe fn alloc_dma_frames(num_pages: usize) -> Option<u64> {
+    unsafe { alloc_contiguous_in(num_pages, Zone::Dma32) }
+}
+
+unsafe fn alloc_contiguous_in(num_pages: usize, zone: Zone) -> Option<u64> {
     let inner = unsafe { &mut *ALLOCATOR.inner.get() };
-    if !inner.initialized.load(Ordering::Relaxed) { return 0; }
-
-    // Allocate frames one by one.  We need them to be virtually contiguous in
-    // the HHDM so we can hand a single pointer to the caller.  The Limine
-    // physical memory map typically gives us large usable regions, so frames
-    // allocated consecutively from the bitmap *are* physically contiguous.
-    // If the first frame is at address P, subsequent frames should be P+4096,
-    // P+8192, etc.  We verify this and fall back to returning 0 on failure.
-
-    let first = match inner.frame_allocator.allocate_frame() {
-        Some(f) => f,
-        None    => return 0,
-    };
+    if !inner.initialized.load(Ordering::Relaxed) { return None; }
+    if num_pages > 1 << MAX_ORDER { return None; }
 
+    let base = inner.frame_allocator.alloc_contiguous(num_pages, zone)?;
     let hho = 0xFFFF800000000000u64;
-    // Zero the first frame.
-    unsafe { core::ptr::write_bytes((first + hho) as *mut u8, 0, 4096); }
+    unsafe { core::ptr::write_bytes((base + hho) as *mut u8, 0, num_pages * 4096); }
+    Some(base)
+}
+
+/// Release frames obtained from [`alloc_phys_frames`] or [`alloc_dma_frames`].
+pub unsafe fn free_phys_frames(phys: u64, num_pages: usize) {
+    let inner = unsafe { &mut *ALLOCATOR.inner.get() };
+    if !inner.initialized.load(Ordering::Relaxed) { return; }
+    for i in 0..num_pages {
+        inner.frame_allocator.free_frame(phys + i as u64 * 4096);
+    }
+}
 
-    for i in 1..num_pages {
-        let next = match inner.frame_allocator.allocate_frame() {
-            Some(f) => f,
-            None    => return 0, // partial alloc — frames leak, acceptable for now
+/// Per-zone `(name, managed_frames, free blocks per order)` for `/proc/buddyinfo`.
+pub fn buddy_info() -> [(&'static str, usize, [usize; MAX_ORDER + 1]); 2] {
+    let inner = unsafe { &*ALLOCATOR.inner.get() };
+    [Zone::Dma32, Zone::Normal].map(|z| {
+        let (managed, nr_free) = inner.frame_allocator.zone_info(z);
+        (z.name(), managed, nr_free)
+    })
+}
+
+/// Kernel window the framebuffer is remapped into.  It shares L4 slot 510
+/// with the kernel heap, so every address space created later sees it.
+const FB_WINDOW: u64 = 0xFFFF_FF00_8000_0000;
+
+/// Remap the framebuffer at HHDM address `fb_virt` (`len` bytes) into
+/// `FB_WINDOW` with 2 MiB write-combining pages (PAT entry 5, which Limine
+/// programs as WC).  The mapping is rounded out to whole 2 MiB pages.
+/// Returns the new address of the first pixel, or `fb_virt` unchanged when
+/// huge pages are disabled or the mapping fails.
+pub unsafe fn remap_framebuffer(fb_virt: u64, len: usize) -> u64 {
+    if !HUGE_PAGES { return fb_virt; }
+    let inner = unsafe { &mut *ALLOCATOR.inner.get() };
+    if !inner.initialized.load(Ordering::Relaxed) { return fb_virt; }
+    let Some(ptm) = inner.page_table_manager.as_mut() else { return fb_virt };
+
+    let phys   = fb_virt.wrapping_sub(ptm.higher_half_offset);
+    let offset = phys & (HUGE_PAGE_SIZE - 1);
+    let base   = phys - offset;
+    let chunks = (offset + len as u64).div_ceil(HUGE_PAGE_SIZE);
+    let mut flags = PageTableFlags::kernel_flags();
+    flags.0 |= PageTableFlags::WRITE_THROUGH | PageTableFlags::NO_EXECUTE; // PAT5 = PAT|PWT
+
+    for i in 0..chunks {
+        let r = unsafe {
+            ptm.map_huge(FB_WINDOW + i * HUGE_PAGE_SIZE, base + i * HUGE_PAGE_SIZE,
+                         flags, true, &mut inner.frame_allocator)
         };
-        unsafe { core::ptr::write_bytes((next + hho) as *mut u8, 0, 4096); }
-        let _ = next; // physical address; contiguity not strictly required for shm
-                       // because we map each page individually below.
+        if r.is_err() { return fb_virt; }
+    }
+    unsafe {
+        SERIAL_PORT.write_str("Framebuffer: ");
+        SERIAL_PORT.write_decimal(chunks as u32);
+        SERIAL_PORT.write_str(" x 2 MiB WC pages\n");
+    }
+    FB_WINDOW + offset
+}
+
+/// Map `len` bytes of device registers at physical `phys` (a PCI memory
+/// BAR) uncached at their HHDM address, and return that address.  Limine
+/// only direct-maps RAM, so MMIO has to be mapped before it is touched.
+/// Pages the HHDM already covers are left as they are.
+pub unsafe fn map_mmio(phys: u64, len: usize) -> Option<u64> {
+    let inner = unsafe { &mut *ALLOCATOR.inner.get() };
+    if !inner.initialized.load(Ordering::Relaxed) { return None; }
+    let ptm = inner.page_table_manager.as_mut()?;
+    let hho = ptm.higher_half_offset;
+    let flags = PageTableFlags(
+        PageTableFlags::PRESENT | PageTableFlags::WRITABLE
+        | PageTableFlags::WRITE_THROUGH | PageTableFlags::CACHE_DISABLE,
+    );
+    let first = phys & !0xFFF;
+    let pages = (phys + len as u64 - first).div_ceil(4096);
+    for i in 0..pages {
+        let page = first + i * 4096;
+        match unsafe { ptm.map(hho + page, page, flags, &mut inner.frame_allocator) } {
+            Ok(()) | Err("Page already mapped") | Err("Inside a 2 MiB page") => {}
+            Err(_) => return None,
+        }
     }
-    first
+    Some(hho + phys)
 }
 
 /// Map `num_pages` physical frames (starting at `phys_base`, each `4096` bytes apart
@@ -1029,7 +1057,8 @@ pub unsafe fn map_phys_pages_in(
     // We need to operate on `cr3_phys`'s page table, not the kernel's.
     // Build a temporary PageTableManager pointing at the target CR3.
     let ptm_tmp = PageTableManager { l4_table_phys: cr3_phys, higher_half_offset: hho };
-    let flags   = PageTableFlags::user_flags(writable, false);
+    let mut flags = PageTableFlags::user_flags(writable, false);
+    flags.0 |= PageTableFlags::PINNED; // the frames belong to the caller, never swap them
 
     for i in 0..num_pages {
         let virt = virt_base + i as u64 * 4096;
@@ -1094,6 +1123,7 @@ unsafe fn map_phys_leaf(
 
 /// Walk the *current* CR3's page tables to check if `virt` has a present leaf mapping.
 /// Used by syscall path to safely read user strings without faulting on unmapped pages.
+/// A swapped-out page is read back in and reported as mapped.
 pub unsafe fn is_page_mapped_current(virt: u64) -> bool {
     const HHO: u64 = 0xFFFF_8000_0000_0000;
     let cr3: u64;
@@ -1122,10 +1152,43 @@ pub unsafe fn is_page_mapped_current(virt: u64) -> bool {
 
         let l1 = ((l2e & 0x000F_FFFF_FFFF_F000) + HHO) as *const u64;
         let l1e = *l1.add(l1i);
+        if is_swap_entry(l1e) {
+            // Bring it back so the caller can read it directly.
+            return crate::kernel::swap::swap_in(l4_phys, virt);
+        }
         l1e & 1 != 0
     }
 }
 
+/// Translate a kernel virtual address (heap, stack, image or HHDM) to the
+/// physical address behind it in the current CR3, for building DMA
+/// descriptors.  Returns `None` if it is not mapped.
+pub unsafe fn kernel_virt_to_phys(virt: u64) -> Option<u64> {
+    const HHO: u64 = 0xFFFF_8000_0000_0000;
+    const ADDR: u64 = 0x000F_FFFF_FFFF_F000;
+    let cr3: u64;
+    unsafe { core::arch::asm!("mov {}, cr3", out(reg) cr3); }
+
+    let l4i = ((virt >> 39) & 0x1FF) as usize;
+    let l3i = ((virt >> 30) & 0x1FF) as usize;
+    let l2i = ((virt >> 21) & 0x1FF) as usize;
+    let l1i = ((virt >> 12) & 0x1FF) as usize;
+
+    unsafe {
+        let l4e = *(((cr3 & ADDR) + HHO) as *const u64).add(l4i);
+        if l4e & 1 == 0 { return None; }
+        let l3e = *(((l4e & ADDR) + HHO) as *const u64).add(l3i);
+        if l3e & 1 == 0 { return None; }
+        if l3e & (1 << 7) != 0 { return Some((l3e & 0x000F_FFFF_C000_0000) | (virt & 0x3FFF_FFFF)); }
+        let l2e = *(((l3e & ADDR) + HHO) as *const u64).add(l2i);
+        if l2e & 1 == 0 { return None; }
+        if l2e & (1 << 7) != 0 { return Some((l2e & 0x000F_FFFF_FFE0_0000) | (virt & 0x1F_FFFF)); }
+        let l1e = *(((l2e & ADDR) + HHO) as *const u64).add(l1i);
+        if l1e & 1 == 0 { return None; }
+        Some((l1e & ADDR) | (virt & 0xFFF))
+    }
+}
+
 /// Attempt to resolve a write fault on a copy-on-write page in the *current*
 /// CR3 (the faulting task's page table, already live).
 ///
@@ -1192,3 +1255,429 @@ pub unsafe fn try_resolve_cow_fault(fault_addr: u64) -> bool {
     }
     true
 }
+
+// ── mmap support helpers ─────────────────────────────────────────────────────
+
+/// Hardware-set "dirty" bit of a leaf PTE (written by the MMU on first store).
+const PTE_DIRTY: u64 = 1 << 6;
+
+/// Return a pointer (via the HHDM) to the leaf PTE for `virt` in `cr3_phys`,
+/// or `None` if any level of the walk is not present or is a huge page.
+unsafe fn leaf_pte_in(cr3_phys: u64, virt: u64) -> Option<*mut u64> {
+    let pte = unsafe { leaf_slot_in(cr3_phys, virt)? };
+    if unsafe { *pte } & 1 == 0 { return None; }
+    Some(pte)
+}
+
+/// Like `leaf_pte_in`, but returns the L1 slot whatever its contents
+/// (present, swapped out or empty) as long as the tables above it exist.
+unsafe fn leaf_slot_in(cr3_phys: u64, virt: u64) -> Option<*mut u64> {
+    const HHO: u64 = 0xFFFF_8000_0000_0000;
+    let l4i = ((virt >> 39) & 0x1FF) as usize;
+    let l3i = ((virt >> 30) & 0x1FF) as usize;
+    let l2i = ((virt >> 21) & 0x1FF) as usize;
+    let l1i = ((virt >> 12) & 0x1FF) as usize;
+
+    unsafe {
+        let l4  = ((cr3_phys & 0x000F_FFFF_FFFF_F000) + HHO) as *const u64;
+        let l4e = *l4.add(l4i);
+        if l4e & 1 == 0 { return None; }
+
+        let l3  = ((l4e & 0x000F_FFFF_FFFF_F000) + HHO) as *const u64;
+        let l3e = *l3.add(l3i);
+        if l3e & 1 == 0 || l3e & (1 << 7) != 0 { return None; }
+
+        let l2  = ((l3e & 0x000F_FFFF_FFFF_F000) + HHO) as *const u64;
+        let l2e = *l2.add(l2i);
+        if l2e & 1 == 0 || l2e & (1 << 7) != 0 { return None; }
+
+        let l1 = ((l2e & 0x000F_FFFF_FFFF_F000) + HHO) as *mut u64;
+        Some(l1.add(l1i))
+    }
+}
+
+/// Translate a user page in `cr3_phys` to its backing physical frame.
+/// Returns `None` if the page is not mapped.  A swapped-out page is read
+/// back in first, so the returned frame is always resident.
+pub unsafe fn user_page_phys_in(cr3_phys: u64, virt: u64) -> Option<u64> {
+    let slot = unsafe { leaf_slot_in(cr3_phys, virt & !0xFFF)? };
+    if is_swap_entry(unsafe { *slot })
+        && !unsafe { crate::kernel::swap::swap_in(cr3_phys, virt & !0xFFF) }
+    {
+        return None;
+    }
+    let pte = unsafe { leaf_pte_in(cr3_phys, virt & !0xFFF)? };
+    Some(unsafe { *pte } & 0x000F_FFFF_FFFF_F000)
+}
+
+/// True if every page of `[virt, virt + len)` is a present user page in
+/// `cr3_phys` (swapped-out pages are read back in).
+pub unsafe fn user_range_mapped_in(cr3_phys: u64, virt: u64, len: usize) -> bool {
+    if len == 0 { return true; }
+    let Some(end) = virt.checked_add(len as u64 - 1) else { return false };
+    let mut page = virt & !0xFFF;
+    while page <= end {
+        if unsafe { user_page_phys_in(cr3_phys, page) }.is_none() { return false; }
+        let Some(pte) = (unsafe { leaf_pte_in(cr3_phys, page) }) else { return false };
+        if unsafe { *pte } & PageTableFlags::USER == 0 { return false; }
+        page += 4096;
+    }
+    true
+}
+
+/// Make `[virt, virt + len)` safe for the kernel to write on a task's behalf
+/// (e.g. pushing a signal frame): every page must be a present, user-writable
+/// page.  Copy-on-write pages are broken first, exactly as a user write fault
+/// would, so the kernel never scribbles on a frame another process shares.
+pub unsafe fn prepare_user_write_in(cr3_phys: u64, virt: u64, len: usize) -> bool {
+    if !unsafe { user_range_mapped_in(cr3_phys, virt, len) } { return false; }
+    if len == 0 { return true; }
+    let end = virt + len as u64 - 1;
+    let mut page = virt & !0xFFF;
+    while page <= end {
+        // Swapping later pages in may have evicted this one again.
+        let Some(pte) = (unsafe { leaf_pte_in(cr3_phys, page) }) else { return false };
+        let flags = PageTableFlags(unsafe { *pte } & 0xFFF);
+        if flags.is_cow() {
+            // try_resolve_cow_fault works on the live CR3 and copies through
+            // the user address, so run it inside the task's address space.
+            let saved: u64;
+            let ok;
+            unsafe {
+                core::arch::asm!("mov {}, cr3", out(reg) saved, options(nostack, nomem));
+                core::arch::asm!("mov cr3, {}", in(reg) cr3_phys, options(nostack, nomem));
+                ok = try_resolve_cow_fault(page);
+                core::arch::asm!("mov cr3, {}", in(reg) saved, options(nostack, nomem));
+            }
+            if !ok { return false; }
+        } else if !flags.is_writable() {
+            return false;
+        }
+        page += 4096;
+    }
+    true
+}
+
+/// Frame backing `virt` in `cr3_phys` that a debugger may write through,
+/// even if the page is read-only (ptrace `POKETEXT` planting a breakpoint).
+/// A frame shared with another address space — copy-on-write, or read-only
+/// text shared since fork — is first replaced by a private copy so the
+/// write stays in this task.  Shared-memory and other pinned pages are
+/// written in place, as a store by the task itself would be.
+pub unsafe fn private_user_page_in(cr3_phys: u64, virt: u64) -> Option<u64> {
+    const HHO: u64 = 0xFFFF_8000_0000_0000;
+    let frame = unsafe { user_page_phys_in(cr3_phys, virt)? };
+    let pte   = unsafe { leaf_pte_in(cr3_phys, virt & !0xFFF)? };
+    let entry = unsafe { *pte };
+    if entry & PageTableFlags::USER == 0 { return None; }
+    if entry & PageTableFlags::PINNED != 0 { return Some(frame); }
+
+    let inner = unsafe { &mut *ALLOCATOR.inner.get() };
+    let fa    = &mut inner.frame_allocator;
+    // A COW page regains write access once it has a single owner.
+    let mut flags = entry & !0x000F_FFFF_FFFF_F000;
+    if flags & PageTableFlags::COW != 0 {
+        flags = (flags | PageTableFlags::WRITABLE) & !PageTableFlags::COW;
+    }
+    let target = if fa.get_refcount(frame) <= 1 {
+        frame
+    } else {
+        let copy = fa.allocate_frame()?;
+        unsafe {
+            core::ptr::copy_nonoverlapping(
+                (frame + HHO) as *const u8,
+                (copy + HHO) as *mut u8,
+                4096,
+            );
+        }
+        fa.free_frame(frame);
+        copy
+    };
+    unsafe {
+        *pte = target | flags;
+        let live: u64;
+        core::arch::asm!("mov {}, cr3", out(reg) live, options(nostack, nomem));
+        if live & 0x000F_FFFF_FFFF_F000 == cr3_phys & 0x000F_FFFF_FFFF_F000 {
+            core::arch::asm!("invlpg [{}]", in(reg) virt & !0xFFF);
+        }
+    }
+    Some(target)
+}
+
+/// Test and clear the hardware dirty bit of the page at `virt`.
+/// Returns `true` if the page had been written since the last call (or since
+/// it was mapped).  Used by `msync` to write back only modified pages.
+pub unsafe fn take_dirty_in(cr3_phys: u64, virt: u64) -> bool {
+    let Some(pte) = (unsafe { leaf_pte_in(cr3_phys, virt & !0xFFF) }) else { return false };
+    unsafe {
+        if *pte & PTE_DIRTY == 0 { return false; }
+        *pte &= !PTE_DIRTY;
+        core::arch::asm!("invlpg [{}]", in(reg) virt);
+    }
+    true
+}
+
+/// Rewrite the permission bits of `num_pages` already-mapped user pages.
+///
+/// - `accessible == false` implements `PROT_NONE`: the USER bit is cleared so
+///   any ring-3 access faults, while the frame stays owned by the page table.
+/// - `writable` pages whose frame is still shared (refcount > 1) after a
+///   fork are made COW rather than directly writable, unless `shared` is set
+///   (MAP_SHARED mappings must keep writing to the common frame).
+///
+/// Fails without modifying anything if any page in the range is unmapped,
+/// or part-way through if memory pressure evicts a page while the range is
+/// being swapped in.
+pub unsafe fn protect_user_region_in(
+    cr3_phys:   u64,
+    virt_addr:  u64,
+    num_pages:  usize,
+    accessible: bool,
+    writable:   bool,
+    executable: bool,
+    shared:     bool,
+) -> Result<(), &'static str> {
+    let inner = unsafe { &mut *ALLOCATOR.inner.get() };
+    if !inner.initialized.load(Ordering::Relaxed) {
+        return Err("Paging allocator not initialized");
+    }
+    for page in 0..num_pages {
+        let virt = virt_addr + (page * 4096) as u64;
+        if unsafe { user_page_phys_in(cr3_phys, virt) }.is_none() { return Err("Not mapped"); }
+    }
+
+    let fa = &inner.frame_allocator;
+    for page in 0..num_pages {
+        let virt  = virt_addr + (page * 4096) as u64;
+        let Some(pte) = (unsafe { leaf_pte_in(cr3_phys, virt) }) else { return Err("Not mapped") };
+        let old   = unsafe { *pte };
+        let frame = old & 0x000F_FFFF_FFFF_F000;
+
+        let mut flags = PageTableFlags::user_flags(writable, executable);
+        if !accessible { flags.0 &= !PageTableFlags::USER; }
+        if writable && !shared && fa.get_refcount(frame) > 1 {
+            flags = flags.as_cow();
+        }
+        // Keep the accessed/dirty bits so msync still sees earlier writes,
+        // and the pin so shared frames stay resident.
+        let keep = old & (PTE_DIRTY | (1 << 5) | PageTableFlags::PINNED);
+        unsafe {
+            *pte = frame | (flags.0 & 0xFFF) | keep;
+            core::arch::asm!("invlpg [{}]", in(reg) virt);
+        }
+    }
+    Ok(())
+}
+
+/// Move the leaf mappings of `num_pages` pages from `old_virt` to `new_virt`
+/// inside `cr3_phys` without copying any data (used by `mremap`).
+///
+/// The destination range must be unmapped.  Intermediate tables for the new
+/// range are allocated as needed.
+pub unsafe fn move_user_region_in(
+    cr3_phys:  u64,
+    old_virt:  u64,
+    new_virt:  u64,
+    num_pages: usize,
+) -> Result<(), &'static str> {
+    let inner = unsafe { &mut *ALLOCATOR.inner.get() };
+    if !inner.initialized.load(Ordering::Relaxed) {
+        return Err("Paging allocator not initialized");
+    }
+    let hho = inner.page_table_manager.as_ref().ok_or("PTM None")?.higher_half_offset;
+    let ptm_tmp = PageTableManager { l4_table_phys: cr3_phys, higher_half_offset: hho };
+
+    for page in 0..num_pages {
+        let src = old_virt + (page * 4096) as u64;
+        let dst = new_virt + (page * 4096) as u64;
+        let Some(pte) = (unsafe { leaf_slot_in(cr3_phys, src) }) else { continue };
+        let entry = unsafe { *pte };
+        if entry & 1 == 0 && !is_swap_entry(entry) { continue; }
+        unsafe {
+            // Build the intermediate tables, then copy the raw entry so
+            // swapped-out pages and the NX bit move along unchanged.
+            map_phys_leaf(&ptm_tmp, dst, entry & 0x000F_FFFF_FFFF_F000,
+                          PageTableFlags(entry & 0xFFF), &mut inner.frame_allocator)?;
+            if let Some(dst_pte) = leaf_slot_in(cr3_phys, dst) { *dst_pte = entry; }
+            *pte = 0;
+            core::arch::asm!("invlpg [{}]", in(reg) src);
+            core::arch::asm!("invlpg [{}]", in(reg) dst);
+        }
+    }
+    Ok(())
+}
+
+// ── swap support helpers ─────────────────────────────────────────────────────
+
+/// Hardware-set "accessed" bit of a leaf PTE (the clock algorithm's
+/// reference bit).
+const PTE_ACCESSED: u64 = 1 << 5;
+
+/// `true` if `entry` is a non-present leaf that refers to a swap slot.
+pub fn is_swap_entry(entry: u64) -> bool {
+    entry & PageTableFlags::PRESENT == 0 && entry & PageTableFlags::SWAPPED != 0
+}
+
+/// Swap slot index stored in a swap entry.
+pub fn swap_entry_slot(entry: u64) -> usize {
+    ((entry & 0x000F_FFFF_FFFF_F000) >> 12) as usize
+}
+
+/// Number of physical frames currently free.
+pub fn free_frame_count() -> usize {
+    let (alloc, total) = frame_stats();
+    total.saturating_sub(alloc)
+}
+
+/// Reference count of the frame at `phys`; 0 for memory the frame
+/// allocator does not own (framebuffer, MMIO and other physical mappings).
+pub fn frame_refcount(phys: u64) -> u16 {
+    let inner = unsafe { &*ALLOCATOR.inner.get() };
+    inner.frame_allocator.get_refcount(phys)
+}
+
+/// Allocate one physical frame for a page being swapped in (not zeroed).
+pub unsafe fn alloc_user_frame() -> Option<u64> {
+    let inner = unsafe { &mut *ALLOCATOR.inner.get() };
+    if !inner.initialized.load(Ordering::Relaxed) { return None; }
+    inner.frame_allocator.allocate_frame()
+}
+
+/// Set the "never swap out" marker on `num_pages` mapped pages.
+pub unsafe fn pin_user_region_in(cr3_phys: u64, virt_addr: u64, num_pages: usize) {
+    for page in 0..num_pages {
+        let virt = virt_addr + (page * 4096) as u64;
+        if let Some(pte) = unsafe { leaf_pte_in(cr3_phys, virt) } {
+            unsafe { *pte |= PageTableFlags::PINNED; }
+        }
+    }
+}
+
+/// Result of offering one page to `evict_page_in`.
+pub enum EvictResult {
+    /// Page was not a candidate (not present, pinned, shared or kernel-only).
+    Skipped,
+    /// Page was recently used; its accessed bit has been cleared.
+    Referenced,
+    /// Page was written to its swap slot and its frame freed.
+    Evicted,
+    /// The swap area is full or the write failed.
+    Failed,
+}
+
+/// Clock-algorithm step for the leaf at `pte` (belonging to `virt` in
+/// `cr3_phys`).  Only private, unpinned user pages with a single owner are
+/// eligible; `write_slot` stores the frame contents and returns the slot.
+pub unsafe fn evict_page_in(
+    cr3_phys:   u64,
+    virt:       u64,
+    pte:        *mut u64,
+    write_slot: impl FnOnce(u64) -> Option<usize>,
+) -> EvictResult {
+    let inner = unsafe { &mut *ALLOCATOR.inner.get() };
+    let entry = unsafe { *pte };
+    if entry & PageTableFlags::PRESENT == 0
+        || entry & PageTableFlags::USER == 0
+        || entry & PageTableFlags::PINNED != 0
+    {
+        return EvictResult::Skipped;
+    }
+    let frame = entry & 0x000F_FFFF_FFFF_F000;
+    if inner.frame_allocator.get_refcount(frame) != 1 { return EvictResult::Skipped; }
+
+    let current_cr3: u64;
+    unsafe { core::arch::asm!("mov {}, cr3", out(reg) current_cr3); }
+    let live = current_cr3 & 0x000F_FFFF_FFFF_F000 == cr3_phys;
+
+    if entry & PTE_ACCESSED != 0 {
+        unsafe {
+            *pte = entry & !PTE_ACCESSED;
+            if live { core::arch::asm!("invlpg [{}]", in(reg) virt); }
+        }
+        return EvictResult::Referenced;
+    }
+
+    let hho = get_hhdm_offset();
+    let Some(slot) = write_slot(frame + hho) else { return EvictResult::Failed };
+
+    let keep = entry & (0xFFF & !(PageTableFlags::PRESENT | PTE_ACCESSED | PTE_DIRTY))
+             | entry & PageTableFlags::NO_EXECUTE;
+    unsafe {
+        *pte = ((slot as u64) << 12) | keep | PageTableFlags::SWAPPED;
+        if live { core::arch::asm!("invlpg [{}]", in(reg) virt); }
+    }
+    inner.frame_allocator.free_frame(frame);
+    EvictResult::Evicted
+}
+
+/// Replace the swap entry for `virt` in `cr3_phys` with a resident page.
+/// `read_slot` fills the new frame (given by its HHDM address) from the
+/// slot.  The page comes back marked accessed, so the next clock pass does
+/// not evict it before the caller has used it.  Returns the swap entry that
+/// was replaced, or `None` if the page is not swapped out, no frame is
+/// available or the read failed.
+pub unsafe fn swap_in_page_in(
+    cr3_phys:  u64,
+    virt:      u64,
+    read_slot: impl FnOnce(usize, u64) -> bool,
+) -> Option<u64> {
+    let pte   = unsafe { leaf_slot_in(cr3_phys, virt & !0xFFF)? };
+    let entry = unsafe { *pte };
+    if !is_swap_entry(entry) { return None; }
+
+    let frame = unsafe { alloc_user_frame()? };
+    if !read_slot(swap_entry_slot(entry), frame + get_hhdm_offset()) {
+        let inner = unsafe { &mut *ALLOCATOR.inner.get() };
+        inner.frame_allocator.free_frame(frame);
+        return None;
+    }
+    let flags = entry & (0xFFF & !PageTableFlags::SWAPPED) | entry & PageTableFlags::NO_EXECUTE;
+    unsafe {
+        *pte = frame | flags | PageTableFlags::PRESENT | PTE_ACCESSED;
+        core::arch::asm!("invlpg [{}]", in(reg) virt & !0xFFF);
+    }
+    Some(entry)
+}
+
+/// Visit every leaf slot of the user half of `cr3_phys` at or above
+/// `start`, in address order.  `f(virt, pte)` returns `false` to stop; the
+/// address of the leaf where the walk stopped is returned, or `None` if the
+/// end of user space was reached.
+pub unsafe fn scan_user_leaves_in(
+    cr3_phys: u64,
+    start:    u64,
+    mut f:    impl FnMut(u64, *mut u64) -> bool,
+) -> Option<u64> {
+    const HHO: u64 = 0xFFFF_8000_0000_0000;
+    const ADDR: u64 = 0x000F_FFFF_FFFF_F000;
+    let s4 = ((start >> 39) & 0x1FF) as usize;
+    let l4 = ((cr3_phys & ADDR) + HHO) as *const u64;
+
+    for l4i in s4..256usize {
+        let l4e = unsafe { *l4.add(l4i) };
+        if l4e & 1 == 0 { continue; }
+        let first4 = l4i == s4;
+        let s3 = if first4 { ((start >> 30) & 0x1FF) as usize } else { 0 };
+        let l3 = ((l4e & ADDR) + HHO) as *const u64;
+        for l3i in s3..512usize {
+            let l3e = unsafe { *l3.add(l3i) };
+            if l3e & 1 == 0 || l3e & (1 << 7) != 0 { continue; }
+            let first3 = first4 && l3i == s3;
+            let s2 = if first3 { ((start >> 21) & 0x1FF) as usize } else { 0 };
+            let l2 = ((l3e & ADDR) + HHO) as *const u64;
+            for l2i in s2..512usize {
+                let l2e = unsafe { *l2.add(l2i) };
+                if l2e & 1 == 0 || l2e & (1 << 7) != 0 { continue; }
+                let first2 = first3 && l2i == s2;
+                let s1 = if first2 { ((start >> 12) & 0x1FF) as usize } else { 0 };
+                let l1 = ((l2e & ADDR) + HHO) as *mut u64;
+                for l1i in s1..512usize {
+                    let virt = ((l4i as u64) << 39) | ((l3i as u64) << 30)
+                             | ((l2i as u64) << 21) | ((l1i as u64) << 12);
+                    if !f(virt, unsafe { l1.add(l1i) }) { return Some(virt); }
+                }
+            }
+        }
+    }
+    None
+}
@@ -0,0 +1,519 @@
+// src/kernel/mem/slab.rs
+//! Slab caches for small kernel objects.
+//!
+//! # Design
+//!
+//! Every cache hands out fixed-size objects carved from 32 KiB slabs
+//! (order-3 buddy blocks, used through the HHDM).  Because buddy blocks are
+//! naturally aligned, the slab header of any object is found by masking its
+//! address — `kfree` needs neither the layout nor a lookup table.
+//!
+//! | Cache                 | Object size  | Used by                               |
+//! |-----------------------|--------------|---------------------------------------|
+//! | `kmalloc-16` .. `-4096` | power of two | every `Box`/`Vec`/`String` ≤ 4 KiB    |
+//! | `sock_buf`            | 4096         | TCP/UDP socket payload buffers        |
+//! | `ipc_msg`             | `ipc::Message` | messages waiting in an IPC queue    |
+//! | `ramfs_inode`         | `ramfs::INode` | RamFS files and directories         |
+//!
+//! Larger allocations (and alignments the slabs cannot honour) still go to
+//! the linked-list heap in `paging_allocator`.  Tasks and the fd tables
+//! live in the scheduler's static table and never touch the heap.
+//!
+//! Each slab sits on its cache's `partial` list while it has free objects and
+//! moves to `full` when it has none.  One empty slab per cache is kept for
+//! reuse; further empty slabs go back to the buddy allocator.
+//!
+//! # Debug mode (`--features slab_debug`)
+//!
+//! Each object gets a 16-byte header (state, requested size, allocation
+//! sequence number) and an 8-byte red zone after its size class; the slack
+//! between the requested size and the red zone is red-zoned too.  Freed
+//! objects are poisoned with `0x6b`.  Corruption is reported over serial:
+//! red-zone overruns and invalid or double frees on `kfree`, writes to freed
+//! memory on the next `kmalloc`, and a full sweep on every read of
+//! `/proc/slabinfo`.
+//!
+//! # Leak accounting
+//!
+//! Every build counts allocations, frees and live objects per cache; they
+//! appear in `/proc/slabinfo`, and `report_leaks` (run at power-off and
+//! reboot) prints the caches with objects still live.  Debug mode adds the
+//! address, size and sequence number of each outstanding object.
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::alloc::Layout;
+use core::ptr;
+use crate::kernel::serial::SERIAL_PORT;
+
+const SLAB_PAGES: usize = 8;
+const SLAB_BYTES: usize = SLAB_PAGES * 4096;
+/// Bytes reserved for the `SlabHeader` at the start of every slab.
+const HEADER_BYTES: usize = 64;
+const SLAB_MAGIC: u64 = 0x534C_4142_4F58_4944; // "SLABOXID"
+
+const HHDM: usize = 0xFFFF_8000_0000_0000;
+/// Start of the linked-list heap; everything in `[HHDM, HEAP_START)` that
+/// reaches `kfree` is a slab object.
+const HEAP_START: usize = 0xFFFF_FF00_0000_0000;
+
+const DEBUG: bool = cfg!(feature = "slab_debug");
+/// Per-object metadata in front of the object (debug mode only).
+const META_BYTES: usize = if DEBUG { 16 } else { 0 };
+/// Red zone after the size class (debug mode only).
+const REDZONE_BYTES: usize = if DEBUG { 8 } else { 0 };
+/// Offset of the free-list link inside a free slot.
+const LINK_OFFSET: usize = if DEBUG { 8 } else { 0 };
+/// Strongest alignment every object of a cache is guaranteed.
+const MAX_ALIGN: usize = if DEBUG { 16 } else { HEADER_BYTES };
+
+const STATE_ACTIVE: u32 = 0xCCCC_CCCC;
+const STATE_FREE:   u32 = 0xBBBB_BBBB;
+const RED_BYTE:     u8  = 0xCC;
+const POISON_FREE:  u8  = 0x6B;
+const POISON_END:   u8  = 0xA5;
+
+// ── Caches ────────────────────────────────────────────────────────────────────
+
+#[repr(C)]
+struct SlabHeader {
+    magic: u64,
+    cache: *mut KmemCache,
+    next:  *mut SlabHeader,
+    prev:  *mut SlabHeader,
+    /// First free slot, or null.
+    free:  *mut u8,
+    inuse: usize,
+}
+
+pub struct KmemCache {
+    name:        &'static str,
+    obj_size:    usize,
+    /// Slot size including debug metadata and red zone.
+    stride:      usize,
+    per_slab:    usize,
+    partial:     *mut SlabHeader,
+    full:        *mut SlabHeader,
+    empty_slabs: usize,
+    num_slabs:   usize,
+    active_objs: usize,
+    allocs:      u64,
+    frees:       u64,
+}
+
+impl KmemCache {
+    const fn new(name: &'static str, obj_size: usize) -> Self {
+        let raw    = META_BYTES + obj_size + REDZONE_BYTES;
+        let stride = (raw + 15) & !15;
+        Self {
+            name, obj_size, stride,
+            per_slab:    (SLAB_BYTES - HEADER_BYTES) / stride,
+            partial:     ptr::null_mut(),
+            full:        ptr::null_mut(),
+            empty_slabs: 0,
+            num_slabs:   0,
+            active_objs: 0,
+            allocs:      0,
+            frees:       0,
+        }
+    }
+}
+
+/// Handle to one of the named caches.
+#[derive(Clone, Copy)]
+pub struct CacheId(usize);
+
+/// Socket payload buffers (4 KiB).
+pub const SOCK_BUF:    CacheId = CacheId(NR_KMALLOC);
+/// Queued IPC messages.
+pub const IPC_MSG:     CacheId = CacheId(NR_KMALLOC + 1);
+/// RamFS inodes.
+pub const RAMFS_INODE: CacheId = CacheId(NR_KMALLOC + 2);
+
+const NR_KMALLOC: usize = 9;
+const NR_CACHES:  usize = NR_KMALLOC + 3;
+
+static mut CACHES: [KmemCache; NR_CACHES] = [
+    KmemCache::new("kmalloc-16",   16),
+    KmemCache::new("kmalloc-32",   32),
+    KmemCache::new("kmalloc-64",   64),
+    KmemCache::new("kmalloc-128",  128),
+    KmemCache::new("kmalloc-256",  256),
+    KmemCache::new("kmalloc-512",  512),
+    KmemCache::new("kmalloc-1024", 1024),
+    KmemCache::new("kmalloc-2048", 2048),
+    KmemCache::new("kmalloc-4096", 4096),
+    KmemCache::new("sock_buf",     4096),
+    KmemCache::new("ipc_msg",      size_of::<crate::kernel::ipc::Message>()),
+    KmemCache::new("ramfs_inode",  size_of::<crate::kernel::fs::ramfs::INode>()),
+];
+
+/// Running allocation counter, stored in each object's debug header.
+static mut ALLOC_SEQ: u32 = 0;
+
+fn cache(idx: usize) -> *mut KmemCache {
+    unsafe { (&raw mut CACHES).cast::<KmemCache>().add(idx) }
+}
+
+// ── Slab management ───────────────────────────────────────────────────────────
+
+unsafe fn list_push(head: *mut *mut SlabHeader, s: *mut SlabHeader) {
+    unsafe {
+        (*s).prev = ptr::null_mut();
+        (*s).next = *head;
+        if !(*head).is_null() { (**head).prev = s; }
+        *head = s;
+    }
+}
+
+unsafe fn list_remove(head: *mut *mut SlabHeader, s: *mut SlabHeader) {
+    unsafe {
+        if (*s).prev.is_null() { *head = (*s).next; } else { (*(*s).prev).next = (*s).next; }
+        if !(*s).next.is_null() { (*(*s).next).prev = (*s).prev; }
+        (*s).next = ptr::null_mut();
+        (*s).prev = ptr::null_mut();
+    }
+}
+
+#[inline]
+unsafe fn link_of(slot: *mut u8) -> *mut *mut u8 {
+    unsafe { slot.add(LINK_OFFSET) as *mut *mut u8 }
+}
+
+/// Get a fresh slab from the buddy allocator and thread its free list.
+unsafe fn grow(c: *mut KmemCache) -> Option<*mut SlabHeader> {
+    let phys = unsafe { crate::kernel::paging_allocator::alloc_phys_frames(SLAB_PAGES) };
+    if phys == 0 { return None; }
+    let base = (phys as usize + HHDM) as *mut u8;
+    let s    = base as *mut SlabHeader;
+
+    unsafe {
+        let stride = (*c).stride;
+        let mut next: *mut u8 = ptr::null_mut();
+        for i in (0..(*c).per_slab).rev() {
+            let slot = base.add(HEADER_BYTES + i * stride);
+            if DEBUG { init_free_slot(&*c, slot); }
+            *link_of(slot) = next;
+            next = slot;
+        }
+        s.write(SlabHeader {
+            magic: SLAB_MAGIC, cache: c,
+            next: ptr::null_mut(), prev: ptr::null_mut(),
+            free: next, inuse: 0,
+        });
+        list_push(&raw mut (*c).partial, s);
+        (*c).num_slabs   += 1;
+        (*c).empty_slabs += 1;
+    }
+    Some(s)
+}
+
+unsafe fn alloc_from(c: *mut KmemCache, size: usize) -> *mut u8 {
+    unsafe {
+        let s = if (*c).partial.is_null() {
+            match grow(c) {
+                Some(s) => s,
+                None    => return ptr::null_mut(),
+            }
+        } else {
+            (*c).partial
+        };
+
+        let slot = (*s).free;
+        (*s).free = *link_of(slot);
+        if (*s).inuse == 0 { (*c).empty_slabs -= 1; }
+        (*s).inuse += 1;
+        if (*s).free.is_null() {
+            list_remove(&raw mut (*c).partial, s);
+            list_push(&raw mut (*c).full, s);
+        }
+        (*c).active_objs += 1;
+        (*c).allocs      += 1;
+
+        if DEBUG { debug_on_alloc(&*c, slot, size); }
+        slot.add(META_BYTES)
+    }
+}
+
+unsafe fn free_to(c: *mut KmemCache, s: *mut SlabHeader, slot: *mut u8) {
+    unsafe {
+        let was_full = (*s).free.is_null();
+        *link_of(slot) = (*s).free;
+        (*s).free = slot;
+        (*s).inuse -= 1;
+        (*c).active_objs -= 1;
+        (*c).frees       += 1;
+
+        if was_full {
+            list_remove(&raw mut (*c).full, s);
+            list_push(&raw mut (*c).partial, s);
+        }
+        if (*s).inuse == 0 {
+            if (*c).empty_slabs >= 1 {
+                // Keep one empty slab per cache; give the rest back.
+                list_remove(&raw mut (*c).partial, s);
+                (*c).num_slabs -= 1;
+                (*s).magic = 0;
+                let phys = (s as usize - HHDM) as u64;
+                crate::kernel::paging_allocator::free_phys_frames(phys, SLAB_PAGES);
+            } else {
+                (*c).empty_slabs += 1;
+            }
+        }
+    }
+}
+
+// ── Public interface ──────────────────────────────────────────────────────────
+
+/// Allocate from the smallest `kmalloc-*` cache that fits `layout`.
+/// Returns `None` if the request is too large or too strictly aligned for a
+/// slab (the caller falls back to the page heap), or null on OOM.
+pub unsafe fn kmalloc(layout: Layout) -> Option<*mut u8> {
+    let need = layout.size().max(layout.align()).max(1);
+    let idx = (0..NR_KMALLOC).find(|&i| unsafe { (*cache(i)).obj_size } >= need)?;
+    if layout.align() > MAX_ALIGN.min(unsafe { (*cache(idx)).obj_size }) { return None; }
+    Some(unsafe { alloc_from(cache(idx), layout.size()) })
+}
+
+/// `true` if `p` was handed out by a slab cache.
+pub fn owns(p: *mut u8) -> bool {
+    let a = p as usize;
+    a >= HHDM && a < HEAP_START
+}
+
+/// Return an object to the cache it came from.
+pub unsafe fn kfree(p: *mut u8) {
+    let a = p as usize;
+    let s = (a & !(SLAB_BYTES - 1)) as *mut SlabHeader;
+    unsafe {
+        if (*s).magic != SLAB_MAGIC {
+            report("?", "free of non-slab pointer", a, 0);
+            return;
+        }
+        let c = (*s).cache;
+        let first = s as usize + HEADER_BYTES + META_BYTES;
+        if a < first || (a - first) % (*c).stride != 0 {
+            report((*c).name, "invalid free", a, 0);
+            return;
+        }
+        let slot = p.sub(META_BYTES);
+        if DEBUG && !debug_on_free(&*c, slot) { return; }
+        free_to(c, s, slot);
+    }
+}
+
+/// Allocate one object from a named cache (uninitialised).  Free it with
+/// `kfree`, or let a `Box`/`Vec` built on it drop normally.
+pub unsafe fn cache_alloc(id: CacheId) -> *mut u8 {
+    let c = cache(id.0);
+    unsafe { alloc_from(c, (*c).obj_size) }
+}
+
+/// Move `value` into one object of cache `id`; dropping the box frees it
+/// back to the cache.  Falls back to the general heap if the cache cannot
+/// grow.
+pub fn boxed<T>(id: CacheId, value: T) -> Box<T> {
+    let c = cache(id.0);
+    debug_assert!(size_of::<T>() <= unsafe { (*c).obj_size } && align_of::<T>() <= 16);
+    let p = unsafe { cache_alloc(id) } as *mut T;
+    if p.is_null() { return Box::new(value); }
+    unsafe {
+        p.write(value);
+        Box::from_raw(p)
+    }
+}
+
+/// A zero-filled `Vec<u8>` whose storage is one object of cache `id`.
+/// Falls back to the general heap if the cache cannot grow.
+pub fn zeroed_vec(id: CacheId) -> Vec<u8> {
+    let size = unsafe { (*cache(id.0)).obj_size };
+    let p = unsafe { cache_alloc(id) };
+    if p.is_null() { return alloc::vec![0u8; size]; }
+    unsafe {
+        ptr::write_bytes(p, 0, size);
+        Vec::from_raw_parts(p, size, size)
+    }
+}
+
+/// One `/proc/slabinfo` row per cache:
+/// `(name, active_objs, num_objs, objsize, objperslab, num_slabs, allocs, frees)`.
+pub fn stats() -> Vec<(&'static str, usize, usize, usize, usize, usize, u64, u64)> {
+    (0..NR_CACHES).map(|i| unsafe {
+        let c = cache(i);
+        ((*c).name, (*c).active_objs, (*c).num_slabs * (*c).per_slab, (*c).obj_size,
+         (*c).per_slab, (*c).num_slabs, (*c).allocs, (*c).frees)
+    }).collect()
+}
+
+/// Pages per slab, for `/proc/slabinfo`.
+pub const PAGES_PER_SLAB: usize = SLAB_PAGES;
+
+// ── Debug mode ────────────────────────────────────────────────────────────────
+
+fn report(cache: &str, what: &str, addr: usize, detail: usize) {
+    unsafe {
+        SERIAL_PORT.write_str("slab: ");
+        SERIAL_PORT.write_str(cache);
+        SERIAL_PORT.write_str(": ");
+        SERIAL_PORT.write_str(what);
+        SERIAL_PORT.write_str(" at 0x");
+        SERIAL_PORT.write_hex((addr >> 32) as u32);
+        SERIAL_PORT.write_hex(addr as u32);
+        if detail != 0 {
+            SERIAL_PORT.write_str(" (+");
+            SERIAL_PORT.write_decimal(detail as u32);
+            SERIAL_PORT.write_str(")");
+        }
+        SERIAL_PORT.write_str("\n");
+    }
+}
+
+#[inline]
+unsafe fn state_of(slot: *mut u8) -> *mut u32 { slot as *mut u32 }
+#[inline]
+unsafe fn size_of_obj(slot: *mut u8) -> *mut u32 { unsafe { slot.add(4) as *mut u32 } }
+#[inline]
+unsafe fn seq_of(slot: *mut u8) -> *mut u64 { unsafe { slot.add(8) as *mut u64 } }
+
+unsafe fn init_free_slot(c: &KmemCache, slot: *mut u8) {
+    unsafe {
+        *state_of(slot) = STATE_FREE;
+        *size_of_obj(slot) = 0;
+        let obj = slot.add(META_BYTES);
+        ptr::write_bytes(obj, POISON_FREE, c.obj_size - 1);
+        *obj.add(c.obj_size - 1) = POISON_END;
+        ptr::write_bytes(obj.add(c.obj_size), RED_BYTE, REDZONE_BYTES);
+    }
+}
+
+unsafe fn debug_on_alloc(c: &KmemCache, slot: *mut u8, size: usize) {
+    unsafe {
+        let obj = slot.add(META_BYTES);
+        if *state_of(slot) != STATE_FREE {
+            report(c.name, "free object header corrupted", obj as usize, 0);
+        } else if let Some(off) = (0..c.obj_size).find(|&i| {
+            *obj.add(i) != if i == c.obj_size - 1 { POISON_END } else { POISON_FREE }
+        }) {
+            report(c.name, "write after free", obj as usize, off);
+        }
+        ALLOC_SEQ = ALLOC_SEQ.wrapping_add(1);
+        *state_of(slot) = STATE_ACTIVE;
+        *size_of_obj(slot) = size as u32;
+        *seq_of(slot) = ALLOC_SEQ as u64;
+        ptr::write_bytes(obj.add(size), RED_BYTE, c.obj_size - size + REDZONE_BYTES);
+    }
+}
+
+/// Check a live object's header and red zone; reports and returns `false`
+/// on corruption.
+unsafe fn check_active(c: &KmemCache, slot: *mut u8) -> bool {
+    unsafe {
+        let obj  = slot.add(META_BYTES);
+        let size = *size_of_obj(slot) as usize;
+        if size > c.obj_size {
+            report(c.name, "object header overwritten", obj as usize, 0);
+            return false;
+        }
+        if let Some(off) = (size..c.obj_size + REDZONE_BYTES).find(|&i| *obj.add(i) != RED_BYTE) {
+            report(c.name, "red zone overwritten", obj as usize, off);
+            return false;
+        }
+        true
+    }
+}
+
+/// Returns `false` if the object must not be put back on the free list.
+unsafe fn debug_on_free(c: &KmemCache, slot: *mut u8) -> bool {
+    unsafe {
+        let obj = slot.add(META_BYTES);
+        match *state_of(slot) {
+            STATE_ACTIVE => {}
+            STATE_FREE   => { report(c.name, "double free", obj as usize, 0); return false; }
+            _            => { report(c.name, "object header overwritten", obj as usize, 0); return false; }
+        }
+        // Report overruns but still recycle the object.
+        check_active(c, slot);
+        init_free_slot(c, slot);
+        true
+    }
+}
+
+/// Visit every slot of every slab of cache `c`.
+unsafe fn for_each_slot(c: *mut KmemCache, mut f: impl FnMut(*mut u8)) {
+    unsafe {
+        for head in [(*c).partial, (*c).full] {
+            let mut s = head;
+            while !s.is_null() {
+                for i in 0..(*c).per_slab {
+                    f((s as *mut u8).add(HEADER_BYTES + i * (*c).stride));
+                }
+                s = (*s).next;
+            }
+        }
+    }
+}
+
+/// Debug mode: verify the red zones of every live object and the poison of
+/// every free one.  Returns the number of corrupted objects.
+pub fn check_all() -> usize {
+    if !DEBUG { return 0; }
+    let mut bad = 0;
+    for i in 0..NR_CACHES {
+        let c = cache(i);
+        unsafe {
+            for_each_slot(c, |slot| {
+                let ok = match *state_of(slot) {
+                    STATE_ACTIVE => check_active(&*c, slot),
+                    STATE_FREE   => {
+                        let obj = slot.add(META_BYTES);
+                        let n = (*c).obj_size;
+                        match (0..n - 1).find(|&j| *obj.add(j) != POISON_FREE) {
+                            Some(off) => { report((*c).name, "write after free", obj as usize, off); false }
+                            None      => true,
+                        }
+                    }
+                    _ => { report((*c).name, "object header overwritten", slot as usize, 0); false }
+                };
+                if !ok { bad += 1; }
+            });
+        }
+    }
+    bad
+}
+
+/// List the objects still allocated over serial: every cache's live count
+/// (from the counters kept in all builds) and, in debug mode, each object's
+/// address, requested size and allocation sequence number.
+pub fn report_leaks() {
+    unsafe { SERIAL_PORT.write_str("slab: outstanding allocations\n"); }
+    for i in 0..NR_CACHES {
+        let c = cache(i);
+        unsafe {
+            if (*c).active_objs == 0 { continue; }
+            SERIAL_PORT.write_str("  ");
+            SERIAL_PORT.write_str((*c).name);
+            SERIAL_PORT.write_str(": ");
+            SERIAL_PORT.write_decimal((*c).active_objs as u32);
+            SERIAL_PORT.write_str(" live of ");
+            SERIAL_PORT.write_decimal((*c).allocs as u32);
+            SERIAL_PORT.write_str(" allocated\n");
+            if !DEBUG { continue; }
+            let mut shown = 0;
+            for_each_slot(c, |slot| {
+                if *state_of(slot) != STATE_ACTIVE || shown >= 16 { return; }
+                shown += 1;
+                let obj = slot.add(META_BYTES) as usize;
+                SERIAL_PORT.write_str("    0x");
+                SERIAL_PORT.write_hex((obj >> 32) as u32);
+                SERIAL_PORT.write_hex(obj as u32);
+                SERIAL_PORT.write_str(" size ");
+                SERIAL_PORT.write_decimal(*size_of_obj(slot));
+                SERIAL_PORT.write_str(" seq ");
+                SERIAL_PORT.write_decimal(*seq_of(slot) as u32);
+                SERIAL_PORT.write_str("\n");
+            });
+        }
+    }
+}
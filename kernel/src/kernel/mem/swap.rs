@@ -0,0 +1,449 @@
+// src/kernel/mem/swap.rs
+//! Swap space and page reclaim for OxideOS.
+//!
+//! # Design
+//!
+//! One swap area can be active at a time.  It is either an ATA partition
+//! (`/dev/hda2`, MBR type 0x82, or any partition/disk carrying a Linux
+//! `mkswap` header) or a swap file on the FAT16 or ext2 volume.  Slot 0 is
+//! the header page and is never handed out, so areas made by `mkswap` on a
+//! Linux host work unchanged.
+//!
+//! Reclaim is a clock (second-chance) algorithm over the user half of every
+//! live task's page table.  The hand remembers the task slot and virtual
+//! address where the last pass stopped.  A page whose accessed bit is set
+//! only loses that bit; an unreferenced private page with a single owner is
+//! written to a free slot, its PTE becomes a swap entry (present bit clear,
+//! software bit 11 set, slot index in the address field, original flags kept
+//! in the low bits) and its frame is freed.  Shared-memory, MAP_SHARED and
+//! COW-shared frames are never swapped.
+//!
+//! A fault on a swap entry — from user mode, or from the kernel touching a
+//! user buffer during a syscall — reads the page back into a fresh frame.
+//! `fork()` shares swap slots between parent and child via a per-slot
+//! reference count; each side gets a private copy when it faults the page in.
+//!
+//! Reclaim runs when an allocation path finds fewer than `LOW_WATERMARK`
+//! free frames, and from the scheduler tick as a background balancer.
+
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec;
+use crate::kernel::paging_allocator::{self as pa, EvictResult};
+use crate::kernel::scheduler::{SCHED, MAX_TASKS};
+use crate::kernel::serial::SERIAL_PORT;
+
+/// Largest supported swap area, in pages (512 MiB).
+pub const MAX_SWAP_SLOTS: usize = 131072;
+/// Reclaim starts when fewer than this many frames are free (1 MiB).
+pub const LOW_WATERMARK: usize = 256;
+/// Minimum number of pages one reclaim pass tries to free.
+const RECLAIM_BATCH: usize = 64;
+
+const PAGE_SIZE: usize = 4096;
+const SECTORS_PER_PAGE: u32 = (PAGE_SIZE / 512) as u32;
+/// Linux swap header: signature at the end of page 0, `last_page` at 1028.
+const SWAP_MAGIC: &[u8; 10] = b"SWAPSPACE2";
+const SWAP_MAGIC_OFFSET: usize = PAGE_SIZE - 10;
+const SWAP_LAST_PAGE_OFFSET: usize = 1028;
+
+// ── Swap area state ───────────────────────────────────────────────────────────
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SwapDevice {
+    None,
+    /// Raw sectors on an ATA disk starting at `start_lba`.
+    Ata { disk: usize, start_lba: u32 },
+    /// A swap file, accessed through a kernel-owned raw FD.
+    Fat16(i32),
+    Ext2(i32),
+}
+
+struct SwapArea {
+    device:    SwapDevice,
+    /// Size of the area in pages, including the header slot.
+    pages:     usize,
+    /// Slots currently holding at least one page.
+    used:      usize,
+    /// Next slot to try when allocating.
+    next:      usize,
+    /// Number of swap entries referring to each slot (0 = free).
+    refcount:  [u8; MAX_SWAP_SLOTS],
+    /// Path given to `swapon`, for `swapoff` and `/proc/swaps`.
+    path:      [u8; 64],
+    path_len:  usize,
+    is_file:   bool,
+    /// Clock hand: task slot and next user address to examine.
+    hand_task: usize,
+    hand_virt: u64,
+    /// Set while a reclaim pass runs, so nested allocations do not recurse.
+    reclaiming: bool,
+}
+
+static mut SWAP: SwapArea = SwapArea {
+    device:    SwapDevice::None,
+    pages:     0,
+    used:      0,
+    next:      1,
+    refcount:  [0; MAX_SWAP_SLOTS],
+    path:      [0; 64],
+    path_len:  0,
+    is_file:   false,
+    hand_task: 0,
+    hand_virt: 0,
+    reclaiming: false,
+};
+
+/// `(total_pages, used_pages)` of the active swap area (header excluded).
+pub fn stats() -> (usize, usize) {
+    let area = &raw const SWAP;
+    unsafe {
+        if (*area).device == SwapDevice::None { return (0, 0); }
+        ((*area).pages - 1, (*area).used)
+    }
+}
+
+/// Path of the active swap area and whether it is a file (for `/proc/swaps`).
+pub fn active() -> Option<(String, bool)> {
+    let area = &raw const SWAP;
+    unsafe {
+        if (*area).device == SwapDevice::None { return None; }
+        let path = core::str::from_utf8(&(&(*area).path)[..(*area).path_len]).unwrap_or("?");
+        Some((String::from(path), (*area).is_file))
+    }
+}
+
+// ── Device I/O ────────────────────────────────────────────────────────────────
+
+unsafe fn write_page(device: SwapDevice, slot: usize, src: &[u8]) -> bool {
+    match device {
+        SwapDevice::None => false,
+        SwapDevice::Ata { disk, start_lba } => unsafe {
+            crate::kernel::ata::write_sectors(
+                disk, start_lba + slot as u32 * SECTORS_PER_PAGE, SECTORS_PER_PAGE, src)
+        },
+        SwapDevice::Fat16(fd) => unsafe {
+            crate::kernel::fat::pwrite(fd, (slot * PAGE_SIZE) as u32, src) == PAGE_SIZE as i64
+        },
+        SwapDevice::Ext2(fd) => unsafe {
+            crate::kernel::ext2::pwrite(fd, (slot * PAGE_SIZE) as u32, src) == PAGE_SIZE as i64
+        },
+    }
+}
+
+unsafe fn read_page(device: SwapDevice, slot: usize, dst: &mut [u8]) -> bool {
+    match device {
+        SwapDevice::None => false,
+        SwapDevice::Ata { disk, start_lba } => unsafe {
+            crate::kernel::ata::read_sectors(
+                disk, start_lba + slot as u32 * SECTORS_PER_PAGE, SECTORS_PER_PAGE, dst)
+        },
+        SwapDevice::Fat16(fd) => unsafe {
+            crate::kernel::fat::pread(fd, (slot * PAGE_SIZE) as u32, dst) == PAGE_SIZE as i64
+        },
+        SwapDevice::Ext2(fd) => unsafe {
+            crate::kernel::ext2::pread(fd, (slot * PAGE_SIZE) as u32, dst) == PAGE_SIZE as i64
+        },
+    }
+}
+
+unsafe fn close_device(device: SwapDevice) {
+    match device {
+        SwapDevice::Fat16(fd) => { let _ = unsafe { crate::kernel::fat::close(fd) }; }
+        SwapDevice::Ext2(fd)  => { let _ = unsafe { crate::kernel::ext2::close(fd) }; }
+        _ => {}
+    }
+}
+
+// ── Slot allocation ───────────────────────────────────────────────────────────
+
+/// Store the page at HHDM address `src` in a free slot; returns the slot.
+unsafe fn store_page(src: u64) -> Option<usize> {
+    let area = &raw mut SWAP;
+    unsafe {
+        let pages = (*area).pages;
+        if (*area).used + 1 >= pages { return None; }
+        let start = (*area).next;
+        let slot = (0..pages - 1)
+            .map(|i| 1 + (start - 1 + i) % (pages - 1))
+            .find(|&s| (*area).refcount[s] == 0)?;
+
+        let data = core::slice::from_raw_parts(src as *const u8, PAGE_SIZE);
+        if !write_page((*area).device, slot, data) { return None; }
+
+        (*area).refcount[slot] = 1;
+        (*area).used += 1;
+        (*area).next = if slot + 1 < pages { slot + 1 } else { 1 };
+        Some(slot)
+    }
+}
+
+/// Drop one reference to the slot named by swap entry `entry`.
+/// Called when a swap entry is unmapped, freed or replaced.
+pub unsafe fn release_entry(entry: u64) {
+    let slot = pa::swap_entry_slot(entry);
+    let area = &raw mut SWAP;
+    unsafe {
+        if slot == 0 || slot >= (*area).pages || (*area).refcount[slot] == 0 { return; }
+        (*area).refcount[slot] -= 1;
+        if (*area).refcount[slot] == 0 { (*area).used -= 1; }
+    }
+}
+
+/// Add a reference to the slot named by `entry` (fork copies a swap entry).
+pub unsafe fn dup_entry(entry: u64) {
+    let slot = pa::swap_entry_slot(entry);
+    let area = &raw mut SWAP;
+    unsafe {
+        if slot == 0 || slot >= (*area).pages { return; }
+        (*area).refcount[slot] = (*area).refcount[slot].saturating_add(1);
+    }
+}
+
+// ── Swap-in ───────────────────────────────────────────────────────────────────
+
+/// Read the swapped-out page at `virt` in `cr3` back into memory.
+/// Returns `false` if it is not a swap entry or no frame could be found.
+pub unsafe fn swap_in(cr3: u64, virt: u64) -> bool {
+    unsafe { ensure_free(1); }
+    let device = unsafe { (*(&raw const SWAP)).device };
+    let entry = unsafe {
+        pa::swap_in_page_in(cr3, virt, |slot, dst| {
+            let buf = core::slice::from_raw_parts_mut(dst as *mut u8, PAGE_SIZE);
+            read_page(device, slot, buf)
+        })
+    };
+    match entry {
+        Some(e) => { unsafe { release_entry(e); } true }
+        None    => false,
+    }
+}
+
+/// Page-fault hook: resolve a not-present fault on a swapped-out user page
+/// of the current address space.  Returns `true` if the access can be
+/// retried.
+pub unsafe fn handle_fault(fault_addr: u64) -> bool {
+    if fault_addr >= 0x0000_8000_0000_0000 { return false; }
+    let cr3: u64;
+    unsafe { core::arch::asm!("mov {}, cr3", out(reg) cr3); }
+    unsafe { swap_in(cr3 & 0x000F_FFFF_FFFF_F000, fault_addr & !0xFFF) }
+}
+
+// ── Reclaim ───────────────────────────────────────────────────────────────────
+
+/// Run the clock over all tasks until `target` pages have been swapped out,
+/// the swap area is full, or two full sweeps found nothing more to evict.
+/// Returns the number of frames freed.
+pub unsafe fn reclaim(target: usize) -> usize {
+    let area  = &raw mut SWAP;
+    let sched = &raw const SCHED;
+    unsafe {
+        if (*area).device == SwapDevice::None || (*area).reclaiming { return 0; }
+        (*area).reclaiming = true;
+    }
+
+    let mut freed = 0usize;
+    let mut full  = false;
+    let mut tasks_visited = 0usize;
+    while freed < target && !full && tasks_visited <= 2 * MAX_TASKS {
+        let t   = unsafe { (*area).hand_task };
+        let cr3 = unsafe { (*sched).tasks[t].cr3 };
+        let stop = if cr3 == 0 { None } else {
+            unsafe {
+                pa::scan_user_leaves_in(cr3, (*area).hand_virt, |virt, pte| {
+                    match pa::evict_page_in(cr3, virt, pte, |src| store_page(src)) {
+                        EvictResult::Evicted => { freed += 1; freed < target }
+                        EvictResult::Failed  => { full = true; false }
+                        EvictResult::Skipped | EvictResult::Referenced => true,
+                    }
+                })
+            }
+        };
+        match stop {
+            // Stopped mid-task: resume after this page next time.
+            Some(virt) => unsafe { (*area).hand_virt = virt + PAGE_SIZE as u64; },
+            None => unsafe {
+                (*area).hand_task = (t + 1) % MAX_TASKS;
+                (*area).hand_virt = 0;
+                tasks_visited += 1;
+            },
+        }
+    }
+
+    unsafe { (*area).reclaiming = false; }
+    freed
+}
+
+/// Make sure at least `n` frames (plus the low watermark) are free,
+/// swapping pages out if necessary.  A no-op while swap is off.
+pub unsafe fn ensure_free(n: usize) {
+    let device = unsafe { (*(&raw const SWAP)).device };
+    if device == SwapDevice::None { return; }
+    let want = n + LOW_WATERMARK;
+    let free = pa::free_frame_count();
+    if free < want {
+        unsafe { reclaim((want - free).max(RECLAIM_BATCH)); }
+    }
+}
+
+/// Background balancing, called from the scheduler tick.
+pub unsafe fn balance() {
+    unsafe { ensure_free(0); }
+}
+
+// ── swapon / swapoff ──────────────────────────────────────────────────────────
+
+/// Check page 0 of a candidate area for a `mkswap` header and return the
+/// number of pages it declares, if any.
+unsafe fn read_header(device: SwapDevice) -> Option<usize> {
+    let mut page = vec![0u8; PAGE_SIZE];
+    if !unsafe { read_page(device, 0, &mut page) } { return None; }
+    if &page[SWAP_MAGIC_OFFSET..] != SWAP_MAGIC { return None; }
+    let o = SWAP_LAST_PAGE_OFFSET;
+    let last = u32::from_le_bytes([page[o], page[o + 1], page[o + 2], page[o + 3]]);
+    Some(last as usize + 1)
+}
+
+/// Open `path` as a swap device.  Returns the device, its size in pages,
+/// whether a raw swap partition type (0x82) vouches for it without a header,
+/// and whether it is a file.
+unsafe fn open_device(path: &str) -> Result<(SwapDevice, usize, bool, bool), i64> {
+    use crate::kernel::vfs::{resolve, Resolved};
+
+    if let Some(dev) = path.strip_prefix("/dev/hd") {
+        let b = dev.as_bytes();
+        if b.is_empty() || !(b'a'..=b'd').contains(&b[0]) { return Err(-2); } // ENOENT
+        let disk = (b[0] - b'a') as usize;
+        let Some((sectors, _, _)) = crate::kernel::ata::disk_info(disk) else { return Err(-2) };
+        return match &b[1..] {
+            [] => {
+                let dev = SwapDevice::Ata { disk, start_lba: 0 };
+                Ok((dev, (sectors / SECTORS_PER_PAGE as u64) as usize, false, false))
+            }
+            [n @ b'1'..=b'4'] => {
+                let part = unsafe { crate::kernel::mbr::read_partition(disk, (n - b'0') as usize) };
+                let Some(p) = part else { return Err(-2) };
+                let dev = SwapDevice::Ata { disk, start_lba: p.start_lba };
+                let raw_ok = p.partition_type == crate::kernel::mbr::PTYPE_LINUX_SWAP;
+                Ok((dev, (p.size_sectors / SECTORS_PER_PAGE) as usize, raw_ok, false))
+            }
+            _ => Err(-2),
+        };
+    }
+
+    match resolve(path) {
+        Resolved::Fat16 { fat_path } => {
+            let fd = unsafe { crate::kernel::fat::open(fat_path, crate::kernel::fs::O_RDWR) };
+            if fd < 0 { return Err(fd); }
+            let size = crate::kernel::fat::file_size(fd as i32) as usize;
+            Ok((SwapDevice::Fat16(fd as i32), size / PAGE_SIZE, false, true))
+        }
+        Resolved::Ext2 { path } => {
+            let fd = unsafe { crate::kernel::ext2::open(path, crate::kernel::fs::O_RDWR) };
+            if fd < 0 { return Err(fd); }
+            let size = crate::kernel::ext2::file_size(fd as i32) as usize;
+            Ok((SwapDevice::Ext2(fd as i32), size / PAGE_SIZE, false, true))
+        }
+        _ => Err(-22), // EINVAL: RamFS/procfs live in RAM, swapping there is pointless
+    }
+}
+
+/// Enable swapping to `path`.  The area must carry a `mkswap` header, or be
+/// an MBR partition of type 0x82.
+pub unsafe fn swapon(path: &str) -> i64 {
+    let area = &raw mut SWAP;
+    if unsafe { (*area).device } != SwapDevice::None { return -16; } // EBUSY
+    if path.len() > 64 { return -36; } // ENAMETOOLONG
+
+    let (device, dev_pages, raw_ok, is_file) = match unsafe { open_device(path) } {
+        Ok(d)  => d,
+        Err(e) => return e,
+    };
+    let pages = match unsafe { read_header(device) } {
+        Some(n)         => n.min(dev_pages),
+        None if raw_ok  => dev_pages,
+        None            => { unsafe { close_device(device); } return -22; } // EINVAL
+    }.min(MAX_SWAP_SLOTS);
+    if pages < 2 {
+        unsafe { close_device(device); }
+        return -22;
+    }
+
+    unsafe {
+        (*area).refcount  = [0; MAX_SWAP_SLOTS];
+        (*area).device    = device;
+        (*area).pages     = pages;
+        (*area).used      = 0;
+        (*area).next      = 1;
+        (*area).is_file   = is_file;
+        (*area).path_len  = path.len();
+        (&mut (*area).path)[..path.len()].copy_from_slice(path.as_bytes());
+        (*area).hand_task = 0;
+        (*area).hand_virt = 0;
+
+        SERIAL_PORT.write_str("swap: enabled ");
+        SERIAL_PORT.write_str(path);
+        SERIAL_PORT.write_str(" (");
+        SERIAL_PORT.write_decimal(((pages - 1) * PAGE_SIZE / 1024) as u32);
+        SERIAL_PORT.write_str(" KB)\n");
+    }
+    0
+}
+
+/// Disable the swap area at `path`, reading every swapped-out page of every
+/// task back into memory first.  Fails with ENOMEM (leaving swap enabled)
+/// if RAM cannot hold them.
+pub unsafe fn swapoff(path: &str) -> i64 {
+    let area  = &raw mut SWAP;
+    let sched = &raw const SCHED;
+    unsafe {
+        if (*area).device == SwapDevice::None
+            || (&(*area).path)[..(*area).path_len] != *path.as_bytes()
+        {
+            return -22; // EINVAL
+        }
+    }
+
+    for t in 0..MAX_TASKS {
+        let cr3 = unsafe { (*sched).tasks[t].cr3 };
+        if cr3 == 0 { continue; }
+        let mut ok = true;
+        unsafe {
+            pa::scan_user_leaves_in(cr3, 0, |virt, pte| {
+                if pa::is_swap_entry(*pte) && !swap_in(cr3, virt) { ok = false; }
+                ok
+            });
+        }
+        if !ok { return -12; } // ENOMEM
+    }
+
+    unsafe {
+        let device = (*area).device;
+        (*area).device   = SwapDevice::None;
+        (*area).pages    = 0;
+        (*area).used     = 0;
+        (*area).path_len = 0;
+        close_device(device);
+        SERIAL_PORT.write_str("swap: disabled ");
+        SERIAL_PORT.write_str(path);
+        SERIAL_PORT.write_str("\n");
+    }
+    0
+}
+
+/// Boot-time auto-activation: enable the first MBR partition of type 0x82
+/// found on any ATA disk.
+pub unsafe fn init() {
+    for disk in 0..crate::kernel::ata::MAX_DISKS {
+        for n in 1..=4usize {
+            let Some(p) = (unsafe { crate::kernel::mbr::read_partition(disk, n) }) else { continue };
+            if p.partition_type != crate::kernel::mbr::PTYPE_LINUX_SWAP { continue; }
+            let mut name = *b"/dev/hda0";
+            name[7] = b'a' + disk as u8;
+            name[8] = b'0' + n as u8;
+            let path = core::str::from_utf8(&name).unwrap_or("");
+            if unsafe { swapon(path) } == 0 { return; }
+        }
+    }
+}
@@ -1,11 +1,11 @@
 // ── Category modules ──────────────────────────────────────────────────────────
-pub mod drivers;  // serial, pic, timer, keyboard, ata, shutdown, net/
+pub mod drivers;  // serial, pic, timer, keyboard, evdev, vt, ata, fbdev, shutdown, net/
 pub mod arch;     // gdt, idt, interrupts, interrupts_asm
 pub mod mem;      // paging_allocator
-pub mod fs;       // ramfs, fat, ext2, mbr, vfs, procfs
-pub mod proc;     // scheduler, elf_loader, user_mode, programs, env, tty
-pub mod ipc;      // ipc, pipe, shm, stdin
-pub mod sys;      // syscall_core, syscall, syscall_handler
+pub mod fs;       // ramfs, fat, ext2, mbr, vfs, procfs, devfs
+pub mod proc;     // scheduler, elf_loader, user_mode, programs, env, tty, pty, timers, jobctl
+pub mod ipc;      // ipc, pipe, anonfd, epoll, shm, stdin
+pub mod sys;      // syscall_core, syscall, syscall_handler, systrace
 pub mod gui;      // compositor, gui_proc
 
 // ── Remaining root files ──────────────────────────────────────────────────────
@@ -21,9 +21,21 @@ pub mod installer;
 pub use drivers::serial;
 pub use drivers::pic;
 pub use drivers::timer;
+pub use drivers::speaker;
 pub use drivers::rtc;
+pub use drivers::rng;
 pub use drivers::keyboard;
+pub use drivers::keymap;
+pub use drivers::evdev;
+pub use drivers::vt;
 pub use drivers::ata;
+pub use drivers::ahci;
+pub use drivers::virtio;
+pub use drivers::virtio_blk;
+pub use drivers::nvme;
+pub use drivers::fbdev;
+pub use drivers::ac97;
+pub use drivers::dsp;
 pub use drivers::disk_store;
 pub use drivers::shutdown;
 pub use drivers::net;
@@ -36,6 +48,9 @@ pub use arch::interrupts_asm;
 
 // mem/
 pub use mem::paging_allocator;
+pub use mem::mmap;
+pub use mem::swap;
+pub use mem::slab;
 
 // fs/ (individual submodules)
 pub use fs::fat;
@@ -44,6 +59,7 @@ pub use fs::mbr;
 pub use fs::vfs;
 pub use fs::procfs;
 pub use fs::diskfs;
+pub use fs::devfs;
 
 // proc/
 pub use proc::scheduler;
@@ -52,9 +68,17 @@ pub use proc::user_mode;
 pub use proc::programs;
 pub use proc::env;
 pub use proc::tty;
+pub use proc::pty;
+pub use proc::coredump;
+pub use proc::ptrace;
+pub use proc::timers;
+pub use proc::cred;
+pub use proc::jobctl;
 
 // ipc/ (ipc::Message etc. are re-exported at the ipc module level via ipc/mod.rs)
 pub use ipc::pipe;
+pub use ipc::anonfd;
+pub use ipc::epoll;
 pub use ipc::shm;
 pub use ipc::stdin;
 
@@ -62,7 +86,9 @@ pub use ipc::stdin;
 pub use sys::syscall_core;
 pub use sys::syscall;
 pub use sys::syscall_handler;
+pub use sys::systrace;
 
 // gui/ (kernel-side)
 pub use gui::compositor;
 pub use gui::gui_proc;
+pub use gui::session;
@@ -0,0 +1,447 @@
+// src/kernel/proc/coredump.rs
+//! ELF core dumps for processes killed by a core-generating signal.
+//!
+//! # Layout
+//!
+//! The dump is an `ET_CORE` file in the form gdb, lldb and `readelf` expect:
+//!
+//! ```text
+//! Elf64_Ehdr
+//! Elf64_Phdr  PT_NOTE
+//! Elf64_Phdr  PT_LOAD × n        one per run of user pages with equal perms
+//! notes       "CORE" NT_PRSTATUS  registers, signal, pid/ppid
+//!             "CORE" NT_PRPSINFO  command name
+//!             "CORE" NT_AUXV      the auxiliary vector from the initial stack
+//!             "CORE" NT_FILE      file-backed ranges (image, interpreter, mmaps)
+//! (pad to 4 KiB)
+//! page data for each PT_LOAD, in order
+//! ```
+//!
+//! Segments are found by walking the task's page table, so anything mapped
+//! — image, heap, stack, mmap regions, shared memory, the signal trampoline —
+//! is included.  Swapped-out pages are read back in.  Pages of physical
+//! mappings the frame allocator does not own (framebuffer, MMIO) get a
+//! `PT_LOAD` with no file data, like Linux's `VM_IO` areas.
+//!
+//! # Path and limits
+//!
+//! The output path is `/proc/sys/kernel/core_pattern` (default
+//! `/tmp/core.%e.%p`) with `%e` (command name), `%p` (pid), `%s` (signal),
+//! `%t` (seconds since boot) and `%%` expanded.  A relative pattern is taken
+//! relative to the task's working directory.  Any VFS backend with plain
+//! files works (RamFS, `/disk`, `/ext2`).  Piping to a helper (`|prog`) is
+//! not supported.
+//!
+//! On RamFS the core is created 0600 and owned by the task's filesystem
+//! ids; an existing file is replaced only if they own it.  Tasks whose
+//! credentials changed since exec (set-id programs) are not dumped.
+//!
+//! The file is truncated to the task's `RLIMIT_CORE` soft limit; a limit of
+//! 0 disables dumping.  `MAX_CORE_BYTES` caps the dump independently, since
+//! the image is assembled on the kernel heap before it is written.
+
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::kernel::paging_allocator::{self as pa, get_hhdm_offset, is_swap_entry};
+use crate::kernel::scheduler::{SCHED, Task, MmapBacking, SIGILL, SIGTRAP, SIGBUS, SIGFPE,
+                               SIGSEGV, SIGQUIT, SIGABRT};
+use crate::kernel::serial::SERIAL_PORT;
+use crate::kernel::user_mode::TaskContext;
+
+/// Pattern written to `/proc/sys/kernel/core_pattern` at boot.
+pub const DEFAULT_PATTERN: &str = "/tmp/core.%e.%p";
+
+/// Upper bound on a dump regardless of `RLIMIT_CORE` (4 MiB).
+pub const MAX_CORE_BYTES: usize = 4 * 1024 * 1024;
+
+const PAGE: u64 = 4096;
+/// Most `PT_LOAD` segments recorded; later mappings are left out.
+const MAX_SEGMENTS: usize = 256;
+/// Most auxv pairs copied into `NT_AUXV` (including `AT_NULL`).
+const MAX_AUXV: usize = 64;
+
+// ── ELF constants ──────────────────────────────────────────────────────────
+
+const ET_CORE:    u16 = 4;
+const EM_X86_64:  u16 = 62;
+const PT_LOAD:    u32 = 1;
+const PT_NOTE:    u32 = 4;
+const PF_X:       u32 = 1;
+const PF_W:       u32 = 2;
+const PF_R:       u32 = 4;
+const EHDR_SIZE:  usize = 64;
+const PHDR_SIZE:  usize = 56;
+
+const NT_PRSTATUS: u32 = 1;
+const NT_PRPSINFO: u32 = 3;
+const NT_AUXV:     u32 = 6;
+const NT_FILE:     u32 = 0x4649_4C45;
+
+/// `sizeof(struct elf_prstatus)` on x86_64; `pr_reg` starts at 112.
+const PRSTATUS_SIZE: usize = 336;
+const PRSTATUS_REG:  usize = 112;
+/// `sizeof(struct elf_prpsinfo)` on x86_64.
+const PRPSINFO_SIZE: usize = 136;
+
+// PTE bits the segment walk looks at.
+const PTE_PRESENT: u64 = 1 << 0;
+const PTE_WRITE:   u64 = 1 << 1;
+const PTE_USER:    u64 = 1 << 2;
+const PTE_COW:     u64 = 1 << 9;
+const PTE_PINNED:  u64 = 1 << 10;
+const PTE_NX:      u64 = 1 << 63;
+
+/// True if the default action of `signum` is "terminate and dump core".
+pub fn is_core_signal(signum: u8) -> bool {
+    const SIGXCPU: u8 = 24;
+    const SIGXFSZ: u8 = 25;
+    const SIGSYS:  u8 = 31;
+    matches!(signum, SIGQUIT | SIGILL | SIGTRAP | SIGABRT | SIGBUS | SIGFPE | SIGSEGV
+                   | SIGXCPU | SIGXFSZ | SIGSYS)
+}
+
+// ── Segments ───────────────────────────────────────────────────────────────
+
+#[derive(Clone, Copy)]
+struct Segment {
+    start: u64,
+    end:   u64,
+    flags: u32,
+    /// `false` for device memory, which is described but not copied.
+    dump:  bool,
+}
+
+impl Segment {
+    fn filesz(&self) -> u64 { if self.dump { self.end - self.start } else { 0 } }
+}
+
+/// Group the user pages of `cr3` into runs with equal permissions.
+unsafe fn collect_segments(cr3: u64) -> Vec<Segment> {
+    let mut segs: Vec<Segment> = Vec::new();
+    unsafe {
+        pa::scan_user_leaves_in(cr3, 0, |virt, slot| {
+            let entry = *slot;
+            let mapped = (entry & PTE_PRESENT != 0 || is_swap_entry(entry))
+                      && entry & PTE_USER != 0;
+            if !mapped { return true; }
+
+            let mut flags = PF_R;
+            if entry & (PTE_WRITE | PTE_COW) != 0 { flags |= PF_W; }
+            if entry & PTE_NX == 0 { flags |= PF_X; }
+            let dump = entry & PTE_PINNED == 0
+                    || is_swap_entry(entry)
+                    || pa::frame_refcount(entry & 0x000F_FFFF_FFFF_F000) != 0;
+
+            if let Some(last) = segs.last_mut() {
+                if last.end == virt && last.flags == flags && last.dump == dump {
+                    last.end += PAGE;
+                    return true;
+                }
+            }
+            if segs.len() == MAX_SEGMENTS { return false; }
+            segs.push(Segment { start: virt, end: virt + PAGE, flags, dump });
+            true
+        });
+    }
+    segs
+}
+
+/// Copy `buf.len()` bytes of user memory at `virt` in `cr3`.
+unsafe fn read_user(cr3: u64, virt: u64, buf: &mut [u8]) -> bool {
+    let hhdm = get_hhdm_offset();
+    let mut done = 0usize;
+    while done < buf.len() {
+        let addr  = virt + done as u64;
+        let off   = (addr & (PAGE - 1)) as usize;
+        let chunk = (PAGE as usize - off).min(buf.len() - done);
+        let Some(phys) = (unsafe { pa::user_page_phys_in(cr3, addr) }) else { return false };
+        unsafe {
+            core::ptr::copy_nonoverlapping(
+                (phys + hhdm + off as u64) as *const u8,
+                buf[done..].as_mut_ptr(),
+                chunk,
+            );
+        }
+        done += chunk;
+    }
+    true
+}
+
+unsafe fn read_user_u64(cr3: u64, virt: u64) -> Option<u64> {
+    let mut b = [0u8; 8];
+    if unsafe { read_user(cr3, virt, &mut b) } { Some(u64::from_le_bytes(b)) } else { None }
+}
+
+// ── Notes ──────────────────────────────────────────────────────────────────
+
+fn put_u16(v: &mut [u8], off: usize, x: u16) { v[off..off + 2].copy_from_slice(&x.to_le_bytes()); }
+fn put_u32(v: &mut [u8], off: usize, x: u32) { v[off..off + 4].copy_from_slice(&x.to_le_bytes()); }
+fn put_u64(v: &mut [u8], off: usize, x: u64) { v[off..off + 8].copy_from_slice(&x.to_le_bytes()); }
+
+fn pad4(v: &mut Vec<u8>) {
+    while v.len() % 4 != 0 { v.push(0); }
+}
+
+/// Append one note with owner name "CORE".
+fn push_note(out: &mut Vec<u8>, ty: u32, desc: &[u8]) {
+    const NAME: &[u8] = b"CORE\0";
+    out.extend_from_slice(&(NAME.len() as u32).to_le_bytes());
+    out.extend_from_slice(&(desc.len() as u32).to_le_bytes());
+    out.extend_from_slice(&ty.to_le_bytes());
+    out.extend_from_slice(NAME);
+    pad4(out);
+    out.extend_from_slice(desc);
+    pad4(out);
+}
+
+/// `struct elf_prstatus`: the signal, ids and `user_regs_struct`.
+fn prstatus(task: &Task, signum: u8, ctx: &TaskContext) -> [u8; PRSTATUS_SIZE] {
+    let mut d = [0u8; PRSTATUS_SIZE];
+    put_u32(&mut d, 0, signum as u32);                    // si_signo
+    put_u16(&mut d, 12, signum as u16);                   // pr_cursig
+    put_u64(&mut d, 16, task.pending_signals as u64);     // pr_sigpend
+    put_u64(&mut d, 24, task.signal_mask as u64);         // pr_sighold
+    put_u32(&mut d, 32, task.pid as u32);                 // pr_pid
+    put_u32(&mut d, 36, task.parent_pid as u32);          // pr_ppid
+    put_u32(&mut d, 40, task.pgid as u32);                // pr_pgrp
+    put_u32(&mut d, 44, task.pgid as u32);                // pr_sid
+
+    // user_regs_struct, in the kernel's (ptrace) order.
+    let regs: [u64; 27] = [
+        ctx.r15, ctx.r14, ctx.r13, ctx.r12, ctx.rbp, ctx.rbx, ctx.r11, ctx.r10,
+        ctx.r9, ctx.r8, ctx.rax, ctx.rcx, ctx.rdx, ctx.rsi, ctx.rdi,
+        u64::MAX,                                         // orig_rax
+        ctx.rip, ctx.cs, ctx.rflags, ctx.rsp, ctx.ss,
+        task.fs_base, 0,                                  // fs_base, gs_base
+        0, 0, 0, 0,                                       // ds, es, fs, gs
+    ];
+    for (i, r) in regs.iter().enumerate() {
+        put_u64(&mut d, PRSTATUS_REG + i * 8, *r);
+    }
+    d
+}
+
+/// `struct elf_prpsinfo`: state, ids and command name.
+fn prpsinfo(task: &Task) -> [u8; PRPSINFO_SIZE] {
+    let mut d = [0u8; PRPSINFO_SIZE];
+    d[1] = b'R';                                          // pr_sname
+    put_u32(&mut d, 24, task.pid as u32);                 // pr_pid
+    put_u32(&mut d, 28, task.parent_pid as u32);          // pr_ppid
+    put_u32(&mut d, 32, task.pgid as u32);                // pr_pgrp
+    put_u32(&mut d, 36, task.pgid as u32);                // pr_sid
+    let name = &task.name[..task.name_len.min(15)];
+    d[40..40 + name.len()].copy_from_slice(name);         // pr_fname
+    d[56..56 + name.len()].copy_from_slice(name);         // pr_psargs
+    d
+}
+
+/// The auxv pairs above `envp` on the initial stack, through `AT_NULL`.
+unsafe fn auxv(task: &Task) -> Vec<u8> {
+    let mut out = Vec::new();
+    let (cr3, sp) = (task.cr3, task.initial_rsp);
+    if sp == 0 { return out; }
+    let Some(argc) = (unsafe { read_user_u64(cr3, sp) }) else { return out };
+    // argc, argv[0..argc], NULL, then envp up to its NULL.
+    let mut p = sp + 8 * (argc + 2);
+    loop {
+        match unsafe { read_user_u64(cr3, p) } {
+            Some(0)    => break,
+            Some(_)    => p += 8,
+            None       => return out,
+        }
+    }
+    p += 8;
+    for _ in 0..MAX_AUXV {
+        let (Some(ty), Some(val)) = (unsafe { read_user_u64(cr3, p) },
+                                     unsafe { read_user_u64(cr3, p + 8) }) else { break };
+        out.extend_from_slice(&ty.to_le_bytes());
+        out.extend_from_slice(&val.to_le_bytes());
+        if ty == 0 { break; }
+        p += 16;
+    }
+    out
+}
+
+/// `NT_FILE`: count, page size, `(start, end, page offset)` triples, then
+/// the NUL-terminated file names.
+fn file_note(task: &Task) -> Vec<u8> {
+    let mut ranges: Vec<(u64, u64, u64, String)> = Vec::new();
+    for m in task.image_maps.iter().filter(|m| m.end > m.start) {
+        ranges.push((m.start, m.end, 0, String::from(m.path_str())));
+    }
+    if let Some(fs) = unsafe { crate::kernel::fs::ramfs::RAMFS.get() } {
+        for r in task.mmap_regions[..task.mmap_nregions].iter() {
+            if let MmapBacking::RamFS(inode) = r.backing {
+                if let Some(path) = fs.path_of(inode) {
+                    ranges.push((r.virt, r.end(), r.offset / PAGE, path));
+                }
+            }
+        }
+    }
+
+    let mut out = Vec::new();
+    out.extend_from_slice(&(ranges.len() as u64).to_le_bytes());
+    out.extend_from_slice(&PAGE.to_le_bytes());
+    for (start, end, pgoff, _) in ranges.iter() {
+        out.extend_from_slice(&start.to_le_bytes());
+        out.extend_from_slice(&end.to_le_bytes());
+        out.extend_from_slice(&pgoff.to_le_bytes());
+    }
+    for (.., name) in ranges.iter() {
+        out.extend_from_slice(name.as_bytes());
+        out.push(0);
+    }
+    out
+}
+
+// ── Path ───────────────────────────────────────────────────────────────────
+
+fn push_dec(s: &mut String, mut n: u64) {
+    let mut buf = [0u8; 20];
+    let mut i = buf.len();
+    loop {
+        i -= 1;
+        buf[i] = b'0' + (n % 10) as u8;
+        n /= 10;
+        if n == 0 { break; }
+    }
+    s.push_str(core::str::from_utf8(&buf[i..]).unwrap_or("0"));
+}
+
+/// Expand the current core pattern for `task`.  `None` if dumping is
+/// disabled by the pattern (empty, or a pipe).
+fn core_path(task: &Task, signum: u8) -> Option<String> {
+    let fs = unsafe { crate::kernel::fs::ramfs::RAMFS.get() };
+    let raw = fs.and_then(|fs| fs.read_file("/proc/sys/kernel/core_pattern"))
+                .unwrap_or(DEFAULT_PATTERN.as_bytes());
+    let pattern = core::str::from_utf8(raw).ok()?.trim_end_matches('\n');
+    if pattern.is_empty() || pattern.starts_with('|') { return None; }
+
+    let mut path = String::new();
+    if !pattern.starts_with('/') {
+        let cwd = core::str::from_utf8(&task.cwd[..task.cwd_len]).unwrap_or("/");
+        path.push_str(cwd.trim_end_matches('/'));
+        path.push('/');
+    }
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' { path.push(c); continue; }
+        match chars.next() {
+            Some('e') => path.push_str(core::str::from_utf8(&task.name[..task.name_len]).unwrap_or("?")),
+            Some('p') => push_dec(&mut path, task.pid as u64),
+            Some('s') => push_dec(&mut path, signum as u64),
+            Some('t') => push_dec(&mut path, unsafe { crate::kernel::timer::get_ticks() } / 100),
+            Some('%') => path.push('%'),
+            _         => {} // unknown specifiers expand to nothing
+        }
+    }
+    Some(path)
+}
+
+// ── Dump ───────────────────────────────────────────────────────────────────
+
+/// Write a core file for task slot `idx`, killed by `signum` with user
+/// registers `ctx`.  Does nothing unless `signum` dumps core, the task is
+/// dumpable (its credentials have not changed since exec, so a set-id
+/// program's memory never leaks), its `RLIMIT_CORE` is non-zero and a path
+/// can be formed.  Returns `true` if a file was written.
+pub unsafe fn dump(idx: usize, signum: u8, ctx: &TaskContext) -> bool {
+    if !is_core_signal(signum) { return false; }
+    let sched = &raw const SCHED;
+    let task  = unsafe { &(*sched).tasks[idx] };
+    if !task.dumpable { return false; }
+    let limit = (task.core_rlimit.0.min(MAX_CORE_BYTES as u64)) as usize;
+    if limit == 0 || task.cr3 == 0 { return false; }
+    let Some(path) = core_path(task, signum) else { return false };
+
+    let segs = unsafe { collect_segments(task.cr3) };
+
+    let mut notes = Vec::new();
+    push_note(&mut notes, NT_PRSTATUS, &prstatus(task, signum, ctx));
+    push_note(&mut notes, NT_PRPSINFO, &prpsinfo(task));
+    let aux = unsafe { auxv(task) };
+    if !aux.is_empty() { push_note(&mut notes, NT_AUXV, &aux); }
+    push_note(&mut notes, NT_FILE, &file_note(task));
+
+    let phnum      = 1 + segs.len();
+    let notes_off  = EHDR_SIZE + phnum * PHDR_SIZE;
+    let data_off   = (notes_off + notes.len()).next_multiple_of(PAGE as usize);
+    // The headers and notes must fit; page data is cut off at the limit.
+    if notes_off + notes.len() > limit { return false; }
+
+    let mut out = Vec::with_capacity(data_off.min(limit));
+
+    // Elf64_Ehdr
+    let mut eh = [0u8; EHDR_SIZE];
+    eh[..4].copy_from_slice(b"\x7fELF");
+    eh[4] = 2;                                            // ELFCLASS64
+    eh[5] = 1;                                            // ELFDATA2LSB
+    eh[6] = 1;                                            // EV_CURRENT
+    put_u16(&mut eh, 16, ET_CORE);
+    put_u16(&mut eh, 18, EM_X86_64);
+    put_u32(&mut eh, 20, 1);                              // e_version
+    put_u64(&mut eh, 32, EHDR_SIZE as u64);               // e_phoff
+    put_u16(&mut eh, 52, EHDR_SIZE as u16);               // e_ehsize
+    put_u16(&mut eh, 54, PHDR_SIZE as u16);               // e_phentsize
+    put_u16(&mut eh, 56, phnum as u16);                   // e_phnum
+    out.extend_from_slice(&eh);
+
+    // Elf64_Phdr × phnum
+    let mut ph = [0u8; PHDR_SIZE];
+    put_u32(&mut ph, 0, PT_NOTE);
+    put_u64(&mut ph, 8, notes_off as u64);                // p_offset
+    put_u64(&mut ph, 32, notes.len() as u64);             // p_filesz
+    put_u64(&mut ph, 48, 4);                              // p_align
+    out.extend_from_slice(&ph);
+    let mut off = data_off as u64;
+    for seg in segs.iter() {
+        let mut ph = [0u8; PHDR_SIZE];
+        put_u32(&mut ph, 0, PT_LOAD);
+        put_u32(&mut ph, 4, seg.flags);
+        put_u64(&mut ph, 8, off);                         // p_offset
+        put_u64(&mut ph, 16, seg.start);                  // p_vaddr
+        put_u64(&mut ph, 32, seg.filesz());               // p_filesz
+        put_u64(&mut ph, 40, seg.end - seg.start);        // p_memsz
+        put_u64(&mut ph, 48, PAGE);                       // p_align
+        out.extend_from_slice(&ph);
+        off += seg.filesz();
+    }
+
+    out.extend_from_slice(&notes);
+    out.resize(data_off.min(limit), 0);
+
+    // Page data, stopping at the limit.
+    let mut page = [0u8; PAGE as usize];
+    'segs: for seg in segs.iter().filter(|s| s.dump) {
+        let mut va = seg.start;
+        while va < seg.end {
+            if out.len() >= limit { break 'segs; }
+            if !unsafe { read_user(task.cr3, va, &mut page) } { page.fill(0); }
+            let n = page.len().min(limit - out.len());
+            out.extend_from_slice(&page[..n]);
+            va += PAGE;
+        }
+    }
+
+    let written = unsafe { crate::kernel::vfs::vfs_write_file(&path, &out, &task.cred, 0o600) };
+    unsafe {
+        SERIAL_PORT.write_str("coredump: pid=");
+        SERIAL_PORT.write_decimal(task.pid as u32);
+        SERIAL_PORT.write_str(" signal=");
+        SERIAL_PORT.write_decimal(signum as u32);
+        SERIAL_PORT.write_str(" -> ");
+        SERIAL_PORT.write_str(&path);
+        if written < 0 {
+            SERIAL_PORT.write_str(" failed (");
+            SERIAL_PORT.write_decimal((-written) as u32);
+            SERIAL_PORT.write_str(")\n");
+        } else {
+            SERIAL_PORT.write_str(" (");
+            SERIAL_PORT.write_decimal(out.len() as u32);
+            SERIAL_PORT.write_str(" bytes)\n");
+        }
+    }
+    written >= 0
+}
@@ -0,0 +1,299 @@
+// src/kernel/proc/cred.rs
+//! Process credentials: real/effective/saved/filesystem uid and gid plus
+//! supplementary groups, and the `set*id` family of syscalls.
+//!
+//! Every task carries a [`Credentials`].  Tasks spawned by the kernel start
+//! as root; fork copies the parent's; exec keeps them except that a set-uid
+//! (set-gid) image makes its owner (group) the effective id, and the saved
+//! ids then follow the effective ones.  "Privileged" means an effective uid
+//! of 0 — there are no capabilities.
+//!
+//! File access checks (`may_access`) use the filesystem ids, which follow
+//! the effective ids unless changed with `setfsuid` / `setfsgid`.
+
+use crate::kernel::scheduler::{SCHED, CURRENT_TASK_IDX};
+use crate::kernel::syscall_core::validate_user_range;
+
+pub use crate::kernel::syscall_core::NGROUPS_MAX;
+
+pub const S_ISUID: u16 = 0o4000;
+pub const S_ISGID: u16 = 0o2000;
+pub const S_ISVTX: u16 = 0o1000;
+
+// Access bits for `may_access`, as in `access(2)`.
+pub const MAY_READ:  u32 = 4;
+pub const MAY_WRITE: u32 = 2;
+pub const MAY_EXEC:  u32 = 1;
+
+/// `-1` as a `uid_t`: "leave unchanged" for setreuid and friends.
+const UNCHANGED: u32 = u32::MAX;
+
+const EPERM:  i64 = -1;
+const EFAULT: i64 = -14;
+const EINVAL: i64 = -22;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Credentials {
+    pub ruid:    u32,
+    pub euid:    u32,
+    pub suid:    u32,
+    pub fsuid:   u32,
+    pub rgid:    u32,
+    pub egid:    u32,
+    pub sgid:    u32,
+    pub fsgid:   u32,
+    pub groups:  [u32; NGROUPS_MAX],
+    pub ngroups: usize,
+}
+
+impl Credentials {
+    pub const ROOT: Self = Self {
+        ruid: 0, euid: 0, suid: 0, fsuid: 0,
+        rgid: 0, egid: 0, sgid: 0, fsgid: 0,
+        groups: [0; NGROUPS_MAX], ngroups: 0,
+    };
+
+    pub fn privileged(&self) -> bool { self.euid == 0 }
+
+    /// May these credentials signal a task holding `target`?  Root may
+    /// signal anyone; others need their real or effective uid to match the
+    /// target's real or saved uid, as in Linux.
+    pub fn may_signal(&self, target: &Credentials) -> bool {
+        self.privileged()
+            || [self.ruid, self.euid].iter().any(|&u| u == target.ruid || u == target.suid)
+    }
+
+    /// `gid` is the filesystem gid or a supplementary group.
+    pub fn in_group(&self, gid: u32) -> bool {
+        self.fsgid == gid || self.groups[..self.ngroups].contains(&gid)
+    }
+
+    /// May these credentials access a file with `mode`, owned by
+    /// `uid`:`gid`, for the `MAY_*` bits in `want`?  Root may do anything
+    /// except execute a file with no execute bit at all.
+    pub fn may_access(&self, want: u32, mode: u16, uid: u32, gid: u32) -> bool {
+        if self.fsuid == 0 {
+            return want & MAY_EXEC == 0 || mode & 0o111 != 0;
+        }
+        let bits = if self.fsuid == uid {
+            (mode >> 6) & 7
+        } else if self.in_group(gid) {
+            (mode >> 3) & 7
+        } else {
+            mode & 7
+        } as u32;
+        bits & want == want
+    }
+
+    /// Apply exec of `image`.  Returns true if the ids changed
+    /// (`AT_SECURE`).  `nosuid` (a traced task) ignores the set-id bits, as
+    /// Linux does without `CAP_SYS_PTRACE`.
+    pub fn exec(&mut self, image: ImageOwner, nosuid: bool) -> bool {
+        if !nosuid {
+            if image.mode & S_ISUID != 0 { self.euid = image.uid; }
+            if image.mode & S_ISGID != 0 { self.egid = image.gid; }
+        }
+        self.suid  = self.euid;
+        self.sgid  = self.egid;
+        self.fsuid = self.euid;
+        self.fsgid = self.egid;
+        self.euid != self.ruid || self.egid != self.rgid
+    }
+}
+
+/// Mode and owner of an executable image, for set-id handling at exec.
+#[derive(Clone, Copy)]
+pub struct ImageOwner {
+    pub mode: u16,
+    pub uid:  u32,
+    pub gid:  u32,
+}
+
+impl ImageOwner {
+    /// Images from filesystems without ownership (FAT16): root-owned,
+    /// never set-id.
+    pub const PLAIN: Self = Self { mode: 0o755, uid: 0, gid: 0 };
+}
+
+/// Pointer to the running task's credentials.
+fn current() -> *mut Credentials {
+    unsafe { &raw mut (*(&raw mut SCHED)).tasks[CURRENT_TASK_IDX].cred }
+}
+
+/// Copy of the running task's credentials.
+pub fn get() -> Credentials {
+    unsafe { *current() }
+}
+
+/// Shared logic of setuid/setgid and friends, over one id family.
+struct Ids<'a> {
+    r:  &'a mut u32,
+    e:  &'a mut u32,
+    s:  &'a mut u32,
+    fs: &'a mut u32,
+}
+
+impl Ids<'_> {
+    fn is_one_of(&self, id: u32) -> bool {
+        id == *self.r || id == *self.e || id == *self.s
+    }
+
+    /// `setuid(id)`: root sets all three, others only the effective id to
+    /// the real or saved one.
+    fn set(&mut self, privileged: bool, id: u32) -> i64 {
+        if id == UNCHANGED { return EINVAL; }
+        if privileged {
+            *self.r = id;
+            *self.s = id;
+        } else if id != *self.r && id != *self.s {
+            return EPERM;
+        }
+        *self.e  = id;
+        *self.fs = id;
+        0
+    }
+
+    /// `setreuid(r, e)`.  The saved id becomes the new effective id when the
+    /// real id is set, or the effective id set to something other than the
+    /// old real id.
+    fn set_re(&mut self, privileged: bool, r: u32, e: u32) -> i64 {
+        if !privileged {
+            if r != UNCHANGED && r != *self.r && r != *self.e { return EPERM; }
+            if e != UNCHANGED && !self.is_one_of(e) { return EPERM; }
+        }
+        let old_r = *self.r;
+        if r != UNCHANGED { *self.r = r; }
+        if e != UNCHANGED { *self.e = e; }
+        if r != UNCHANGED || (e != UNCHANGED && e != old_r) { *self.s = *self.e; }
+        *self.fs = *self.e;
+        0
+    }
+
+    /// `setresuid(r, e, s)`: unprivileged callers may only pick among their
+    /// current ids.
+    fn set_res(&mut self, privileged: bool, r: u32, e: u32, s: u32) -> i64 {
+        if !privileged
+            && [r, e, s].iter().any(|&id| id != UNCHANGED && !self.is_one_of(id))
+        {
+            return EPERM;
+        }
+        if r != UNCHANGED { *self.r = r; }
+        if e != UNCHANGED { *self.e = e; }
+        if s != UNCHANGED { *self.s = s; }
+        *self.fs = *self.e;
+        0
+    }
+
+    /// `setfsuid(id)`: returns the previous filesystem id either way.
+    fn set_fs(&mut self, privileged: bool, id: u32) -> i64 {
+        let old = *self.fs;
+        if privileged || self.is_one_of(id) || id == old { *self.fs = id; }
+        old as i64
+    }
+}
+
+fn uids(c: &mut Credentials) -> Ids<'_> {
+    Ids { r: &mut c.ruid, e: &mut c.euid, s: &mut c.suid, fs: &mut c.fsuid }
+}
+
+fn gids(c: &mut Credentials) -> Ids<'_> {
+    Ids { r: &mut c.rgid, e: &mut c.egid, s: &mut c.sgid, fs: &mut c.fsgid }
+}
+
+/// Apply `f` to the running task's credentials; its second argument says
+/// whether they were privileged.  A changed effective or filesystem id
+/// makes the task non-dumpable (`Task::dumpable`), as in Linux.
+fn update(f: impl FnOnce(&mut Credentials, bool) -> i64) -> i64 {
+    let task = unsafe { &mut (*(&raw mut SCHED)).tasks[CURRENT_TASK_IDX] };
+    let old  = task.cred;
+    let r    = f(&mut task.cred, old.privileged());
+    let c    = &task.cred;
+    if (c.euid, c.egid, c.fsuid, c.fsgid) != (old.euid, old.egid, old.fsuid, old.fsgid) {
+        task.dumpable = false;
+    }
+    r
+}
+
+// ── Syscalls ───────────────────────────────────────────────────────────────
+
+pub fn getuid()  -> i64 { get().ruid as i64 }
+pub fn geteuid() -> i64 { get().euid as i64 }
+pub fn getgid()  -> i64 { get().rgid as i64 }
+pub fn getegid() -> i64 { get().egid as i64 }
+
+pub fn setuid(uid: u32) -> i64 {
+    update(|c, p| uids(c).set(p, uid))
+}
+
+pub fn setgid(gid: u32) -> i64 {
+    update(|c, p| gids(c).set(p, gid))
+}
+
+pub fn setreuid(ruid: u32, euid: u32) -> i64 {
+    update(|c, p| uids(c).set_re(p, ruid, euid))
+}
+
+pub fn setregid(rgid: u32, egid: u32) -> i64 {
+    update(|c, p| gids(c).set_re(p, rgid, egid))
+}
+
+pub fn setresuid(ruid: u32, euid: u32, suid: u32) -> i64 {
+    update(|c, p| uids(c).set_res(p, ruid, euid, suid))
+}
+
+pub fn setresgid(rgid: u32, egid: u32, sgid: u32) -> i64 {
+    update(|c, p| gids(c).set_res(p, rgid, egid, sgid))
+}
+
+pub fn setfsuid(uid: u32) -> i64 {
+    update(|c, p| uids(c).set_fs(p, uid))
+}
+
+pub fn setfsgid(gid: u32) -> i64 {
+    update(|c, p| gids(c).set_fs(p, gid))
+}
+
+/// Write three ids to three user `u32` pointers (getresuid/getresgid).
+unsafe fn write_ids(ptrs: [u64; 3], ids: [u32; 3]) -> i64 {
+    for p in ptrs {
+        if validate_user_range(p, 4).is_err() { return EFAULT; }
+    }
+    for (p, id) in ptrs.into_iter().zip(ids) {
+        unsafe { core::ptr::write_unaligned(p as *mut u32, id); }
+    }
+    0
+}
+
+pub unsafe fn getresuid(r: u64, e: u64, s: u64) -> i64 {
+    let c = get();
+    unsafe { write_ids([r, e, s], [c.ruid, c.euid, c.suid]) }
+}
+
+pub unsafe fn getresgid(r: u64, e: u64, s: u64) -> i64 {
+    let c = get();
+    unsafe { write_ids([r, e, s], [c.rgid, c.egid, c.sgid]) }
+}
+
+/// `getgroups(size, list)`: size 0 only counts.
+pub unsafe fn getgroups(size: u32, list_ptr: u64) -> i64 {
+    let c = get();
+    if size == 0 { return c.ngroups as i64; }
+    if (size as usize) < c.ngroups { return EINVAL; }
+    if validate_user_range(list_ptr, c.ngroups as u64 * 4).is_err() { return EFAULT; }
+    for (i, &g) in c.groups[..c.ngroups].iter().enumerate() {
+        unsafe { core::ptr::write_unaligned((list_ptr as *mut u32).add(i), g); }
+    }
+    c.ngroups as i64
+}
+
+/// `setgroups(size, list)`; root only.  syscall_core has checked `size`
+/// against `NGROUPS_MAX` and the list pointer.
+pub unsafe fn setgroups(size: u64, list_ptr: u64) -> i64 {
+    let c = unsafe { &mut *current() };
+    if !c.privileged() { return EPERM; }
+    for i in 0..size as usize {
+        c.groups[i] = unsafe { core::ptr::read_unaligned((list_ptr as *const u32).add(i)) };
+    }
+    c.ngroups = size as usize;
+    0
+}
@@ -1,7 +1,18 @@
 //! Minimal ELF64 loader for OxideOS.
 //!
-//! Supports ET_EXEC (statically linked executable) for x86-64.
-//! Maps every PT_LOAD segment, copies file data, zeros BSS, returns e_entry.
+//! Supports ET_EXEC (statically linked executable) and ET_DYN (position-
+//! independent executable or shared object) for x86-64.  PIEs are loaded at a
+//! random, page-aligned bias above `PIE_BASE` (ASLR); a `PT_INTERP` program
+//! interpreter is loaded separately via `load_interp_in` in its own window.
+//! Maps every PT_LOAD segment, copies file data, zeros BSS, applies the
+//! segment permissions, and reports the entry point and image layout.
+//!
+//! Other program headers:
+//! - `PT_INTERP`    — path returned by `interp()`; the caller loads it.
+//! - `PT_TLS`       — validated only; libc builds the TLS block from `AT_PHDR`.
+//! - `PT_GNU_STACK` — `ElfImage::exec_stack` (stack is NX unless `PF_X`).
+//! - `PT_GNU_RELRO` — `ElfImage::relro`; sealed by `seal_relro` for static
+//!   ET_EXEC images, and by the dynamic linker otherwise.
 
 use crate::kernel::paging_allocator;
 use core::arch::asm;
@@ -11,10 +22,27 @@ const ELFMAG: [u8; 4] = [0x7F, b'E', b'L', b'F'];
 const ELFCLASS64:  u8  = 2;
 const ELFDATA2LSB: u8  = 1;
 const ET_EXEC:     u16 = 2;
+const ET_DYN:      u16 = 3;
 const EM_X86_64:   u16 = 0x3E;
 const PT_LOAD:     u32 = 1;
+const PT_INTERP:   u32 = 3;
+const PT_PHDR:     u32 = 6;
+const PT_TLS:      u32 = 7;
+const PT_GNU_STACK: u32 = 0x6474_E551;
+const PT_GNU_RELRO: u32 = 0x6474_E552;
+const PF_X:        u32 = 1; // executable segment
 const PF_W:        u32 = 2; // writable segment
 
+/// Longest `PT_INTERP` path accepted.
+const INTERP_MAX:  usize = 256;
+
+/// Lowest load address for PIEs; the bias adds up to `PIE_SLIDE_PAGES` pages.
+const PIE_BASE:        u64 = 0x0000_5555_0000_0000;
+const PIE_SLIDE_PAGES: u64 = 1 << 28; // 1 TiB of slide
+/// Program interpreters (ld.so) get their own window below the trampoline.
+const INTERP_BASE:        u64 = 0x0000_7E00_0000_0000;
+const INTERP_SLIDE_PAGES: u64 = 1 << 24; // 64 GiB of slide
+
 /// Returns true if `data` begins with the ELF magic bytes.
 pub fn is_elf(data: &[u8]) -> bool {
     data.len() >= 4 && data[..4] == ELFMAG
@@ -44,43 +72,171 @@ struct Elf64Phdr {
     _p_paddr: u64,
     p_filesz: u64,
     p_memsz:  u64,
-    _p_align: u64,
+    p_align:  u64,
 }
 
-/// Load an ELF64 binary from `data`.
-///
-/// Maps each PT_LOAD segment into user virtual memory, copies the file
-/// content, and zeroes the BSS region. Returns the entry point on success.
-pub unsafe fn load(data: &[u8]) -> Result<u64, &'static str> {
-    let ehdr_size = core::mem::size_of::<Elf64Ehdr>();
-    if data.len() < ehdr_size { return Err("file too small"); }
+/// Where an image ended up after loading.  All addresses include the bias.
+#[derive(Clone, Copy)]
+pub struct ElfImage {
+    /// Entry point (`e_entry` + bias).
+    pub entry: u64,
+    /// Load bias — 0 for ET_EXEC, the random slide for ET_DYN.
+    pub bias:  u64,
+    /// First page of the lowest PT_LOAD segment.
+    pub start: u64,
+    /// First page past the highest PT_LOAD segment (brk starts above this).
+    pub end:   u64,
+    /// Virtual address of the program header table (0 if not mapped).
+    pub phdr:  u64,
+    pub phent: u64,
+    pub phnum: u64,
+    /// `PT_GNU_STACK` asked for an executable stack.
+    pub exec_stack: bool,
+    /// Page-aligned `PT_GNU_RELRO` range `[start, end)`; empty if absent.
+    pub relro: (u64, u64),
+}
+
+impl ElfImage {
+    /// Summary for a flat binary copied to `base` (no program headers).
+    pub fn flat(base: u64, len: usize) -> Self {
+        Self {
+            entry: base,
+            bias:  0,
+            start: base,
+            end:   base + len.div_ceil(PAGE_SIZE) as u64 * PAGE_SIZE as u64,
+            phdr:  0,
+            phent: 0,
+            phnum: 0,
+            exec_stack: false,
+            relro: (0, 0),
+        }
+    }
+}
+
+/// Validate the ELF header and return it.
+fn header(data: &[u8]) -> Result<&Elf64Ehdr, &'static str> {
+    if data.len() < core::mem::size_of::<Elf64Ehdr>() { return Err("file too small"); }
 
     let ehdr = unsafe { &*(data.as_ptr() as *const Elf64Ehdr) };
+    if ehdr.e_ident[..4] != ELFMAG    { return Err("bad ELF magic"); }
+    if ehdr.e_ident[4] != ELFCLASS64  { return Err("not ELF64"); }
+    if ehdr.e_ident[5] != ELFDATA2LSB { return Err("not little-endian"); }
+    if ehdr.e_type != ET_EXEC && ehdr.e_type != ET_DYN { return Err("not an executable"); }
+    if ehdr.e_machine != EM_X86_64    { return Err("not x86-64"); }
+    if (ehdr.e_phentsize as usize) < core::mem::size_of::<Elf64Phdr>() {
+        return Err("phdr too small");
+    }
+    for ph in phdrs(data, ehdr) {
+        if ph.p_type == PT_TLS
+            && (ph.p_filesz > ph.p_memsz || (ph.p_align > 1 && !ph.p_align.is_power_of_two()))
+        {
+            return Err("bad PT_TLS");
+        }
+        if ph.p_type == PT_LOAD && ph.p_filesz > ph.p_memsz {
+            return Err("segment filesz > memsz");
+        }
+    }
+    Ok(ehdr)
+}
 
-    if ehdr.e_ident[..4] != ELFMAG      { return Err("bad ELF magic"); }
-    if ehdr.e_ident[4] != ELFCLASS64    { return Err("not ELF64"); }
-    if ehdr.e_ident[5] != ELFDATA2LSB   { return Err("not little-endian"); }
-    if ehdr.e_type    != ET_EXEC        { return Err("not an executable"); }
-    if ehdr.e_machine != EM_X86_64      { return Err("not x86-64"); }
+/// The `PT_INTERP` path of `data`, if it names a program interpreter.
+pub fn interp(data: &[u8]) -> Option<&str> {
+    let ehdr = header(data).ok()?;
+    let ph   = phdrs(data, ehdr).find(|ph| ph.p_type == PT_INTERP)?;
+    let start = ph.p_offset as usize;
+    let len   = (ph.p_filesz as usize).min(INTERP_MAX);
+    let raw   = data.get(start..start.checked_add(len)?)?;
+    let raw   = raw.split(|&b| b == 0).next()?;
+    core::str::from_utf8(raw).ok().filter(|s| s.starts_with('/'))
+}
 
+/// Iterate the in-bounds program headers of `data`.
+fn phdrs<'a>(data: &'a [u8], ehdr: &Elf64Ehdr) -> impl Iterator<Item = &'a Elf64Phdr> {
+    let phoff   = ehdr.e_phoff as usize;
     let ph_size = ehdr.e_phentsize as usize;
-    if ph_size < core::mem::size_of::<Elf64Phdr>() { return Err("phdr too small"); }
+    (0..ehdr.e_phnum as usize).filter_map(move |i| {
+        let off = phoff.checked_add(i * ph_size)?;
+        if off + core::mem::size_of::<Elf64Phdr>() > data.len() { return None; }
+        Some(unsafe { &*(data[off..].as_ptr() as *const Elf64Phdr) })
+    })
+}
 
-    let phoff = ehdr.e_phoff as usize;
-    let phnum = ehdr.e_phnum as usize;
+/// Page-aligned `[min, max)` spanned by the PT_LOAD segments (unbiased).
+fn load_span(data: &[u8], ehdr: &Elf64Ehdr) -> (u64, u64) {
+    let mut min_va = u64::MAX;
+    let mut max_va = 0u64;
+    for ph in phdrs(data, ehdr) {
+        if ph.p_type != PT_LOAD || ph.p_memsz == 0 { continue; }
+        let va_start = ph.p_vaddr & !0xFFF;
+        let va_end   = (ph.p_vaddr + ph.p_memsz + 0xFFF) & !0xFFF;
+        if va_start < min_va { min_va = va_start; }
+        if va_end   > max_va { max_va = va_end;   }
+    }
+    if min_va > max_va { (0, 0) } else { (min_va, max_va) }
+}
+
+/// Pick the load bias: none for ET_EXEC, a random slide for ET_DYN.
+fn choose_bias(ehdr: &Elf64Ehdr, min_va: u64, base: u64, slide_pages: u64) -> u64 {
+    if ehdr.e_type != ET_DYN { return 0; }
+    let slide = crate::kernel::rng::below(slide_pages) * PAGE_SIZE as u64;
+    (base + slide).wrapping_sub(min_va)
+}
 
-    for i in 0..phnum {
-        let ph_off = phoff + i * ph_size;
-        if ph_off + core::mem::size_of::<Elf64Phdr>() > data.len() {
-            return Err("phdr out of bounds");
+/// Build the `ElfImage` summary once the bias and span are known.
+fn image(data: &[u8], ehdr: &Elf64Ehdr, bias: u64, min_va: u64, max_va: u64) -> ElfImage {
+    // Prefer PT_PHDR; otherwise find the PT_LOAD that covers e_phoff.
+    let mut phdr = 0;
+    for ph in phdrs(data, ehdr) {
+        if ph.p_type == PT_PHDR { phdr = ph.p_vaddr + bias; break; }
+        if ph.p_type == PT_LOAD && phdr == 0
+            && ehdr.e_phoff >= ph.p_offset
+            && ehdr.e_phoff < ph.p_offset + ph.p_filesz
+        {
+            phdr = ph.p_vaddr + (ehdr.e_phoff - ph.p_offset) + bias;
         }
-        let ph = unsafe { &*(data[ph_off..].as_ptr() as *const Elf64Phdr) };
+    }
+    let mut exec_stack = false;
+    let mut relro = (0, 0);
+    for ph in phdrs(data, ehdr) {
+        match ph.p_type {
+            PT_GNU_STACK => exec_stack = ph.p_flags & PF_X != 0,
+            PT_GNU_RELRO => {
+                let start = (ph.p_vaddr + bias) & !0xFFF;
+                let end   = (ph.p_vaddr + ph.p_memsz + bias) & !0xFFF;
+                if end > start { relro = (start, end); }
+            }
+            _ => {}
+        }
+    }
+    ElfImage {
+        entry: ehdr.e_entry.wrapping_add(bias),
+        bias,
+        start: min_va.wrapping_add(bias),
+        end:   max_va.wrapping_add(bias),
+        phdr,
+        phent: ehdr.e_phentsize as u64,
+        phnum: ehdr.e_phnum as u64,
+        exec_stack,
+        relro,
+    }
+}
+
+/// Load an ELF64 binary from `data`.
+///
+/// Maps each PT_LOAD segment into user virtual memory, copies the file
+/// content, and zeroes the BSS region. Returns the entry point on success.
+pub unsafe fn load(data: &[u8]) -> Result<ElfImage, &'static str> {
+    let ehdr = header(data)?;
+    let (min_va, max_va) = load_span(data, ehdr);
+    let bias = choose_bias(ehdr, min_va, PIE_BASE, PIE_SLIDE_PAGES);
 
+    for ph in phdrs(data, ehdr) {
         if ph.p_type != PT_LOAD || ph.p_memsz == 0 { continue; }
 
         // Page-align the virtual range.
-        let va_start = ph.p_vaddr & !0xFFF;
-        let va_end   = (ph.p_vaddr + ph.p_memsz + 0xFFF) & !0xFFF;
+        let vaddr    = ph.p_vaddr + bias;
+        let va_start = vaddr & !0xFFF;
+        let va_end   = (vaddr + ph.p_memsz + 0xFFF) & !0xFFF;
         let npages   = ((va_end - va_start) / PAGE_SIZE as u64) as usize;
         let writable = (ph.p_flags & PF_W) != 0;
 
@@ -102,38 +258,50 @@ pub unsafe fn load(data: &[u8]) -> Result<u64, &'static str> {
             unsafe {
                 core::ptr::copy_nonoverlapping(
                     data[src_start..].as_ptr(),
-                    ph.p_vaddr as *mut u8,
+                    vaddr as *mut u8,
                     ph.p_filesz as usize,
                 );
             }
         }
     }
 
-    Ok(ehdr.e_entry)
+    Ok(image(data, ehdr, bias, min_va, max_va))
 }
 
 /// Load an ELF64 binary into the address space identified by `cr3`.
 ///
 /// Pass 1 maps segments into `cr3` via `map_user_region_in` (no CR3 switch).
 /// Pass 2 switches to `cr3`, zeroes+copies each segment, then restores CR3.
+/// Pass 3 drops write/execute permission where the segment flags ask for it.
 /// `map_user_region_in` pre-zeros every physical frame, so BSS is implicitly
 /// cleared during pass 1.
-pub unsafe fn load_in(data: &[u8], cr3: u64) -> Result<u64, &'static str> {
-    let ehdr_size = core::mem::size_of::<Elf64Ehdr>();
-    if data.len() < ehdr_size { return Err("file too small"); }
+pub unsafe fn load_in(data: &[u8], cr3: u64) -> Result<ElfImage, &'static str> {
+    unsafe { load_at(data, cr3, PIE_BASE, PIE_SLIDE_PAGES) }
+}
 
-    let ehdr = unsafe { &*(data.as_ptr() as *const Elf64Ehdr) };
-    if ehdr.e_ident[..4] != ELFMAG    { return Err("bad ELF magic"); }
-    if ehdr.e_ident[4] != ELFCLASS64  { return Err("not ELF64"); }
-    if ehdr.e_ident[5] != ELFDATA2LSB { return Err("not little-endian"); }
-    if ehdr.e_type    != ET_EXEC      { return Err("not an executable"); }
-    if ehdr.e_machine != EM_X86_64    { return Err("not x86-64"); }
+/// Load a `PT_INTERP` program interpreter (must be ET_DYN) into `cr3` at a
+/// random bias in the interpreter window.
+pub unsafe fn load_interp_in(data: &[u8], cr3: u64) -> Result<ElfImage, &'static str> {
+    if header(data)?.e_type != ET_DYN { return Err("interpreter not ET_DYN"); }
+    unsafe { load_at(data, cr3, INTERP_BASE, INTERP_SLIDE_PAGES) }
+}
 
-    let ph_size = ehdr.e_phentsize as usize;
-    if ph_size < core::mem::size_of::<Elf64Phdr>() { return Err("phdr too small"); }
+/// Make `image`'s `PT_GNU_RELRO` range read-only.  Only valid once nothing
+/// needs to write there — i.e. for static ET_EXEC images, which carry no
+/// run-time relocations.
+pub unsafe fn seal_relro(cr3: u64, image: &ElfImage) {
+    let (start, end) = image.relro;
+    if end <= start { return; }
+    let pages = ((end - start) / PAGE_SIZE as u64) as usize;
+    let _ = unsafe {
+        paging_allocator::protect_user_region_in(cr3, start, pages, true, false, false, false)
+    };
+}
 
-    let phoff = ehdr.e_phoff as usize;
-    let phnum = ehdr.e_phnum as usize;
+unsafe fn load_at(
+    data: &[u8], cr3: u64, base: u64, slide_pages: u64,
+) -> Result<ElfImage, &'static str> {
+    let ehdr = header(data)?;
 
     // ── Pass 1: map the full virtual range spanned by all PT_LOAD segments ───
     //
@@ -144,24 +312,12 @@ pub unsafe fn load_in(data: &[u8], cr3: u64) -> Result<u64, &'static str> {
     // unmapped.  Instead we find the total [min_va, max_va) range and map it
     // in one call.  All pages are mapped writable so Pass 2 can copy without
     // faulting (CR0.WP prevents supervisor writes to read-only pages).
-    {
-        let mut min_va = u64::MAX;
-        let mut max_va = 0u64;
-        for i in 0..phnum {
-            let ph_off = phoff + i * ph_size;
-            if ph_off + core::mem::size_of::<Elf64Phdr>() > data.len() { continue; }
-            let ph = unsafe { &*(data[ph_off..].as_ptr() as *const Elf64Phdr) };
-            if ph.p_type != PT_LOAD || ph.p_memsz == 0 { continue; }
-            let va_start = ph.p_vaddr & !0xFFF;
-            let va_end   = (ph.p_vaddr + ph.p_memsz + 0xFFF) & !0xFFF;
-            if va_start < min_va { min_va = va_start; }
-            if va_end   > max_va { max_va = va_end;   }
-        }
-        if min_va < max_va {
-            let npages = ((max_va - min_va) / PAGE_SIZE as u64) as usize;
-            paging_allocator::map_user_region_in(cr3, min_va, npages, true, true)
-                .map_err(|_| "OOM: ELF segments")?;
-        }
+    let (min_va, max_va) = load_span(data, ehdr);
+    let bias = choose_bias(ehdr, min_va, base, slide_pages);
+    if min_va < max_va {
+        let npages = ((max_va - min_va) / PAGE_SIZE as u64) as usize;
+        paging_allocator::map_user_region_in(cr3, min_va + bias, npages, true, true)
+            .map_err(|_| "OOM: ELF segments")?;
     }
 
     // ── Pass 2: copy file data via CR3 switch ────────────────────────────────
@@ -172,9 +328,7 @@ pub unsafe fn load_in(data: &[u8], cr3: u64) -> Result<u64, &'static str> {
         asm!("mov cr3, {}", in(reg) cr3,        options(nostack, nomem));
     }
 
-    for i in 0..phnum {
-        let ph_off = phoff + i * ph_size;
-        let ph = unsafe { &*(data[ph_off..].as_ptr() as *const Elf64Phdr) };
+    for ph in phdrs(data, ehdr) {
         if ph.p_type != PT_LOAD || ph.p_memsz == 0 { continue; }
 
         // Copy file image only (BSS tail is already zeroed by pre-zeroed frames).
@@ -188,7 +342,7 @@ pub unsafe fn load_in(data: &[u8], cr3: u64) -> Result<u64, &'static str> {
             unsafe {
                 core::ptr::copy_nonoverlapping(
                     data[src..].as_ptr(),
-                    ph.p_vaddr as *mut u8,
+                    (ph.p_vaddr + bias) as *mut u8,
                     ph.p_filesz as usize,
                 );
             }
@@ -196,5 +350,26 @@ pub unsafe fn load_in(data: &[u8], cr3: u64) -> Result<u64, &'static str> {
     }
 
     unsafe { asm!("mov cr3, {}", in(reg) saved_cr3, options(nostack, nomem)); }
-    Ok(ehdr.e_entry)
+
+    // ── Pass 3: apply segment permissions ────────────────────────────────────
+    // A page shared by two segments gets the union of their flags.
+    let mut va = min_va;
+    while va < max_va {
+        let (mut w, mut x) = (false, false);
+        for ph in phdrs(data, ehdr) {
+            if ph.p_type != PT_LOAD || ph.p_memsz == 0 { continue; }
+            let seg_start = ph.p_vaddr & !0xFFF;
+            let seg_end   = (ph.p_vaddr + ph.p_memsz + 0xFFF) & !0xFFF;
+            if va >= seg_start && va < seg_end {
+                w |= ph.p_flags & PF_W != 0;
+                x |= ph.p_flags & PF_X != 0;
+            }
+        }
+        let _ = unsafe {
+            paging_allocator::protect_user_region_in(cr3, va + bias, 1, true, w, x, false)
+        };
+        va += PAGE_SIZE as u64;
+    }
+
+    Ok(image(data, ehdr, bias, min_va, max_va))
 }
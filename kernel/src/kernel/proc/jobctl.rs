@@ -0,0 +1,221 @@
+//! Sessions, process groups and job control.
+//!
+//! Every task is in a process group and a session (`Task::pgrp`,
+//! `Task::session`).  Both are inherited across fork and kept across exec;
+//! tasks the kernel spawns lead their own.  `setsid` starts a new session,
+//! which has no controlling terminal until its leader opens one (without
+//! `O_NOCTTY`) or claims one with `TIOCSCTTY` — `tty` records which session
+//! owns each terminal and which of its groups is in the foreground.
+//!
+//! Stopping and continuing are the scheduler's (`TaskState::Stopped`); this
+//! module holds the rules around them: who may move into which group, which
+//! groups are orphaned — no member has a parent in another group of the same
+//! session, so no shell is left to continue them — and what a task's exit
+//! does to its terminal and to the groups it leaves orphaned.
+
+use crate::kernel::scheduler::{
+    self, SCHED, CURRENT_TASK_IDX, MAX_TASKS, TaskState, SigInfo, SIGHUP, SIGCONT,
+};
+use crate::kernel::tty;
+
+const EPERM: i64 = -1;
+const ESRCH: i64 = -3;
+const EINVAL: i64 = -22;
+
+/// Slot of the live task `pid`.
+unsafe fn find(pid: u8) -> Option<usize> {
+    let sched = &raw const SCHED;
+    (0..MAX_TASKS).find(|&i| unsafe { (*sched).tasks[i].is_live() && (*sched).tasks[i].pid == pid })
+}
+
+/// Slot named by a syscall's `pid` argument: 0 is the caller.
+unsafe fn target(pid: u32) -> Option<usize> {
+    match pid {
+        0 => Some(unsafe { CURRENT_TASK_IDX }),
+        p if p <= u8::MAX as u32 => unsafe { find(p as u8) },
+        _ => None,
+    }
+}
+
+/// Whether process group `pgrp` has a live member in session `sid`.
+pub unsafe fn group_in_session(pgrp: u8, sid: u8) -> bool {
+    let sched = &raw const SCHED;
+    (0..MAX_TASKS).any(|i| {
+        let t = unsafe { &(*sched).tasks[i] };
+        t.is_live() && t.pgrp() == pgrp && t.session() == sid
+    })
+}
+
+// ── Syscalls ──────────────────────────────────────────────────────────────
+
+/// `getpgid(pid)`.
+pub unsafe fn getpgid(pid: u32) -> i64 {
+    match unsafe { target(pid) } {
+        Some(i) => unsafe { (*(&raw const SCHED)).tasks[i].pgrp() as i64 },
+        None    => ESRCH,
+    }
+}
+
+/// `getsid(pid)`.
+pub unsafe fn getsid(pid: u32) -> i64 {
+    match unsafe { target(pid) } {
+        Some(i) => unsafe { (*(&raw const SCHED)).tasks[i].session() as i64 },
+        None    => ESRCH,
+    }
+}
+
+/// `setsid()`: make the caller the leader of a new session and of a new
+/// process group in it, with no controlling terminal.  A process group
+/// leader cannot, or its group would straddle two sessions.
+pub unsafe fn setsid() -> i64 {
+    let sched = &raw mut SCHED;
+    let me    = unsafe { &raw mut (*sched).tasks[CURRENT_TASK_IDX] };
+    let pid   = unsafe { (*me).pid };
+    let leader = (0..MAX_TASKS).any(|i| {
+        let t = unsafe { &(*sched).tasks[i] };
+        t.is_live() && t.pgrp() == pid
+    });
+    if leader { return EPERM; }
+    unsafe {
+        (*me).sid  = pid;
+        (*me).pgid = pid;
+    }
+    pid as i64
+}
+
+/// `setpgid(pid, pgid)`: move the caller or one of its children (0: the
+/// caller) into group `pgid` (0: a new group named after it).  The group
+/// must already exist in the caller's session unless it is the new one; a
+/// session leader cannot move, and children in other sessions cannot be
+/// moved.  (Linux also refuses children that have exec'd; that is not
+/// tracked here.)
+pub unsafe fn setpgid(pid: u32, pgid: u32) -> i64 {
+    if (pgid as i32) < 0 { return EINVAL; }
+    if pgid > u8::MAX as u32 { return EPERM; }
+    let sched = &raw mut SCHED;
+    let me    = unsafe { CURRENT_TASK_IDX };
+    let Some(idx) = (unsafe { target(pid) }) else { return ESRCH };
+    let (my_pid, my_sid) = unsafe { ((*sched).tasks[me].pid, (*sched).tasks[me].session()) };
+    let t = unsafe { &raw mut (*sched).tasks[idx] };
+    let (t_pid, t_ppid, t_sid) = unsafe { ((*t).pid, (*t).parent_pid, (*t).session()) };
+    if idx != me {
+        if t_ppid != my_pid { return ESRCH; }
+        if t_sid != my_sid  { return EPERM; }
+    }
+    if t_sid == t_pid { return EPERM; }
+    let pgid = if pgid == 0 { t_pid } else { pgid as u8 };
+    if pgid != t_pid && !unsafe { group_in_session(pgid, my_sid) } { return EPERM; }
+    unsafe { (*t).pgid = pgid; }
+    0
+}
+
+/// Whether the caller may `kill` the task at `idx`: its credentials allow
+/// it (`Credentials::may_signal`), or it is SIGCONT within the caller's
+/// session.
+pub unsafe fn may_kill(idx: usize, signum: u8) -> bool {
+    let tasks = unsafe { &(*(&raw const SCHED)).tasks };
+    let (me, t) = (&tasks[unsafe { CURRENT_TASK_IDX }], &tasks[idx]);
+    me.cred.may_signal(&t.cred) || (signum == SIGCONT && t.session() == me.session())
+}
+
+/// `kill(pid, signum)` beyond a single process: 0 signals the caller's
+/// group, -1 every process but init and the caller, and `-pgrp` a group.
+/// `sender` goes into the handlers' `siginfo_t`.  Targets the caller may
+/// not signal are skipped; EPERM if that leaves none.
+pub unsafe fn kill_many(pid: i32, signum: u8, sender: u32) -> i64 {
+    let tasks = unsafe { &(*(&raw const SCHED)).tasks };
+    let me    = &tasks[unsafe { CURRENT_TASK_IDX }];
+    let info  = SigInfo::user(sender, me.cred.ruid);
+    let pgrp  = match pid {
+        0  => Some(me.pgrp()),
+        -1 => None,
+        p => match u8::try_from(p.unsigned_abs()) {
+            Ok(g)  => Some(g),
+            Err(_) => return ESRCH,
+        },
+    };
+    let (mut found, mut any) = (false, false);
+    for (i, t) in tasks.iter().enumerate() {
+        if !t.is_live() { continue; }
+        let wanted = match pgrp {
+            Some(g) => t.pgrp() == g,
+            None    => t.pid != me.pid && t.pid != scheduler::INIT_PID,
+        };
+        if !wanted { continue; }
+        found = true;
+        if unsafe { may_kill(i, signum) } {
+            any |= unsafe { scheduler::send_signal_info(t.pid, signum, info) };
+        }
+    }
+    if any { 0 } else if found { EPERM } else { ESRCH }
+}
+
+// ── Orphaned process groups ───────────────────────────────────────────────
+
+/// Whether process group `pgrp` is orphaned.  A group with no live
+/// members counts as orphaned.
+pub unsafe fn orphaned(pgrp: u8) -> bool {
+    unsafe { orphaned_without(pgrp, 0) }
+}
+
+/// `orphaned`, as it will be once the task `gone` has exited and its
+/// children have gone to the reaper.
+unsafe fn orphaned_without(pgrp: u8, gone: u8) -> bool {
+    let sched  = &raw const SCHED;
+    let reaper = scheduler::reaper();
+    for i in 0..MAX_TASKS {
+        let t = unsafe { &(*sched).tasks[i] };
+        if !t.is_live() || t.pid == gone || t.pgrp() != pgrp { continue; }
+        let ppid = if t.parent_pid == gone { reaper } else { t.parent_pid };
+        if let Some(p) = unsafe { find(ppid) } {
+            let parent = unsafe { &(*sched).tasks[p] };
+            if parent.pgrp() != pgrp && parent.session() == t.session() { return false; }
+        }
+    }
+    true
+}
+
+/// Whether a live member of `pgrp` is stopped.
+unsafe fn has_stopped(pgrp: u8) -> bool {
+    let sched = &raw const SCHED;
+    (0..MAX_TASKS).any(|i| {
+        let t = unsafe { &(*sched).tasks[i] };
+        t.is_live() && t.pgrp() == pgrp && t.state == TaskState::Stopped
+    })
+}
+
+// ── Exit ──────────────────────────────────────────────────────────────────
+
+/// The task at `idx` is exiting (already `Dead`, children not yet handed
+/// on).  A session leader takes its controlling terminal with it.  Every
+/// group the exit orphans — the task's own, or a child's — that has a
+/// stopped member is sent SIGHUP and then SIGCONT, as POSIX requires, so
+/// it does not stay stopped forever.
+pub unsafe fn on_exit(idx: usize) {
+    let sched = &raw const SCHED;
+    let t     = unsafe { &(*sched).tasks[idx] };
+    let pid   = t.pid;
+    if t.session() == pid {
+        unsafe { tty::session_ended(pid); }
+    }
+
+    let mut groups = [0u8; MAX_TASKS + 1];
+    let mut n = 0;
+    groups[n] = t.pgrp();
+    n += 1;
+    for i in 0..MAX_TASKS {
+        let c = unsafe { &(*sched).tasks[i] };
+        if c.is_live() && c.parent_pid == pid && !groups[..n].contains(&c.pgrp()) {
+            groups[n] = c.pgrp();
+            n += 1;
+        }
+    }
+    for &g in &groups[..n] {
+        if unsafe { has_stopped(g) && orphaned_without(g, pid) } {
+            unsafe {
+                scheduler::signal_group(g, SIGHUP);
+                scheduler::signal_group(g, SIGCONT);
+            }
+        }
+    }
+}
@@ -1,7 +1,13 @@
-//! Process management: scheduling, ELF loading, user mode, env, TTY.
+//! Process management: scheduling, ELF loading, user mode, env, TTY, ptys, core dumps, ptrace, timers, credentials, job control.
 pub mod scheduler;
 pub mod elf_loader;
 pub mod user_mode;
 pub mod programs;
 pub mod env;
 pub mod tty;
+pub mod pty;
+pub mod coredump;
+pub mod ptrace;
+pub mod timers;
+pub mod cred;
+pub mod jobctl;
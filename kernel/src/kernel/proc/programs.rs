@@ -152,6 +152,10 @@ pub static FALSE: &[u8] =
 pub static FORKTEST: &[u8] =
     include_bytes!("../../../../userspace/bin/forktest.elf");
 
+/// strace — traces a program's syscalls and signals with ptrace.
+pub static STRACE: &[u8] =
+    include_bytes!("../../../../userspace/bin/strace.elf");
+
 /// hello_c — "Hello from C on OxideOS!" compiled from C with gcc (Linux syscall ABI).
 pub static HELLO_C: &[u8] =
     include_bytes!("../../../../userspace/bin/hello_c.elf");
@@ -183,6 +187,50 @@ pub static BUSYBOX: &[u8] =
 pub static BASH: &[u8] =
     include_bytes!("../../../../userspace/bin/bash.elf");
 
+/// login — prompt for a user name and password and start their shell.
+pub static LOGIN: &[u8] =
+    include_bytes!("../../../../userspace/bin/login.elf");
+
+/// su — run a shell as another user after checking their password.
+pub static SU: &[u8] =
+    include_bytes!("../../../../userspace/bin/su.elf");
+
+/// id — print the real and effective user and group ids.
+pub static ID: &[u8] =
+    include_bytes!("../../../../userspace/bin/id.elf");
+
+/// init — PID 1: runs /etc/rc.d, supervises services, shuts the system down.
+pub static INIT: &[u8] =
+    include_bytes!("../../../../userspace/bin/init.elf");
+
+/// getty — open a terminal line and run login on it.
+pub static GETTY: &[u8] =
+    include_bytes!("../../../../userspace/bin/getty.elf");
+
+/// netd — follow the DHCP lease and keep /etc/resolv.conf current.
+pub static NETD: &[u8] =
+    include_bytes!("../../../../userspace/bin/netd.elf");
+
+/// session — desktop session that starts programs for the GUI.
+pub static SESSION: &[u8] =
+    include_bytes!("../../../../userspace/bin/session.elf");
+
+/// evtest — print the events of an input device.
+pub static EVTEST: &[u8] =
+    include_bytes!("../../../../userspace/bin/evtest.elf");
+
+/// loadkeys — show or change the keyboard layout.
+pub static LOADKEYS: &[u8] =
+    include_bytes!("../../../../userspace/bin/loadkeys.elf");
+
+/// play — play a WAV file through /dev/dsp.
+pub static PLAY: &[u8] =
+    include_bytes!("../../../../userspace/bin/play.elf");
+
+/// beep — sound the PC speaker.
+pub static BEEP: &[u8] =
+    include_bytes!("../../../../userspace/bin/beep.elf");
+
 /// sysmon — GUI system monitor showing memory, uptime, and process count.
 pub static SYSMON: &[u8] =
     include_bytes!("../../../../userspace/bin/sysmon.elf");
@@ -230,6 +278,18 @@ pub fn find(name: &str) -> Option<&'static [u8]> {
         "true"    => Some(TRUE),
         "false"   => Some(FALSE),
         "forktest" => Some(FORKTEST),
+        "strace"   => Some(STRACE),
+        "login"    => Some(LOGIN),
+        "su"       => Some(SU),
+        "id"       => Some(ID),
+        "init"     => Some(INIT),
+        "getty"    => Some(GETTY),
+        "netd"     => Some(NETD),
+        "session"  => Some(SESSION),
+        "evtest"   => Some(EVTEST),
+        "loadkeys" => Some(LOADKEYS),
+        "play"     => Some(PLAY),
+        "beep"     => Some(BEEP),
         "hello_c" => Some(HELLO_C),
         "install"    => Some(INSTALL),
         "hello_musl" => Some(HELLO_MUSL),
@@ -246,6 +306,15 @@ pub fn find(name: &str) -> Option<&'static [u8]> {
     }
 }
 
+/// Mode bits of a built-in program, all owned by root:root.  `su` is
+/// set-uid so it can switch to the target user.
+pub fn mode(name: &str) -> u16 {
+    match name {
+        "su" => 0o4755,
+        _    => 0o755,
+    }
+}
+
 /// List of available program names (shown by `run` with no arguments).
 pub const NAMES: &[&str] = &[
     "hello", "counter", "sysinfo", "input",
@@ -254,7 +323,8 @@ pub const NAMES: &[&str] = &[
     "ls", "cat", "ps", "cp", "mkdir", "pwd", "wget", "edit", "nc", "rm", "mv",
     "filemanager",
     "echo", "grep", "wc", "head", "tail", "sort", "sleep", "kill", "touch",
-    "true", "false", "forktest",
+    "true", "false", "forktest", "strace", "login", "su", "id",
+    "init", "getty", "netd", "session", "evtest", "loadkeys", "play", "beep",
     "hello_c",
     "install",
     "hello_musl",
@@ -0,0 +1,607 @@
+// src/kernel/proc/ptrace.rs
+//! Process tracing (`ptrace`) for debuggers and `strace`.
+//!
+//! # Stops
+//!
+//! A traced task (the *tracee*) parks in [`TaskState::Traced`] whenever
+//! something happens that its tracer asked to see:
+//!
+//! - **signal-delivery-stop** — any signal but SIGKILL is about to be
+//!   delivered.  The `data` argument of the resuming request decides whether
+//!   it is delivered after all (the signal number) or suppressed (0).
+//! - **syscall-stop** — on entry to and exit from each syscall after
+//!   `PTRACE_SYSCALL`.  An entry stop rewinds the task to its `syscall` /
+//!   `int 0x80` instruction, so the call re-executes with whatever registers
+//!   the tracer left and only then runs.  A syscall that blocks reports its
+//!   exit stop when the scheduler next picks the task.
+//! - **single-step** — `PTRACE_SINGLESTEP` resumes with RFLAGS.TF set; the
+//!   #DB after one instruction arrives as a SIGTRAP signal-delivery-stop.
+//! - **exec** — a successful exec queues SIGTRAP, or stops with
+//!   `PTRACE_EVENT_EXEC` under `PTRACE_O_TRACEEXEC`.
+//!
+//! Every stop leaves a `wait4` status `(sig << 8) | 0x7f` for the tracer;
+//! syscall stops report SIGTRAP, or `SIGTRAP | 0x80` with
+//! `PTRACE_O_TRACESYSGOOD`.
+//!
+//! # Registers
+//!
+//! `PTRACE_GETREGS`/`SETREGS` and `PEEKUSER`/`POKEUSER` use Linux's
+//! `struct user_regs_struct`.  At a syscall-entry-stop `rax` reads as
+//! `-ENOSYS` and `orig_rax` holds the syscall number; writing `orig_rax`
+//! there changes which syscall runs.  `PTRACE_GET_SYSCALL_INFO` tells
+//! entry and exit stops apart without guessing from `rax`.
+
+use crate::kernel::paging_allocator as pa;
+use crate::kernel::scheduler::{
+    SCHED, CURRENT_TASK_IDX, MAX_TASKS, NSIG, EXIT_SLEEPING, Task, TaskState, SigInfo,
+    UserSigInfo, SIGKILL, SIGSTOP, SIGTRAP, SIGCHLD, SYSCALL_INSN_LEN, USER_RFLAGS_MASK,
+    RFLAGS_IF, RFLAGS_TF, USER_CS, USER_SS, INIT_PID, send_signal,
+};
+use crate::kernel::syscall_core::validate_user_range;
+use crate::kernel::user_mode::{self, TaskContext, CURRENT_SYSCALL_CTX};
+
+// ── Requests and options (Linux ABI) ───────────────────────────────────────
+//
+// Defined in syscall_core, which refuses unknown requests and malformed
+// `addr`/`data` before `request` runs.
+
+pub use crate::kernel::syscall_core::{
+    PTRACE_TRACEME, PTRACE_PEEKTEXT, PTRACE_PEEKDATA, PTRACE_PEEKUSER, PTRACE_POKETEXT,
+    PTRACE_POKEDATA, PTRACE_POKEUSER, PTRACE_CONT, PTRACE_KILL, PTRACE_SINGLESTEP,
+    PTRACE_GETREGS, PTRACE_SETREGS, PTRACE_ATTACH, PTRACE_DETACH, PTRACE_SYSCALL,
+    PTRACE_SETOPTIONS, PTRACE_GETEVENTMSG, PTRACE_GETSIGINFO, PTRACE_GET_SYSCALL_INFO,
+    PTRACE_O_TRACESYSGOOD, PTRACE_O_TRACEEXEC, PTRACE_O_EXITKILL,
+};
+
+pub const PTRACE_EVENT_EXEC: u32 = 4;
+
+// `struct ptrace_syscall_info` ops: which kind of stop the tracee is in.
+pub const PTRACE_SYSCALL_INFO_NONE:  u8 = 0;
+pub const PTRACE_SYSCALL_INFO_ENTRY: u8 = 1;
+pub const PTRACE_SYSCALL_INFO_EXIT:  u8 = 2;
+const AUDIT_ARCH_X86_64: u32 = 0xC000_003E;
+
+const USER_REGS_ORIG_RAX: usize = 15;
+
+const EPERM:  i64 = -1;
+const ESRCH:  i64 = -3;
+const EIO:    i64 = -5;
+const EFAULT: i64 = -14;
+const ENOSYS: i64 = -38;
+
+/// End of the canonical lower half; user RIP/RSP/FS base must stay below.
+const USER_ADDR_END: u64 = 0x0000_8000_0000_0000;
+
+// ── Per-task state ─────────────────────────────────────────────────────────
+
+/// How the tracer last resumed the tracee.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Resume {
+    /// Run until the next signal (`PTRACE_CONT`).
+    Cont,
+    /// Also stop at syscall entry and exit (`PTRACE_SYSCALL`).
+    Syscall,
+    /// Stop after one instruction (`PTRACE_SINGLESTEP`).
+    Step,
+}
+
+/// Where a traced task is in its current syscall.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SyscallPhase {
+    Idle,
+    /// Stopped at syscall-entry; the call re-executes on resume.
+    Entry(u64),
+    /// Running syscall `nr`; its exit is reported on return.
+    Running(u64),
+}
+
+#[derive(Clone, Copy)]
+pub struct TraceState {
+    /// PID of the tracer; 0 = not traced.
+    pub tracer:    u8,
+    pub options:   u32,
+    pub resume:    Resume,
+    pub phase:     SyscallPhase,
+    /// `orig_rax` as the tracer sees it (-1 outside syscall stops).
+    pub orig_rax:  u64,
+    /// `wait4` status of the current stop, until the tracer collects it.
+    pub report:    u32,
+    /// Signal (and its details) that caused the current stop.
+    pub stop_sig:  u8,
+    pub stop_info: SigInfo,
+    /// `PTRACE_SYSCALL_INFO_*` kind of the current stop.
+    pub syscall_op: u8,
+    /// Signals the tracer chose to deliver; they do not stop again.
+    pub bypass:    u32,
+    /// `PTRACE_GETEVENTMSG` value for the last event stop.
+    pub event_msg: u64,
+}
+
+impl TraceState {
+    pub const NONE: Self = Self {
+        tracer:    0,
+        options:   0,
+        resume:    Resume::Cont,
+        phase:     SyscallPhase::Idle,
+        orig_rax:  u64::MAX,
+        report:    0,
+        stop_sig:  0,
+        stop_info: SigInfo::EMPTY,
+        syscall_op: PTRACE_SYSCALL_INFO_NONE,
+        bypass:    0,
+        event_msg: 0,
+    };
+
+    const fn attached(tracer: u8) -> Self {
+        Self { tracer, ..Self::NONE }
+    }
+
+    pub fn is_traced(&self) -> bool { self.tracer != 0 }
+}
+
+// ── Stops ──────────────────────────────────────────────────────────────────
+
+/// Park the tracee at `idx` with wait status `status` and tell its tracer.
+unsafe fn enter_stop(idx: usize, signum: u8, info: SigInfo, status: u32) {
+    let task = unsafe { &mut (*(&raw mut SCHED)).tasks[idx] };
+    task.state           = TaskState::Traced;
+    task.trace.report    = status;
+    task.trace.stop_sig  = signum;
+    task.trace.stop_info = info;
+    task.trace.syscall_op = PTRACE_SYSCALL_INFO_NONE;
+    unsafe { send_signal(task.trace.tracer, SIGCHLD); }
+}
+
+/// Wait status for a syscall stop, honouring `PTRACE_O_TRACESYSGOOD`.
+fn syscall_stop_sig(trace: &TraceState) -> u32 {
+    SIGTRAP as u32 | if trace.options & PTRACE_O_TRACESYSGOOD != 0 { 0x80 } else { 0 }
+}
+
+/// Park the tracee at `idx` in a syscall-entry or -exit stop (`op`).
+unsafe fn enter_syscall_stop(idx: usize, op: u8) {
+    let trace = unsafe { &raw mut (*(&raw mut SCHED)).tasks[idx].trace };
+    let sig  = syscall_stop_sig(unsafe { &*trace });
+    let info = SigInfo { code: sig as i32, ..SigInfo::EMPTY };
+    unsafe {
+        enter_stop(idx, SIGTRAP, info, sig << 8 | 0x7f);
+        (*trace).syscall_op = op;
+    }
+}
+
+/// Stop the running task at a syscall boundary with registers `ctx` and
+/// return to the scheduler.
+unsafe fn syscall_stop(idx: usize, ctx: TaskContext, op: u8) -> ! {
+    unsafe {
+        (*(&raw mut SCHED)).tasks[idx].ctx = ctx;
+        enter_syscall_stop(idx, op);
+        user_mode::exit_to_kernel(EXIT_SLEEPING)
+    }
+}
+
+/// Syscall-entry hook, called before every syscall is dispatched.
+pub unsafe fn syscall_enter(nr: u64) {
+    if !user_mode::is_active() { return; }
+    let idx   = unsafe { CURRENT_TASK_IDX };
+    let trace = unsafe { &mut (*(&raw mut SCHED)).tasks[idx].trace };
+    if !trace.is_traced() { return; }
+
+    match trace.phase {
+        // Re-executing after the entry stop (or a restart): run it now.
+        SyscallPhase::Entry(_) | SyscallPhase::Running(_) => {
+            trace.phase = SyscallPhase::Running(nr);
+            return;
+        }
+        SyscallPhase::Idle if trace.resume != Resume::Syscall => return,
+        SyscallPhase::Idle => {}
+    }
+    let Some(mut ctx) = (unsafe { CURRENT_SYSCALL_CTX }) else { return };
+    trace.phase    = SyscallPhase::Entry(nr);
+    trace.orig_rax = nr;
+    ctx.rip -= SYSCALL_INSN_LEN;
+    ctx.rax  = nr;
+    unsafe { syscall_stop(idx, ctx, PTRACE_SYSCALL_INFO_ENTRY) }
+}
+
+/// Syscall-exit hook, called with the result of a syscall that returned.
+pub unsafe fn syscall_exit(result: i64) {
+    if !user_mode::is_active() { return; }
+    let idx   = unsafe { CURRENT_TASK_IDX };
+    let trace = unsafe { &mut (*(&raw mut SCHED)).tasks[idx].trace };
+    if !matches!(trace.phase, SyscallPhase::Running(_)) { return; }
+    trace.phase = SyscallPhase::Idle;
+    if trace.resume != Resume::Syscall {
+        trace.orig_rax = u64::MAX;
+        return;
+    }
+    let Some(mut ctx) = (unsafe { CURRENT_SYSCALL_CTX }) else { return };
+    ctx.rax = result as u64;
+    unsafe { syscall_stop(idx, ctx, PTRACE_SYSCALL_INFO_EXIT) }
+}
+
+/// Report the exit of a syscall the task at `idx` blocked in (sleep, wait,
+/// msgrcv, sigreturn).  Called by the scheduler just before the task runs;
+/// its saved `rax` already holds the result.  Returns `true` if it stopped.
+pub unsafe fn resume_check(idx: usize) -> bool {
+    let trace = unsafe { &mut (*(&raw mut SCHED)).tasks[idx].trace };
+    if !matches!(trace.phase, SyscallPhase::Running(_)) { return false; }
+    trace.phase = SyscallPhase::Idle;
+    if trace.resume != Resume::Syscall {
+        trace.orig_rax = u64::MAX;
+        return false;
+    }
+    unsafe { enter_syscall_stop(idx, PTRACE_SYSCALL_INFO_EXIT); }
+    true
+}
+
+/// Forget the syscall in progress: it is being restarted from scratch or
+/// was interrupted, so no exit stop is reported for it.
+pub unsafe fn syscall_abandon(idx: usize) {
+    unsafe { (*(&raw mut SCHED)).tasks[idx].trace.phase = SyscallPhase::Idle; }
+}
+
+/// Signal-delivery hook: called by the scheduler with a signal it is about
+/// to act on.  Returns `true` if the task stopped instead; the signal is
+/// then consumed unless the tracer hands it back on resume.
+pub unsafe fn signal_stop(idx: usize, signum: u8, info: &SigInfo) -> bool {
+    let trace = unsafe { &mut (*(&raw mut SCHED)).tasks[idx].trace };
+    if !trace.is_traced() || signum == SIGKILL { return false; }
+    let bit = 1u32 << (signum as u32 - 1);
+    if trace.bypass & bit != 0 {
+        trace.bypass &= !bit;
+        return false;
+    }
+    unsafe { enter_stop(idx, signum, *info, (signum as u32) << 8 | 0x7f); }
+    true
+}
+
+/// Exec hook, called once the new image is installed in the task at `idx`.
+/// Gives the image a register context so it can stop before its first
+/// instruction.  Returns `true` if the task is now in an exec event stop.
+pub unsafe fn on_exec(idx: usize) -> bool {
+    let task = unsafe { &mut (*(&raw mut SCHED)).tasks[idx] };
+    if !task.trace.is_traced() { return false; }
+
+    let mut ctx = TaskContext::zeroed();
+    ctx.rip    = task.entry;
+    ctx.rsp    = task.initial_rsp;
+    ctx.rflags = RFLAGS_IF | 0x2;
+    ctx.cs     = USER_CS;
+    ctx.ss     = USER_SS;
+    task.ctx       = ctx;
+    task.first_run = false;
+    task.trace.phase    = SyscallPhase::Idle;
+    task.trace.orig_rax = u64::MAX;
+
+    if task.trace.options & PTRACE_O_TRACEEXEC != 0 {
+        task.trace.event_msg = task.pid as u64;
+        let status = (SIGTRAP as u32 | PTRACE_EVENT_EXEC << 8) << 8 | 0x7f;
+        unsafe { enter_stop(idx, SIGTRAP, SigInfo::user(task.pid as u32, task.cred.ruid), status); }
+        return true;
+    }
+    task.pending_signals |= 1u32 << (SIGTRAP as u32 - 1);
+    task.signal_info[SIGTRAP as usize] = SigInfo::user(task.pid as u32, task.cred.ruid);
+    false
+}
+
+/// Exit hook for the task at `idx`: its tracees are released (or killed
+/// under `PTRACE_O_EXITKILL`) and its own tracer is notified.
+pub unsafe fn on_exit(idx: usize) {
+    let sched = &raw mut SCHED;
+    let pid   = unsafe { (*sched).tasks[idx].pid };
+    for i in 0..MAX_TASKS {
+        let t = unsafe { &mut (*sched).tasks[i] };
+        if i == idx || t.trace.tracer != pid { continue; }
+        if matches!(t.state, TaskState::Empty | TaskState::Dead(_)) {
+            t.trace = TraceState::NONE;
+            continue;
+        }
+        if t.trace.options & PTRACE_O_EXITKILL != 0 {
+            unsafe { send_signal(t.pid, SIGKILL); }
+            continue;
+        }
+        unsafe { detach(i); }
+    }
+    let (tracer, parent) = unsafe { ((*sched).tasks[idx].trace.tracer, (*sched).tasks[idx].parent_pid) };
+    if tracer != 0 && tracer != parent {
+        unsafe { send_signal(tracer, SIGCHLD); }
+    }
+}
+
+/// Drop the trace on the task at `idx` and let it run freely.
+unsafe fn detach(idx: usize) {
+    let task = unsafe { &mut (*(&raw mut SCHED)).tasks[idx] };
+    task.trace = TraceState::NONE;
+    task.ctx.rflags &= !RFLAGS_TF;
+    if task.state == TaskState::Traced {
+        task.state = TaskState::Ready;
+    }
+}
+
+// ── Requests ───────────────────────────────────────────────────────────────
+
+/// Live task with `pid`, if any.
+pub unsafe fn find_task(pid: u32) -> Option<usize> {
+    let tasks = unsafe { &(*(&raw const SCHED)).tasks };
+    (0..MAX_TASKS).find(|&i| {
+        tasks[i].pid as u32 == pid && !matches!(tasks[i].state, TaskState::Empty | TaskState::Dead(_))
+    })
+}
+
+/// Whether the running task may attach to the task at `idx`.  Never init.
+/// Root may trace anything else; other tasks need their real uid and gid
+/// to equal the target's real, effective and saved ones, and the target
+/// must be dumpable (no credential change since its exec).  A task that
+/// traces itself into a set-id image is covered by exec, which ignores the
+/// set-id bits of traced tasks.
+pub unsafe fn may_attach(idx: usize) -> bool {
+    let tasks = unsafe { &(*(&raw const SCHED)).tasks };
+    let (me, t) = (&tasks[unsafe { CURRENT_TASK_IDX }].cred, &tasks[idx]);
+    if t.pid == INIT_PID { return false; }
+    if me.privileged() { return true; }
+    let c = &t.cred;
+    t.dumpable
+        && [c.ruid, c.euid, c.suid].iter().all(|&u| u == me.ruid)
+        && [c.rgid, c.egid, c.sgid].iter().all(|&g| g == me.rgid)
+}
+
+/// `ptrace(request, pid, addr, data)` on behalf of the running task.
+pub unsafe fn request(req: i64, pid: u32, addr: u64, data: u64) -> i64 {
+    let sched = &raw mut SCHED;
+    let me    = unsafe { CURRENT_TASK_IDX };
+    let my_pid = unsafe { (*sched).tasks[me].pid };
+
+    match req {
+        PTRACE_TRACEME => {
+            let task = unsafe { &mut (*sched).tasks[me] };
+            if task.trace.is_traced() || task.parent_pid == 0 { return EPERM; }
+            task.trace = TraceState::attached(task.parent_pid);
+            return 0;
+        }
+        PTRACE_ATTACH => {
+            let Some(idx) = (unsafe { find_task(pid) }) else { return ESRCH };
+            if idx == me || unsafe { (*sched).tasks[idx].trace.is_traced() } { return EPERM; }
+            if !unsafe { may_attach(idx) } { return EPERM; }
+            unsafe { (*sched).tasks[idx].trace = TraceState::attached(my_pid); }
+            unsafe { send_signal(pid as u8, SIGSTOP); }
+            return 0;
+        }
+        _ => {}
+    }
+
+    // Everything else acts on a tracee of ours, which must be stopped
+    // (PTRACE_KILL excepted).
+    let Some(idx) = (unsafe { find_task(pid) }) else { return ESRCH };
+    let task = unsafe { &mut (*sched).tasks[idx] };
+    if task.trace.tracer != my_pid { return ESRCH; }
+    if req == PTRACE_KILL {
+        unsafe { kill_tracee(idx); }
+        return 0;
+    }
+    if task.state != TaskState::Traced { return ESRCH; }
+
+    match req {
+        PTRACE_PEEKTEXT | PTRACE_PEEKDATA => {
+            let mut word = [0u8; 8];
+            if !unsafe { read_tracee(task.cr3, addr, &mut word) } { return EIO; }
+            unsafe { put_u64(data, u64::from_le_bytes(word)) }
+        }
+        PTRACE_POKETEXT | PTRACE_POKEDATA => {
+            if unsafe { write_tracee(task.cr3, addr, &data.to_le_bytes()) } { 0 } else { EIO }
+        }
+        PTRACE_PEEKUSER => {
+            let regs = get_regs(task);
+            unsafe { put_u64(data, regs[addr as usize / 8]) }
+        }
+        PTRACE_POKEUSER => {
+            let mut regs = get_regs(task);
+            regs[addr as usize / 8] = data;
+            set_regs(task, &regs)
+        }
+        PTRACE_GETREGS => {
+            let regs = get_regs(task);
+            unsafe { core::ptr::write_unaligned(data as *mut [u64; 27], regs); }
+            0
+        }
+        PTRACE_SETREGS => {
+            let regs = unsafe { core::ptr::read_unaligned(data as *const [u64; 27]) };
+            set_regs(task, &regs)
+        }
+        PTRACE_GETSIGINFO => {
+            let si = UserSigInfo::new(task.trace.stop_sig, &task.trace.stop_info);
+            unsafe { core::ptr::write_unaligned(data as *mut UserSigInfo, si); }
+            0
+        }
+        PTRACE_GET_SYSCALL_INFO => unsafe { get_syscall_info(task, addr, data) },
+        PTRACE_SETOPTIONS => {
+            task.trace.options = data as u32;
+            0
+        }
+        PTRACE_GETEVENTMSG => unsafe { put_u64(data, task.trace.event_msg) },
+        PTRACE_CONT       => unsafe { resume(idx, Resume::Cont, data) },
+        PTRACE_SYSCALL    => unsafe { resume(idx, Resume::Syscall, data) },
+        PTRACE_SINGLESTEP => unsafe { resume(idx, Resume::Step, data) },
+        PTRACE_DETACH => {
+            let r = unsafe { resume(idx, Resume::Cont, data) };
+            if r == 0 {
+                // An injected signal is delivered normally once untraced.
+                unsafe { detach(idx); }
+            }
+            r
+        }
+        _ => EIO,
+    }
+}
+
+/// Restart a stopped tracee in `mode`, delivering signal `sig` (0 = none).
+unsafe fn resume(idx: usize, mode: Resume, sig: u64) -> i64 {
+    if sig as usize >= NSIG { return EIO; }
+    let task = unsafe { &mut (*(&raw mut SCHED)).tasks[idx] };
+    if sig != 0 {
+        let bit = 1u32 << (sig as u32 - 1);
+        task.pending_signals |= bit;
+        task.trace.bypass    |= bit;
+        task.signal_info[sig as usize] = if sig as u8 == task.trace.stop_sig {
+            task.trace.stop_info
+        } else {
+            SigInfo::user(task.trace.tracer as u32, crate::kernel::cred::get().ruid)
+        };
+    }
+    if mode == Resume::Step {
+        task.ctx.rflags |= RFLAGS_TF;
+    } else {
+        task.ctx.rflags &= !RFLAGS_TF;
+    }
+    if task.trace.phase == SyscallPhase::Idle {
+        task.trace.orig_rax = u64::MAX;
+    }
+    task.trace.resume   = mode;
+    task.trace.report   = 0;
+    task.trace.stop_sig = 0;
+    task.trace.syscall_op = PTRACE_SYSCALL_INFO_NONE;
+    task.state = TaskState::Ready;
+    0
+}
+
+/// `PTRACE_KILL`: a stopped tracee is resumed so the scheduler reaps it.
+unsafe fn kill_tracee(idx: usize) {
+    let task = unsafe { &mut (*(&raw mut SCHED)).tasks[idx] };
+    if task.state == TaskState::Traced {
+        task.pending_signals |= 1u32 << (SIGKILL as u32 - 1);
+        task.state = TaskState::Ready;
+    } else {
+        unsafe { send_signal(task.pid, SIGKILL); }
+    }
+}
+
+/// `PTRACE_GET_SYSCALL_INFO`: copy up to `size` bytes of the tracee's
+/// `struct ptrace_syscall_info` to `ptr`; returns the full size.
+unsafe fn get_syscall_info(task: &Task, size: u64, ptr: u64) -> i64 {
+    let c  = &task.ctx;
+    let op = task.trace.syscall_op;
+    let mut info = [0u64; 10];
+    info[0] = op as u64 | (AUDIT_ARCH_X86_64 as u64) << 32;
+    info[1] = if op == PTRACE_SYSCALL_INFO_ENTRY { c.rip + SYSCALL_INSN_LEN } else { c.rip };
+    info[2] = c.rsp;
+    let len = match op {
+        PTRACE_SYSCALL_INFO_ENTRY => {
+            info[3] = task.trace.orig_rax;
+            info[4..10].copy_from_slice(&[c.rdi, c.rsi, c.rdx, c.r10, c.r8, c.r9]);
+            80
+        }
+        PTRACE_SYSCALL_INFO_EXIT => {
+            info[3] = c.rax;
+            info[4] = ((c.rax as i64) < 0 && (c.rax as i64) >= -4095) as u64;
+            33
+        }
+        _ => 24,
+    };
+    let n = (size as usize).min(len);
+    if n == 0 { return len as i64; }
+    if validate_user_range(ptr, n as u64).is_err() { return EFAULT; }
+    unsafe {
+        core::ptr::copy_nonoverlapping(info.as_ptr() as *const u8, ptr as *mut u8, n);
+    }
+    len as i64
+}
+
+unsafe fn put_u64(ptr: u64, value: u64) -> i64 {
+    if validate_user_range(ptr, 8).is_err() { return EFAULT; }
+    unsafe { core::ptr::write_unaligned(ptr as *mut u64, value); }
+    0
+}
+
+// ── Registers ──────────────────────────────────────────────────────────────
+
+/// The tracee's registers as a `user_regs_struct`.
+fn get_regs(task: &Task) -> [u64; 27] {
+    let c = &task.ctx;
+    let (rax, rip) = match task.trace.phase {
+        // The task sits on its syscall instruction with rax = nr; show it
+        // the way Linux does, just past the instruction with -ENOSYS.
+        SyscallPhase::Entry(_) => (ENOSYS as u64, c.rip + SYSCALL_INSN_LEN),
+        _ => (c.rax, c.rip),
+    };
+    [
+        c.r15, c.r14, c.r13, c.r12, c.rbp, c.rbx, c.r11, c.r10,
+        c.r9, c.r8, rax, c.rcx, c.rdx, c.rsi, c.rdi,
+        task.trace.orig_rax,
+        rip, c.cs, c.rflags, c.rsp, c.ss,
+        task.fs_base, 0,                 // fs_base, gs_base
+        0, 0, 0, 0,                      // ds, es, fs, gs
+    ]
+}
+
+/// Load a `user_regs_struct` into the tracee.  Segment selectors and the
+/// privileged RFLAGS bits are kept; non-canonical addresses are refused.
+fn set_regs(task: &mut Task, r: &[u64; 27]) -> i64 {
+    let (rip, rsp, fs_base) = (r[16], r[19], r[21]);
+    if rip >= USER_ADDR_END || rsp >= USER_ADDR_END || fs_base >= USER_ADDR_END {
+        return EIO;
+    }
+    let c = &mut task.ctx;
+    c.r15 = r[0];  c.r14 = r[1];  c.r13 = r[2];  c.r12 = r[3];
+    c.rbp = r[4];  c.rbx = r[5];  c.r11 = r[6];  c.r10 = r[7];
+    c.r9  = r[8];  c.r8  = r[9];  c.rax = r[10]; c.rcx = r[11];
+    c.rdx = r[12]; c.rsi = r[13]; c.rdi = r[14];
+    c.rip = rip;
+    c.rsp = rsp;
+    c.rflags = (c.rflags & !USER_RFLAGS_MASK) | (r[18] & USER_RFLAGS_MASK);
+    task.trace.orig_rax = r[USER_REGS_ORIG_RAX];
+    if let SyscallPhase::Entry(_) = task.trace.phase {
+        // Re-executing the syscall instruction runs `orig_rax`.
+        c.rip = rip.wrapping_sub(SYSCALL_INSN_LEN);
+        c.rax = r[USER_REGS_ORIG_RAX];
+        task.trace.phase = SyscallPhase::Entry(c.rax);
+    }
+    task.fs_base = fs_base;
+    0
+}
+
+// ── Tracee memory ──────────────────────────────────────────────────────────
+
+fn user_span(addr: u64, len: usize) -> bool {
+    addr >= 0x1000 && addr.checked_add(len as u64).is_some_and(|end| end <= USER_ADDR_END)
+}
+
+/// Read `buf.len()` bytes at `addr` in the address space `cr3`.
+unsafe fn read_tracee(cr3: u64, addr: u64, buf: &mut [u8]) -> bool {
+    if !user_span(addr, buf.len()) || !unsafe { pa::user_range_mapped_in(cr3, addr, buf.len()) } {
+        return false;
+    }
+    let hhdm = pa::get_hhdm_offset();
+    let mut done = 0usize;
+    while done < buf.len() {
+        let va    = addr + done as u64;
+        let off   = (va & 0xFFF) as usize;
+        let chunk = (4096 - off).min(buf.len() - done);
+        let Some(phys) = (unsafe { pa::user_page_phys_in(cr3, va) }) else { return false };
+        unsafe {
+            core::ptr::copy_nonoverlapping(
+                (phys + hhdm + off as u64) as *const u8, buf[done..].as_mut_ptr(), chunk);
+        }
+        done += chunk;
+    }
+    true
+}
+
+/// Write `bytes` at `addr` in the address space `cr3`, read-only text
+/// included (breakpoints); shared frames are copied first.
+unsafe fn write_tracee(cr3: u64, addr: u64, bytes: &[u8]) -> bool {
+    if !user_span(addr, bytes.len()) || !unsafe { pa::user_range_mapped_in(cr3, addr, bytes.len()) } {
+        return false;
+    }
+    let hhdm = pa::get_hhdm_offset();
+    let mut done = 0usize;
+    while done < bytes.len() {
+        let va    = addr + done as u64;
+        let off   = (va & 0xFFF) as usize;
+        let chunk = (4096 - off).min(bytes.len() - done);
+        let Some(phys) = (unsafe { pa::private_user_page_in(cr3, va) }) else { return false };
+        unsafe {
+            core::ptr::copy_nonoverlapping(
+                bytes[done..].as_ptr(), (phys + hhdm + off as u64) as *mut u8, chunk);
+        }
+        done += chunk;
+    }
+    true
+}
@@ -0,0 +1,268 @@
+//! Pseudo-terminals — `/dev/ptmx` and `/dev/pts/N`.
+//!
+//! Opening `/dev/ptmx` allocates a pair and returns its master end.  The
+//! slave is an ordinary tty (`tty::PTY_BASE + n`, `/dev/pts/n`) running the
+//! same `tty::Ldisc` as the serial line and the consoles; it can be opened
+//! once the master has cleared the lock with `TIOCSPTLCK` (`unlockpt`).
+//!
+//! Master writes are the slave's keyboard: they go through `tty::input`, so
+//! Ctrl+C and friends signal the slave's foreground group.  Whatever the
+//! slave outputs — program output and echo — queues here for master reads.
+//!
+//! Both ends are reference-counted across dup and fork.  When the last
+//! master fd closes the slave hangs up (SIGHUP to its foreground group,
+//! reads return end-of-file, writes `EIO`); when the last slave fd closes
+//! master reads return `EIO` once drained.  The pair is freed when neither
+//! end is open.
+
+use crate::kernel::anonfd::EAGAIN;
+use crate::kernel::epoll::{self, Source};
+use crate::kernel::fs::ramfs::{FdBackend, FdEntry, FdTable, MAX_FD};
+use crate::kernel::scheduler::{SCHED, CURRENT_TASK_IDX, wait_for_fd};
+use crate::kernel::syscall_core::validate_user_range;
+use crate::kernel::tty::{self, Ring, PTY_BASE};
+use crate::kernel::user_mode::CURRENT_SYSCALL_CTX;
+
+pub const NR_PTYS: usize = 8;
+
+/// ioctl requests handled on the master.
+pub const TIOCGPTN:   u64 = 0x8004_5430;
+pub const TIOCSPTLCK: u64 = 0x4004_5431;
+
+const O_NONBLOCK: u32 = 0x800;
+
+const EIO:    i64 = -5;
+const EFAULT: i64 = -14;
+const ENOSPC: i64 = -28;
+
+/// Bytes of slave output waiting for the master.
+const OUT_SIZE: usize = 4096;
+
+struct Pty {
+    used:         bool,
+    locked:       bool,
+    nonblock:     bool,
+    master_refs:  u32,
+    slave_refs:   u32,
+    /// The slave has been opened at least once (so its closing means EIO).
+    slave_opened: bool,
+    out:          Ring<OUT_SIZE>,
+}
+
+impl Pty {
+    const FREE: Self = Self {
+        used: false, locked: true, nonblock: false,
+        master_refs: 0, slave_refs: 0, slave_opened: false,
+        out: Ring::EMPTY,
+    };
+}
+
+static mut PTYS: [Pty; NR_PTYS] = [const { Pty::FREE }; NR_PTYS];
+
+unsafe fn pty(n: usize) -> &'static mut Pty {
+    unsafe { &mut (*(&raw mut PTYS))[n] }
+}
+
+/// The pty number behind tty `index`, if it is a pty slave.
+fn of_tty(index: usize) -> Option<usize> {
+    index.checked_sub(PTY_BASE).filter(|&n| n < NR_PTYS)
+}
+
+// ── Opening and closing ───────────────────────────────────────────────────
+
+/// Add `/dev/ptmx` to devfs.  The slaves are looked up through
+/// `tty::lookup` as they come and go.
+pub fn register_devices() {
+    use crate::kernel::devfs::{self, DevNode};
+    devfs::register(DevNode::chr("ptmx", FdBackend::PtyMaster, 0, (5, 2)));
+}
+
+/// Open `/dev/ptmx`: allocate a locked pair, its slave owned by the caller's
+/// real uid, and install its master in `fdt`.
+pub unsafe fn open_master(fdt: &mut FdTable, flags: u32) -> i64 {
+    let Some(n) = (0..NR_PTYS).find(|&n| unsafe { !pty(n).used }) else { return ENOSPC };
+    let fd = fdt.open_dev(FdBackend::PtyMaster, n as i32, true);
+    if fd < 0 { return fd; }
+    unsafe {
+        *pty(n) = Pty::FREE;
+        let p = pty(n);
+        p.used        = true;
+        p.nonblock    = flags & O_NONBLOCK != 0;
+        p.master_refs = 1;
+        tty::reset(PTY_BASE + n);
+        tty::grant(PTY_BASE + n, crate::kernel::cred::get().ruid);
+    }
+    fd
+}
+
+/// May `/dev/<backend,minor>` be opened?  Only unlocked slaves of live
+/// masters can be; every other device always can.
+pub fn may_open(backend: FdBackend, minor: i32) -> bool {
+    if backend != FdBackend::Tty { return true; }
+    match of_tty(minor as usize) {
+        Some(n) => unsafe { let p = pty(n); p.used && !p.locked && p.master_refs > 0 },
+        None    => true,
+    }
+}
+
+/// A new fd refers to the object behind `e` (open, dup, fork).
+pub unsafe fn addref(e: &FdEntry) {
+    match e.backend {
+        FdBackend::PtyMaster => unsafe { pty(e.raw_fd as usize).master_refs += 1 },
+        FdBackend::Tty => if let Some(n) = of_tty(e.raw_fd as usize) {
+            let p = unsafe { pty(n) };
+            p.slave_refs += 1;
+            p.slave_opened = true;
+        },
+        _ => {}
+    }
+}
+
+/// An fd referring to the object behind `e` went away.
+pub unsafe fn release(e: &FdEntry) {
+    let n = match e.backend {
+        FdBackend::PtyMaster => e.raw_fd as usize,
+        FdBackend::Tty => match of_tty(e.raw_fd as usize) {
+            Some(n) => n,
+            None    => return,
+        },
+        _ => return,
+    };
+    let p = unsafe { pty(n) };
+    if e.backend == FdBackend::PtyMaster {
+        p.master_refs = p.master_refs.saturating_sub(1);
+        if p.master_refs == 0 {
+            epoll::forget(Source::PtyMaster(n));
+            unsafe { tty::hangup(PTY_BASE + n); }
+        }
+    } else {
+        p.slave_refs = p.slave_refs.saturating_sub(1);
+        if p.slave_refs == 0 { epoll::notify(Source::PtyMaster(n)); }
+    }
+    if p.master_refs == 0 && p.slave_refs == 0 {
+        epoll::forget(Source::Terminal(PTY_BASE + n));
+        *p = Pty::FREE;
+    }
+}
+
+/// The task at `idx` is exiting: release the pty ends it holds.
+pub unsafe fn on_exit(idx: usize) {
+    let fdt = unsafe { &raw mut (*(&raw mut SCHED)).tasks[idx].fd_table };
+    for slot in unsafe { (*fdt).entries.iter_mut() } {
+        if let Some(e) = slot {
+            if matches!(e.backend, FdBackend::PtyMaster | FdBackend::Tty) {
+                unsafe { release(e); }
+                *slot = None;
+            }
+        }
+    }
+}
+
+// ── Slave side (called by `tty`) ──────────────────────────────────────────
+
+/// Slave output for the master.
+pub unsafe fn to_master(n: usize, bytes: &[u8]) {
+    let p = unsafe { pty(n) };
+    if p.master_refs == 0 { return; }
+    for &b in bytes { p.out.push(b); }
+    epoll::notify(Source::PtyMaster(n));
+}
+
+/// Room in the master's queue, if tty `index` is a pty slave.
+pub fn room(index: usize) -> Option<usize> {
+    of_tty(index).map(|n| unsafe { pty(n).out.free() })
+}
+
+/// True if tty `index` is a pty slave whose master has closed.
+pub fn hung_up(index: usize) -> bool {
+    of_tty(index).is_some_and(|n| unsafe { pty(n).master_refs == 0 })
+}
+
+// ── Master side ───────────────────────────────────────────────────────────
+
+/// `read(2)` on master `n`: slave output, `EAGAIN` if there is none yet,
+/// `EIO` once the slave has closed and everything was read.
+pub unsafe fn read(n: usize, buf: &mut [u8]) -> i64 {
+    let p = unsafe { pty(n) };
+    if p.out.len == 0 {
+        return if p.slave_opened && p.slave_refs == 0 { EIO } else { EAGAIN };
+    }
+    let mut count = 0;
+    while count < buf.len() {
+        match p.out.pop() {
+            Some(b) => { buf[count] = b; count += 1; }
+            None    => break,
+        }
+    }
+    epoll::notify(Source::Terminal(PTY_BASE + n));
+    count as i64
+}
+
+/// `write(2)` on master `n`: input for the slave, as far as it has room.
+pub unsafe fn write(n: usize, buf: &[u8]) -> i64 {
+    let mut count = 0;
+    for &b in buf {
+        if !unsafe { tty::input(PTY_BASE + n, b) } { break; }
+        count += 1;
+    }
+    if count == 0 && !buf.is_empty() { EAGAIN } else { count as i64 }
+}
+
+/// `(readable, writable)` for master `n`.
+pub unsafe fn ready(n: usize) -> (bool, bool) {
+    let p = unsafe { pty(n) };
+    let hup = p.slave_opened && p.slave_refs == 0;
+    (p.out.len > 0 || hup, unsafe { tty::input_room(PTY_BASE + n) } > 0)
+}
+
+/// True if the master's slave has been opened and closed again.
+pub fn slave_closed(n: usize) -> bool {
+    unsafe { let p = pty(n); p.slave_opened && p.slave_refs == 0 }
+}
+
+/// Called when a read or write on the current task's master `fd` returned
+/// `EAGAIN`: unless it was opened `O_NONBLOCK`, park the task until the
+/// master is ready.  Returns only if the caller should report `EAGAIN`.
+pub unsafe fn block(fd: i32, write: bool) {
+    if fd < 0 || fd as usize >= MAX_FD { return; }
+    let e = match unsafe { (*(&raw const SCHED)).tasks[CURRENT_TASK_IDX].fd_table.entries[fd as usize] } {
+        Some(e) if e.backend == FdBackend::PtyMaster => e,
+        _ => return,
+    };
+    if unsafe { pty(e.raw_fd as usize).nonblock } { return; }
+    if let Some(ctx) = unsafe { core::ptr::replace(&raw mut CURRENT_SYSCALL_CTX, None) } {
+        unsafe { wait_for_fd(fd, write, ctx); }
+    }
+}
+
+/// `ioctl` on master `n`.  Requests other than the pty ones act on the
+/// slave, as on Linux (window size, termios, foreground group).
+pub unsafe fn ioctl(n: usize, request: u64, arg: u64) -> i64 {
+    match request {
+        TIOCGPTN => {
+            if validate_user_range(arg, 4).is_err() { return EFAULT; }
+            unsafe { core::ptr::write_unaligned(arg as *mut u32, n as u32); }
+            0
+        }
+        TIOCSPTLCK => {
+            if validate_user_range(arg, 4).is_err() { return EFAULT; }
+            let lock = unsafe { core::ptr::read_unaligned(arg as *const i32) };
+            unsafe { pty(n).locked = lock != 0; }
+            0
+        }
+        _ => unsafe { tty::ioctl_as(PTY_BASE + n, request, arg, true) },
+    }
+}
+
+/// `/dev/pts` listing: the slaves that can be opened.
+pub fn list(buf: &mut [u8]) -> usize {
+    let mut len = 0;
+    for n in 0..NR_PTYS {
+        if !may_open(FdBackend::Tty, (PTY_BASE + n) as i32) { continue; }
+        if len + 2 > buf.len() { break; }
+        buf[len] = b'0' + n as u8;
+        buf[len + 1] = b'\n';
+        len += 2;
+    }
+    len
+}
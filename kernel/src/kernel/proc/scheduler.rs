@@ -6,16 +6,27 @@
 //! next ready task in round-robin order.
 //!
 //! # Address layout (per task)
-//! Code: `0x0040_0000`  Stack top: `0x0080_0000`
-//! All tasks share the same virtual addresses — per-process CR3 maps them to
-//! different physical frames.
+//! Flat binaries and ET_EXEC images load at their link address (normally
+//! `0x0040_0000`); PIEs load at a random bias.  Stack, heap, mmap, shm and the
+//! signal trampoline are placed at random bases drawn per exec — see
+//! [`UserLayout`].  Per-process CR3 maps each layout to its own frames.
+
+extern crate alloc;
 
 use crate::kernel::paging_allocator;
 use crate::kernel::serial::SERIAL_PORT;
 use crate::kernel::user_mode::TaskContext;
 use crate::kernel::fs::ramfs::FdTable;
-
-pub const MAX_TASKS:       usize = 8;
+use crate::kernel::elf_loader::ElfImage;
+use crate::kernel::ptrace::{self, TraceState};
+use crate::kernel::systrace::{self, Outcome};
+use crate::kernel::timers::{self, CpuUsage, TaskTimers};
+use crate::kernel::cred::Credentials;
+use crate::kernel::syscall_core::validate_user_range;
+use crate::kernel::anonfd;
+use crate::kernel::epoll;
+
+pub const MAX_TASKS:       usize = 16;
 const  PAGE_SIZE:          usize = 4096;
 const  USER_CODE_ADDR:     u64   = 0x0040_0000;
 const  USER_STACK_TOP:     u64   = 0x0080_0000;
@@ -38,9 +49,13 @@ pub enum TaskState {
     Ready,
     Running,
     Sleeping(u64),           // wake at this tick
-    Waiting(u8),             // waiting for child with this PID to die
+    Waiting(i32, u32),       // blocked in wait4 with this pid selector and options
     WaitingForMsg(u32, u64), // blocking msgrcv: (queue_id, user msg_out ptr)
-    Dead(i64),               // exit code (pages already freed)
+    WaitingForFd(i32, bool), // blocking read (false) / write (true) on an eventfd/timerfd/signalfd
+    WaitingForEvents(i32, u64), // epoll_wait: (epfd, deadline tick or u64::MAX)
+    Traced,                  // ptrace-stop; resumed by the tracer
+    Stopped,                 // job-control stop (SIGSTOP, SIGTSTP, ...); resumed by SIGCONT
+    Dead(i64),               // exit code, or -signal if killed (pages already freed)
 }
 
 impl TaskState {
@@ -51,20 +66,78 @@ impl TaskState {
 
 pub const CWD_MAX: usize = 128;
 
-/// Maximum number of tracked anonymous mmap regions per process.
-pub const MAX_MMAP_REGIONS: usize = 32;
+/// Maximum number of tracked mmap regions per process.  Neighbouring
+/// regions that agree on protection and backing share a slot.
+pub const MAX_MMAP_REGIONS: usize = 128;
+
+/// What backs the pages of an mmap region.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MmapBacking {
+    /// Anonymous zero-filled memory.
+    Anon,
+    /// RamFS file; the value is the inode index.
+    RamFS(usize),
+    /// FAT16 file; the value is a raw FAT fd owned by the mapping.
+    Fat16(i32),
+    /// ext2 file; the value is a raw ext2 fd owned by the mapping.
+    Ext2(i32),
+    /// `/dev/fb0`: the framebuffer's own physical pages, always shared.
+    Fb,
+}
 
-/// A tracked anonymous mmap allocation (virtual_base, page_count).
+/// A tracked mmap allocation (see `mem::mmap`).
 #[derive(Clone, Copy)]
 pub struct MmapRegion {
-    pub virt:  u64,
-    pub pages: u32,
-    pub _pad:  u32,
+    pub virt:    u64,
+    pub pages:   u32,
+    /// PROT_* bits currently applied to the region.
+    pub prot:    u32,
+    /// MAP_SHARED: frames are shared across fork and written back to the file.
+    pub shared:  bool,
+    /// The backing file was opened writable (required for shared PROT_WRITE).
+    pub file_writable: bool,
+    pub backing: MmapBacking,
+    /// File offset of the region's first page (0 for anonymous regions).
+    pub offset:  u64,
 }
 impl MmapRegion {
-    pub const fn empty() -> Self { Self { virt: 0, pages: 0, _pad: 0 } }
+    pub const fn empty() -> Self {
+        Self {
+            virt: 0, pages: 0, prot: 0, shared: false, file_writable: false,
+            backing: MmapBacking::Anon, offset: 0,
+        }
+    }
+    pub fn end(&self) -> u64 { self.virt + self.pages as u64 * PAGE_SIZE as u64 }
+}
+
+/// A file-backed part of the address space set up by exec: the program
+/// image or its interpreter.  Recorded for the core dump's `NT_FILE` note.
+#[derive(Clone, Copy)]
+pub struct ImageMap {
+    pub start:    u64,
+    pub end:      u64,
+    pub path:     [u8; 64],
+    pub path_len: usize,
+}
+
+impl ImageMap {
+    pub const NONE: Self = Self { start: 0, end: 0, path: [0; 64], path_len: 0 };
+
+    pub fn new(start: u64, end: u64, path: &str) -> Self {
+        let mut m = Self { start, end, ..Self::NONE };
+        m.path_len = path.len().min(m.path.len());
+        m.path[..m.path_len].copy_from_slice(&path.as_bytes()[..m.path_len]);
+        m
+    }
+
+    pub fn path_str(&self) -> &str {
+        core::str::from_utf8(&self.path[..self.path_len]).unwrap_or("")
+    }
 }
 
+/// `RLIM_INFINITY`.
+pub const RLIM_INFINITY: u64 = u64::MAX;
+
 /// Number of signal slots (POSIX requires at least 32).
 pub const NSIG: usize = 32;
 
@@ -77,55 +150,341 @@ pub const SIG_IGN: u64 = 1;
 pub const SIGHUP:  u8 = 1;
 pub const SIGINT:  u8 = 2;
 pub const SIGQUIT: u8 = 3;
+pub const SIGABRT: u8 = 6;
 pub const SIGALRM: u8 = 14;
 pub const SIGKILL: u8 = 9;
+pub const SIGUSR2: u8 = 12;
 pub const SIGTERM: u8 = 15;
 pub const SIGCHLD: u8 = 17;
 pub const SIGCONT: u8 = 18;
 pub const SIGSTOP: u8 = 19;
 pub const SIGTSTP: u8 = 20;
+pub const SIGTTIN: u8 = 21;
+pub const SIGTTOU: u8 = 22;
+pub const SIGVTALRM: u8 = 26;
+pub const SIGWINCH: u8 = 28;
+pub const SIGPROF: u8 = 27;
 
 // sigprocmask "how" values (Linux ABI)
 pub const SIG_BLOCK:   u32 = 0;
 pub const SIG_UNBLOCK: u32 = 1;
 pub const SIG_SETMASK: u32 = 2;
 
-/// Virtual address of the signal-return trampoline page mapped into every process.
-/// Sits between the stack (tops at 0x0080_0000) and the heap base (0x0100_0000).
-pub const USER_SIGTRAMP: u64 = 0x0090_0000;
+// Signals raised by CPU exceptions.
+pub const SIGILL:  u8 = 4;
+pub const SIGTRAP: u8 = 5;
+pub const SIGBUS:  u8 = 7;
+pub const SIGFPE:  u8 = 8;
+pub const SIGSEGV: u8 = 11;
+
+// sa_flags (Linux ABI)
+pub const SA_NOCLDSTOP: u64 = 0x0000_0001;
+pub const SA_NOCLDWAIT: u64 = 0x0000_0002;
+pub const SA_RESTORER:  u64 = 0x0400_0000;
+pub const SA_ONSTACK:   u64 = 0x0800_0000;
+pub const SA_RESTART:   u64 = 0x1000_0000;
+pub const SA_NODEFER:   u64 = 0x4000_0000;
+pub const SA_RESETHAND: u64 = 0x8000_0000;
+
+// sigaltstack ss_flags
+pub use crate::kernel::syscall_core::{SS_ONSTACK, SS_DISABLE};
+
+// si_code values (Linux ABI)
+pub const SI_USER:    i32 = 0;
+pub const SI_KERNEL:  i32 = 0x80;
+pub const SI_TIMER:   i32 = -2;
+pub const ILL_ILLOPN: i32 = 2;
+pub const FPE_INTDIV: i32 = 1;
+pub const FPE_FLTDIV: i32 = 3;
+pub const FPE_FLTOVF: i32 = 4;
+pub const FPE_FLTUND: i32 = 5;
+pub const FPE_FLTRES: i32 = 6;
+pub const FPE_FLTINV: i32 = 7;
+pub const SEGV_MAPERR: i32 = 1;
+pub const SEGV_ACCERR: i32 = 2;
+pub const BUS_ADRALN: i32 = 1;
+pub const TRAP_TRACE: i32 = 2;
+
+/// `-EINTR`, returned by a blocking syscall a handler interrupted.
+const EINTR: i64 = -4;
+/// Both `int 0x80` and `syscall` are two bytes long; backing RIP up by this
+/// much re-executes the call (SA_RESTART).
+pub const SYSCALL_INSN_LEN: u64 = 2;
+/// User-modifiable RFLAGS bits restored by sigreturn (CF PF AF ZF SF TF DF OF AC).
+pub const USER_RFLAGS_MASK: u64 = 0x0004_0DD5;
+pub const RFLAGS_IF: u64 = 1 << 9;
+pub const RFLAGS_TF: u64 = 1 << 8;
+const RFLAGS_DF: u64 = 1 << 10;
+pub const USER_CS: u64 = 0x23;
+pub const USER_SS: u64 = 0x1B;
+/// Bytes below the interrupted RSP the ABI lets leaf functions use.
+const RED_ZONE: u64 = 128;
+
+// ── Address space layout (ASLR) ────────────────────────────────────────────
+
+/// Highest stack top; the real top slides down by up to 16 GiB.
+const STACK_TOP_MAX:     u64 = 0x0000_7FFF_FFFF_F000;
+const STACK_SLIDE_PAGES: u64 = 1 << 22;
+/// Anonymous/file mmap search starts here plus up to 1 TiB.
+const MMAP_BASE_MIN:     u64 = 0x0000_1000_0000_0000;
+const MMAP_SLIDE_PAGES:  u64 = 1 << 28;
+/// SysV shm attach window base plus up to 64 GiB.
+const SHM_BASE_MIN:      u64 = 0x0000_2000_0000_0000;
+const SHM_SLIDE_PAGES:   u64 = 1 << 24;
+/// Signal-return trampoline page plus up to 4 GiB.
+const SIGTRAMP_MIN:      u64 = 0x0000_7F00_0000_0000;
+const SIGTRAMP_SLIDE_PAGES: u64 = 1 << 20;
+/// brk starts up to 32 MiB above the end of the loaded image.
+const HEAP_SLIDE_PAGES:  u64 = 1 << 13;
+
+/// Per-process virtual address layout.  Drawn fresh on spawn/exec and
+/// inherited unchanged across fork.
+#[derive(Clone, Copy)]
+pub struct UserLayout {
+    /// Top of the user stack (exclusive).
+    pub stack_top: u64,
+    /// Initial program break.
+    pub heap_base: u64,
+    /// Where non-fixed mmap searches start.
+    pub mmap_base: u64,
+    /// Base of the shm attach window (`shm_base + shmid * 1 MiB`).
+    pub shm_base:  u64,
+    /// Signal-return trampoline page.
+    pub sigtramp:  u64,
+}
+
+impl UserLayout {
+    /// The historical fixed layout; used for empty slots.
+    pub const FIXED: Self = Self {
+        stack_top: USER_STACK_TOP,
+        heap_base: 0x0100_0000,
+        mmap_base: 0x0800_0000,
+        shm_base:  0x2000_0000,
+        sigtramp:  0x0090_0000,
+    };
+
+    /// Draw a random layout for an image whose last page ends at `image_end`.
+    pub fn randomized(image_end: u64) -> Self {
+        use crate::kernel::rng::below;
+        let page = PAGE_SIZE as u64;
+        let image_end = (image_end + page - 1) & !(page - 1);
+        Self {
+            stack_top: STACK_TOP_MAX - below(STACK_SLIDE_PAGES) * page,
+            heap_base: image_end + below(HEAP_SLIDE_PAGES) * page,
+            mmap_base: MMAP_BASE_MIN + below(MMAP_SLIDE_PAGES) * page,
+            shm_base:  SHM_BASE_MIN + below(SHM_SLIDE_PAGES) * page,
+            sigtramp:  SIGTRAMP_MIN + below(SIGTRAMP_SLIDE_PAGES) * page,
+        }
+    }
+
+    /// Lowest address of the user stack mapping.
+    pub fn stack_base(&self) -> u64 {
+        self.stack_top - (USER_STACK_PAGES * PAGE_SIZE) as u64
+    }
+}
 
-/// Trampoline machine code: `mov rax, 95; int 0x80; ud2`
-/// When a signal handler returns, it `ret`s here, which calls SIGRETURN.
+/// Trampoline machine code: `mov rax, 15; int 0x80; ud2`
+/// A handler installed without `SA_RESTORER` `ret`s here, which calls
+/// `rt_sigreturn` (15).
 pub const SIGTRAMP_BYTES: &[u8] = &[
-    0x48, 0xc7, 0xc0, 95, 0, 0, 0,  // mov rax, 95
+    0x48, 0xc7, 0xc0, 15, 0, 0, 0,  // mov rax, 15
     0xcd, 0x80,                       // int 0x80
     0x0f, 0x0b,                       // ud2 (should not reach here)
 ];
 
-/// Signal frame saved on the user stack during signal delivery.
-/// The kernel pushes this before jumping to the handler; SIGRETURN pops it.
+/// One `rt_sigaction` entry, laid out like the kernel's `struct k_sigaction`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct SigAction {
+    /// Handler address, `SIG_DFL` or `SIG_IGN`.
+    pub handler:  u64,
+    pub flags:    u64,
+    /// Return address for the handler when `SA_RESTORER` is set.
+    pub restorer: u64,
+    /// Extra signals blocked while the handler runs.
+    pub mask:     u64,
+}
+
+impl SigAction {
+    pub const DEFAULT: Self = Self { handler: SIG_DFL, flags: 0, restorer: 0, mask: 0 };
+}
+
+/// Why a signal was raised; becomes the handler's `siginfo_t`.
+#[derive(Clone, Copy)]
+pub struct SigInfo {
+    pub code:   i32,
+    /// Faulting address for SIGSEGV/SIGBUS/SIGILL/SIGFPE/SIGTRAP.
+    pub addr:   u64,
+    /// Sending (or exiting child) process.
+    pub pid:    u32,
+    pub uid:    u32,
+    /// Child exit status for SIGCHLD.
+    pub status: i32,
+    /// Exception vector and error code, copied into the sigcontext.
+    pub trapno: u64,
+    pub err:    u64,
+    /// POSIX timer id, overrun count and `sigev_value` for SI_TIMER.
+    pub timerid: i32,
+    pub overrun: i32,
+    pub value:   u64,
+}
+
+impl SigInfo {
+    pub const EMPTY: Self = Self {
+        code: SI_KERNEL, addr: 0, pid: 0, uid: 0, status: 0, trapno: 0, err: 0,
+        timerid: 0, overrun: 0, value: 0,
+    };
+
+    /// Sent with `kill()` by `pid`, whose real UID is `uid`.
+    pub const fn user(pid: u32, uid: u32) -> Self {
+        Self { code: SI_USER, pid, uid, ..Self::EMPTY }
+    }
+
+    /// Raised by a CPU exception.
+    pub const fn fault(code: i32, addr: u64, trapno: u64, err: u64) -> Self {
+        Self { code, addr, trapno, err, ..Self::EMPTY }
+    }
+
+    /// Expiry of POSIX timer `timerid`.
+    pub const fn timer(timerid: i32, overrun: i32, value: u64) -> Self {
+        Self { code: SI_TIMER, timerid, overrun, value, ..Self::EMPTY }
+    }
+}
+
+/// `siginfo_t` as the handler sees it (128 bytes).
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct UserSigInfo {
+    pub signo:  i32,
+    pub errno:  i32,
+    pub code:   i32,
+    _pad:       i32,
+    /// The `_sifields` union: `si_addr` for faults, `si_pid`/`si_uid` for
+    /// kill(), plus `si_status` for SIGCHLD, `si_timerid`/`si_overrun`/
+    /// `si_value` for timers.
+    pub fields: [u64; 14],
+}
+
+impl UserSigInfo {
+    pub fn new(signum: u8, info: &SigInfo) -> Self {
+        let mut fields = [0u64; 14];
+        match signum {
+            SIGSEGV | SIGBUS | SIGILL | SIGFPE | SIGTRAP if info.code > 0 => {
+                fields[0] = info.addr;
+            }
+            _ if info.code == SI_TIMER => {
+                fields[0] = info.timerid as u32 as u64 | (info.overrun as u32 as u64) << 32;
+                fields[1] = info.value;
+            }
+            _ => {
+                fields[0] = info.pid as u64 | (info.uid as u64) << 32;
+                fields[1] = info.status as u32 as u64;
+            }
+        }
+        Self { signo: signum as i32, errno: 0, code: info.code, _pad: 0, fields }
+    }
+}
+
+/// `stack_t` — an alternate signal stack (`sigaltstack`).
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct SignalStack {
+    pub sp:    u64,
+    pub flags: i32,
+    _pad:      i32,
+    pub size:  u64,
+}
+
+impl SignalStack {
+    pub const DISABLED: Self = Self { sp: 0, flags: SS_DISABLE, _pad: 0, size: 0 };
+
+    pub const fn new(sp: u64, flags: i32, size: u64) -> Self {
+        Self { sp, flags, _pad: 0, size }
+    }
+
+    /// True if `rsp` lies on this stack.
+    pub fn contains(&self, rsp: u64) -> bool {
+        self.flags & SS_DISABLE == 0 && rsp > self.sp && rsp <= self.sp + self.size
+    }
+}
+
+/// `struct sigcontext` — the register state saved across a handler.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct SigContext {
+    pub r8:  u64, pub r9:  u64, pub r10: u64, pub r11: u64,
+    pub r12: u64, pub r13: u64, pub r14: u64, pub r15: u64,
+    pub rdi: u64, pub rsi: u64, pub rbp: u64, pub rbx: u64,
+    pub rdx: u64, pub rax: u64, pub rcx: u64, pub rsp: u64,
+    pub rip: u64, pub eflags: u64,
+    pub cs:  u16, pub gs: u16, pub fs: u16, pub ss: u16,
+    pub err:     u64,
+    pub trapno:  u64,
+    pub oldmask: u64,
+    pub cr2:     u64,
+    /// No FPU state is saved; always 0.
+    pub fpstate: u64,
+    _reserved:   [u64; 8],
+}
+
+impl SigContext {
+    fn from_ctx(ctx: &TaskContext, info: &SigInfo, oldmask: u64) -> Self {
+        Self {
+            r8: ctx.r8, r9: ctx.r9, r10: ctx.r10, r11: ctx.r11,
+            r12: ctx.r12, r13: ctx.r13, r14: ctx.r14, r15: ctx.r15,
+            rdi: ctx.rdi, rsi: ctx.rsi, rbp: ctx.rbp, rbx: ctx.rbx,
+            rdx: ctx.rdx, rax: ctx.rax, rcx: ctx.rcx, rsp: ctx.rsp,
+            rip: ctx.rip, eflags: ctx.rflags,
+            cs: ctx.cs as u16, gs: 0, fs: 0, ss: ctx.ss as u16,
+            err: info.err, trapno: info.trapno, oldmask,
+            cr2: if info.trapno == 14 { info.addr } else { 0 },
+            fpstate: 0, _reserved: [0; 8],
+        }
+    }
+
+    /// Rebuild a resumable context.  Segment selectors and privileged
+    /// RFLAGS bits come from the kernel, never from the (user-writable) frame.
+    pub fn to_ctx(&self) -> TaskContext {
+        TaskContext {
+            r15: self.r15, r14: self.r14, r13: self.r13, r12: self.r12,
+            r11: self.r11, r10: self.r10, r9: self.r9, r8: self.r8,
+            rdi: self.rdi, rsi: self.rsi, rbp: self.rbp, rdx: self.rdx,
+            rcx: self.rcx, rbx: self.rbx, rax: self.rax,
+            rip: self.rip, cs: USER_CS,
+            rflags: (self.eflags & USER_RFLAGS_MASK) | RFLAGS_IF | 0x2,
+            rsp: self.rsp, ss: USER_SS,
+        }
+    }
+}
+
+/// `struct ucontext` — the handler's third argument under `SA_SIGINFO`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct UContext {
+    pub flags:    u64,
+    pub link:     u64,
+    pub stack:    SignalStack,
+    pub mcontext: SigContext,
+    /// Signal mask to restore on sigreturn.
+    pub sigmask:  u64,
+}
+
+/// Signal frame pushed on the user (or alternate) stack during delivery,
+/// laid out like Linux's `rt_sigframe`.  The handler is entered with RSP at
+/// `restorer`; when it returns, `rt_sigreturn` finds the frame at RSP - 8.
 #[repr(C)]
 #[derive(Clone, Copy)]
 pub struct SignalFrame {
-    pub rip:    u64,
-    pub rax:    u64,
-    pub rbx:    u64,
-    pub rcx:    u64,
-    pub rdx:    u64,
-    pub rsi:    u64,
-    pub rdi:    u64,
-    pub r8:     u64,
-    pub r9:     u64,
-    pub r10:    u64,
-    pub r11:    u64,
-    pub r12:    u64,
-    pub r13:    u64,
-    pub r14:    u64,
-    pub r15:    u64,
-    pub rbp:    u64,
-    pub rflags: u64,
+    pub restorer: u64,
+    pub uc:       UContext,
+    pub info:     UserSigInfo,
 }
 
+const _: () = assert!(core::mem::size_of::<UserSigInfo>() == 128);
+const _: () = assert!(core::mem::size_of::<SigContext>()  == 256);
+const _: () = assert!(core::mem::size_of::<UContext>()    == 304);
+const _: () = assert!(core::mem::size_of::<SignalStack>() == 24);
+
 pub struct Task {
     pub state:      TaskState,
     pub ctx:        TaskContext,
@@ -139,12 +498,20 @@ pub struct Task {
     pub parent_pid: u8,
     /// Process group ID. 0 means "same as pid" (set on first use).
     pub pgid:       u8,
+    /// Session ID. 0 means "same as pid": the task leads its own session.
+    pub sid:        u8,
+    /// Job-control change not yet collected by the parent's `wait4`: a
+    /// stop (`sig << 8 | 0x7f`, for WUNTRACED) or a continue (`0xffff`, for
+    /// WCONTINUED); 0 when there is none.
+    pub job_report: u32,
     /// IA32_FS_BASE MSR value for this task's TLS pointer (set via arch_prctl).
     pub fs_base:    u64,
-    /// Current userspace heap break (virtual address).  0 = unset (use USER_HEAP_BASE).
+    /// Current userspace heap break (virtual address).  0 = unset (use `layout.heap_base`).
     pub heap_end:   u64,
-    /// Top of the anonymous-mmap area.  0 = unset (use USER_MMAP_BASE).
+    /// Top of the anonymous-mmap area.  0 = unset (use `layout.mmap_base`).
     pub mmap_end:   u64,
+    /// Randomised stack / heap / mmap / shm / trampoline bases.
+    pub layout:     UserLayout,
     pub output:     [u8; TASK_OUTPUT_CAP],
     pub output_len: usize,
     /// Per-process open file-descriptor table.
@@ -161,19 +528,42 @@ pub struct Task {
     pub saved_signal_mask: u32,
     /// Set while a sigsuspend is in progress; cleared by sigreturn.
     pub in_sigsuspend: bool,
-    /// Tick at which SIGALRM fires; 0 = no alarm armed.
-    pub alarm_deadline: u64,
-    /// Per-signal handler addresses (index = signal number).
-    /// 0 (SIG_DFL) = default action; 1 (SIG_IGN) = ignore.
-    pub signal_handlers: [u64; NSIG],
+    /// Interval timers (`alarm` is ITIMER_REAL) and POSIX timers.
+    pub timers: TaskTimers,
+    /// CPU time and context switches, for times/getrusage.
+    pub cpu: CpuUsage,
+    /// Accumulated usage of reaped children (RUSAGE_CHILDREN).
+    pub child_cpu: CpuUsage,
+    /// Per-signal dispositions (index = signal number).
+    /// Handler 0 (SIG_DFL) = default action; 1 (SIG_IGN) = ignore.
+    pub signal_actions: [SigAction; NSIG],
+    /// `siginfo_t` details for each pending signal (index = signal number).
+    pub signal_info: [SigInfo; NSIG],
+    /// Alternate signal stack for `SA_ONSTACK` handlers.
+    pub sigaltstack: SignalStack,
+    /// Number of the blocking syscall this task is parked in, so a handler
+    /// can interrupt it with `EINTR` or restart it (`SA_RESTART`).
+    pub blocked_syscall: Option<u64>,
     /// Shared memory attachments for this process.
     pub shm_attaches: [crate::kernel::shm::ShmAttach; crate::kernel::shm::MAX_ATTACH],
     /// Initial RSP for the first-run launch — points to the argc value on the
     /// user stack (System V AMD64 ABI). Set by spawn() / exec_binary().
     pub initial_rsp: u64,
-    /// Tracked anonymous mmap allocations (for munmap).
+    /// Tracked mmap allocations (for munmap/mprotect/msync).
     pub mmap_regions:  [MmapRegion; MAX_MMAP_REGIONS],
     pub mmap_nregions: usize,
+    /// The executable and its interpreter, as loaded by the last exec.
+    pub image_maps: [ImageMap; 2],
+    /// `RLIMIT_CORE` (soft, hard) in bytes; inherited across fork and exec.
+    pub core_rlimit: (u64, u64),
+    /// ptrace state: who traces this task and why it is stopped.
+    pub trace: TraceState,
+    /// uids, gids and supplementary groups; inherited across fork and exec.
+    pub cred: Credentials,
+    /// Cleared when the credentials change, by a set-id exec or a set*id
+    /// call; set again by an ordinary exec.  Only root may ptrace a task
+    /// that is not dumpable, and it leaves no core dump.
+    pub dumpable: bool,
 }
 
 impl Task {
@@ -191,9 +581,12 @@ impl Task {
             pid:        0,
             parent_pid: 0,
             pgid:       0,
+            sid:        0,
+            job_report: 0,
             fs_base:    0,
             heap_end:   0,
             mmap_end:   0,
+            layout:     UserLayout::FIXED,
             output:     [0u8; TASK_OUTPUT_CAP],
             output_len: 0,
             fd_table:   FdTable::new(),
@@ -203,18 +596,43 @@ impl Task {
             signal_mask: 0,
             saved_signal_mask: 0,
             in_sigsuspend: false,
-            alarm_deadline: 0,
-            signal_handlers: [0u64; NSIG],
+            timers: TaskTimers::NONE,
+            cpu: CpuUsage::ZERO,
+            child_cpu: CpuUsage::ZERO,
+            signal_actions: [SigAction::DEFAULT; NSIG],
+            signal_info: [SigInfo::EMPTY; NSIG],
+            sigaltstack: SignalStack::DISABLED,
+            blocked_syscall: None,
             shm_attaches: [const { crate::kernel::shm::ShmAttach::empty() }; crate::kernel::shm::MAX_ATTACH],
             initial_rsp: USER_STACK_TOP - 16,
             mmap_regions:  [const { MmapRegion::empty() }; MAX_MMAP_REGIONS],
             mmap_nregions: 0,
+            image_maps:    [ImageMap::NONE; 2],
+            core_rlimit:   (RLIM_INFINITY, RLIM_INFINITY),
+            trace:         TraceState::NONE,
+            cred:          Credentials::ROOT,
+            dumpable:      true,
         }
     }
 
     pub fn name_str(&self) -> &str {
         core::str::from_utf8(&self.name[..self.name_len]).unwrap_or("?")
     }
+
+    /// Process group ID, resolving the "same as pid" default.
+    pub fn pgrp(&self) -> u8 {
+        if self.pgid == 0 { self.pid } else { self.pgid }
+    }
+
+    /// Session ID, resolving the "same as pid" default.
+    pub fn session(&self) -> u8 {
+        if self.sid == 0 { self.pid } else { self.sid }
+    }
+
+    /// Neither an empty slot nor a zombie.
+    pub fn is_live(&self) -> bool {
+        self.pid != 0 && !matches!(self.state, TaskState::Empty | TaskState::Dead(_))
+    }
 }
 
 // ── Scheduler state ────────────────────────────────────────────────────────
@@ -223,6 +641,8 @@ pub struct Scheduler {
     pub tasks:           [Task; MAX_TASKS],
     pub current:         usize,
     pub slice_remaining: u64,
+    /// Slot 0 was given to `/sbin/init` by `spawn_init`.
+    pub init:            bool,
 }
 
 impl Scheduler {
@@ -231,6 +651,7 @@ impl Scheduler {
             tasks:           [const { Task::empty() }; MAX_TASKS],
             current:         0,
             slice_remaining: 0,
+            init:            false,
         }
     }
 }
@@ -276,8 +697,49 @@ pub fn output_drain_task(idx: usize, mut f: impl FnMut(&str)) {
 
 // ── argv/argc helpers ──────────────────────────────────────────────────────
 
-/// Build a System V AMD64 ABI argv+envp block at the top of the user stack
-/// and copy it into the page table at `cr3`.
+// Auxiliary vector tags (System V AMD64 ABI).
+pub const AT_NULL:     u64 = 0;
+pub const AT_PHDR:     u64 = 3;
+pub const AT_PHENT:    u64 = 4;
+pub const AT_PHNUM:    u64 = 5;
+pub const AT_PAGESZ:   u64 = 6;
+pub const AT_BASE:     u64 = 7;
+pub const AT_FLAGS:    u64 = 8;
+pub const AT_ENTRY:    u64 = 9;
+pub const AT_UID:      u64 = 11;
+pub const AT_EUID:     u64 = 12;
+pub const AT_GID:      u64 = 13;
+pub const AT_EGID:     u64 = 14;
+pub const AT_PLATFORM: u64 = 15;
+pub const AT_HWCAP:    u64 = 16;
+pub const AT_CLKTCK:   u64 = 17;
+pub const AT_SECURE:   u64 = 23;
+pub const AT_RANDOM:   u64 = 25;
+pub const AT_EXECFN:   u64 = 31;
+
+/// Size of the scratch buffer the argv/envp/auxv block is assembled in.
+const ARGV_BLOCK_MAX: usize = 4096;
+
+/// A program loaded into a fresh address space, ready to be started.
+pub struct LoadedProgram {
+    /// The executable itself.
+    pub image:  ElfImage,
+    /// Its `PT_INTERP` program interpreter, if it has one.
+    pub interp: Option<ElfImage>,
+    /// Where the interpreter was loaded from (`ImageMap::NONE` if none).
+    pub interp_map: ImageMap,
+    pub layout: UserLayout,
+}
+
+impl LoadedProgram {
+    /// Where execution starts: the interpreter if present, else the program.
+    pub fn entry(&self) -> u64 {
+        self.interp.map_or(self.image.entry, |i| i.entry)
+    }
+}
+
+/// Build a System V AMD64 ABI argv+envp+auxv block at the top of the user
+/// stack and copy it into the page table at `cr3`.
 ///
 /// Stack layout (little-endian u64 values):
 ///   [rsp +  0]                        = argc
@@ -285,30 +747,66 @@ pub fn output_drain_task(idx: usize, mut f: impl FnMut(&str)) {
 ///   [rsp +  8+argc*8]                 = NULL  (end of argv)
 ///   [rsp +  8+(argc+1)*8 .. ...]      = envp[0..envc] ptrs
 ///   [rsp +  8+(argc+1+envc)*8]        = NULL  (end of envp)
-///   [rsp + ptr_section_size]          = argv strings, then envp "K=V\0" strings
+///   [...]                             = auxv (type, value) pairs, AT_NULL last
+///   [rsp + ptr_section_size]          = argv strings, envp "K=V\0" strings,
+///                                       "x86_64\0", then 16 AT_RANDOM bytes
 ///
-/// `stack_top` is the first byte **above** the mapped stack region.
-pub unsafe fn write_argv_to_stack(cr3: u64, stack_top: u64, args: &[&str]) -> u64 {
+/// The block is placed just below `prog.layout.stack_top`.  The auxv ids
+/// come from `cred`; `secure` sets `AT_SECURE` after a set-id exec.
+pub unsafe fn write_argv_to_stack(
+    cr3: u64, prog: &LoadedProgram, args: &[&str], cred: &Credentials, secure: bool,
+) -> u64 {
+    extern crate alloc;
+    const PLATFORM: &[u8] = b"x86_64\0";
+
+    let stack_top = prog.layout.stack_top;
+    let image     = &prog.image;
     let argc = args.len().min(31);
 
     // Collect all env vars as "KEY=VALUE\0" strings into a temporary buffer.
     let mut env_raw = [0u8; 1024];
     let (envc, env_raw_len) = crate::kernel::env::write_env_strings(&mut env_raw);
 
-    // Pointer table: [argc] + [argv*argc] + [NULL] + [envp*envc] + [NULL]
-    let ptr_table_bytes = 8 * (1 + argc + 1 + envc + 1);
+    // auxv entries; pointer-valued ones are patched once the block is placed.
+    let mut auxv = [(0u64, 0u64); 20];
+    let mut auxc = 0;
+    let mut push = |tag: u64, val: u64| { auxv[auxc] = (tag, val); auxc += 1; };
+    if image.phdr != 0 {
+        push(AT_PHDR,  image.phdr);
+        push(AT_PHENT, image.phent);
+        push(AT_PHNUM, image.phnum);
+    }
+    push(AT_PAGESZ, PAGE_SIZE as u64);
+    push(AT_BASE,   prog.interp.map_or(0, |i| i.bias));
+    push(AT_FLAGS,  0);
+    push(AT_ENTRY,  image.entry);
+    push(AT_UID,    cred.ruid as u64);
+    push(AT_EUID,   cred.euid as u64);
+    push(AT_GID,    cred.rgid as u64);
+    push(AT_EGID,   cred.egid as u64);
+    push(AT_SECURE, secure as u64);
+    push(AT_HWCAP,  core::arch::x86_64::__cpuid(1).edx as u64);
+    push(AT_CLKTCK, 100);
+    push(AT_PLATFORM, 0);
+    push(AT_EXECFN,   0);
+    push(AT_RANDOM,   0);
+    push(AT_NULL,     0);
+
+    // Pointer table: [argc] + [argv*argc] + [NULL] + [envp*envc] + [NULL] + auxv
+    let auxv_off        = 8 * (1 + argc + 1 + envc + 1);
+    let ptr_table_bytes = auxv_off + 16 * auxc;
 
     let mut argv_str_bytes = 0usize;
     for a in &args[..argc] { argv_str_bytes += a.len() + 1; }
 
-    // Total = pointer table + argv strings + envp strings, aligned.
-    let raw_total = ptr_table_bytes + argv_str_bytes + env_raw_len;
+    // Total = pointer table + strings + platform + random bytes, aligned.
+    let raw_total = ptr_table_bytes + argv_str_bytes + env_raw_len + PLATFORM.len() + 16;
     let total = ((raw_total + 15) & !15) + 8;
 
-    if total > 2048 { return stack_top - 8; }
+    if total > ARGV_BLOCK_MAX { return stack_top - 8; }
 
     let initial_rsp = stack_top - total as u64;
-    let mut buf = [0u8; 2048];
+    let mut buf = alloc::vec![0u8; total];
 
     // argc
     buf[0..8].copy_from_slice(&(argc as u64).to_le_bytes());
@@ -345,12 +843,136 @@ pub unsafe fn write_argv_to_stack(cr3: u64, stack_top: u64, args: &[&str]) -> u6
     }
     // envp[envc] = NULL: slot = 8 + (argc+1+envc)*8, already 0
 
-    unsafe { paging_allocator::copy_to_region_in(cr3, initial_rsp, &buf[..total]); }
+    // AT_PLATFORM string, then 16 AT_RANDOM bytes for the libc stack
+    // protector / pointer guard.
+    let platform_off = str_off + env_raw_len;
+    buf[platform_off..platform_off + PLATFORM.len()].copy_from_slice(PLATFORM);
+    let random_off = platform_off + PLATFORM.len();
+    crate::kernel::rng::fill(&mut buf[random_off..random_off + 16]);
+
+    // auxv pairs follow the envp NULL.
+    let argv0_va = if argc > 0 { initial_rsp + ptr_table_bytes as u64 } else { 0 };
+    for (i, &(tag, val)) in auxv[..auxc].iter().enumerate() {
+        let val = match tag {
+            AT_PLATFORM => initial_rsp + platform_off as u64,
+            AT_RANDOM   => initial_rsp + random_off as u64,
+            AT_EXECFN   => argv0_va,
+            _           => val,
+        };
+        let off = auxv_off + i * 16;
+        buf[off..off + 8].copy_from_slice(&tag.to_le_bytes());
+        buf[off + 8..off + 16].copy_from_slice(&val.to_le_bytes());
+    }
+
+    unsafe { paging_allocator::copy_to_region_in(cr3, initial_rsp, &buf); }
     initial_rsp
 }
 
+/// Load `code` (ELF or flat binary) into `cr3` and lay out the rest of the
+/// address space: the program interpreter (if `PT_INTERP` names one), random
+/// stack, heap, mmap and shm bases, and the signal trampoline page.  The
+/// caller builds the argv block with `write_argv_to_stack`.
+pub unsafe fn build_address_space(cr3: u64, code: &[u8]) -> Result<LoadedProgram, &'static str> {
+    use crate::kernel::elf_loader;
+
+    // Map code / load ELF — all into `cr3` without switching the kernel CR3.
+    let mut interp_map = ImageMap::NONE;
+    let (image, interp) = if elf_loader::is_elf(code) {
+        let image = unsafe { elf_loader::load_in(code, cr3)? };
+        let interp = match elf_loader::interp(code) {
+            Some(path) => {
+                let data = unsafe { crate::kernel::vfs::vfs_read_file(path) }.ok_or("interpreter not found")?;
+                let interp = unsafe { elf_loader::load_interp_in(&data, cr3)? };
+                interp_map = ImageMap::new(interp.start, interp.end, path);
+                Some(interp)
+            }
+            None => {
+                // Static ET_EXEC: nothing relocates at run time, so RELRO can
+                // be sealed now.  Static PIEs relocate themselves first.
+                if image.bias == 0 { unsafe { elf_loader::seal_relro(cr3, &image); } }
+                None
+            }
+        };
+        (image, interp)
+    } else {
+        let program_pages = code.len().div_ceil(PAGE_SIZE);
+        unsafe {
+            paging_allocator::map_user_region_in(
+                cr3, USER_CODE_ADDR, program_pages, true, true)
+                .map_err(|_| "OOM: code")?;
+            paging_allocator::copy_to_region_in(cr3, USER_CODE_ADDR, code);
+        }
+        (ElfImage::flat(USER_CODE_ADDR, code.len()), None)
+    };
+
+    let layout = UserLayout::randomized(image.end);
+
+    // Map user stack (executable only if PT_GNU_STACK asks for it).
+    unsafe {
+        paging_allocator::map_user_region_in(
+            cr3, layout.stack_base(), USER_STACK_PAGES, true, image.exec_stack)
+            .map_err(|_| "OOM: stack")?;
+    }
+
+    // Map the signal-return trampoline page as writable so copy_to_region_in
+    // can write to it in supervisor mode (CR0.WP faults on non-writable pages
+    // regardless of privilege level).  User-writable is acceptable here.
+    if unsafe { paging_allocator::map_user_region_in(cr3, layout.sigtramp, 1, true, true) }.is_ok() {
+        unsafe { paging_allocator::copy_to_region_in(cr3, layout.sigtramp, SIGTRAMP_BYTES); }
+    }
+
+    Ok(LoadedProgram { image, interp, interp_map, layout })
+}
+
 // ── Public API ─────────────────────────────────────────────────────────────
 
+// ── init (PID 1) ───────────────────────────────────────────────────────────
+
+/// PID of `/sbin/init`.  Slot 0 is reserved for it, so no other task ever
+/// gets this PID.
+pub const INIT_PID: u8 = 1;
+
+/// Whether task slot `i` can take a new task: empty, or a zombie that nobody
+/// will wait for (no parent, no tracer).  Slot 0 belongs to init.
+unsafe fn slot_free(i: usize) -> bool {
+    let t = unsafe { &(*(&raw const SCHED)).tasks[i] };
+    i != 0 && match t.state {
+        TaskState::Empty   => true,
+        TaskState::Dead(_) => t.parent_pid == 0 && t.trace.tracer == 0,
+        _                  => false,
+    }
+}
+
+/// The PID orphans are handed to: init while it runs, else 0 — the kernel,
+/// which frees a task's slot as soon as it exits.
+pub fn reaper() -> u8 {
+    unsafe {
+        let sched = &raw const SCHED;
+        let init  = &(*sched).tasks[0];
+        let alive = !matches!(init.state, TaskState::Empty | TaskState::Dead(_));
+        if (*sched).init && alive { INIT_PID } else { 0 }
+    }
+}
+
+/// Start `/sbin/init` from `code` as PID 1.  Called once at boot, before
+/// anything else is spawned.
+pub unsafe fn spawn_init(code: &[u8]) -> Result<u8, &'static str> {
+    let sched = &raw mut SCHED;
+    if unsafe { (*sched).tasks[0].state } != TaskState::Empty { return Err("pid 1 already used"); }
+    let pid = unsafe { spawn_in(0, code, "init", 0)? };
+    unsafe { (*sched).init = true; }
+    Ok(pid)
+}
+
+/// Whether a user-sent `signum` to `pid` is dropped: as on Linux, init only
+/// receives the signals it has installed a handler for — never SIGKILL or
+/// SIGSTOP.
+pub fn init_ignores(pid: u8, signum: u8) -> bool {
+    if pid != INIT_PID || reaper() == 0 || signum as usize >= NSIG { return false; }
+    let handler = unsafe { (*(&raw const SCHED)).tasks[0].signal_actions[signum as usize].handler };
+    signum == SIGKILL || signum == SIGSTOP || handler == SIG_DFL
+}
+
 /// Snapshot used by the `ps` terminal command.
 #[derive(Clone, Copy)]
 pub struct TaskInfo {
@@ -381,83 +1003,78 @@ pub fn task_infos() -> [TaskInfo; MAX_TASKS] {
 ///
 /// Finds a free slot, creates a per-process page table, maps code + stack,
 /// and marks the task Ready.  Returns the 1-based PID on success.
+///
+/// The task is a child of init, which reaps it; without init nobody waits
+/// and the slot is reused once it exits.
 pub unsafe fn spawn(code: &[u8], name: &str) -> Result<u8, &'static str> {
+    let slot = (0..MAX_TASKS).find(|&i| unsafe { slot_free(i) })
+        .ok_or("max tasks reached (16)")?;
+    unsafe { spawn_in(slot, code, name, reaper()) }
+}
+
+/// Load `code` into task slot `slot` as a Ready task with parent `parent_pid`.
+unsafe fn spawn_in(slot: usize, code: &[u8], name: &str, parent_pid: u8) -> Result<u8, &'static str> {
     if code.is_empty() { return Err("empty binary"); }
 
     let sched = &raw mut SCHED;
 
-    // Find a free slot.
-    let slot = (0..MAX_TASKS).find(|&i| {
-        matches!((*sched).tasks[i].state, TaskState::Empty | TaskState::Dead(_))
-    }).ok_or("max tasks reached (8)")?;
-
     // Create per-process page table (copies kernel higher-half entries).
-    let cr3 = paging_allocator::create_user_page_table()
+    let cr3 = unsafe { paging_allocator::create_user_page_table() }
         .ok_or("OOM: cannot allocate page table")?;
 
-    // Map user stack in the new page table.
-    let stack_base = USER_STACK_TOP - (USER_STACK_PAGES * PAGE_SIZE) as u64;
-    unsafe {
-        paging_allocator::map_user_region_in(cr3, stack_base, USER_STACK_PAGES, true, false)
-            .map_err(|_| "OOM: stack")?;
-    }
-
-    // Map code / load ELF — all into `cr3` without switching the kernel CR3.
-    let entry = if crate::kernel::elf_loader::is_elf(code) {
-        unsafe { crate::kernel::elf_loader::load_in(code, cr3)? }
-    } else {
-        let program_pages = code.len().div_ceil(PAGE_SIZE);
-        unsafe {
-            paging_allocator::map_user_region_in(
-                cr3, USER_CODE_ADDR, program_pages, true, true)
-                .map_err(|_| "OOM: code")?;
-            paging_allocator::copy_to_region_in(cr3, USER_CODE_ADDR, code);
-        }
-        USER_CODE_ADDR
-    };
+    let prog  = unsafe { build_address_space(cr3, code)? };
+    let entry = prog.entry();
 
     // Initialise the task slot.
     let pid  = (slot + 1) as u8;
-    let task = &raw mut (*sched).tasks[slot];
-
-    (*task).state           = TaskState::Ready;
-    (*task).first_run       = true;
-    (*task).ctx             = TaskContext::zeroed();
-    (*task).entry           = entry;
-    (*task).cr3             = cr3;
-    (*task).pid             = pid;
-    (*task).parent_pid      = 0;
-    (*task).fs_base         = 0;
-    (*task).heap_end        = 0;
-    (*task).mmap_end        = 0;
-    (*task).output_len          = 0;
-    (*task).fd_table            = FdTable::new();
-    (*task).pending_signals     = 0;
-    (*task).signal_mask         = 0;
-    (*task).saved_signal_mask   = 0;
-    (*task).in_sigsuspend       = false;
-    (*task).alarm_deadline      = 0;
-    (*task).signal_handlers     = [0u64; NSIG];
-    (*task).shm_attaches    = [const { crate::kernel::shm::ShmAttach::empty() }; crate::kernel::shm::MAX_ATTACH];
-    (*task).mmap_regions    = [const { MmapRegion::empty() }; MAX_MMAP_REGIONS];
-    (*task).mmap_nregions   = 0;
-
-    // Map the signal-return trampoline page as writable so copy_to_region_in
-    // can write to it in supervisor mode (CR0.WP faults on non-writable pages
-    // regardless of privilege level).  User-writable is acceptable here.
-    if paging_allocator::map_user_region_in(cr3, USER_SIGTRAMP, 1, true, true).is_ok() {
-        paging_allocator::copy_to_region_in(cr3, USER_SIGTRAMP, SIGTRAMP_BYTES);
-    }
+    let task = unsafe { &mut (*sched).tasks[slot] };
+
+    task.state           = TaskState::Ready;
+    task.first_run       = true;
+    task.ctx             = TaskContext::zeroed();
+    task.entry           = entry;
+    task.cr3             = cr3;
+    task.pid             = pid;
+    task.parent_pid      = parent_pid;
+    task.pgid            = 0;
+    task.sid             = 0;
+    task.job_report      = 0;
+    task.fs_base         = 0;
+    task.heap_end        = 0;
+    task.mmap_end        = 0;
+    task.layout          = prog.layout;
+    task.output_len          = 0;
+    task.fd_table            = FdTable::new();
+    task.pending_signals     = 0;
+    task.signal_mask         = 0;
+    task.saved_signal_mask   = 0;
+    task.in_sigsuspend       = false;
+    task.timers              = TaskTimers::NONE;
+    task.cpu                 = CpuUsage::ZERO;
+    task.child_cpu           = CpuUsage::ZERO;
+    task.signal_actions      = [SigAction::DEFAULT; NSIG];
+    task.sigaltstack         = SignalStack::DISABLED;
+    task.blocked_syscall     = None;
+    task.shm_attaches    = [const { crate::kernel::shm::ShmAttach::empty() }; crate::kernel::shm::MAX_ATTACH];
+    task.mmap_regions    = [const { MmapRegion::empty() }; MAX_MMAP_REGIONS];
+    task.mmap_nregions   = 0;
+    task.image_maps      = [ImageMap::new(prog.image.start, prog.image.end, name), prog.interp_map];
+    task.core_rlimit     = (RLIM_INFINITY, RLIM_INFINITY);
+    task.trace           = TraceState::NONE;
+    // Kernel-spawned tasks run as root.
+    task.cred            = Credentials::ROOT;
+    task.dumpable        = true;
+    unsafe { systrace::reset(slot); }
 
     // Build the System V AMD64 argv block on the user stack (argv[0] = name).
-    let initial_rsp = unsafe { write_argv_to_stack(cr3, USER_STACK_TOP, &[name]) };
-    (*task).initial_rsp = initial_rsp;
+    let initial_rsp = unsafe { write_argv_to_stack(cr3, &prog, &[name], &Credentials::ROOT, false) };
+    task.initial_rsp = initial_rsp;
 
     let bytes = name.as_bytes();
     let len   = bytes.len().min(16);
-    let name_dst = core::ptr::addr_of_mut!((*task).name) as *mut u8;
+    let name_dst = task.name.as_mut_ptr();
     unsafe { core::ptr::copy_nonoverlapping(bytes.as_ptr(), name_dst, len); }
-    (*task).name_len = len;
+    task.name_len = len;
 
     unsafe {
         SERIAL_PORT.write_str("scheduler: spawned '");
@@ -483,22 +1100,34 @@ pub unsafe fn tick() -> Option<(u8, i64)> {
     let sched = &raw mut SCHED;
     let now   = crate::kernel::timer::get_ticks();
 
+    // Keep a reserve of free frames by swapping out cold pages.
+    unsafe { crate::kernel::swap::balance(); }
+
+    // A task SIGKILLed while off the CPU is already Dead but still owns its
+    // address space; tear it down like any other exit.
+    if let Some(i) = (0..MAX_TASKS).find(|&i| unsafe {
+        matches!((*sched).tasks[i].state, TaskState::Dead(_)) && (*sched).tasks[i].cr3 != 0
+    }) {
+        return Some(unsafe { finish_exit(i) });
+    }
+
     // Wake sleeping tasks.
     for i in 0..MAX_TASKS {
         if let TaskState::Sleeping(wake) = (*sched).tasks[i].state {
-            if now >= wake { (*sched).tasks[i].state = TaskState::Ready; }
+            if now >= wake {
+                unsafe {
+                    (*sched).tasks[i].state           = TaskState::Ready;
+                    (*sched).tasks[i].blocked_syscall = None;
+                }
+            }
         }
     }
 
-    // Fire SIGALRM for any task whose alarm has expired.
+    // Fire expired alarms, interval timers and POSIX timers.
     for i in 0..MAX_TASKS {
-        let deadline = (*sched).tasks[i].alarm_deadline;
-        if deadline != 0 && now >= deadline {
-            (*sched).tasks[i].alarm_deadline = 0;
-            let pid = (*sched).tasks[i].pid;
-            if pid != 0 { unsafe { send_signal(pid, SIGALRM) }; }
-        }
+        unsafe { timers::expire(i); }
     }
+    unsafe { anonfd::expire_timers(); }
 
     // Wake tasks blocked on msgrcv_wait if their queue now has a message.
     for i in 0..MAX_TASKS {
@@ -509,25 +1138,50 @@ pub unsafe fn tick() -> Option<(u8, i64)> {
                     core::ptr::write_unaligned(
                         msg_ptr as *mut crate::kernel::ipc::Message, msg);
                 }
-                (*sched).tasks[i].ctx.rax = 0; // success
-                (*sched).tasks[i].state   = TaskState::Ready;
+                unsafe {
+                    (*sched).tasks[i].ctx.rax = 0; // success
+                    (*sched).tasks[i].state   = TaskState::Ready;
+                    (*sched).tasks[i].blocked_syscall = None;
+                }
             }
         }
     }
 
-    // Wake tasks waiting for a child that has died, and reap the child.
+    // Wake tasks in wait4 once a child has something to report (or none is
+    // left).  The call is restarted and collects the report itself.
     for i in 0..MAX_TASKS {
-        if let TaskState::Waiting(child_pid) = (*sched).tasks[i].state {
-            for j in 0..MAX_TASKS {
-                if (*sched).tasks[j].pid == child_pid {
-                    if let TaskState::Dead(code) = (*sched).tasks[j].state {
-                        (*sched).tasks[i].ctx.rax = code as u64;
-                        (*sched).tasks[i].state   = TaskState::Ready;
-                        (*sched).tasks[j].state   = TaskState::Empty;
-                        (*sched).tasks[j].pid     = 0;
-                        (*sched).tasks[j].parent_pid = 0;
-                    }
-                    break;
+        if let TaskState::Waiting(selector, options) = unsafe { (*sched).tasks[i].state } {
+            if !matches!(unsafe { wait_check(i, selector, options) }, WaitCheck::Pending) {
+                unsafe { restart_blocked(i); }
+            }
+        }
+    }
+
+    // Wake tasks blocked on an eventfd/timerfd/signalfd that became ready
+    // (or was closed); the read or write is restarted.
+    for i in 0..MAX_TASKS {
+        if let TaskState::WaitingForFd(fd, write) = unsafe { (*sched).tasks[i].state } {
+            if unsafe { anonfd::fd_ready(i, fd, write) } {
+                unsafe { restart_blocked(i); }
+            }
+        }
+    }
+
+    // Wake tasks in epoll_wait whose set was notified (the call is restarted
+    // with what is left of its timeout) or whose timeout ran out.
+    for i in 0..MAX_TASKS {
+        if let TaskState::WaitingForEvents(epfd, deadline) = unsafe { (*sched).tasks[i].state } {
+            if unsafe { epoll::woken(i, epfd) } {
+                if deadline != u64::MAX {
+                    unsafe { (*sched).tasks[i].ctx.r10 = epoll::remaining_ms(deadline, now); }
+                }
+                unsafe { restart_blocked(i); }
+            } else if now >= deadline {
+                unsafe {
+                    epoll::restore_mask(i);
+                    (*sched).tasks[i].ctx.rax         = 0;
+                    (*sched).tasks[i].state           = TaskState::Ready;
+                    (*sched).tasks[i].blocked_syscall = None;
                 }
             }
         }
@@ -548,20 +1202,21 @@ pub unsafe fn tick() -> Option<(u8, i64)> {
     (*sched).current     = idx;
     CURRENT_TASK_IDX     = idx;
 
-    // Deliver any pending signals before the task runs.
-    if (*sched).tasks[idx].pending_signals != 0 {
+    // Deliver any pending signals before the task runs (a task that has not
+    // started yet has no context to interrupt; it takes them once it has).
+    if unsafe { (*sched).tasks[idx].pending_signals != 0 && !(*sched).tasks[idx].first_run } {
         if unsafe { deliver_pending_signals(idx) } {
             // Task was killed by default action — reap it.
-            let pid = (*sched).tasks[idx].pid;
-            let cr3 = (*sched).tasks[idx].cr3;
-            if cr3 != 0 {
-                paging_allocator::free_user_page_table(cr3);
-                (*sched).tasks[idx].cr3 = 0;
-            }
-            return Some((pid, (*sched).tasks[idx].state
-                .exit_code().unwrap_or(-1)));
+            return Some(unsafe { finish_exit(idx) });
         }
+        // Stopped for its tracer, or by job control, instead.
+        if matches!(unsafe { (*sched).tasks[idx].state }, TaskState::Traced | TaskState::Stopped) { return None; }
     }
+    // A traced syscall that blocked reports its exit now.
+    if unsafe { ptrace::resume_check(idx) } { return None; }
+    unsafe { systrace::resume(idx); }
+    // Whatever woke it, the task is no longer parked in a syscall.
+    unsafe { (*sched).tasks[idx].blocked_syscall = None; }
 
     (*sched).tasks[idx].state = TaskState::Running;
     (*sched).slice_remaining  = TICKS_PER_SLICE;
@@ -582,6 +1237,7 @@ pub unsafe fn tick() -> Option<(u8, i64)> {
         options(nostack, nomem)
     );
 
+    unsafe { timers::run_begin(); }
     let exit_code = if first {
         (*sched).tasks[idx].first_run = false;
         crate::kernel::user_mode::launch_at(entry, initial_rsp, cr3)
@@ -589,6 +1245,7 @@ pub unsafe fn tick() -> Option<(u8, i64)> {
         let ctx_ptr = &raw const (*sched).tasks[idx].ctx;
         crate::kernel::user_mode::resume_user_context(&*ctx_ptr, cr3)
     };
+    unsafe { timers::run_end(idx, exit_code); }
 
     match exit_code {
         EXIT_PREEMPTED => {
@@ -597,41 +1254,86 @@ pub unsafe fn tick() -> Option<(u8, i64)> {
         }
         EXIT_SLEEPING => None,
         code => {
-            let pid        = (*sched).tasks[idx].pid;
-            let parent_pid = (*sched).tasks[idx].parent_pid;
             (*sched).tasks[idx].state = TaskState::Dead(code);
+            Some(unsafe { finish_exit(idx) })
+        }
+    }
+}
 
-            // Free user-space physical frames immediately — waitpid only needs the
-            // exit code which is stored in the Dead variant.
-            let cr3 = (*sched).tasks[idx].cr3;
-            if cr3 != 0 {
-                paging_allocator::free_user_page_table(cr3);
-                (*sched).tasks[idx].cr3 = 0;
-            }
-            unsafe {
-                SERIAL_PORT.write_str("scheduler: pid=");
-                SERIAL_PORT.write_decimal(pid as u32);
-                SERIAL_PORT.write_str(" '");
-                let nlen = (*sched).tasks[idx].name_len;
-                let mut nb = [0u8; 16];
-                let nsrc = core::ptr::addr_of!((*sched).tasks[idx].name) as *const u8;
-                core::ptr::copy_nonoverlapping(nsrc, nb.as_mut_ptr(), nlen);
-                if let Ok(s) = core::str::from_utf8(&nb[..nlen]) {
-                    SERIAL_PORT.write_str(s);
-                }
-                SERIAL_PORT.write_str("' exited (code ");
-                SERIAL_PORT.write_decimal(code as u32);
-                SERIAL_PORT.write_str(")\n");
-            }
+/// Tear down a task that has just become `Dead`: detach it from tracers and
+/// anonymous fds, free its address space, hand its children to the reaper
+/// and tell its parent.  Returns `(pid, exit_code)` for `tick()`'s caller.
+unsafe fn finish_exit(idx: usize) -> (u8, i64) {
+    let sched = &raw mut SCHED;
+    let pid   = unsafe { (*sched).tasks[idx].pid };
+    let code  = unsafe { (*sched).tasks[idx].state.exit_code().unwrap_or(-1) };
+    unsafe { ptrace::on_exit(idx); }
+    unsafe { systrace::on_exit(idx); }
+    unsafe { anonfd::on_exit(idx); }
+    unsafe { crate::kernel::pty::on_exit(idx); }
+    unsafe { crate::kernel::evdev::on_exit(idx); }
+    unsafe { crate::kernel::dsp::on_exit(idx); }
+
+    // Free user-space physical frames immediately — waitpid only needs the
+    // exit code which is stored in the Dead variant.  Shared file
+    // mappings are flushed first, while the page table is still live.
+    unsafe { crate::kernel::mmap::release_task(idx); }
+    let cr3 = unsafe { (*sched).tasks[idx].cr3 };
+    if cr3 != 0 {
+        unsafe {
+            paging_allocator::free_user_page_table(cr3);
+            (*sched).tasks[idx].cr3 = 0;
+        }
+    }
+    unsafe {
+        SERIAL_PORT.write_str("scheduler: pid=");
+        SERIAL_PORT.write_decimal(pid as u32);
+        SERIAL_PORT.write_str(" '");
+        let nlen = (*sched).tasks[idx].name_len;
+        let mut nb = [0u8; 16];
+        let nsrc = core::ptr::addr_of!((*sched).tasks[idx].name) as *const u8;
+        core::ptr::copy_nonoverlapping(nsrc, nb.as_mut_ptr(), nlen);
+        if let Ok(s) = core::str::from_utf8(&nb[..nlen]) {
+            SERIAL_PORT.write_str(s);
+        }
+        SERIAL_PORT.write_str("' exited (code ");
+        SERIAL_PORT.write_decimal(code as u32);
+        SERIAL_PORT.write_str(")\n");
+    }
+    if pid == INIT_PID && unsafe { (*sched).init } {
+        unsafe { SERIAL_PORT.write_str("scheduler: init exited — orphans are no longer reaped\n"); }
+    }
 
-            // Deliver SIGCHLD to parent so bash/shells notice child exit.
-            if parent_pid != 0 {
-                send_signal(parent_pid, SIGCHLD);
-            }
+    // A session leader's exit hangs up its terminal; process groups the
+    // exit orphans are dealt with as POSIX requires.
+    unsafe { crate::kernel::jobctl::on_exit(idx); }
+
+    // Orphans go to init; zombies among them are announced so it reaps them.
+    let reaper = reaper();
+    let mut zombies = false;
+    for i in 0..MAX_TASKS {
+        let t = unsafe { &mut (*sched).tasks[i] };
+        if i == idx || t.pid == 0 || t.parent_pid != pid { continue; }
+        t.parent_pid = reaper;
+        zombies |= matches!(t.state, TaskState::Dead(_));
+    }
+    if zombies && reaper != 0 {
+        unsafe { send_signal(reaper, SIGCHLD); }
+    }
 
-            Some((pid, code))
+    // Deliver SIGCHLD to parent so bash/shells notice child exit.  A parent
+    // that ignores SIGCHLD never waits: the slot is freed straight away.
+    let parent_pid = unsafe { (*sched).tasks[idx].parent_pid };
+    if let Some(p) = (0..MAX_TASKS).find(|&i| parent_pid != 0 && unsafe { (*sched).tasks[i].pid } == parent_pid) {
+        let action = unsafe { (*sched).tasks[p].signal_actions[SIGCHLD as usize] };
+        if action.handler == SIG_IGN || action.flags & SA_NOCLDWAIT != 0 {
+            unsafe { (*sched).tasks[idx].parent_pid = 0; }
+        } else {
+            unsafe { send_signal(parent_pid, SIGCHLD); }
         }
     }
+
+    (pid, code)
 }
 
 /// Called from the timer ISR when the running task's slice expires.
@@ -645,9 +1347,10 @@ pub unsafe fn preempt(ctx: TaskContext) -> ! {
 
 /// Called by the Sleep syscall.  Yields until `wake_tick`.
 pub unsafe fn sleep_task(wake_tick: u64, mut ctx: TaskContext) -> ! {
-    ctx.rax = 0;
     let sched = &raw mut SCHED;
     let cur   = (*sched).current;
+    unsafe { (*sched).tasks[cur].blocked_syscall = Some(ctx.rax); }
+    ctx.rax = 0;
     (*sched).tasks[cur].ctx   = ctx;
     (*sched).tasks[cur].state = TaskState::Sleeping(wake_tick);
     crate::kernel::user_mode::exit_to_kernel(EXIT_SLEEPING)
@@ -658,24 +1361,67 @@ pub unsafe fn sleep_task(wake_tick: u64, mut ctx: TaskContext) -> ! {
 /// SIGKILL kills immediately; all other signals set a pending bit for delivery
 /// before the next time the task runs.  Returns `false` if pid not found.
 pub unsafe fn send_signal(pid: u8, signum: u8) -> bool {
+    unsafe { send_signal_info(pid, signum, SigInfo::EMPTY) }
+}
+
+/// `send_signal` with an explicit `siginfo_t` payload for the handler.
+pub unsafe fn send_signal_info(pid: u8, signum: u8, info: SigInfo) -> bool {
     if signum == 0 || signum as usize >= NSIG { return false; }
     let sched = &raw mut SCHED;
     for i in 0..MAX_TASKS {
-        let task = &raw mut (*sched).tasks[i];
-        if (*task).pid != pid { continue; }
-        if matches!((*task).state, TaskState::Empty | TaskState::Dead(_)) { break; }
+        let task = unsafe { &raw mut (*sched).tasks[i] };
+        if unsafe { (*task).pid } != pid { continue; }
+        if matches!(unsafe { (*task).state }, TaskState::Empty | TaskState::Dead(_)) { break; }
 
         if signum == SIGKILL {
-            // SIGKILL cannot be caught or ignored — kill immediately.
-            (*task).state = TaskState::Dead(128 + signum as i64);
+            // SIGKILL cannot be caught or ignored — kill immediately.  A
+            // ptrace-stopped task is resumed instead so tick() reaps it.
+            unsafe {
+                if (*task).state == TaskState::Traced {
+                    (*task).pending_signals |= 1u32 << (SIGKILL as u32 - 1);
+                    (*task).state = TaskState::Ready;
+                } else {
+                    (*task).state = TaskState::Dead(-(signum as i64));
+                }
+            }
             return true;
         }
 
-        // Set the pending bit.
-        (*task).pending_signals |= 1u32 << (signum as u32 - 1);
+        // Set the pending bit.  SIGCONT resumes a stopped task whatever its
+        // disposition; it and the stop signals cancel each other's pending
+        // instances.
+        let bit = 1u32 << (signum as u32 - 1);
+        if signum == SIGCONT {
+            unsafe {
+                (*task).pending_signals &= !STOP_SIGNALS;
+                if (*task).state == TaskState::Stopped { continue_task(i); }
+            }
+        } else if STOP_SIGNALS & bit != 0 {
+            unsafe { (*task).pending_signals &= !(1u32 << (SIGCONT as u32 - 1)); }
+        }
+        unsafe {
+            (*task).pending_signals |= bit;
+            (*task).signal_info[signum as usize] = info;
+        }
+        anonfd::signal_queued(signum as usize);
         // If the task is sleeping, wake it so it can process the signal.
-        if matches!((*task).state, TaskState::Sleeping(_)) {
-            (*task).state = TaskState::Ready;
+        // A task blocked in waitpid/msgrcv is only interrupted when a handler
+        // will actually run (SIGCHLD already wakes waitpid on its own), or
+        // to stop — the call is then re-issued once it is continued.
+        let handled = unsafe {
+            (*task).signal_actions[signum as usize].handler > SIG_IGN
+                && (*task).signal_mask & bit == 0
+        };
+        let stops = STOP_SIGNALS & bit != 0 && unsafe { will_stop(i, signum) };
+        match unsafe { (*task).state } {
+            TaskState::Sleeping(_) => unsafe { (*task).state = TaskState::Ready },
+            TaskState::Waiting(..) | TaskState::WaitingForMsg(..) | TaskState::WaitingForFd(..)
+            | TaskState::WaitingForEvents(..)
+                if handled && signum != SIGCHLD => unsafe { (*task).state = TaskState::Ready },
+            TaskState::Waiting(..) | TaskState::WaitingForMsg(..) | TaskState::WaitingForFd(..)
+            | TaskState::WaitingForEvents(..)
+                if stops => unsafe { restart_blocked(i) },
+            _ => {}
         }
         return true;
     }
@@ -688,97 +1434,201 @@ pub unsafe fn kill(pid: u8) -> bool {
     unsafe { send_signal(pid, SIGKILL) }
 }
 
+/// Send `signum` to every live task in process group `pgrp`, as the kernel
+/// does for terminal-generated signals.  Returns `false` if the group has
+/// no live members.
+pub unsafe fn signal_group(pgrp: u8, signum: u8) -> bool {
+    unsafe { signal_group_info(pgrp, signum, SigInfo::EMPTY) }
+}
+
+/// `signal_group` with an explicit `siginfo_t` payload (`kill(-pgrp, sig)`).
+pub unsafe fn signal_group_info(pgrp: u8, signum: u8, info: SigInfo) -> bool {
+    let sched = &raw const SCHED;
+    let mut any = false;
+    for i in 0..MAX_TASKS {
+        let t = unsafe { &(*sched).tasks[i] };
+        if t.is_live() && t.pgrp() == pgrp {
+            any |= unsafe { send_signal_info(t.pid, signum, info) };
+        }
+    }
+    any
+}
+
+// ── Job-control stops ──────────────────────────────────────────────────────
+
+/// SIGSTOP, SIGTSTP, SIGTTIN and SIGTTOU, as a pending-signal mask.
+const STOP_SIGNALS: u32 = 1 << (SIGSTOP - 1) | 1 << (SIGTSTP - 1)
+                        | 1 << (SIGTTIN - 1) | 1 << (SIGTTOU - 1);
+
+/// Whether stop signal `signum` would stop the task at `idx`: it is left
+/// at its default action and not blocked, and — for the three terminal
+/// stops — the task's process group is not orphaned, since nobody would
+/// be left to continue it (POSIX discards the signal instead).
+unsafe fn will_stop(idx: usize, signum: u8) -> bool {
+    let t = unsafe { &(*(&raw const SCHED)).tasks[idx] };
+    if signum == SIGSTOP { return true; }
+    t.signal_actions[signum as usize].handler == SIG_DFL
+        && t.signal_mask & 1u32 << (signum - 1) == 0
+        && !unsafe { crate::kernel::jobctl::orphaned(t.pgrp()) }
+}
+
+/// Stop the task at `idx` for `signum` and tell its parent.
+unsafe fn stop_task(idx: usize, signum: u8) {
+    let task = unsafe { &mut (*(&raw mut SCHED)).tasks[idx] };
+    task.state      = TaskState::Stopped;
+    task.job_report = (signum as u32) << 8 | 0x7f;
+    unsafe { notify_job_change(idx); }
+}
+
+/// SIGCONT for the stopped task at `idx`: run it again, re-issuing the
+/// syscall it was parked in, and tell its parent.
+unsafe fn continue_task(idx: usize) {
+    let task = unsafe { &raw mut (*(&raw mut SCHED)).tasks[idx] };
+    if unsafe { (*task).blocked_syscall.is_some() } {
+        unsafe { restart_blocked(idx); }
+    } else {
+        unsafe { (*task).state = TaskState::Ready; }
+    }
+    unsafe { (*task).job_report = 0xffff; }
+    unsafe { notify_job_change(idx); }
+}
+
+/// SIGCHLD for a stop or continue, unless the parent asked not to be told
+/// (`SA_NOCLDSTOP`).  A parent in `wait4` is woken by `tick()` regardless.
+unsafe fn notify_job_change(idx: usize) {
+    let sched = &raw const SCHED;
+    let parent_pid = unsafe { (*sched).tasks[idx].parent_pid };
+    let Some(p) = (0..MAX_TASKS).find(|&i| parent_pid != 0 && unsafe { (*sched).tasks[i].pid } == parent_pid)
+        else { return };
+    if unsafe { (*sched).tasks[p].signal_actions[SIGCHLD as usize].flags } & SA_NOCLDSTOP == 0 {
+        unsafe { send_signal(parent_pid, SIGCHLD); }
+    }
+}
+
+/// Queue a synchronous fault signal (SIGSEGV, SIGFPE, ...) for the running
+/// task, whose registers at the faulting instruction are `ctx`.
+///
+/// Returns `false` if no handler can run — the signal is blocked, ignored or
+/// left at its default — in which case the caller kills the task, as Linux
+/// does for a forced signal.  On `true` the caller yields to the scheduler,
+/// which builds the handler frame before the task next runs.
+///
+/// A traced task always takes the signal so its tracer sees the fault; a
+/// blocked or ignored one is reset to the default action first.
+pub unsafe fn raise_fault(signum: u8, info: SigInfo, ctx: TaskContext) -> bool {
+    let sched = &raw mut SCHED;
+    let task  = unsafe { &mut (*sched).tasks[(*sched).current] };
+    let bit   = 1u32 << (signum as u32 - 1);
+    if task.signal_actions[signum as usize].handler <= SIG_IGN
+        || task.signal_mask & bit != 0
+    {
+        if !task.trace.is_traced() { return false; }
+        task.signal_mask &= !bit;
+        if task.signal_actions[signum as usize].handler == SIG_IGN {
+            task.signal_actions[signum as usize] = SigAction::DEFAULT;
+        }
+    }
+    task.ctx = ctx;
+    task.pending_signals |= bit;
+    task.signal_info[signum as usize] = info;
+    true
+}
+
 /// Deliver any pending signals for the task at `idx`.
 ///
 /// Called from `tick()` just before running the task.
 /// Returns `true` if the task was killed by a default-action signal.
 unsafe fn deliver_pending_signals(idx: usize) -> bool {
     let sched = &raw mut SCHED;
-    let task  = &raw mut (*sched).tasks[idx];
+    let task  = unsafe { &raw mut (*sched).tasks[idx] };
 
     // SIGKILL (9) and SIGSTOP (19) cannot be blocked; all other signals
     // respect signal_mask.
     let always_deliverable = (1u32 << (SIGKILL as u32 - 1)) | (1u32 << (SIGSTOP as u32 - 1));
-    let deliverable = ((*task).pending_signals & !(*task).signal_mask)
-                    | ((*task).pending_signals &  always_deliverable);
+    let deliverable = unsafe {
+        ((*task).pending_signals & !(*task).signal_mask)
+            | ((*task).pending_signals &  always_deliverable)
+    };
     if deliverable == 0 { return false; }
 
-    // Work only on deliverable bits; leave masked signals in pending_signals.
+    // Synchronous faults go first so the frame describes the faulting
+    // instruction rather than some unrelated asynchronous signal.
+    let faults = [SIGSEGV, SIGBUS, SIGILL, SIGFPE, SIGTRAP].iter()
+        .fold(0u32, |m, &s| m | 1u32 << (s as u32 - 1));
     let mut to_deliver = deliverable;
-    (*task).pending_signals &= !deliverable; // will be restored if handler re-queues
 
     while to_deliver != 0 {
         // Find lowest set bit (signal number = bit position + 1).
-        let bit    = to_deliver.trailing_zeros();
+        let pick   = if to_deliver & faults != 0 { to_deliver & faults } else { to_deliver };
+        let bit    = pick.trailing_zeros();
         let signum = (bit + 1) as u8;
         to_deliver &= !(1u32 << bit);
+        unsafe { (*task).pending_signals &= !(1u32 << bit); }
 
-        let handler = if (signum as usize) < NSIG {
-            (*task).signal_handlers[signum as usize]
-        } else {
-            SIG_DFL
-        };
+        let action = unsafe { (*task).signal_actions[signum as usize] };
+        let info   = unsafe { (*task).signal_info[signum as usize] };
+
+        // A tracer sees every signal first (signal-delivery-stop).
+        if unsafe { ptrace::signal_stop(idx, signum, &info) } {
+            return false;
+        }
 
-        if handler == SIG_IGN {
+        if action.handler == SIG_IGN {
             continue; // explicitly ignored
         }
 
-        if handler == SIG_DFL {
+        if action.handler == SIG_DFL {
             // Default action: most signals terminate the process.
             match signum {
-                SIGCHLD | SIGCONT => continue, // default = ignore
+                SIGCHLD | SIGCONT | SIGWINCH => continue, // default = ignore
+                SIGSTOP | SIGTSTP | SIGTTIN | SIGTTOU => {
+                    if !unsafe { will_stop(idx, signum) } { continue; }
+                    unsafe { stop_task(idx, signum); }
+                    return false;
+                }
                 _ => {
-                    (*task).state = TaskState::Dead(128 + signum as i64);
+                    unsafe {
+                        let ctx = (*task).ctx;
+                        crate::kernel::coredump::dump(idx, signum, &ctx);
+                        (*task).state = TaskState::Dead(-(signum as i64));
+                    }
                     return true;
                 }
             }
         }
 
-        // User-defined handler: set up a signal frame on the user stack.
-        let cr3 = (*task).cr3;
-        let rsp = (*task).ctx.rsp;
-
-        // Push trampoline address (return address for the handler).
-        let rsp = rsp - 8;
-        let tramp = USER_SIGTRAMP;
-        paging_allocator::copy_to_region_in(
-            cr3, rsp, &tramp.to_ne_bytes());
-
-        // Push SignalFrame below that.
-        let frame = SignalFrame {
-            rip:    (*task).ctx.rip,
-            rax:    (*task).ctx.rax,
-            rbx:    (*task).ctx.rbx,
-            rcx:    (*task).ctx.rcx,
-            rdx:    (*task).ctx.rdx,
-            rsi:    (*task).ctx.rsi,
-            rdi:    (*task).ctx.rdi,
-            r8:     (*task).ctx.r8,
-            r9:     (*task).ctx.r9,
-            r10:    (*task).ctx.r10,
-            r11:    (*task).ctx.r11,
-            r12:    (*task).ctx.r12,
-            r13:    (*task).ctx.r13,
-            r14:    (*task).ctx.r14,
-            r15:    (*task).ctx.r15,
-            rbp:    (*task).ctx.rbp,
-            rflags: (*task).ctx.rflags,
-        };
-        let frame_size = core::mem::size_of::<SignalFrame>() as u64;
-        let rsp = rsp - frame_size;
-        // Align to 16 bytes as required by the System V ABI.
-        let rsp = rsp & !0xF;
-
-        let frame_bytes = core::slice::from_raw_parts(
-            &frame as *const SignalFrame as *const u8,
-            frame_size as usize,
-        );
-        paging_allocator::copy_to_region_in(cr3, rsp, frame_bytes);
-
-        // Redirect execution to the handler.
-        (*task).ctx.rsp = rsp;
-        (*task).ctx.rip = handler;
-        (*task).ctx.rdi = signum as u64; // first argument: signal number
+        // A handler is about to run: settle any blocking syscall it interrupts.
+        if let Some(nr) = unsafe { (*task).blocked_syscall.take() } {
+            unsafe { ptrace::syscall_abandon(idx); }
+            if action.flags & SA_RESTART != 0 {
+                unsafe {
+                    (*task).ctx.rip -= SYSCALL_INSN_LEN;
+                    (*task).ctx.rax  = nr;
+                    systrace::finish(idx, Outcome::Unfinished);
+                }
+            } else {
+                unsafe {
+                    (*task).ctx.rax  = EINTR as u64;
+                    systrace::finish(idx, Outcome::Returned(EINTR));
+                }
+            }
+        }
+
+        if !unsafe { setup_signal_frame(task, signum, &action, &info) } {
+            // The frame could not be written (stack overflow, bad altstack
+            // or RSP): SIGSEGV with its default action, like Linux's
+            // force_sigsegv, core dump included.
+            unsafe {
+                (*task).signal_actions[SIGSEGV as usize] = SigAction::DEFAULT;
+                let ctx = (*task).ctx;
+                crate::kernel::coredump::dump(idx, SIGSEGV, &ctx);
+                (*task).state = TaskState::Dead(-(SIGSEGV as i64));
+            }
+            return true;
+        }
+        if action.flags & SA_RESETHAND != 0 {
+            unsafe { (*task).signal_actions[signum as usize] = SigAction::DEFAULT; }
+        }
 
         // Only deliver one signal per tick to avoid stack overflow.
         break;
@@ -786,6 +1636,91 @@ unsafe fn deliver_pending_signals(idx: usize) -> bool {
     false
 }
 
+/// Push a `SignalFrame` for `signum` and redirect the task to its handler.
+/// Returns `false` if the target stack is not writable.
+unsafe fn setup_signal_frame(
+    task:   *mut Task,
+    signum: u8,
+    action: &SigAction,
+    info:   &SigInfo,
+) -> bool {
+    let task = unsafe { &mut *task };
+    let cr3 = task.cr3;
+    let ctx = task.ctx;
+    let alt = task.sigaltstack;
+
+    // SA_ONSTACK switches to the alternate stack unless already on it.
+    let on_alt = alt.contains(ctx.rsp);
+    let top = if action.flags & SA_ONSTACK != 0 && alt.flags & SS_DISABLE == 0 && !on_alt {
+        alt.sp.checked_add(alt.size)
+    } else {
+        ctx.rsp.checked_sub(RED_ZONE)
+    };
+    let frame_size = core::mem::size_of::<SignalFrame>() as u64;
+    // Like a `call`: RSP + 8 is 16-byte aligned when the handler starts.
+    // The user picks RSP, so anything may wrap or leave user space.
+    let Some(rsp) = top
+        .and_then(|t| t.checked_sub(frame_size))
+        .and_then(|t| (t & !0xF).checked_sub(8))
+    else { return false };
+    if validate_user_range(rsp, frame_size).is_err() { return false; }
+
+    // The mask restored on sigreturn: sigsuspend's caller mask if the task
+    // was suspended, otherwise the mask in force right now.
+    let old_mask = if task.in_sigsuspend {
+        task.in_sigsuspend = false;
+        task.saved_signal_mask
+    } else {
+        task.signal_mask
+    };
+
+    let restorer = if action.flags & SA_RESTORER != 0 && action.restorer != 0 {
+        action.restorer
+    } else {
+        task.layout.sigtramp
+    };
+    let mut stack = alt;
+    stack.flags = if alt.flags & SS_DISABLE != 0 { SS_DISABLE }
+                  else if alt.contains(rsp) { SS_ONSTACK } else { 0 };
+    let frame = SignalFrame {
+        restorer,
+        uc: UContext {
+            flags:    0,
+            link:     0,
+            stack,
+            mcontext: SigContext::from_ctx(&ctx, info, old_mask as u64),
+            sigmask:  old_mask as u64,
+        },
+        info: UserSigInfo::new(signum, info),
+    };
+
+    if !unsafe { paging_allocator::prepare_user_write_in(cr3, rsp, frame_size as usize) } {
+        return false;
+    }
+    let frame_bytes = unsafe { core::slice::from_raw_parts(
+        &frame as *const SignalFrame as *const u8,
+        frame_size as usize,
+    ) };
+    unsafe { paging_allocator::copy_to_region_in(cr3, rsp, frame_bytes); }
+
+    // Block the handler's sa_mask, plus the signal itself unless SA_NODEFER.
+    let unblockable = (1u32 << (SIGKILL as u32 - 1)) | (1u32 << (SIGSTOP as u32 - 1));
+    let mut block = action.mask as u32;
+    if action.flags & SA_NODEFER == 0 { block |= 1u32 << (signum as u32 - 1); }
+    task.signal_mask = (old_mask | block) & !unblockable;
+
+    // Redirect execution to the handler: handler(signo, &info, &uc).
+    let frame_ptr = rsp;
+    task.ctx.rsp = rsp;
+    task.ctx.rip = action.handler;
+    task.ctx.rdi = signum as u64;
+    task.ctx.rsi = frame_ptr + core::mem::offset_of!(SignalFrame, info) as u64;
+    task.ctx.rdx = frame_ptr + core::mem::offset_of!(SignalFrame, uc) as u64;
+    task.ctx.rax = 0;
+    task.ctx.rflags &= !(RFLAGS_DF | RFLAGS_TF);
+    true
+}
+
 /// Create a child process that is a full copy of the task at `parent_idx`.
 ///
 /// `child_ctx` is the register snapshot to use for the child (caller sets
@@ -799,82 +1734,112 @@ pub unsafe fn fork_task(
 
     // Find a free slot (any slot other than the parent's).
     let child_slot = (0..MAX_TASKS)
-        .find(|&i| i != parent_idx
-            && matches!((*sched).tasks[i].state, TaskState::Empty | TaskState::Dead(_)))
+        .find(|&i| i != parent_idx && unsafe { slot_free(i) })
         .ok_or("max tasks reached")?;
 
-    let parent_cr3 = (*sched).tasks[parent_idx].cr3;
+    let parent     = unsafe { &(*sched).tasks[parent_idx] };
+    let parent_cr3 = parent.cr3;
 
     // Build the shm exclude ranges (shared memory must remain truly shared
     // across fork, not become COW-private) and the stack range (always
     // deep-copied so kernel-side writes never hit a read-only COW page).
     let mut shm_ranges = [(0u64, 0u64); crate::kernel::shm::MAX_ATTACH];
     let mut shm_count  = 0;
-    for attach in &(*sched).tasks[parent_idx].shm_attaches {
+    for attach in &parent.shm_attaches {
         if !attach.active { continue; }
         let pages = crate::kernel::shm::segment_pages(attach.shmid);
         if pages == 0 { continue; }
         shm_ranges[shm_count] = (attach.vaddr, attach.vaddr + (pages * PAGE_SIZE) as u64);
         shm_count += 1;
     }
-    let stack_range = (USER_STACK_TOP - (USER_STACK_PAGES * PAGE_SIZE) as u64, USER_STACK_TOP);
+    let parent_layout = parent.layout;
+    let stack_range   = (parent_layout.stack_base(), parent_layout.stack_top);
+    // MAP_SHARED mmap regions stay shared between parent and child.
+    let shared_ranges = crate::kernel::mmap::shared_ranges(parent);
 
     // Build the child's page table with copy-on-write sharing.
     let child_cr3 = unsafe {
-        paging_allocator::cow_fork_user_page_table(parent_cr3, stack_range, &shm_ranges[..shm_count])
+        paging_allocator::cow_fork_user_page_table(
+            parent_cr3, stack_range, &shm_ranges[..shm_count], &shared_ranges,
+        )
     }.ok_or("OOM: fork page table")?;
 
     let child_pid  = (child_slot + 1) as u8;
-    let parent_pid = (*sched).tasks[parent_idx].pid;
+    let parent_pid = parent.pid;
 
     // Copy all task fields from parent; override the child-specific ones.
-    let parent_fd       = (*sched).tasks[parent_idx].fd_table;
-    let parent_heap     = (*sched).tasks[parent_idx].heap_end;
-    let parent_mmap     = (*sched).tasks[parent_idx].mmap_end;
-    let parent_mregions = (*sched).tasks[parent_idx].mmap_regions;
-    let parent_nregions = (*sched).tasks[parent_idx].mmap_nregions;
-    let parent_entry   = (*sched).tasks[parent_idx].entry;
-    let parent_name    = (*sched).tasks[parent_idx].name;
-    let parent_nlen    = (*sched).tasks[parent_idx].name_len;
-    let parent_cwd     = (*sched).tasks[parent_idx].cwd;
-    let parent_cwdl    = (*sched).tasks[parent_idx].cwd_len;
-    let parent_sighand = (*sched).tasks[parent_idx].signal_handlers;
-
-    let child = &raw mut (*sched).tasks[child_slot];
-    (*child).state      = TaskState::Ready;
-    (*child).ctx        = child_ctx;
-    (*child).first_run  = false;   // resume via context restore
-    (*child).entry      = parent_entry;
-    (*child).cr3        = child_cr3;
-    (*child).pid        = child_pid;
-    (*child).parent_pid = parent_pid;
-    (*child).pgid       = (*sched).tasks[parent_idx].pgid; // inherit parent's pgid
-    (*child).heap_end   = parent_heap;
-    (*child).mmap_end   = parent_mmap;
-    (*child).output_len = 0;
-    (*child).fd_table   = parent_fd;
-    (*child).cwd             = parent_cwd;
-    (*child).cwd_len         = parent_cwdl;
+    let parent_fd       = parent.fd_table;
+    let parent_heap     = parent.heap_end;
+    let parent_mmap     = parent.mmap_end;
+    let parent_entry   = parent.entry;
+    let parent_name    = parent.name;
+    let parent_nlen    = parent.name_len;
+    let parent_cwd     = parent.cwd;
+    let parent_cwdl    = parent.cwd_len;
+    let parent_sighand = parent.signal_actions;
+    let parent_altstk  = parent.sigaltstack;
+
+    let child = unsafe { &mut (*sched).tasks[child_slot] };
+    child.state      = TaskState::Ready;
+    child.ctx        = child_ctx;
+    child.first_run  = false;   // resume via context restore
+    child.entry      = parent_entry;
+    child.cr3        = child_cr3;
+    child.pid        = child_pid;
+    child.parent_pid = parent_pid;
+    child.pgid       = parent.pgrp();    // inherit parent's group
+    child.sid        = parent.session(); // and session
+    child.job_report = 0;
+    child.heap_end   = parent_heap;
+    child.mmap_end   = parent_mmap;
+    child.layout     = parent_layout;
+    child.output_len = 0;
+    child.fd_table   = parent_fd;
+    child.cwd             = parent_cwd;
+    child.cwd_len         = parent_cwdl;
     // Children inherit signal handlers but start with clean pending mask, mask, alarm, and shm.
-    (*child).pending_signals   = 0;
-    (*child).signal_mask       = 0;
-    (*child).saved_signal_mask = 0;
-    (*child).in_sigsuspend     = false;
-    (*child).alarm_deadline    = 0;
-    (*child).signal_handlers   = parent_sighand;
-    (*child).shm_attaches    = [const { crate::kernel::shm::ShmAttach::empty() }; crate::kernel::shm::MAX_ATTACH];
-    (*child).mmap_regions    = parent_mregions;
-    (*child).mmap_nregions   = parent_nregions;
-    // Addref every pipe end the child inherited so reference counts stay correct.
-    for slot in &(*child).fd_table.entries {
+    child.pending_signals   = 0;
+    child.signal_mask       = 0;
+    child.saved_signal_mask = 0;
+    child.in_sigsuspend     = false;
+    child.signal_actions    = parent_sighand;
+    child.sigaltstack       = parent_altstk;
+    child.blocked_syscall   = None;
+    child.shm_attaches    = [const { crate::kernel::shm::ShmAttach::empty() }; crate::kernel::shm::MAX_ATTACH];
+    child.mmap_regions    = parent.mmap_regions;
+    child.mmap_nregions   = parent.mmap_nregions;
+    child.image_maps      = parent.image_maps;
+    child.core_rlimit     = parent.core_rlimit;
+    child.cred            = parent.cred;
+    child.dumpable        = parent.dumpable;
+    child.initial_rsp     = parent.initial_rsp;
+    // Tracing is not inherited (no PTRACE_O_TRACEFORK).
+    child.trace           = TraceState::NONE;
+    // Timers are not inherited; CPU usage starts from zero.
+    child.timers          = TaskTimers::NONE;
+    child.cpu             = CpuUsage::ZERO;
+    child.child_cpu       = CpuUsage::ZERO;
+    // Addref every pipe end, anonymous fd, pty end, input client and sound
+    // stream the child inherited so reference counts stay correct.
+    for slot in &child.fd_table.entries {
         if let Some(e) = slot {
-            if e.backend == crate::kernel::fs::ramfs::FdBackend::Pipe {
-                crate::kernel::pipe::addref(e.raw_fd);
+            unsafe {
+                match e.backend {
+                    crate::kernel::fs::ramfs::FdBackend::Pipe => crate::kernel::pipe::addref(e.raw_fd),
+                    crate::kernel::fs::ramfs::FdBackend::Anon => anonfd::addref(e.raw_fd),
+                    crate::kernel::fs::ramfs::FdBackend::PtyMaster
+                    | crate::kernel::fs::ramfs::FdBackend::Tty => crate::kernel::pty::addref(e),
+                    crate::kernel::fs::ramfs::FdBackend::Evdev => crate::kernel::evdev::addref(e),
+                    crate::kernel::fs::ramfs::FdBackend::Dsp   => crate::kernel::dsp::addref(e),
+                    _ => {}
+                }
             }
         }
     }
-    (*child).name       = parent_name;
-    (*child).name_len   = parent_nlen;
+    child.name       = parent_name;
+    child.name_len   = parent_nlen;
+    unsafe { systrace::reset(child_slot); }
+    unsafe { crate::kernel::mmap::on_fork(child_slot); }
 
     unsafe {
         SERIAL_PORT.write_str("scheduler: fork parent=");
@@ -889,22 +1854,151 @@ pub unsafe fn fork_task(
     Ok(child_pid)
 }
 
-/// Block the task at `parent_idx` until the child with `child_pid` dies.
+/// What `wait4` would find for the waiter at `waiter_idx`.
+pub enum WaitCheck {
+    /// No child (or tracee) matches the selector — `ECHILD`.
+    NoChild,
+    /// Matching children exist but none has anything to report yet.
+    Pending,
+    /// The task at this index has a stop or an exit to report.
+    Ready(usize),
+}
+
+/// `wait4` options: report job-control stops, and continues.
+pub const WUNTRACED:  u32 = 0x2;
+pub const WCONTINUED: u32 = 0x8;
+
+/// Look for a child or tracee of the task at `waiter_idx` that matches the
+/// `wait4` pid `selector` (> 0: that pid; -1: any; 0: the waiter's process
+/// group; < -1: process group `-selector`) and has something to report.
+///
+/// A tracer collects its tracees' stops and exits; the parent of a traced
+/// child collects the exit only once the tracer has seen it.  Job-control
+/// stops and continues are reported to the parent if `options` asks for
+/// them (WUNTRACED, WCONTINUED).
+pub unsafe fn wait_check(waiter_idx: usize, selector: i32, options: u32) -> WaitCheck {
+    let tasks = unsafe { &(*(&raw const SCHED)).tasks };
+    let me    = tasks[waiter_idx].pid;
+    let pgrp  = |i: usize| tasks[i].pgrp();
+    let my_pgrp = pgrp(waiter_idx);
+    let mut found = false;
+    for i in 0..MAX_TASKS {
+        let t = &tasks[i];
+        if i == waiter_idx || t.pid == 0 || t.state == TaskState::Empty { continue; }
+        let traced = t.trace.tracer == me;
+        if t.parent_pid != me && !traced { continue; }
+        let wanted = match selector {
+            -1          => true,
+            0           => pgrp(i) == my_pgrp,
+            s if s > 0  => t.pid as i32 == s,
+            s           => pgrp(i) as u32 == s.unsigned_abs(),
+        };
+        if !wanted { continue; }
+        found = true;
+        match t.state {
+            TaskState::Traced if traced && t.trace.report != 0 => return WaitCheck::Ready(i),
+            TaskState::Dead(_) if traced || !t.trace.is_traced() => return WaitCheck::Ready(i),
+            TaskState::Dead(_) => {}
+            _ if t.parent_pid == me && t.job_report != 0 => {
+                let wanted = if t.job_report == 0xffff { WCONTINUED } else { WUNTRACED };
+                if options & wanted != 0 { return WaitCheck::Ready(i); }
+            }
+            _ => {}
+        }
+    }
+    if found { WaitCheck::Pending } else { WaitCheck::NoChild }
+}
+
+/// `wait4` status word for a task that ended with `Dead(code)`.
+pub fn wait_status(code: i64) -> u32 {
+    if code < 0 {
+        (-code) as u32 & 0x7f        // killed by signal
+    } else {
+        (code as u32 & 0xff) << 8    // exit(code)
+    }
+}
+
+/// Block the task at `parent_idx` in `wait4` until `wait_check` finds a
+/// report for `selector` and `options`; the syscall is then restarted to
+/// collect it.
 ///
-/// Sets the parent's state to `Waiting(child_pid)`, saves its context, then
-/// jumps back to the scheduler via `exit_to_kernel(EXIT_SLEEPING)`.
+/// Saves the task's context, then jumps back to the scheduler via
+/// `exit_to_kernel(EXIT_SLEEPING)`.
 pub unsafe fn wait_for_pid(
     parent_idx: usize,
-    child_pid:  u8,
-    mut ctx:    crate::kernel::user_mode::TaskContext,
+    selector:   i32,
+    options:    u32,
+    ctx:        crate::kernel::user_mode::TaskContext,
+) -> ! {
+    let task = unsafe { &mut (*(&raw mut SCHED)).tasks[parent_idx] };
+    task.blocked_syscall = Some(ctx.rax);
+    task.ctx   = ctx;
+    task.state = TaskState::Waiting(selector, options);
+    unsafe { crate::kernel::user_mode::exit_to_kernel(EXIT_SLEEPING) }
+}
+
+/// Block the current task in a read (or, with `write`, a write) on `fd`,
+/// an eventfd, timerfd or signalfd, until `anonfd::fd_ready`; the syscall
+/// is then restarted.
+pub unsafe fn wait_for_fd(
+    fd:    i32,
+    write: bool,
+    ctx:   crate::kernel::user_mode::TaskContext,
+) -> ! {
+    let sched = &raw mut SCHED;
+    let task  = unsafe { &mut (*sched).tasks[(*sched).current] };
+    task.blocked_syscall = Some(ctx.rax);
+    task.ctx   = ctx;
+    task.state = TaskState::WaitingForFd(fd, write);
+    unsafe { crate::kernel::user_mode::exit_to_kernel(EXIT_SLEEPING) }
+}
+
+/// Block the current task in `epoll_wait` on `epfd` until the set is
+/// notified (the call is restarted) or `deadline` passes (it returns 0).
+pub unsafe fn wait_for_events(
+    epfd:     i32,
+    deadline: u64,
+    ctx:      crate::kernel::user_mode::TaskContext,
 ) -> ! {
-    ctx.rax = 0; // will be overwritten with the exit code on wakeup
     let sched = &raw mut SCHED;
-    (*sched).tasks[parent_idx].ctx   = ctx;
-    (*sched).tasks[parent_idx].state = TaskState::Waiting(child_pid);
+    let task  = unsafe { &mut (*sched).tasks[(*sched).current] };
+    task.blocked_syscall = Some(ctx.rax);
+    task.ctx   = ctx;
+    task.state = TaskState::WaitingForEvents(epfd, deadline);
     unsafe { crate::kernel::user_mode::exit_to_kernel(EXIT_SLEEPING) }
 }
 
+/// Give up the current syscall for now and re-issue it the next time the
+/// task runs — after the signals just sent to it (a background job touching
+/// its terminal) have stopped it or run their handlers.
+pub unsafe fn restart_syscall(mut ctx: TaskContext) -> ! {
+    let sched = &raw mut SCHED;
+    let cur   = unsafe { (*sched).current };
+    ctx.rip -= SYSCALL_INSN_LEN;
+    unsafe {
+        (*sched).tasks[cur].ctx   = ctx;
+        (*sched).tasks[cur].state = TaskState::Ready;
+    }
+    unsafe { ptrace::syscall_abandon(cur); }
+    unsafe { systrace::finish(cur, Outcome::Unfinished); }
+    unsafe { crate::kernel::user_mode::exit_to_kernel(EXIT_SLEEPING) }
+}
+
+/// Make the task at `idx`, parked in a restartable syscall (wait4, an
+/// anonymous-fd read/write or epoll_wait), runnable again with the call re-issued.
+unsafe fn restart_blocked(idx: usize) {
+    let task = unsafe { &raw mut (*(&raw mut SCHED)).tasks[idx] };
+    if let Some(nr) = unsafe { (*task).blocked_syscall.take() } {
+        unsafe {
+            (*task).ctx.rip -= SYSCALL_INSN_LEN;
+            (*task).ctx.rax  = nr;
+        }
+    }
+    unsafe { ptrace::syscall_abandon(idx); }
+    unsafe { systrace::finish(idx, Outcome::Unfinished); }
+    unsafe { (*task).state = TaskState::Ready; }
+}
+
 /// Block the current task until a message arrives on `queue_id`.
 ///
 /// On wakeup, `tick()` will have already written the message to `msg_ptr`
@@ -915,9 +2009,10 @@ pub unsafe fn wait_for_msg(
     msg_ptr:  u64,
     mut ctx:  crate::kernel::user_mode::TaskContext,
 ) -> ! {
-    ctx.rax = 0;
     let sched = &raw mut SCHED;
     let cur   = (*sched).current;
+    unsafe { (*sched).tasks[cur].blocked_syscall = Some(ctx.rax); }
+    ctx.rax = 0;
     (*sched).tasks[cur].ctx   = ctx;
     (*sched).tasks[cur].state = TaskState::WaitingForMsg(queue_id, msg_ptr);
     unsafe { crate::kernel::user_mode::exit_to_kernel(EXIT_SLEEPING) }
@@ -932,13 +2027,15 @@ pub fn has_task() -> bool {
     }
 }
 
-/// Count of tasks currently Ready, Running, Sleeping, or Waiting.
+/// Count of tasks currently Ready, Running, Sleeping, Waiting or stopped.
 pub fn task_count() -> usize {
     unsafe {
         let sched = &raw const SCHED;
         (0..MAX_TASKS).filter(|&i| matches!((*sched).tasks[i].state,
             TaskState::Ready | TaskState::Running
-            | TaskState::Sleeping(_) | TaskState::Waiting(_)
-            | TaskState::WaitingForMsg(_, _))).count()
+            | TaskState::Sleeping(_) | TaskState::Waiting(..)
+            | TaskState::WaitingForMsg(_, _) | TaskState::WaitingForFd(..)
+            | TaskState::WaitingForEvents(..)
+            | TaskState::Traced | TaskState::Stopped)).count()
     }
 }
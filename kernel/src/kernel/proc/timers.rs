@@ -0,0 +1,558 @@
+// src/kernel/proc/timers.rs
+//! Per-task CPU accounting and timers.
+//!
+//! CPU time is measured with the TSC.  `tick()` brackets every launch or
+//! resume of a task with [`run_begin`] / [`run_end`], and the syscall entry
+//! path brackets each call with [`syscall_begin`] / [`syscall_end`]: cycles
+//! inside a syscall are system time, the rest of the slice is user time.
+//! Cycles become nanoseconds at the TSC rate measured against the PIT.
+//!
+//! `alarm`, the three `setitimer` timers and POSIX `timer_create` timers are
+//! all a [`Timer`]: a deadline and interval in nanoseconds on a [`Clock`].
+//! `tick()` calls [`expire`] for every task once per scheduler pass, so
+//! wall-clock timers have the resolution of the 100 Hz timer.
+
+use crate::kernel::scheduler::{
+    SCHED, CURRENT_TASK_IDX, TaskState, SigInfo, send_signal, send_signal_info,
+    EXIT_PREEMPTED, SIGALRM, SIGVTALRM, SIGPROF, SI_TIMER,
+};
+use crate::kernel::syscall_core::{validate_user_range, TIMER_HZ};
+use crate::kernel::timer::{get_ticks, rdtsc, tsc_hz};
+
+pub const NS_PER_SEC:  u64 = 1_000_000_000;
+pub const NS_PER_TICK: u64 = NS_PER_SEC / TIMER_HZ;
+
+// `which` values, sigev_notify values and TIMER_ABSTIME live with the
+// argument checks in syscall_core.
+pub use crate::kernel::syscall_core::{
+    ITIMER_REAL, ITIMER_VIRTUAL, ITIMER_PROF, SIGEV_SIGNAL, SIGEV_NONE, SIGEV_THREAD_ID,
+    TIMER_ABSTIME,
+};
+
+/// POSIX timers per process.
+pub const MAX_POSIX_TIMERS: usize = 8;
+
+// getrusage who values.
+pub const RUSAGE_SELF:     i32 = 0;
+pub const RUSAGE_CHILDREN: i32 = -1;
+pub const RUSAGE_THREAD:   i32 = 1;
+
+/// Size of `struct rusage`.
+pub const RUSAGE_SIZE: usize = 144;
+
+const EFAULT: i64 = -14;
+const EINVAL: i64 = -22;
+const EAGAIN: i64 = -11;
+
+// ── CPU accounting ─────────────────────────────────────────────────────────
+
+/// CPU time and context switches of a task (or of its reaped children).
+#[derive(Clone, Copy)]
+pub struct CpuUsage {
+    pub user_ns:   u64,
+    pub system_ns: u64,
+    /// Voluntary context switches: the task blocked.
+    pub nvcsw:     u64,
+    /// Involuntary context switches: the task was preempted.
+    pub nivcsw:    u64,
+}
+
+impl CpuUsage {
+    pub const ZERO: Self = Self { user_ns: 0, system_ns: 0, nvcsw: 0, nivcsw: 0 };
+
+    pub fn total_ns(&self) -> u64 { self.user_ns + self.system_ns }
+
+    pub fn add(&mut self, other: &CpuUsage) {
+        self.user_ns   += other.user_ns;
+        self.system_ns += other.system_ns;
+        self.nvcsw     += other.nvcsw;
+        self.nivcsw    += other.nivcsw;
+    }
+}
+
+/// TSC stamps for the slice in progress.
+struct Meter {
+    /// TSC when the running task was entered; 0 = no task running.
+    run_start:  u64,
+    /// TSC at entry to the syscall in progress; 0 = in user mode.
+    sys_start:  u64,
+    /// Cycles of this slice already spent in completed syscalls.
+    sys_cycles: u64,
+}
+
+static mut METER: Meter = Meter { run_start: 0, sys_start: 0, sys_cycles: 0 };
+
+fn cycles_to_ns(cycles: u64) -> u64 {
+    (cycles as u128 * NS_PER_SEC as u128 / unsafe { tsc_hz() } as u128) as u64
+}
+
+/// `(user, system)` cycles of the slice in progress, up to `now`.
+unsafe fn slice_cycles(now: u64) -> (u64, u64) {
+    let m   = unsafe { &*(&raw const METER) };
+    let sys = m.sys_cycles
+        + if m.sys_start != 0 { now.saturating_sub(m.sys_start) } else { 0 };
+    let total = now.saturating_sub(m.run_start);
+    (total.saturating_sub(sys), sys.min(total))
+}
+
+/// `tick()` is about to launch or resume the current task.
+pub unsafe fn run_begin() {
+    let m = unsafe { &mut *(&raw mut METER) };
+    m.run_start  = unsafe { rdtsc() };
+    m.sys_start  = 0;
+    m.sys_cycles = 0;
+}
+
+/// Syscall entry from user mode.
+pub unsafe fn syscall_begin() {
+    let m = unsafe { &mut *(&raw mut METER) };
+    if m.run_start != 0 { m.sys_start = unsafe { rdtsc() }; }
+}
+
+/// Syscall return to user mode.  A call that blocks or exits never gets
+/// here; [`run_end`] charges its time instead.
+pub unsafe fn syscall_end() {
+    let m = unsafe { &mut *(&raw mut METER) };
+    if m.sys_start != 0 {
+        m.sys_cycles += unsafe { rdtsc() }.saturating_sub(m.sys_start);
+        m.sys_start   = 0;
+    }
+}
+
+/// The task at `idx` has come back to `tick()` with `exit_code`: charge the
+/// slice and count the context switch.
+pub unsafe fn run_end(idx: usize, exit_code: i64) {
+    let m = unsafe { &mut *(&raw mut METER) };
+    if m.run_start == 0 { return; }
+    let (user, sys) = unsafe { slice_cycles(rdtsc()) };
+    m.run_start = 0;
+    m.sys_start = 0;
+
+    let cpu = unsafe { &mut (*(&raw mut SCHED)).tasks[idx].cpu };
+    cpu.user_ns   += cycles_to_ns(user);
+    cpu.system_ns += cycles_to_ns(sys);
+    if exit_code == EXIT_PREEMPTED { cpu.nivcsw += 1; } else { cpu.nvcsw += 1; }
+}
+
+/// CPU usage of the task at `idx`, including the running slice if it is
+/// the current task.
+pub unsafe fn usage(idx: usize) -> CpuUsage {
+    let mut cpu = unsafe { (*(&raw const SCHED)).tasks[idx].cpu };
+    if unsafe { (*(&raw const METER)).run_start != 0 && idx == CURRENT_TASK_IDX } {
+        let (user, sys) = unsafe { slice_cycles(rdtsc()) };
+        cpu.user_ns   += cycles_to_ns(user);
+        cpu.system_ns += cycles_to_ns(sys);
+    }
+    cpu
+}
+
+// ── Clocks and timers ──────────────────────────────────────────────────────
+
+/// What a timer counts.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Clock {
+    /// Time since boot; also stands in for CLOCK_REALTIME and CLOCK_BOOTTIME.
+    Monotonic,
+    /// User CPU time of the task (ITIMER_VIRTUAL).
+    User,
+    /// User + system CPU time (ITIMER_PROF, CLOCK_PROCESS_CPUTIME_ID).
+    Cpu,
+}
+
+impl Clock {
+    /// Map a Linux `clockid_t`.
+    pub fn from_id(id: u64) -> Option<Self> {
+        match id {
+            // REALTIME, MONOTONIC, MONOTONIC_RAW, *_COARSE, BOOTTIME
+            0 | 1 | 4 | 5 | 6 | 7 => Some(Self::Monotonic),
+            // PROCESS_CPUTIME_ID, THREAD_CPUTIME_ID
+            2 | 3 => Some(Self::Cpu),
+            _ => None,
+        }
+    }
+
+    fn read(self, cpu: &CpuUsage) -> u64 {
+        match self {
+            Self::Monotonic => (unsafe { get_ticks() }) * NS_PER_TICK,
+            Self::User      => cpu.user_ns,
+            Self::Cpu       => cpu.total_ns(),
+        }
+    }
+
+    /// Current value for the task at `idx`, in nanoseconds.
+    pub unsafe fn now(self, idx: usize) -> u64 {
+        self.read(&unsafe { usage(idx) })
+    }
+}
+
+/// A one-shot or periodic timer on a [`Clock`].
+#[derive(Clone, Copy)]
+pub struct Timer {
+    pub clock:    Clock,
+    /// Next expiry in nanoseconds on `clock`; 0 = disarmed.
+    pub deadline: u64,
+    /// Period in nanoseconds; 0 = one-shot.
+    pub interval: u64,
+}
+
+impl Timer {
+    pub const fn off(clock: Clock) -> Self {
+        Self { clock, deadline: 0, interval: 0 }
+    }
+
+    /// Arm (or, with `value` 0, disarm) the timer.  `value` is relative to
+    /// `now` unless `absolute`.
+    pub fn set(&mut self, now: u64, value: u64, interval: u64, absolute: bool) {
+        self.deadline = match value {
+            0 => 0,
+            _ if absolute => value,
+            _ => now.saturating_add(value),
+        };
+        self.interval = interval;
+    }
+
+    /// Time left until the next expiry (0 if disarmed).
+    pub fn remaining(&self, now: u64) -> u64 {
+        if self.deadline == 0 { 0 } else { self.deadline.saturating_sub(now).max(1) }
+    }
+
+    /// Number of expiries up to `now`.  A periodic timer is moved to its
+    /// next deadline, saturating at `u64::MAX` (never again) for periods
+    /// too large to add; a one-shot timer is disarmed.
+    pub fn expire(&mut self, now: u64) -> u64 {
+        if self.deadline == 0 || now < self.deadline { return 0; }
+        if self.interval == 0 {
+            self.deadline = 0;
+            return 1;
+        }
+        let n = (now - self.deadline) / self.interval + 1;
+        self.deadline = n.checked_mul(self.interval)
+            .and_then(|d| self.deadline.checked_add(d))
+            .unwrap_or(u64::MAX);
+        n
+    }
+}
+
+/// A `timer_create` timer.
+#[derive(Clone, Copy)]
+pub struct PosixTimer {
+    pub used:    bool,
+    pub timer:   Timer,
+    /// Signal sent on expiry; 0 for SIGEV_NONE.
+    pub signo:   u8,
+    /// `sigev_value`, passed back in `si_value`.
+    pub value:   u64,
+    /// Overrun count of the last signal, for `timer_getoverrun`.
+    pub overrun: i32,
+}
+
+impl PosixTimer {
+    const UNUSED: Self = Self {
+        used: false, timer: Timer::off(Clock::Monotonic), signo: 0, value: 0, overrun: 0,
+    };
+}
+
+/// A task's interval timers (including `alarm`) and POSIX timers.  Not
+/// inherited across fork; exec keeps the interval timers only.
+#[derive(Clone, Copy)]
+pub struct TaskTimers {
+    pub itimer: [Timer; 3],
+    pub posix:  [PosixTimer; MAX_POSIX_TIMERS],
+}
+
+impl TaskTimers {
+    pub const NONE: Self = Self {
+        itimer: [Timer::off(Clock::Monotonic), Timer::off(Clock::User), Timer::off(Clock::Cpu)],
+        posix:  [PosixTimer::UNUSED; MAX_POSIX_TIMERS],
+    };
+
+    /// exec deletes POSIX timers.
+    pub fn on_exec(&mut self) {
+        self.posix = [PosixTimer::UNUSED; MAX_POSIX_TIMERS];
+    }
+}
+
+/// Fire every expired timer of the task at `idx`.  Called from `tick()`.
+pub unsafe fn expire(idx: usize) {
+    let task = unsafe { &raw mut (*(&raw mut SCHED)).tasks[idx] };
+    let (state, pid) = unsafe { ((*task).state, (*task).pid) };
+    if matches!(state, TaskState::Empty | TaskState::Dead(_)) { return; }
+    let cpu = unsafe { usage(idx) };
+
+    for (which, signo) in [(ITIMER_REAL, SIGALRM), (ITIMER_VIRTUAL, SIGVTALRM), (ITIMER_PROF, SIGPROF)] {
+        let t = unsafe { &mut (*task).timers.itimer[which] };
+        if t.expire(t.clock.read(&cpu)) > 0 {
+            unsafe { send_signal(pid, signo); }
+        }
+    }
+
+    for id in 0..MAX_POSIX_TIMERS {
+        // Sending the signal writes the task's pending set and siginfo, so
+        // the task is only reached through `task`, never held by reference.
+        let pt = unsafe { &raw mut (*task).timers.posix[id] };
+        let (n, signo, value) = unsafe {
+            if !(*pt).used { continue; }
+            ((*pt).timer.expire((*pt).timer.clock.read(&cpu)), (*pt).signo, (*pt).value)
+        };
+        if n == 0 || signo == 0 { continue; }
+
+        let extra  = (n - 1).min(i32::MAX as u64) as i32;
+        unsafe {
+            let queued = &raw mut (*task).signal_info[signo as usize];
+            if (*task).pending_signals & 1u32 << (signo - 1) != 0
+                && (*queued).code == SI_TIMER && (*queued).timerid == id as i32
+            {
+                // The last signal is still queued: these expiries are overruns.
+                (*queued).overrun = (*queued).overrun.saturating_add(extra).saturating_add(1);
+                (*pt).overrun     = (*queued).overrun;
+            } else {
+                (*pt).overrun = extra;
+                send_signal_info(pid, signo, SigInfo::timer(id as i32, extra, value));
+            }
+        }
+    }
+}
+
+// ── User-memory helpers ────────────────────────────────────────────────────
+
+/// Read a `struct timespec` as nanoseconds.
+pub unsafe fn read_timespec(ptr: u64) -> Result<u64, i64> {
+    if validate_user_range(ptr, 16).is_err() { return Err(EFAULT); }
+    let (sec, nsec) = unsafe {
+        (core::ptr::read_unaligned(ptr as *const i64),
+         core::ptr::read_unaligned((ptr + 8) as *const i64))
+    };
+    if sec < 0 || !(0..NS_PER_SEC as i64).contains(&nsec) { return Err(EINVAL); }
+    Ok((sec as u64).saturating_mul(NS_PER_SEC).saturating_add(nsec as u64))
+}
+
+pub unsafe fn write_timespec(ptr: u64, ns: u64) {
+    unsafe {
+        core::ptr::write_unaligned(ptr as *mut u64, ns / NS_PER_SEC);
+        core::ptr::write_unaligned((ptr + 8) as *mut u64, ns % NS_PER_SEC);
+    }
+}
+
+/// Read a `struct timeval` as nanoseconds.
+unsafe fn read_timeval(ptr: u64) -> Result<u64, i64> {
+    let (sec, usec) = unsafe {
+        (core::ptr::read_unaligned(ptr as *const i64),
+         core::ptr::read_unaligned((ptr + 8) as *const i64))
+    };
+    if sec < 0 || !(0..1_000_000).contains(&usec) { return Err(EINVAL); }
+    Ok((sec as u64).saturating_mul(NS_PER_SEC).saturating_add(usec as u64 * 1000))
+}
+
+/// Write nanoseconds as a `struct timeval`, rounding up so a pending timer
+/// never reads as zero.
+unsafe fn write_timeval(ptr: u64, ns: u64) {
+    let us = ns.div_ceil(1000);
+    unsafe {
+        core::ptr::write_unaligned(ptr as *mut u64, us / 1_000_000);
+        core::ptr::write_unaligned((ptr + 8) as *mut u64, us % 1_000_000);
+    }
+}
+
+/// Read a `struct itimerspec` as `(value, interval)` nanoseconds.
+pub unsafe fn read_itimerspec(ptr: u64) -> Result<(u64, u64), i64> {
+    if validate_user_range(ptr, 32).is_err() { return Err(EFAULT); }
+    let interval = unsafe { read_timespec(ptr)? };
+    let value    = unsafe { read_timespec(ptr + 16)? };
+    Ok((value, interval))
+}
+
+/// Write a `struct itimerspec`; `ptr` 0 is skipped.
+pub unsafe fn write_itimerspec(ptr: u64, value: u64, interval: u64) -> i64 {
+    if ptr == 0 { return 0; }
+    if validate_user_range(ptr, 32).is_err() { return EFAULT; }
+    unsafe {
+        write_timespec(ptr, interval);
+        write_timespec(ptr + 16, value);
+    }
+    0
+}
+
+// ── Syscalls ───────────────────────────────────────────────────────────────
+
+/// `getitimer(which, curr)`.
+pub unsafe fn getitimer(which: u64, curr_ptr: u64) -> i64 {
+    let idx = unsafe { CURRENT_TASK_IDX };
+    let t   = unsafe { (*(&raw const SCHED)).tasks[idx].timers.itimer[which as usize] };
+    let now = unsafe { t.clock.now(idx) };
+    unsafe {
+        write_timeval(curr_ptr, t.interval);
+        write_timeval(curr_ptr + 16, t.remaining(now));
+    }
+    0
+}
+
+/// `setitimer(which, new, old)`.  A null `new` disarms the timer.
+pub unsafe fn setitimer(which: u64, new_ptr: u64, old_ptr: u64) -> i64 {
+    let (value, interval) = if new_ptr == 0 { (0, 0) } else {
+        match unsafe { (read_timeval(new_ptr + 16), read_timeval(new_ptr)) } {
+            (Ok(v), Ok(i)) => (v, i),
+            _ => return EINVAL,
+        }
+    };
+    if old_ptr != 0 {
+        let r = unsafe { getitimer(which, old_ptr) };
+        if r < 0 { return r; }
+    }
+    let idx = unsafe { CURRENT_TASK_IDX };
+    let t   = unsafe { &mut (*(&raw mut SCHED)).tasks[idx].timers.itimer[which as usize] };
+    let now = unsafe { t.clock.now(idx) };
+    t.set(now, value, interval, false);
+    0
+}
+
+/// `alarm(seconds)`: a one-shot ITIMER_REAL.  Returns the seconds left on
+/// the previous alarm, rounded to nearest (at least 1 if one was pending).
+pub unsafe fn alarm(seconds: u32) -> i64 {
+    let idx  = unsafe { CURRENT_TASK_IDX };
+    let t    = unsafe { &mut (*(&raw mut SCHED)).tasks[idx].timers.itimer[ITIMER_REAL] };
+    let now  = unsafe { t.clock.now(idx) };
+    let left = t.remaining(now);
+    t.set(now, seconds as u64 * NS_PER_SEC, 0, false);
+    if left == 0 { 0 } else { ((left + NS_PER_SEC / 2) / NS_PER_SEC).max(1) as i64 }
+}
+
+/// `timer_create(clockid, sevp, timerid)`.
+pub unsafe fn timer_create(clock_id: u64, sevp: u64, id_ptr: u64) -> i64 {
+    let clock = match Clock::from_id(clock_id) { Some(c) => c, None => return EINVAL };
+    let idx  = unsafe { CURRENT_TASK_IDX };
+    let task = unsafe { &mut (*(&raw mut SCHED)).tasks[idx] };
+
+    let id = match task.timers.posix.iter().position(|t| !t.used) {
+        Some(id) => id,
+        None     => return EAGAIN,
+    };
+    // struct sigevent { sigev_value, sigev_signo, sigev_notify, sigev_tid }
+    let (signo, value) = if sevp == 0 {
+        (SIGALRM, id as u64)
+    } else {
+        let (value, signo, notify, tid) = unsafe {
+            (core::ptr::read_unaligned(sevp as *const u64),
+             core::ptr::read_unaligned((sevp + 8) as *const i32),
+             core::ptr::read_unaligned((sevp + 12) as *const i32),
+             core::ptr::read_unaligned((sevp + 16) as *const i32))
+        };
+        match notify {
+            SIGEV_NONE => (0, value),
+            SIGEV_SIGNAL | SIGEV_THREAD_ID => {
+                if notify == SIGEV_THREAD_ID && tid != task.pid as i32 { return EINVAL; }
+                (signo as u8, value)
+            }
+            // SIGEV_THREAD needs a C library helper thread.
+            _ => return EINVAL,
+        }
+    };
+    task.timers.posix[id] = PosixTimer {
+        used: true, timer: Timer::off(clock), signo, value, overrun: 0,
+    };
+    unsafe { core::ptr::write_unaligned(id_ptr as *mut i32, id as i32); }
+    0
+}
+
+unsafe fn posix_timer(id: u64) -> Option<*mut PosixTimer> {
+    if id as usize >= MAX_POSIX_TIMERS { return None; }
+    let pt = unsafe { &raw mut (*(&raw mut SCHED)).tasks[CURRENT_TASK_IDX].timers.posix[id as usize] };
+    if unsafe { (*pt).used } { Some(pt) } else { None }
+}
+
+/// `timer_gettime(timerid, curr)`.
+pub unsafe fn timer_gettime(id: u64, curr_ptr: u64) -> i64 {
+    let pt = match unsafe { posix_timer(id) } { Some(p) => p, None => return EINVAL };
+    let t   = unsafe { (*pt).timer };
+    let now = unsafe { t.clock.now(CURRENT_TASK_IDX) };
+    unsafe { write_itimerspec(curr_ptr, t.remaining(now), t.interval) }
+}
+
+/// `timer_settime(timerid, flags, new, old)`.
+pub unsafe fn timer_settime(id: u64, flags: u64, new_ptr: u64, old_ptr: u64) -> i64 {
+    let pt = match unsafe { posix_timer(id) } { Some(p) => p, None => return EINVAL };
+    let (value, interval) = match unsafe { read_itimerspec(new_ptr) } {
+        Ok(v)  => v,
+        Err(e) => return e,
+    };
+    if old_ptr != 0 {
+        let r = unsafe { timer_gettime(id, old_ptr) };
+        if r < 0 { return r; }
+    }
+    let pt  = unsafe { &mut *pt };
+    let now = unsafe { pt.timer.clock.now(CURRENT_TASK_IDX) };
+    pt.timer.set(now, value, interval, flags & TIMER_ABSTIME != 0);
+    pt.overrun = 0;
+    0
+}
+
+/// `timer_getoverrun(timerid)`.
+pub unsafe fn timer_getoverrun(id: u64) -> i64 {
+    match unsafe { posix_timer(id) } {
+        Some(pt) => unsafe { (*pt).overrun as i64 },
+        None     => EINVAL,
+    }
+}
+
+/// `timer_delete(timerid)`.
+pub unsafe fn timer_delete(id: u64) -> i64 {
+    match unsafe { posix_timer(id) } {
+        Some(pt) => { unsafe { *pt = PosixTimer::UNUSED; } 0 }
+        None     => EINVAL,
+    }
+}
+
+/// `clock_gettime(clockid, tp)`.
+pub unsafe fn clock_gettime(clock_id: u64, tp_ptr: u64) -> i64 {
+    let clock = match Clock::from_id(clock_id) { Some(c) => c, None => return EINVAL };
+    if validate_user_range(tp_ptr, 16).is_err() { return EFAULT; }
+    unsafe { write_timespec(tp_ptr, clock.now(CURRENT_TASK_IDX)); }
+    0
+}
+
+/// `times(buf)`: CPU times in clock ticks; returns ticks since boot.
+pub unsafe fn times(buf_ptr: u64) -> i64 {
+    if buf_ptr != 0 {
+        if validate_user_range(buf_ptr, 32).is_err() { return EFAULT; }
+        let idx   = unsafe { CURRENT_TASK_IDX };
+        let own   = unsafe { usage(idx) };
+        let kids  = unsafe { (*(&raw const SCHED)).tasks[idx].child_cpu };
+        let out   = buf_ptr as *mut u64;
+        for (i, ns) in [own.user_ns, own.system_ns, kids.user_ns, kids.system_ns].iter().enumerate() {
+            unsafe { core::ptr::write_unaligned(out.add(i), ns / NS_PER_TICK); }
+        }
+    }
+    unsafe { get_ticks() as i64 }
+}
+
+/// Fill a `struct rusage` (already validated) from `cpu`.
+pub unsafe fn write_rusage(ptr: u64, cpu: &CpuUsage) {
+    unsafe {
+        core::ptr::write_bytes(ptr as *mut u8, 0, RUSAGE_SIZE);
+        write_timeval(ptr, cpu.user_ns);
+        write_timeval(ptr + 16, cpu.system_ns);
+        core::ptr::write_unaligned((ptr + 128) as *mut u64, cpu.nvcsw);
+        core::ptr::write_unaligned((ptr + 136) as *mut u64, cpu.nivcsw);
+    }
+}
+
+/// `getrusage(who, usage)`.
+pub unsafe fn getrusage(who: i32, buf_ptr: u64) -> i64 {
+    if validate_user_range(buf_ptr, RUSAGE_SIZE as u64).is_err() { return EFAULT; }
+    let idx = unsafe { CURRENT_TASK_IDX };
+    let cpu = match who {
+        RUSAGE_SELF | RUSAGE_THREAD => unsafe { usage(idx) },
+        RUSAGE_CHILDREN => unsafe { (*(&raw const SCHED)).tasks[idx].child_cpu },
+        _ => return EINVAL,
+    };
+    unsafe { write_rusage(buf_ptr, &cpu); }
+    0
+}
+
+/// A child is reaped: its usage, and its own children's, count towards the
+/// parent's RUSAGE_CHILDREN.
+pub unsafe fn reap(parent_idx: usize, child_idx: usize) {
+    let tasks = unsafe { &mut (*(&raw mut SCHED)).tasks };
+    let mut total = tasks[child_idx].cpu;
+    total.add(&tasks[child_idx].child_cpu);
+    tasks[parent_idx].child_cpu.add(&total);
+}
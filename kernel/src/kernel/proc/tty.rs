@@ -1,30 +1,114 @@
-//! TTY subsystem for OxideOS — POSIX termios interface.
+//! TTY subsystem for OxideOS — terminal devices and the termios line discipline.
 //!
-//! Provides a global TTY state and handles `ioctl` requests for terminal
-//! attribute queries (TCGETS) and updates (TCSETS/TCSETSW/TCSETSF).
-//! Also handles TIOCGWINSZ for terminal window size queries.
+//! `TTYS` holds every terminal, by index:
 //!
-//! The current implementation supports:
-//! - TCGETS/TCSETS: get/set termios (raw vs canonical mode flag tracking)
-//! - TIOCGWINSZ: return fixed terminal size (80×24 chars, 720×384 pixels)
-//! - TIOCSPGRP/TIOCGPGRP: process-group stubs (return current PID)
+//! | index           | device                   | input                    | output              |
+//! |-----------------|--------------------------|--------------------------|---------------------|
+//! | `CONSOLE`       | `/dev/tty`, bare fds 0–2 | `stdin` ring (keyboard)  | GUI terminal/serial |
+//! | `TTY_S0`        | `/dev/ttyS0`             | COM1 receive interrupt   | COM1                |
+//! | `VT_BASE` + n   | `/dev/tty1`..`/dev/tty6` | keyboard, when n active  | `drivers::vt`       |
+//! | `PTY_BASE` + n  | `/dev/pts/n`             | pty master writes        | pty master reads    |
 //!
-//! Actual canonical mode line-buffering is not yet implemented; the stdin
-//! ring is still raw. Programs that call TCSETS to set raw mode will find
-//! they are already effectively in raw mode.
-
+//! The console keeps its historical behaviour — programs read it a byte at a
+//! time and echo and edit for themselves — so only its termios, window size
+//! and foreground group live here.
+//!
+//! Every other tty runs an `Ldisc`.  The driver hands it raw bytes through
+//! `input`; signal characters (ISIG) take effect at once, the rest wait in
+//! the raw queue.  `read(2)` runs that queue through the discipline — input
+//! mapping, canonical editing with VERASE/VKILL/VEOF, echo — and returns
+//! cooked bytes, blocking while there are none.  `write(2)` goes through
+//! output processing (OPOST/ONLCR).  The GetChar syscall takes raw bytes
+//! straight off the queue, so programs that do their own echo (`sh`,
+//! `login`) behave the same on every tty.
+//!
+//! A tty other than the console can be the controlling terminal of one
+//! session (`Ldisc::session`): the first one its leader opens without
+//! `O_NOCTTY`, or claims with `TIOCSCTTY`.  On its controlling terminal a
+//! task outside the foreground group is a background job — reading sends
+//! its group SIGTTIN, writing with TOSTOP set or changing the terminal
+//! sends SIGTTOU, and the call is re-issued once the job is continued
+//! (`job_check`).  Orphaned background groups get `EIO` instead, since
+//! nobody would continue them.  When the session leader exits, or the far
+//! end of a pty goes away, the terminal is released and its foreground
+//! group gets SIGHUP.
+//!
+//! A BEL written to the console on screen sounds the PC speaker
+//! (`speaker::bell`); `KIOCSOUND` and `KDMKTONE` play tones on it directly.
+
+use crate::kernel::anonfd::EAGAIN;
+use crate::kernel::epoll::{self, Source};
+use crate::kernel::fs::ramfs::{FdBackend, MAX_FD};
+use crate::kernel::pty;
+use crate::kernel::scheduler::{
+    self, SCHED, CURRENT_TASK_IDX, SIG_IGN, SIGINT, SIGQUIT, SIGTSTP, SIGTTIN, SIGTTOU, SIGWINCH,
+    SIGHUP, SIGCONT,
+};
 use crate::kernel::serial::SERIAL_PORT;
+use crate::kernel::speaker;
+use crate::kernel::syscall_core::validate_user_range;
+use crate::kernel::user_mode::CURRENT_SYSCALL_CTX;
+use crate::kernel::vt;
+
+// ── Devices ───────────────────────────────────────────────────────────────
+
+pub const CONSOLE: usize = 0;
+pub const TTY_S0:  usize = 1;
+pub const VT_BASE: usize = 2;
+pub const PTY_BASE: usize = VT_BASE + vt::NR_VTS;
+pub const NR_TTYS: usize = PTY_BASE + pty::NR_PTYS;
+
+/// The tty index of `/dev/<name>`, for the names this module owns.
+pub fn lookup(name: &str) -> Option<usize> {
+    if let Some(n) = name.strip_prefix("pts/") {
+        let n: usize = n.parse().ok()?;
+        return (n < pty::NR_PTYS).then(|| PTY_BASE + n);
+    }
+    match name {
+        "ttyS0" => Some(TTY_S0),
+        _ => {
+            let n: usize = name.strip_prefix("tty")?.parse().ok()?;
+            (1..=vt::NR_VTS).contains(&n).then(|| VT_BASE + n - 1)
+        }
+    }
+}
+
+/// Add `/dev/tty`, `/dev/ttyS0` and `/dev/tty1`..`/dev/tty6` to devfs.
+/// The lines are 0620, group tty: only their owner (see `owner`) may read
+/// them, while `write` and friends can still reach a logged-in user.
+pub fn register_devices() {
+    use crate::kernel::devfs::{self, DevNode};
+    const VT_NAMES: [&str; vt::NR_VTS] = ["tty1", "tty2", "tty3", "tty4", "tty5", "tty6"];
+    devfs::register(DevNode::chr("tty", FdBackend::DevTty, CONSOLE as i32, (5, 0)));
+    devfs::register(DevNode::line("ttyS0", TTY_S0, (4, 64)));
+    for (n, name) in VT_NAMES.iter().enumerate() {
+        devfs::register(DevNode::line(name, VT_BASE + n, (4, n as u32 + 1)));
+    }
+}
 
 // ── ioctl request numbers (matching Linux) ────────────────────────────────
 
-pub const TCGETS:    u64 = 0x5401;
-pub const TCSETS:    u64 = 0x5402;
-pub const TCSETSW:   u64 = 0x5403;
-pub const TCSETSF:   u64 = 0x5404;
-pub const TIOCGPGRP: u64 = 0x540F;
-pub const TIOCSPGRP: u64 = 0x5410;
-pub const TIOCGWINSZ:u64 = 0x5413;
-pub const TIOCSWINSZ:u64 = 0x5414;
+pub const TCGETS:     u64 = 0x5401;
+pub const TCSETS:     u64 = 0x5402;
+pub const TCSETSW:    u64 = 0x5403;
+pub const TCSETSF:    u64 = 0x5404;
+pub const TCFLSH:     u64 = 0x540B;
+pub const TIOCSCTTY:  u64 = 0x540E;
+pub const TIOCGPGRP:  u64 = 0x540F;
+pub const TIOCSPGRP:  u64 = 0x5410;
+pub const TIOCGWINSZ: u64 = 0x5413;
+pub const TIOCSWINSZ: u64 = 0x5414;
+pub const FIONREAD:   u64 = 0x541B;
+pub const TIOCNOTTY:  u64 = 0x5422;
+pub const TIOCGSID:   u64 = 0x5429;
+pub const KIOCSOUND:  u64 = 0x4B2F;
+pub const KDMKTONE:   u64 = 0x4B30;
+
+const EPERM:  i64 = -1;
+const EIO:    i64 = -5;
+const EFAULT: i64 = -14;
+const EINVAL: i64 = -22;
+const ENOTTY: i64 = -25;
 
 // ── termios c_lflag bits ─────────────────────────────────────────────────
 
@@ -35,11 +119,15 @@ pub const ECHOE:  u32 = 0x0010;   // echo erase as BS-SP-BS
 pub const ECHOK:  u32 = 0x0020;   // echo NL after kill char
 pub const ECHONL: u32 = 0x0040;   // echo NL even if ECHO is off
 pub const NOFLSH: u32 = 0x0080;   // don't flush on signal chars
+pub const TOSTOP: u32 = 0x0100;   // background writes raise SIGTTOU
 
 // ── termios c_iflag bits ─────────────────────────────────────────────────
 
+pub const INLCR:  u32 = 0x0040;   // map NL to CR
+pub const IGNCR:  u32 = 0x0080;   // drop CR
 pub const ICRNL:  u32 = 0x0100;   // map CR to NL
 pub const IXON:   u32 = 0x0400;   // XON/XOFF flow control
+pub const IUTF8:  u32 = 0x4000;   // input is UTF-8: erase whole characters
 
 // ── termios c_oflag bits ─────────────────────────────────────────────────
 
@@ -53,8 +141,10 @@ pub const VQUIT:  usize = 1;    // Ctrl+\ → SIGQUIT
 pub const VERASE: usize = 2;    // Backspace / DEL
 pub const VKILL:  usize = 3;    // Ctrl+U — kill line
 pub const VEOF:   usize = 4;    // Ctrl+D — EOF
+pub const VTIME:  usize = 5;    // read() timeout in 0.1 s units
 pub const VMIN:   usize = 6;    // min bytes for read()
-pub const VTIME:  usize = 7;    // read() timeout in 0.1 s units
+pub const VSUSP:  usize = 10;   // Ctrl+Z → SIGTSTP
+pub const VEOL:   usize = 11;   // extra end-of-line character
 
 // ── On-disk / in-memory struct ────────────────────────────────────────────
 
@@ -83,9 +173,9 @@ pub struct Winsize {
 }
 
 /// Default "cooked" (canonical) termios matching a typical Linux terminal.
-fn default_termios() -> Termios {
+const fn default_termios() -> Termios {
     let mut t = Termios {
-        c_iflag: ICRNL | IXON,
+        c_iflag: ICRNL | IXON | IUTF8,
         c_oflag: OPOST | ONLCR,
         c_cflag: 0x00BF,     // CS8 | CREAD | HUPCL
         c_lflag: ISIG | ICANON | ECHO | ECHOE | ECHOK,
@@ -100,6 +190,7 @@ fn default_termios() -> Termios {
     t.c_cc[VEOF]   = 4;    // Ctrl+D
     t.c_cc[VMIN]   = 1;
     t.c_cc[VTIME]  = 0;
+    t.c_cc[VSUSP]  = 26;   // Ctrl+Z
     t
 }
 
@@ -108,128 +199,677 @@ const DEFAULT_WINSIZE: Winsize = Winsize {
     ws_row: 24, ws_col: 80, ws_xpixel: 720, ws_ypixel: 384,
 };
 
-// ── Global TTY state ──────────────────────────────────────────────────────
+// ── Line discipline ───────────────────────────────────────────────────────
 
-struct TtyState {
-    termios: Termios,
-    winsize: Winsize,
+/// Longest canonical line, newline included.
+const LINE_MAX: usize = 255;
+
+/// Fixed-size byte FIFO.
+pub(crate) struct Ring<const N: usize> {
+    buf:  [u8; N],
+    head: usize,
+    pub(crate) len: usize,
 }
 
-impl TtyState {
-    fn new() -> Self {
-        Self { termios: default_termios(), winsize: DEFAULT_WINSIZE }
+impl<const N: usize> Ring<N> {
+    pub(crate) const EMPTY: Self = Self { buf: [0; N], head: 0, len: 0 };
+
+    pub(crate) fn push(&mut self, b: u8) -> bool {
+        if self.len == N { return false; }
+        self.buf[(self.head + self.len) % N] = b;
+        self.len += 1;
+        true
     }
+
+    pub(crate) fn pop(&mut self) -> Option<u8> {
+        if self.len == 0 { return None; }
+        let b = self.buf[self.head];
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        Some(b)
+    }
+
+    fn front_mut(&mut self) -> Option<&mut u8> {
+        if self.len == 0 { None } else { Some(&mut self.buf[self.head]) }
+    }
+
+    pub(crate) fn free(&self) -> usize { N - self.len }
+
+    pub(crate) fn clear(&mut self) { self.len = 0; }
 }
 
-static mut TTY: TtyState = TtyState {
-    termios: Termios {
-        c_iflag: ICRNL | IXON,
-        c_oflag: OPOST | ONLCR,
-        c_cflag: 0x00BF,
-        c_lflag: ISIG | ICANON | ECHO | ECHOE | ECHOK,
-        c_line:  0,
-        c_cc: [0u8; 32],
-        _pad: [0u8; 3],
-    },
-    winsize: DEFAULT_WINSIZE,
+/// termios state and input queues of one terminal.  Output goes to
+/// whatever `out` the caller passes, so the same discipline serves the
+/// serial line, the virtual consoles and pseudo-terminals.
+pub struct Ldisc {
+    pub termios: Termios,
+    pub winsize: Winsize,
+    /// Foreground process group; 0 while nobody has claimed the terminal.
+    pub fg_pgrp: u8,
+    /// Session this is the controlling terminal of; 0 for none.
+    pub session: u8,
+    /// Owner of the device node (`/dev` reports it with mode 0620, group
+    /// tty).  Root until login gives the line to the user.
+    pub owner:   u32,
+    /// Bytes from the driver that `read` has not processed yet.
+    raw:      Ring<256>,
+    /// Canonical mode: the line being edited.
+    line:     [u8; LINE_MAX],
+    line_len: usize,
+    /// Processed bytes waiting for `read`.
+    ready:    Ring<1024>,
+    /// Canonical mode: lengths of the complete lines in `ready`, oldest
+    /// first; 0 is an end-of-file mark.
+    lines:    Ring<32>,
+}
+
+impl Ldisc {
+    pub const fn new(winsize: Winsize) -> Self {
+        Self {
+            termios: default_termios(),
+            winsize,
+            fg_pgrp: 0,
+            session: 0,
+            owner: 0,
+            raw: Ring::EMPTY,
+            line: [0; LINE_MAX],
+            line_len: 0,
+            ready: Ring::EMPTY,
+            lines: Ring::EMPTY,
+        }
+    }
+
+    fn canonical(&self) -> bool { self.termios.c_lflag & ICANON != 0 }
+
+    /// A byte from the driver.  Signal characters are sent to the
+    /// foreground group right away; everything else is queued.  Returns
+    /// `false` if the byte was dropped because the raw queue is full.
+    pub unsafe fn input(&mut self, c: u8, out: &mut dyn FnMut(&[u8])) -> bool {
+        let t = self.termios;
+        if t.c_lflag & ISIG != 0 && c != 0 {
+            let sig = if c == t.c_cc[VINTR] { SIGINT }
+                      else if c == t.c_cc[VQUIT] { SIGQUIT }
+                      else if c == t.c_cc[VSUSP] { SIGTSTP }
+                      else { 0 };
+            // With no live foreground group the byte is ordinary input,
+            // so programs that never claim the terminal still see Ctrl+C.
+            if sig != 0 && self.fg_pgrp != 0
+                && unsafe { scheduler::signal_group(self.fg_pgrp, sig) }
+            {
+                if t.c_lflag & NOFLSH == 0 { self.flush_input(); }
+                if t.c_lflag & ECHO != 0 { self.output(&[b'^', c | 0x40, b'\n'], out); }
+                return true;
+            }
+        }
+        self.raw.push(c)
+    }
+
+    /// Run the raw queue through input processing and, in canonical mode,
+    /// line editing, echoing as configured.
+    fn cook(&mut self, out: &mut dyn FnMut(&[u8])) {
+        loop {
+            let canon = self.canonical();
+            if canon && (self.lines.free() == 0 || self.ready.free() <= LINE_MAX) { break; }
+            if !canon && self.ready.free() == 0 { break; }
+            let Some(mut c) = self.raw.pop() else { break };
+
+            let t = self.termios;
+            if c == b'\r' {
+                if t.c_iflag & IGNCR != 0 { continue; }
+                if t.c_iflag & ICRNL != 0 { c = b'\n'; }
+            } else if c == b'\n' && t.c_iflag & INLCR != 0 {
+                c = b'\r';
+            }
+            let echo = t.c_lflag & ECHO != 0;
+
+            if !canon {
+                self.ready.push(c);
+                if echo { self.output(&[c], out); }
+                continue;
+            }
+
+            // The PS/2 driver sends ^H for Backspace; accept it as erase too.
+            if c == t.c_cc[VERASE] || c == 0x08 {
+                if self.line_len > 0 {
+                    // Continuation bytes go with the character they follow.
+                    if t.c_iflag & IUTF8 != 0 {
+                        while self.line_len > 1 && self.line[self.line_len - 1] & 0xC0 == 0x80 {
+                            self.line_len -= 1;
+                        }
+                    }
+                    self.line_len -= 1;
+                    if echo && t.c_lflag & ECHOE != 0 { self.output(b"\x08 \x08", out); }
+                }
+            } else if c == t.c_cc[VKILL] {
+                if echo && t.c_lflag & ECHOK != 0 {
+                    let utf8 = t.c_iflag & IUTF8 != 0;
+                    let chars = self.line[..self.line_len].iter()
+                        .filter(|&&b| !utf8 || b & 0xC0 != 0x80)
+                        .count();
+                    for _ in 0..chars { self.output(b"\x08 \x08", out); }
+                }
+                self.line_len = 0;
+            } else if c == t.c_cc[VEOF] {
+                self.commit();
+            } else if c == b'\n' || (c != 0 && c == t.c_cc[VEOL]) {
+                self.line[self.line_len] = c;
+                self.line_len += 1;
+                self.commit();
+                if echo || t.c_lflag & ECHONL != 0 { self.output(&[c], out); }
+            } else if self.line_len < LINE_MAX - 1 {
+                self.line[self.line_len] = c;
+                self.line_len += 1;
+                if echo { self.output(&[c], out); }
+            }
+        }
+    }
+
+    /// Move the edited line to `ready` as one complete line.
+    fn commit(&mut self) {
+        for i in 0..self.line_len { self.ready.push(self.line[i]); }
+        self.lines.push(self.line_len as u8);
+        self.line_len = 0;
+    }
+
+    /// Read cooked input.  `None` means the caller would block.
+    pub fn read(&mut self, buf: &mut [u8], out: &mut dyn FnMut(&[u8])) -> Option<usize> {
+        self.cook(out);
+        let n = if self.canonical() {
+            let front = self.lines.front_mut()?;
+            let n = (*front as usize).min(buf.len());
+            *front -= n as u8;
+            if *front == 0 { self.lines.pop(); }
+            n
+        } else {
+            if self.ready.len == 0 {
+                return if self.termios.c_cc[VMIN] == 0 { Some(0) } else { None };
+            }
+            self.ready.len.min(buf.len())
+        };
+        for b in &mut buf[..n] { *b = self.ready.pop().unwrap_or(0); }
+        Some(n)
+    }
+
+    /// True when `read` would not block.
+    pub fn readable(&mut self, out: &mut dyn FnMut(&[u8])) -> bool {
+        self.cook(out);
+        if self.canonical() { self.lines.len > 0 } else { self.ready.len > 0 || self.termios.c_cc[VMIN] == 0 }
+    }
+
+    /// Bytes a `read` could return right now (FIONREAD).
+    pub fn pending(&mut self, out: &mut dyn FnMut(&[u8])) -> usize {
+        self.cook(out);
+        if self.canonical() {
+            self.lines.front_mut().map_or(0, |n| *n as usize)
+        } else {
+            self.ready.len
+        }
+    }
+
+    /// An unprocessed byte, for the GetChar syscall.
+    pub fn getchar(&mut self) -> Option<u8> {
+        self.raw.pop()
+    }
+
+    pub fn flush_input(&mut self) {
+        self.raw.clear();
+        self.ready.clear();
+        self.lines.clear();
+        self.line_len = 0;
+    }
+
+    /// Install new termios.  Input already cooked for the old mode is kept:
+    /// leaving canonical mode hands over the partial line, entering it
+    /// turns what is queued into one line.
+    pub fn set_termios(&mut self, t: Termios) {
+        let was = self.canonical();
+        self.termios = t;
+        match (was, self.canonical()) {
+            (true, false) => {
+                for i in 0..self.line_len { self.ready.push(self.line[i]); }
+                self.line_len = 0;
+                self.lines.clear();
+            }
+            (false, true) => {
+                let mut left = self.ready.len;
+                while left > 0 {
+                    let n = left.min(LINE_MAX);
+                    self.lines.push(n as u8);
+                    left -= n;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Output processing.
+    pub fn output(&self, mut bytes: &[u8], out: &mut dyn FnMut(&[u8])) {
+        if self.termios.c_oflag & (OPOST | ONLCR) != OPOST | ONLCR {
+            out(bytes);
+            return;
+        }
+        while let Some(i) = bytes.iter().position(|&b| b == b'\n') {
+            if i > 0 { out(&bytes[..i]); }
+            out(b"\r\n");
+            bytes = &bytes[i + 1..];
+        }
+        if !bytes.is_empty() { out(bytes); }
+    }
+}
+
+// ── Global TTY state ──────────────────────────────────────────────────────
+
+static mut TTYS: [Ldisc; NR_TTYS] = {
+    let mut ttys = [const { Ldisc::new(DEFAULT_WINSIZE) }; NR_TTYS];
+    let mut i = VT_BASE;
+    while i < PTY_BASE {
+        ttys[i].winsize = vt::WINSIZE;
+        i += 1;
+    }
+    ttys
 };
 
+unsafe fn tty(index: usize) -> &'static mut Ldisc {
+    unsafe { &mut (*(&raw mut TTYS))[index] }
+}
+
+/// Send bytes to the hardware behind `index`.
+fn device_write(index: usize, bytes: &[u8]) {
+    match index {
+        TTY_S0 => unsafe {
+            for &b in bytes { SERIAL_PORT.write_byte(b); }
+        },
+        i if i >= VT_BASE && i < PTY_BASE => unsafe {
+            // Only the console on screen beeps, as on Linux.
+            if vt::write(i - VT_BASE, bytes) && i - VT_BASE == vt::active() { speaker::bell(); }
+        },
+        i if i >= PTY_BASE && i < NR_TTYS => unsafe { pty::to_master(i - PTY_BASE, bytes) },
+        _ => {}
+    }
+}
+
+/// Back to the boot-time state (a pty being handed out again).
+pub unsafe fn reset(index: usize) {
+    unsafe { *tty(index) = Ldisc::new(DEFAULT_WINSIZE); }
+}
+
+// ── Driver interface ──────────────────────────────────────────────────────
+
+/// A byte received by the device behind `index` (called from its IRQ, or
+/// a pty master write).  Returns `false` if it was dropped.
+pub unsafe fn input(index: usize, c: u8) -> bool {
+    if index == CONSOLE || index >= NR_TTYS { return false; }
+    let ok = unsafe { tty(index).input(c, &mut |b| device_write(index, b)) };
+    epoll::notify(Source::Terminal(index));
+    ok
+}
+
+/// Room left in the raw input queue of tty `index`.
+pub unsafe fn input_room(index: usize) -> usize {
+    unsafe { tty(index).raw.free() }
+}
+
+/// The far end of tty `index` went away (pty master closed): its session
+/// loses it, the foreground group and the session leader get SIGHUP (and
+/// SIGCONT, in case they are stopped), and readers are woken to see
+/// end-of-file.
+pub unsafe fn hangup(index: usize) {
+    let state = unsafe { tty(index) };
+    if state.session != 0 {
+        unsafe { scheduler::send_signal(state.session, SIGHUP); }
+        unsafe { scheduler::send_signal(state.session, SIGCONT); }
+    }
+    unsafe { release(index); }
+    epoll::notify(Source::Terminal(index));
+}
+
+// ── Controlling terminals ─────────────────────────────────────────────────
+
+/// The controlling terminal of session `sid`.
+pub fn ctty_of(sid: u8) -> Option<usize> {
+    if sid == 0 { return None; }
+    (0..NR_TTYS).find(|&i| unsafe { tty(i).session == sid })
+}
+
+/// Tie tty `index` to the current task's session and make the task's group
+/// the foreground one.
+unsafe fn attach(index: usize) {
+    let t = unsafe { &(*(&raw const SCHED)).tasks[CURRENT_TASK_IDX] };
+    let state = unsafe { tty(index) };
+    state.session = t.session();
+    state.fg_pgrp = t.pgrp();
+}
+
+/// Detach tty `index` from its session, sending SIGHUP and SIGCONT to the
+/// foreground group.
+unsafe fn release(index: usize) {
+    let state = unsafe { tty(index) };
+    let pgrp = state.fg_pgrp;
+    state.session = 0;
+    state.fg_pgrp = 0;
+    // The next login starts from a root-owned line; a pty stays with
+    // whoever opened its master.
+    if index < PTY_BASE { state.owner = 0; }
+    if pgrp != 0 {
+        unsafe {
+            scheduler::signal_group(pgrp, SIGHUP);
+            scheduler::signal_group(pgrp, SIGCONT);
+        }
+    }
+}
+
+/// tty `index` was opened without `O_NOCTTY`: a session leader with no
+/// controlling terminal acquires it, if no other session has.
+pub unsafe fn opened(index: usize) {
+    if index == CONSOLE || index >= NR_TTYS { return; }
+    let t = unsafe { &(*(&raw const SCHED)).tasks[CURRENT_TASK_IDX] };
+    if t.session() != t.pid || ctty_of(t.pid).is_some() { return; }
+    if unsafe { tty(index).session } != 0 { return; }
+    unsafe { attach(index); }
+}
+
+/// The leader of session `sid` exited: its controlling terminal is free
+/// again.
+pub unsafe fn session_ended(sid: u8) {
+    if let Some(index) = ctty_of(sid) {
+        unsafe { release(index); }
+    }
+}
+
+/// Owner of tty `index`, as its `/dev` node reports it.
+pub fn owner(index: usize) -> u32 {
+    unsafe { tty(index).owner }
+}
+
+/// Give pty slave `index` to `uid`, the real uid of whoever opened its
+/// master (`grantpt`).
+pub unsafe fn grant(index: usize, uid: u32) {
+    unsafe { tty(index).owner = uid; }
+}
+
+/// `chown` of tty `index`'s node.  Root may give the line to anyone; its
+/// owner may only keep it.  The group is always tty.
+pub unsafe fn chown(index: usize, uid: u32, gid: u32) -> i64 {
+    use crate::kernel::devfs::GID_TTY;
+    let state = unsafe { tty(index) };
+    let uid = if uid == u32::MAX { state.owner } else { uid };
+    let c = crate::kernel::cred::get();
+    if c.fsuid != 0 && (c.fsuid != state.owner || uid != state.owner) { return EPERM; }
+    if gid != u32::MAX && gid != GID_TTY { return EPERM; }
+    state.owner = uid;
+    0
+}
+
+/// Whether tty `index` is the current task's controlling terminal.
+unsafe fn is_ctty(index: usize) -> bool {
+    let sid = unsafe { (*(&raw const SCHED)).tasks[CURRENT_TASK_IDX].session() };
+    index != CONSOLE && unsafe { tty(index).session } == sid
+}
+
+/// Job control for the current task using tty `index`: `signum` is SIGTTIN
+/// for a read, SIGTTOU for a write or a terminal change.  A member of the
+/// foreground group, or a task for which this is not the controlling
+/// terminal, may go ahead (0).  A background job gets `signum` sent to its
+/// group and the call is re-issued once it continues (no return); if that
+/// cannot work — the group is orphaned, or a SIGTTIN would be ignored or
+/// blocked — the call fails with `EIO`.  Ignoring or blocking SIGTTOU lets
+/// the write go ahead, as POSIX says.
+pub unsafe fn job_check(index: usize, signum: u8) -> i64 {
+    if index >= NR_TTYS || !unsafe { is_ctty(index) } { return 0; }
+    let state = unsafe { tty(index) };
+    let t     = unsafe { &(*(&raw const SCHED)).tasks[CURRENT_TASK_IDX] };
+    let pgrp  = t.pgrp();
+    if state.fg_pgrp == 0 || state.fg_pgrp == pgrp { return 0; }
+    let ignored = t.signal_actions[signum as usize].handler == SIG_IGN
+        || t.signal_mask & (1u32 << (signum as u32 - 1)) != 0;
+    if ignored { return if signum == SIGTTOU { 0 } else { EIO }; }
+    if unsafe { crate::kernel::jobctl::orphaned(pgrp) } { return EIO; }
+    unsafe { scheduler::signal_group(pgrp, signum); }
+    if let Some(ctx) = unsafe { core::ptr::replace(&raw mut CURRENT_SYSCALL_CTX, None) } {
+        unsafe { scheduler::restart_syscall(ctx); }
+    }
+    0
+}
+
+/// `job_check` for a read from tty `index`.
+pub unsafe fn may_read(index: usize) -> i64 {
+    unsafe { job_check(index, SIGTTIN) }
+}
+
+/// `job_check` for a write to tty `index`, which only background jobs on a
+/// TOSTOP terminal are stopped for.
+pub unsafe fn may_write(index: usize) -> i64 {
+    if index >= NR_TTYS || unsafe { tty(index).termios.c_lflag } & TOSTOP == 0 { return 0; }
+    unsafe { job_check(index, SIGTTOU) }
+}
+
+// ── File operations ───────────────────────────────────────────────────────
+
+/// `read(2)` on tty `index`: cooked bytes, or `EAGAIN` if it would block.
+/// A hung-up tty reads as end-of-file once its input is drained.
+pub unsafe fn read(index: usize, buf: &mut [u8]) -> i64 {
+    match unsafe { tty(index).read(buf, &mut |b| device_write(index, b)) } {
+        Some(n) => n as i64,
+        None if pty::hung_up(index) => 0,
+        None    => EAGAIN,
+    }
+}
+
+/// `write(2)` on tty `index`.  A pty slave takes what fits in the master's
+/// queue (counting ONLCR's worst case) and reports `EAGAIN` when it is full.
+pub unsafe fn write(index: usize, mut buf: &[u8]) -> i64 {
+    if pty::hung_up(index) { return EIO; }
+    if let Some(room) = pty::room(index) {
+        if room < 2 { return EAGAIN; }
+        buf = &buf[..buf.len().min(room / 2)];
+    }
+    unsafe { tty(index).output(buf, &mut |b| device_write(index, b)); }
+    buf.len() as i64
+}
+
+/// The next raw input byte, for GetChar.
+pub unsafe fn getchar(index: usize) -> Option<u8> {
+    unsafe { tty(index).getchar() }
+}
+
+/// True when a `read` on tty `index` would not block.
+pub unsafe fn readable(index: usize) -> bool {
+    pty::hung_up(index) || unsafe { tty(index).readable(&mut |b| device_write(index, b)) }
+}
+
+/// True when a `write` on tty `index` would not block.
+pub fn writable(index: usize) -> bool {
+    pty::hung_up(index) || pty::room(index).is_none_or(|room| room >= 2)
+}
+
+/// The tty behind the current task's `fd`, if it is one of ours.
+pub unsafe fn of_fd(fd: i32) -> Option<usize> {
+    if fd < 0 || fd as usize >= MAX_FD { return None; }
+    match unsafe { (*(&raw const SCHED)).tasks[CURRENT_TASK_IDX].fd_table.entries[fd as usize] } {
+        Some(e) if e.backend == FdBackend::Tty => Some(e.raw_fd as usize),
+        _ => None,
+    }
+}
+
+/// Called when a read (or write) on the current task's `fd` returned
+/// `EAGAIN`: park the task until the tty is ready.  Returns only if `fd` is
+/// not a tty.
+pub unsafe fn block(fd: i32, write: bool) {
+    if unsafe { of_fd(fd) }.is_none() { return; }
+    if let Some(ctx) = unsafe { core::ptr::replace(&raw mut CURRENT_SYSCALL_CTX, None) } {
+        unsafe { scheduler::wait_for_fd(fd, write, ctx); }
+    }
+}
+
 // ── Public ioctl handler ──────────────────────────────────────────────────
 
-/// Handle an `ioctl(fd, request, arg)` call.
+/// Handle an `ioctl(fd, request, arg)` call on tty `index`.
 ///
-/// `fd` 0/1/2 (or any tty-like fd) routes here.
-/// Returns 0 on success, negative error on failure.
+/// Returns 0 (or the requested value) on success, negative errno on failure.
 ///
 /// # Safety
-/// `arg` is a raw user-space pointer; caller must validate it points to a
-/// valid `Termios` or `Winsize` buffer in user space.
-pub unsafe fn ioctl(fd: i32, request: u64, arg: u64) -> i64 {
-    let _ = fd; // for now all fds share one global TTY
+/// `arg` is a raw user-space pointer; it is validated against the size of
+/// the structure each request reads or writes.
+pub unsafe fn ioctl(index: usize, request: u64, arg: u64) -> i64 {
+    unsafe { ioctl_as(index, request, arg, false) }
+}
 
-    let state = &raw mut TTY;
+/// `ioctl`, issued on the slave or (`master`) through a pty master.  The
+/// master's holder is not subject to job control and may read the
+/// foreground group and session of a terminal it does not control.
+///
+/// The console has no sessions: it keeps accepting every request from
+/// anyone, as before.
+pub unsafe fn ioctl_as(index: usize, request: u64, arg: u64, master: bool) -> i64 {
+    if index >= NR_TTYS { return EINVAL; }
+    let state = unsafe { tty(index) };
+    let user = |size: usize| validate_user_range(arg, size as u64).is_ok();
+    let legacy = index == CONSOLE;
+    let ctty   = unsafe { is_ctty(index) };
+    let task   = unsafe { &(*(&raw const SCHED)).tasks[CURRENT_TASK_IDX] };
+
+    // Changing the terminal is a write as far as job control goes.
+    if !master && matches!(request, TCSETS | TCSETSW | TCSETSF | TCFLSH | TIOCSPGRP) {
+        let r = unsafe { job_check(index, SIGTTOU) };
+        if r < 0 { return r; }
+    }
 
     match request {
         // TCGETS — copy current termios into user buffer
         TCGETS => {
-            if let Err(_) = crate::kernel::syscall_core::validate_user_range(
-                arg, core::mem::size_of::<Termios>() as u64,
-            ) {
-                return -1; // EINVAL
-            }
-            unsafe {
-                core::ptr::write_unaligned(arg as *mut Termios, (*state).termios);
-            }
+            if !user(core::mem::size_of::<Termios>()) { return EFAULT; }
+            unsafe { core::ptr::write_unaligned(arg as *mut Termios, state.termios); }
             0
         }
 
-        // TCSETS / TCSETSW / TCSETSF — update termios from user buffer
+        // TCSETS / TCSETSW / TCSETSF — update termios from user buffer.
+        // Output is never queued, so TCSETSW has nothing to wait for.
         TCSETS | TCSETSW | TCSETSF => {
-            if let Err(_) = crate::kernel::syscall_core::validate_user_range(
-                arg, core::mem::size_of::<Termios>() as u64,
-            ) {
-                return -1; // EINVAL
-            }
+            if !user(core::mem::size_of::<Termios>()) { return EFAULT; }
             let new_termios = unsafe { core::ptr::read_unaligned(arg as *const Termios) };
-            unsafe { (*state).termios = new_termios; }
+            if request == TCSETSF { state.flush_input(); }
+            state.set_termios(new_termios);
+            0
+        }
+
+        // TCFLSH — discard input (TCIFLUSH / TCIOFLUSH); output is unbuffered
+        TCFLSH => {
+            if arg == 0 || arg == 2 { state.flush_input(); }
             0
         }
 
         // TIOCGWINSZ — copy window size into user buffer
         TIOCGWINSZ => {
-            if let Err(_) = crate::kernel::syscall_core::validate_user_range(
-                arg, core::mem::size_of::<Winsize>() as u64,
-            ) {
-                return -1; // EINVAL
-            }
-            unsafe {
-                core::ptr::write_unaligned(arg as *mut Winsize, (*state).winsize);
-            }
+            if !user(core::mem::size_of::<Winsize>()) { return EFAULT; }
+            unsafe { core::ptr::write_unaligned(arg as *mut Winsize, state.winsize); }
             0
         }
 
-        // TIOCSWINSZ — update window size from user buffer
+        // TIOCSWINSZ — update window size from user buffer; a change is
+        // reported to the foreground group with SIGWINCH
         TIOCSWINSZ => {
-            if let Err(_) = crate::kernel::syscall_core::validate_user_range(
-                arg, core::mem::size_of::<Winsize>() as u64,
-            ) {
-                return -1; // EINVAL
-            }
+            if !user(core::mem::size_of::<Winsize>()) { return EFAULT; }
             let ws = unsafe { core::ptr::read_unaligned(arg as *const Winsize) };
-            unsafe { (*state).winsize = ws; }
+            let old = state.winsize;
+            state.winsize = ws;
+            let changed = (ws.ws_row, ws.ws_col, ws.ws_xpixel, ws.ws_ypixel)
+                != (old.ws_row, old.ws_col, old.ws_xpixel, old.ws_ypixel);
+            if changed && state.fg_pgrp != 0 {
+                unsafe { scheduler::signal_group(state.fg_pgrp, SIGWINCH); }
+            }
             0
         }
 
-        // TIOCGPGRP — get foreground process group (return current PID as stub)
+        // TIOCGPGRP — foreground process group of the caller's controlling
+        // terminal; on the console, the caller's own group while nobody has
+        // set one
         TIOCGPGRP => {
-            if let Err(_) = crate::kernel::syscall_core::validate_user_range(arg, 4) {
-                return -1;
+            if !legacy && !ctty && !master { return ENOTTY; }
+            if !user(4) { return EFAULT; }
+            let pgrp = if state.fg_pgrp != 0 || !legacy { state.fg_pgrp } else { task.pgrp() };
+            unsafe { core::ptr::write_unaligned(arg as *mut u32, pgrp as u32); }
+            0
+        }
+
+        // TIOCSPGRP — make a process group of the caller's session the
+        // foreground one
+        TIOCSPGRP => {
+            if !legacy && !ctty { return ENOTTY; }
+            if !user(4) { return EFAULT; }
+            let pgrp = unsafe { core::ptr::read_unaligned(arg as *const i32) };
+            if pgrp <= 0 { return EINVAL; }
+            if pgrp > u8::MAX as i32 { return EPERM; }
+            if !legacy && !unsafe { crate::kernel::jobctl::group_in_session(pgrp as u8, task.session()) } {
+                return EPERM;
             }
-            let pid = unsafe {
-                let sched = &raw const crate::kernel::scheduler::SCHED;
-                let idx   = crate::kernel::scheduler::CURRENT_TASK_IDX;
-                (*sched).tasks[idx].pid as u32
-            };
-            unsafe { core::ptr::write_unaligned(arg as *mut u32, pid); }
+            state.fg_pgrp = pgrp as u8;
             0
         }
 
-        // TIOCSPGRP — set foreground process group (stub: accept and ignore)
-        TIOCSPGRP => 0,
+        // TIOCGSID — session the terminal is the controlling terminal of
+        TIOCGSID => {
+            if legacy || (!ctty && !master) || state.session == 0 { return ENOTTY; }
+            if !user(4) { return EFAULT; }
+            unsafe { core::ptr::write_unaligned(arg as *mut u32, state.session as u32); }
+            0
+        }
 
-        _ => {
-            // Unknown ioctl — not supported
-            -1 // EINVAL
+        // FIONREAD — bytes a read would return
+        FIONREAD => {
+            if !user(4) { return EFAULT; }
+            let n = state.pending(&mut |b| device_write(index, b)) as u32;
+            unsafe { core::ptr::write_unaligned(arg as *mut u32, n); }
+            0
+        }
+
+        // TIOCSCTTY — a session leader without a controlling terminal
+        // claims this one; root can take it from another session with arg 1
+        TIOCSCTTY => {
+            if legacy { return 0; }
+            if ctty { return 0; }
+            if task.session() != task.pid || ctty_of(task.pid).is_some() { return EPERM; }
+            if state.session != 0 {
+                if arg != 1 || crate::kernel::cred::geteuid() != 0 { return EPERM; }
+                unsafe { release(index); }
+            }
+            unsafe { attach(index); }
+            0
         }
+
+        // TIOCNOTTY — give up the controlling terminal; when the session
+        // leader does, the foreground group is hung up
+        TIOCNOTTY => {
+            if legacy { return 0; }
+            if !ctty { return ENOTTY; }
+            if task.session() == task.pid { unsafe { release(index); } }
+            0
+        }
+
+        // KIOCSOUND — start a tone of PIT period `arg` (0 stops it) on the
+        // PC speaker.  Like the other KD* requests, consoles only.
+        KIOCSOUND => {
+            if !legacy && !(VT_BASE..PTY_BASE).contains(&index) { return ENOTTY; }
+            speaker::sound(arg as u32 & 0xFFFF);
+            0
+        }
+
+        // KDMKTONE — tone of period `arg & 0xFFFF` for `arg >> 16` ms
+        KDMKTONE => {
+            if !legacy && !(VT_BASE..PTY_BASE).contains(&index) { return ENOTTY; }
+            speaker::tone(arg as u32 & 0xFFFF, (arg as u32 >> 16) & 0xFFFF);
+            0
+        }
+
+        _ => EINVAL,
     }
 }
 
-/// Returns `true` if the TTY is in canonical (line-buffered) mode.
+/// Returns `true` if the console is in canonical (line-buffered) mode.
 pub fn is_canonical() -> bool {
-    unsafe { TTY.termios.c_lflag & ICANON != 0 }
+    unsafe { TTYS[CONSOLE].termios.c_lflag & ICANON != 0 }
 }
 
-/// Returns `true` if echo is enabled.
+/// Returns `true` if echo is enabled on the console.
 pub fn is_echo() -> bool {
-    unsafe { TTY.termios.c_lflag & ECHO != 0 }
+    unsafe { TTYS[CONSOLE].termios.c_lflag & ECHO != 0 }
 }
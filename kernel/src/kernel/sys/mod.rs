@@ -2,3 +2,4 @@
 pub mod syscall_core;
 pub mod syscall;
 pub mod syscall_handler;
+pub mod systrace;
@@ -8,16 +8,17 @@ use core::arch::asm;
 
 pub use super::syscall_core::{Syscall, SyscallRequest, SyscallResult, SystemInfo};
 use super::syscall_core::{dispatch, SyscallRuntime};
+use crate::kernel::cred::ImageOwner;
 
 struct KernelRuntime;
 
 impl SyscallRuntime for KernelRuntime {
-    fn trace(&mut self, syscall: Syscall) {
-        unsafe {
-            SERIAL_PORT.write_str("SYSCALL: ");
-            SERIAL_PORT.write_str(syscall.name());
-            SERIAL_PORT.write_str("\n");
-        }
+    fn trace(&mut self, syscall: Syscall, request: &SyscallRequest) {
+        unsafe { crate::kernel::systrace::enter(syscall, request); }
+    }
+
+    fn trace_exit(&mut self, _syscall: Syscall, result: &SyscallResult) {
+        unsafe { crate::kernel::systrace::exit(result); }
     }
 
     fn trace_unknown(&mut self, num: u64) {
@@ -32,7 +33,17 @@ impl SyscallRuntime for KernelRuntime {
         unsafe { crate::kernel::timer::get_ticks() }
     }
 
+    fn user_page_mapped(&self, addr: u64) -> bool {
+        unsafe { crate::kernel::paging_allocator::is_page_mapped_current(addr) }
+    }
+
     fn write_console(&mut self, bytes: &[u8]) {
+        // A task whose stdout is a tty device (getty's children) prints there.
+        if let Some(tty) = unsafe { crate::kernel::tty::of_fd(1) } {
+            if unsafe { crate::kernel::tty::may_write(tty) } < 0 { return; }
+            unsafe { crate::kernel::tty::write(tty, bytes); }
+            return;
+        }
         // Always echo to serial for debugging.
         unsafe {
             for &byte in bytes {
@@ -77,6 +88,14 @@ impl SyscallRuntime for KernelRuntime {
     }
 
     fn get_char(&mut self) -> i64 {
+        if let Some(tty) = unsafe { crate::kernel::tty::of_fd(0) } {
+            let r = unsafe { crate::kernel::tty::may_read(tty) };
+            if r < 0 { return r; }
+            return match unsafe { crate::kernel::tty::getchar(tty) } {
+                Some(ch) => ch as i64,
+                None     => -6, // EAGAIN
+            };
+        }
         match crate::kernel::stdin::pop() {
             Some(ch) => ch as i64,
             None     => -6, // EAGAIN
@@ -89,7 +108,8 @@ impl SyscallRuntime for KernelRuntime {
                 SERIAL_PORT.write_str("User task exiting (code ");
                 SERIAL_PORT.write_decimal(code as u32);
                 SERIAL_PORT.write_str(")\n");
-                crate::kernel::user_mode::exit_to_kernel(code as i64);
+                // Only the low 8 bits reach wait4; negative codes mean "killed".
+                crate::kernel::user_mode::exit_to_kernel((code & 0xff) as i64);
             }
         }
         unsafe {
@@ -141,37 +161,71 @@ impl SyscallRuntime for KernelRuntime {
         }
     }
 
-    fn waitpid_impl(&mut self, pid: u64) -> i64 {
-        unsafe {
-            use crate::kernel::scheduler::{SCHED, CURRENT_TASK_IDX, MAX_TASKS, TaskState};
-            use crate::kernel::user_mode::CURRENT_SYSCALL_CTX;
-
-            let target_pid = pid as u8;
-            let parent_idx = CURRENT_TASK_IDX;
-            let parent_pid = (*(&raw const SCHED)).tasks[parent_idx].pid;
+    fn wait4_impl(&mut self, pid: i32, status_ptr: u64, options: u32, rusage_ptr: u64) -> i64 {
+        use crate::kernel::scheduler::{SCHED, CURRENT_TASK_IDX, TaskState, WaitCheck,
+                                       WUNTRACED, WCONTINUED,
+                                       wait_check, wait_status, wait_for_pid};
+        use crate::kernel::ptrace::TraceState;
+        use crate::kernel::user_mode::CURRENT_SYSCALL_CTX;
+        use crate::kernel::syscall_core::validate_user_range;
+        const WNOHANG: u32 = 1;
+        // __WNOTHREAD, __WALL, __WCLONE: accepted; there is one thread per
+        // process.
+        const KNOWN: u32 = WNOHANG | WUNTRACED | WCONTINUED | 0x2000_0000 | 0x4000_0000 | 0x8000_0000;
+        use crate::kernel::timers::RUSAGE_SIZE;
+        if options & !KNOWN != 0 { return -22; } // EINVAL
+        if status_ptr != 0 && validate_user_range(status_ptr, 4).is_err() { return -14; } // EFAULT
+        if rusage_ptr != 0 && validate_user_range(rusage_ptr, RUSAGE_SIZE as u64).is_err() { return -14; }
 
-            // Check if the child is already dead.
-            let sched = &raw mut SCHED;
-            for i in 0..MAX_TASKS {
-                if (*sched).tasks[i].pid        == target_pid
-                && (*sched).tasks[i].parent_pid == parent_pid
-                {
-                    if let TaskState::Dead(code) = (*sched).tasks[i].state {
-                        (*sched).tasks[i].state   = TaskState::Empty;
-                        (*sched).tasks[i].pid     = 0;
-                        (*sched).tasks[i].parent_pid = 0;
-                        return code;
-                    }
-                    // Child exists but still alive — fall through to block.
+        unsafe {
+            let sched  = &raw mut SCHED;
+            let me     = CURRENT_TASK_IDX;
+            let my_pid = (*sched).tasks[me].pid;
+            let idx = match wait_check(me, pid, options) {
+                WaitCheck::Ready(i) => i,
+                WaitCheck::NoChild  => return -10, // ECHILD
+                WaitCheck::Pending  => {
+                    if options & WNOHANG != 0 { return 0; }
                     let ctx_opt = core::ptr::replace(&raw mut CURRENT_SYSCALL_CTX, None);
                     if let Some(ctx) = ctx_opt {
-                        crate::kernel::scheduler::wait_for_pid(parent_idx, target_pid, ctx);
-                        // ^^^ diverges — resumes in ring-3 when child dies
+                        wait_for_pid(me, pid, options, ctx);
+                        // ^^^ diverges — the call restarts once a report is ready
                     }
                     return -6; // EAGAIN (only if context was not available)
                 }
+            };
+
+            let child = &raw mut (*sched).tasks[idx];
+            let child_pid = (*child).pid;
+            let status = match (*child).state {
+                TaskState::Dead(code) => {
+                    if (*child).trace.tracer == my_pid && (*child).parent_pid != my_pid {
+                        // A tracer sees the exit once; the real parent reaps.
+                        (*child).trace = TraceState::NONE;
+                    } else {
+                        crate::kernel::timers::reap(me, idx);
+                        (*child).state      = TaskState::Empty;
+                        (*child).pid        = 0;
+                        (*child).parent_pid = 0;
+                        (*child).trace      = TraceState::NONE;
+                    }
+                    wait_status(code)
+                }
+                // A ptrace-stop, or a job-control stop or continue: each
+                // one is reported once.
+                TaskState::Traced if (*child).trace.tracer == my_pid =>
+                    core::mem::take(&mut (*child).trace.report),
+                _ => core::mem::take(&mut (*child).job_report),
+            };
+            if status_ptr != 0 {
+                core::ptr::write_unaligned(status_ptr as *mut i32, status as i32);
+            }
+            if rusage_ptr != 0 {
+                let mut usage = (*child).cpu;
+                usage.add(&(*child).child_cpu);
+                crate::kernel::timers::write_rusage(rusage_ptr, &usage);
             }
-            -3 // EACCES — no such child of this process
+            child_pid as i64
         }
     }
 
@@ -180,15 +234,16 @@ impl SyscallRuntime for KernelRuntime {
             use crate::kernel::scheduler::{SCHED, CURRENT_TASK_IDX};
             use crate::kernel::paging_allocator as pa;
 
-            const USER_HEAP_BASE: u64 = 0x0100_0000; // 16 MB — well above code+stack
             const PAGE_SIZE:      u64 = 4096;
 
             let sched   = &raw mut SCHED;
             let idx     = CURRENT_TASK_IDX;
             let cr3     = (*sched).tasks[idx].cr3;
+            // Randomised per exec, just above the loaded image.
+            let heap_base = (*sched).tasks[idx].layout.heap_base;
             let cur_end = {
                 let h = (*sched).tasks[idx].heap_end;
-                if h == 0 { USER_HEAP_BASE } else { h }
+                if h == 0 { heap_base } else { h }
             };
 
             // brk(0) — query current break.
@@ -196,7 +251,7 @@ impl SyscallRuntime for KernelRuntime {
                 return cur_end as i64;
             }
             // Refuse to shrink below heap base or to move backwards (keep it simple).
-            if new_end < USER_HEAP_BASE || new_end <= cur_end {
+            if new_end < heap_base || new_end <= cur_end {
                 return cur_end as i64;
             }
 
@@ -215,75 +270,85 @@ impl SyscallRuntime for KernelRuntime {
         }
     }
 
-    fn mmap_anon(&mut self, _hint: u64, len: u64) -> i64 {
-        if len == 0 { return -22; } // EINVAL
-        unsafe {
-            use crate::kernel::scheduler::{SCHED, CURRENT_TASK_IDX, MmapRegion, MAX_MMAP_REGIONS};
-            use crate::kernel::paging_allocator as pa;
+    fn mmap_anon(&mut self, hint: u64, len: u64) -> i64 {
+        use crate::kernel::mmap::{self, PROT_READ, PROT_WRITE, MAP_PRIVATE, MAP_ANONYMOUS};
+        unsafe { mmap::mmap(hint, len, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0) }
+    }
 
-            const USER_MMAP_BASE: u64 = 0x0800_0000;
-            const PAGE_SIZE: u64 = 4096;
+    fn mmap_impl(&mut self, addr: u64, len: u64, prot: u32, flags: u32, fd: i32, offset: u64) -> i64 {
+        unsafe { crate::kernel::mmap::mmap(addr, len, prot, flags, fd, offset) }
+    }
 
-            let sched = &raw mut SCHED;
-            let idx   = CURRENT_TASK_IDX;
-            let cr3   = (*sched).tasks[idx].cr3;
+    fn munmap_impl(&mut self, addr: u64, len: u64) -> i64 {
+        unsafe { crate::kernel::mmap::munmap(addr, len) }
+    }
 
-            let base = {
-                let end = (*sched).tasks[idx].mmap_end;
-                if end == 0 { USER_MMAP_BASE } else { end }
-            };
+    fn mprotect_impl(&mut self, addr: u64, len: u64, prot: u64) -> i64 {
+        unsafe { crate::kernel::mmap::mprotect(addr, len, prot as u32) }
+    }
 
-            let pages = ((len + PAGE_SIZE - 1) / PAGE_SIZE) as usize;
+    fn mremap_impl(&mut self, old_addr: u64, old_len: u64, new_len: u64, flags: u32) -> i64 {
+        unsafe { crate::kernel::mmap::mremap(old_addr, old_len, new_len, flags) }
+    }
 
-            if pa::map_user_region_in(cr3, base, pages, true, false).is_err() {
-                return -12; // ENOMEM
-            }
+    fn msync_impl(&mut self, addr: u64, len: u64, flags: u32) -> i64 {
+        unsafe { crate::kernel::mmap::msync(addr, len, flags) }
+    }
 
-            let new_end = base + pages as u64 * PAGE_SIZE;
-            (*sched).tasks[idx].mmap_end = new_end;
+    fn mincore_impl(&mut self, addr: u64, len: u64, vec_ptr: u64) -> i64 {
+        unsafe { crate::kernel::mmap::mincore(addr, len, vec_ptr) }
+    }
 
-            // Record this region for munmap.
-            let nr = (*sched).tasks[idx].mmap_nregions;
-            if nr < MAX_MMAP_REGIONS {
-                (*sched).tasks[idx].mmap_regions[nr] = MmapRegion { virt: base, pages: pages as u32, _pad: 0 };
-                (*sched).tasks[idx].mmap_nregions = nr + 1;
-            }
+    fn madvise_impl(&mut self, addr: u64, len: u64, advice: u32) -> i64 {
+        unsafe { crate::kernel::mmap::madvise(addr, len, advice) }
+    }
 
-            base as i64
+    fn swapon_impl(&mut self, path: &[u8], _flags: u32) -> i64 {
+        if !crate::kernel::cred::get().privileged() { return -1; } // EPERM
+        match core::str::from_utf8(path) {
+            Ok(p)  => unsafe { crate::kernel::swap::swapon(p) },
+            Err(_) => -22, // EINVAL
         }
     }
 
-    fn munmap_impl(&mut self, addr: u64, len: u64) -> i64 {
-        if len == 0 { return -22; } // EINVAL
-        unsafe {
-            use crate::kernel::scheduler::{SCHED, CURRENT_TASK_IDX, MmapRegion, MAX_MMAP_REGIONS};
-            use crate::kernel::paging_allocator as pa;
-
-            const PAGE_SIZE: u64 = 4096;
+    fn swapoff_impl(&mut self, path: &[u8]) -> i64 {
+        if !crate::kernel::cred::get().privileged() { return -1; } // EPERM
+        match core::str::from_utf8(path) {
+            Ok(p)  => unsafe { crate::kernel::swap::swapoff(p) },
+            Err(_) => -22, // EINVAL
+        }
+    }
 
-            let sched = &raw mut SCHED;
-            let idx   = CURRENT_TASK_IDX;
-            let cr3   = (*sched).tasks[idx].cr3;
-            let pages = ((len + PAGE_SIZE - 1) / PAGE_SIZE) as usize;
-
-            pa::unmap_user_region_in(cr3, addr, pages);
-
-            // Remove from the tracked region list (swap-remove).
-            let nr = (*sched).tasks[idx].mmap_nregions;
-            for i in 0..nr {
-                if (*sched).tasks[idx].mmap_regions[i].virt == addr {
-                    (*sched).tasks[idx].mmap_regions[i] = (*sched).tasks[idx].mmap_regions[nr - 1];
-                    (*sched).tasks[idx].mmap_regions[nr - 1] = MmapRegion::empty();
-                    (*sched).tasks[idx].mmap_nregions = nr - 1;
-                    break;
-                }
-            }
-            0
+    fn reboot_impl(&mut self, cmd: u32) -> i64 {
+        use super::syscall_core::{
+            LINUX_REBOOT_CMD_HALT, LINUX_REBOOT_CMD_POWER_OFF, LINUX_REBOOT_CMD_RESTART,
+        };
+        use crate::kernel::shutdown;
+        if !crate::kernel::cred::get().privileged() { return -1; } // EPERM
+        match cmd {
+            LINUX_REBOOT_CMD_RESTART   => shutdown::reboot(),
+            LINUX_REBOOT_CMD_HALT      => shutdown::halt(),
+            LINUX_REBOOT_CMD_POWER_OFF => shutdown::poweroff(),
+            _ => -22, // EINVAL; syscall_core refuses other commands
         }
     }
 
     fn kill_pid_sig(&mut self, pid: u64, signum: u8) -> i64 {
-        let ok = unsafe { crate::kernel::scheduler::send_signal(pid as u8, signum) };
+        use crate::kernel::scheduler::{init_ignores, send_signal_info, SigInfo};
+        let sender = self.current_pid() as u32;
+        // 0, -1 and -pgrp address several processes.
+        if pid as i32 <= 0 {
+            return unsafe { crate::kernel::jobctl::kill_many(pid as i32, signum, sender) };
+        }
+        if pid > u8::MAX as u64 { return -3; } // ESRCH
+        let sched = unsafe { &*(&raw const crate::kernel::scheduler::SCHED) };
+        let Some(idx) = sched.tasks.iter().position(|t| t.is_live() && t.pid == pid as u8) else {
+            return -3; // ESRCH
+        };
+        if !unsafe { crate::kernel::jobctl::may_kill(idx, signum) } { return -1; } // EPERM
+        // Signals init has no handler for are dropped, not refused.
+        if init_ignores(pid as u8, signum) { return 0; }
+        let ok = unsafe { send_signal_info(pid as u8, signum, SigInfo::user(sender, crate::kernel::cred::get().ruid)) };
         if ok { 0 } else { -3 }
     }
 
@@ -295,48 +360,62 @@ impl SyscallRuntime for KernelRuntime {
         }
     }
 
-    fn getpgid_impl(&mut self, pid: u32) -> i64 {
-        unsafe {
-            let sched = &raw const crate::kernel::scheduler::SCHED;
-            let idx   = crate::kernel::scheduler::CURRENT_TASK_IDX;
-            // pid == 0 means calling process
-            let target = if pid == 0 {
-                idx
-            } else {
-                let mut found = idx;
-                for i in 0..crate::kernel::scheduler::MAX_TASKS {
-                    if (*sched).tasks[i].pid == pid as u8 { found = i; break; }
-                }
-                found
-            };
-            let task = &(*sched).tasks[target];
-            // pgid == 0 means same as pid
-            let pgid = if task.pgid == 0 { task.pid } else { task.pgid };
-            pgid as i64
-        }
-    }
+    // ── Sessions and process groups (see jobctl.rs) ───────────────────────
+
+    fn getpgid_impl(&mut self, pid: u32) -> i64 { unsafe { crate::kernel::jobctl::getpgid(pid) } }
+    fn getsid_impl(&mut self, pid: u32)  -> i64 { unsafe { crate::kernel::jobctl::getsid(pid) } }
+    fn getpgrp_impl(&mut self)           -> i64 { self.getpgid_impl(0) }
+    fn setsid_impl(&mut self)            -> i64 { unsafe { crate::kernel::jobctl::setsid() } }
 
     fn setpgid_impl(&mut self, pid: u32, pgid: u32) -> i64 {
-        unsafe {
-            let sched = &raw mut crate::kernel::scheduler::SCHED;
-            let cur_idx = crate::kernel::scheduler::CURRENT_TASK_IDX;
-            let cur_pid = (*sched).tasks[cur_idx].pid;
-            let target_pid = if pid == 0 { cur_pid as u32 } else { pid };
-            let new_pgid   = if pgid == 0 { target_pid as u8 } else { pgid as u8 };
-            for i in 0..crate::kernel::scheduler::MAX_TASKS {
-                if (*sched).tasks[i].pid == target_pid as u8 {
-                    (*sched).tasks[i].pgid = new_pgid;
-                    return 0;
-                }
-            }
-            -3 // ESRCH
-        }
+        unsafe { crate::kernel::jobctl::setpgid(pid, pgid) }
     }
 
-    fn getpgrp_impl(&mut self) -> i64 {
-        self.getpgid_impl(0)
+    // ── Credentials (see cred.rs) ──────────────────────────────────────────
+
+    fn getuid_impl(&mut self)  -> i64 { crate::kernel::cred::getuid() }
+    fn geteuid_impl(&mut self) -> i64 { crate::kernel::cred::geteuid() }
+    fn getgid_impl(&mut self)  -> i64 { crate::kernel::cred::getgid() }
+    fn getegid_impl(&mut self) -> i64 { crate::kernel::cred::getegid() }
+
+    fn setuid_impl(&mut self, uid: u32) -> i64 { crate::kernel::cred::setuid(uid) }
+    fn setgid_impl(&mut self, gid: u32) -> i64 { crate::kernel::cred::setgid(gid) }
+
+    fn setreuid_impl(&mut self, ruid: u32, euid: u32) -> i64 {
+        crate::kernel::cred::setreuid(ruid, euid)
+    }
+
+    fn setregid_impl(&mut self, rgid: u32, egid: u32) -> i64 {
+        crate::kernel::cred::setregid(rgid, egid)
     }
 
+    fn setresuid_impl(&mut self, ruid: u32, euid: u32, suid: u32) -> i64 {
+        crate::kernel::cred::setresuid(ruid, euid, suid)
+    }
+
+    fn setresgid_impl(&mut self, rgid: u32, egid: u32, sgid: u32) -> i64 {
+        crate::kernel::cred::setresgid(rgid, egid, sgid)
+    }
+
+    fn getresuid_impl(&mut self, ruid_ptr: u64, euid_ptr: u64, suid_ptr: u64) -> i64 {
+        unsafe { crate::kernel::cred::getresuid(ruid_ptr, euid_ptr, suid_ptr) }
+    }
+
+    fn getresgid_impl(&mut self, rgid_ptr: u64, egid_ptr: u64, sgid_ptr: u64) -> i64 {
+        unsafe { crate::kernel::cred::getresgid(rgid_ptr, egid_ptr, sgid_ptr) }
+    }
+
+    fn getgroups_impl(&mut self, size: u32, list_ptr: u64) -> i64 {
+        unsafe { crate::kernel::cred::getgroups(size, list_ptr) }
+    }
+
+    fn setgroups_impl(&mut self, size: u64, list_ptr: u64) -> i64 {
+        unsafe { crate::kernel::cred::setgroups(size, list_ptr) }
+    }
+
+    fn setfsuid_impl(&mut self, uid: u32) -> i64 { crate::kernel::cred::setfsuid(uid) }
+    fn setfsgid_impl(&mut self, gid: u32) -> i64 { crate::kernel::cred::setfsgid(gid) }
+
     fn arch_prctl_impl(&mut self, code: u64, addr: u64) -> i64 {
         const ARCH_SET_GS: u64 = 0x1001;
         const ARCH_SET_FS: u64 = 0x1002;
@@ -424,17 +503,10 @@ impl SyscallRuntime for KernelRuntime {
         0
     }
 
-    fn clock_gettime_impl(&mut self, _clk_id: u64, tp_ptr: u64) -> i64 {
-        if tp_ptr == 0 { return -14; } // EFAULT
-        // Return monotonic time from PIT ticks (100 Hz).
-        let ticks = unsafe { crate::kernel::timer::get_ticks() };
-        let secs  = ticks / 100;
-        let nsecs = (ticks % 100) * 10_000_000;
-        unsafe {
-            core::ptr::write_unaligned(tp_ptr as *mut u64, secs);
-            core::ptr::write_unaligned((tp_ptr + 8) as *mut u64, nsecs);
-        }
-        0
+    fn clock_gettime_impl(&mut self, clk_id: u64, tp_ptr: u64) -> i64 {
+        // Wall clocks read PIT ticks (100 Hz); the CPU-time clocks read the
+        // task's TSC-measured usage.
+        unsafe { crate::kernel::timers::clock_gettime(clk_id, tp_ptr) }
     }
 
     fn fcntl_impl(&mut self, fd: i32, cmd: u64, arg: u64) -> i64 {
@@ -687,6 +759,13 @@ impl SyscallRuntime for KernelRuntime {
                         new_off
                     } else { -9 }
                 }
+                FdBackend::Mem | FdBackend::Block | FdBackend::Fb => {
+                    let new_off = crate::kernel::devfs::lseek(&entry, offset, whence);
+                    if new_off < 0 { return new_off; }
+                    (*sched).tasks[idx].fd_table.entries[fd as usize]
+                        .as_mut().unwrap().offset = new_off as usize;
+                    new_off
+                }
                 _ => -29, // ESPIPE (pipes, ttys, sockets)
             }
         }
@@ -728,14 +807,20 @@ impl SyscallRuntime for KernelRuntime {
         total
     }
 
-    fn access_impl(&mut self, path: &[u8], _mode: u32) -> i64 {
-        // Just check existence via stat
+    fn access_impl(&mut self, path: &[u8], mode: u32) -> i64 {
+        // Existence via stat, then the permission bits against the *real*
+        // uid and gid, so set-uid programs can ask what their caller may do.
         let path_str = match core::str::from_utf8(path) {
             Ok(s) => s,
             Err(_) => return -22,
         };
-        let mut dummy = crate::kernel::vfs::LinuxStat::zeroed();
-        unsafe { crate::kernel::vfs::vfs_stat_linux(path_str, &mut dummy) }
+        let mut st = crate::kernel::vfs::LinuxStat::zeroed();
+        let r = unsafe { crate::kernel::vfs::vfs_stat_linux(path_str, &mut st) };
+        if r < 0 || mode & 7 == 0 { return r; }
+        let mut c = crate::kernel::cred::get();
+        c.fsuid = c.ruid;
+        c.fsgid = c.rgid;
+        if c.may_access(mode & 7, st.st_mode as u16 & 0o7777, st.st_uid, st.st_gid) { 0 } else { -13 } // EACCES
     }
 
     fn dup_impl(&mut self, fd: i32) -> i64 {
@@ -792,25 +877,154 @@ impl SyscallRuntime for KernelRuntime {
     }
 
     fn getrlimit_impl(&mut self, resource: u32, rlim_ptr: u64) -> i64 {
-        // struct rlimit { rlim_cur: u64, rlim_max: u64 } = 16 bytes
-        // Return RLIM_INFINITY for all resources
-        if rlim_ptr == 0 { return -22; }
-        const RLIM_INF: u64 = u64::MAX;
+        self.prlimit64_impl(0, resource, 0, rlim_ptr)
+    }
+
+    fn setrlimit_impl(&mut self, resource: u32, rlim_ptr: u64) -> i64 {
+        self.prlimit64_impl(0, resource, rlim_ptr, 0)
+    }
+
+    fn prlimit64_impl(&mut self, pid: u32, resource: u32, new_ptr: u64, old_ptr: u64) -> i64 {
+        // struct rlimit { rlim_cur: u64, rlim_max: u64 } = 16 bytes.
+        // RLIMIT_CORE is tracked per task; every other resource reads as
+        // RLIM_INFINITY and accepts (and ignores) new values.  syscall_core
+        // has checked the resource, both pointers and soft <= hard.
+        use crate::kernel::scheduler::{SCHED, CURRENT_TASK_IDX, MAX_TASKS, TaskState, RLIM_INFINITY};
+        const RLIMIT_CORE: u32 = 4;
         unsafe {
-            core::ptr::write_unaligned(rlim_ptr as *mut u64, RLIM_INF);
-            core::ptr::write_unaligned((rlim_ptr + 8) as *mut u64, RLIM_INF);
+            let sched = &raw mut SCHED;
+            let idx = if pid == 0 {
+                CURRENT_TASK_IDX
+            } else {
+                match (0..MAX_TASKS).find(|&i| {
+                    (*sched).tasks[i].pid as u32 == pid
+                        && !matches!((*sched).tasks[i].state, TaskState::Empty | TaskState::Dead(_))
+                }) {
+                    Some(i) => i,
+                    None    => return -3, // ESRCH
+                }
+            };
+            let task = &raw mut (*sched).tasks[idx];
+            let cur = if resource == RLIMIT_CORE {
+                (*task).core_rlimit
+            } else {
+                (RLIM_INFINITY, RLIM_INFINITY)
+            };
+            let new = if new_ptr != 0 {
+                let soft = core::ptr::read_unaligned(new_ptr as *const u64);
+                let hard = core::ptr::read_unaligned((new_ptr + 8) as *const u64);
+                if hard > cur.1 { return -1; } // EPERM — the hard limit only goes down
+                Some((soft, hard))
+            } else {
+                None
+            };
+            if old_ptr != 0 {
+                core::ptr::write_unaligned(old_ptr as *mut u64, cur.0);
+                core::ptr::write_unaligned((old_ptr + 8) as *mut u64, cur.1);
+            }
+            if let (Some(new), RLIMIT_CORE) = (new, resource) {
+                (*task).core_rlimit = new;
+            }
         }
-        let _ = resource;
         0
     }
 
-    fn getrusage_impl(&mut self, _who: i32, buf_ptr: u64) -> i64 {
-        // struct rusage is 144 bytes — zero it out
-        if buf_ptr == 0 { return -22; }
+    fn getrandom_impl(&mut self, buf: &mut [u8], _flags: u32) -> i64 {
+        // The generator is seeded at boot, before any task runs, so nothing
+        // ever waits: the flags syscall_core lets through are all no-ops.
+        // Linux returns at most 32 MiB - 1 per call.
+        let n = buf.len().min(0x01FF_FFFF);
+        crate::kernel::rng::fill(&mut buf[..n]);
+        n as i64
+    }
+
+    fn ptrace_impl(&mut self, request: i64, pid: u32, addr: u64, data: u64) -> i64 {
+        unsafe { crate::kernel::ptrace::request(request, pid, addr, data) }
+    }
+
+    fn systrace_impl(&mut self, op: u64, pid: u64) -> i64 {
+        unsafe { crate::kernel::systrace::control(op, pid) }
+    }
+
+    fn loadkeys_impl(&mut self, name: &[u8]) -> i64 {
+        // The layout is system-wide, like the console keymap on Linux.
+        if !crate::kernel::cred::get().privileged() { return -1; } // EPERM
+        match core::str::from_utf8(name) {
+            Ok(name) => unsafe { crate::kernel::keymap::load(name) },
+            Err(_)   => -22, // EINVAL
+        }
+    }
+
+    fn getrusage_impl(&mut self, who: i32, buf_ptr: u64) -> i64 {
+        unsafe { crate::kernel::timers::getrusage(who, buf_ptr) }
+    }
+
+    fn times_impl(&mut self, buf_ptr: u64) -> i64 {
+        unsafe { crate::kernel::timers::times(buf_ptr) }
+    }
+
+    fn getitimer_impl(&mut self, which: u64, curr_ptr: u64) -> i64 {
+        unsafe { crate::kernel::timers::getitimer(which, curr_ptr) }
+    }
+
+    fn setitimer_impl(&mut self, which: u64, new_ptr: u64, old_ptr: u64) -> i64 {
+        unsafe { crate::kernel::timers::setitimer(which, new_ptr, old_ptr) }
+    }
+
+    fn timer_create_impl(&mut self, clock_id: u64, sevp: u64, timerid_ptr: u64) -> i64 {
+        unsafe { crate::kernel::timers::timer_create(clock_id, sevp, timerid_ptr) }
+    }
+
+    fn timer_settime_impl(&mut self, id: u64, flags: u64, new_ptr: u64, old_ptr: u64) -> i64 {
+        unsafe { crate::kernel::timers::timer_settime(id, flags, new_ptr, old_ptr) }
+    }
+
+    fn timer_gettime_impl(&mut self, id: u64, curr_ptr: u64) -> i64 {
+        unsafe { crate::kernel::timers::timer_gettime(id, curr_ptr) }
+    }
+
+    fn timer_getoverrun_impl(&mut self, id: u64) -> i64 {
+        unsafe { crate::kernel::timers::timer_getoverrun(id) }
+    }
+
+    fn timer_delete_impl(&mut self, id: u64) -> i64 {
+        unsafe { crate::kernel::timers::timer_delete(id) }
+    }
+
+    fn timerfd_create_impl(&mut self, clock_id: u64, flags: u64) -> i64 {
+        unsafe { crate::kernel::anonfd::timerfd_create(clock_id, flags) }
+    }
+
+    fn timerfd_settime_impl(&mut self, fd: i32, flags: u64, new_ptr: u64, old_ptr: u64) -> i64 {
+        unsafe { crate::kernel::anonfd::timerfd_settime(fd, flags, new_ptr, old_ptr) }
+    }
+
+    fn timerfd_gettime_impl(&mut self, fd: i32, curr_ptr: u64) -> i64 {
+        unsafe { crate::kernel::anonfd::timerfd_gettime(fd, curr_ptr) }
+    }
+
+    fn eventfd2_impl(&mut self, initval: u64, flags: u64) -> i64 {
+        unsafe { crate::kernel::anonfd::eventfd2(initval, flags) }
+    }
+
+    fn signalfd4_impl(&mut self, fd: i32, mask_ptr: u64, sizemask: u64, flags: u64) -> i64 {
+        unsafe { crate::kernel::anonfd::signalfd4(fd, mask_ptr, sizemask, flags) }
+    }
+
+    fn epoll_create1_impl(&mut self, flags: u64) -> i64 {
+        unsafe { crate::kernel::epoll::epoll_create1(flags) }
+    }
+
+    fn epoll_ctl_impl(&mut self, epfd: i32, op: u64, fd: i32, event_ptr: u64) -> i64 {
+        unsafe { crate::kernel::epoll::epoll_ctl(epfd, op, fd, event_ptr) }
+    }
+
+    fn epoll_pwait_impl(&mut self, epfd: i32, events_ptr: u64, maxevents: i64, timeout_ms: i64,
+                        sigmask_ptr: u64, sigsetsize: u64) -> i64 {
         unsafe {
-            core::ptr::write_bytes(buf_ptr as *mut u8, 0, 144);
+            crate::kernel::epoll::epoll_pwait(epfd, events_ptr, maxevents, timeout_ms,
+                                              sigmask_ptr, sigsetsize)
         }
-        0
     }
 
     fn sysinfo_impl(&mut self, buf_ptr: u64) -> i64 {
@@ -868,8 +1082,13 @@ impl SyscallRuntime for KernelRuntime {
             *out = LinuxStat::zeroed();
 
             match entry.backend {
-                FdBackend::DevNull | FdBackend::DevTty => {
-                    *out = LinuxStat::fill_chardev(fd as u64 + 1);
+                FdBackend::DevNull | FdBackend::DevTty | FdBackend::Tty | FdBackend::PtyMaster
+                | FdBackend::Mem | FdBackend::Block | FdBackend::Fb | FdBackend::Evdev
+                | FdBackend::Dsp | FdBackend::Mixer => {
+                    *out = match crate::kernel::devfs::node_of(&entry) {
+                        Some(node) => crate::kernel::devfs::stat(&node),
+                        None       => LinuxStat::fill_chardev(fd as u64 + 1),
+                    };
                     0
                 }
                 FdBackend::Fat16 => {
@@ -890,6 +1109,12 @@ impl SyscallRuntime for KernelRuntime {
                     (*out).st_ino  = 500 + fd as u64;
                     0
                 }
+                FdBackend::Anon => {
+                    // anon_inode: no file type bits, like Linux.
+                    (*out).st_mode = 0o600;
+                    (*out).st_ino  = 700 + entry.raw_fd as u64;
+                    0
+                }
                 FdBackend::Ext2 => {
                     *out = LinuxStat::fill_file(0, 200 + entry.raw_fd as u64);
                     0
@@ -903,14 +1128,13 @@ impl SyscallRuntime for KernelRuntime {
     }
 
     fn poll_impl(&mut self, fds_ptr: u64, nfds: u64, timeout_ms: i64) -> i64 {
-        use crate::kernel::fs::ramfs::{FdBackend, MAX_FD};
-        use crate::kernel::scheduler::{SCHED, CURRENT_TASK_IDX};
+        use crate::kernel::epoll::{self, EPOLLERR, EPOLLHUP};
+        use crate::kernel::scheduler::CURRENT_TASK_IDX;
         use super::syscall_core::TIMER_HZ;
 
+        // revents uses the EPOLL* bit values, which match POLL*.
         #[repr(C)]
         struct PollFd { fd: i32, events: i16, revents: i16 }
-        const POLLIN:  i16 = 0x0001;
-        const POLLOUT: i16 = 0x0004;
         const POLLHUP: i16 = 0x0010;
 
         let n = nfds as usize;
@@ -922,65 +1146,15 @@ impl SyscallRuntime for KernelRuntime {
         };
 
         let mut ready = 0i64;
-        unsafe {
-            let sched = &raw const SCHED;
-            let task  = &(*sched).tasks[CURRENT_TASK_IDX];
-
-            for pfd in fds.iter_mut() {
-                pfd.revents = 0;
-                let fd = pfd.fd;
-                if fd < 0 { continue; }
-
-                // Socket FDs (fd >= 200)
-                if fd >= 200 {
-                    if (pfd.events & POLLIN) != 0
-                        && crate::kernel::net::socket::socket_read_ready(fd as i64)
-                    {
-                        pfd.revents |= POLLIN;
-                    }
-                    if (pfd.events & POLLOUT) != 0 { pfd.revents |= POLLOUT; }
-                    if pfd.revents != 0 { ready += 1; }
-                    continue;
-                }
-
-                if fd as usize >= MAX_FD { continue; }
-
-                let entry = match task.fd_table.entries[fd as usize] {
-                    None    => { pfd.revents = POLLHUP; ready += 1; continue; }
-                    Some(e) => e,
-                };
-
-                match entry.backend {
-                    FdBackend::DevTty => {
-                        if (pfd.events & POLLIN) != 0
-                            && crate::kernel::stdin::available() > 0
-                        {
-                            pfd.revents |= POLLIN;
-                        }
-                        if (pfd.events & POLLOUT) != 0 { pfd.revents |= POLLOUT; }
-                    }
-                    FdBackend::DevNull => {
-                        if (pfd.events & POLLIN)  != 0 { pfd.revents |= POLLIN; }
-                        if (pfd.events & POLLOUT) != 0 { pfd.revents |= POLLOUT; }
-                    }
-                    FdBackend::Pipe => {
-                        if entry.writable {
-                            if (pfd.events & POLLOUT) != 0 { pfd.revents |= POLLOUT; }
-                        } else {
-                            if (pfd.events & POLLIN) != 0
-                                && crate::kernel::pipe::read_ready(entry.raw_fd)
-                            {
-                                pfd.revents |= POLLIN;
-                            }
-                        }
-                    }
-                    FdBackend::RamFS | FdBackend::Fat16 | FdBackend::Ext2 | FdBackend::Dir => {
-                        if (pfd.events & POLLIN)  != 0 { pfd.revents |= POLLIN; }
-                        if (pfd.events & POLLOUT) != 0 { pfd.revents |= POLLOUT; }
-                    }
-                }
-                if pfd.revents != 0 { ready += 1; }
-            }
+        for pfd in fds.iter_mut() {
+            pfd.revents = 0;
+            if pfd.fd < 0 { continue; }
+            pfd.revents = match unsafe { epoll::fd_events(CURRENT_TASK_IDX, pfd.fd) } {
+                // Errors and hangups are reported whether asked for or not.
+                Some(ev) => (ev & (pfd.events as u16 as u32 | EPOLLERR | EPOLLHUP)) as i16,
+                None     => POLLHUP,
+            };
+            if pfd.revents != 0 { ready += 1; }
         }
 
         if ready > 0 || timeout_ms == 0 {
@@ -1076,7 +1250,34 @@ impl SyscallRuntime for KernelRuntime {
     }
 
     fn ioctl_impl(&mut self, fd: i32, request: u64, arg: u64) -> i64 {
-        unsafe { crate::kernel::tty::ioctl(fd, request, arg) }
+        use crate::kernel::scheduler::{SCHED, CURRENT_TASK_IDX};
+        use crate::kernel::fs::ramfs::{FdBackend, MAX_FD};
+
+        // The console (fds 0-2 when not redirected, /dev/tty) and the tty
+        // devices are terminals; isatty() on anything else must fail.
+        if fd < 0 || fd as usize >= MAX_FD { return -9; } // EBADF
+        let entry = unsafe { (*(&raw const SCHED)).tasks[CURRENT_TASK_IDX].fd_table.entries[fd as usize] };
+        let tty = match entry {
+            None if fd > 2 => return -9, // EBADF
+            None => crate::kernel::tty::CONSOLE,
+            Some(e) => match e.backend {
+                FdBackend::DevTty => crate::kernel::tty::CONSOLE,
+                FdBackend::Tty    => e.raw_fd as usize,
+                FdBackend::PtyMaster => {
+                    return unsafe { crate::kernel::pty::ioctl(e.raw_fd as usize, request, arg) };
+                }
+                FdBackend::Mem | FdBackend::Block | FdBackend::Fb => {
+                    return unsafe { crate::kernel::devfs::ioctl(&e, request, arg) };
+                }
+                FdBackend::Evdev => {
+                    return unsafe { crate::kernel::evdev::ioctl(e.raw_fd as usize, request, arg) };
+                }
+                FdBackend::Dsp   => return unsafe { crate::kernel::dsp::ioctl(fd, request, arg) },
+                FdBackend::Mixer => return unsafe { crate::kernel::dsp::mixer_ioctl(request, arg) },
+                _ => return -25, // ENOTTY
+            },
+        };
+        unsafe { crate::kernel::tty::ioctl(tty, request, arg) }
     }
 
     fn chmod_impl(&mut self, path: &[u8], mode: u16) -> i64 {
@@ -1093,7 +1294,10 @@ impl SyscallRuntime for KernelRuntime {
                         let fs_mut = unsafe {
                             &mut *(fs as *const _ as *mut crate::kernel::fs::ramfs::RamFs)
                         };
-                        fs_mut.inodes[idx].mode = mode;
+                        // Only the owner (or root) may change the mode.
+                        let c = crate::kernel::cred::get();
+                        if c.fsuid != 0 && c.fsuid != fs_mut.inodes[idx].uid { return -1; } // EPERM
+                        fs_mut.inodes[idx].mode = mode & 0o7777;
                         0
                     }
                 }
@@ -1101,11 +1305,32 @@ impl SyscallRuntime for KernelRuntime {
         }
     }
 
+    /// fchown — terminal lines only (login hands its line to the user);
+    /// other descriptors keep the old no-op.
+    fn fchown_impl(&mut self, fd: i32, uid: u32, gid: u32) -> i64 {
+        use crate::kernel::fs::ramfs::{FdBackend, MAX_FD};
+        use crate::kernel::scheduler::{SCHED, CURRENT_TASK_IDX};
+        if fd < 0 || fd as usize >= MAX_FD { return -9; } // EBADF
+        let task = unsafe { &(*(&raw const SCHED)).tasks[CURRENT_TASK_IDX] };
+        let Some(entry) = task.fd_table.entries[fd as usize] else { return -9 };
+        if entry.backend != FdBackend::Tty { return 0; }
+        match crate::kernel::devfs::node_of(&entry) {
+            Some(node) => crate::kernel::devfs::chown(&node, uid, gid),
+            None       => 0,
+        }
+    }
+
     fn chown_impl(&mut self, path: &[u8], uid: u32, gid: u32) -> i64 {
         let path_str = match core::str::from_utf8(path) {
             Ok(s)  => s,
             Err(_) => return -22,
         };
+        if let crate::kernel::vfs::Resolved::Dev { node } = crate::kernel::vfs::resolve(path_str) {
+            return match node {
+                Some(node) => crate::kernel::devfs::chown(&node, uid, gid),
+                None       => -2, // ENOENT
+            };
+        }
         match unsafe { crate::kernel::fs::ramfs::RAMFS.get() } {
             None     => -2,
             Some(fs) => {
@@ -1115,8 +1340,21 @@ impl SyscallRuntime for KernelRuntime {
                         let fs_mut = unsafe {
                             &mut *(fs as *const _ as *mut crate::kernel::fs::ramfs::RamFs)
                         };
-                        fs_mut.inodes[idx].uid = uid;
-                        fs_mut.inodes[idx].gid = gid;
+                        // Root may give files away; an owner may only move
+                        // them between its own groups.  -1 leaves an id as is.
+                        let node = &mut fs_mut.inodes[idx];
+                        let uid = if uid == u32::MAX { node.uid } else { uid };
+                        let gid = if gid == u32::MAX { node.gid } else { gid };
+                        let c = crate::kernel::cred::get();
+                        if c.fsuid != 0
+                            && (c.fsuid != node.uid || uid != node.uid || !c.in_group(gid))
+                        {
+                            return -1; // EPERM
+                        }
+                        // As on Linux, chown drops the set-id bits.
+                        node.mode &= !(crate::kernel::cred::S_ISUID | crate::kernel::cred::S_ISGID);
+                        node.uid = uid;
+                        node.gid = gid;
                         0
                     }
                 }
@@ -1140,6 +1378,8 @@ impl SyscallRuntime for KernelRuntime {
         match unsafe { crate::kernel::fs::ramfs::RAMFS.get() } {
             None => -2,
             Some(fs) => {
+                let err = crate::kernel::vfs::ramfs_may_remove(fs, path_str);
+                if err < 0 { return err; }
                 match fs.remove_file(path_str) {
                     Ok(removed_idx) => {
                         // Fix up open FD tables in all tasks.
@@ -1148,6 +1388,7 @@ impl SyscallRuntime for KernelRuntime {
                             for task in (*core::ptr::addr_of_mut!(SCHED)).tasks.iter_mut() {
                                 task.fd_table.on_inode_removed(removed_idx);
                             }
+                            crate::kernel::mmap::on_ramfs_inode_removed(removed_idx);
                         }
                         0
                     }
@@ -1180,7 +1421,14 @@ impl SyscallRuntime for KernelRuntime {
 
         match unsafe { crate::kernel::fs::ramfs::RAMFS.get() } {
             None => -2,
-            Some(fs) => match fs.rename(old, new) { Ok(()) => 0, Err(e) => e },
+            Some(fs) => {
+                let err = crate::kernel::vfs::ramfs_may_remove(fs, old);
+                if err < 0 { return err; }
+                if !crate::kernel::vfs::ramfs_may(fs, fs.parent(new), crate::kernel::cred::MAY_WRITE | crate::kernel::cred::MAY_EXEC) {
+                    return -13; // EACCES
+                }
+                match fs.rename(old, new) { Ok(()) => 0, Err(e) => e }
+            }
         }
     }
 
@@ -1234,8 +1482,9 @@ impl SyscallRuntime for KernelRuntime {
             let sched = &raw mut SCHED;
             let idx   = CURRENT_TASK_IDX;
             let cr3   = (*sched).tasks[idx].cr3;
+            let base  = (*sched).tasks[idx].layout.shm_base;
             let att   = &raw mut (*sched).tasks[idx].shm_attaches;
-            crate::kernel::shm::shmat(shmid, &mut *att, cr3)
+            crate::kernel::shm::shmat(shmid, &mut *att, cr3, base)
         }
     }
 
@@ -1249,26 +1498,34 @@ impl SyscallRuntime for KernelRuntime {
         }
     }
 
-    fn sigaction_impl(&mut self, signum: u32, handler: u64, old_ptr: u64) -> i64 {
-        use crate::kernel::scheduler::{SCHED, CURRENT_TASK_IDX, NSIG};
+    fn sigaction_impl(&mut self, signum: u32, act_ptr: u64, old_ptr: u64) -> i64 {
+        use crate::kernel::scheduler::{SCHED, CURRENT_TASK_IDX, NSIG, SigAction, SIGKILL, SIGSTOP};
+        use crate::kernel::syscall_core::validate_user_range;
+        let size = core::mem::size_of::<SigAction>() as u64;
         if signum == 0 || signum as usize >= NSIG { return -22; } // EINVAL
+        if act_ptr != 0 && (signum == SIGKILL as u32 || signum == SIGSTOP as u32) {
+            return -22; // EINVAL
+        }
+        if act_ptr != 0 && validate_user_range(act_ptr, size).is_err() { return -14; } // EFAULT
+        if old_ptr != 0 && validate_user_range(old_ptr, size).is_err() { return -14; } // EFAULT
         unsafe {
             let sched = &raw mut SCHED;
             let idx   = CURRENT_TASK_IDX;
-            let prev  = (*sched).tasks[idx].signal_handlers[signum as usize];
+            let slot  = &raw mut (*sched).tasks[idx].signal_actions[signum as usize];
+            let prev  = *slot;
+            if act_ptr != 0 {
+                *slot = core::ptr::read_unaligned(act_ptr as *const SigAction);
+            }
             if old_ptr != 0 {
-                if let Err(_) = crate::kernel::syscall_core::validate_user_range(old_ptr, 8) {
-                    return -14; // EFAULT
-                }
-                core::ptr::write_unaligned(old_ptr as *mut u64, prev);
+                core::ptr::write_unaligned(old_ptr as *mut SigAction, prev);
             }
-            (*sched).tasks[idx].signal_handlers[signum as usize] = handler;
         }
         0
     }
 
     fn sigreturn_impl(&mut self) -> i64 {
-        use crate::kernel::scheduler::{SCHED, CURRENT_TASK_IDX, SignalFrame};
+        use crate::kernel::scheduler::{SCHED, CURRENT_TASK_IDX, SignalFrame,
+                                       SIGKILL, SIGSTOP, SIGSEGV, EXIT_PREEMPTED};
         use crate::kernel::paging_allocator;
         unsafe {
             let sched = &raw mut SCHED;
@@ -1276,54 +1533,62 @@ impl SyscallRuntime for KernelRuntime {
             let task  = &raw mut (*sched).tasks[idx];
             let cr3   = (*task).cr3;
 
-            // RSP currently points at the saved SignalFrame.
-            let rsp = (*task).ctx.rsp;
-            let frame_size = core::mem::size_of::<SignalFrame>() as u64;
-
-            // Read the frame from user memory via a temporary CR3 switch.
-            let mut frame = SignalFrame {
-                rip: 0, rax: 0, rbx: 0, rcx: 0, rdx: 0,
-                rsi: 0, rdi: 0, r8:  0, r9:  0, r10: 0,
-                r11: 0, r12: 0, r13: 0, r14: 0, r15: 0,
-                rbp: 0, rflags: 0,
+            // The handler's `ret` popped the restorer address, so the frame
+            // starts 8 bytes below the RSP the sigreturn call was made with.
+            let user_rsp = match crate::kernel::user_mode::CURRENT_SYSCALL_CTX {
+                Some(ctx) => ctx.rsp,
+                None      => super::syscall_handler::user_rsp(),
             };
-            {
-                let saved_cr3: u64;
-                core::arch::asm!("mov {}, cr3", out(reg) saved_cr3, options(nostack, nomem));
-                core::arch::asm!("mov cr3, {}", in(reg) cr3, options(nostack, nomem));
-                core::ptr::copy_nonoverlapping(
-                    rsp as *const u8,
-                    &raw mut frame as *mut u8,
-                    frame_size as usize,
-                );
-                core::arch::asm!("mov cr3, {}", in(reg) saved_cr3, options(nostack, nomem));
+            let frame_addr = user_rsp.wrapping_sub(8);
+            let frame_size = core::mem::size_of::<SignalFrame>();
+            if !paging_allocator::user_range_mapped_in(cr3, frame_addr, frame_size) {
+                // A corrupt frame cannot be returned through; Linux kills with SIGSEGV.
+                crate::kernel::user_mode::exit_to_kernel(-(SIGSEGV as i64));
             }
+            // The task's page table is live during the syscall.
+            let frame = core::ptr::read_unaligned(frame_addr as *const SignalFrame);
+
+            let unblockable = (1u32 << (SIGKILL as u32 - 1)) | (1u32 << (SIGSTOP as u32 - 1));
+            (*task).signal_mask   = frame.uc.sigmask as u32 & !unblockable;
+            (*task).in_sigsuspend = false;
+            (*task).ctx           = frame.uc.mcontext.to_ctx();
+
+            // Every register — including the caller-saved ones the syscall
+            // return paths clobber — comes from the frame, so resume through
+            // the scheduler's full context restore instead of returning.
+            crate::kernel::user_mode::exit_to_kernel(EXIT_PREEMPTED);
+        }
+    }
+
+    fn sigaltstack_impl(&mut self, ss_ptr: u64, old_ptr: u64) -> i64 {
+        // syscall_core::sys_sigaltstack has checked both pointers and the
+        // new stack's flags, size and range.
+        use crate::kernel::scheduler::{SCHED, CURRENT_TASK_IDX, SignalStack,
+                                       SS_ONSTACK, SS_DISABLE};
+        unsafe {
+            let sched = &raw mut SCHED;
+            let idx   = CURRENT_TASK_IDX;
+            let task  = &raw mut (*sched).tasks[idx];
+            let user_rsp = match crate::kernel::user_mode::CURRENT_SYSCALL_CTX {
+                Some(ctx) => ctx.rsp,
+                None      => super::syscall_handler::user_rsp(),
+            };
+            let cur      = (*task).sigaltstack;
+            let on_stack = cur.contains(user_rsp);
 
-            // Restore all general-purpose registers and RIP.
-            (*task).ctx.rip    = frame.rip;
-            (*task).ctx.rax    = frame.rax;
-            (*task).ctx.rbx    = frame.rbx;
-            (*task).ctx.rcx    = frame.rcx;
-            (*task).ctx.rdx    = frame.rdx;
-            (*task).ctx.rsi    = frame.rsi;
-            (*task).ctx.rdi    = frame.rdi;
-            (*task).ctx.r8     = frame.r8;
-            (*task).ctx.r9     = frame.r9;
-            (*task).ctx.r10    = frame.r10;
-            (*task).ctx.r11    = frame.r11;
-            (*task).ctx.r12    = frame.r12;
-            (*task).ctx.r13    = frame.r13;
-            (*task).ctx.r14    = frame.r14;
-            (*task).ctx.r15    = frame.r15;
-            (*task).ctx.rbp    = frame.rbp;
-            (*task).ctx.rflags = frame.rflags;
-            // Pop frame + return address (8 bytes) off the stack.
-            (*task).ctx.rsp    = rsp + frame_size + 8;
-
-            // Restore signal mask if we were in sigsuspend.
-            if (*task).in_sigsuspend {
-                (*task).in_sigsuspend = false;
-                (*task).signal_mask   = (*task).saved_signal_mask;
+            if old_ptr != 0 {
+                let mut old = cur;
+                if on_stack { old.flags = SS_ONSTACK; }
+                core::ptr::write_unaligned(old_ptr as *mut SignalStack, old);
+            }
+            if ss_ptr != 0 {
+                if on_stack { return -1; } // EPERM — can't swap the stack we're running on
+                let new = core::ptr::read_unaligned(ss_ptr as *const SignalStack);
+                (*task).sigaltstack = if new.flags & SS_DISABLE != 0 {
+                    SignalStack::DISABLED
+                } else {
+                    SignalStack::new(new.sp, 0, new.size)
+                };
             }
         }
         0
@@ -1361,28 +1626,24 @@ impl SyscallRuntime for KernelRuntime {
     }
 
     fn alarm_impl(&mut self, seconds: u32) -> i64 {
-        use crate::kernel::scheduler::{SCHED, CURRENT_TASK_IDX};
-        unsafe {
-            let sched = &raw mut SCHED;
-            let idx   = CURRENT_TASK_IDX;
-            let task  = &raw mut (*sched).tasks[idx];
-            let now   = crate::kernel::timer::get_ticks();
-
-            // Compute remaining seconds on any existing alarm.
-            let remaining = if (*task).alarm_deadline > now {
-                ((*task).alarm_deadline - now) / 100
-            } else {
-                0
-            };
+        unsafe { crate::kernel::timers::alarm(seconds) }
+    }
 
-            if seconds == 0 {
-                (*task).alarm_deadline = 0; // cancel alarm
-            } else {
-                (*task).alarm_deadline = now + (seconds as u64 * 100); // 100 Hz timer
+    fn pause_impl(&mut self) -> i64 {
+        use crate::kernel::scheduler::{SCHED, CURRENT_TASK_IDX, TaskState, EXIT_SLEEPING};
+        use crate::kernel::user_mode::CURRENT_SYSCALL_CTX;
+        // Sleep until send_signal wakes the task; pause always returns EINTR
+        // and is never restarted, so it is not marked as a blocked syscall.
+        unsafe {
+            if let Some(mut ctx) = core::ptr::replace(&raw mut CURRENT_SYSCALL_CTX, None) {
+                let task = &raw mut (*(&raw mut SCHED)).tasks[CURRENT_TASK_IDX];
+                ctx.rax = -4i64 as u64; // EINTR
+                (*task).ctx   = ctx;
+                (*task).state = TaskState::Sleeping(u64::MAX / 2);
+                crate::kernel::user_mode::exit_to_kernel(EXIT_SLEEPING);
             }
-
-            remaining as i64
         }
+        -4 // EINTR
     }
 
     fn sigpending_impl(&mut self, set_ptr: u64, _sigset_size: u64) -> i64 {
@@ -1449,10 +1710,24 @@ impl SyscallRuntime for KernelRuntime {
             let sched = &raw mut crate::kernel::scheduler::SCHED;
             let idx   = crate::kernel::scheduler::CURRENT_TASK_IDX;
             let fdt   = &raw mut (*sched).tasks[idx].fd_table;
-            match crate::kernel::fs::ramfs::RAMFS.get() {
+            // A background job reading its terminal is stopped here first.
+            if let Some(tty) = crate::kernel::tty::of_fd(fd) {
+                let r = crate::kernel::tty::may_read(tty);
+                if r < 0 { return r; }
+            }
+            let r = match crate::kernel::fs::ramfs::RAMFS.get() {
                 Some(fs) => (*fdt).read_fd(fs, fd, buf),
                 None     => -2,
+            };
+            // A blocking eventfd/timerfd/signalfd, tty, pty master or input
+            // device with nothing to read parks the task here (does not return).
+            if r == crate::kernel::anonfd::EAGAIN {
+                crate::kernel::anonfd::block(fd, false);
+                crate::kernel::tty::block(fd, false);
+                crate::kernel::pty::block(fd, false);
+                crate::kernel::evdev::block(fd);
             }
+            r
         }
     }
 
@@ -1463,10 +1738,28 @@ impl SyscallRuntime for KernelRuntime {
             let sched = &raw mut crate::kernel::scheduler::SCHED;
             let idx   = crate::kernel::scheduler::CURRENT_TASK_IDX;
             let fdt   = &raw mut (*sched).tasks[idx].fd_table;
-            match crate::kernel::fs::ramfs::RAMFS.get() {
+            if let Some(tty) = crate::kernel::tty::of_fd(fd) {
+                let r = crate::kernel::tty::may_write(tty);
+                if r < 0 { return r; }
+            }
+            let r = match crate::kernel::fs::ramfs::RAMFS.get() {
                 Some(fs) => (*fdt).write_fd(fs, fd, buf),
                 None     => -2,
+            };
+            if r > 0 {
+                if let Some(e) = (*fdt).entries.get(fd as usize).copied().flatten() {
+                    if e.backend == crate::kernel::fs::ramfs::FdBackend::RamFS {
+                        crate::kernel::procfs::written(e.inode_idx);
+                    }
+                }
+            }
+            if r == crate::kernel::anonfd::EAGAIN {
+                crate::kernel::anonfd::block(fd, true);
+                crate::kernel::tty::block(fd, true);
+                crate::kernel::pty::block(fd, true);
+                crate::kernel::dsp::block(fd);
             }
+            r
         }
     }
 
@@ -1630,26 +1923,12 @@ fn set_fdset_bit(ptr: u64, fd: usize) {
 
 /// Returns true if the given fd has data available for reading right now.
 fn fd_read_ready(fd: usize) -> bool {
-    use crate::kernel::fs::ramfs::{FdBackend, MAX_FD};
-    use crate::kernel::scheduler::{SCHED, CURRENT_TASK_IDX};
+    use crate::kernel::epoll::{self, EPOLLIN, EPOLLHUP};
+    use crate::kernel::scheduler::CURRENT_TASK_IDX;
 
-    if fd >= 200 {
-        return unsafe { crate::kernel::net::socket::socket_read_ready(fd as i64) };
-    }
-    if fd >= MAX_FD { return false; }
-    unsafe {
-        let sched = &raw const SCHED;
-        let task  = &(*sched).tasks[CURRENT_TASK_IDX];
-        match task.fd_table.entries[fd] {
-            None    => true, // closed fd is "readable" (returns EOF)
-            Some(e) => match e.backend {
-                FdBackend::DevTty =>
-                    crate::kernel::stdin::available() > 0,
-                FdBackend::Pipe if !e.writable =>
-                    crate::kernel::pipe::read_ready(e.raw_fd),
-                _ => true, // regular files, /dev/null, dirs always ready
-            }
-        }
+    match unsafe { epoll::fd_events(CURRENT_TASK_IDX, fd as i32) } {
+        Some(ev) => ev & (EPOLLIN | EPOLLHUP) != 0, // HUP: read returns EOF
+        None     => true, // closed fd is "readable" (returns EOF)
     }
 }
 
@@ -1667,26 +1946,23 @@ impl KernelRuntime {
             Err(_) => return -1,
         };
 
-        // Derive argv[0] from the path (basename without leading slashes).
-        let prog_name = path_str
-            .trim_end_matches('/')
-            .rsplit('/')
-            .next()
-            .unwrap_or(path_str);
-
         // 1. Built-in registry (embedded binaries — no disk needed).
-        let short = path_str.trim_start_matches('/').trim_start_matches("bin/");
+        let rel   = path_str.trim_start_matches('/');
+        let short = rel.strip_prefix("sbin/").unwrap_or(rel).trim_start_matches("bin/");
         if let Some(b) = crate::kernel::programs::find(short) {
-            return self.exec_binary(b, prog_name, extra_args);
+            let owner = ImageOwner { mode: crate::kernel::programs::mode(short), uid: 0, gid: 0 };
+            return self.exec_binary(b, path_str, extra_args, owner);
         }
 
         // 2. RamFS
-        if let Some(data) = unsafe { crate::kernel::fs::ramfs::RAMFS.get() }
-            .and_then(|fs| fs.read_file(path_str))
-        {
-            if !data.is_empty() {
-                let owned: Vec<u8> = data.to_vec();
-                return self.exec_binary(&owned, prog_name, extra_args);
+        if let Some(fs) = unsafe { crate::kernel::fs::ramfs::RAMFS.get() } {
+            if let (Some(idx), Some(data)) = (fs.resolve(path_str), fs.read_file(path_str)) {
+                if !data.is_empty() {
+                    let node  = &fs.inodes[idx];
+                    let owner = ImageOwner { mode: node.mode, uid: node.uid, gid: node.gid };
+                    let owned: Vec<u8> = data.to_vec();
+                    return self.exec_binary(&owned, path_str, extra_args, owner);
+                }
             }
         }
 
@@ -1695,6 +1971,15 @@ impl KernelRuntime {
             return self.exec_fat(path, extra_args);
         }
 
+        // 3b. ext2 (and anything else the VFS can read, e.g. /lib on ext2)
+        if path.starts_with(b"/ext2/") {
+            let Some((mode, uid, gid)) = (unsafe { crate::kernel::ext2::owner(path) }) else { return -2 };
+            return match unsafe { crate::kernel::vfs::vfs_read_file(path_str) } {
+                Some(data) => self.exec_binary(&data, path_str, extra_args, ImageOwner { mode, uid, gid }),
+                None       => -2,
+            };
+        }
+
         // 4. PATH fallback: bare names (e.g. "python3") are looked up on the
         // FAT disk as `/disk/<name>` and `/disk/<name>.elf`. This lets large
         // optional interpreters (CPython, etc.) ship as plain files on the
@@ -1728,26 +2013,28 @@ impl KernelRuntime {
         let _ = unsafe { crate::kernel::fat::close(fd as i32) };
         if buf.is_empty() { return -2; }
         let path_str = core::str::from_utf8(path).unwrap_or("");
-        let prog_name = path_str.trim_end_matches('/').rsplit('/').next().unwrap_or(path_str);
-        self.exec_binary(&buf, prog_name, extra_args)
+        self.exec_binary(&buf, path_str, extra_args, ImageOwner::PLAIN)
     }
 
     /// Load `binary` into a fresh address space and replace the current task.
-    /// `prog_name` is the argv[0] string; `extra_args` is the space-separated
-    /// argument string (argv[1..]).  On success this never returns.
-    fn exec_binary(&mut self, binary: &[u8], prog_name: &str, extra_args: &str) -> i64 {
+    /// `path` is where it was loaded from (its basename becomes argv[0]);
+    /// `extra_args` is the space-separated argument string (argv[1..]);
+    /// `owner` decides whether a set-uid/set-gid bit changes credentials.
+    /// On success this never returns.
+    fn exec_binary(&mut self, binary: &[u8], path: &str, extra_args: &str, owner: ImageOwner) -> i64 {
         extern crate alloc;
         use alloc::vec::Vec;
-        use crate::kernel::scheduler::{SCHED, CURRENT_TASK_IDX, EXIT_PREEMPTED,
-                                       USER_SIGTRAMP, SIGTRAMP_BYTES, write_argv_to_stack};
+        use crate::kernel::scheduler::{SCHED, CURRENT_TASK_IDX, EXIT_PREEMPTED, EXIT_SLEEPING, ImageMap,
+                                       build_address_space, write_argv_to_stack};
         use crate::kernel::paging_allocator as pa;
         use crate::kernel::fs::ramfs::FdTable;
 
-        const PAGE_SIZE:        usize = 4096;
-        const USER_STACK_TOP:   u64   = 0x0080_0000;
-        const USER_STACK_PAGES: usize = 64; // 256 KB
-        const USER_CODE_ADDR:   u64   = 0x0040_0000;
-        let stack_base = USER_STACK_TOP - (USER_STACK_PAGES * PAGE_SIZE) as u64;
+        // The image must be executable by the caller before its set-id
+        // bits can mean anything.
+        let mut cred = crate::kernel::cred::get();
+        if !cred.may_access(crate::kernel::cred::MAY_EXEC, owner.mode, owner.uid, owner.gid) {
+            return -13; // EACCES
+        }
 
         // Create a fresh page table.
         let new_cr3 = match unsafe { pa::create_user_page_table() } {
@@ -1755,30 +2042,16 @@ impl KernelRuntime {
             None      => return -4,
         };
 
-        // Map user stack.
-        if unsafe { pa::map_user_region_in(new_cr3, stack_base, USER_STACK_PAGES, true, false) }.is_err() {
-            return -4;
-        }
-
-        // Map signal trampoline.
-        if unsafe { pa::map_user_region_in(new_cr3, USER_SIGTRAMP, 1, true, true) }.is_ok() {
-            unsafe { pa::copy_to_region_in(new_cr3, USER_SIGTRAMP, SIGTRAMP_BYTES); }
-        }
-
-        // Load ELF or flat binary into the new CR3.
-        let entry = if crate::kernel::elf_loader::is_elf(binary) {
-            match unsafe { crate::kernel::elf_loader::load_in(binary, new_cr3) } {
-                Ok(e)  => e,
-                Err(_) => return -1,
-            }
-        } else {
-            let npages = binary.len().div_ceil(PAGE_SIZE);
-            if unsafe { pa::map_user_region_in(new_cr3, USER_CODE_ADDR, npages, true, true) }.is_err() {
-                return -4;
-            }
-            unsafe { pa::copy_to_region_in(new_cr3, USER_CODE_ADDR, binary); }
-            USER_CODE_ADDR
+        // Load the image (and its PT_INTERP interpreter) and draw a fresh
+        // randomised layout (stack, heap, mmap, shm, signal trampoline).
+        let prog = match unsafe { build_address_space(new_cr3, binary) } {
+            Ok(p)  => p,
+            Err(e) => return if e.starts_with("OOM") { -4 } else { -1 },
         };
+        let entry = prog.entry();
+
+        // Derive argv[0] from the path (basename without leading slashes).
+        let prog_name = path.trim_end_matches('/').rsplit('/').next().unwrap_or(path);
 
         // Build argv: argv[0] = prog_name, then split extra_args by whitespace.
         let mut argv_buf: Vec<&str> = Vec::new();
@@ -1787,7 +2060,10 @@ impl KernelRuntime {
             if argv_buf.len() >= 31 { break; }
             argv_buf.push(token);
         }
-        let initial_rsp = unsafe { write_argv_to_stack(new_cr3, USER_STACK_TOP, &argv_buf) };
+        // Set-id bits are ignored for traced tasks.
+        let traced   = unsafe { (*(&raw const SCHED)).tasks[CURRENT_TASK_IDX].trace.tracer != 0 };
+        let secure   = cred.exec(owner, traced);
+        let initial_rsp = unsafe { write_argv_to_stack(new_cr3, &prog, &argv_buf, &cred, secure) };
 
         // Capture old CR3 before overwriting.
         let old_cr3 = unsafe {
@@ -1795,6 +2071,9 @@ impl KernelRuntime {
             (*s).tasks[CURRENT_TASK_IDX].cr3
         };
 
+        // Flush and drop the old image's mmap regions while its CR3 is still live.
+        unsafe { crate::kernel::mmap::release_task(CURRENT_TASK_IDX); }
+
         // Update current task: new image, reset FD table but inherit stdin/stdout/stderr.
         unsafe {
             let s    = &raw mut SCHED;
@@ -1810,6 +2089,31 @@ impl KernelRuntime {
             (*task).entry       = entry;
             (*task).first_run   = true;
             (*task).initial_rsp = initial_rsp;
+            (*task).layout      = prog.layout;
+            (*task).heap_end    = 0;
+            (*task).image_maps  = [ImageMap::new(prog.image.start, prog.image.end, path), prog.interp_map];
+            (*task).cred        = cred;
+            (*task).dumpable    = !secure;
+            // Caught signals revert to SIG_DFL (the handlers are gone with the
+            // old image); ignored ones stay ignored.  The altstack is dropped.
+            for act in (*task).signal_actions.iter_mut() {
+                if act.handler != crate::kernel::scheduler::SIG_IGN {
+                    *act = crate::kernel::scheduler::SigAction::DEFAULT;
+                }
+            }
+            (*task).sigaltstack = crate::kernel::scheduler::SignalStack::DISABLED;
+            // POSIX timers are deleted; interval timers survive exec.
+            (*task).timers.on_exec();
+            // Release the eventfds/timerfds/signalfds, pty ends, input
+            // clients and sound stream the wiped table holds.
+            for e in (&(*task).fd_table.entries)[3..].iter().flatten() {
+                if e.backend == crate::kernel::fs::ramfs::FdBackend::Anon {
+                    crate::kernel::anonfd::close(e.raw_fd);
+                }
+                crate::kernel::pty::release(e);
+                crate::kernel::evdev::release(e);
+                crate::kernel::dsp::release(e);
+            }
             (*task).fd_table    = FdTable::new();
             (*task).fd_table.entries[0] = saved_std[0];
             (*task).fd_table.entries[1] = saved_std[1];
@@ -1822,11 +2126,15 @@ impl KernelRuntime {
             unsafe { pa::free_user_page_table(old_cr3); }
         }
 
+        // A traced task gets a SIGTRAP (or an exec event stop) before the
+        // new image runs.
+        let stopped = unsafe { crate::kernel::ptrace::on_exec(CURRENT_TASK_IDX) };
+
         // Non-local goto back to tick().  tick() will see EXIT_PREEMPTED,
         // mark the task Ready, and on the next tick launch_at(entry, initial_rsp, new_cr3).
         unsafe {
             crate::kernel::user_mode::CURRENT_SYSCALL_CTX = None;
-            crate::kernel::user_mode::exit_to_kernel(EXIT_PREEMPTED);
+            crate::kernel::user_mode::exit_to_kernel(if stopped { EXIT_SLEEPING } else { EXIT_PREEMPTED });
         }
     }
 }
@@ -1839,12 +2147,21 @@ pub unsafe fn handle_syscall(
     arg3: u64,
     arg4: u64,
     arg5: u64,
+    arg6: u64,
 ) -> SyscallResult {
+    // A traced task may stop here (syscall-entry-stop) and re-issue the
+    // call with new registers later; it may also stop on the way out.
+    // From here until the return to user mode counts as system time.
+    unsafe { crate::kernel::timers::syscall_begin(); }
+    unsafe { crate::kernel::ptrace::syscall_enter(syscall_num); }
     let mut runtime = KernelRuntime;
-    dispatch(
+    let result = dispatch(
         &mut runtime,
-        SyscallRequest::new(syscall_num, arg1, arg2, arg3, arg4, arg5),
-    )
+        SyscallRequest::new(syscall_num, arg1, arg2, arg3, arg4, arg5).with_arg6(arg6),
+    );
+    unsafe { crate::kernel::ptrace::syscall_exit(result.value); }
+    unsafe { crate::kernel::timers::syscall_end(); }
+    result
 }
 
 /// Lightweight boot-time smoke tests for the dispatcher.
@@ -1855,17 +2172,17 @@ pub unsafe fn handle_syscall(
 pub unsafe fn run_boot_self_tests() {
     SERIAL_PORT.write_str("\n=== SYSCALL BOOT SELF-TESTS ===\n");
 
-    let pid = handle_syscall(Syscall::GetPid as u64, 0, 0, 0, 0, 0);
+    let pid = handle_syscall(Syscall::GetPid as u64, 0, 0, 0, 0, 0, 0);
     SERIAL_PORT.write_str("  getpid -> ");
     SERIAL_PORT.write_decimal(pid.value as u32);
     SERIAL_PORT.write_str("\n");
 
-    let ticks = handle_syscall(Syscall::GetTime as u64, 0, 0, 0, 0, 0);
+    let ticks = handle_syscall(Syscall::GetTime as u64, 0, 0, 0, 0, 0, 0);
     SERIAL_PORT.write_str("  gettime -> ");
     SERIAL_PORT.write_decimal(ticks.value as u32);
     SERIAL_PORT.write_str(" ticks\n");
 
-    let unsupported = handle_syscall(0xFFFF, 0, 0, 0, 0, 0);
+    let unsupported = handle_syscall(0xFFFF, 0, 0, 0, 0, 0, 0);
     SERIAL_PORT.write_str("  invalid syscall -> ");
     SERIAL_PORT.write_decimal(unsupported.value as u32);
     SERIAL_PORT.write_str("\n");
@@ -30,21 +30,24 @@ pub enum Syscall {
     Writev        = 20,  // scatter write
     Access        = 21,  // access(path, mode)
     SchedYield    = 24,  // sched_yield
-    Msync         = 26,  // msync — stub
-    Mincore       = 27,  // mincore — stub
-    Madvise       = 28,  // madvise — stub
+    Msync         = 26,  // msync
+    Mincore       = 27,  // mincore
+    Madvise       = 28,  // madvise
     Shmctl        = 31,  // shmctl
     Dup           = 32,  // dup(fd)
-    Pause         = 34,  // pause — stub
-    Getitimer     = 36,  // getitimer — stub
-    Alarm         = 37,  // alarm — stub
-    Setitimer     = 38,  // setitimer — stub
+    Pause         = 34,  // pause — sleep until a signal
+    Getitimer     = 36,  // getitimer(which, curr)
+    Alarm         = 37,  // alarm(seconds) — one-shot ITIMER_REAL
+    Setitimer     = 38,  // setitimer(which, new, old)
     Sendfile      = 40,  // sendfile — stub
     Vfork         = 58,  // vfork — alias to fork
     Flock         = 73,  // flock — stub
     Fsync         = 74,  // fsync — stub
     Fdatasync     = 75,  // fdatasync — stub
     Sync          = 162, // sync — flush all dirty filesystem buffers to disk
+    Swapon        = 167, // swapon(path, flags) — enable a swap area
+    Swapoff       = 168, // swapoff(path) — disable a swap area
+    Reboot        = 169, // reboot(magic1, magic2, cmd, arg) — restart, halt or power off
     Ftruncate     = 77,  // ftruncate(fd, length)
     Fchdir        = 81,  // fchdir(fd)
     Rmdir         = 84,  // rmdir(path)
@@ -55,22 +58,29 @@ pub enum Syscall {
     Lchown        = 94,  // lchown — stub
     Umask         = 95,  // umask — returns 0o022
     Getrlimit     = 97,  // getrlimit — returns sensible max
-    Getrusage     = 98,  // getrusage — returns zeros
+    Setrlimit     = 160, // setrlimit — only RLIMIT_CORE is enforced
+    Getrusage     = 98,  // getrusage(who, usage)
     Sysinfo       = 99,  // sysinfo — fills struct sysinfo
-    Times         = 100, // times — stub
+    Times         = 100, // times(buf) → ticks since boot
+    Ptrace        = 101, // ptrace(request, pid, addr, data)
     Syslog        = 103, // syslog — stub
-    Setpgid       = 109, // setpgid — stub
-    Getpgrp       = 111, // getpgrp → getpid
-    Setsid        = 112, // setsid → getpid
-    Setreuid      = 113, // setreuid — stub
-    Setregid      = 114, // setregid — stub
-    Getgroups     = 115, // getgroups — returns []
-    Setresuid     = 117, // setresuid — stub
-    Getresuid     = 118, // getresuid — stub (was wrongly 121)
-    Setresgid     = 119, // setresgid — stub
-    Getresgid     = 120, // getresgid — stub
+    Setpgid       = 109, // setpgid(pid, pgid)
+    Getpgrp       = 111, // getpgrp → pgid of the caller
+    Setsid        = 112, // setsid → new session id
+    Setreuid      = 113, // setreuid(ruid, euid)
+    Setregid      = 114, // setregid(rgid, egid)
+    Getgroups     = 115, // getgroups(size, list)
+    Setgroups     = 116, // setgroups(size, list) — root only
+    Setresuid     = 117, // setresuid(ruid, euid, suid)
+    Getresuid     = 118, // getresuid(ruid*, euid*, suid*)
+    Setresgid     = 119, // setresgid(rgid, egid, sgid)
+    Getresgid     = 120, // getresgid(rgid*, egid*, sgid*)
     Getpgid       = 121, // getpgid(pid) → pgid
+    Setfsuid      = 122, // setfsuid(uid) → previous fsuid
+    Setfsgid      = 123, // setfsgid(gid) → previous fsgid
+    Getsid        = 124, // getsid(pid) → session id
     Prlimit64     = 302, // prlimit64 — resource limit with pid
+    Getrandom     = 318, // getrandom(buf, len, flags) → bytes written
     Select        = 23,  // select(nfds, readfds, writefds, exceptfds, timeval)
     Poll          = 7,   // poll(fds, nfds, timeout_ms)
     Pselect6      = 270, // pselect6 — wrapper around select
@@ -110,19 +120,20 @@ pub enum Syscall {
     Lseek         = 8,
     Lstat         = 6,   // stat for symlinks → alias to stat
     Readv         = 19,  // scatter read
-    Mremap        = 25,  // memory remap — stub
+    Mremap        = 25,  // memory remap
     Sigprocmask   = 14,  // rt_sigprocmask
     SigPending    = 127, // rt_sigpending
     SigSuspend    = 130, // rt_sigsuspend
+    Sigaltstack   = 131,
     GetTime       = 96,  // gettimeofday
     Mprotect      = 10,
     Getppid       = 110,
     Getuid        = 102,
     Getgid        = 104,
-    Setuid        = 105, // stub → 0
-    Setgid        = 106, // stub → 0
-    Geteuid       = 107, // → 0 (same as uid)
-    Getegid       = 108, // → 0 (same as gid)
+    Setuid        = 105,
+    Setgid        = 106,
+    Geteuid       = 107,
+    Getegid       = 108,
     Gettid        = 186, // → getpid (single-threaded)
     Futex         = 202, // stub → 0
     ArchPrctl     = 158,
@@ -131,6 +142,23 @@ pub enum Syscall {
     ExitGroup     = 231, // musl uses this instead of exit(60)
     Openat        = 257, // openat(dirfd, path, flags[, mode]) — treat AT_FDCWD as open()
     Pipe2         = 293, // pipe with flags — ignore flags, call pipe
+    TimerCreate   = 222, // timer_create(clockid, sevp, timerid)
+    TimerSettime  = 223, // timer_settime(timerid, flags, new, old)
+    TimerGettime  = 224, // timer_gettime(timerid, curr)
+    TimerGetoverrun = 225, // timer_getoverrun(timerid)
+    TimerDelete   = 226, // timer_delete(timerid)
+    Signalfd      = 282, // signalfd(fd, mask, sizemask) → signalfd4 without flags
+    TimerfdCreate = 283, // timerfd_create(clockid, flags)
+    Eventfd       = 284, // eventfd(initval) → eventfd2 without flags
+    TimerfdSettime = 286, // timerfd_settime(fd, flags, new, old)
+    TimerfdGettime = 287, // timerfd_gettime(fd, curr)
+    Signalfd4     = 289, // signalfd4(fd, mask, sizemask, flags)
+    Eventfd2      = 290, // eventfd2(initval, flags)
+    EpollCreate   = 213, // epoll_create(size) → epoll_create1(0)
+    EpollWait     = 232, // epoll_wait(epfd, events, maxevents, timeout)
+    EpollCtl      = 233, // epoll_ctl(epfd, op, fd, event)
+    EpollPwait    = 281, // epoll_pwait(epfd, events, maxevents, timeout, sigmask, sigsetsize)
+    EpollCreate1  = 291, // epoll_create1(flags)
     // ── SysV shared memory (Linux x86-64 numbers) ───────────────────────
     Shmget        = 29,
     Shmat         = 30,
@@ -167,6 +195,10 @@ pub enum Syscall {
     InstallBegin  = 434,
     /// DNS A-record resolve: arg1=hostname_ptr, arg2=hostname_len → packed IPv4 u32
     DnsResolve    = 435,
+    /// Kernel syscall tracer control: arg1=op (SYSTRACE_*), arg2=pid
+    SysTrace      = 436,
+    /// Select the keyboard layout: arg1=name_ptr, arg2=name_len
+    Loadkeys      = 437,
     Invalid       = u64::MAX,
 }
 
@@ -175,7 +207,7 @@ impl Syscall {
         match self {
             Self::Exit          => "exit",
             Self::Fork          => "fork",
-            Self::Wait          => "wait",
+            Self::Wait          => "wait4",
             Self::GetPid        => "getpid",
             Self::Exec          => "exec",
             Self::ExecArgs      => "exec_args",
@@ -215,6 +247,7 @@ impl Syscall {
             Self::Sigprocmask   => "sigprocmask",
             Self::SigPending    => "sigpending",
             Self::SigSuspend    => "sigsuspend",
+            Self::Sigaltstack   => "sigaltstack",
             Self::Getuid        => "getuid",
             Self::Getgid        => "getgid",
             Self::Setuid        => "setuid",
@@ -266,6 +299,8 @@ impl Syscall {
             Self::InstallQuery  => "install_query",
             Self::InstallBegin  => "install_begin",
             Self::DnsResolve    => "dns_resolve",
+            Self::SysTrace      => "systrace",
+            Self::Loadkeys      => "loadkeys",
             Self::Pread64       => "pread64",
             Self::Pwrite64      => "pwrite64",
             Self::Writev        => "writev",
@@ -286,6 +321,9 @@ impl Syscall {
             Self::Fsync         => "fsync",
             Self::Fdatasync     => "fdatasync",
             Self::Sync          => "sync",
+            Self::Swapon        => "swapon",
+            Self::Swapoff       => "swapoff",
+            Self::Reboot        => "reboot",
             Self::Ftruncate     => "ftruncate",
             Self::Fchdir        => "fchdir",
             Self::Rmdir         => "rmdir",
@@ -296,9 +334,11 @@ impl Syscall {
             Self::Lchown        => "lchown",
             Self::Umask         => "umask",
             Self::Getrlimit     => "getrlimit",
+            Self::Setrlimit     => "setrlimit",
             Self::Getrusage     => "getrusage",
             Self::Sysinfo       => "sysinfo",
             Self::Times         => "times",
+            Self::Ptrace        => "ptrace",
             Self::Syslog        => "syslog",
             Self::Setpgid       => "setpgid",
             Self::Getpgrp       => "getpgrp",
@@ -306,14 +346,36 @@ impl Syscall {
             Self::Setreuid      => "setreuid",
             Self::Setregid      => "setregid",
             Self::Getgroups     => "getgroups",
+            Self::Setgroups     => "setgroups",
+            Self::Setfsuid      => "setfsuid",
+            Self::Setfsgid      => "setfsgid",
             Self::Setresuid     => "setresuid",
             Self::Setresgid     => "setresgid",
             Self::Getresuid     => "getresuid",
             Self::Getresgid     => "getresgid",
             Self::Getpgid       => "getpgid",
+            Self::Getsid        => "getsid",
             Self::Select        => "select",
             Self::Pselect6      => "pselect6",
             Self::Prlimit64     => "prlimit64",
+            Self::Getrandom     => "getrandom",
+            Self::TimerCreate   => "timer_create",
+            Self::TimerSettime  => "timer_settime",
+            Self::TimerGettime  => "timer_gettime",
+            Self::TimerGetoverrun => "timer_getoverrun",
+            Self::TimerDelete   => "timer_delete",
+            Self::Signalfd      => "signalfd",
+            Self::TimerfdCreate => "timerfd_create",
+            Self::Eventfd       => "eventfd",
+            Self::TimerfdSettime => "timerfd_settime",
+            Self::TimerfdGettime => "timerfd_gettime",
+            Self::Signalfd4     => "signalfd4",
+            Self::Eventfd2      => "eventfd2",
+            Self::EpollCreate   => "epoll_create",
+            Self::EpollWait     => "epoll_wait",
+            Self::EpollCtl      => "epoll_ctl",
+            Self::EpollPwait    => "epoll_pwait",
+            Self::EpollCreate1  => "epoll_create1",
             Self::Invalid       => "invalid",
         }
     }
@@ -365,6 +427,9 @@ impl From<u64> for Syscall {
             74  => Self::Fsync,
             75  => Self::Fdatasync,
             162 => Self::Sync,
+            167 => Self::Swapon,
+            168 => Self::Swapoff,
+            169 => Self::Reboot,
             76  => Self::Truncate,
             77  => Self::Ftruncate,
             78  => Self::ReadDir,
@@ -385,9 +450,11 @@ impl From<u64> for Syscall {
             95  => Self::Umask,
             96  => Self::GetTime,
             97  => Self::Getrlimit,
+            160 => Self::Setrlimit,
             98  => Self::Getrusage,
             99  => Self::Sysinfo,
             100 => Self::Times,
+            101 => Self::Ptrace,
             103 => Self::Syslog,
             109 => Self::Setpgid,
             111 => Self::Getpgrp,
@@ -395,19 +462,25 @@ impl From<u64> for Syscall {
             113 => Self::Setreuid,
             114 => Self::Setregid,
             115 => Self::Getgroups,
+            116 => Self::Setgroups,
             117 => Self::Setresuid,
             118 => Self::Getresuid,
             119 => Self::Setresgid,
             120 => Self::Getresgid,
             121 => Self::Getpgid,
+            122 => Self::Setfsuid,
+            123 => Self::Setfsgid,
+            124 => Self::Getsid,
             23  => Self::Select,
             270 => Self::Pselect6,
             302 => Self::Prlimit64,
+            318 => Self::Getrandom,
             17  => Self::Pread64,
             18  => Self::Pwrite64,
             14  => Self::Sigprocmask,
             127 => Self::SigPending,
             130 => Self::SigSuspend,
+            131 => Self::Sigaltstack,
             19  => Self::Readv,
             20  => Self::Writev,
             21  => Self::Access,
@@ -438,6 +511,23 @@ impl From<u64> for Syscall {
             231 => Self::ExitGroup,
             257 => Self::Openat,
             293 => Self::Pipe2,
+            222 => Self::TimerCreate,
+            223 => Self::TimerSettime,
+            224 => Self::TimerGettime,
+            225 => Self::TimerGetoverrun,
+            226 => Self::TimerDelete,
+            282 => Self::Signalfd,
+            283 => Self::TimerfdCreate,
+            284 => Self::Eventfd,
+            286 => Self::TimerfdSettime,
+            287 => Self::TimerfdGettime,
+            289 => Self::Signalfd4,
+            290 => Self::Eventfd2,
+            213 => Self::EpollCreate,
+            232 => Self::EpollWait,
+            233 => Self::EpollCtl,
+            281 => Self::EpollPwait,
+            291 => Self::EpollCreate1,
             // ── OxideOS-specific ─────────────────────────────────────────
             400 => Self::Print,
             401 => Self::GetChar,
@@ -465,6 +555,8 @@ impl From<u64> for Syscall {
             433 => Self::InstallQuery,
             434 => Self::InstallBegin,
             435 => Self::DnsResolve,
+            436 => Self::SysTrace,
+            437 => Self::Loadkeys,
             _   => Self::Invalid,
         }
     }
@@ -500,12 +592,18 @@ pub struct SyscallRequest {
     pub arg3:   u64,
     pub arg4:   u64,
     pub arg5:   u64,
+    /// Sixth argument (r9); only read by syscalls that take six, e.g. mmap.
+    pub arg6:   u64,
 }
 
 impl SyscallRequest {
     pub const fn new(number: u64, arg1: u64, arg2: u64,
                      arg3: u64, arg4: u64, arg5: u64) -> Self {
-        Self { number, arg1, arg2, arg3, arg4, arg5 }
+        Self { number, arg1, arg2, arg3, arg4, arg5, arg6: 0 }
+    }
+    pub const fn with_arg6(mut self, arg6: u64) -> Self {
+        self.arg6 = arg6;
+        self
     }
     pub fn syscall(self) -> Syscall { Syscall::from(self.number) }
 }
@@ -518,10 +616,138 @@ pub const ENOMEM: i64 = -4;
 pub const EBADF:  i64 = -5;
 pub const EAGAIN: i64 = -6;
 pub const ENOENT: i64 = -7;
+/// The kernel FD table's `EBADF` (Linux value): `fs_write_file` returns it
+/// when fd 1/2 has no table entry, and the write falls back to the console.
+pub const FS_EBADF: i64 = -9;
+
+/// `size_of::<ipc::Message>()`: `type_id` + `size` + 256 payload bytes.
+pub const IPC_MESSAGE_SIZE: u64 = 8 + 256;
+
+// ── reboot(2) ──────────────────────────────────────────────────────────────
+pub const LINUX_REBOOT_MAGIC1: u32 = 0xfee1_dead;
+/// Any of these is accepted as the second magic number.
+pub const LINUX_REBOOT_MAGIC2: [u32; 4] = [672_274_793, 85_072_278, 369_367_448, 537_993_216];
+pub const LINUX_REBOOT_CMD_RESTART:   u32 = 0x0123_4567;
+pub const LINUX_REBOOT_CMD_HALT:      u32 = 0xcdef_0123;
+pub const LINUX_REBOOT_CMD_POWER_OFF: u32 = 0x4321_fedc;
+
+// ── mmap(2) ────────────────────────────────────────────────────────────────
+pub const MAP_SHARED:          u32 = 0x01;
+pub const MAP_PRIVATE:         u32 = 0x02;
+pub const MAP_SHARED_VALIDATE: u32 = 0x03;
+pub const MAP_TYPE:            u32 = 0x0F;
+pub const MAP_FIXED:           u32 = 0x10;
+pub const MAP_ANONYMOUS:       u32 = 0x20;
+const PAGE_SIZE: u64 = 4096;
+/// First address past the lower (user) half of the address space.
+const USER_SPACE_END: u64 = 0x0000_8000_0000_0000;
+
+// ── sigaltstack(2) ─────────────────────────────────────────────────────────
+pub const SS_ONSTACK: i32 = 1;
+pub const SS_DISABLE: i32 = 2;
+/// Smallest alternate signal stack `sigaltstack` accepts.
+pub const MINSIGSTKSZ: u64 = 2048;
+/// `stack_t`: `ss_sp` at 0, `ss_flags` at 8, `ss_size` at 16.
+const STACK_T_SIZE: u64 = 24;
+
+// ── getrandom(2) ───────────────────────────────────────────────────────────
+const GRND_NONBLOCK: u32 = 1;
+const GRND_RANDOM:   u32 = 2;
+const GRND_INSECURE: u32 = 4;
+
+// ── Credentials ────────────────────────────────────────────────────────────
+/// Supplementary groups per task (Linux allows 65536).
+pub const NGROUPS_MAX: usize = 32;
+
+// ── Resource limits ────────────────────────────────────────────────────────
+const RLIM_NLIMITS: u32 = 16;
+/// `struct rlimit { rlim_cur, rlim_max }`.
+const RLIMIT_SIZE: u64 = 16;
+
+// ── ptrace(2) ──────────────────────────────────────────────────────────────
+pub const PTRACE_TRACEME:     i64 = 0;
+pub const PTRACE_PEEKTEXT:    i64 = 1;
+pub const PTRACE_PEEKDATA:    i64 = 2;
+pub const PTRACE_PEEKUSER:    i64 = 3;
+pub const PTRACE_POKETEXT:    i64 = 4;
+pub const PTRACE_POKEDATA:    i64 = 5;
+pub const PTRACE_POKEUSER:    i64 = 6;
+pub const PTRACE_CONT:        i64 = 7;
+pub const PTRACE_KILL:        i64 = 8;
+pub const PTRACE_SINGLESTEP:  i64 = 9;
+pub const PTRACE_GETREGS:     i64 = 12;
+pub const PTRACE_SETREGS:     i64 = 13;
+pub const PTRACE_ATTACH:      i64 = 16;
+pub const PTRACE_DETACH:      i64 = 17;
+pub const PTRACE_SYSCALL:     i64 = 24;
+pub const PTRACE_SETOPTIONS:  i64 = 0x4200;
+pub const PTRACE_GETEVENTMSG: i64 = 0x4201;
+pub const PTRACE_GETSIGINFO:  i64 = 0x4202;
+pub const PTRACE_GET_SYSCALL_INFO: i64 = 0x420e;
+
+// PTRACE_SETOPTIONS bits.  Only these are implemented; others are refused.
+pub const PTRACE_O_TRACESYSGOOD: u32 = 0x0000_0001;
+pub const PTRACE_O_TRACEEXEC:    u32 = 0x0000_0010;
+pub const PTRACE_O_EXITKILL:     u32 = 0x0010_0000;
+const PTRACE_O_SUPPORTED: u32 = PTRACE_O_TRACESYSGOOD | PTRACE_O_TRACEEXEC | PTRACE_O_EXITKILL;
+
+/// `sizeof(struct user_regs_struct)`: 27 registers.
+pub const USER_REGS_SIZE: usize = 27 * 8;
+/// `sizeof(siginfo_t)`.
+const SIGINFO_SIZE: u64 = 128;
+
+// ── systrace ───────────────────────────────────────────────────────────────
+pub const SYSTRACE_OFF:     u64 = 0;
+pub const SYSTRACE_ALL:     u64 = 1;
+pub const SYSTRACE_ADD_PID: u64 = 2;
+pub const SYSTRACE_DEL_PID: u64 = 3;
+pub const SYSTRACE_CLEAR:   u64 = 4;
+
+// ── Timers and event fds ───────────────────────────────────────────────────
+// getitimer/setitimer `which`.
+pub const ITIMER_REAL:    usize = 0;
+pub const ITIMER_VIRTUAL: usize = 1;
+pub const ITIMER_PROF:    usize = 2;
+
+// sigev_notify values.
+pub const SIGEV_SIGNAL:    i32 = 0;
+pub const SIGEV_NONE:      i32 = 1;
+pub const SIGEV_THREAD_ID: i32 = 4;
+
+/// `timer_settime` / `timerfd_settime` flag: the value is an absolute time.
+pub const TIMER_ABSTIME: u64 = 1;
+pub const TFD_TIMER_CANCEL_ON_SET: u64 = 2;
+
+// eventfd2 / timerfd_create / signalfd4 flags.
+pub const EFD_SEMAPHORE: u64 = 1;
+pub const O_NONBLOCK:    u64 = 0o4000;
+pub const O_CLOEXEC:     u64 = 0o2000000;
+
+/// `struct itimerval` / `struct itimerspec`: two (seconds, fraction) pairs.
+const ITIMER_SIZE: u64 = 32;
+/// `struct sigevent` up to `sigev_notify_thread_id`.
+const SIGEVENT_SIZE: u64 = 20;
+/// Signal numbers run 1..NSIG; SIGKILL and SIGSTOP cannot be redirected.
+const NSIG: i32 = 32;
+const SIGKILL: i32 = 9;
+const SIGSTOP: i32 = 19;
+
+// ── epoll ──────────────────────────────────────────────────────────────────
+pub const EPOLL_CTL_ADD: u64 = 1;
+pub const EPOLL_CTL_DEL: u64 = 2;
+pub const EPOLL_CTL_MOD: u64 = 3;
+
+/// `struct epoll_event` is packed on x86-64: `u32 events; u64 data`.
+pub const EPOLL_EVENT_SIZE: u64 = 12;
+/// Largest `maxevents` whose buffer size still fits an `int` (Linux's
+/// EP_MAX_EVENTS).
+const EP_MAX_EVENTS: i64 = i32::MAX as i64 / EPOLL_EVENT_SIZE as i64;
 
 // ── Runtime trait ──────────────────────────────────────────────────────────
 pub trait SyscallRuntime {
-    fn trace(&mut self, _syscall: Syscall) {}
+    fn trace(&mut self, _syscall: Syscall, _request: &SyscallRequest) {}
+    /// Called with the result of every recognised syscall that returns.
+    fn trace_exit(&mut self, _syscall: Syscall, _result: &SyscallResult) {}
     fn trace_unknown(&mut self, _num: u64) {}  // override to log unrecognized syscall numbers
     fn current_pid(&self) -> u64 { 1 }
     fn current_ticks(&self) -> u64;
@@ -539,6 +765,10 @@ pub trait SyscallRuntime {
     fn sleep_until_tick(&mut self, target_tick: u64);
     fn exit(&mut self, code: i32) -> !;
 
+    /// True if the page holding `addr` is mapped in the current address
+    /// space.  User strings are probed page by page before they are read.
+    fn user_page_mapped(&self, addr: u64) -> bool;
+
     // ── stdin ──────────────────────────────────────────────────────────────
     /// Pop one byte from the stdin ring. Returns EAGAIN (-6) if empty.
     fn get_char(&mut self) -> i64 { EAGAIN }
@@ -558,9 +788,10 @@ pub trait SyscallRuntime {
     /// Fork the current process.  Returns child PID to parent, 0 to child.
     fn fork_child(&mut self) -> i64 { ENOSYS }
 
-    /// Block until the child with `pid` exits; return its exit code.
+    /// wait4(pid, status, options, rusage): collect a child's exit (or a
+    /// tracee's stop) and return its PID; 0 with WNOHANG if none is ready.
     /// May never return if it blocks (calls exit_to_kernel internally).
-    fn waitpid_impl(&mut self, _pid: u64) -> i64 { ENOSYS }
+    fn wait4_impl(&mut self, _pid: i32, _status_ptr: u64, _options: u32, _rusage_ptr: u64) -> i64 { ENOSYS }
 
     /// Set/query the userspace heap break.  `new_end == 0` returns current end.
     fn brk_program(&mut self, _new_end: u64) -> i64 { ENOSYS }
@@ -570,10 +801,17 @@ pub trait SyscallRuntime {
     /// or a negative error code. Only MAP_ANONYMOUS|MAP_PRIVATE is supported.
     fn mmap_anon(&mut self, _addr: u64, _len: u64) -> i64 { ENOSYS }
 
+    /// Full mmap(addr, len, prot, flags, fd, offset).  The default only
+    /// handles MAP_ANONYMOUS by delegating to `mmap_anon`.
+    fn mmap_impl(&mut self, addr: u64, len: u64, _prot: u32, flags: u32,
+                 _fd: i32, _offset: u64) -> i64 {
+        if flags & MAP_ANONYMOUS != 0 { self.mmap_anon(addr, len) } else { ENOSYS }
+    }
+
     /// Unmap a previously mapped region and free its physical frames.
     fn munmap_impl(&mut self, _addr: u64, _len: u64) -> i64 { 0 }
 
-    /// Change memory protection on a range — stub, returns 0 (success).
+    /// Change memory protection on a range.  Default stub returns 0.
     fn mprotect_impl(&mut self, _addr: u64, _len: u64, _prot: u64) -> i64 { 0 }
 
     /// Return the parent PID of the calling process.
@@ -609,18 +847,29 @@ pub trait SyscallRuntime {
     /// readv — scatter read: read into multiple iovec buffers.
     fn readv_impl(&mut self, _fd: i32, _iov_ptr: u64, _iovcnt: u32) -> i64 { ENOSYS }
 
-    /// mremap — stub returns ENOMEM (not implemented).
-    fn mremap_impl(&mut self, _old_addr: u64, _old_len: u64, _new_len: u64) -> i64 { -12 }
+    /// mremap — resize (and with MREMAP_MAYMOVE, move) a mapping.
+    /// Default stub returns ENOMEM.
+    fn mremap_impl(&mut self, _old_addr: u64, _old_len: u64, _new_len: u64,
+                   _flags: u32) -> i64 { -12 }
 
-    /// getuid — stub returns 1000 (unprivileged user).
+    /// getuid — real uid.  Stub returns 1000 (unprivileged user).
     fn getuid_impl(&mut self) -> i64 { 1000 }
 
-    /// getgid — stub returns 1000.
+    /// geteuid — effective uid.  Defaults to the real uid.
+    fn geteuid_impl(&mut self) -> i64 { self.getuid_impl() }
+
+    /// getgid — real gid.  Stub returns 1000.
     fn getgid_impl(&mut self) -> i64 { 1000 }
 
+    /// getegid — effective gid.  Defaults to the real gid.
+    fn getegid_impl(&mut self) -> i64 { self.getgid_impl() }
+
     /// setuid — stub returns 0 (success, single-user OS).
     fn setuid_impl(&mut self, _uid: u32) -> i64 { 0 }
 
+    /// setgid — stub returns 0.
+    fn setgid_impl(&mut self, _gid: u32) -> i64 { 0 }
+
     /// gettid — returns current PID (single-threaded, tid == pid).
     fn gettid_impl(&mut self) -> i64 { self.current_pid() as i64 }
 
@@ -641,13 +890,13 @@ pub trait SyscallRuntime {
     /// sched_yield — yield the CPU. Returns 0.
     fn sched_yield_impl(&mut self) -> i64 { 0 }
 
-    /// msync — flush mapped memory. Stub returns 0.
+    /// msync — write back MAP_SHARED pages. Default stub returns 0.
     fn msync_impl(&mut self, _addr: u64, _len: u64, _flags: u32) -> i64 { 0 }
 
-    /// mincore — check if pages are in core. Stub returns ENOMEM.
+    /// mincore — check if pages are in core. Default stub returns ENOMEM.
     fn mincore_impl(&mut self, _addr: u64, _len: u64, _vec_ptr: u64) -> i64 { -12 }
 
-    /// madvise — advise on memory usage. Stub returns 0.
+    /// madvise — advise on memory usage. Default stub returns 0.
     fn madvise_impl(&mut self, _addr: u64, _len: u64, _advice: u32) -> i64 { 0 }
 
     /// dup — duplicate fd to the lowest available fd.
@@ -670,6 +919,17 @@ pub trait SyscallRuntime {
     /// TODO(12.2): flush block cache here once one exists.
     fn sync_impl(&mut self) -> i64 { 0 }
 
+    /// swapon — start paging to the swap partition or file at `path` (no
+    /// trailing NUL).  Unknown `flags` bits were already rejected.
+    fn swapon_impl(&mut self, _path: &[u8], _flags: u32) -> i64 { ENOSYS }
+
+    /// swapoff — page everything back in and stop using `path` for swap.
+    fn swapoff_impl(&mut self, _path: &[u8]) -> i64 { ENOSYS }
+
+    /// reboot — restart, halt or power off the machine (`cmd` is one of the
+    /// `LINUX_REBOOT_CMD_*` values; the magic numbers are already checked).
+    fn reboot_impl(&mut self, _cmd: u32) -> i64 { ENOSYS }
+
     /// ftruncate — truncate an open fd to length bytes.
     fn ftruncate_impl(&mut self, _fd: i32, _length: u64) -> i64 { ENOSYS }
 
@@ -702,14 +962,19 @@ pub trait SyscallRuntime {
 
     /// getrlimit — returns generous limits.
     fn getrlimit_impl(&mut self, _resource: u32, _rlim_ptr: u64) -> i64 { ENOSYS }
+    /// setrlimit — `rlim_ptr` points at `struct rlimit { rlim_cur, rlim_max }`.
+    fn setrlimit_impl(&mut self, _resource: u32, _rlim_ptr: u64) -> i64 { ENOSYS }
+
+    /// ptrace — trace another process (PTRACE_* request numbers, Linux ABI).
+    fn ptrace_impl(&mut self, _request: i64, _pid: u32, _addr: u64, _data: u64) -> i64 { ENOSYS }
 
-    /// getrusage — returns zeroed struct rusage.
+    /// getrusage — fills struct rusage for RUSAGE_SELF/CHILDREN/THREAD.
     fn getrusage_impl(&mut self, _who: i32, _buf_ptr: u64) -> i64 { ENOSYS }
 
     /// sysinfo — fills a minimal struct sysinfo.
     fn sysinfo_impl(&mut self, _buf_ptr: u64) -> i64 { ENOSYS }
 
-    /// times — fills tms struct with zeros.
+    /// times — fills struct tms (may be null); returns clock ticks since boot.
     fn times_impl(&mut self, _buf_ptr: u64) -> i64 { 0 }
 
     /// syslog — stub returns 0.
@@ -733,12 +998,22 @@ pub trait SyscallRuntime {
     /// getgroups — returns 0 groups.
     fn getgroups_impl(&mut self, _size: u32, _list_ptr: u64) -> i64 { 0 }
 
-    /// getresuid — writes (1000, 1000, 1000) to the three u32 pointers.
+    /// setgroups — replace the supplementary group list (root only).
+    fn setgroups_impl(&mut self, _size: u64, _list_ptr: u64) -> i64 { ENOSYS }
+
+    /// setfsuid / setfsgid — set the id used for file access checks;
+    /// return the previous one.
+    fn setfsuid_impl(&mut self, _uid: u32) -> i64 { ENOSYS }
+    fn setfsgid_impl(&mut self, _gid: u32) -> i64 { ENOSYS }
+
+    /// getresuid — write real, effective and saved uid to three u32 pointers.
     fn getresuid_impl(&mut self, _ruid_ptr: u64, _euid_ptr: u64, _suid_ptr: u64) -> i64 { 0 }
     /// getresgid — stub → 0
     fn getresgid_impl(&mut self, _rgid: u64, _egid: u64, _sgid: u64) -> i64 { 0 }
     /// getpgid(pid) → process group id. 0 means calling process.
     fn getpgid_impl(&mut self, _pid: u32) -> i64 { 1 }
+    /// getsid(pid) → session id. 0 means calling process.
+    fn getsid_impl(&mut self, _pid: u32) -> i64 { ENOSYS }
     /// select(nfds, readfds, writefds, exceptfds, timeval_ptr)
     fn select_impl(&mut self, nfds: u64, read_ptr: u64, write_ptr: u64, except_ptr: u64, timeout_ptr: u64) -> i64;
     /// pselect6 — wrapper for select ignoring sigmask argument.
@@ -758,12 +1033,50 @@ pub trait SyscallRuntime {
     /// prlimit64 — get/set resource limits. Returns 0 (no enforcement).
     fn prlimit64_impl(&mut self, _pid: u32, _resource: u32, _new_ptr: u64, _old_ptr: u64) -> i64 { 0 }
 
+    /// getrandom(buf, len, flags) — fill `buf` from the kernel CSPRNG.
+    fn getrandom_impl(&mut self, _buf: &mut [u8], _flags: u32) -> i64 { ENOSYS }
+
     /// alarm — stub returns 0 (no previous alarm).
     fn alarm_impl(&mut self, _seconds: u32) -> i64 { 0 }
 
     /// pause — block until signal. Stub returns -EINTR.
     fn pause_impl(&mut self) -> i64 { -4 }
 
+    /// getitimer/setitimer — `which` is ITIMER_REAL/VIRTUAL/PROF; the
+    /// pointers are `struct itimerval` (new may be 0, old may be 0).
+    fn getitimer_impl(&mut self, _which: u64, _curr_ptr: u64) -> i64 { ENOSYS }
+    fn setitimer_impl(&mut self, _which: u64, _new_ptr: u64, _old_ptr: u64) -> i64 { ENOSYS }
+
+    /// timer_create — `sevp` is a `struct sigevent` (0 = SIGALRM); the new
+    /// timer id is written to `timerid_ptr` as an `int`.
+    fn timer_create_impl(&mut self, _clock_id: u64, _sevp: u64, _timerid_ptr: u64) -> i64 { ENOSYS }
+    /// timer_settime/gettime — `struct itimerspec` pointers.
+    fn timer_settime_impl(&mut self, _id: u64, _flags: u64, _new_ptr: u64, _old_ptr: u64) -> i64 { ENOSYS }
+    fn timer_gettime_impl(&mut self, _id: u64, _curr_ptr: u64) -> i64 { ENOSYS }
+    fn timer_getoverrun_impl(&mut self, _id: u64) -> i64 { ENOSYS }
+    fn timer_delete_impl(&mut self, _id: u64) -> i64 { ENOSYS }
+
+    /// timerfd_create — a file descriptor that counts timer expirations.
+    fn timerfd_create_impl(&mut self, _clock_id: u64, _flags: u64) -> i64 { ENOSYS }
+    fn timerfd_settime_impl(&mut self, _fd: i32, _flags: u64, _new_ptr: u64, _old_ptr: u64) -> i64 { ENOSYS }
+    fn timerfd_gettime_impl(&mut self, _fd: i32, _curr_ptr: u64) -> i64 { ENOSYS }
+
+    /// eventfd2 — a file descriptor holding a 64-bit counter.
+    fn eventfd2_impl(&mut self, _initval: u64, _flags: u64) -> i64 { ENOSYS }
+
+    /// signalfd4 — a file descriptor that dequeues the signals in `mask_ptr`
+    /// (a `sigset_t` of `sizemask` bytes).  `fd` -1 creates one.
+    fn signalfd4_impl(&mut self, _fd: i32, _mask_ptr: u64, _sizemask: u64, _flags: u64) -> i64 { ENOSYS }
+
+    /// epoll_create1 — a new epoll instance.
+    fn epoll_create1_impl(&mut self, _flags: u64) -> i64 { ENOSYS }
+    /// epoll_ctl — add, modify or remove the watch on `fd`.
+    fn epoll_ctl_impl(&mut self, _epfd: i32, _op: u64, _fd: i32, _event_ptr: u64) -> i64 { ENOSYS }
+    /// epoll_pwait — wait for events, with `sigmask_ptr` (if non-null)
+    /// installed meanwhile.  epoll_wait passes 0.
+    fn epoll_pwait_impl(&mut self, _epfd: i32, _events_ptr: u64, _maxevents: i64, _timeout_ms: i64,
+                        _sigmask_ptr: u64, _sigsetsize: u64) -> i64 { ENOSYS }
+
     /// rt_sigpending — write set of pending-but-masked signals to user ptr.
     fn sigpending_impl(&mut self, _set_ptr: u64, _sigset_size: u64) -> i64 { 0 }
 
@@ -777,13 +1090,16 @@ pub trait SyscallRuntime {
     /// `fd`: file descriptor (0=stdin, 1=stdout), `request`: ioctl code, `arg`: pointer/value.
     fn ioctl_impl(&mut self, _fd: i32, _request: u64, _arg: u64) -> i64 { ENOSYS }
 
-    /// Register a signal handler. `handler` = user fn ptr, `SIG_DFL` (0), or `SIG_IGN` (1).
-    /// If `old_ptr` is non-zero it receives the previous handler address.
-    fn sigaction_impl(&mut self, _signum: u32, _handler: u64, _old_ptr: u64) -> i64 { ENOSYS }
+    /// rt_sigaction — `act_ptr`/`old_ptr` point at a `struct k_sigaction`
+    /// (handler, flags, restorer, mask); either may be 0.
+    fn sigaction_impl(&mut self, _signum: u32, _act_ptr: u64, _old_ptr: u64) -> i64 { ENOSYS }
 
     /// Restore the pre-signal execution context (called by the trampoline after a handler returns).
     fn sigreturn_impl(&mut self) -> i64 { ENOSYS }
 
+    /// sigaltstack — install and/or query the alternate signal stack (`stack_t`).
+    fn sigaltstack_impl(&mut self, _ss_ptr: u64, _old_ptr: u64) -> i64 { ENOSYS }
+
     // ── File permission syscalls ───────────────────────────────────────────
     /// Change permission bits on a file at `path`.  `mode` is the POSIX mode (e.g. 0o644).
     fn chmod_impl(&mut self, _path: &[u8], _mode: u16) -> i64 { ENOSYS }
@@ -916,6 +1232,10 @@ pub trait SyscallRuntime {
     fn install_begin_impl(&mut self) -> i64 { ENOSYS }
     /// DNS A-record lookup. Returns packed IPv4 (ip[0] | ip[1]<<8 | ...) or negative.
     fn dns_resolve_impl(&mut self, _hostname: &[u8]) -> i64 { ENOSYS }
+    /// Configure the kernel syscall tracer (SYSTRACE_* op, optional pid).
+    fn systrace_impl(&mut self, _op: u64, _pid: u64) -> i64 { ENOSYS }
+    /// Switch the keyboard to the layout called `name` ("us", "de", ...).
+    fn loadkeys_impl(&mut self, _name: &[u8]) -> i64 { ENOSYS }
 }
 
 // ── Validation ─────────────────────────────────────────────────────────────
@@ -940,23 +1260,26 @@ pub unsafe fn dispatch<R: SyscallRuntime>(
     let syscall = request.syscall();
     if syscall == Syscall::Invalid {
         runtime.trace_unknown(request.number);
-    } else {
-        runtime.trace(syscall);
+        return SyscallResult::err(ENOSYS);
     }
+    runtime.trace(syscall, &request);
+    let result = unsafe { dispatch_syscall(runtime, syscall, request) };
+    runtime.trace_exit(syscall, &result);
+    result
+}
 
+unsafe fn dispatch_syscall<R: SyscallRuntime>(
+    runtime: &mut R, syscall: Syscall, request: SyscallRequest,
+) -> SyscallResult {
     match syscall {
         Syscall::Exit          => runtime.exit(request.arg1 as i32),
         Syscall::Fork          => sys_fork(runtime),
-        Syscall::Wait          => sys_waitpid(runtime, request.arg1),
+        Syscall::Wait          => sys_wait4(runtime, request.arg1, request.arg2, request.arg3, request.arg4),
         Syscall::Exec          => unsafe { sys_exec(runtime, request.arg1, request.arg2) },
         Syscall::ExecArgs      => unsafe { sys_exec_args(runtime, request.arg1, request.arg2,
                                                           request.arg3, request.arg4) },
         Syscall::GetPid        => SyscallResult::ok(runtime.current_pid() as i64),
-        Syscall::Mmap          => {
-            // arg1=addr, arg2=len (prot/flags/fd/offset ignored — anon only)
-            let r = runtime.mmap_anon(request.arg1, request.arg2);
-            if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) }
-        }
+        Syscall::Mmap          => sys_mmap(runtime, request),
         Syscall::Munmap        => {
             let r = runtime.munmap_impl(request.arg1, request.arg2);
             if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) }
@@ -1212,7 +1535,7 @@ pub unsafe fn dispatch<R: SyscallRuntime>(
         }
         Syscall::Lstat => unsafe {
             let (path_len, stat_buf) = if request.arg2 >= 4096 {
-                (strnlen_user(request.arg1, 4096), request.arg2)
+                (strnlen_user(runtime, request.arg1, 4096), request.arg2)
             } else {
                 (request.arg2, request.arg3)
             };
@@ -1228,15 +1551,16 @@ pub unsafe fn dispatch<R: SyscallRuntime>(
             if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) }
         }
         Syscall::Mremap => {
-            let r = runtime.mremap_impl(request.arg1, request.arg2, request.arg3);
+            let r = runtime.mremap_impl(request.arg1, request.arg2, request.arg3,
+                                        request.arg4 as u32);
             if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) }
         }
         Syscall::Getuid    => SyscallResult::ok(runtime.getuid_impl()),
-        Syscall::Geteuid   => SyscallResult::ok(runtime.getuid_impl()),  // euid = uid (no setuid)
+        Syscall::Geteuid   => SyscallResult::ok(runtime.geteuid_impl()),
         Syscall::Getgid    => SyscallResult::ok(runtime.getgid_impl()),
-        Syscall::Getegid   => SyscallResult::ok(runtime.getgid_impl()),  // egid = gid
-        Syscall::Setuid    => SyscallResult::ok(runtime.setuid_impl(request.arg1 as u32)),
-        Syscall::Setgid    => SyscallResult::ok(0),
+        Syscall::Getegid   => SyscallResult::ok(runtime.getegid_impl()),
+        Syscall::Setuid    => { let r = runtime.setuid_impl(request.arg1 as u32); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) } }
+        Syscall::Setgid    => { let r = runtime.setgid_impl(request.arg1 as u32); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) } }
         Syscall::Gettid    => SyscallResult::ok(runtime.gettid_impl()),
         Syscall::Futex     => {
             let r = runtime.futex_impl(request.arg1, request.arg2 as u32, request.arg3 as u32);
@@ -1245,7 +1569,7 @@ pub unsafe fn dispatch<R: SyscallRuntime>(
         Syscall::Openat => unsafe {
             // openat(dirfd, path_ptr, flags[, mode])
             // AT_FDCWD = -100; we ignore dirfd for now and treat as open().
-            let path_len = strnlen_user(request.arg2, 4096);
+            let path_len = strnlen_user(runtime, request.arg2, 4096);
             if path_len == 0 { return SyscallResult::err(EINVAL); }
             if let Err(code) = validate_user_range(request.arg2, path_len) { return SyscallResult::err(code); }
             let path = slice::from_raw_parts(request.arg2 as *const u8, path_len as usize);
@@ -1264,7 +1588,7 @@ pub unsafe fn dispatch<R: SyscallRuntime>(
         }
         Syscall::Access => unsafe {
             // Linux ABI: access(path_ptr, mode) — arg2 is mode flags, not path_len.
-            let path_len = strnlen_user(request.arg1, 4096);
+            let path_len = strnlen_user(runtime, request.arg1, 4096);
             if path_len == 0 { return SyscallResult::err(EINVAL); }
             if let Err(e) = validate_user_range(request.arg1, path_len) { return SyscallResult::err(e); }
             let path = slice::from_raw_parts(request.arg1 as *const u8, path_len as usize);
@@ -1272,15 +1596,44 @@ pub unsafe fn dispatch<R: SyscallRuntime>(
             if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) }
         }
         Syscall::SchedYield  => SyscallResult::ok(runtime.sched_yield_impl()),
-        Syscall::Msync       => SyscallResult::ok(runtime.msync_impl(request.arg1, request.arg2, request.arg3 as u32)),
+        Syscall::Msync       => { let r = runtime.msync_impl(request.arg1, request.arg2, request.arg3 as u32); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) } }
         Syscall::Mincore     => { let r = runtime.mincore_impl(request.arg1, request.arg2, request.arg3); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) } }
-        Syscall::Madvise     => SyscallResult::ok(runtime.madvise_impl(request.arg1, request.arg2, request.arg3 as u32)),
+        Syscall::Madvise     => { let r = runtime.madvise_impl(request.arg1, request.arg2, request.arg3 as u32); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) } }
         Syscall::Dup         => { let r = runtime.dup_impl(request.arg1 as i32); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) } }
         Syscall::Vfork       => { let r = runtime.vfork_impl(); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) } }
         Syscall::Flock       => SyscallResult::ok(runtime.flock_impl(request.arg1 as i32, request.arg2 as u32)),
         Syscall::Fsync       => SyscallResult::ok(runtime.fsync_impl(request.arg1 as i32)),
         Syscall::Fdatasync   => SyscallResult::ok(runtime.fdatasync_impl(request.arg1 as i32)),
         Syscall::Sync        => SyscallResult::ok(runtime.sync_impl()),
+        Syscall::Swapon | Syscall::Swapoff => unsafe {
+            // SWAP_FLAG_PRIO_MASK | PREFER | DISCARD | DISCARD_ONCE | DISCARD_PAGES
+            const SWAP_FLAGS_VALID: u32 = 0x7_FFFF;
+            if syscall == Syscall::Swapon && request.arg2 as u32 & !SWAP_FLAGS_VALID != 0 {
+                return SyscallResult::err(-22); // EINVAL
+            }
+            let path = match user_path(runtime, request.arg1) {
+                Ok(p)  => p,
+                Err(e) => return SyscallResult::err(e),
+            };
+            if path.is_empty() { return SyscallResult::err(-2); } // ENOENT
+            let r = if syscall == Syscall::Swapon {
+                runtime.swapon_impl(path, request.arg2 as u32)
+            } else {
+                runtime.swapoff_impl(path)
+            };
+            if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) }
+        }
+        Syscall::Reboot      => {
+            let cmd = request.arg3 as u32;
+            if request.arg1 as u32 != LINUX_REBOOT_MAGIC1
+                || !LINUX_REBOOT_MAGIC2.contains(&(request.arg2 as u32))
+                || ![LINUX_REBOOT_CMD_RESTART, LINUX_REBOOT_CMD_HALT, LINUX_REBOOT_CMD_POWER_OFF].contains(&cmd)
+            {
+                return SyscallResult::err(-22); // EINVAL
+            }
+            let r = runtime.reboot_impl(cmd);
+            if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) }
+        }
         Syscall::Ftruncate   => { let r = runtime.ftruncate_impl(request.arg1 as i32, request.arg2); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) } }
         Syscall::Fchdir      => { let r = runtime.fchdir_impl(request.arg1 as i32); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) } }
         Syscall::Rmdir       => unsafe {
@@ -1297,7 +1650,7 @@ pub unsafe fn dispatch<R: SyscallRuntime>(
         }
         Syscall::Readlink => unsafe {
             // Linux ABI: readlink(path_ptr, buf_ptr, bufsiz)
-            let path_len = strnlen_user(request.arg1, 4096);
+            let path_len = strnlen_user(runtime, request.arg1, 4096);
             if path_len == 0 { return SyscallResult::err(EINVAL); }
             if let Err(e) = validate_user_range(request.arg1, path_len) { return SyscallResult::err(e); }
             let path = slice::from_raw_parts(request.arg1 as *const u8, path_len as usize);
@@ -1313,22 +1666,36 @@ pub unsafe fn dispatch<R: SyscallRuntime>(
             if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) }
         }
         Syscall::Umask       => SyscallResult::ok(runtime.umask_impl(request.arg1 as u32)),
-        Syscall::Getrlimit   => { let r = runtime.getrlimit_impl(request.arg1 as u32, request.arg2); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) } }
+        Syscall::Getrlimit | Syscall::Setrlimit => unsafe {
+            let (resource, rlim_ptr) = (request.arg1 as u32, request.arg2);
+            if rlim_ptr == 0 { return SyscallResult::err(-14); } // EFAULT
+            let set = syscall == Syscall::Setrlimit;
+            let (new_ptr, old_ptr) = if set { (rlim_ptr, 0) } else { (0, rlim_ptr) };
+            if let Err(e) = check_rlimit(resource, new_ptr, old_ptr) { return SyscallResult::err(e); }
+            let r = if set { runtime.setrlimit_impl(resource, rlim_ptr) }
+                    else   { runtime.getrlimit_impl(resource, rlim_ptr) };
+            if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) }
+        }
         Syscall::Getrusage   => { let r = runtime.getrusage_impl(request.arg1 as i32, request.arg2); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) } }
         Syscall::Sysinfo     => { let r = runtime.sysinfo_impl(request.arg1); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) } }
-        Syscall::Times       => SyscallResult::ok(runtime.times_impl(request.arg1)),
+        Syscall::Times       => { let r = runtime.times_impl(request.arg1); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) } }
+        Syscall::Ptrace      => sys_ptrace(runtime, request.arg1 as i64, request.arg2 as u32, request.arg3, request.arg4),
         Syscall::Syslog      => SyscallResult::ok(runtime.syslog_impl(request.arg1 as u32, request.arg2, request.arg3 as u32)),
-        Syscall::Setpgid     => SyscallResult::ok(runtime.setpgid_impl(request.arg1 as u32, request.arg2 as u32)),
-        Syscall::Getpgrp     => SyscallResult::ok(runtime.getpgrp_impl()),
-        Syscall::Setsid      => SyscallResult::ok(runtime.setsid_impl()),
-        Syscall::Setreuid    => SyscallResult::ok(runtime.setreuid_impl(request.arg1 as u32, request.arg2 as u32)),
-        Syscall::Setregid    => SyscallResult::ok(runtime.setregid_impl(request.arg1 as u32, request.arg2 as u32)),
-        Syscall::Getgroups   => SyscallResult::ok(runtime.getgroups_impl(request.arg1 as u32, request.arg2)),
-        Syscall::Setresuid   => SyscallResult::ok(runtime.setresuid_impl(request.arg1 as u32, request.arg2 as u32, request.arg3 as u32)),
-        Syscall::Setresgid   => SyscallResult::ok(runtime.setresgid_impl(request.arg1 as u32, request.arg2 as u32, request.arg3 as u32)),
-        Syscall::Getresuid   => SyscallResult::ok(runtime.getresuid_impl(request.arg1, request.arg2, request.arg3)),
-        Syscall::Getresgid   => SyscallResult::ok(runtime.getresgid_impl(request.arg1, request.arg2, request.arg3)),
-        Syscall::Getpgid     => SyscallResult::ok(runtime.getpgid_impl(request.arg1 as u32)),
+        Syscall::Setpgid     => { let r = runtime.setpgid_impl(request.arg1 as u32, request.arg2 as u32); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) } }
+        Syscall::Getpgrp     => { let r = runtime.getpgrp_impl(); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) } }
+        Syscall::Setsid      => { let r = runtime.setsid_impl(); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) } }
+        Syscall::Setreuid    => { let r = runtime.setreuid_impl(request.arg1 as u32, request.arg2 as u32); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) } }
+        Syscall::Setregid    => { let r = runtime.setregid_impl(request.arg1 as u32, request.arg2 as u32); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) } }
+        Syscall::Getgroups   => { let r = runtime.getgroups_impl(request.arg1 as u32, request.arg2); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) } }
+        Syscall::Setgroups   => sys_setgroups(runtime, request.arg1, request.arg2),
+        Syscall::Setresuid   => { let r = runtime.setresuid_impl(request.arg1 as u32, request.arg2 as u32, request.arg3 as u32); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) } }
+        Syscall::Setresgid   => { let r = runtime.setresgid_impl(request.arg1 as u32, request.arg2 as u32, request.arg3 as u32); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) } }
+        Syscall::Getresuid   => { let r = runtime.getresuid_impl(request.arg1, request.arg2, request.arg3); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) } }
+        Syscall::Getresgid   => { let r = runtime.getresgid_impl(request.arg1, request.arg2, request.arg3); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) } }
+        Syscall::Setfsuid    => { let r = runtime.setfsuid_impl(request.arg1 as u32); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) } }
+        Syscall::Setfsgid    => { let r = runtime.setfsgid_impl(request.arg1 as u32); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) } }
+        Syscall::Getpgid     => { let r = runtime.getpgid_impl(request.arg1 as u32); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) } }
+        Syscall::Getsid      => { let r = runtime.getsid_impl(request.arg1 as u32); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) } }
         Syscall::Select      => {
             let r = runtime.select_impl(request.arg1, request.arg2, request.arg3, request.arg4, request.arg5);
             if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) }
@@ -1345,9 +1712,64 @@ pub unsafe fn dispatch<R: SyscallRuntime>(
         Syscall::Pause       => { let r = runtime.pause_impl(); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) } }
         Syscall::SigPending  => { let r = runtime.sigpending_impl(request.arg1, request.arg2); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) } }
         Syscall::SigSuspend  => { let r = runtime.sigsuspend_impl(request.arg1, request.arg2); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) } }
-        Syscall::Getitimer   => SyscallResult::ok(0),
-        Syscall::Setitimer   => SyscallResult::ok(0),
-        Syscall::Prlimit64   => SyscallResult::ok(runtime.prlimit64_impl(request.arg1 as u32, request.arg2 as u32, request.arg3, request.arg4)),
+        Syscall::Sigaltstack => unsafe { sys_sigaltstack(runtime, request.arg1, request.arg2) },
+        Syscall::Getitimer => checked(unsafe { check_timer_args(syscall, &request) },
+                                      || runtime.getitimer_impl(request.arg1, request.arg2)),
+        Syscall::Setitimer => checked(unsafe { check_timer_args(syscall, &request) },
+                                      || runtime.setitimer_impl(request.arg1, request.arg2, request.arg3)),
+        Syscall::TimerCreate => checked(unsafe { check_timer_args(syscall, &request) },
+                                        || runtime.timer_create_impl(request.arg1, request.arg2, request.arg3)),
+        Syscall::TimerSettime => checked(unsafe { check_timer_args(syscall, &request) },
+                                         || runtime.timer_settime_impl(request.arg1, request.arg2, request.arg3, request.arg4)),
+        Syscall::TimerGettime => checked(unsafe { check_timer_args(syscall, &request) },
+                                         || runtime.timer_gettime_impl(request.arg1, request.arg2)),
+        Syscall::TimerGetoverrun => { let r = runtime.timer_getoverrun_impl(request.arg1); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) } }
+        Syscall::TimerDelete => { let r = runtime.timer_delete_impl(request.arg1); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) } }
+        Syscall::TimerfdCreate => checked(unsafe { check_timer_args(syscall, &request) },
+                                          || runtime.timerfd_create_impl(request.arg1, request.arg2)),
+        Syscall::TimerfdSettime => checked(unsafe { check_timer_args(syscall, &request) },
+                                           || runtime.timerfd_settime_impl(request.arg1 as i32, request.arg2, request.arg3, request.arg4)),
+        Syscall::TimerfdGettime => checked(unsafe { check_timer_args(syscall, &request) },
+                                           || runtime.timerfd_gettime_impl(request.arg1 as i32, request.arg2)),
+        Syscall::Eventfd     => { let r = runtime.eventfd2_impl(request.arg1, 0); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) } }
+        Syscall::Eventfd2 => checked(unsafe { check_timer_args(syscall, &request) },
+                                     || runtime.eventfd2_impl(request.arg1, request.arg2)),
+        Syscall::Signalfd => checked(unsafe { check_timer_args(syscall, &request) },
+                                     || runtime.signalfd4_impl(request.arg1 as i32, request.arg2, request.arg3, 0)),
+        Syscall::Signalfd4 => checked(unsafe { check_timer_args(syscall, &request) },
+                                      || runtime.signalfd4_impl(request.arg1 as i32, request.arg2, request.arg3, request.arg4)),
+        Syscall::EpollCreate => {
+            if request.arg1 as i32 <= 0 { return SyscallResult::err(-22); } // EINVAL
+            let r = runtime.epoll_create1_impl(0); if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) }
+        }
+        Syscall::EpollCreate1 | Syscall::EpollCtl | Syscall::EpollWait | Syscall::EpollPwait => {
+            checked(check_epoll_args(syscall, &request), || match syscall {
+                Syscall::EpollCreate1 => runtime.epoll_create1_impl(request.arg1),
+                Syscall::EpollCtl => runtime.epoll_ctl_impl(request.arg1 as i32, request.arg2, request.arg3 as i32, request.arg4),
+                Syscall::EpollWait => runtime.epoll_pwait_impl(request.arg1 as i32, request.arg2, request.arg3 as i32 as i64, request.arg4 as i32 as i64, 0, 0),
+                _ => runtime.epoll_pwait_impl(request.arg1 as i32, request.arg2, request.arg3 as i32 as i64, request.arg4 as i32 as i64, request.arg5, request.arg6),
+            })
+        }
+        Syscall::Prlimit64   => unsafe {
+            if let Err(e) = check_rlimit(request.arg2 as u32, request.arg3, request.arg4) {
+                return SyscallResult::err(e);
+            }
+            let r = runtime.prlimit64_impl(request.arg1 as u32, request.arg2 as u32, request.arg3, request.arg4);
+            if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) }
+        }
+        Syscall::Getrandom   => unsafe {
+            let flags = request.arg3 as u32;
+            if flags & !(GRND_NONBLOCK | GRND_RANDOM | GRND_INSECURE) != 0
+                || flags & (GRND_RANDOM | GRND_INSECURE) == GRND_RANDOM | GRND_INSECURE
+            {
+                return SyscallResult::err(-22); // EINVAL
+            }
+            if validate_user_range(request.arg1, request.arg2).is_err() { return SyscallResult::err(-14); } // EFAULT
+            let buf: &mut [u8] = if request.arg2 == 0 { &mut [] }
+                                 else { slice::from_raw_parts_mut(request.arg1 as *mut u8, request.arg2 as usize) };
+            let r = runtime.getrandom_impl(buf, flags);
+            if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) }
+        }
         Syscall::DnsResolve => unsafe {
             let host_ptr = request.arg1;
             let host_len = request.arg2 as usize;
@@ -1357,6 +1779,26 @@ pub unsafe fn dispatch<R: SyscallRuntime>(
             let r = runtime.dns_resolve_impl(hostname);
             if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) }
         }
+        Syscall::SysTrace    => {
+            let (op, pid) = (request.arg1, request.arg2);
+            match op {
+                SYSTRACE_OFF | SYSTRACE_ALL | SYSTRACE_CLEAR => {}
+                // The filter holds 8-bit PIDs, like the rest of the scheduler.
+                SYSTRACE_ADD_PID | SYSTRACE_DEL_PID if (1..=u8::MAX as u64).contains(&pid) => {}
+                _ => return SyscallResult::err(-22), // EINVAL
+            }
+            let r = runtime.systrace_impl(op, pid);
+            if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) }
+        }
+        Syscall::Loadkeys    => unsafe {
+            let (name_ptr, name_len) = (request.arg1, request.arg2 as usize);
+            // Linux errnos: the loadkeys tool tells -1 (EPERM) from -22.
+            if name_len == 0 || name_len > 16 { return SyscallResult::err(-22); } // EINVAL
+            if validate_user_range(name_ptr, name_len as u64).is_err() { return SyscallResult::err(-14); } // EFAULT
+            let name = slice::from_raw_parts(name_ptr as *const u8, name_len);
+            let r = runtime.loadkeys_impl(name);
+            if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) }
+        }
         Syscall::Invalid       => SyscallResult::err(ENOSYS),
     }
 }
@@ -1367,7 +1809,7 @@ unsafe fn sys_open<R: SyscallRuntime>(
     runtime: &mut R, path_ptr: u64, flags: u64, _mode: u64,
 ) -> SyscallResult {
     // Always Linux ABI: open(path_ptr, flags[, mode]) — path is NUL-terminated.
-    let path_len = unsafe { strnlen_user(path_ptr, 4096) };
+    let path_len = unsafe { strnlen_user(runtime, path_ptr, 4096) };
     if path_len == 0 { return SyscallResult::err(EINVAL); }
     if let Err(code) = validate_user_range(path_ptr, path_len) {
         return SyscallResult::err(code);
@@ -1419,7 +1861,7 @@ unsafe fn sys_write<R: SyscallRuntime>(
         // Try FD-table first (supports dup2-redirected stdout/stderr to a pipe).
         // write_fd returns fs::EBADF (-9) when fd=1/2 has no FdTable entry.
         let r = runtime.fs_write_file(fd, buf);
-        if r != crate::kernel::fs::EBADF {
+        if r != FS_EBADF {
             return if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) };
         }
         // Fallback: plain console.
@@ -1470,9 +1912,9 @@ unsafe fn sys_exec<R: SyscallRuntime>(
     // Our custom exec ABI: exec(path_ptr, path_len) — arg2 is path_len.
     // Use NUL-scan when arg2 looks like a pointer (>= 4096), or always for safety.
     let path_len = if arg2 >= 4096 {
-        unsafe { strnlen_user(path_ptr, 4096) }
+        unsafe { strnlen_user(runtime, path_ptr, 4096) }
     } else if arg2 == 0 {
-        unsafe { strnlen_user(path_ptr, 4096) }
+        unsafe { strnlen_user(runtime, path_ptr, 4096) }
     } else {
         arg2
     };
@@ -1523,13 +1965,241 @@ fn sys_fork<R: SyscallRuntime>(runtime: &mut R) -> SyscallResult {
     if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) }
 }
 
-fn sys_waitpid<R: SyscallRuntime>(runtime: &mut R, pid: u64) -> SyscallResult {
-    // waitpid_impl either returns immediately (child already dead) or
-    // diverges via exit_to_kernel (blocks the calling task).
-    let r = runtime.waitpid_impl(pid);
+fn sys_wait4<R: SyscallRuntime>(runtime: &mut R, pid: u64, status_ptr: u64,
+                                 options: u64, rusage_ptr: u64) -> SyscallResult {
+    // wait4_impl either returns immediately (a report is ready, WNOHANG, or
+    // an error) or diverges via exit_to_kernel (blocks the calling task).
+    let r = runtime.wait4_impl(pid as i32, status_ptr, options as u32, rusage_ptr);
+    if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) }
+}
+
+/// mmap: arg1=addr, arg2=len, arg3=prot, arg4=flags, arg5=fd, arg6=offset.
+/// Malformed arguments are refused before the runtime resolves the backing
+/// object or touches page tables.
+fn sys_mmap<R: SyscallRuntime>(runtime: &mut R, request: SyscallRequest) -> SyscallResult {
+    let (addr, len, flags, offset) = (request.arg1, request.arg2, request.arg4 as u32, request.arg6);
+    if len == 0 || offset % PAGE_SIZE != 0 { return SyscallResult::err(-22); } // EINVAL
+    if !matches!(flags & MAP_TYPE, MAP_SHARED | MAP_PRIVATE | MAP_SHARED_VALIDATE) {
+        return SyscallResult::err(-22); // EINVAL
+    }
+    let pages = len.div_ceil(PAGE_SIZE);
+    if pages > USER_SPACE_END / PAGE_SIZE { return SyscallResult::err(-12); } // ENOMEM
+    // The file offset of the last page must not wrap.
+    let bytes = pages * PAGE_SIZE;
+    if offset.checked_add(bytes).is_none() { return SyscallResult::err(-75); } // EOVERFLOW
+    if flags & MAP_FIXED != 0
+        && (addr % PAGE_SIZE != 0 || addr.checked_add(bytes).is_none_or(|end| end > USER_SPACE_END))
+    {
+        return SyscallResult::err(-22); // EINVAL
+    }
+    let r = runtime.mmap_impl(addr, len, request.arg3 as u32, flags, request.arg5 as i32, offset);
+    if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) }
+}
+
+/// sigaltstack(ss, old_ss): both `stack_t` pointers must be in user space,
+/// and a new stack must carry known flags and, unless disabled, at least
+/// `MINSIGSTKSZ` bytes of user memory.  Whether the caller is running on
+/// its current stack (EPERM) is the runtime's call.
+unsafe fn sys_sigaltstack<R: SyscallRuntime>(runtime: &mut R, ss_ptr: u64, old_ptr: u64) -> SyscallResult {
+    if ss_ptr  != 0 && validate_user_range(ss_ptr,  STACK_T_SIZE).is_err() { return SyscallResult::err(-14); } // EFAULT
+    if old_ptr != 0 && validate_user_range(old_ptr, STACK_T_SIZE).is_err() { return SyscallResult::err(-14); }
+    if ss_ptr != 0 {
+        let (sp, flags, size) = unsafe {
+            (ptr::read_unaligned(ss_ptr as *const u64),
+             ptr::read_unaligned((ss_ptr + 8) as *const i32),
+             ptr::read_unaligned((ss_ptr + 16) as *const u64))
+        };
+        if flags & !(SS_DISABLE | SS_ONSTACK) != 0 { return SyscallResult::err(-22); } // EINVAL
+        if flags & SS_DISABLE == 0 {
+            if size < MINSIGSTKSZ { return SyscallResult::err(-12); } // ENOMEM
+            if validate_user_range(sp, size).is_err() { return SyscallResult::err(-14); } // EFAULT
+        }
+    }
+    let r = runtime.sigaltstack_impl(ss_ptr, old_ptr);
+    if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) }
+}
+
+/// Checks shared by getrlimit, setrlimit and prlimit64: a known resource,
+/// user pointers for whichever of `new_ptr`/`old_ptr` are given, and a new
+/// soft limit no higher than its hard limit.
+/// `setgroups(size, list)`: at most `NGROUPS_MAX` groups, read from user
+/// space.  Whether the caller may set them is the runtime's call.
+fn sys_setgroups<R: SyscallRuntime>(runtime: &mut R, size: u64, list_ptr: u64) -> SyscallResult {
+    if size > NGROUPS_MAX as u64 { return SyscallResult::err(-22); } // EINVAL
+    if size > 0 && validate_user_range(list_ptr, size * 4).is_err() {
+        return SyscallResult::err(-14); // EFAULT
+    }
+    let r = runtime.setgroups_impl(size, list_ptr);
+    if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) }
+}
+
+unsafe fn check_rlimit(resource: u32, new_ptr: u64, old_ptr: u64) -> Result<(), i64> {
+    if resource >= RLIM_NLIMITS { return Err(-22); } // EINVAL
+    if new_ptr != 0 && validate_user_range(new_ptr, RLIMIT_SIZE).is_err() { return Err(-14); } // EFAULT
+    if old_ptr != 0 && validate_user_range(old_ptr, RLIMIT_SIZE).is_err() { return Err(-14); }
+    if new_ptr != 0 {
+        let (soft, hard) = unsafe {
+            (ptr::read_unaligned(new_ptr as *const u64), ptr::read_unaligned((new_ptr + 8) as *const u64))
+        };
+        if soft > hard { return Err(-22); } // EINVAL
+    }
+    Ok(())
+}
+
+/// ptrace(request, pid, addr, data): the request must be one the runtime
+/// implements, with an `addr`/`data` that makes sense for it.  Whether `pid`
+/// is a stopped tracee of the caller is the runtime's call.
+fn sys_ptrace<R: SyscallRuntime>(runtime: &mut R, req: i64, pid: u32, addr: u64, data: u64) -> SyscallResult {
+    let bad = match req {
+        PTRACE_TRACEME | PTRACE_ATTACH | PTRACE_KILL
+        | PTRACE_PEEKTEXT | PTRACE_PEEKDATA | PTRACE_POKETEXT | PTRACE_POKEDATA
+        | PTRACE_CONT | PTRACE_SYSCALL | PTRACE_SINGLESTEP | PTRACE_DETACH
+        | PTRACE_GETEVENTMSG | PTRACE_GET_SYSCALL_INFO => None,
+        PTRACE_PEEKUSER | PTRACE_POKEUSER => {
+            (addr % 8 != 0 || addr >= USER_REGS_SIZE as u64).then_some(-5) // EIO
+        }
+        PTRACE_GETREGS | PTRACE_SETREGS => {
+            validate_user_range(data, USER_REGS_SIZE as u64).is_err().then_some(-14) // EFAULT
+        }
+        PTRACE_GETSIGINFO => validate_user_range(data, SIGINFO_SIZE).is_err().then_some(-14),
+        PTRACE_SETOPTIONS => {
+            (data > u32::MAX as u64 || data as u32 & !PTRACE_O_SUPPORTED != 0).then_some(-22) // EINVAL
+        }
+        _ => Some(-5), // EIO
+    };
+    if let Some(e) = bad { return SyscallResult::err(e); }
+    let r = runtime.ptrace_impl(req, pid, addr, data);
+    if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) }
+}
+
+/// Map a runtime return value to a result, or fail with `check`'s error
+/// without calling the runtime.
+fn checked(check: Result<(), i64>, f: impl FnOnce() -> i64) -> SyscallResult {
+    let r = match check { Ok(()) => f(), Err(e) => e };
     if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) }
 }
 
+/// A user `struct itimerval` (`per_sec` = 10^6) or `struct itimerspec`
+/// (10^9): EFAULT outside user space, EINVAL for a negative second count or
+/// a fraction of a second out of range.
+unsafe fn check_interval(ptr: u64, per_sec: i64) -> Result<(), i64> {
+    if validate_user_range(ptr, ITIMER_SIZE).is_err() { return Err(-14); } // EFAULT
+    for half in [ptr, ptr + 16] {
+        let (sec, frac) = unsafe {
+            (ptr::read_unaligned(half as *const i64), ptr::read_unaligned((half + 8) as *const i64))
+        };
+        if sec < 0 || !(0..per_sec).contains(&frac) { return Err(-22); } // EINVAL
+    }
+    Ok(())
+}
+
+/// An optional output buffer: null, or `len` bytes of user space.
+fn check_out(ptr: u64, len: u64) -> Result<(), i64> {
+    if ptr != 0 && validate_user_range(ptr, len).is_err() { return Err(-14); } // EFAULT
+    Ok(())
+}
+
+/// Argument checks for the interval timer, POSIX timer, timerfd, eventfd
+/// and signalfd calls — everything that does not need the caller's timers
+/// or descriptors.
+unsafe fn check_timer_args(syscall: Syscall, a: &SyscallRequest) -> Result<(), i64> {
+    const EFAULT: i64 = -14;
+    const EINVAL: i64 = -22;
+    match syscall {
+        Syscall::Getitimer | Syscall::Setitimer => {
+            if a.arg1 > ITIMER_PROF as u64 { return Err(EINVAL); }
+            if syscall == Syscall::Getitimer {
+                if validate_user_range(a.arg2, ITIMER_SIZE).is_err() { return Err(EFAULT); }
+            } else {
+                if a.arg2 != 0 { unsafe { check_interval(a.arg2, 1_000_000)? }; }
+                check_out(a.arg3, ITIMER_SIZE)?;
+            }
+        }
+        Syscall::TimerCreate => {
+            // REALTIME, MONOTONIC, the CPU-time clocks, MONOTONIC_RAW,
+            // the *_COARSE clocks and BOOTTIME.
+            if a.arg1 > 7 { return Err(EINVAL); }
+            if validate_user_range(a.arg3, 4).is_err() { return Err(EFAULT); }
+            if a.arg2 != 0 {
+                // struct sigevent { sigev_value, sigev_signo, sigev_notify, sigev_tid }
+                let sevp = a.arg2;
+                if validate_user_range(sevp, SIGEVENT_SIZE).is_err() { return Err(EFAULT); }
+                let (signo, notify) = unsafe {
+                    (ptr::read_unaligned((sevp + 8) as *const i32),
+                     ptr::read_unaligned((sevp + 12) as *const i32))
+                };
+                match notify {
+                    SIGEV_NONE => {}
+                    SIGEV_SIGNAL | SIGEV_THREAD_ID => {
+                        if !(1..NSIG).contains(&signo) || signo == SIGKILL || signo == SIGSTOP {
+                            return Err(EINVAL);
+                        }
+                    }
+                    // SIGEV_THREAD needs a C library helper thread.
+                    _ => return Err(EINVAL),
+                }
+            }
+        }
+        Syscall::TimerSettime | Syscall::TimerfdSettime => {
+            let allowed = if syscall == Syscall::TimerSettime { TIMER_ABSTIME }
+                          else { TIMER_ABSTIME | TFD_TIMER_CANCEL_ON_SET };
+            if a.arg2 & !allowed != 0 { return Err(EINVAL); }
+            unsafe { check_interval(a.arg3, 1_000_000_000)? };
+            check_out(a.arg4, ITIMER_SIZE)?;
+        }
+        Syscall::TimerGettime | Syscall::TimerfdGettime => {
+            if validate_user_range(a.arg2, ITIMER_SIZE).is_err() { return Err(EFAULT); }
+        }
+        Syscall::TimerfdCreate => {
+            // REALTIME, MONOTONIC, BOOTTIME and the *_ALARM variants.
+            if !matches!(a.arg1, 0 | 1 | 7 | 8 | 9) { return Err(EINVAL); }
+            if a.arg2 & !(O_NONBLOCK | O_CLOEXEC) != 0 { return Err(EINVAL); }
+        }
+        Syscall::Eventfd2 => {
+            if a.arg2 & !(EFD_SEMAPHORE | O_NONBLOCK | O_CLOEXEC) != 0 { return Err(EINVAL); }
+        }
+        Syscall::Signalfd | Syscall::Signalfd4 => {
+            let flags = if syscall == Syscall::Signalfd4 { a.arg4 } else { 0 };
+            if a.arg3 != 8 || flags & !(O_NONBLOCK | O_CLOEXEC) != 0 { return Err(EINVAL); }
+            if validate_user_range(a.arg2, 8).is_err() { return Err(EFAULT); }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Argument checks for the epoll calls that do not need the epoll set or
+/// the watched fd: creation flags, the `epoll_ctl` op and event pointer, and
+/// the `epoll_pwait` event buffer and signal mask.
+fn check_epoll_args(syscall: Syscall, a: &SyscallRequest) -> Result<(), i64> {
+    const EFAULT: i64 = -14;
+    const EINVAL: i64 = -22;
+    match syscall {
+        Syscall::EpollCreate1 => {
+            if a.arg1 & !O_CLOEXEC != 0 { return Err(EINVAL); }
+        }
+        Syscall::EpollCtl => {
+            if !(EPOLL_CTL_ADD..=EPOLL_CTL_MOD).contains(&a.arg2) { return Err(EINVAL); }
+            if a.arg2 != EPOLL_CTL_DEL && validate_user_range(a.arg4, EPOLL_EVENT_SIZE).is_err() {
+                return Err(EFAULT);
+            }
+        }
+        Syscall::EpollWait | Syscall::EpollPwait => {
+            let maxevents = a.arg3 as i32 as i64;
+            if !(1..=EP_MAX_EVENTS).contains(&maxevents) { return Err(EINVAL); }
+            if validate_user_range(a.arg2, maxevents as u64 * EPOLL_EVENT_SIZE).is_err() {
+                return Err(EFAULT);
+            }
+            if syscall == Syscall::EpollPwait && a.arg5 != 0 {
+                if a.arg6 != 8 { return Err(EINVAL); }
+                if validate_user_range(a.arg5, 8).is_err() { return Err(EFAULT); }
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
 fn sys_brk<R: SyscallRuntime>(runtime: &mut R, new_end: u64) -> SyscallResult {
     let r = runtime.brk_program(new_end);
     if r < 0 { SyscallResult::err(r) } else { SyscallResult::ok(r) }
@@ -1566,7 +2236,7 @@ unsafe fn sys_chdir<R: SyscallRuntime>(
     runtime: &mut R, path_ptr: u64, _arg2: u64,
 ) -> SyscallResult {
     // Linux ABI: chdir(path_ptr) — single NUL-terminated argument.
-    let path_len = unsafe { strnlen_user(path_ptr, 4096) };
+    let path_len = unsafe { strnlen_user(runtime, path_ptr, 4096) };
     if path_len == 0 { return SyscallResult::err(EINVAL); }
     if let Err(e) = validate_user_range(path_ptr, path_len) { return SyscallResult::err(e); }
     let path = unsafe { slice::from_raw_parts(path_ptr as *const u8, path_len as usize) };
@@ -1592,7 +2262,7 @@ unsafe fn sys_stat<R: SyscallRuntime>(
     // If arg2 < 4096, it looks like a length → custom ABI (arg2=path_len, arg3=statbuf).
     let (path_len, stat_buf) = if arg2 >= 4096 {
         // Linux ABI: NUL-terminated path, arg2 = statbuf pointer
-        let len = unsafe { strnlen_user(path_ptr, 4096) };
+        let len = unsafe { strnlen_user(runtime, path_ptr, 4096) };
         (len, arg2)
     } else {
         (arg2, arg3)
@@ -1607,15 +2277,14 @@ unsafe fn sys_stat<R: SyscallRuntime>(
 
 /// Measure length of a NUL-terminated user string (including NUL), up to `max` bytes.
 /// Returns 0 if an unmapped page is encountered before the NUL terminator.
-unsafe fn strnlen_user(ptr: u64, max: usize) -> u64 {
-    use crate::kernel::paging_allocator::is_page_mapped_current;
+unsafe fn strnlen_user<R: SyscallRuntime>(runtime: &R, ptr: u64, max: usize) -> u64 {
     let mut i = 0usize;
     let mut checked_page = u64::MAX; // sentinel: no page checked yet
     while i < max {
         let addr = ptr + i as u64;
         let page = addr & !0xFFF_u64;
         if page != checked_page {
-            if !unsafe { is_page_mapped_current(addr) } {
+            if !runtime.user_page_mapped(addr) {
                 return 0; // unmapped — signal failure to caller
             }
             checked_page = page;
@@ -1627,6 +2296,16 @@ unsafe fn strnlen_user(ptr: u64, max: usize) -> u64 {
     i as u64
 }
 
+/// Borrow the NUL-terminated user string at `ptr`, without its NUL.  A
+/// pointer outside user space or into an unmapped page is EFAULT.
+unsafe fn user_path<'a, R: SyscallRuntime>(runtime: &R, ptr: u64) -> Result<&'a [u8], i64> {
+    if validate_user_range(ptr, 1).is_err() { return Err(-14); } // EFAULT
+    let len = unsafe { strnlen_user(runtime, ptr, 4096) };
+    if len == 0 || validate_user_range(ptr, len).is_err() { return Err(-14); }
+    let bytes = unsafe { slice::from_raw_parts(ptr as *const u8, len as usize) };
+    Ok(bytes.strip_suffix(&[0]).unwrap_or(bytes))
+}
+
 /// Getenv: arg1=key_ptr, arg2=key_len, arg3=val_buf_ptr, arg4=val_buf_len
 unsafe fn sys_getenv<R: SyscallRuntime>(
     runtime: &mut R, key_ptr: u64, key_len: u64, buf_ptr: u64, buf_len: u64,
@@ -1677,7 +2356,7 @@ unsafe fn sys_msgsnd<R: SyscallRuntime>(
 unsafe fn sys_msgrcv<R: SyscallRuntime>(
     runtime: &mut R, id: u32, msg_out_ptr: u64,
 ) -> SyscallResult {
-    if let Err(e) = validate_user_range(msg_out_ptr, size_of::<crate::kernel::ipc::Message>() as u64) {
+    if let Err(e) = validate_user_range(msg_out_ptr, IPC_MESSAGE_SIZE) {
         return SyscallResult::err(e);
     }
     let r = runtime.msgrcv(id, msg_out_ptr);
@@ -1694,7 +2373,7 @@ fn sys_msgq_destroy<R: SyscallRuntime>(runtime: &mut R, id: u32) -> SyscallResul
 unsafe fn sys_msgrcv_wait<R: SyscallRuntime>(
     runtime: &mut R, id: u32, msg_out_ptr: u64,
 ) -> SyscallResult {
-    if let Err(e) = validate_user_range(msg_out_ptr, size_of::<crate::kernel::ipc::Message>() as u64) {
+    if let Err(e) = validate_user_range(msg_out_ptr, IPC_MESSAGE_SIZE) {
         return SyscallResult::err(e);
     }
     let r = runtime.msgrcv_wait(id, msg_out_ptr);
@@ -23,6 +23,7 @@
 use core::arch::asm;
 use core::arch::naked_asm;
 use crate::kernel::serial::SERIAL_PORT;
+use crate::kernel::user_mode::TaskContext;
 use super::syscall::handle_syscall;
 
 const IA32_STAR:  u32 = 0xC000_0081;
@@ -62,12 +63,19 @@ pub unsafe fn init() {
     let entry_addr = syscall_entry as *const () as u64;
     wrmsr(IA32_LSTAR, entry_addr);
 
-    // FMASK: clear IF (disable interrupts) on syscall entry.
-    wrmsr(IA32_FMASK, 0x200);
+    // FMASK: clear IF (disable interrupts) and TF (a single-stepped task
+    // must not trap inside the kernel) on syscall entry.
+    unsafe { wrmsr(IA32_FMASK, 0x200 | 0x100); }
 
     SERIAL_PORT.write_str("syscall/sysret enabled (STAR=0x10/0x08)\n");
 }
 
+/// User RSP at the most recent `syscall` instruction (still valid while the
+/// handler for that call runs).
+pub fn user_rsp() -> u64 {
+    unsafe { USER_RSP_SAVE }
+}
+
 #[inline]
 unsafe fn rdmsr(msr: u32) -> u64 {
     let lo: u32; let hi: u32;
@@ -95,21 +103,21 @@ unsafe fn wrmsr(msr: u32, value: u64) {
 // We must:
 //   1. Save user RSP.
 //   2. Switch to kernel stack.
-//   3. Build a minimal stack frame, call handler.
+//   3. Push every user register as a `TaskContext`, call the handler.
 //   4. Restore state, sysretq.
 //
+// The full snapshot lets syscalls that yield to the scheduler (fork, wait,
+// sleep, ptrace stops) resume the task later through `resume_user_context`,
+// exactly as on the int 0x80 path.  As after a real `syscall`, the saved
+// rcx/r11 are the return RIP and RFLAGS.
+//
 // sysretq uses rcx (return RIP) and r11 (RFLAGS) — we must preserve them.
 
-// Stack layout after the 9 pushes below (rsp = lowest address):
-//   [rsp + 0 ] = rax  (syscall number)
-//   [rsp + 8 ] = rdi  (arg1)
-//   [rsp + 16] = rsi  (arg2)
-//   [rsp + 24] = rdx  (arg3)
-//   [rsp + 32] = r8   (arg5, Linux syscall ABI)
-//   [rsp + 40] = r9   (arg6, Linux syscall ABI)
-//   [rsp + 48] = r10  (arg4, Linux uses r10 not rcx)
-//   [rsp + 56] = rcx  (user RIP,    saved by syscall instruction)
-//   [rsp + 64] = r11  (user RFLAGS, saved by syscall instruction)
+// Stack layout after the 20 pushes below (rsp = lowest address) matches
+// `TaskContext` (see user_mode.rs):
+//   r15+0  r14+8  r13+16 r12+24 r11+32 r10+40 r9+48  r8+56
+//   rdi+64 rsi+72 rbp+80 rdx+88 rcx+96 rbx+104 rax+112
+//   rip+120 cs+128 rflags+136 rsp+144 ss+152
 #[unsafe(naked)]
 unsafe extern "C" fn syscall_entry() {
     naked_asm!(
@@ -118,44 +126,50 @@ unsafe extern "C" fn syscall_entry() {
         "mov rsp, [rip + {stk_top}]",
         "and rsp, -16",
 
-        // Push all registers we need to survive the call.
-        "push r11",  // [rsp+64] user RFLAGS
-        "push rcx",  // [rsp+56] user RIP
-        "push r10",  // [rsp+48] arg4
-        "push r9",   // [rsp+40] arg6
-        "push r8",   // [rsp+32] arg5
-        "push rdx",  // [rsp+24] arg3
-        "push rsi",  // [rsp+16] arg2
-        "push rdi",  // [rsp+ 8] arg1
-        "push rax",  // [rsp+ 0] syscall number
-
-        // Load args for handle_syscall(num, a1, a2, a3, a4, a5) [SysV AMD64].
-        "mov rdi, [rsp]",       // num
-        "mov rsi, [rsp +  8]",  // a1  = rdi
-        "mov rdx, [rsp + 16]",  // a2  = rsi
-        "mov rcx, [rsp + 24]",  // a3  = rdx
-        "mov r8,  [rsp + 48]",  // a4  = r10
-        "mov r9,  [rsp + 32]",  // a5  = r8
+        // Build the TaskContext, highest field first.
+        "push 0x1b",                      // ss     (user data selector)
+        "push qword ptr [rip + {user_rsp}]", // rsp
+        "push r11",                       // rflags
+        "push 0x23",                      // cs     (user code selector)
+        "push rcx",                       // rip
+        "push rax",                       // syscall number
+        "push rbx",
+        "push rcx",
+        "push rdx",                       // arg3
+        "push rbp",
+        "push rsi",                       // arg2
+        "push rdi",                       // arg1
+        "push r8",                        // arg5
+        "push r9",                        // arg6
+        "push r10",                       // arg4 (Linux uses r10 not rcx)
+        "push r11",
+        "push r12",
+        "push r13",
+        "push r14",
+        "push r15",
 
+        // handler(ctx) [SysV AMD64]; 20 pushes keep rsp 16-byte aligned.
+        "mov rdi, rsp",
         "call {handler}",       // result in rax
 
         // Restore registers that the Linux syscall ABI requires to be preserved.
-        // The Rust handler (SysV ABI) may clobber rdi, rsi, rdx, rcx, r8, r9, r10.
+        // The Rust handler (SysV ABI) may clobber rdi, rsi, rdx, rcx, r8, r9, r10;
+        // rbx, rbp and r12-r15 are callee-saved and already intact.
         // Per Linux x86-64 syscall ABI: rdi, rsi, rdx, r8, r9, r10 are preserved;
         // only rax (return value), rcx (user RIP), r11 (user RFLAGS) are changed.
-        "mov r10, [rsp + 48]",  // restore r10 (arg4)
-        "mov r9,  [rsp + 40]",  // restore r9  (arg6)
-        "mov r8,  [rsp + 32]",  // restore r8  (arg5) ← critical: __sigsetjmp_tail uses r8
-        "mov rdx, [rsp + 24]",  // restore rdx (arg3)
-        "mov rsi, [rsp + 16]",  // restore rsi (arg2)
-        "mov rdi, [rsp +  8]",  // restore rdi (arg1)
+        "mov r10, [rsp + 40]",  // restore r10 (arg4)
+        "mov r9,  [rsp + 48]",  // restore r9  (arg6)
+        "mov r8,  [rsp + 56]",  // restore r8  (arg5) ← critical: __sigsetjmp_tail uses r8
+        "mov rdi, [rsp + 64]",  // restore rdi (arg1)
+        "mov rsi, [rsp + 72]",  // restore rsi (arg2)
+        "mov rdx, [rsp + 88]",  // restore rdx (arg3)
 
         // Restore sysretq-required registers from saved slots.
-        "mov rcx, [rsp + 56]",  // user RIP   → rcx
-        "mov r11, [rsp + 64]",  // user RFLAGS → r11
+        "mov rcx, [rsp + 120]", // user RIP   → rcx
+        "mov r11, [rsp + 136]", // user RFLAGS → r11
 
         // Restore user stack, return to ring 3.
-        "mov rsp, [rip + {user_rsp}]",
+        "mov rsp, [rsp + 144]",
         "sysretq",
 
         user_rsp = sym USER_RSP_SAVE,
@@ -165,10 +179,11 @@ unsafe extern "C" fn syscall_entry() {
 }
 
 #[unsafe(no_mangle)]
-unsafe extern "C" fn syscall_handler_wrapper(
-    syscall_num: u64,
-    arg1: u64, arg2: u64, arg3: u64, arg4: u64, arg5: u64,
-) -> i64 {
+unsafe extern "C" fn syscall_handler_wrapper(ctx: *const TaskContext) -> i64 {
+    let ctx = unsafe { *ctx };
+    let (syscall_num, arg1, arg2, arg3, arg4, arg5, arg6) =
+        (ctx.rax, ctx.rdi, ctx.rsi, ctx.rdx, ctx.r10, ctx.r8, ctx.r9);
+
     // Log arguments for key early-startup syscalls to help debug bash crash.
     match syscall_num {
         9 => {  // mmap
@@ -270,7 +285,14 @@ unsafe extern "C" fn syscall_handler_wrapper(
         _ => {}
     }
 
-    let result = handle_syscall(syscall_num, arg1, arg2, arg3, arg4, arg5);
+    // Make the caller's full register state available to syscalls that need
+    // to yield, as handle_system_call does for int 0x80.
+    let result = unsafe {
+        crate::kernel::user_mode::CURRENT_SYSCALL_CTX = Some(ctx);
+        let result = handle_syscall(syscall_num, arg1, arg2, arg3, arg4, arg5, arg6);
+        crate::kernel::user_mode::CURRENT_SYSCALL_CTX = None;
+        result
+    };
 
     // Log return value for the same key syscalls.
     match syscall_num {
@@ -0,0 +1,291 @@
+// src/kernel/sys/systrace.rs
+//! Kernel syscall tracer.
+//!
+//! [`syscall_core::dispatch`](super::syscall_core::dispatch) reports every
+//! recognised syscall to `SyscallRuntime::trace` on entry and `trace_exit`
+//! on return.  While tracing is on for the calling task, each call becomes a
+//! [`Record`] in a ring holding the last [`RING_SIZE`] calls: number,
+//! arguments, result and duration in timer ticks.  `/proc/trace` renders
+//! the ring as text.
+//!
+//! What is traced is set by `/proc/sys/kernel/trace`: `0` (nothing), `1`
+//! (every task) or a list of PIDs.  The kernel re-reads the file whenever
+//! it is written; the `systrace` syscall (436) edits the same setting and
+//! rewrites the file.  A PID drops out of the list when its task exits.
+//! Only root may switch tracing off or on for everyone or clear the ring;
+//! other users may add and remove the PIDs they could `ptrace`.
+//!
+//! A call that blocks (sleep, wait4, msgrcv_wait) stays open until the
+//! scheduler resumes the task with its result.  One that never returns
+//! (exit, a restarted call, or a task killed mid-call) is closed as
+//! [`Outcome::Unfinished`].
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+use crate::kernel::scheduler::{SCHED, CURRENT_TASK_IDX, MAX_TASKS};
+use crate::kernel::syscall_core::{Syscall, SyscallRequest, SyscallResult};
+use crate::kernel::user_mode;
+
+/// The tunable that selects what is traced.
+pub const TRACE_PATH: &str = "/proc/sys/kernel/trace";
+
+/// Number of calls kept; older ones are overwritten.
+pub const RING_SIZE: usize = 256;
+
+// `systrace(op, pid)` operations; syscall_core refuses unknown ones.
+pub use crate::kernel::syscall_core::{
+    SYSTRACE_OFF, SYSTRACE_ALL, SYSTRACE_ADD_PID, SYSTRACE_DEL_PID, SYSTRACE_CLEAR,
+};
+
+const EPERM:  i64 = -1;
+const ESRCH:  i64 = -3;
+const EINVAL: i64 = -22;
+
+// ── Records ────────────────────────────────────────────────────────────────
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Outcome {
+    /// Still in the kernel (blocked, or the record is being read mid-call).
+    Running,
+    /// Returned this value to user space.
+    Returned(i64),
+    /// Never returned: exit, restarted, or killed.
+    Unfinished,
+}
+
+#[derive(Clone, Copy)]
+pub struct Record {
+    /// 1-based sequence number; 0 marks an unused slot.
+    pub seq:     u64,
+    pub pid:     u8,
+    pub nr:      u64,
+    pub args:    [u64; 6],
+    /// Tick the call was entered.
+    pub start:   u64,
+    /// Ticks spent in the call (valid once it is no longer running).
+    pub ticks:   u64,
+    pub outcome: Outcome,
+}
+
+impl Record {
+    const EMPTY: Self = Self {
+        seq: 0, pid: 0, nr: 0, args: [0; 6], start: 0, ticks: 0, outcome: Outcome::Running,
+    };
+
+    pub fn name(&self) -> &'static str {
+        Syscall::from(self.nr).name()
+    }
+}
+
+// ── Filter ─────────────────────────────────────────────────────────────────
+
+#[derive(Clone, Copy, PartialEq)]
+struct Filter {
+    all:  bool,
+    /// Bitmap of traced PIDs.
+    pids: [u64; 4],
+}
+
+impl Filter {
+    const OFF: Self = Self { all: false, pids: [0; 4] };
+
+    fn has(&self, pid: u8) -> bool {
+        self.all || self.pids[pid as usize / 64] & 1u64 << (pid % 64) != 0
+    }
+
+    fn set(&mut self, pid: u8, on: bool) {
+        let bit = 1u64 << (pid % 64);
+        if on { self.pids[pid as usize / 64] |= bit; } else { self.pids[pid as usize / 64] &= !bit; }
+    }
+
+    /// `0`, `1`, or whitespace/comma separated PIDs.  Anything unparsable
+    /// turns tracing off.
+    fn parse(text: &[u8]) -> Self {
+        let text = core::str::from_utf8(text).unwrap_or("").trim();
+        if text == "1" { return Self { all: true, pids: [0; 4] }; }
+        let mut f = Self::OFF;
+        for word in text.split(|c: char| c.is_ascii_whitespace() || c == ',') {
+            if word.is_empty() { continue; }
+            match word.parse::<u8>() {
+                Ok(0)   => {}
+                Ok(pid) => f.set(pid, true),
+                Err(_)  => return Self::OFF,
+            }
+        }
+        f
+    }
+
+    fn render(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        if self.all {
+            out.extend_from_slice(b"1\n");
+            return out;
+        }
+        for pid in 1..=255u8 {
+            if !self.has(pid) { continue; }
+            if !out.is_empty() { out.push(b' '); }
+            let mut n = pid;
+            let mut digits = [0u8; 3];
+            let mut i = 3;
+            while n > 0 || i == 3 { i -= 1; digits[i] = b'0' + n % 10; n /= 10; }
+            out.extend_from_slice(&digits[i..]);
+        }
+        if out.is_empty() { out.push(b'0'); }
+        out.push(b'\n');
+        out
+    }
+}
+
+// ── Tracer state ───────────────────────────────────────────────────────────
+
+struct Tracer {
+    filter:    Filter,
+    ring:      [Record; RING_SIZE],
+    next_seq:  u64,
+    /// Sequence number of each task's call in progress; 0 = none.
+    open:      [u64; MAX_TASKS],
+}
+
+static mut TRACER: Tracer = Tracer {
+    filter:    Filter::OFF,
+    ring:      [Record::EMPTY; RING_SIZE],
+    next_seq:  1,
+    open:      [0; MAX_TASKS],
+};
+
+/// Current filter.
+unsafe fn filter() -> Filter {
+    unsafe { (*(&raw const TRACER)).filter }
+}
+
+/// Re-read the filter from the tunable.  Called by `procfs::written`.
+pub unsafe fn reload() {
+    let fs = unsafe { crate::kernel::fs::ramfs::RAMFS.get() };
+    let filter = match fs.and_then(|fs| fs.read_file(TRACE_PATH)) {
+        Some(text) => Filter::parse(text),
+        None       => Filter::OFF,
+    };
+    unsafe { (*(&raw mut TRACER)).filter = filter; }
+}
+
+/// Make `filter` current and write it back to the tunable.
+unsafe fn store_filter(filter: Filter) {
+    let t = unsafe { &mut *(&raw mut TRACER) };
+    t.filter = filter;
+    if let Some(fs) = unsafe { crate::kernel::fs::ramfs::RAMFS.get() } {
+        let _ = fs.write_file(TRACE_PATH, &filter.render());
+    }
+}
+
+/// Close the call in progress for the task at `idx`, if any.
+pub unsafe fn finish(idx: usize, outcome: Outcome) {
+    let t   = unsafe { &mut *(&raw mut TRACER) };
+    let seq = core::mem::replace(&mut t.open[idx], 0);
+    if seq == 0 { return; }
+    let rec = &mut t.ring[(seq as usize - 1) % RING_SIZE];
+    if rec.seq != seq { return; } // already overwritten
+    let now = unsafe { crate::kernel::timer::get_ticks() };
+    rec.ticks   = now.saturating_sub(rec.start);
+    rec.outcome = outcome;
+}
+
+// ── Hooks ──────────────────────────────────────────────────────────────────
+
+/// Syscall entry, from `SyscallRuntime::trace`.
+pub unsafe fn enter(syscall: Syscall, request: &SyscallRequest) {
+    if !user_mode::is_active() { return; }
+    let idx = unsafe { CURRENT_TASK_IDX };
+    // A call still open here never came back (e.g. exec, or a restart).
+    unsafe { finish(idx, Outcome::Unfinished); }
+
+    let pid = unsafe { (*(&raw const SCHED)).tasks[idx].pid };
+    if !unsafe { filter() }.has(pid) { return; }
+
+    let t   = unsafe { &mut *(&raw mut TRACER) };
+    let seq = t.next_seq;
+    t.next_seq += 1;
+    t.ring[(seq as usize - 1) % RING_SIZE] = Record {
+        seq,
+        pid,
+        nr:      syscall as u64,
+        args:    [request.arg1, request.arg2, request.arg3,
+                  request.arg4, request.arg5, request.arg6],
+        start:   unsafe { crate::kernel::timer::get_ticks() },
+        ticks:   0,
+        outcome: Outcome::Running,
+    };
+    t.open[idx] = seq;
+}
+
+/// Syscall return, from `SyscallRuntime::trace_exit`.
+pub unsafe fn exit(result: &SyscallResult) {
+    if !user_mode::is_active() { return; }
+    unsafe { finish(CURRENT_TASK_IDX, Outcome::Returned(result.value)); }
+}
+
+/// Scheduler hook, just before the task at `idx` runs again: a call it
+/// blocked in has its result in `rax`.  A successful exec returns 0.
+pub unsafe fn resume(idx: usize) {
+    if unsafe { (*(&raw const TRACER)).open[idx] } == 0 { return; }
+    let task = unsafe { &(*(&raw const SCHED)).tasks[idx] };
+    let value = if task.first_run { 0 } else { task.ctx.rax as i64 };
+    unsafe { finish(idx, Outcome::Returned(value)); }
+}
+
+/// Task-exit hook: close its call and stop tracing its PID, which will be
+/// reused by the next task in the slot.
+pub unsafe fn on_exit(idx: usize) {
+    unsafe { finish(idx, Outcome::Unfinished); }
+    let pid = unsafe { (*(&raw const SCHED)).tasks[idx].pid };
+    let mut f = unsafe { filter() };
+    if pid != 0 && !f.all && f.has(pid) {
+        f.set(pid, false);
+        unsafe { store_filter(f); }
+    }
+}
+
+/// The slot at `idx` gets a new task: forget its predecessor's call.
+pub unsafe fn reset(idx: usize) {
+    unsafe { (*(&raw mut TRACER)).open[idx] = 0; }
+}
+
+// ── Control ────────────────────────────────────────────────────────────────
+
+/// `systrace(op, pid)`.  syscall_core has checked `op`, and that `pid` is
+/// a valid PID for `SYSTRACE_ADD_PID`/`SYSTRACE_DEL_PID`.
+pub unsafe fn control(op: u64, pid: u64) -> i64 {
+    use crate::kernel::ptrace::{find_task, may_attach};
+    let privileged = crate::kernel::cred::get().privileged();
+    let mut f = unsafe { filter() };
+    match op {
+        SYSTRACE_OFF | SYSTRACE_ALL | SYSTRACE_CLEAR if !privileged => return EPERM,
+        SYSTRACE_OFF => f = Filter::OFF,
+        SYSTRACE_ALL => f = Filter { all: true, pids: [0; 4] },
+        SYSTRACE_ADD_PID | SYSTRACE_DEL_PID => {
+            if !privileged {
+                let Some(idx) = (unsafe { find_task(pid as u32) }) else { return ESRCH };
+                if !unsafe { may_attach(idx) } { return EPERM; }
+            }
+            if op == SYSTRACE_ADD_PID { f.all = false; }
+            f.set(pid as u8, op == SYSTRACE_ADD_PID);
+        }
+        SYSTRACE_CLEAR => {
+            let t = unsafe { &mut *(&raw mut TRACER) };
+            t.ring = [Record::EMPTY; RING_SIZE];
+            t.open = [0; MAX_TASKS];
+            return 0;
+        }
+        _ => return EINVAL,
+    }
+    unsafe { store_filter(f); }
+    0
+}
+
+/// Records in the ring, oldest first.
+pub fn records() -> Vec<Record> {
+    let t = &raw const TRACER;
+    let mut out: Vec<Record> = unsafe { (*t).ring.iter().filter(|r| r.seq != 0).copied().collect() };
+    out.sort_unstable_by_key(|r| r.seq);
+    out
+}
@@ -88,10 +88,13 @@ unsafe extern "C" fn kmain() -> ! {
     assert!(BASE_REVISION.is_supported());
 
     // Capture kernel binary pointer for the installer.
+    let mut headless = false;
     if let Some(resp) = KERNEL_FILE_REQUEST.get_response() {
         let f = resp.file();
         unsafe { KERNEL_BINARY_PTR = f.addr(); KERNEL_BINARY_LEN = f.size() as usize; }
         unsafe { SERIAL_PORT.write_str("Kernel file captured\n"); }
+        // `headless` on the command line: text consoles instead of the GUI.
+        headless = f.cmdline().split(|&b| b == b' ').any(|w| w == b"headless");
     }
 
     // ── Stage 2: Interrupts ────────────────────────────────────────────────
@@ -102,11 +105,17 @@ unsafe extern "C" fn kmain() -> ! {
     unsafe { boot_init::init_memory_and_fs(&MEMORY_MAP_REQUEST); }
     unsafe { boot_init::test_paging_allocation(); }
 
-    // ── Stage 4: Graphics + GUI ────────────────────────────────────────────
+    // ── Stage 4: init (PID 1) ──────────────────────────────────────────────
+    unsafe { boot_init::start_init(); }
+
+    // ── Stage 5: Graphics + GUI ────────────────────────────────────────────
     if let Some(fb_resp) = FRAMEBUFFER_REQUEST.get_response() {
         if let Some(framebuffer) = fb_resp.framebuffers().next() {
             unsafe { SERIAL_PORT.write_str("✓ Framebuffer acquired\n"); }
             let graphics = Graphics::new(framebuffer);
+            if headless {
+                unsafe { boot_init::run_text_mode_kernel(Some(graphics)); }
+            }
             let (width, height) = graphics.get_dimensions();
             unsafe {
                 SERIAL_PORT.write_str("=== INITIALIZING MOUSE ===\n");
@@ -117,11 +126,11 @@ unsafe extern "C" fn kmain() -> ! {
             }
         } else {
             unsafe { SERIAL_PORT.write_str("✗ No framebuffer\n"); }
-            unsafe { boot_init::run_text_mode_kernel(); }
+            unsafe { boot_init::run_text_mode_kernel(None); }
         }
     } else {
         unsafe { SERIAL_PORT.write_str("✗ No framebuffer response\n"); }
-        unsafe { boot_init::run_text_mode_kernel(); }
+        unsafe { boot_init::run_text_mode_kernel(None); }
     }
 
     hcf()
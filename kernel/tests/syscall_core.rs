@@ -1,11 +1,15 @@
-#[path = "../src/kernel/syscall_core.rs"]
+#[path = "../src/kernel/sys/syscall_core.rs"]
 mod syscall_core;
 
 use std::vec::Vec;
 
 use syscall_core::{
     dispatch, validate_user_range, Syscall, SyscallRequest, SyscallRuntime, SyscallResult,
-    SystemInfo, EBADF, EINVAL, ENOSYS,
+    SystemInfo, EINVAL, ENOSYS, FS_EBADF,
+    EFD_SEMAPHORE, EPOLL_CTL_ADD, EPOLL_CTL_DEL, EPOLL_CTL_MOD, ITIMER_PROF, ITIMER_REAL,
+    ITIMER_VIRTUAL, LINUX_REBOOT_CMD_HALT, LINUX_REBOOT_CMD_POWER_OFF, LINUX_REBOOT_CMD_RESTART,
+    LINUX_REBOOT_MAGIC1, LINUX_REBOOT_MAGIC2, NGROUPS_MAX, O_CLOEXEC, O_NONBLOCK, SIGEV_SIGNAL,
+    TIMER_ABSTIME,
 };
 
 #[derive(Default)]
@@ -16,13 +20,48 @@ struct FakeRuntime {
     pid: u64,
     sleep_target: Option<u64>,
     system_info: SystemInfo,
+    /// Runtime hooks reached past syscall_core's argument checks, with the
+    /// arguments they were handed.
+    forwarded: Vec<(&'static str, Vec<u64>)>,
+    /// Path or name bytes handed to the last string-taking hook.
+    last_path: Vec<u8>,
+    /// A page `user_page_mapped` reports as not present.
+    unmapped_page: Option<u64>,
 }
 
+impl FakeRuntime {
+    fn forward(&mut self, name: &'static str, args: &[u64]) -> i64 {
+        self.forwarded.push((name, args.to_vec()));
+        0
+    }
+}
+
+/// Dispatch `syscall` with up to six arguments against `runtime`.
+fn call(runtime: &mut FakeRuntime, syscall: Syscall, args: &[u64]) -> SyscallResult {
+    let mut a = [0u64; 6];
+    a[..args.len()].copy_from_slice(args);
+    unsafe {
+        dispatch(runtime, SyscallRequest::new(syscall as u64, a[0], a[1], a[2], a[3], a[4]).with_arg6(a[5]))
+    }
+}
+
+// Linux errno values returned by the newer syscalls.
+const LINUX_ENOENT: i64 = -2;
+const LINUX_EIO: i64 = -5;
+const LINUX_ENOMEM: i64 = -12;
+const LINUX_EFAULT: i64 = -14;
+const LINUX_EINVAL: i64 = -22;
+const LINUX_EOVERFLOW: i64 = -75;
+
 impl SyscallRuntime for FakeRuntime {
-    fn trace(&mut self, syscall: Syscall) {
+    fn trace(&mut self, syscall: Syscall, _request: &SyscallRequest) {
         self.trace_log.push(syscall);
     }
 
+    fn trace_unknown(&mut self, _num: u64) {
+        self.trace_log.push(Syscall::Invalid);
+    }
+
     fn current_pid(&self) -> u64 {
         self.pid
     }
@@ -46,6 +85,130 @@ impl SyscallRuntime for FakeRuntime {
     fn exit(&mut self, code: i32) -> ! {
         panic!("unexpected exit({code}) in test runtime");
     }
+
+    fn user_page_mapped(&self, addr: u64) -> bool {
+        self.unmapped_page != Some(addr & !0xFFF)
+    }
+
+    fn mmap_impl(&mut self, addr: u64, len: u64, prot: u32, flags: u32, fd: i32, offset: u64) -> i64 {
+        self.forward("mmap", &[addr, len, prot as u64, flags as u64, fd as u64, offset]);
+        0x4000_0000
+    }
+
+    fn swapon_impl(&mut self, path: &[u8], flags: u32) -> i64 {
+        self.last_path = path.to_vec();
+        self.forward("swapon", &[flags as u64])
+    }
+
+    fn swapoff_impl(&mut self, path: &[u8]) -> i64 {
+        self.last_path = path.to_vec();
+        self.forward("swapoff", &[])
+    }
+
+    fn sigaltstack_impl(&mut self, ss_ptr: u64, old_ptr: u64) -> i64 {
+        self.forward("sigaltstack", &[ss_ptr, old_ptr])
+    }
+
+    fn getrlimit_impl(&mut self, resource: u32, rlim_ptr: u64) -> i64 {
+        self.forward("getrlimit", &[resource as u64, rlim_ptr])
+    }
+
+    fn setrlimit_impl(&mut self, resource: u32, rlim_ptr: u64) -> i64 {
+        self.forward("setrlimit", &[resource as u64, rlim_ptr])
+    }
+
+    fn prlimit64_impl(&mut self, pid: u32, resource: u32, new_ptr: u64, old_ptr: u64) -> i64 {
+        self.forward("prlimit64", &[pid as u64, resource as u64, new_ptr, old_ptr])
+    }
+
+    fn ptrace_impl(&mut self, request: i64, pid: u32, addr: u64, data: u64) -> i64 {
+        self.forward("ptrace", &[request as u64, pid as u64, addr, data])
+    }
+
+    fn systrace_impl(&mut self, op: u64, pid: u64) -> i64 {
+        self.forward("systrace", &[op, pid])
+    }
+
+    fn setitimer_impl(&mut self, which: u64, new_ptr: u64, old_ptr: u64) -> i64 {
+        self.forward("setitimer", &[which, new_ptr, old_ptr])
+    }
+
+    fn timer_create_impl(&mut self, clock_id: u64, sevp: u64, timerid_ptr: u64) -> i64 {
+        self.forward("timer_create", &[clock_id, sevp, timerid_ptr])
+    }
+
+    fn timer_settime_impl(&mut self, id: u64, flags: u64, new_ptr: u64, old_ptr: u64) -> i64 {
+        self.forward("timer_settime", &[id, flags, new_ptr, old_ptr])
+    }
+
+    fn timerfd_create_impl(&mut self, clock_id: u64, flags: u64) -> i64 {
+        self.forward("timerfd_create", &[clock_id, flags])
+    }
+
+    fn eventfd2_impl(&mut self, initval: u64, flags: u64) -> i64 {
+        self.forward("eventfd2", &[initval, flags])
+    }
+
+    fn signalfd4_impl(&mut self, fd: i32, mask_ptr: u64, sizemask: u64, flags: u64) -> i64 {
+        self.forward("signalfd4", &[fd as u64, mask_ptr, sizemask, flags])
+    }
+
+    fn setgroups_impl(&mut self, size: u64, list_ptr: u64) -> i64 {
+        self.forward("setgroups", &[size, list_ptr])
+    }
+
+    fn setfsuid_impl(&mut self, uid: u32) -> i64 {
+        self.forward("setfsuid", &[uid as u64])
+    }
+
+    fn setfsgid_impl(&mut self, gid: u32) -> i64 {
+        self.forward("setfsgid", &[gid as u64])
+    }
+
+    // The caller leads its own session; no other process exists.
+    fn getsid_impl(&mut self, pid: u32) -> i64 {
+        self.forward("getsid", &[pid as u64]);
+        if pid == 0 || pid as u64 == self.pid { self.pid as i64 } else { -3 } // ESRCH
+    }
+
+    fn reboot_impl(&mut self, cmd: u32) -> i64 {
+        self.forward("reboot", &[cmd as u64])
+    }
+
+    fn getrandom_impl(&mut self, buf: &mut [u8], flags: u32) -> i64 {
+        buf.fill(0xA5);
+        self.forward("getrandom", &[buf.len() as u64, flags as u64]);
+        buf.len() as i64
+    }
+
+    fn loadkeys_impl(&mut self, name: &[u8]) -> i64 {
+        self.last_path = name.to_vec();
+        self.forward("loadkeys", &[name.len() as u64])
+    }
+
+    fn epoll_create1_impl(&mut self, flags: u64) -> i64 {
+        self.forward("epoll_create1", &[flags])
+    }
+
+    fn epoll_ctl_impl(&mut self, epfd: i32, op: u64, fd: i32, event_ptr: u64) -> i64 {
+        self.forward("epoll_ctl", &[epfd as u64, op, fd as u64, event_ptr])
+    }
+
+    fn epoll_pwait_impl(&mut self, epfd: i32, events_ptr: u64, maxevents: i64, timeout_ms: i64,
+                        sigmask_ptr: u64, sigsetsize: u64) -> i64 {
+        self.forward("epoll_pwait", &[epfd as u64, events_ptr, maxevents as u64, timeout_ms as u64,
+                                      sigmask_ptr, sigsetsize])
+    }
+
+    // No FD table: stdout/stderr fall back to the console, other fds are bad.
+    fn fs_write_file(&mut self, _fd: i32, _buf: &[u8]) -> i64 {
+        FS_EBADF
+    }
+
+    fn select_impl(&mut self, _nfds: u64, _read_ptr: u64, _write_ptr: u64,
+                   _except_ptr: u64, _timeout_ptr: u64) -> i64 {
+        0
+    }
 }
 
 #[test]
@@ -77,7 +240,7 @@ fn write_rejects_bad_file_descriptor() {
         )
     };
 
-    assert_eq!(result, SyscallResult::err(EBADF));
+    assert_eq!(result, SyscallResult::err(FS_EBADF));
     assert!(runtime.output.is_empty());
 }
 
@@ -191,3 +354,755 @@ fn validate_user_range_catches_overflow() {
     let err = validate_user_range(u64::MAX - 1, 8).unwrap_err();
     assert_eq!(err, EINVAL);
 }
+
+const PROT_READ: u64 = 0x1;
+const MAP_SHARED: u64 = 0x01;
+const MAP_PRIVATE: u64 = 0x02;
+const MAP_FIXED: u64 = 0x10;
+const MAP_ANONYMOUS: u64 = 0x20;
+
+#[test]
+fn mmap_forwards_file_mapping() {
+    let mut runtime = FakeRuntime::default();
+
+    let result = call(&mut runtime, Syscall::Mmap, &[0, 8192, PROT_READ, MAP_SHARED, 3, 4096]);
+
+    assert_eq!(result, SyscallResult::ok(0x4000_0000));
+    assert_eq!(runtime.forwarded, vec![("mmap", vec![0, 8192, PROT_READ, MAP_SHARED, 3, 4096])]);
+}
+
+#[test]
+fn mmap_rejects_empty_length_and_unaligned_offset() {
+    let mut runtime = FakeRuntime::default();
+
+    let empty = call(&mut runtime, Syscall::Mmap, &[0, 0, PROT_READ, MAP_PRIVATE | MAP_ANONYMOUS, u64::MAX, 0]);
+    let unaligned = call(&mut runtime, Syscall::Mmap, &[0, 4096, PROT_READ, MAP_SHARED, 3, 100]);
+
+    assert_eq!(empty, SyscallResult::err(LINUX_EINVAL));
+    assert_eq!(unaligned, SyscallResult::err(LINUX_EINVAL));
+    assert!(runtime.forwarded.is_empty());
+}
+
+#[test]
+fn mmap_requires_exactly_one_mapping_type() {
+    let mut runtime = FakeRuntime::default();
+
+    let none = call(&mut runtime, Syscall::Mmap, &[0, 4096, PROT_READ, MAP_ANONYMOUS, u64::MAX, 0]);
+    let bogus = call(&mut runtime, Syscall::Mmap, &[0, 4096, PROT_READ, 0x0F | MAP_ANONYMOUS, u64::MAX, 0]);
+
+    assert_eq!(none, SyscallResult::err(LINUX_EINVAL));
+    assert_eq!(bogus, SyscallResult::err(LINUX_EINVAL));
+    assert!(runtime.forwarded.is_empty());
+}
+
+#[test]
+fn mmap_rejects_lengths_beyond_user_space() {
+    let mut runtime = FakeRuntime::default();
+
+    let result = call(&mut runtime, Syscall::Mmap, &[0, u64::MAX, PROT_READ, MAP_PRIVATE | MAP_ANONYMOUS, u64::MAX, 0]);
+
+    assert_eq!(result, SyscallResult::err(LINUX_ENOMEM));
+    assert!(runtime.forwarded.is_empty());
+}
+
+#[test]
+fn mmap_rejects_file_offset_that_wraps() {
+    let mut runtime = FakeRuntime::default();
+    let last_page = u64::MAX & !0xFFF;
+
+    let result = call(&mut runtime, Syscall::Mmap, &[0, 8192, PROT_READ, MAP_SHARED, 3, last_page]);
+
+    assert_eq!(result, SyscallResult::err(LINUX_EOVERFLOW));
+    assert!(runtime.forwarded.is_empty());
+}
+
+#[test]
+fn mmap_fixed_requires_aligned_user_address() {
+    let mut runtime = FakeRuntime::default();
+    let flags = MAP_PRIVATE | MAP_ANONYMOUS | MAP_FIXED;
+
+    let unaligned = call(&mut runtime, Syscall::Mmap, &[0x1000_0010, 4096, PROT_READ, flags, u64::MAX, 0]);
+    let past_user = call(&mut runtime, Syscall::Mmap, &[0x7FFF_FFFF_F000, 8192, PROT_READ, flags, u64::MAX, 0]);
+    let kernel = call(&mut runtime, Syscall::Mmap, &[0xFFFF_8000_0000_0000, 4096, PROT_READ, flags, u64::MAX, 0]);
+
+    assert_eq!(unaligned, SyscallResult::err(LINUX_EINVAL));
+    assert_eq!(past_user, SyscallResult::err(LINUX_EINVAL));
+    assert_eq!(kernel, SyscallResult::err(LINUX_EINVAL));
+    assert!(runtime.forwarded.is_empty());
+}
+
+const SWAP_FLAG_PREFER: u64 = 0x8000;
+
+#[test]
+fn swapon_forwards_path_without_nul_and_priority() {
+    let mut runtime = FakeRuntime::default();
+    let path = *b"/dev/hda2\0";
+
+    let result = call(&mut runtime, Syscall::Swapon, &[path.as_ptr() as u64, SWAP_FLAG_PREFER | 5]);
+
+    assert_eq!(result, SyscallResult::ok(0));
+    assert_eq!(runtime.forwarded, vec![("swapon", vec![SWAP_FLAG_PREFER | 5])]);
+    assert_eq!(runtime.last_path, b"/dev/hda2");
+}
+
+#[test]
+fn swapon_rejects_unknown_flags() {
+    let mut runtime = FakeRuntime::default();
+    let path = *b"/dev/hda2\0";
+
+    let result = call(&mut runtime, Syscall::Swapon, &[path.as_ptr() as u64, 0x8_0000]);
+
+    assert_eq!(result, SyscallResult::err(LINUX_EINVAL));
+    assert!(runtime.forwarded.is_empty());
+}
+
+#[test]
+fn swapoff_rejects_empty_path() {
+    let mut runtime = FakeRuntime::default();
+    let path = *b"\0";
+
+    let result = call(&mut runtime, Syscall::Swapoff, &[path.as_ptr() as u64]);
+
+    assert_eq!(result, SyscallResult::err(LINUX_ENOENT));
+    assert!(runtime.forwarded.is_empty());
+}
+
+#[test]
+fn swapon_faults_on_bad_path_pointer() {
+    let mut runtime = FakeRuntime { unmapped_page: Some(0x7000_0000), ..FakeRuntime::default() };
+
+    let null = call(&mut runtime, Syscall::Swapon, &[0, 0]);
+    let kernel = call(&mut runtime, Syscall::Swapon, &[0xFFFF_8000_0000_1000, 0]);
+    let unmapped = call(&mut runtime, Syscall::Swapon, &[0x7000_0010, 0]);
+
+    assert_eq!(null, SyscallResult::err(LINUX_EFAULT));
+    assert_eq!(kernel, SyscallResult::err(LINUX_EFAULT));
+    assert_eq!(unmapped, SyscallResult::err(LINUX_EFAULT));
+    assert!(runtime.forwarded.is_empty());
+}
+
+const SS_DISABLE: u64 = 2;
+const STACK_BASE: u64 = 0x10_0000;
+
+#[test]
+fn sigaltstack_forwards_valid_stack() {
+    let mut runtime = FakeRuntime::default();
+    // stack_t { ss_sp, ss_flags, ss_size }
+    let ss: [u64; 3] = [STACK_BASE, 0, 8192];
+    let old = [0u64; 3];
+
+    let result = call(&mut runtime, Syscall::Sigaltstack, &[ss.as_ptr() as u64, old.as_ptr() as u64]);
+
+    assert_eq!(result, SyscallResult::ok(0));
+    assert_eq!(runtime.forwarded, vec![("sigaltstack", vec![ss.as_ptr() as u64, old.as_ptr() as u64])]);
+}
+
+#[test]
+fn sigaltstack_rejects_unknown_flags() {
+    let mut runtime = FakeRuntime::default();
+    let ss: [u64; 3] = [STACK_BASE, 0x10, 8192];
+
+    let result = call(&mut runtime, Syscall::Sigaltstack, &[ss.as_ptr() as u64]);
+
+    assert_eq!(result, SyscallResult::err(LINUX_EINVAL));
+    assert!(runtime.forwarded.is_empty());
+}
+
+#[test]
+fn sigaltstack_rejects_stack_below_minsigstksz() {
+    let mut runtime = FakeRuntime::default();
+    let ss: [u64; 3] = [STACK_BASE, 0, 1024];
+
+    let result = call(&mut runtime, Syscall::Sigaltstack, &[ss.as_ptr() as u64]);
+
+    assert_eq!(result, SyscallResult::err(LINUX_ENOMEM));
+    assert!(runtime.forwarded.is_empty());
+}
+
+#[test]
+fn sigaltstack_disable_ignores_size() {
+    let mut runtime = FakeRuntime::default();
+    let ss: [u64; 3] = [0, SS_DISABLE, 0];
+
+    let result = call(&mut runtime, Syscall::Sigaltstack, &[ss.as_ptr() as u64]);
+
+    assert_eq!(result, SyscallResult::ok(0));
+    assert_eq!(runtime.forwarded.len(), 1);
+}
+
+#[test]
+fn sigaltstack_faults_on_kernel_pointers() {
+    let mut runtime = FakeRuntime::default();
+    let kernel_stack: [u64; 3] = [0xFFFF_8000_0000_0000, 0, 8192];
+
+    let bad_ss = call(&mut runtime, Syscall::Sigaltstack, &[0xFFFF_8000_0000_0000]);
+    let bad_old = call(&mut runtime, Syscall::Sigaltstack, &[0, 0xFFFF_8000_0000_0000]);
+    let bad_sp = call(&mut runtime, Syscall::Sigaltstack, &[kernel_stack.as_ptr() as u64]);
+
+    assert_eq!(bad_ss, SyscallResult::err(LINUX_EFAULT));
+    assert_eq!(bad_old, SyscallResult::err(LINUX_EFAULT));
+    assert_eq!(bad_sp, SyscallResult::err(LINUX_EFAULT));
+    assert!(runtime.forwarded.is_empty());
+}
+
+const RLIMIT_CORE: u64 = 4;
+
+#[test]
+fn setrlimit_forwards_core_limit() {
+    let mut runtime = FakeRuntime::default();
+    // struct rlimit { rlim_cur, rlim_max }
+    let lim: [u64; 2] = [0, u64::MAX];
+
+    let result = call(&mut runtime, Syscall::Setrlimit, &[RLIMIT_CORE, lim.as_ptr() as u64]);
+
+    assert_eq!(result, SyscallResult::ok(0));
+    assert_eq!(runtime.forwarded, vec![("setrlimit", vec![RLIMIT_CORE, lim.as_ptr() as u64])]);
+}
+
+#[test]
+fn setrlimit_rejects_soft_limit_above_hard() {
+    let mut runtime = FakeRuntime::default();
+    let lim: [u64; 2] = [4096, 1024];
+
+    let result = call(&mut runtime, Syscall::Setrlimit, &[RLIMIT_CORE, lim.as_ptr() as u64]);
+
+    assert_eq!(result, SyscallResult::err(LINUX_EINVAL));
+    assert!(runtime.forwarded.is_empty());
+}
+
+#[test]
+fn getrlimit_rejects_unknown_resource() {
+    let mut runtime = FakeRuntime::default();
+    let mut lim = [0u64; 2];
+
+    let result = call(&mut runtime, Syscall::Getrlimit, &[16, lim.as_mut_ptr() as u64]);
+
+    assert_eq!(result, SyscallResult::err(LINUX_EINVAL));
+    assert!(runtime.forwarded.is_empty());
+}
+
+#[test]
+fn getrlimit_faults_on_null_or_kernel_pointer() {
+    let mut runtime = FakeRuntime::default();
+
+    let null = call(&mut runtime, Syscall::Getrlimit, &[RLIMIT_CORE, 0]);
+    let kernel = call(&mut runtime, Syscall::Getrlimit, &[RLIMIT_CORE, 0xFFFF_8000_0000_0000]);
+
+    assert_eq!(null, SyscallResult::err(LINUX_EFAULT));
+    assert_eq!(kernel, SyscallResult::err(LINUX_EFAULT));
+    assert!(runtime.forwarded.is_empty());
+}
+
+#[test]
+fn prlimit64_checks_both_limit_pointers() {
+    let mut runtime = FakeRuntime::default();
+    let lim: [u64; 2] = [0, 0];
+
+    let bad_old = call(&mut runtime, Syscall::Prlimit64, &[0, RLIMIT_CORE, lim.as_ptr() as u64, 0xFFFF_8000_0000_0000]);
+    let query = call(&mut runtime, Syscall::Prlimit64, &[7, RLIMIT_CORE, 0, lim.as_ptr() as u64]);
+
+    assert_eq!(bad_old, SyscallResult::err(LINUX_EFAULT));
+    assert_eq!(query, SyscallResult::ok(0));
+    assert_eq!(runtime.forwarded, vec![("prlimit64", vec![7, RLIMIT_CORE, 0, lim.as_ptr() as u64])]);
+}
+
+const PTRACE_PEEKUSER: u64 = 3;
+const PTRACE_GETREGS: u64 = 12;
+const PTRACE_ATTACH: u64 = 16;
+const PTRACE_SETOPTIONS: u64 = 0x4200;
+const PTRACE_O_TRACESYSGOOD: u64 = 1;
+
+#[test]
+fn ptrace_forwards_attach_and_supported_options() {
+    let mut runtime = FakeRuntime::default();
+
+    let attach = call(&mut runtime, Syscall::Ptrace, &[PTRACE_ATTACH, 42]);
+    let options = call(&mut runtime, Syscall::Ptrace, &[PTRACE_SETOPTIONS, 42, 0, PTRACE_O_TRACESYSGOOD]);
+
+    assert_eq!(attach, SyscallResult::ok(0));
+    assert_eq!(options, SyscallResult::ok(0));
+    assert_eq!(runtime.forwarded, vec![
+        ("ptrace", vec![PTRACE_ATTACH, 42, 0, 0]),
+        ("ptrace", vec![PTRACE_SETOPTIONS, 42, 0, PTRACE_O_TRACESYSGOOD]),
+    ]);
+}
+
+#[test]
+fn ptrace_rejects_unknown_request() {
+    let mut runtime = FakeRuntime::default();
+
+    let result = call(&mut runtime, Syscall::Ptrace, &[0x4206, 42]); // PTRACE_SEIZE
+
+    assert_eq!(result, SyscallResult::err(LINUX_EIO));
+    assert!(runtime.forwarded.is_empty());
+}
+
+#[test]
+fn ptrace_peekuser_requires_aligned_register_offset() {
+    let mut runtime = FakeRuntime::default();
+    let mut word = 0u64;
+    let data = &mut word as *mut u64 as u64;
+
+    let unaligned = call(&mut runtime, Syscall::Ptrace, &[PTRACE_PEEKUSER, 42, 4, data]);
+    let past_regs = call(&mut runtime, Syscall::Ptrace, &[PTRACE_PEEKUSER, 42, 27 * 8, data]);
+    let orig_rax = call(&mut runtime, Syscall::Ptrace, &[PTRACE_PEEKUSER, 42, 15 * 8, data]);
+
+    assert_eq!(unaligned, SyscallResult::err(LINUX_EIO));
+    assert_eq!(past_regs, SyscallResult::err(LINUX_EIO));
+    assert_eq!(orig_rax, SyscallResult::ok(0));
+    assert_eq!(runtime.forwarded.len(), 1);
+}
+
+#[test]
+fn ptrace_getregs_faults_on_kernel_buffer() {
+    let mut runtime = FakeRuntime::default();
+
+    let result = call(&mut runtime, Syscall::Ptrace, &[PTRACE_GETREGS, 42, 0, 0xFFFF_8000_0000_0000]);
+
+    assert_eq!(result, SyscallResult::err(LINUX_EFAULT));
+    assert!(runtime.forwarded.is_empty());
+}
+
+#[test]
+fn ptrace_setoptions_rejects_unsupported_bits() {
+    let mut runtime = FakeRuntime::default();
+
+    let seccomp = call(&mut runtime, Syscall::Ptrace, &[PTRACE_SETOPTIONS, 42, 0, 0x80]); // TRACESECCOMP
+    let wide = call(&mut runtime, Syscall::Ptrace, &[PTRACE_SETOPTIONS, 42, 0, 1 << 32]);
+
+    assert_eq!(seccomp, SyscallResult::err(LINUX_EINVAL));
+    assert_eq!(wide, SyscallResult::err(LINUX_EINVAL));
+    assert!(runtime.forwarded.is_empty());
+}
+
+const SYSTRACE_ALL: u64 = 1;
+const SYSTRACE_ADD_PID: u64 = 2;
+const SYSTRACE_DEL_PID: u64 = 3;
+
+#[test]
+fn systrace_forwards_known_operations() {
+    let mut runtime = FakeRuntime::default();
+
+    let all = call(&mut runtime, Syscall::SysTrace, &[SYSTRACE_ALL]);
+    let add = call(&mut runtime, Syscall::SysTrace, &[SYSTRACE_ADD_PID, 7]);
+
+    assert_eq!(all, SyscallResult::ok(0));
+    assert_eq!(add, SyscallResult::ok(0));
+    assert_eq!(runtime.forwarded, vec![("systrace", vec![SYSTRACE_ALL, 0]), ("systrace", vec![SYSTRACE_ADD_PID, 7])]);
+    assert_eq!(runtime.trace_log, vec![Syscall::SysTrace, Syscall::SysTrace]);
+}
+
+#[test]
+fn systrace_rejects_unknown_operation() {
+    let mut runtime = FakeRuntime::default();
+
+    let result = call(&mut runtime, Syscall::SysTrace, &[5]);
+
+    assert_eq!(result, SyscallResult::err(LINUX_EINVAL));
+    assert!(runtime.forwarded.is_empty());
+}
+
+#[test]
+fn systrace_pid_operations_require_a_valid_pid() {
+    let mut runtime = FakeRuntime::default();
+
+    let zero = call(&mut runtime, Syscall::SysTrace, &[SYSTRACE_ADD_PID, 0]);
+    let wide = call(&mut runtime, Syscall::SysTrace, &[SYSTRACE_DEL_PID, 256]);
+
+    assert_eq!(zero, SyscallResult::err(LINUX_EINVAL));
+    assert_eq!(wide, SyscallResult::err(LINUX_EINVAL));
+    assert!(runtime.forwarded.is_empty());
+}
+
+const CLOCK_MONOTONIC: u64 = 1;
+
+/// A `struct sigevent` prefix: value, signo, notify, tid.
+fn sigevent(signo: i32, notify: i32) -> [i32; 6] {
+    [0, 0, signo, notify, 0, 0]
+}
+
+#[test]
+fn setitimer_forwards_a_valid_interval_for_each_timer() {
+    let mut runtime = FakeRuntime::default();
+    let new: [i64; 4] = [0, 0, 1, 500_000];
+    let new_ptr = new.as_ptr() as u64;
+
+    for which in [ITIMER_REAL, ITIMER_VIRTUAL, ITIMER_PROF] {
+        let result = call(&mut runtime, Syscall::Setitimer, &[which as u64, new_ptr, 0]);
+        assert_eq!(result, SyscallResult::ok(0));
+    }
+
+    assert_eq!(runtime.forwarded, vec![
+        ("setitimer", vec![0, new_ptr, 0]),
+        ("setitimer", vec![1, new_ptr, 0]),
+        ("setitimer", vec![2, new_ptr, 0]),
+    ]);
+}
+
+#[test]
+fn setitimer_rejects_unknown_timer_and_bad_microseconds() {
+    let mut runtime = FakeRuntime::default();
+    let new: [i64; 4] = [0, 0, 1, 1_000_000];
+
+    let which = call(&mut runtime, Syscall::Setitimer, &[3, 0, 0]);
+    let usec = call(&mut runtime, Syscall::Setitimer, &[ITIMER_PROF as u64, new.as_ptr() as u64, 0]);
+    let old = call(&mut runtime, Syscall::Setitimer, &[ITIMER_PROF as u64, 0, 0xFFFF_8000_0000_0000]);
+
+    assert_eq!(which, SyscallResult::err(LINUX_EINVAL));
+    assert_eq!(usec, SyscallResult::err(LINUX_EINVAL));
+    assert_eq!(old, SyscallResult::err(LINUX_EFAULT));
+    assert!(runtime.forwarded.is_empty());
+}
+
+#[test]
+fn timer_create_checks_clock_and_sigevent() {
+    let mut runtime = FakeRuntime::default();
+    let mut id = 0i32;
+    let id_ptr = &mut id as *mut i32 as u64;
+    let kill = sigevent(9, SIGEV_SIGNAL);
+    let thread = sigevent(10, 2); // SIGEV_THREAD
+    let usr1 = sigevent(10, SIGEV_SIGNAL);
+
+    let clock = call(&mut runtime, Syscall::TimerCreate, &[8, 0, id_ptr]);
+    let sigkill = call(&mut runtime, Syscall::TimerCreate, &[CLOCK_MONOTONIC, kill.as_ptr() as u64, id_ptr]);
+    let notify = call(&mut runtime, Syscall::TimerCreate, &[CLOCK_MONOTONIC, thread.as_ptr() as u64, id_ptr]);
+    let no_id = call(&mut runtime, Syscall::TimerCreate, &[CLOCK_MONOTONIC, 0, 0]);
+    let ok = call(&mut runtime, Syscall::TimerCreate, &[CLOCK_MONOTONIC, usr1.as_ptr() as u64, id_ptr]);
+
+    assert_eq!(clock, SyscallResult::err(LINUX_EINVAL));
+    assert_eq!(sigkill, SyscallResult::err(LINUX_EINVAL));
+    assert_eq!(notify, SyscallResult::err(LINUX_EINVAL));
+    assert_eq!(no_id, SyscallResult::err(LINUX_EFAULT));
+    assert_eq!(ok, SyscallResult::ok(0));
+    assert_eq!(runtime.forwarded, vec![("timer_create", vec![CLOCK_MONOTONIC, usr1.as_ptr() as u64, id_ptr])]);
+}
+
+#[test]
+fn timer_settime_rejects_unknown_flags_and_bad_nanoseconds() {
+    let mut runtime = FakeRuntime::default();
+    let good: [i64; 4] = [0, 0, 1, 0];
+    let bad: [i64; 4] = [0, 1_000_000_000, 1, 0];
+
+    let flags = call(&mut runtime, Syscall::TimerSettime, &[0, 2, good.as_ptr() as u64, 0]);
+    let nsec = call(&mut runtime, Syscall::TimerSettime, &[0, 0, bad.as_ptr() as u64, 0]);
+    let abs = call(&mut runtime, Syscall::TimerSettime, &[0, TIMER_ABSTIME, good.as_ptr() as u64, 0]);
+
+    assert_eq!(flags, SyscallResult::err(LINUX_EINVAL));
+    assert_eq!(nsec, SyscallResult::err(LINUX_EINVAL));
+    assert_eq!(abs, SyscallResult::ok(0));
+    assert_eq!(runtime.forwarded, vec![("timer_settime", vec![0, TIMER_ABSTIME, good.as_ptr() as u64, 0])]);
+}
+
+#[test]
+fn timerfd_create_rejects_cpu_clocks_and_unknown_flags() {
+    let mut runtime = FakeRuntime::default();
+
+    let clock = call(&mut runtime, Syscall::TimerfdCreate, &[2, 0]); // CLOCK_PROCESS_CPUTIME_ID
+    let flags = call(&mut runtime, Syscall::TimerfdCreate, &[CLOCK_MONOTONIC, 1]);
+    let ok = call(&mut runtime, Syscall::TimerfdCreate, &[CLOCK_MONOTONIC, O_NONBLOCK | O_CLOEXEC]);
+
+    assert_eq!(clock, SyscallResult::err(LINUX_EINVAL));
+    assert_eq!(flags, SyscallResult::err(LINUX_EINVAL));
+    assert_eq!(ok, SyscallResult::ok(0));
+    assert_eq!(runtime.forwarded, vec![("timerfd_create", vec![CLOCK_MONOTONIC, O_NONBLOCK | O_CLOEXEC])]);
+}
+
+#[test]
+fn eventfd2_rejects_unknown_flags() {
+    let mut runtime = FakeRuntime::default();
+
+    let bad = call(&mut runtime, Syscall::Eventfd2, &[0, 2]);
+    let semaphore = call(&mut runtime, Syscall::Eventfd2, &[3, EFD_SEMAPHORE | O_CLOEXEC]);
+
+    assert_eq!(bad, SyscallResult::err(LINUX_EINVAL));
+    assert_eq!(semaphore, SyscallResult::ok(0));
+    assert_eq!(runtime.forwarded, vec![("eventfd2", vec![3, EFD_SEMAPHORE | O_CLOEXEC])]);
+}
+
+#[test]
+fn signalfd4_checks_mask_size_and_pointer() {
+    let mut runtime = FakeRuntime::default();
+    let mask = 1u64 << 9;
+    let mask_ptr = &mask as *const u64 as u64;
+
+    let size = call(&mut runtime, Syscall::Signalfd4, &[u64::MAX, mask_ptr, 4, 0]);
+    let kernel = call(&mut runtime, Syscall::Signalfd4, &[u64::MAX, 0xFFFF_8000_0000_0000, 8, 0]);
+    let flags = call(&mut runtime, Syscall::Signalfd4, &[u64::MAX, mask_ptr, 8, 1]);
+    let ok = call(&mut runtime, Syscall::Signalfd4, &[u64::MAX, mask_ptr, 8, O_NONBLOCK]);
+
+    assert_eq!(size, SyscallResult::err(LINUX_EINVAL));
+    assert_eq!(kernel, SyscallResult::err(LINUX_EFAULT));
+    assert_eq!(flags, SyscallResult::err(LINUX_EINVAL));
+    assert_eq!(ok, SyscallResult::ok(0));
+    assert_eq!(runtime.forwarded, vec![("signalfd4", vec![u64::MAX, mask_ptr, 8, O_NONBLOCK])]);
+}
+
+#[test]
+fn epoll_create1_accepts_only_cloexec() {
+    let mut runtime = FakeRuntime::default();
+
+    let nonblock = call(&mut runtime, Syscall::EpollCreate1, &[O_NONBLOCK]);
+    let cloexec = call(&mut runtime, Syscall::EpollCreate1, &[O_CLOEXEC]);
+
+    assert_eq!(nonblock, SyscallResult::err(LINUX_EINVAL));
+    assert_eq!(cloexec, SyscallResult::ok(0));
+    assert_eq!(runtime.forwarded, vec![("epoll_create1", vec![O_CLOEXEC])]);
+}
+
+#[test]
+fn epoll_create_rejects_non_positive_size() {
+    let mut runtime = FakeRuntime::default();
+
+    let zero = call(&mut runtime, Syscall::EpollCreate, &[0]);
+    let negative = call(&mut runtime, Syscall::EpollCreate, &[-1i64 as u64]);
+    let one = call(&mut runtime, Syscall::EpollCreate, &[1]);
+
+    assert_eq!(zero, SyscallResult::err(LINUX_EINVAL));
+    assert_eq!(negative, SyscallResult::err(LINUX_EINVAL));
+    assert_eq!(one, SyscallResult::ok(0));
+    assert_eq!(runtime.forwarded, vec![("epoll_create1", vec![0])]);
+}
+
+#[test]
+fn epoll_ctl_checks_op_and_event_pointer() {
+    let mut runtime = FakeRuntime::default();
+    let event = [0u8; 12];
+    let event_ptr = event.as_ptr() as u64;
+    let kernel = 0xFFFF_8000_0000_0000;
+
+    let op = call(&mut runtime, Syscall::EpollCtl, &[3, 4, 0, event_ptr]);
+    let add = call(&mut runtime, Syscall::EpollCtl, &[3, EPOLL_CTL_ADD, 0, kernel]);
+    let del = call(&mut runtime, Syscall::EpollCtl, &[3, EPOLL_CTL_DEL, 0, kernel]);
+    let modify = call(&mut runtime, Syscall::EpollCtl, &[3, EPOLL_CTL_MOD, 0, event_ptr]);
+
+    assert_eq!(op, SyscallResult::err(LINUX_EINVAL));
+    assert_eq!(add, SyscallResult::err(LINUX_EFAULT));
+    assert_eq!(del, SyscallResult::ok(0));
+    assert_eq!(modify, SyscallResult::ok(0));
+    assert_eq!(runtime.forwarded, vec![
+        ("epoll_ctl", vec![3, EPOLL_CTL_DEL, 0, kernel]),
+        ("epoll_ctl", vec![3, EPOLL_CTL_MOD, 0, event_ptr]),
+    ]);
+}
+
+#[test]
+fn epoll_wait_checks_maxevents_and_event_buffer() {
+    let mut runtime = FakeRuntime::default();
+    let events = [0u8; 4 * 12];
+    let events_ptr = events.as_ptr() as u64;
+
+    let zero = call(&mut runtime, Syscall::EpollWait, &[3, events_ptr, 0, 0]);
+    let negative = call(&mut runtime, Syscall::EpollWait, &[3, events_ptr, -1i32 as u32 as u64, 0]);
+    let kernel = call(&mut runtime, Syscall::EpollWait, &[3, 0xFFFF_8000_0000_0000, 4, 0]);
+    let ok = call(&mut runtime, Syscall::EpollWait, &[3, events_ptr, 4, -1i32 as u32 as u64]);
+
+    assert_eq!(zero, SyscallResult::err(LINUX_EINVAL));
+    assert_eq!(negative, SyscallResult::err(LINUX_EINVAL));
+    assert_eq!(kernel, SyscallResult::err(LINUX_EFAULT));
+    assert_eq!(ok, SyscallResult::ok(0));
+    assert_eq!(runtime.forwarded, vec![("epoll_pwait", vec![3, events_ptr, 4, -1i64 as u64, 0, 0])]);
+}
+
+#[test]
+fn epoll_pwait_checks_signal_mask() {
+    let mut runtime = FakeRuntime::default();
+    let events = [0u8; 12];
+    let events_ptr = events.as_ptr() as u64;
+    let mask = 0u64;
+    let mask_ptr = &mask as *const u64 as u64;
+
+    let size = call(&mut runtime, Syscall::EpollPwait, &[3, events_ptr, 1, 0, mask_ptr, 4]);
+    let kernel = call(&mut runtime, Syscall::EpollPwait, &[3, events_ptr, 1, 0, 0xFFFF_8000_0000_0000, 8]);
+    let ok = call(&mut runtime, Syscall::EpollPwait, &[3, events_ptr, 1, 0, mask_ptr, 8]);
+
+    assert_eq!(size, SyscallResult::err(LINUX_EINVAL));
+    assert_eq!(kernel, SyscallResult::err(LINUX_EFAULT));
+    assert_eq!(ok, SyscallResult::ok(0));
+    assert_eq!(runtime.forwarded, vec![("epoll_pwait", vec![3, events_ptr, 1, 0, mask_ptr, 8])]);
+}
+
+#[test]
+fn setgroups_forwards_a_valid_list() {
+    let mut runtime = FakeRuntime::default();
+    let groups: [u32; 3] = [10, 20, 30];
+    let list = groups.as_ptr() as u64;
+
+    let clear = call(&mut runtime, Syscall::Setgroups, &[0, 0]);
+    let three = call(&mut runtime, Syscall::Setgroups, &[3, list]);
+
+    assert_eq!(clear, SyscallResult::ok(0));
+    assert_eq!(three, SyscallResult::ok(0));
+    assert_eq!(runtime.forwarded, vec![("setgroups", vec![0, 0]), ("setgroups", vec![3, list])]);
+}
+
+#[test]
+fn setgroups_rejects_too_many_groups() {
+    let mut runtime = FakeRuntime::default();
+    let groups = [0u32; NGROUPS_MAX + 1];
+
+    let result = call(&mut runtime, Syscall::Setgroups, &[NGROUPS_MAX as u64 + 1, groups.as_ptr() as u64]);
+
+    assert_eq!(result, SyscallResult::err(LINUX_EINVAL));
+    assert!(runtime.forwarded.is_empty());
+}
+
+#[test]
+fn setgroups_faults_on_bad_list_pointer() {
+    let mut runtime = FakeRuntime::default();
+
+    let null = call(&mut runtime, Syscall::Setgroups, &[1, 0]);
+    let kernel = call(&mut runtime, Syscall::Setgroups, &[2, 0xFFFF_8000_0000_0000]);
+
+    assert_eq!(null, SyscallResult::err(LINUX_EFAULT));
+    assert_eq!(kernel, SyscallResult::err(LINUX_EFAULT));
+    assert!(runtime.forwarded.is_empty());
+}
+
+#[test]
+fn setfsuid_and_setfsgid_pass_32_bit_ids() {
+    let mut runtime = FakeRuntime::default();
+
+    call(&mut runtime, Syscall::Setfsuid, &[0xFFFF_FFFF_0000_03E8]);
+    call(&mut runtime, Syscall::Setfsgid, &[u64::MAX]); // -1: query only
+
+    assert_eq!(runtime.forwarded, vec![("setfsuid", vec![1000]), ("setfsgid", vec![u32::MAX as u64])]);
+}
+
+#[test]
+fn getsid_returns_session_or_runtime_error() {
+    let mut runtime = FakeRuntime { pid: 7, ..FakeRuntime::default() };
+
+    let own = call(&mut runtime, Syscall::Getsid, &[0]);
+    let other = call(&mut runtime, Syscall::Getsid, &[99]);
+
+    assert_eq!(own, SyscallResult::ok(7));
+    assert_eq!(other, SyscallResult::err(-3));
+    assert_eq!(runtime.forwarded, vec![("getsid", vec![0]), ("getsid", vec![99])]);
+}
+
+const GRND_NONBLOCK: u64 = 1;
+const GRND_RANDOM: u64 = 2;
+const GRND_INSECURE: u64 = 4;
+
+#[test]
+fn getrandom_fills_the_user_buffer() {
+    let mut runtime = FakeRuntime::default();
+    let mut buf = [0u8; 16];
+
+    let result = call(&mut runtime, Syscall::Getrandom, &[buf.as_mut_ptr() as u64, 16, GRND_NONBLOCK]);
+
+    assert_eq!(result, SyscallResult::ok(16));
+    assert_eq!(buf, [0xA5; 16]);
+    assert_eq!(runtime.forwarded, vec![("getrandom", vec![16, GRND_NONBLOCK])]);
+}
+
+#[test]
+fn getrandom_rejects_unknown_or_conflicting_flags() {
+    let mut runtime = FakeRuntime::default();
+    let mut buf = [0u8; 4];
+    let ptr = buf.as_mut_ptr() as u64;
+
+    let unknown = call(&mut runtime, Syscall::Getrandom, &[ptr, 4, 8]);
+    let both = call(&mut runtime, Syscall::Getrandom, &[ptr, 4, GRND_RANDOM | GRND_INSECURE]);
+
+    assert_eq!(unknown, SyscallResult::err(LINUX_EINVAL));
+    assert_eq!(both, SyscallResult::err(LINUX_EINVAL));
+    assert!(runtime.forwarded.is_empty());
+}
+
+#[test]
+fn getrandom_faults_on_kernel_buffer() {
+    let mut runtime = FakeRuntime::default();
+
+    let result = call(&mut runtime, Syscall::Getrandom, &[0xFFFF_8000_0000_0000, 16, 0]);
+
+    assert_eq!(result, SyscallResult::err(LINUX_EFAULT));
+    assert!(runtime.forwarded.is_empty());
+}
+
+#[test]
+fn reboot_forwards_each_command_with_any_second_magic() {
+    let mut runtime = FakeRuntime::default();
+    let cmds = [LINUX_REBOOT_CMD_RESTART, LINUX_REBOOT_CMD_HALT, LINUX_REBOOT_CMD_POWER_OFF];
+
+    for (&magic2, &cmd) in LINUX_REBOOT_MAGIC2.iter().zip(cmds.iter().cycle()) {
+        let result = call(&mut runtime, Syscall::Reboot, &[LINUX_REBOOT_MAGIC1 as u64, magic2 as u64, cmd as u64]);
+        assert_eq!(result, SyscallResult::ok(0));
+    }
+
+    let forwarded: Vec<u64> = runtime.forwarded.iter().map(|(name, args)| {
+        assert_eq!(*name, "reboot");
+        args[0]
+    }).collect();
+    assert_eq!(forwarded, vec![
+        LINUX_REBOOT_CMD_RESTART as u64, LINUX_REBOOT_CMD_HALT as u64,
+        LINUX_REBOOT_CMD_POWER_OFF as u64, LINUX_REBOOT_CMD_RESTART as u64,
+    ]);
+}
+
+#[test]
+fn reboot_rejects_bad_magic() {
+    let mut runtime = FakeRuntime::default();
+    let cmd = LINUX_REBOOT_CMD_POWER_OFF as u64;
+
+    let magic1 = call(&mut runtime, Syscall::Reboot, &[0xdead_beef, LINUX_REBOOT_MAGIC2[0] as u64, cmd]);
+    let magic2 = call(&mut runtime, Syscall::Reboot, &[LINUX_REBOOT_MAGIC1 as u64, 0x1234_5678, cmd]);
+
+    assert_eq!(magic1, SyscallResult::err(LINUX_EINVAL));
+    assert_eq!(magic2, SyscallResult::err(LINUX_EINVAL));
+    assert!(runtime.forwarded.is_empty());
+}
+
+#[test]
+fn reboot_rejects_unsupported_command() {
+    let mut runtime = FakeRuntime::default();
+
+    // LINUX_REBOOT_CMD_CAD_ON
+    let result = call(&mut runtime, Syscall::Reboot, &[LINUX_REBOOT_MAGIC1 as u64, LINUX_REBOOT_MAGIC2[0] as u64, 0x89ab_cdef]);
+
+    assert_eq!(result, SyscallResult::err(LINUX_EINVAL));
+    assert!(runtime.forwarded.is_empty());
+}
+
+#[test]
+fn loadkeys_forwards_the_layout_name() {
+    let mut runtime = FakeRuntime::default();
+    let name = *b"colemak";
+
+    let result = call(&mut runtime, Syscall::Loadkeys, &[name.as_ptr() as u64, name.len() as u64]);
+
+    assert_eq!(result, SyscallResult::ok(0));
+    assert_eq!(runtime.last_path, b"colemak");
+    assert_eq!(runtime.forwarded, vec![("loadkeys", vec![7])]);
+}
+
+#[test]
+fn loadkeys_rejects_empty_name() {
+    let mut runtime = FakeRuntime::default();
+
+    let result = call(&mut runtime, Syscall::Loadkeys, &[0x1000, 0]);
+
+    assert_eq!(result, SyscallResult::err(LINUX_EINVAL));
+    assert!(runtime.forwarded.is_empty());
+}
+
+#[test]
+fn loadkeys_rejects_overlong_name() {
+    let mut runtime = FakeRuntime::default();
+    let name = [b'x'; 17];
+
+    let result = call(&mut runtime, Syscall::Loadkeys, &[name.as_ptr() as u64, name.len() as u64]);
+
+    assert_eq!(result, SyscallResult::err(LINUX_EINVAL));
+    assert!(runtime.forwarded.is_empty());
+}
+
+#[test]
+fn loadkeys_faults_on_kernel_pointer() {
+    let mut runtime = FakeRuntime::default();
+
+    let result = call(&mut runtime, Syscall::Loadkeys, &[0xFFFF_8000_0000_0000, 2]);
+
+    assert_eq!(result, SyscallResult::err(LINUX_EFAULT));
+    assert!(runtime.forwarded.is_empty());
+}
@@ -0,0 +1,61 @@
+//! beep — sound the PC speaker
+//! Usage: beep [-f freq] [-l length] [-r repeats] [-d delay]
+//!
+//! Plays `repeats` tones of `freq` Hz for `length` ms each, `delay` ms
+//! apart, through the console's KDMKTONE ioctl.  Defaults: 440 Hz, 200 ms,
+//! once, 100 ms.
+#![no_std]
+#![no_main]
+
+use oxide_rt::sound::{KDMKTONE, PIT_HZ};
+use oxide_rt::{arg, argc, close, exit, ioctl, open, println, sleep_ms};
+
+const O_WRONLY: u32 = 1;
+
+fn usage() -> ! {
+    println!("usage: beep [-f freq] [-l length] [-r repeats] [-d delay]");
+    exit(2);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn oxide_main() {
+    let (mut freq, mut length, mut repeats, mut delay) = (440u32, 200u32, 1u32, 100u32);
+    let mut i = 1;
+    while i < argc() {
+        let slot = match arg(i) {
+            Some("-f") => &mut freq,
+            Some("-l") => &mut length,
+            Some("-r") => &mut repeats,
+            Some("-d") => &mut delay,
+            _          => usage(),
+        };
+        i += 1;
+        *slot = match arg(i).and_then(|v| v.parse().ok()) {
+            Some(v) => v,
+            None    => usage(),
+        };
+        i += 1;
+    }
+    if !(20..=20_000).contains(&freq) {
+        println!("beep: frequency must be 20..20000 Hz");
+        exit(2);
+    }
+    let length = length.min(0xFFFF);
+
+    // The tone goes to the console we run on; stdin when it is one.
+    let mut fd = 0;
+    let tone = (length << 16 | PIT_HZ / freq) as u64;
+    if ioctl(fd, KDMKTONE, 0) < 0 {
+        fd = open("/dev/tty", O_WRONLY);
+        if fd < 0 || ioctl(fd, KDMKTONE, 0) < 0 {
+            println!("beep: no console speaker");
+            exit(1);
+        }
+    }
+    for n in 0..repeats {
+        if n > 0 { sleep_ms(delay as u64); }
+        ioctl(fd, KDMKTONE, tone);
+        sleep_ms(length as u64);
+    }
+    if fd != 0 { close(fd); }
+}
@@ -0,0 +1,66 @@
+//! evtest — print the events of an input device
+//! Usage: evtest [-g] [device]
+//!
+//! Reads `struct input_event`s from `/dev/input/event0` (or the given
+//! device) and prints one line per event until interrupted.  `-g` grabs
+//! the device first, so the console and the desktop stop seeing it.
+#![no_std]
+#![no_main]
+
+use oxide_rt::input::{EV_KEY, EV_LED, EV_REL, EV_SYN, SYN_DROPPED};
+use oxide_rt::{arg, argc, evdev_grab, evdev_name, exit, open, println, read_events, InputEvent};
+
+const O_RDONLY: u32 = 0;
+
+fn type_name(t: u16) -> &'static str {
+    match t {
+        EV_SYN => "EV_SYN",
+        EV_KEY => "EV_KEY",
+        EV_REL => "EV_REL",
+        EV_LED => "EV_LED",
+        _      => "?",
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn oxide_main() {
+    let mut grab = false;
+    let mut path = "/dev/input/event0";
+    for i in 1..argc() {
+        match arg(i) {
+            Some("-g") => grab = true,
+            Some(p)    => path = p,
+            None       => {}
+        }
+    }
+
+    let fd = open(path, O_RDONLY);
+    if fd < 0 {
+        println!("evtest: {}: cannot open ({})", path, fd);
+        exit(1);
+    }
+    let mut name = [0u8; 64];
+    println!("{}: {}", path, evdev_name(fd, &mut name).unwrap_or("(unnamed)"));
+    if grab && evdev_grab(fd, true) < 0 {
+        println!("evtest: device is already grabbed");
+        exit(1);
+    }
+
+    let mut events = [InputEvent::default(); 16];
+    loop {
+        let n = read_events(fd, &mut events);
+        if n < 0 {
+            println!("evtest: read failed ({})", n);
+            exit(1);
+        }
+        for ev in &events[..n as usize] {
+            if ev.type_ == EV_SYN {
+                let what = if ev.code == SYN_DROPPED { "SYN_DROPPED" } else { "SYN_REPORT" };
+                println!("{}.{:06} -------------- {} ------------", ev.sec, ev.usec, what);
+            } else {
+                println!("{}.{:06} type {} ({}), code {}, value {}",
+                         ev.sec, ev.usec, ev.type_, type_name(ev.type_), ev.code, ev.value);
+            }
+        }
+    }
+}
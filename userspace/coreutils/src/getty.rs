@@ -0,0 +1,63 @@
+//! getty — open a terminal and run login on it
+//! Usage: getty <tty>
+//!
+//! Starts a new session, opens `/dev/<tty>` — which becomes the session's
+//! controlling terminal — checks that it is a terminal, makes it stdin,
+//! stdout and stderr, prints `/etc/issue` (or the host name) and execs
+//! `/bin/login`.  init respawns it from `/etc/inittab` when the session ends.
+#![no_std]
+#![no_main]
+
+use oxide_rt::passwd::load;
+use oxide_rt::{arg, argc, close, dup2, exec_args, exit, ioctl, open, println, setsid, write, Termios};
+
+const O_RDWR: u32 = 2;
+
+#[unsafe(no_mangle)]
+pub extern "C" fn oxide_main() {
+    let Some(tty) = arg(1).filter(|_| argc() > 1) else {
+        println!("usage: getty <tty>");
+        exit(2);
+    };
+    let mut pbuf = [0u8; 64];
+    let path = match tty.starts_with('/') {
+        true  => tty,
+        false => {
+            let n = 5 + tty.len();
+            if n > pbuf.len() { println!("getty: {}: name too long", tty); exit(1); }
+            pbuf[..5].copy_from_slice(b"/dev/");
+            pbuf[5..n].copy_from_slice(tty.as_bytes());
+            core::str::from_utf8(&pbuf[..n]).unwrap_or("")
+        }
+    };
+
+    // Already a session leader if init set us up that way; either way the
+    // open below makes the tty ours.
+    setsid();
+    let fd = open(path, O_RDWR);
+    if fd < 0 {
+        println!("getty: cannot open {} ({})", path, fd);
+        exit(1);
+    }
+    let mut t = Termios::zeroed();
+    if ioctl(fd, oxide_rt::ioctl::TCGETS, &mut t as *mut Termios as u64) < 0 {
+        println!("getty: {}: not a tty", path);
+        exit(1);
+    }
+    for std in 0..3 {
+        dup2(fd, std);
+    }
+    close(fd);
+
+    let mut buf = [0u8; 512];
+    let mut issue = load("/etc/issue", &mut buf);
+    let mut hbuf = [0u8; 64];
+    if issue.is_empty() { issue = load("/etc/hostname", &mut hbuf); }
+    for part in ["\n", issue.trim_end(), "\n\n"] {
+        write(1, part.as_bytes());
+    }
+
+    exec_args("/bin/login", "");
+    println!("getty: cannot run /bin/login");
+    exit(1);
+}
@@ -0,0 +1,84 @@
+//! id — print user and group ids
+//! Usage: id [user]
+//!
+//! Without an argument prints the caller's real ids, the effective ones when
+//! they differ (after a set-uid exec), and the supplementary groups.  With a
+//! user name (or uid) prints that account's ids from `/etc/passwd` and
+//! `/etc/group`.
+#![no_std]
+#![no_main]
+
+use oxide_rt::passwd::{self, find_gid, find_uid, find_user, groups_of};
+use oxide_rt::{arg, argc, exit, getegid, geteuid, getgid, getgroups, getuid, print, println};
+
+const MAX_GROUPS: usize = 32;
+
+fn print_user(passwd: &str, key: &str, uid: u32) {
+    match find_uid(passwd, uid) {
+        Some(p) => print!("{}={}({})", key, uid, p.name),
+        None    => print!("{}={}", key, uid),
+    }
+}
+
+fn print_group(group: &str, key: &str, gid: u32) {
+    match find_gid(group, gid) {
+        Some(g) => print!("{}={}({})", key, gid, g.name),
+        None    => print!("{}={}", key, gid),
+    }
+}
+
+fn print_groups(group: &str, gids: &[u32]) {
+    print!(" groups=");
+    for (i, &gid) in gids.iter().enumerate() {
+        if i > 0 { print!(","); }
+        match find_gid(group, gid) {
+            Some(g) => print!("{}({})", gid, g.name),
+            None    => print!("{}", gid),
+        }
+    }
+    println!();
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn oxide_main() {
+    let mut pbuf = [0u8; 2048];
+    let mut gbuf = [0u8; 2048];
+    let passwd = passwd::load("/etc/passwd", &mut pbuf);
+    let group  = passwd::load("/etc/group", &mut gbuf);
+    let mut gids = [0u32; MAX_GROUPS];
+
+    if argc() > 1 {
+        let name = arg(1).unwrap_or("");
+        let Some(p) = find_user(passwd, name) else {
+            println!("id: '{}': no such user", name);
+            exit(1);
+        };
+        print_user(passwd, "uid", p.uid);
+        print!(" ");
+        print_group(group, "gid", p.gid);
+        let n = groups_of(group, p.name, p.gid, &mut gids);
+        print_groups(group, &gids[..n]);
+        exit(0);
+    }
+
+    let (uid, euid, gid, egid) = (getuid(), geteuid(), getgid(), getegid());
+    print_user(passwd, "uid", uid);
+    print!(" ");
+    print_group(group, "gid", gid);
+    if euid != uid {
+        print!(" ");
+        print_user(passwd, "euid", euid);
+    }
+    if egid != gid {
+        print!(" ");
+        print_group(group, "egid", egid);
+    }
+    let n = getgroups(&mut gids).clamp(0, MAX_GROUPS as i64) as usize;
+    if n == 0 {
+        // No supplementary groups: show the primary one, as coreutils does.
+        print_groups(group, &[gid]);
+    } else {
+        print_groups(group, &gids[..n]);
+    }
+    exit(0);
+}
@@ -0,0 +1,306 @@
+//! init — process 1
+//! Usage: started by the kernel as PID 1 (`/sbin/init`); not run by hand.
+//!
+//! Boots the system in four stages: the `sysinit` entries of `/etc/inittab`,
+//! the `S*` scripts in `/etc/rc.d` (in name order, each run by `/bin/sh`),
+//! the `wait` entries, then the `once` and `respawn` ones.  It then stays
+//! around to reap every orphan the kernel hands over and to restart
+//! `respawn` services when they exit — at most `BURST` starts in
+//! `BURST_WINDOW`, after which a service is left down for `COOLDOWN`.
+//!
+//! SIGTERM reboots, SIGUSR2 powers off and SIGUSR1 halts.  Going down runs
+//! the `shutdown` entries and the `K*` scripts, sends SIGTERM to services
+//! (newest first) and then to everything else, SIGKILLs whatever is left
+//! after `GRACE`, syncs and calls reboot(2).
+//!
+//! `/etc/inittab` lines are `id::action:command [args]`; `#` starts a comment.
+#![no_std]
+#![no_main]
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use oxide_rt::passwd::load;
+use oxide_rt::{
+    chdir, exec_args, exit, fork, get_time, kill_signal, println, readdir, reboot, reboot_cmd,
+    setenv, sig, sigaction, sleep_ms, sync, wait4, waitpid, wexitstatus, wifsignaled, wtermsig,
+    WNOHANG,
+};
+
+const INITTAB: &str = "/etc/inittab";
+const RC_DIR:  &str = "/etc/rc.d";
+const SHELL:   &str = "/bin/sh";
+
+const MAX_SERVICES: usize = 16;
+const MAX_SCRIPTS:  usize = 32;
+/// PIDs are kernel task slots + 1, so this covers every process.
+const MAX_PID: u32 = 16;
+
+const TICKS_PER_SEC: u64 = 100;
+const BURST:         u32 = 10;
+const BURST_WINDOW:  u64 = 120 * TICKS_PER_SEC;
+const COOLDOWN:      u64 = 300 * TICKS_PER_SEC;
+const GRACE:         u64 = 3 * TICKS_PER_SEC;
+
+const EINTR:  i64 = -4;
+const ECHILD: i64 = -10;
+
+// ── Signals ───────────────────────────────────────────────────────────────────
+
+/// Bit `n` set: signal `n` arrived and has not been acted on yet.
+static PENDING: AtomicU32 = AtomicU32::new(0);
+
+unsafe extern "C" fn on_signal(signum: i32) {
+    PENDING.fetch_or(1 << signum, Ordering::SeqCst);
+}
+
+/// The reboot(2) command requested by a pending signal, if any.
+fn requested_shutdown() -> Option<u32> {
+    let p = PENDING.load(Ordering::SeqCst);
+    if p & (1 << sig::SIGTERM) != 0 { return Some(reboot_cmd::RESTART); }
+    if p & (1 << sig::SIGUSR2) != 0 { return Some(reboot_cmd::POWER_OFF); }
+    if p & (1 << sig::SIGUSR1) != 0 { return Some(reboot_cmd::HALT); }
+    None
+}
+
+// ── inittab ───────────────────────────────────────────────────────────────────
+
+#[derive(Clone, Copy, PartialEq)]
+enum Action {
+    SysInit,
+    Wait,
+    Once,
+    Respawn,
+    Shutdown,
+}
+
+impl Action {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "sysinit"  => Some(Self::SysInit),
+            "wait"     => Some(Self::Wait),
+            "once"     => Some(Self::Once),
+            "respawn"  => Some(Self::Respawn),
+            "shutdown" => Some(Self::Shutdown),
+            _          => None,
+        }
+    }
+}
+
+struct Service<'a> {
+    id:      &'a str,
+    action:  Action,
+    command: &'a str,
+    /// Running instance, or 0.
+    pid:     u32,
+    /// Start of the current rate-limit window and starts made in it.
+    window:  u64,
+    starts:  u32,
+    /// Not restarted before this tick.
+    down_until: u64,
+}
+
+fn parse_inittab<'a>(text: &'a str, out: &mut [Option<Service<'a>>; MAX_SERVICES]) {
+    let mut n = 0;
+    for (lineno, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() { continue; }
+        let mut f = line.splitn(4, ':');
+        let (Some(id), Some(_), Some(action), Some(command)) = (f.next(), f.next(), f.next(), f.next())
+        else {
+            println!("init: {}:{}: expected id::action:command", INITTAB, lineno + 1);
+            continue;
+        };
+        let Some(action) = Action::parse(action.trim()) else {
+            println!("init: {}:{}: unknown action '{}'", INITTAB, lineno + 1, action.trim());
+            continue;
+        };
+        if n == MAX_SERVICES {
+            println!("init: {}: more than {} entries, ignoring the rest", INITTAB, MAX_SERVICES);
+            break;
+        }
+        out[n] = Some(Service {
+            id: id.trim(), action, command: command.trim(),
+            pid: 0, window: 0, starts: 0, down_until: 0,
+        });
+        n += 1;
+    }
+}
+
+// ── Running commands ──────────────────────────────────────────────────────────
+
+/// Fork and exec `command` ("path args...").  Returns the child's pid.
+fn spawn(command: &str) -> Option<u32> {
+    let (path, args) = command.split_once(' ').unwrap_or((command, ""));
+    let pid = fork();
+    if pid == 0 {
+        exec_args(path, args.trim());
+        println!("init: cannot run {}", path);
+        exit(127);
+    }
+    if pid < 0 {
+        println!("init: fork failed for {}", path);
+        return None;
+    }
+    Some(pid as u32)
+}
+
+/// Run `command` to completion.
+fn run(command: &str) {
+    if let Some(pid) = spawn(command) {
+        // A signal for init interrupts the wait; the script keeps running.
+        let code = loop {
+            let c = waitpid(pid);
+            if c != EINTR { break c; }
+        };
+        if code != 0 { println!("init: '{}' exited with status {}", command, code); }
+    }
+}
+
+/// Run the scripts in `RC_DIR` whose names start with `prefix`, in order.
+fn run_scripts(prefix: char) {
+    let mut buf = [0u8; 1024];
+    let n = readdir(RC_DIR, &mut buf);
+    if n <= 0 { return; }
+    let listing = core::str::from_utf8(&buf[..n as usize]).unwrap_or("");
+    let mut names = [""; MAX_SCRIPTS];
+    let mut count = 0;
+    for name in listing.lines() {
+        // Directories are listed with a trailing '/'.
+        if name.starts_with(prefix) && !name.ends_with('/') && count < MAX_SCRIPTS {
+            names[count] = name;
+            count += 1;
+        }
+    }
+    names[..count].sort_unstable();
+    for name in &names[..count] {
+        let mut cmd = [0u8; 128];
+        let Some(cmd) = concat(&mut cmd, &[SHELL, " ", RC_DIR, "/", name]) else { continue };
+        run(cmd);
+    }
+}
+
+fn concat<'a>(buf: &'a mut [u8], parts: &[&str]) -> Option<&'a str> {
+    let mut len = 0;
+    for p in parts {
+        let b = p.as_bytes();
+        buf.get_mut(len..len + b.len())?.copy_from_slice(b);
+        len += b.len();
+    }
+    core::str::from_utf8(&buf[..len]).ok()
+}
+
+/// Start `s`, unless it has been restarted too often lately.
+fn start(s: &mut Service, now: u64) {
+    if now.saturating_sub(s.window) > BURST_WINDOW {
+        s.window = now;
+        s.starts = 0;
+    }
+    if s.starts == BURST {
+        println!("init: '{}' respawning too fast, disabled for {} s", s.id, COOLDOWN / TICKS_PER_SEC);
+        s.down_until = now + COOLDOWN;
+        s.window = s.down_until;
+        s.starts = 0;
+        return;
+    }
+    s.starts += 1;
+    if let Some(pid) = spawn(s.command) { s.pid = pid; }
+}
+
+/// Collect every exited child.  Returns `false` once init has none left.
+fn reap(services: &mut [Option<Service>]) -> bool {
+    loop {
+        let mut status = 0;
+        let pid = wait4(-1, &mut status, WNOHANG);
+        if pid == ECHILD { return false; }
+        if pid <= 0 { return true; }
+        let Some(s) = services.iter_mut().flatten().find(|s| s.pid == pid as u32) else { continue };
+        s.pid = 0;
+        if wifsignaled(status) {
+            println!("init: '{}' (pid {}) killed by signal {}", s.id, pid, wtermsig(status));
+        } else {
+            println!("init: '{}' (pid {}) exited with status {}", s.id, pid, wexitstatus(status));
+        }
+    }
+}
+
+// ── Shutdown ──────────────────────────────────────────────────────────────────
+
+fn shutdown(cmd: u32, services: &mut [Option<Service>]) -> ! {
+    let what = match cmd {
+        reboot_cmd::RESTART   => "reboot",
+        reboot_cmd::POWER_OFF => "power off",
+        _                     => "halt",
+    };
+    println!("init: the system is going down for {}", what);
+
+    for s in services.iter().flatten().filter(|s| s.action == Action::Shutdown) {
+        run(s.command);
+    }
+    run_scripts('K');
+
+    for s in services.iter().rev().flatten().filter(|s| s.pid != 0) {
+        let _ = kill_signal(s.pid, sig::SIGTERM);
+    }
+    for pid in 2..=MAX_PID {
+        let _ = kill_signal(pid, sig::SIGTERM);
+    }
+    let deadline = get_time() + GRACE;
+    while reap(services) && get_time() < deadline {
+        sleep_ms(100);
+    }
+    for pid in 2..=MAX_PID {
+        let _ = kill_signal(pid, sig::SIGKILL);
+    }
+    sleep_ms(100);
+    reap(services);
+
+    sync();
+    let r = reboot(cmd);
+    println!("init: reboot failed ({})", r);
+    loop { sleep_ms(60_000); }
+}
+
+// ── Main ──────────────────────────────────────────────────────────────────────
+
+#[unsafe(no_mangle)]
+pub extern "C" fn oxide_main() {
+    for signum in [sig::SIGTERM, sig::SIGUSR1, sig::SIGUSR2] {
+        sigaction(signum, on_signal as *const () as u64);
+    }
+    let _ = chdir("/");
+    setenv("PATH", "/sbin:/bin");
+
+    let mut tab = [0u8; 2048];
+    let text = load(INITTAB, &mut tab);
+    let mut services: [Option<Service>; MAX_SERVICES] = [const { None }; MAX_SERVICES];
+    parse_inittab(text, &mut services);
+    if text.is_empty() { println!("init: cannot read {}", INITTAB); }
+
+    for s in services.iter().flatten().filter(|s| s.action == Action::SysInit) {
+        run(s.command);
+    }
+    run_scripts('S');
+    for s in services.iter().flatten().filter(|s| s.action == Action::Wait) {
+        run(s.command);
+    }
+
+    let now = get_time();
+    for s in services.iter_mut().flatten() {
+        if matches!(s.action, Action::Once | Action::Respawn) { start(s, now); }
+    }
+
+    loop {
+        reap(&mut services);
+        if let Some(cmd) = requested_shutdown() {
+            shutdown(cmd, &mut services);
+        }
+        let now = get_time();
+        for s in services.iter_mut().flatten() {
+            if s.action == Action::Respawn && s.pid == 0 && now >= s.down_until {
+                start(s, now);
+            }
+        }
+        // Any signal — SIGCHLD included — cuts the sleep short.
+        sleep_ms(1000);
+    }
+}
@@ -0,0 +1,58 @@
+//! loadkeys — show or change the keyboard layout
+//! Usage: loadkeys [-l] [layout]
+//!
+//! Without arguments prints the active layout (from
+//! `/proc/sys/kernel/keymap`); `-l` lists the layouts the kernel knows.
+//! Changing the layout needs root and is remembered across reboots.
+#![no_std]
+#![no_main]
+
+use oxide_rt::{arg, argc, close, exit, loadkeys, open, println, read, KEYMAP_PATH};
+
+const O_RDONLY: u32 = 0;
+
+const LAYOUTS: &[(&str, &str)] = &[
+    ("us",      "US English (QWERTY)"),
+    ("uk",      "UK English (QWERTY)"),
+    ("de",      "German (QWERTZ, dead ^ ´ `)"),
+    ("fr",      "French (AZERTY, dead ^ ¨)"),
+    ("dvorak",  "US Dvorak"),
+    ("colemak", "US Colemak"),
+];
+
+#[unsafe(no_mangle)]
+pub extern "C" fn oxide_main() {
+    match (argc(), arg(1)) {
+        (1, _) => {
+            let mut buf = [0u8; 32];
+            let fd = open(KEYMAP_PATH, O_RDONLY);
+            let n = if fd < 0 { fd as i64 } else { let n = read(fd, &mut buf); close(fd); n };
+            if n < 0 {
+                println!("loadkeys: {}: cannot read ({})", KEYMAP_PATH, n);
+                exit(1);
+            }
+            let name = core::str::from_utf8(&buf[..n as usize]).unwrap_or("?");
+            println!("{}", name.trim());
+        }
+        (2, Some("-l")) => {
+            for (name, desc) in LAYOUTS {
+                println!("{:<8} {}", name, desc);
+            }
+        }
+        (2, Some(name)) => {
+            let r = loadkeys(name);
+            if r < 0 {
+                match r {
+                    -1  => println!("loadkeys: permission denied"),
+                    -22 => println!("loadkeys: unknown layout '{}' (try -l)", name),
+                    _   => println!("loadkeys: failed ({})", r),
+                }
+                exit(1);
+            }
+        }
+        _ => {
+            println!("usage: loadkeys [-l] [layout]");
+            exit(2);
+        }
+    }
+}
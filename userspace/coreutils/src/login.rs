@@ -0,0 +1,107 @@
+//! login — sign on to the system
+//! Usage: login [user]
+//!
+//! Prompts for a user name (unless given) and password, checks them against
+//! `/etc/passwd` and `/etc/shadow`, then prints `/etc/motd`, drops to the
+//! user's ids, changes to their home directory and runs their shell.  The
+//! terminal on stdin is given to the user (group tty) first.  Must be
+//! started as root; gives up after three failed attempts.
+#![no_std]
+#![no_main]
+
+use oxide_rt::passwd::{self, become_user, find_user, getpass, shadow_hash, verify};
+use oxide_rt::{
+    arg, argc, chdir, exec_args, exit, fchown, getchar, geteuid, print, print_bytes, print_str,
+    println, sleep_ms, ID_UNCHANGED,
+};
+
+const ATTEMPTS: usize = 3;
+
+/// Read a line from the terminal, echoing it.
+fn readline(buf: &mut [u8]) -> usize {
+    let mut len = 0;
+    loop {
+        let Some(c) = getchar() else { sleep_ms(10); continue };
+        match c {
+            b'\n' | b'\r' => { print_str("\n"); break; }
+            8 | 127 => {
+                if len > 0 {
+                    len -= 1;
+                    print_str("\x08 \x08");
+                }
+            }
+            c if c >= 32 && len < buf.len() => {
+                buf[len] = c;
+                len += 1;
+                print_bytes(core::slice::from_ref(&c));
+            }
+            _ => {}
+        }
+    }
+    len
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn oxide_main() {
+    if geteuid() != 0 {
+        println!("login: must be run as root");
+        exit(1);
+    }
+
+    let mut hbuf = [0u8; 64];
+    let hostname = passwd::load("/etc/hostname", &mut hbuf).trim();
+    let mut pbuf = [0u8; 2048];
+    let passwd = passwd::load("/etc/passwd", &mut pbuf);
+    let mut sbuf = [0u8; 2048];
+    let shadow = passwd::load("/etc/shadow", &mut sbuf);
+
+    let mut name_buf = [0u8; 32];
+    for attempt in 0..ATTEMPTS {
+        let name = match arg(1).filter(|_| attempt == 0 && argc() > 1) {
+            Some(n) => n,
+            None => {
+                print!("{} login: ", if hostname.is_empty() { "oxideos" } else { hostname });
+                let n = readline(&mut name_buf);
+                core::str::from_utf8(&name_buf[..n]).unwrap_or("").trim()
+            }
+        };
+        if name.is_empty() { continue; }
+
+        let mut pw = [0u8; 128];
+        let n = getpass("Password: ", &mut pw);
+        // Unknown users are asked for a password too, and fail the same way.
+        let user = find_user(passwd, name).filter(|u| u.name == name);
+        let ok = user.as_ref()
+            .and_then(|u| shadow_hash(shadow, u.name))
+            .is_some_and(|h| verify(&pw[..n], h));
+        pw.fill(0);
+        let Some(user) = user.filter(|_| ok) else {
+            sleep_ms(1000);
+            println!("Login incorrect\n");
+            continue;
+        };
+
+        let mut mbuf = [0u8; 1024];
+        print_str(passwd::load("/etc/motd", &mut mbuf));
+
+        let mut gbuf = [0u8; 2048];
+        let group = passwd::load("/etc/group", &mut gbuf);
+        let tty_gid = group.lines().filter_map(passwd::parse_group)
+            .find(|g| g.name == "tty")
+            .map_or(ID_UNCHANGED, |g| g.gid);
+        // Not fatal: stdin may be a pipe or a pty.
+        let _ = fchown(0, user.uid, tty_gid);
+        if become_user(group, &user) < 0 {
+            println!("login: cannot switch to {}", user.name);
+            exit(1);
+        }
+        if chdir(user.home) < 0 {
+            println!("No directory {}, logging in with HOME=/", user.home);
+            let _ = chdir("/");
+        }
+        exec_args(user.shell, "");
+        println!("login: cannot run {}", user.shell);
+        exit(127);
+    }
+    exit(1);
+}
@@ -0,0 +1,69 @@
+//! netd — network configuration daemon
+//! Usage: netd   (started by init from `/etc/inittab`)
+//!
+//! The kernel runs DHCP itself; netd follows the lease through
+//! `/proc/net/dhcp`, reports changes and keeps `/etc/resolv.conf` pointing
+//! at the DNS server the lease handed out.  Without a NIC it says so once
+//! and keeps watching.
+#![no_std]
+#![no_main]
+
+use oxide_rt::passwd::load;
+use oxide_rt::{close, open, println, sleep_ms, write};
+
+const STATE:    &str = "/proc/net/dhcp";
+const RESOLV:   &str = "/etc/resolv.conf";
+const POLL_MS:  u64  = 2000;
+
+const O_WRONLY: u32 = 1;
+const O_CREAT:  u32 = 0x40;
+const O_TRUNC:  u32 = 0x200;
+
+/// The value of the `key` line in the state file.
+fn field<'a>(state: &'a str, key: &str) -> Option<&'a str> {
+    state.lines().find_map(|l| l.strip_prefix(key)?.strip_prefix(' '))
+}
+
+fn write_resolv(dns: &str) {
+    let fd = open(RESOLV, O_WRONLY | O_CREAT | O_TRUNC);
+    if fd < 0 {
+        println!("netd: cannot write {} ({})", RESOLV, fd);
+        return;
+    }
+    for part in ["nameserver ", dns, "\n"] {
+        write(fd, part.as_bytes());
+    }
+    close(fd);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn oxide_main() {
+    let mut last = [0u8; 256];
+    let mut last_len = usize::MAX;
+    let mut said_no_nic = false;
+    loop {
+        let mut buf = [0u8; 256];
+        let state = load(STATE, &mut buf);
+        if state.as_bytes() != last.get(..last_len).unwrap_or(&[]) {
+            last[..state.len()].copy_from_slice(state.as_bytes());
+            last_len = state.len();
+
+            let nic = field(state, "nic").unwrap_or("none");
+            if field(state, "bound").is_none() {
+                if !said_no_nic {
+                    println!("netd: no network interface ({})", nic);
+                    said_no_nic = true;
+                }
+            } else if field(state, "bound") == Some("1") {
+                let ip  = field(state, "ip").unwrap_or("?");
+                let gw  = field(state, "gateway").unwrap_or("?");
+                let dns = field(state, "dns").unwrap_or("0.0.0.0");
+                println!("netd: {} bound to {} via {}, dns {}", nic, ip, gw, dns);
+                if dns != "0.0.0.0" { write_resolv(dns); }
+            } else {
+                println!("netd: {}: waiting for a DHCP lease", nic);
+            }
+        }
+        sleep_ms(POLL_MS);
+    }
+}
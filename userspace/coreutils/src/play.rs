@@ -0,0 +1,177 @@
+//! play — play a WAV file through /dev/dsp
+//! Usage: play [-v volume] <file.wav>
+//!
+//! Handles uncompressed PCM: 8- or 16-bit, mono or stereo, any rate the
+//! driver accepts (it resamples what the sound card cannot do).  `-v` sets
+//! the stream volume in percent without touching the mixer.
+#![no_std]
+#![no_main]
+
+use oxide_rt::sound::{
+    AFMT_S16_LE, AFMT_U8, SNDCTL_DSP_CHANNELS, SNDCTL_DSP_SETFMT, SNDCTL_DSP_SETPLAYVOL,
+    SNDCTL_DSP_SPEED, SNDCTL_DSP_SYNC,
+};
+use oxide_rt::{arg, argc, close, exit, open, oss_ioctl, println, read, write};
+
+const O_RDONLY: u32 = 0;
+const O_WRONLY: u32 = 1;
+
+const WAVE_FORMAT_PCM:        u16 = 0x0001;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+struct Format {
+    channels: u16,
+    rate:     u32,
+    bits:     u16,
+}
+
+fn le16(b: &[u8]) -> u16 { u16::from_le_bytes([b[0], b[1]]) }
+fn le32(b: &[u8]) -> u32 { u32::from_le_bytes([b[0], b[1], b[2], b[3]]) }
+
+/// Read until `buf` is full or the file ends.
+fn read_full(fd: i32, buf: &mut [u8]) -> usize {
+    let mut n = 0;
+    while n < buf.len() {
+        let r = read(fd, &mut buf[n..]);
+        if r <= 0 { break; }
+        n += r as usize;
+    }
+    n
+}
+
+/// Walk the RIFF chunks up to `data`: returns the format, the data length
+/// and whatever part of the data was already read into `buf`.
+fn parse_header(fd: i32, buf: &mut [u8]) -> Result<(Format, usize, core::ops::Range<usize>), &'static str> {
+    let len = read_full(fd, buf);
+    if len < 12 || &buf[0..4] != b"RIFF" || &buf[8..12] != b"WAVE" {
+        return Err("not a WAV file");
+    }
+    let mut fmt = None;
+    let mut pos = 12;
+    while pos + 8 <= len {
+        let id   = &buf[pos..pos + 4];
+        let size = le32(&buf[pos + 4..pos + 8]) as usize;
+        let body = pos + 8;
+        if id == b"fmt " {
+            if body + 16 > len { return Err("truncated fmt chunk"); }
+            let tag = le16(&buf[body..]);
+            if tag != WAVE_FORMAT_PCM && tag != WAVE_FORMAT_EXTENSIBLE {
+                return Err("only PCM WAV files are supported");
+            }
+            fmt = Some(Format {
+                channels: le16(&buf[body + 2..]),
+                rate:     le32(&buf[body + 4..]),
+                bits:     le16(&buf[body + 14..]),
+            });
+        } else if id == b"data" {
+            let fmt = fmt.ok_or("data before fmt chunk")?;
+            return Ok((fmt, size, body..len.min(body + size)));
+        }
+        pos = body + size + (size & 1);
+    }
+    Err("no data chunk in the first 4 KiB")
+}
+
+/// Write all of `data` to the dsp, which may take it in pieces.
+fn write_all(dsp: i32, mut data: &[u8]) -> bool {
+    while !data.is_empty() {
+        let n = write(dsp, data);
+        if n <= 0 { return false; }
+        data = &data[n as usize..];
+    }
+    true
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn oxide_main() {
+    let mut volume = None;
+    let mut path = None;
+    let mut i = 1;
+    while i < argc() {
+        match arg(i) {
+            Some("-v") => {
+                i += 1;
+                volume = arg(i).and_then(|v| v.parse::<u8>().ok());
+                if volume.is_none() {
+                    println!("play: -v takes a volume in percent");
+                    exit(2);
+                }
+            }
+            Some(p) => path = Some(p),
+            None    => {}
+        }
+        i += 1;
+    }
+    let Some(path) = path else {
+        println!("usage: play [-v volume] <file.wav>");
+        exit(2);
+    };
+
+    let fd = open(path, O_RDONLY);
+    if fd < 0 {
+        println!("play: {}: cannot open ({})", path, fd);
+        exit(1);
+    }
+    let mut buf = [0u8; 4096];
+    let (fmt, data_len, head) = match parse_header(fd, &mut buf) {
+        Ok(h)  => h,
+        Err(e) => { println!("play: {}: {}", path, e); exit(1); }
+    };
+    let afmt = match fmt.bits {
+        8  => AFMT_U8,
+        16 => AFMT_S16_LE,
+        b  => { println!("play: {}: {}-bit samples are not supported", path, b); exit(1); }
+    };
+    if !(1..=2).contains(&fmt.channels) {
+        println!("play: {}: {} channels are not supported", path, fmt.channels);
+        exit(1);
+    }
+
+    let dsp = open("/dev/dsp", O_WRONLY);
+    if dsp < 0 {
+        match dsp {
+            -2  => println!("play: no sound card"),
+            -16 => println!("play: /dev/dsp is busy"),
+            _   => println!("play: /dev/dsp: cannot open ({})", dsp),
+        }
+        exit(1);
+    }
+    // Format and channels first: OSS applies the rate last.
+    let ok = oss_ioctl(dsp, SNDCTL_DSP_SETFMT, afmt as i32) == afmt as i64
+          && oss_ioctl(dsp, SNDCTL_DSP_CHANNELS, fmt.channels as i32) == fmt.channels as i64;
+    let rate = oss_ioctl(dsp, SNDCTL_DSP_SPEED, fmt.rate as i32);
+    if !ok || rate < 0 {
+        println!("play: /dev/dsp does not take this format");
+        exit(1);
+    }
+    if let Some(v) = volume {
+        let v = v.min(100) as i32;
+        oss_ioctl(dsp, SNDCTL_DSP_SETPLAYVOL, v | v << 8);
+    }
+
+    let frame = (fmt.bits / 8 * fmt.channels) as usize;
+    let secs = data_len / frame / fmt.rate.max(1) as usize;
+    println!("{}: {} Hz, {}-bit, {}, {}:{:02}", path, fmt.rate, fmt.bits,
+             if fmt.channels == 2 { "stereo" } else { "mono" }, secs / 60, secs % 60);
+    if rate != fmt.rate as i64 {
+        println!("play: playing at {} Hz", rate);
+    }
+
+    let mut left = data_len - head.len();
+    let mut ok = write_all(dsp, &buf[head]);
+    while ok && left > 0 {
+        let want = left.min(buf.len());
+        let n = read_full(fd, &mut buf[..want]);
+        if n == 0 { break; }
+        left -= n;
+        // Only whole frames: a truncated file may end mid-frame.
+        ok = write_all(dsp, &buf[..n - n % frame]);
+    }
+    oss_ioctl(dsp, SNDCTL_DSP_SYNC, 0);
+    close(dsp);
+    close(fd);
+    if !ok {
+        println!("play: write to /dev/dsp failed");
+        exit(1);
+    }
+}
@@ -0,0 +1,82 @@
+//! session — desktop session
+//! Usage: session [user]   (started by init from `/etc/inittab`)
+//!
+//! Starts the programs the desktop asks for, so they run as children of the
+//! session — under `user`'s ids when one is given — rather than being
+//! spawned by the kernel.  Requests arrive on IPC queue 2 and replies go
+//! out on queue 3; see the kernel's `gui::session` module for the format.
+#![no_std]
+#![no_main]
+
+use oxide_rt::passwd::{self, become_user, find_user};
+use oxide_rt::{
+    arg, argc, chdir, exec_args, exit, fork, geteuid, getpid, msgq_create, msgrcv, msgsnd,
+    println, sleep_ms, wait4, IpcMessage, WNOHANG,
+};
+
+const LAUNCH_QUEUE:   u32 = 2;
+const LAUNCHED_QUEUE: u32 = 3;
+
+const MSG_LAUNCH:   u32 = 1;
+const MSG_HELLO:    u32 = 2;
+const MSG_LAUNCHED: u32 = 3;
+
+const POLL_MS: u64 = 50;
+
+fn launch(name: &str) -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        exec_args(name, "");
+        println!("session: cannot run {}", name);
+        exit(127);
+    }
+    pid as i32
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn oxide_main() {
+    if argc() > 1 {
+        let name = arg(1).unwrap_or("");
+        let mut pbuf = [0u8; 2048];
+        let mut gbuf = [0u8; 2048];
+        let Some(user) = find_user(passwd::load("/etc/passwd", &mut pbuf), name) else {
+            println!("session: user {} does not exist", name);
+            exit(1);
+        };
+        if geteuid() != 0 || become_user(passwd::load("/etc/group", &mut gbuf), &user) < 0 {
+            println!("session: cannot switch to {}", name);
+            exit(1);
+        }
+        let _ = chdir(user.home);
+    }
+
+    if msgq_create(LAUNCH_QUEUE) < 0 || msgq_create(LAUNCHED_QUEUE) < 0 {
+        println!("session: cannot create the launch queues");
+        exit(1);
+    }
+    // Requests left over from a previous session were already served by
+    // the desktop's fallback.
+    let mut msg = IpcMessage::empty();
+    while msgrcv(LAUNCH_QUEUE, &mut msg) == 0 {}
+    msgsnd(LAUNCHED_QUEUE, MSG_HELLO, &getpid().to_le_bytes());
+
+    loop {
+        let mut status = 0;
+        while wait4(-1, &mut status, WNOHANG) > 0 {}
+
+        if msgrcv(LAUNCH_QUEUE, &mut msg) != 0 {
+            sleep_ms(POLL_MS);
+            continue;
+        }
+        if msg.type_id != MSG_LAUNCH || msg.size < 4 { continue; }
+        let data = &msg.data[..(msg.size as usize).min(msg.data.len())];
+        let window = [data[0], data[1], data[2], data[3]];
+        let Ok(name) = core::str::from_utf8(&data[4..]) else { continue };
+
+        let pid = launch(name);
+        let mut reply = [0u8; 8];
+        reply[..4].copy_from_slice(&window);
+        reply[4..].copy_from_slice(&pid.to_le_bytes());
+        msgsnd(LAUNCHED_QUEUE, MSG_LAUNCHED, &reply);
+    }
+}
@@ -0,0 +1,393 @@
+//! strace — trace the syscalls and signals of a program
+//! Usage: strace <program> [args...]
+//!        strace -p <pid>
+//!
+//! Runs the program under `ptrace(PTRACE_SYSCALL)` and prints one line per
+//! syscall, `name(args) = result`, plus `--- SIGxxx ---` for each signal
+//! it receives and `+++ exited with N +++` when it finishes.
+//!
+//! With `-p`, follows a running process through the kernel syscall tracer
+//! instead: the PID is added to `/proc/sys/kernel/trace` and its calls are
+//! read back from `/proc/trace` (raw arguments, result and duration in
+//! ticks) until the process exits.
+#![no_std]
+#![no_main]
+
+use core::fmt::{self, Write};
+use oxide_rt::{
+    arg, argc, close, exec_args, exit, fork, open, println, ptrace, ptrace_peek,
+    ptrace_syscall_info, read, sleep_ms, systrace, wait4, wexitstatus, wifexited, wifsignaled,
+    wifstopped, wstopsig, wtermsig,
+};
+
+const SIGTRAP: i32 = 5;
+
+/// Bytes of a string argument shown before it is cut off with `...`.
+const STR_PREVIEW: usize = 32;
+
+/// Syscall number, name and argument layout.  Each layout character is one
+/// argument: `i` signed decimal, `x` hex, `o` octal, `s` a (pointer, length)
+/// pair shown as a string — it consumes two registers.
+const SYSCALLS: &[(u64, &str, &str)] = &[
+    (0, "read", "ixi"), (1, "write", "is"), (2, "open", "sx"), (3, "close", "i"),
+    (4, "stat", "sx"), (5, "fstat", "ix"), (6, "lstat", "sx"), (7, "poll", "xii"),
+    (8, "lseek", "iii"), (9, "mmap", "xixxii"), (10, "mprotect", "xix"), (11, "munmap", "xi"),
+    (12, "brk", "x"), (13, "rt_sigaction", "ixx"), (14, "rt_sigprocmask", "ixx"),
+    (15, "rt_sigreturn", ""), (16, "ioctl", "ixx"), (17, "pread64", "ixii"),
+    (18, "pwrite64", "ixii"), (19, "readv", "ixi"), (20, "writev", "ixi"), (21, "access", "sx"),
+    (22, "pipe", "x"), (23, "select", "ixxxx"), (24, "sched_yield", ""), (25, "mremap", "xiix"),
+    (26, "msync", "xix"), (27, "mincore", "xix"), (28, "madvise", "xii"), (29, "shmget", "iio"),
+    (30, "shmat", "ixx"), (31, "shmctl", "iix"), (32, "dup", "i"), (33, "dup2", "ii"),
+    (34, "pause", ""), (35, "sleep", "i"), (36, "getitimer", "ix"), (37, "alarm", "i"),
+    (38, "setitimer", "ixx"), (39, "getpid", ""), (40, "sendfile", "iixi"),
+    (41, "socket", "iii"), (42, "connect", "ixi"), (43, "accept", "ixx"),
+    (44, "sendto", "ixixxi"), (45, "recvfrom", "ixixxx"), (49, "bind", "ixi"),
+    (50, "listen", "ii"), (57, "fork", ""), (58, "vfork", ""), (59, "execve", "s"),
+    (60, "exit", "i"), (61, "wait4", "ixxx"), (62, "kill", "ii"), (63, "uname", "x"),
+    (67, "shmdt", "x"), (72, "fcntl", "iix"), (73, "flock", "ii"), (74, "fsync", "i"),
+    (75, "fdatasync", "i"), (76, "truncate", "ii"), (77, "ftruncate", "ii"),
+    (78, "readdir", "sxi"), (79, "getcwd", "xi"), (80, "chdir", "s"), (81, "fchdir", "i"),
+    (82, "rename", "ss"), (83, "mkdir", "s"), (84, "rmdir", "s"), (85, "creat", "so"),
+    (87, "unlink", "s"), (89, "readlink", "sxi"), (90, "chmod", "so"), (91, "fchmod", "io"),
+    (92, "chown", "sii"), (93, "fchown", "iii"), (94, "lchown", "sii"), (95, "umask", "o"),
+    (96, "gettime", ""), (97, "getrlimit", "ix"), (98, "getrusage", "ix"), (99, "sysinfo", "x"),
+    (100, "times", "x"), (101, "ptrace", "iixx"), (102, "getuid", ""), (103, "syslog", "ixi"),
+    (104, "getgid", ""), (105, "setuid", "i"), (106, "setgid", "i"), (107, "geteuid", ""),
+    (108, "getegid", ""), (109, "setpgid", "ii"), (110, "getppid", ""), (111, "getpgrp", ""),
+    (112, "setsid", ""), (113, "setreuid", "ii"), (114, "setregid", "ii"),
+    (115, "getgroups", "ix"), (116, "setgroups", "ix"), (117, "setresuid", "iii"),
+    (118, "getresuid", "xxx"), (119, "setresgid", "iii"), (120, "getresgid", "xxx"),
+    (121, "getpgid", "i"), (122, "setfsuid", "i"), (123, "setfsgid", "i"),
+    (127, "rt_sigpending", "x"), (130, "rt_sigsuspend", "x"), (131, "sigaltstack", "xx"),
+    (158, "arch_prctl", "xx"), (160, "setrlimit", "ix"), (162, "sync", ""),
+    (167, "swapon", "sx"), (168, "swapoff", "s"), (169, "reboot", "xxx"),
+    (186, "gettid", ""),
+    (202, "futex", "xixxxi"), (218, "set_tid_address", "x"), (228, "clock_gettime", "ix"),
+    (231, "exit_group", "i"), (257, "openat", "ixxo"), (270, "pselect6", "ixxxxx"),
+    (293, "pipe2", "xx"), (302, "prlimit64", "iixx"), (400, "print", "s"),
+    (401, "getchar", ""), (402, "get_system_info", "x"), (403, "getenv", "sxi"),
+    (404, "setenv", "ss"), (405, "exec_args", "ss"), (406, "send", "is"), (407, "recv", "ixi"),
+    (408, "close_socket", "i"), (415, "msgq_create", "i"), (416, "msgsnd", "ixi"),
+    (417, "msgrcv", "ixi"), (418, "msgq_destroy", "i"), (419, "msgrcv_wait", "ixi"),
+    (420, "msgq_len", "i"), (425, "gui_create", "xxii"), (426, "gui_destroy", "i"),
+    (427, "gui_fill_rect", "ixxx"), (428, "gui_draw_text", "ixsx"), (429, "gui_present", "i"),
+    (430, "gui_poll_event", "ix"), (431, "gui_get_size", "ix"), (432, "gui_blit_shm", "ixxx"),
+    (433, "install_query", "x"), (434, "install_begin", "x"), (435, "dns_resolve", "sx"),
+    (436, "systrace", "xi"), (437, "loadkeys", "s"),
+];
+
+/// Syscalls whose result is an address, printed in hex.
+const ADDR_RESULT: &[u64] = &[9, 12, 25, 30];
+
+fn lookup(nr: u64) -> Option<(&'static str, &'static str)> {
+    SYSCALLS.iter().find(|e| e.0 == nr).map(|e| (e.1, e.2))
+}
+
+fn signal_name(sig: i32) -> &'static str {
+    const NAMES: [&str; 32] = [
+        "SIG0", "SIGHUP", "SIGINT", "SIGQUIT", "SIGILL", "SIGTRAP", "SIGABRT", "SIGBUS",
+        "SIGFPE", "SIGKILL", "SIGUSR1", "SIGSEGV", "SIGUSR2", "SIGPIPE", "SIGALRM", "SIGTERM",
+        "SIGSTKFLT", "SIGCHLD", "SIGCONT", "SIGSTOP", "SIGTSTP", "SIGTTIN", "SIGTTOU", "SIGURG",
+        "SIGXCPU", "SIGXFSZ", "SIGVTALRM", "SIGPROF", "SIGWINCH", "SIGIO", "SIGPWR", "SIGSYS",
+    ];
+    NAMES.get(sig as usize).copied().unwrap_or("SIG?")
+}
+
+fn errno_name(e: i64) -> &'static str {
+    match e {
+        1 => "EPERM", 2 => "ENOENT", 3 => "ESRCH", 4 => "EINTR", 5 => "EIO", 9 => "EBADF",
+        10 => "ECHILD", 11 => "EAGAIN", 12 => "ENOMEM", 13 => "EACCES", 14 => "EFAULT",
+        17 => "EEXIST", 20 => "ENOTDIR", 21 => "EISDIR", 22 => "EINVAL", 24 => "EMFILE",
+        25 => "ENOTTY", 28 => "ENOSPC", 29 => "ESPIPE", 32 => "EPIPE", 34 => "ERANGE",
+        38 => "ENOSYS", 39 => "ENOTEMPTY",
+        _ => "E?",
+    }
+}
+
+/// One output line, assembled in a fixed buffer so that the tracee's own
+/// output (which shares the console) cannot land in the middle of it.
+struct Line {
+    buf: [u8; 160],
+    len: usize,
+}
+
+impl Line {
+    const fn new() -> Self { Self { buf: [0; 160], len: 0 } }
+
+    fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.buf[..self.len]).unwrap_or("")
+    }
+
+    fn clear(&mut self) { self.len = 0; }
+}
+
+impl Write for Line {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let n = s.len().min(self.buf.len() - self.len);
+        self.buf[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+        self.len += n;
+        Ok(())
+    }
+}
+
+/// Append `len` bytes of tracee memory at `addr` as a quoted, escaped string.
+fn put_string(line: &mut Line, pid: u32, addr: u64, len: u64) {
+    let shown = (len as usize).min(STR_PREVIEW);
+    let _ = line.write_char('"');
+    let mut i = 0usize;
+    while i < shown {
+        let Ok(word) = ptrace_peek(pid, addr + i as u64) else {
+            let _ = write!(line, "\" <{:#x}>", addr);
+            return;
+        };
+        for &b in word.to_le_bytes().iter().take(shown - i) {
+            let _ = match b {
+                b'\n' => line.write_str("\\n"),
+                b'\t' => line.write_str("\\t"),
+                b'"'  => line.write_str("\\\""),
+                b'\\' => line.write_str("\\\\"),
+                0x20..=0x7e => line.write_char(b as char),
+                _ => write!(line, "\\x{:02x}", b),
+            };
+        }
+        i += 8;
+    }
+    let _ = line.write_char('"');
+    if shown < len as usize { let _ = line.write_str("..."); }
+}
+
+/// Append `name(args` for syscall `nr` with arguments `args`.
+fn put_entry(line: &mut Line, pid: u32, nr: u64, args: &[u64]) {
+    let Some((name, layout)) = lookup(nr) else {
+        let _ = write!(line, "syscall_{}({:#x}, {:#x}, {:#x}", nr, args[0], args[1], args[2]);
+        return;
+    };
+    let _ = write!(line, "{}(", name);
+    let mut a = 0usize;
+    for (n, kind) in layout.bytes().enumerate() {
+        if n > 0 { let _ = line.write_str(", "); }
+        if a >= args.len() { break; }
+        let _ = match kind {
+            b'i' => write!(line, "{}", args[a] as i64),
+            b'o' => write!(line, "{:#o}", args[a]),
+            b's' if a + 1 < args.len() => {
+                put_string(line, pid, args[a], args[a + 1]);
+                a += 1;
+                Ok(())
+            }
+            _ => write!(line, "{:#x}", args[a]),
+        };
+        a += 1;
+    }
+}
+
+/// Append ` = result` for syscall `nr`.
+fn put_result(line: &mut Line, nr: u64, rval: i64, is_error: bool) {
+    let _ = if is_error {
+        write!(line, ") = -1 {} ({})", errno_name(-rval), -rval)
+    } else if ADDR_RESULT.contains(&nr) {
+        write!(line, ") = {:#x}", rval)
+    } else {
+        write!(line, ") = {}", rval)
+    };
+}
+
+fn spawn_traced(prog: &str, args: &str) -> u32 {
+    let pid = fork();
+    if pid < 0 {
+        println!("strace: fork failed ({})", pid);
+        exit(1);
+    }
+    if pid == 0 {
+        let _ = ptrace(ptrace::TRACEME, 0, 0, 0);
+        let r = exec_args(prog, args);
+        println!("strace: {}: cannot execute ({})", prog, r);
+        exit(127);
+    }
+    pid as u32
+}
+
+// ── strace -p: follow /proc/trace ─────────────────────────────────────────
+
+/// How often `/proc/trace` is polled.
+const POLL_MS: u64 = 50;
+
+/// Call `f` with each line of the file at `path`.  Lines longer than the
+/// buffer are cut short.
+fn for_each_line(path: &str, mut f: impl FnMut(&str)) {
+    let fd = open(path, 1);
+    if fd < 0 { return; }
+    let mut chunk = [0u8; 512];
+    let mut line  = [0u8; 256];
+    let mut len   = 0usize;
+    loop {
+        let n = read(fd, &mut chunk);
+        if n <= 0 { break; }
+        for &b in &chunk[..n as usize] {
+            if b == b'\n' {
+                f(core::str::from_utf8(&line[..len]).unwrap_or(""));
+                len = 0;
+            } else if len < line.len() {
+                line[len] = b;
+                len += 1;
+            }
+        }
+    }
+    if len > 0 { f(core::str::from_utf8(&line[..len]).unwrap_or("")); }
+    close(fd);
+}
+
+/// Is `pid` still listed in `/proc/sys/kernel/trace`?
+fn still_traced(pid: u32) -> bool {
+    let mut found = false;
+    for_each_line("/proc/sys/kernel/trace", |l| {
+        found |= l.trim() == "1" || l.split(' ').any(|w| w.parse::<u32>() == Ok(pid));
+    });
+    found
+}
+
+/// Print the records of `pid` after sequence number `last`, in order,
+/// stopping at one still in progress.  Returns the new `last`.
+fn drain(pid: u32, mut last: u64) -> u64 {
+    let mut blocked = false;
+    for_each_line("/proc/trace", |l| {
+        if blocked || l.starts_with('#') { return; }
+        let mut fields = l.splitn(4, ' ');
+        let (Some(seq), Some(rpid), Some(_tick), Some(call)) =
+            (fields.next(), fields.next(), fields.next(), fields.next()) else { return };
+        let (Ok(seq), Ok(rpid)) = (seq.parse::<u64>(), rpid.parse::<u32>()) else { return };
+        if seq <= last { return; }
+        if rpid == pid {
+            if call.ends_with(" ...") { blocked = true; return; }
+            println!("{}", call);
+        }
+        last = seq;
+    });
+    last
+}
+
+fn watch(pid: u32) -> ! {
+    let r = systrace(systrace::ADD_PID, pid);
+    if r < 0 {
+        println!("strace: cannot trace pid {} ({})", pid, r);
+        exit(1);
+    }
+    // Only calls made from now on.
+    let mut last = 0u64;
+    for_each_line("/proc/trace", |l| {
+        if let Some(seq) = l.split(' ').next().and_then(|s| s.parse::<u64>().ok()) {
+            last = last.max(seq);
+        }
+    });
+    println!("strace: following pid {}", pid);
+    loop {
+        sleep_ms(POLL_MS);
+        last = drain(pid, last);
+        if !still_traced(pid) {
+            let _ = drain(pid, last);
+            println!("+++ pid {} no longer traced +++", pid);
+            exit(0);
+        }
+    }
+}
+
+// ── strace PROG: ptrace a child ───────────────────────────────────────────
+
+#[unsafe(no_mangle)]
+pub extern "C" fn oxide_main() {
+    if argc() < 2 {
+        println!("Usage: strace <program> [args...] | strace -p <pid>");
+        exit(1);
+    }
+    if arg(1) == Some("-p") {
+        match arg(2).and_then(|p| p.parse::<u32>().ok()) {
+            Some(pid) => watch(pid),
+            None => {
+                println!("strace: -p needs a pid");
+                exit(1);
+            }
+        }
+    }
+    let prog = arg(1).unwrap_or("");
+
+    // exec_args takes argv[1..] as one space-separated string.
+    let mut args = [0u8; 256];
+    let mut alen = 0usize;
+    for i in 2..argc() {
+        let a = arg(i).unwrap_or("").as_bytes();
+        if alen + a.len() + 1 > args.len() { break; }
+        if alen > 0 { args[alen] = b' '; alen += 1; }
+        args[alen..alen + a.len()].copy_from_slice(a);
+        alen += a.len();
+    }
+    let args = core::str::from_utf8(&args[..alen]).unwrap_or("");
+
+    let pid = spawn_traced(prog, args);
+    let mut status = 0i32;
+
+    // The child stops with SIGTRAP once exec has loaded the program.
+    if wait4(pid as i32, &mut status, 0) < 0 || !wifstopped(status) {
+        println!("strace: {}: did not start", prog);
+        exit(1);
+    }
+    let opts = ptrace::O_TRACESYSGOOD | ptrace::O_EXITKILL;
+    let _ = ptrace(ptrace::SETOPTIONS, pid, 0, opts);
+
+    let mut line = Line::new();
+    let mut pending: Option<u64> = None;
+    let mut inject = 0u64;
+    loop {
+        if ptrace(ptrace::SYSCALL, pid, 0, inject) < 0 { break; }
+        inject = 0;
+        if wait4(pid as i32, &mut status, 0) < 0 { break; }
+
+        if wifexited(status) || wifsignaled(status) {
+            if pending.take().is_some() {
+                println!("{}) = ?", line.as_str());
+            }
+            if wifexited(status) {
+                println!("+++ exited with {} +++", wexitstatus(status));
+                exit(wexitstatus(status));
+            }
+            println!("+++ killed by {} +++", signal_name(wtermsig(status)));
+            exit(128 + wtermsig(status));
+        }
+
+        let sig = wstopsig(status);
+        if sig != SIGTRAP | 0x80 {
+            if let Some(nr) = pending.take() {
+                // A successful exec never returns; it stops with SIGTRAP.
+                if sig == SIGTRAP && (nr == 59 || nr == 405) {
+                    println!("{}) = 0", line.as_str());
+                    continue;
+                }
+                println!("{} <unfinished ...>", line.as_str());
+            }
+            println!("--- {} ---", signal_name(sig));
+            if sig != SIGTRAP { inject = sig as u64; }
+            continue;
+        }
+
+        let Ok(info) = ptrace_syscall_info(pid) else { continue };
+        match info.op {
+            ptrace::SYSCALL_INFO_ENTRY => {
+                if pending.is_some() {
+                    // Restarted after blocking: the first attempt never returned.
+                    println!("{} <unfinished ...>", line.as_str());
+                }
+                let nr = info.data[0];
+                line.clear();
+                put_entry(&mut line, pid, nr, &info.data[1..7]);
+                pending = Some(nr);
+            }
+            ptrace::SYSCALL_INFO_EXIT => {
+                let Some(nr) = pending.take() else { continue };
+                put_result(&mut line, nr, info.data[0] as i64, info.data[1] & 0xff != 0);
+                println!("{}", line.as_str());
+            }
+            _ => {}
+        }
+    }
+    println!("strace: lost track of pid {}", pid);
+    exit(1);
+}
@@ -0,0 +1,67 @@
+//! su — run a shell as another user
+//! Usage: su [-] [user]
+//!
+//! Switches to `user` (default root) and runs their login shell.  Callers
+//! other than root must type the target's password, checked against
+//! `/etc/shadow` — `su` is installed set-uid root so it can read that file
+//! and change ids.  With `-` the shell starts in the user's home directory.
+#![no_std]
+#![no_main]
+
+use oxide_rt::passwd::{self, become_user, find_user, getpass, shadow_hash, verify};
+use oxide_rt::{arg, argc, chdir, exec_args, exit, geteuid, getuid, println, sleep_ms};
+
+#[unsafe(no_mangle)]
+pub extern "C" fn oxide_main() {
+    let mut login = false;
+    let mut name  = "root";
+    for i in 1..argc() {
+        match arg(i).unwrap_or("") {
+            "-" | "-l" | "--login" => login = true,
+            a if a.starts_with('-') => {
+                println!("Usage: su [-] [user]");
+                exit(1);
+            }
+            a => name = a,
+        }
+    }
+
+    if geteuid() != 0 {
+        println!("su: must be set-uid root");
+        exit(1);
+    }
+
+    let mut pbuf = [0u8; 2048];
+    let passwd = passwd::load("/etc/passwd", &mut pbuf);
+    let Some(user) = find_user(passwd, name) else {
+        println!("su: user {} does not exist", name);
+        exit(1);
+    };
+
+    if getuid() != 0 {
+        let mut sbuf = [0u8; 2048];
+        let shadow = passwd::load("/etc/shadow", &mut sbuf);
+        let mut pw = [0u8; 128];
+        let n = getpass("Password: ", &mut pw);
+        let ok = shadow_hash(shadow, user.name).is_some_and(|h| verify(&pw[..n], h));
+        pw.fill(0);
+        if !ok {
+            sleep_ms(1000);
+            println!("su: Authentication failure");
+            exit(1);
+        }
+    }
+
+    let mut gbuf = [0u8; 2048];
+    let group = passwd::load("/etc/group", &mut gbuf);
+    if become_user(group, &user) < 0 {
+        println!("su: cannot switch to {}", user.name);
+        exit(1);
+    }
+    if login && chdir(user.home) < 0 {
+        let _ = chdir("/");
+    }
+    exec_args(user.shell, "");
+    println!("su: cannot run {}", user.shell);
+    exit(127);
+}
@@ -202,6 +202,25 @@ pub mod raw {
         }
         ret
     }
+
+    #[inline(always)]
+    pub unsafe fn syscall6(nr: u64, a1: u64, a2: u64, a3: u64, a4: u64, a5: u64, a6: u64) -> i64 {
+        let ret: i64;
+        unsafe {
+            core::arch::asm!(
+                "int 0x80",
+                inlateout("rax") nr => ret,
+                in("rdi") a1,
+                in("rsi") a2,
+                in("rdx") a3,
+                in("r10") a4,
+                in("r8")  a5,
+                in("r9")  a6,
+                options(nostack)
+            );
+        }
+        ret
+    }
 }
 
 // ── Syscall numbers (Linux x86-64 ABI) ──────────────────────────────────────
@@ -216,16 +235,27 @@ pub mod sys {
     pub const STAT:     u64 = 4;
     pub const FSTAT:    u64 = 5;
     pub const MMAP:     u64 = 9;
+    pub const MPROTECT: u64 = 10;
     pub const MUNMAP:   u64 = 11;
     pub const BRK:      u64 = 12;
     pub const SIGACTION: u64 = 13;
     pub const SIGRETURN: u64 = 15;
+    pub const SIGALTSTACK: u64 = 131;
+    pub const GETRLIMIT: u64 = 97;
+    pub const SETRLIMIT: u64 = 160;
+    pub const PTRACE:   u64 = 101;
     pub const IOCTL:    u64 = 16;
     pub const PIPE:     u64 = 22;
+    pub const MREMAP:   u64 = 25;
+    pub const MSYNC:    u64 = 26;
     pub const SHMGET:   u64 = 29;
     pub const SHMAT:    u64 = 30;
     pub const DUP2:     u64 = 33;
+    pub const PAUSE:    u64 = 34;
     pub const SLEEP:    u64 = 35;
+    pub const GETITIMER: u64 = 36;
+    pub const ALARM:    u64 = 37;
+    pub const SETITIMER: u64 = 38;
     pub const GETPID:   u64 = 39;
     pub const SOCKET:   u64 = 41;
     pub const CONNECT:  u64 = 42;
@@ -237,7 +267,7 @@ pub mod sys {
     pub const FORK:     u64 = 57;
     pub const EXEC:     u64 = 59;
     pub const EXIT:     u64 = 60;
-    pub const WAIT:     u64 = 61;
+    pub const WAIT4:    u64 = 61;
     pub const KILL:     u64 = 62;
     pub const SHMDT:    u64 = 67;
     pub const TRUNCATE: u64 = 76;
@@ -249,7 +279,51 @@ pub mod sys {
     pub const UNLINK:   u64 = 87;
     pub const CHMOD:    u64 = 90;
     pub const CHOWN:    u64 = 92;
+    pub const FCHOWN:   u64 = 93;
     pub const GETTIME:  u64 = 96;
+    pub const GETRUSAGE: u64 = 98;
+    pub const TIMES:    u64 = 100;
+    pub const GETUID:    u64 = 102;
+    pub const GETGID:    u64 = 104;
+    pub const SETUID:    u64 = 105;
+    pub const SETGID:    u64 = 106;
+    pub const GETEUID:   u64 = 107;
+    pub const GETEGID:   u64 = 108;
+    pub const SETPGID:   u64 = 109;
+    pub const GETPGRP:   u64 = 111;
+    pub const SETSID:    u64 = 112;
+    pub const SETREUID:  u64 = 113;
+    pub const SETREGID:  u64 = 114;
+    pub const GETGROUPS: u64 = 115;
+    pub const SETGROUPS: u64 = 116;
+    pub const SETRESUID: u64 = 117;
+    pub const GETRESUID: u64 = 118;
+    pub const SETRESGID: u64 = 119;
+    pub const GETRESGID: u64 = 120;
+    pub const GETPGID:   u64 = 121;
+    pub const SETFSUID:  u64 = 122;
+    pub const SETFSGID:  u64 = 123;
+    pub const GETSID:    u64 = 124;
+    pub const SWAPON:   u64 = 167;
+    pub const SWAPOFF:  u64 = 168;
+    pub const SYNC:     u64 = 162;
+    pub const REBOOT:   u64 = 169;
+    pub const TIMER_CREATE:     u64 = 222;
+    pub const TIMER_SETTIME:    u64 = 223;
+    pub const TIMER_GETTIME:    u64 = 224;
+    pub const TIMER_GETOVERRUN: u64 = 225;
+    pub const TIMER_DELETE:     u64 = 226;
+    pub const CLOCK_GETTIME:    u64 = 228;
+    pub const TIMERFD_CREATE:   u64 = 283;
+    pub const TIMERFD_SETTIME:  u64 = 286;
+    pub const TIMERFD_GETTIME:  u64 = 287;
+    pub const SIGNALFD4:        u64 = 289;
+    pub const EVENTFD2:         u64 = 290;
+    pub const EPOLL_WAIT:       u64 = 232;
+    pub const EPOLL_CTL:        u64 = 233;
+    pub const EPOLL_PWAIT:      u64 = 281;
+    pub const EPOLL_CREATE1:    u64 = 291;
+    pub const GETRANDOM:        u64 = 318;
     // OxideOS-specific (≥ 400)
     pub const PRINT:        u64 = 400;
     pub const GETCHAR:      u64 = 401;
@@ -267,6 +341,8 @@ pub mod sys {
     pub const MSGRCV_WAIT:  u64 = 419;
     pub const MSGQ_LEN:     u64 = 420;
     pub const DNS_RESOLVE:  u64 = 435;
+    pub const SYSTRACE:     u64 = 436;
+    pub const LOADKEYS:     u64 = 437;
 }
 
 // ── TTY / termios structs ─────────────────────────────────────────────────────
@@ -311,6 +387,10 @@ pub mod ioctl {
     pub const TIOCSPGRP:  u64 = 0x5410;
     pub const TIOCGWINSZ: u64 = 0x5413;
     pub const TIOCSWINSZ: u64 = 0x5414;
+    pub const TIOCNOTTY:  u64 = 0x5422;
+    pub const TIOCGSID:   u64 = 0x5429;
+    pub const TIOCGPTN:   u64 = 0x8004_5430;
+    pub const TIOCSPTLCK: u64 = 0x4004_5431;
 }
 
 /// Perform an ioctl on `fd`. `arg` is typically a pointer to a struct.
@@ -319,6 +399,46 @@ pub fn ioctl(fd: i32, request: u64, arg: u64) -> i64 {
     unsafe { raw::syscall3(sys::IOCTL, fd as u64, request, arg) }
 }
 
+/// `open` flags for `posix_openpt`.
+pub const O_RDWR:     u32 = 2;
+pub const O_NONBLOCK: u32 = 0x800;
+
+/// Open `/dev/ptmx`: a new pseudo-terminal master, locked until
+/// `unlockpt`.  `flags` is `O_RDWR`, optionally with `O_NONBLOCK`.
+pub fn posix_openpt(flags: u32) -> i32 {
+    open("/dev/ptmx", flags)
+}
+
+/// Kept for POSIX compatibility: slave ownership is already right.
+pub fn grantpt(_master: i32) -> i32 {
+    0
+}
+
+/// Allow the slave of `master` to be opened.
+pub fn unlockpt(master: i32) -> i64 {
+    let unlock: i32 = 0;
+    ioctl(master, ioctl::TIOCSPTLCK, &unlock as *const i32 as u64)
+}
+
+/// Write the slave path of `master` (`/dev/pts/N`) into `buf`.
+pub fn ptsname(master: i32, buf: &mut [u8; 16]) -> Option<&str> {
+    let mut n: u32 = 0;
+    if ioctl(master, ioctl::TIOCGPTN, &mut n as *mut u32 as u64) < 0 { return None; }
+    let prefix = b"/dev/pts/";
+    buf[..prefix.len()].copy_from_slice(prefix);
+    let mut len = prefix.len();
+    let mut digits = [0u8; 10];
+    let mut d = 0;
+    loop {
+        digits[d] = b'0' + (n % 10) as u8;
+        d += 1;
+        n /= 10;
+        if n == 0 { break; }
+    }
+    while d > 0 { d -= 1; buf[len] = digits[d]; len += 1; }
+    core::str::from_utf8(&buf[..len]).ok()
+}
+
 // ── Signal handling ───────────────────────────────────────────────────────────
 
 /// Standard signal numbers.
@@ -327,10 +447,19 @@ pub mod sig {
     pub const SIGINT:  u32 = 2;
     pub const SIGQUIT: u32 = 3;
     pub const SIGKILL: u32 = 9;
+    pub const SIGUSR1: u32 = 10;
+    pub const SIGUSR2: u32 = 12;
+    pub const SIGALRM: u32 = 14;
     pub const SIGTERM: u32 = 15;
     pub const SIGCHLD: u32 = 17;
     pub const SIGCONT: u32 = 18;
     pub const SIGSTOP: u32 = 19;
+    pub const SIGTSTP: u32 = 20;
+    pub const SIGTTIN: u32 = 21;
+    pub const SIGTTOU: u32 = 22;
+    pub const SIGVTALRM: u32 = 26;
+    pub const SIGPROF: u32 = 27;
+    pub const SIGWINCH: u32 = 28;
 }
 
 /// Default signal action (terminate the process for most signals).
@@ -341,12 +470,36 @@ pub const SIG_IGN: u64 = 1;
 /// Type alias for a signal handler function pointer.
 pub type SigHandler = unsafe extern "C" fn(signum: i32);
 
+/// `struct k_sigaction` as passed to `rt_sigaction`.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct KSigAction {
+    pub handler:  u64,
+    pub flags:    u64,
+    pub restorer: u64,
+    pub mask:     u64,
+}
+
+/// `sa_flags`: call the handler as `fn(signo, *siginfo, *ucontext)`.
+pub const SA_SIGINFO: u64 = 0x0000_0004;
+/// `sa_flags`: run the handler on the `sigaltstack` stack.
+pub const SA_ONSTACK: u64 = 0x0800_0000;
+/// `sa_flags`: restart an interrupted blocking syscall instead of failing with EINTR.
+pub const SA_RESTART: u64 = 0x1000_0000;
+
 /// Register a signal handler for `signum`.
 /// `handler` is the user-space function to call, or `SIG_DFL`/`SIG_IGN`.
 /// Returns 0 on success, negative on error.
 #[inline]
 pub fn sigaction(signum: u32, handler: u64) -> i64 {
-    unsafe { raw::syscall3(sys::SIGACTION, signum as u64, handler, 0) }
+    sigaction_full(signum, &KSigAction { handler, ..KSigAction::default() }, None)
+}
+
+/// Full `rt_sigaction`: install `act`, optionally returning the previous action.
+#[inline]
+pub fn sigaction_full(signum: u32, act: &KSigAction, old: Option<&mut KSigAction>) -> i64 {
+    let old_ptr = old.map_or(0, |o| o as *mut KSigAction as u64);
+    unsafe { raw::syscall4(sys::SIGACTION, signum as u64, act as *const KSigAction as u64, old_ptr, 8) }
 }
 
 /// Restore the context saved before a signal handler was called.
@@ -356,6 +509,35 @@ pub fn sigreturn() -> i64 {
     unsafe { raw::syscall1(sys::SIGRETURN, 0) }
 }
 
+/// Install `[sp, sp + size)` as the alternate stack for `SA_ONSTACK`
+/// handlers (`size` must be at least 2048).  Returns 0 on success.
+#[inline]
+pub fn sigaltstack(sp: u64, size: u64) -> i64 {
+    // stack_t { ss_sp, ss_flags, ss_size }
+    let ss: [u64; 3] = [sp, 0, size];
+    unsafe { raw::syscall2(sys::SIGALTSTACK, ss.as_ptr() as u64, 0) }
+}
+
+/// `RLIMIT_CORE` — largest core file written when the process crashes.
+pub const RLIMIT_CORE: u32 = 4;
+/// No limit.
+pub const RLIM_INFINITY: u64 = u64::MAX;
+
+/// Current `(soft, hard)` limit for `resource`, or a negative errno.
+#[inline]
+pub fn getrlimit(resource: u32) -> Result<(u64, u64), i64> {
+    let mut lim = [0u64; 2];
+    let r = unsafe { raw::syscall2(sys::GETRLIMIT, resource as u64, lim.as_mut_ptr() as u64) };
+    if r < 0 { Err(r) } else { Ok((lim[0], lim[1])) }
+}
+
+/// Set the `(soft, hard)` limit for `resource`.  Returns 0 on success.
+#[inline]
+pub fn setrlimit(resource: u32, soft: u64, hard: u64) -> i64 {
+    let lim = [soft, hard];
+    unsafe { raw::syscall2(sys::SETRLIMIT, resource as u64, lim.as_ptr() as u64) }
+}
+
 /// Send signal `signum` to process `pid`. Returns 0 on success.
 #[inline]
 pub fn kill_signal(pid: u32, signum: u32) -> i64 {
@@ -385,6 +567,639 @@ pub fn shmdt(addr: usize) -> i64 {
     unsafe { raw::syscall1(sys::SHMDT, addr as u64) }
 }
 
+// ── Memory mappings ───────────────────────────────────────────────────────────
+
+pub const PROT_NONE:  u32 = 0x0;
+pub const PROT_READ:  u32 = 0x1;
+pub const PROT_WRITE: u32 = 0x2;
+pub const PROT_EXEC:  u32 = 0x4;
+
+pub const MAP_SHARED:    u32 = 0x01;
+pub const MAP_PRIVATE:   u32 = 0x02;
+pub const MAP_FIXED:     u32 = 0x10;
+pub const MAP_ANONYMOUS: u32 = 0x20;
+
+pub const MREMAP_MAYMOVE: u32 = 1;
+pub const MS_SYNC:        u32 = 4;
+
+/// Map `len` bytes of `fd` starting at `offset` (or anonymous memory with
+/// `MAP_ANONYMOUS`).  Returns the mapped address or a negative error code.
+#[inline]
+pub fn mmap(addr: usize, len: usize, prot: u32, flags: u32, fd: i32, offset: u64) -> i64 {
+    unsafe {
+        raw::syscall6(sys::MMAP, addr as u64, len as u64, prot as u64,
+                      flags as u64, fd as i64 as u64, offset)
+    }
+}
+
+/// Change the protection of the pages in `[addr, addr + len)`.
+#[inline]
+pub fn mprotect(addr: usize, len: usize, prot: u32) -> i64 {
+    unsafe { raw::syscall3(sys::MPROTECT, addr as u64, len as u64, prot as u64) }
+}
+
+/// Write dirty pages of a `MAP_SHARED` file mapping back to the file.
+#[inline]
+pub fn msync(addr: usize, len: usize, flags: u32) -> i64 {
+    unsafe { raw::syscall3(sys::MSYNC, addr as u64, len as u64, flags as u64) }
+}
+
+/// Grow or shrink a mapping.  With `MREMAP_MAYMOVE` the kernel may move it.
+#[inline]
+pub fn mremap(addr: usize, old_len: usize, new_len: usize, flags: u32) -> i64 {
+    unsafe {
+        raw::syscall4(sys::MREMAP, addr as u64, old_len as u64, new_len as u64, flags as u64)
+    }
+}
+
+// ── Randomness ────────────────────────────────────────────────────────────────
+
+/// `getrandom` flags.  The kernel generator is seeded at boot, so neither
+/// ever changes the outcome.
+pub const GRND_NONBLOCK: u32 = 1;
+pub const GRND_RANDOM:   u32 = 2;
+
+/// Fill `buf` from the kernel CSPRNG; returns the number of bytes written.
+pub fn getrandom(buf: &mut [u8], flags: u32) -> i64 {
+    unsafe { raw::syscall3(sys::GETRANDOM, buf.as_mut_ptr() as u64, buf.len() as u64, flags as u64) }
+}
+
+// ── Input devices ─────────────────────────────────────────────────────────────
+
+/// `struct input_event`, as read from `/dev/input/eventN`.
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct InputEvent {
+    pub sec:   i64,
+    pub usec:  i64,
+    pub type_: u16,
+    pub code:  u16,
+    pub value: i32,
+}
+
+/// Event types and the codes the kernel's devices produce.
+pub mod input {
+    pub const EV_SYN: u16 = 0x00;
+    pub const EV_KEY: u16 = 0x01;
+    pub const EV_REL: u16 = 0x02;
+    pub const EV_LED: u16 = 0x11;
+    pub const EV_REP: u16 = 0x14;
+
+    pub const SYN_REPORT:  u16 = 0;
+    pub const SYN_DROPPED: u16 = 3;
+    pub const REL_X: u16 = 0;
+    pub const REL_Y: u16 = 1;
+    pub const BTN_LEFT:   u16 = 0x110;
+    pub const BTN_RIGHT:  u16 = 0x111;
+    pub const BTN_MIDDLE: u16 = 0x112;
+
+    pub const EVIOCGRAB: u64 = 0x4004_4590;
+
+    /// `EVIOCGNAME(len)`.
+    pub const fn eviocgname(len: usize) -> u64 { 0x8000_4506 | ((len as u64) << 16) }
+}
+
+/// Device name of the evdev `fd` into `buf`.
+pub fn evdev_name(fd: i32, buf: &mut [u8]) -> Option<&str> {
+    let n = ioctl(fd, input::eviocgname(buf.len()), buf.as_mut_ptr() as u64);
+    if n <= 0 { return None; }
+    let len = buf[..n as usize].iter().position(|&b| b == 0).unwrap_or(n as usize);
+    core::str::from_utf8(&buf[..len]).ok()
+}
+
+/// Take (or give back) exclusive use of the evdev `fd`.
+pub fn evdev_grab(fd: i32, grab: bool) -> i64 {
+    ioctl(fd, input::EVIOCGRAB, grab as u64)
+}
+
+/// Read whole events from the evdev `fd`; returns how many, or a negative
+/// errno.
+pub fn read_events(fd: i32, events: &mut [InputEvent]) -> i64 {
+    let size = core::mem::size_of::<InputEvent>();
+    let buf = unsafe {
+        core::slice::from_raw_parts_mut(events.as_mut_ptr() as *mut u8, events.len() * size)
+    };
+    let n = read(fd, buf);
+    if n < 0 { n } else { n / size as i64 }
+}
+
+/// Tunable holding the active keyboard layout name.
+pub const KEYMAP_PATH: &str = "/proc/sys/kernel/keymap";
+
+/// Switch the keyboard to the layout called `name` (`us`, `uk`, `de`, `fr`,
+/// `dvorak`, `colemak`).  Root only; the choice is saved across boots.
+pub fn loadkeys(name: &str) -> i64 {
+    unsafe { raw::syscall2(sys::LOADKEYS, name.as_ptr() as u64, name.len() as u64) }
+}
+
+// ── Sound ─────────────────────────────────────────────────────────────────────
+
+/// OSS sample formats and the `/dev/dsp` and `/dev/mixer` ioctls.
+pub mod sound {
+    pub const AFMT_U8:     u32 = 0x0008;
+    pub const AFMT_S16_LE: u32 = 0x0010;
+    pub const AFMT_S8:     u32 = 0x0040;
+
+    pub const SNDCTL_DSP_RESET:      u64 = 0x0000_5000;
+    pub const SNDCTL_DSP_SYNC:       u64 = 0x0000_5001;
+    pub const SNDCTL_DSP_SPEED:      u64 = 0xC004_5002;
+    pub const SNDCTL_DSP_SETFMT:     u64 = 0xC004_5005;
+    pub const SNDCTL_DSP_CHANNELS:   u64 = 0xC004_5006;
+    pub const SNDCTL_DSP_GETPLAYVOL: u64 = 0x8004_5018;
+    pub const SNDCTL_DSP_SETPLAYVOL: u64 = 0xC004_5018;
+
+    pub const SOUND_MIXER_READ_VOLUME:  u64 = 0x8004_4D00;
+    pub const SOUND_MIXER_READ_PCM:     u64 = 0x8004_4D04;
+    pub const SOUND_MIXER_WRITE_VOLUME: u64 = 0xC004_4D00;
+    pub const SOUND_MIXER_WRITE_PCM:    u64 = 0xC004_4D04;
+
+    /// PC speaker console ioctls: the argument is a PIT period
+    /// (`PIT_HZ / freq`); KDMKTONE carries the length in ms in its top half.
+    pub const KIOCSOUND: u64 = 0x4B2F;
+    pub const KDMKTONE:  u64 = 0x4B30;
+    pub const PIT_HZ:    u32 = 1_193_182;
+}
+
+/// Issue an int-valued OSS ioctl on `fd` with `value`; returns the value
+/// the driver settled on (the rate it chose, the level it set, …) or a
+/// negative errno.
+pub fn oss_ioctl(fd: i32, request: u64, value: i32) -> i64 {
+    let mut v = value;
+    let r = ioctl(fd, request, &mut v as *mut i32 as u64);
+    if r < 0 { r } else { v as i64 }
+}
+
+// ── Swap ──────────────────────────────────────────────────────────────────────
+
+/// Copy `path` into `buf` as a NUL-terminated C string (swapon/swapoff take
+/// Linux-style `const char *` paths).  Returns `None` if it does not fit.
+fn c_path<'a>(path: &str, buf: &'a mut [u8; 256]) -> Option<&'a [u8]> {
+    let b = path.as_bytes();
+    if b.len() >= buf.len() { return None; }
+    buf[..b.len()].copy_from_slice(b);
+    buf[b.len()] = 0;
+    Some(&buf[..=b.len()])
+}
+
+/// Enable swapping to a partition (`/dev/hda2`) or swap file.  Returns 0 on success.
+pub fn swapon(path: &str, flags: u32) -> i64 {
+    let mut buf = [0u8; 256];
+    let Some(p) = c_path(path, &mut buf) else { return -36 }; // ENAMETOOLONG
+    unsafe { raw::syscall2(sys::SWAPON, p.as_ptr() as u64, flags as u64) }
+}
+
+/// Disable the swap area at `path`, paging its contents back in.
+pub fn swapoff(path: &str) -> i64 {
+    let mut buf = [0u8; 256];
+    let Some(p) = c_path(path, &mut buf) else { return -36 }; // ENAMETOOLONG
+    unsafe { raw::syscall1(sys::SWAPOFF, p.as_ptr() as u64) }
+}
+
+// ── Power ─────────────────────────────────────────────────────────────────────
+
+/// `reboot` commands (Linux values).
+pub mod reboot_cmd {
+    pub const RESTART:   u32 = 0x0123_4567;
+    pub const HALT:      u32 = 0xcdef_0123;
+    pub const POWER_OFF: u32 = 0x4321_fedc;
+}
+
+/// Flush filesystem buffers to disk.
+pub fn sync() {
+    unsafe { raw::syscall0(sys::SYNC); }
+}
+
+/// Restart, halt or power off the machine (one of `reboot_cmd::*`).  Only
+/// returns on error — `-1` (EPERM) unless running as root.  Nothing is
+/// stopped or flushed first: that is init's job.
+pub fn reboot(cmd: u32) -> i64 {
+    const MAGIC1: u64 = 0xfee1_dead;
+    const MAGIC2: u64 = 672_274_793;
+    unsafe { raw::syscall3(sys::REBOOT, MAGIC1, MAGIC2, cmd as u64) }
+}
+
+// ── Process tracing ───────────────────────────────────────────────────────────
+
+/// `ptrace` requests and options (Linux numbering).
+pub mod ptrace {
+    pub const TRACEME:     i64 = 0;
+    pub const PEEKDATA:    i64 = 2;
+    pub const PEEKUSER:    i64 = 3;
+    pub const POKEDATA:    i64 = 5;
+    pub const CONT:        i64 = 7;
+    pub const KILL:        i64 = 8;
+    pub const SINGLESTEP:  i64 = 9;
+    pub const GETREGS:     i64 = 12;
+    pub const SETREGS:     i64 = 13;
+    pub const ATTACH:      i64 = 16;
+    pub const DETACH:      i64 = 17;
+    pub const SYSCALL:     i64 = 24;
+    pub const SETOPTIONS:  i64 = 0x4200;
+    pub const GETEVENTMSG: i64 = 0x4201;
+    pub const GETSIGINFO:  i64 = 0x4202;
+    pub const GET_SYSCALL_INFO: i64 = 0x420e;
+
+    pub const SYSCALL_INFO_NONE:  u8 = 0;
+    pub const SYSCALL_INFO_ENTRY: u8 = 1;
+    pub const SYSCALL_INFO_EXIT:  u8 = 2;
+
+    pub const O_TRACESYSGOOD: u64 = 0x0000_0001;
+    pub const O_TRACEEXEC:    u64 = 0x0000_0010;
+    pub const O_EXITKILL:     u64 = 0x0010_0000;
+}
+
+/// `struct user_regs_struct` as filled in by `PTRACE_GETREGS`.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct UserRegs {
+    pub r15: u64, pub r14: u64, pub r13: u64, pub r12: u64,
+    pub rbp: u64, pub rbx: u64, pub r11: u64, pub r10: u64,
+    pub r9:  u64, pub r8:  u64, pub rax: u64, pub rcx: u64,
+    pub rdx: u64, pub rsi: u64, pub rdi: u64, pub orig_rax: u64,
+    pub rip: u64, pub cs:  u64, pub eflags: u64, pub rsp: u64,
+    pub ss:  u64, pub fs_base: u64, pub gs_base: u64,
+    pub ds:  u64, pub es:  u64, pub fs:  u64, pub gs:  u64,
+}
+
+/// `struct ptrace_syscall_info` from `PTRACE_GET_SYSCALL_INFO`.  `data`
+/// holds `nr, args[6]` at an entry stop and `rval, is_error` at an exit stop.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct SyscallInfo {
+    pub op:    u8,
+    pub pad:   [u8; 3],
+    pub arch:  u32,
+    pub instruction_pointer: u64,
+    pub stack_pointer: u64,
+    pub data:  [u64; 7],
+}
+
+/// Raw `ptrace(request, pid, addr, data)`.  Returns 0 or a negative errno.
+#[inline]
+pub fn ptrace(request: i64, pid: u32, addr: u64, data: u64) -> i64 {
+    unsafe { raw::syscall4(sys::PTRACE, request as u64, pid as u64, addr, data) }
+}
+
+/// Read the registers of stopped tracee `pid`.
+pub fn ptrace_getregs(pid: u32) -> Result<UserRegs, i64> {
+    let mut regs = UserRegs::default();
+    let r = ptrace(ptrace::GETREGS, pid, 0, &mut regs as *mut UserRegs as u64);
+    if r < 0 { Err(r) } else { Ok(regs) }
+}
+
+/// Which syscall stop tracee `pid` is in, with its number/arguments or result.
+pub fn ptrace_syscall_info(pid: u32) -> Result<SyscallInfo, i64> {
+    let mut info = SyscallInfo::default();
+    let size = core::mem::size_of::<SyscallInfo>() as u64;
+    let r = ptrace(ptrace::GET_SYSCALL_INFO, pid, size, &mut info as *mut SyscallInfo as u64);
+    if r < 0 { Err(r) } else { Ok(info) }
+}
+
+/// Read one word of tracee memory at `addr`.
+pub fn ptrace_peek(pid: u32, addr: u64) -> Result<u64, i64> {
+    let mut word = 0u64;
+    let r = ptrace(ptrace::PEEKDATA, pid, addr, &mut word as *mut u64 as u64);
+    if r < 0 { Err(r) } else { Ok(word) }
+}
+
+/// `systrace` operations for the kernel syscall tracer (`/proc/trace`).
+pub mod systrace {
+    pub const OFF:     u64 = 0;
+    pub const ALL:     u64 = 1;
+    pub const ADD_PID: u64 = 2;
+    pub const DEL_PID: u64 = 3;
+    pub const CLEAR:   u64 = 4;
+}
+
+/// Configure the kernel syscall tracer; same effect as writing
+/// `/proc/sys/kernel/trace`.  Returns 0 or a negative errno.
+#[inline]
+pub fn systrace(op: u64, pid: u32) -> i64 {
+    unsafe { raw::syscall2(sys::SYSTRACE, op, pid as u64) }
+}
+
+// ── Timers and event fds ──────────────────────────────────────────────────────
+
+/// Clock ids for `clock_gettime`, `timer_create` and `timerfd_create`.
+pub mod clock {
+    pub const REALTIME:           i32 = 0;
+    pub const MONOTONIC:          i32 = 1;
+    pub const PROCESS_CPUTIME_ID: i32 = 2;
+    pub const THREAD_CPUTIME_ID:  i32 = 3;
+    pub const BOOTTIME:           i32 = 7;
+}
+
+/// `setitimer` timer selectors.
+pub const ITIMER_REAL:    i32 = 0;
+pub const ITIMER_VIRTUAL: i32 = 1;
+pub const ITIMER_PROF:    i32 = 2;
+
+/// `timer_settime` / `timerfd_settime` flag: `value` is an absolute time.
+pub const TIMER_ABSTIME: i32 = 1;
+
+pub const EFD_SEMAPHORE: i32 = 1;
+pub const EFD_NONBLOCK:  i32 = 0o4000;
+pub const EFD_CLOEXEC:   i32 = 0o2000000;
+pub const TFD_NONBLOCK:  i32 = EFD_NONBLOCK;
+pub const SFD_NONBLOCK:  i32 = EFD_NONBLOCK;
+
+pub const RUSAGE_SELF:     i32 = 0;
+pub const RUSAGE_CHILDREN: i32 = -1;
+
+/// `struct timespec`.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct Timespec {
+    pub sec:  i64,
+    pub nsec: i64,
+}
+
+impl Timespec {
+    pub const fn from_ms(ms: u64) -> Self {
+        Timespec { sec: (ms / 1000) as i64, nsec: ((ms % 1000) * 1_000_000) as i64 }
+    }
+    pub fn as_ns(&self) -> u64 { self.sec as u64 * 1_000_000_000 + self.nsec as u64 }
+}
+
+/// `struct itimerspec` — first expiry in `value`, then every `interval`.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct ITimerspec {
+    pub interval: Timespec,
+    pub value:    Timespec,
+}
+
+/// `struct timeval`.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct Timeval {
+    pub sec:  i64,
+    pub usec: i64,
+}
+
+/// `struct itimerval` as used by `getitimer` / `setitimer`.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct ITimerval {
+    pub interval: Timeval,
+    pub value:    Timeval,
+}
+
+/// `struct rusage`; only the CPU times and context-switch counts are filled in.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct Rusage {
+    pub utime:  Timeval,
+    pub stime:  Timeval,
+    pub maxrss: i64,
+    pub ixrss:  i64,
+    pub idrss:  i64,
+    pub isrss:  i64,
+    pub minflt: i64,
+    pub majflt: i64,
+    pub nswap:  i64,
+    pub inblock: i64,
+    pub oublock: i64,
+    pub msgsnd: i64,
+    pub msgrcv: i64,
+    pub nsignals: i64,
+    pub nvcsw:  i64,
+    pub nivcsw: i64,
+}
+
+/// `struct tms` in clock ticks (100 Hz).
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct Tms {
+    pub utime:  i64,
+    pub stime:  i64,
+    pub cutime: i64,
+    pub cstime: i64,
+}
+
+/// `struct sigevent` for `timer_create`.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct SigEvent {
+    pub value:  u64,
+    pub signo:  i32,
+    pub notify: i32,
+    pub pad:    [i32; 12],
+}
+
+pub const SIGEV_SIGNAL: i32 = 0;
+pub const SIGEV_NONE:   i32 = 1;
+
+/// `struct signalfd_siginfo` — one record per `read` from a signalfd.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct SignalfdSiginfo {
+    pub signo:   u32,
+    pub errno:   i32,
+    pub code:    i32,
+    pub pid:     u32,
+    pub uid:     u32,
+    pub fd:      i32,
+    pub tid:     u32,
+    pub band:    u32,
+    pub overrun: u32,
+    pub trapno:  u32,
+    pub status:  i32,
+    pub int:     i32,
+    pub ptr:     u64,
+    pub utime:   u64,
+    pub stime:   u64,
+    pub addr:    u64,
+    pub pad:     [u8; 48],
+}
+
+impl Default for SignalfdSiginfo {
+    fn default() -> Self { unsafe { core::mem::zeroed() } }
+}
+
+/// Read clock `id`.
+pub fn clock_gettime(id: i32) -> Result<Timespec, i64> {
+    let mut ts = Timespec::default();
+    let r = unsafe { raw::syscall2(sys::CLOCK_GETTIME, id as u64, &mut ts as *mut Timespec as u64) };
+    if r < 0 { Err(r) } else { Ok(ts) }
+}
+
+/// Deliver `SIGALRM` after `secs` seconds (0 cancels).  Returns the seconds
+/// left on the previous alarm.
+#[inline]
+pub fn alarm(secs: u32) -> u32 {
+    unsafe { raw::syscall1(sys::ALARM, secs as u64) as u32 }
+}
+
+/// Sleep until a signal is handled.  Always returns `-EINTR`.
+#[inline]
+pub fn pause() -> i64 {
+    unsafe { raw::syscall0(sys::PAUSE) }
+}
+
+/// Arm interval timer `which`; returns the previous setting.
+pub fn setitimer(which: i32, new: &ITimerval) -> Result<ITimerval, i64> {
+    let mut old = ITimerval::default();
+    let r = unsafe {
+        raw::syscall3(sys::SETITIMER, which as u64,
+                      new as *const ITimerval as u64, &mut old as *mut ITimerval as u64)
+    };
+    if r < 0 { Err(r) } else { Ok(old) }
+}
+
+/// Current setting of interval timer `which`.
+pub fn getitimer(which: i32) -> Result<ITimerval, i64> {
+    let mut cur = ITimerval::default();
+    let r = unsafe { raw::syscall2(sys::GETITIMER, which as u64, &mut cur as *mut ITimerval as u64) };
+    if r < 0 { Err(r) } else { Ok(cur) }
+}
+
+/// Create a POSIX timer on `clock`.  `None` delivers `SIGALRM` on expiry.
+pub fn timer_create(clock: i32, sev: Option<&SigEvent>) -> Result<i32, i64> {
+    let mut id = 0i32;
+    let sevp = sev.map_or(0, |s| s as *const SigEvent as u64);
+    let r = unsafe { raw::syscall3(sys::TIMER_CREATE, clock as u64, sevp, &mut id as *mut i32 as u64) };
+    if r < 0 { Err(r) } else { Ok(id) }
+}
+
+/// Arm (or disarm, with a zero `value`) POSIX timer `id`.
+pub fn timer_settime(id: i32, flags: i32, new: &ITimerspec) -> Result<ITimerspec, i64> {
+    let mut old = ITimerspec::default();
+    let r = unsafe {
+        raw::syscall4(sys::TIMER_SETTIME, id as u64, flags as u64,
+                      new as *const ITimerspec as u64, &mut old as *mut ITimerspec as u64)
+    };
+    if r < 0 { Err(r) } else { Ok(old) }
+}
+
+/// Time left on POSIX timer `id`.
+pub fn timer_gettime(id: i32) -> Result<ITimerspec, i64> {
+    let mut cur = ITimerspec::default();
+    let r = unsafe { raw::syscall2(sys::TIMER_GETTIME, id as u64, &mut cur as *mut ITimerspec as u64) };
+    if r < 0 { Err(r) } else { Ok(cur) }
+}
+
+/// Expirations of timer `id` lost while its last signal was pending.
+#[inline]
+pub fn timer_getoverrun(id: i32) -> i64 {
+    unsafe { raw::syscall1(sys::TIMER_GETOVERRUN, id as u64) }
+}
+
+#[inline]
+pub fn timer_delete(id: i32) -> i64 {
+    unsafe { raw::syscall1(sys::TIMER_DELETE, id as u64) }
+}
+
+/// Create a timerfd; reads return the expiry count as a `u64`.
+#[inline]
+pub fn timerfd_create(clock: i32, flags: i32) -> i64 {
+    unsafe { raw::syscall2(sys::TIMERFD_CREATE, clock as u64, flags as u64) }
+}
+
+/// Arm timerfd `fd`; returns the previous setting.
+pub fn timerfd_settime(fd: i32, flags: i32, new: &ITimerspec) -> Result<ITimerspec, i64> {
+    let mut old = ITimerspec::default();
+    let r = unsafe {
+        raw::syscall4(sys::TIMERFD_SETTIME, fd as u64, flags as u64,
+                      new as *const ITimerspec as u64, &mut old as *mut ITimerspec as u64)
+    };
+    if r < 0 { Err(r) } else { Ok(old) }
+}
+
+pub fn timerfd_gettime(fd: i32) -> Result<ITimerspec, i64> {
+    let mut cur = ITimerspec::default();
+    let r = unsafe { raw::syscall2(sys::TIMERFD_GETTIME, fd as u64, &mut cur as *mut ITimerspec as u64) };
+    if r < 0 { Err(r) } else { Ok(cur) }
+}
+
+/// Create an eventfd holding `initval`.  Reads drain the counter (or take 1
+/// with `EFD_SEMAPHORE`); writes add to it.
+#[inline]
+pub fn eventfd(initval: u32, flags: i32) -> i64 {
+    unsafe { raw::syscall2(sys::EVENTFD2, initval as u64, flags as u64) }
+}
+
+/// Create (`fd == -1`) or update a signalfd accepting the signals in `mask`.
+/// Those signals should be blocked so they queue for the fd instead.
+#[inline]
+pub fn signalfd(fd: i32, mask: u64, flags: i32) -> i64 {
+    unsafe {
+        raw::syscall4(sys::SIGNALFD4, fd as i64 as u64, &mask as *const u64 as u64, 8, flags as u64)
+    }
+}
+
+pub fn getrusage(who: i32) -> Result<Rusage, i64> {
+    let mut ru = Rusage::default();
+    let r = unsafe { raw::syscall2(sys::GETRUSAGE, who as i64 as u64, &mut ru as *mut Rusage as u64) };
+    if r < 0 { Err(r) } else { Ok(ru) }
+}
+
+/// Process and reaped-children CPU times; returns ticks since boot.
+pub fn times(buf: &mut Tms) -> i64 {
+    unsafe { raw::syscall1(sys::TIMES, buf as *mut Tms as u64) }
+}
+
+// ── epoll ─────────────────────────────────────────────────────────────────────
+
+/// `epoll_ctl` operations and event bits.
+pub mod epoll {
+    pub const CTL_ADD: i32 = 1;
+    pub const CTL_DEL: i32 = 2;
+    pub const CTL_MOD: i32 = 3;
+
+    pub const IN:       u32 = 0x001;
+    pub const PRI:      u32 = 0x002;
+    pub const OUT:      u32 = 0x004;
+    pub const ERR:      u32 = 0x008;
+    pub const HUP:      u32 = 0x010;
+    pub const RDHUP:    u32 = 0x2000;
+    pub const ONESHOT:  u32 = 1 << 30;
+    pub const ET:       u32 = 1 << 31;
+
+    pub const CLOEXEC:  i32 = 0o2000000;
+}
+
+/// `struct epoll_event` (packed on x86-64).
+#[repr(C, packed)]
+#[derive(Clone, Copy, Default)]
+pub struct EpollEvent {
+    pub events: u32,
+    pub data:   u64,
+}
+
+/// Create an epoll instance.  Returns its fd or a negative errno.
+#[inline]
+pub fn epoll_create1(flags: i32) -> i64 {
+    unsafe { raw::syscall1(sys::EPOLL_CREATE1, flags as u64) }
+}
+
+/// Add, modify (`epoll::CTL_MOD`) or remove the watch on `fd`.
+pub fn epoll_ctl(epfd: i32, op: i32, fd: i32, events: u32, data: u64) -> i64 {
+    let ev = EpollEvent { events, data };
+    unsafe {
+        raw::syscall4(sys::EPOLL_CTL, epfd as u64, op as u64, fd as u64,
+                      &ev as *const EpollEvent as u64)
+    }
+}
+
+/// Wait up to `timeout_ms` (-1 = forever) for events.  Returns how many
+/// entries of `events` were filled, or a negative errno.
+pub fn epoll_wait(epfd: i32, events: &mut [EpollEvent], timeout_ms: i32) -> i64 {
+    unsafe {
+        raw::syscall4(sys::EPOLL_WAIT, epfd as u64, events.as_mut_ptr() as u64,
+                      events.len() as u64, timeout_ms as i64 as u64)
+    }
+}
+
+/// `epoll_wait` with the signals in `mask` blocked while waiting.
+pub fn epoll_pwait(epfd: i32, events: &mut [EpollEvent], timeout_ms: i32, mask: u64) -> i64 {
+    unsafe {
+        raw::syscall6(sys::EPOLL_PWAIT, epfd as u64, events.as_mut_ptr() as u64,
+                      events.len() as u64, timeout_ms as i64 as u64,
+                      &mask as *const u64 as u64, 8)
+    }
+}
+
 // ── File permissions ──────────────────────────────────────────────────────────
 
 /// Change permission bits on `path` (RamFS only for now).
@@ -402,6 +1217,350 @@ pub fn chown(path: &str, uid: u32, gid: u32) -> i64 {
     unsafe { raw::syscall4(sys::CHOWN, b.as_ptr() as u64, b.len() as u64, uid as u64, gid as u64) }
 }
 
+/// Change owner and group of the file open on `fd` (terminal lines only).
+#[inline]
+pub fn fchown(fd: i32, uid: u32, gid: u32) -> i64 {
+    unsafe { raw::syscall3(sys::FCHOWN, fd as u64, uid as u64, gid as u64) }
+}
+
+// ── Users and groups ──────────────────────────────────────────────────────────
+
+/// `-1` as a uid/gid: leave unchanged (setreuid, setresuid, chown, ...).
+pub const ID_UNCHANGED: u32 = u32::MAX;
+
+#[inline] pub fn getuid()  -> u32 { unsafe { raw::syscall0(sys::GETUID)  as u32 } }
+#[inline] pub fn geteuid() -> u32 { unsafe { raw::syscall0(sys::GETEUID) as u32 } }
+#[inline] pub fn getgid()  -> u32 { unsafe { raw::syscall0(sys::GETGID)  as u32 } }
+#[inline] pub fn getegid() -> u32 { unsafe { raw::syscall0(sys::GETEGID) as u32 } }
+
+#[inline]
+pub fn setuid(uid: u32) -> i64 { unsafe { raw::syscall1(sys::SETUID, uid as u64) } }
+
+#[inline]
+pub fn setgid(gid: u32) -> i64 { unsafe { raw::syscall1(sys::SETGID, gid as u64) } }
+
+#[inline]
+pub fn setreuid(ruid: u32, euid: u32) -> i64 {
+    unsafe { raw::syscall2(sys::SETREUID, ruid as u64, euid as u64) }
+}
+
+#[inline]
+pub fn setregid(rgid: u32, egid: u32) -> i64 {
+    unsafe { raw::syscall2(sys::SETREGID, rgid as u64, egid as u64) }
+}
+
+#[inline]
+pub fn setresuid(ruid: u32, euid: u32, suid: u32) -> i64 {
+    unsafe { raw::syscall3(sys::SETRESUID, ruid as u64, euid as u64, suid as u64) }
+}
+
+#[inline]
+pub fn setresgid(rgid: u32, egid: u32, sgid: u32) -> i64 {
+    unsafe { raw::syscall3(sys::SETRESGID, rgid as u64, egid as u64, sgid as u64) }
+}
+
+/// Real, effective and saved uid.
+pub fn getresuid() -> (u32, u32, u32) {
+    let (mut r, mut e, mut s) = (0u32, 0u32, 0u32);
+    unsafe {
+        raw::syscall3(sys::GETRESUID, &mut r as *mut u32 as u64,
+                      &mut e as *mut u32 as u64, &mut s as *mut u32 as u64);
+    }
+    (r, e, s)
+}
+
+/// Real, effective and saved gid.
+pub fn getresgid() -> (u32, u32, u32) {
+    let (mut r, mut e, mut s) = (0u32, 0u32, 0u32);
+    unsafe {
+        raw::syscall3(sys::GETRESGID, &mut r as *mut u32 as u64,
+                      &mut e as *mut u32 as u64, &mut s as *mut u32 as u64);
+    }
+    (r, e, s)
+}
+
+/// Fill `list` with the supplementary groups; returns how many there are.
+#[inline]
+pub fn getgroups(list: &mut [u32]) -> i64 {
+    unsafe { raw::syscall2(sys::GETGROUPS, list.len() as u64, list.as_mut_ptr() as u64) }
+}
+
+/// Replace the supplementary groups (root only).
+#[inline]
+pub fn setgroups(list: &[u32]) -> i64 {
+    unsafe { raw::syscall2(sys::SETGROUPS, list.len() as u64, list.as_ptr() as u64) }
+}
+
+/// Set the uid used for file access checks; returns the previous one.
+#[inline]
+pub fn setfsuid(uid: u32) -> u32 { unsafe { raw::syscall1(sys::SETFSUID, uid as u64) as u32 } }
+
+/// Set the gid used for file access checks; returns the previous one.
+#[inline]
+pub fn setfsgid(gid: u32) -> u32 { unsafe { raw::syscall1(sys::SETFSGID, gid as u64) as u32 } }
+
+/// `/etc/passwd`, `/etc/group` and `/etc/shadow` parsing, and the password
+/// hash used in `/etc/shadow`: `$oxide$<salt>$<hex>`, where the digest is
+/// SHA-256 of `salt ‖ password`, then re-hashed 999 times as
+/// SHA-256(`digest ‖ salt ‖ password`).
+pub mod passwd {
+    /// One `/etc/passwd` line: `name:x:uid:gid:gecos:home:shell`.
+    pub struct Passwd<'a> {
+        pub name:  &'a str,
+        pub uid:   u32,
+        pub gid:   u32,
+        pub gecos: &'a str,
+        pub home:  &'a str,
+        pub shell: &'a str,
+    }
+
+    /// One `/etc/group` line: `name:x:gid:member,member`.
+    pub struct Group<'a> {
+        pub name:    &'a str,
+        pub gid:     u32,
+        pub members: &'a str,
+    }
+
+    impl Group<'_> {
+        pub fn has_member(&self, user: &str) -> bool {
+            self.members.split(',').any(|m| m == user)
+        }
+    }
+
+    /// Print `prompt` and read a line from the terminal without echoing it,
+    /// like libc `getpass`.  Returns the length stored in `buf`.
+    pub fn getpass(prompt: &str, buf: &mut [u8]) -> usize {
+        super::print_str(prompt);
+        let mut len = 0usize;
+        loop {
+            let Some(c) = super::getchar() else { super::sleep_ms(10); continue };
+            match c {
+                b'\n' | b'\r' => break,
+                8 | 127 => len = len.saturating_sub(1),
+                c if c >= 32 && len < buf.len() => { buf[len] = c; len += 1; }
+                _ => {}
+            }
+        }
+        super::print_str("\n");
+        len
+    }
+
+    /// Read all of `path` (e.g. `/etc/passwd`) into `buf`; empty if it
+    /// cannot be opened or is not UTF-8.
+    pub fn load<'a>(path: &str, buf: &'a mut [u8]) -> &'a str {
+        let fd = super::open(path, 0);
+        if fd < 0 { return ""; }
+        let mut len = 0;
+        while len < buf.len() {
+            let n = super::read(fd, &mut buf[len..]);
+            if n <= 0 { break; }
+            len += n as usize;
+        }
+        super::close(fd);
+        core::str::from_utf8(&buf[..len]).unwrap_or("")
+    }
+
+    pub fn parse_passwd(line: &str) -> Option<Passwd<'_>> {
+        let mut f = line.split(':');
+        let name = f.next()?;
+        let _pw  = f.next()?;
+        Some(Passwd {
+            name,
+            uid:   f.next()?.parse().ok()?,
+            gid:   f.next()?.parse().ok()?,
+            gecos: f.next()?,
+            home:  f.next()?,
+            shell: f.next()?,
+        })
+    }
+
+    pub fn parse_group(line: &str) -> Option<Group<'_>> {
+        let mut f = line.split(':');
+        let name = f.next()?;
+        let _pw  = f.next()?;
+        let gid  = f.next()?.parse().ok()?;
+        Some(Group { name, gid, members: f.next().unwrap_or("") })
+    }
+
+    /// The `/etc/passwd` entry whose name is `name` (or whose uid is `name`
+    /// in decimal).
+    pub fn find_user<'a>(passwd: &'a str, name: &str) -> Option<Passwd<'a>> {
+        passwd.lines().filter_map(parse_passwd)
+            .find(|p| p.name == name || name.parse() == Ok(p.uid))
+    }
+
+    pub fn find_uid(passwd: &str, uid: u32) -> Option<Passwd<'_>> {
+        passwd.lines().filter_map(parse_passwd).find(|p| p.uid == uid)
+    }
+
+    pub fn find_gid(group: &str, gid: u32) -> Option<Group<'_>> {
+        group.lines().filter_map(parse_group).find(|g| g.gid == gid)
+    }
+
+    /// Fill `out` with the primary group `gid` followed by every group that
+    /// lists `user` as a member; returns the count.
+    pub fn groups_of(group: &str, user: &str, gid: u32, out: &mut [u32]) -> usize {
+        if out.is_empty() { return 0; }
+        out[0] = gid;
+        let mut n = 1;
+        for g in group.lines().filter_map(parse_group) {
+            if n == out.len() { break; }
+            if g.gid != gid && g.has_member(user) {
+                out[n] = g.gid;
+                n += 1;
+            }
+        }
+        n
+    }
+
+    /// Drop to `user`: supplementary groups from `group`, then gid, then
+    /// uid (the order matters — after setuid there is no privilege left to
+    /// change groups), and set `HOME`, `USER`, `LOGNAME` and `SHELL`.
+    /// Needs an effective uid of 0.  Returns 0 or the failing call's error.
+    pub fn become_user(group: &str, user: &Passwd) -> i64 {
+        let mut gids = [0u32; 32];
+        let n = groups_of(group, user.name, user.gid, &mut gids);
+        let r = super::setgroups(&gids[..n]);
+        if r < 0 { return r; }
+        let r = super::setgid(user.gid);
+        if r < 0 { return r; }
+        let r = super::setuid(user.uid);
+        if r < 0 { return r; }
+        super::setenv("HOME", user.home);
+        super::setenv("USER", user.name);
+        super::setenv("LOGNAME", user.name);
+        super::setenv("SHELL", user.shell);
+        0
+    }
+
+    /// The password field of `user` in `/etc/shadow`.
+    pub fn shadow_hash<'a>(shadow: &'a str, user: &str) -> Option<&'a str> {
+        shadow.lines().find_map(|l| {
+            let mut f = l.split(':');
+            if f.next()? == user { f.next() } else { None }
+        })
+    }
+
+    /// Does `password` match a shadow field?  Empty fields accept any
+    /// password; locked (`!`, `*`) and unknown formats accept none.
+    pub fn verify(password: &[u8], field: &str) -> bool {
+        if field.is_empty() { return true; }
+        let Some(rest) = field.strip_prefix("$oxide$") else { return false };
+        let Some((salt, hex)) = rest.split_once('$') else { return false };
+        let digest = hash(password, salt.as_bytes());
+        let mut diff = (hex.len() != 64) as u8;
+        for (i, b) in hex.bytes().take(64).enumerate() {
+            diff |= b ^ digest[i];
+        }
+        diff == 0
+    }
+
+    /// Hex digest of `password` under `salt`, as stored after the salt.
+    pub fn hash(password: &[u8], salt: &[u8]) -> [u8; 64] {
+        let mut h = Sha256::new();
+        h.update(salt);
+        h.update(password);
+        let mut d = h.finish();
+        for _ in 0..999 {
+            let mut h = Sha256::new();
+            h.update(&d);
+            h.update(salt);
+            h.update(password);
+            d = h.finish();
+        }
+        let mut hex = [0u8; 64];
+        for (i, b) in d.iter().enumerate() {
+            hex[2 * i]     = b"0123456789abcdef"[(b >> 4) as usize];
+            hex[2 * i + 1] = b"0123456789abcdef"[(b & 15) as usize];
+        }
+        hex
+    }
+
+    const K: [u32; 64] = [
+        0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+        0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+        0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+        0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+        0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+        0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+        0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+        0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+    ];
+
+    /// Streaming SHA-256.
+    struct Sha256 {
+        state: [u32; 8],
+        block: [u8; 64],
+        fill:  usize,
+        len:   u64,
+    }
+
+    impl Sha256 {
+        fn new() -> Self {
+            Self {
+                state: [0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a,
+                        0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19],
+                block: [0; 64],
+                fill:  0,
+                len:   0,
+            }
+        }
+
+        fn update(&mut self, data: &[u8]) {
+            for &b in data {
+                self.block[self.fill] = b;
+                self.fill += 1;
+                if self.fill == 64 {
+                    self.compress();
+                    self.fill = 0;
+                }
+            }
+            self.len += data.len() as u64;
+        }
+
+        fn finish(mut self) -> [u8; 32] {
+            let bits = self.len * 8;
+            self.update(&[0x80]);
+            while self.fill != 56 { self.update(&[0]); }
+            self.update(&bits.to_be_bytes());
+            let mut out = [0u8; 32];
+            for (i, w) in self.state.iter().enumerate() {
+                out[4 * i..4 * i + 4].copy_from_slice(&w.to_be_bytes());
+            }
+            out
+        }
+
+        fn compress(&mut self) {
+            let mut w = [0u32; 64];
+            for i in 0..16 {
+                w[i] = u32::from_be_bytes([self.block[4 * i], self.block[4 * i + 1],
+                                           self.block[4 * i + 2], self.block[4 * i + 3]]);
+            }
+            for i in 16..64 {
+                let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+                let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+                w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+            }
+            let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.state;
+            for i in 0..64 {
+                let s1  = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+                let ch  = (e & f) ^ (!e & g);
+                let t1  = h.wrapping_add(s1).wrapping_add(ch).wrapping_add(K[i]).wrapping_add(w[i]);
+                let s0  = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+                let maj = (a & b) ^ (a & c) ^ (b & c);
+                let t2  = s0.wrapping_add(maj);
+                h = g; g = f; f = e;
+                e = d.wrapping_add(t1);
+                d = c; c = b; b = a;
+                a = t1.wrapping_add(t2);
+            }
+            for (s, v) in self.state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+                *s = s.wrapping_add(v);
+            }
+        }
+    }
+}
+
 /// Read the value of environment variable `key` into `buf`.
 /// Returns bytes written on success, negative if not found.
 #[inline]
@@ -550,12 +1709,45 @@ pub fn fork() -> i64 {
     unsafe { raw::syscall0(sys::FORK) }
 }
 
-/// Wait for child `pid` to exit.  Returns child's exit code.
+/// Wait for child `pid` to exit.  Returns its exit code, or 128 + the
+/// signal that killed it (as a shell reports it).
 #[inline]
 pub fn waitpid(pid: u32) -> i64 {
-    unsafe { raw::syscall1(sys::WAIT, pid as u64) }
+    let mut status = 0;
+    let r = wait4(pid as i32, &mut status, 0);
+    if r < 0 { return r; }
+    if wifsignaled(status) { 128 + wtermsig(status) as i64 } else { wexitstatus(status) as i64 }
 }
 
+/// `wait4` option: return 0 instead of blocking if no child is ready.
+pub const WNOHANG: u32 = 1;
+/// `wait4` option: also report children stopped by job control.
+pub const WUNTRACED: u32 = 2;
+/// `wait4` option: also report stopped children continued by SIGCONT.
+pub const WCONTINUED: u32 = 8;
+
+/// Wait for a child (`pid` > 0: that one; -1: any) to exit, or for a
+/// tracee to stop.  Stores the raw status word and returns the child's pid.
+#[inline]
+pub fn wait4(pid: i32, status: &mut i32, options: u32) -> i64 {
+    unsafe {
+        raw::syscall4(sys::WAIT4, pid as i64 as u64, status as *mut i32 as u64, options as u64, 0)
+    }
+}
+
+/// The child called `exit`; `wexitstatus` is its code.
+pub fn wifexited(status: i32) -> bool { status & 0x7f == 0 }
+pub fn wexitstatus(status: i32) -> i32 { (status >> 8) & 0xff }
+/// The child was killed by signal `wtermsig`.
+pub fn wifsignaled(status: i32) -> bool { status & 0x7f != 0 && status & 0x7f != 0x7f }
+pub fn wtermsig(status: i32) -> i32 { status & 0x7f }
+/// The child is stopped (job control, or a tracee's ptrace-stop) by
+/// `wstopsig`.
+pub fn wifstopped(status: i32) -> bool { status & 0xff == 0x7f }
+pub fn wstopsig(status: i32) -> i32 { (status >> 8) & 0xff }
+/// The stopped child was continued (`WCONTINUED`).
+pub fn wifcontinued(status: i32) -> bool { status == 0xffff }
+
 /// Set the heap break to `new_end`.  Pass 0 to query current break.
 /// Returns new (or current) break on success, negative on error.
 #[inline]
@@ -565,17 +1757,16 @@ pub fn brk(new_end: u64) -> i64 {
 
 /// Map `len` bytes of anonymous zeroed memory (MAP_ANONYMOUS|MAP_PRIVATE).
 /// Returns a pointer to the mapped region, or null on failure.
-/// The region persists until the process exits (munmap is a no-op).
 #[inline]
 pub fn mmap_anon(len: usize) -> *mut u8 {
-    let r = unsafe { raw::syscall2(sys::MMAP, 0u64, len as u64) };
+    let r = mmap(0, len, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
     if r <= 0 { core::ptr::null_mut() } else { r as *mut u8 }
 }
 
-/// Unmap a previously mapped region. Currently a no-op stub.
+/// Unmap a previously mapped region and free its pages.
 #[inline]
-pub fn munmap(_ptr: *mut u8, _len: usize) -> i64 {
-    unsafe { raw::syscall2(sys::MUNMAP, _ptr as u64, _len as u64) }
+pub fn munmap(ptr: *mut u8, len: usize) -> i64 {
+    unsafe { raw::syscall2(sys::MUNMAP, ptr as u64, len as u64) }
 }
 
 /// Send SIGKILL to `pid`.  Returns 0 on success.
@@ -584,6 +1775,63 @@ pub fn kill(pid: u32) -> i64 {
     unsafe { raw::syscall1(sys::KILL, pid as u64) }
 }
 
+/// Move process `pid` (0: the caller) into group `pgid` (0: its own pid).
+#[inline]
+pub fn setpgid(pid: u32, pgid: u32) -> i64 {
+    unsafe { raw::syscall2(sys::SETPGID, pid as u64, pgid as u64) }
+}
+
+/// Process group of `pid` (0: the caller).
+#[inline]
+pub fn getpgid(pid: u32) -> i64 {
+    unsafe { raw::syscall1(sys::GETPGID, pid as u64) }
+}
+
+/// Process group of the caller.
+#[inline]
+pub fn getpgrp() -> u32 {
+    unsafe { raw::syscall0(sys::GETPGRP) as u32 }
+}
+
+/// Start a new session, without a controlling terminal, led by the caller.
+/// Returns the session id, or `EPERM` for a process group leader.
+#[inline]
+pub fn setsid() -> i64 {
+    unsafe { raw::syscall0(sys::SETSID) }
+}
+
+/// Session of `pid` (0: the caller).
+#[inline]
+pub fn getsid(pid: u32) -> i64 {
+    unsafe { raw::syscall1(sys::GETSID, pid as u64) }
+}
+
+/// Send `signum` to every process in group `pgrp`.
+#[inline]
+pub fn killpg(pgrp: u32, signum: u32) -> i64 {
+    kill_signal((pgrp as i32).wrapping_neg() as u32, signum)
+}
+
+/// Foreground process group of the terminal `fd`, which must be the
+/// caller's controlling terminal.
+pub fn tcgetpgrp(fd: i32) -> i64 {
+    let mut pgrp: u32 = 0;
+    let r = ioctl(fd, ioctl::TIOCGPGRP, &mut pgrp as *mut u32 as u64);
+    if r < 0 { r } else { pgrp as i64 }
+}
+
+/// Put group `pgrp` in the foreground of the caller's controlling terminal `fd`.
+pub fn tcsetpgrp(fd: i32, pgrp: u32) -> i64 {
+    ioctl(fd, ioctl::TIOCSPGRP, &pgrp as *const u32 as u64)
+}
+
+/// Session the terminal `fd` is the controlling terminal of.
+pub fn tcgetsid(fd: i32) -> i64 {
+    let mut sid: u32 = 0;
+    let r = ioctl(fd, ioctl::TIOCGSID, &mut sid as *mut u32 as u64);
+    if r < 0 { r } else { sid as i64 }
+}
+
 /// Duplicate `old_fd` to `new_fd`.  Returns `new_fd` on success.
 #[inline]
 pub fn dup2(old_fd: i32, new_fd: i32) -> i64 {
@@ -996,7 +2244,7 @@ mod gui_sys {
 /// A raw GUI event as written by the kernel.
 ///
 /// `kind` values:
-/// - 0 = key press (`data[0]` = ASCII byte)
+/// - 0 = key press (`data[0]` = ASCII byte or 0, `data[4..8]` = Unicode scalar u32 LE)
 /// - 1 = mouse move (`data[0..2]` = x u16 LE, `data[2..4]` = y u16 LE)
 /// - 2 = mouse button (`data[0..2]` = x, `data[2..4]` = y, `data[4]` = button, `data[5]` = pressed)
 /// - 3 = focus change (`data[0]` = 1 gained / 0 lost)
@@ -1017,10 +2265,19 @@ impl GuiEvent {
 
     const fn zero() -> Self { Self { kind: 0, data: [0; 12] } }
 
-    /// Returns the key byte if this is a key event.
+    /// Returns the key byte if this is a key event for an ASCII character.
     #[inline]
     pub fn as_key(&self) -> Option<u8> {
-        if self.kind == Self::KEY { Some(self.data[0]) } else { None }
+        if self.kind == Self::KEY && self.data[0] != 0 { Some(self.data[0]) } else { None }
+    }
+
+    /// Returns the character if this is a key event, including the accented
+    /// and other non-ASCII ones dead keys and Compose produce.
+    #[inline]
+    pub fn as_char(&self) -> Option<char> {
+        if self.kind != Self::KEY { return None; }
+        let code = u32::from_le_bytes([self.data[4], self.data[5], self.data[6], self.data[7]]);
+        char::from_u32(code)
     }
 
     /// Returns (x, y) if this is a mouse-move event.
@@ -1188,7 +2445,8 @@ pub fn gui_blit_shm(
 //   ...
 //   [rsp + 8*(argc+1)]    = NULL (end of argv)
 //   [rsp + 8*(argc+2)]    = NULL (end of envp)
-//   [string data follows]
+//   [auxv (type, value) pairs, AT_NULL-terminated]
+//   [string data + 16 AT_RANDOM bytes follow]
 //
 // _start caches these so programs can call `oxide_rt::argc()` / `oxide_rt::arg(i)`.
 
@@ -6,15 +6,21 @@
 //!   - Output redirects: cmd > file, cmd >> file
 //!   - Pipelines: cmd1 | cmd2 | ... | cmdN (up to 8 stages)
 //!   - Environment variables: $VAR expansion, export VAR=val
-//!   - Job control: cmd &, jobs, fg N
+//!   - Job control: cmd &, jobs, fg N, bg N; on its controlling terminal
+//!     every job gets its own process group, Ctrl+Z stops the foreground
+//!     one and background jobs that touch the terminal are stopped
+//!   - Scripts: `sh FILE` runs FILE's lines (`#` starts a comment) and exits
 #![no_std]
 #![no_main]
 
 use oxide_rt::{
-    exit, fork, waitpid, exec_args, getchar, sleep_ms,
+    exit, fork, waitpid, wait4, wifstopped, exec_args, getchar, sleep_ms,
     print_str, print_bytes, readdir, open, write, read, close, dup2, pipe,
-    setenv, getenv_bytes,
+    setenv, getenv_bytes, getrlimit, setrlimit, RLIMIT_CORE, RLIM_INFINITY, arg, argc,
+    getpid, getpgrp, getsid, setpgid, tcgetpgrp, tcsetpgrp, tcgetsid, killpg,
+    sigaction, SIG_DFL, SIG_IGN, WUNTRACED,
 };
+use oxide_rt::sig::{SIGINT, SIGQUIT, SIGTSTP, SIGTTIN, SIGTTOU, SIGCONT};
 
 // ── Open-flag constants ────────────────────────────────────────────────────────
 
@@ -61,6 +67,45 @@ fn readline(buf: &mut [u8]) -> usize {
     len
 }
 
+// ── Script input ─────────────────────────────────────────────────────────────
+
+/// A script file read whole, handed out one command line at a time.
+struct Script {
+    text: [u8; 4096],
+    len:  usize,
+    pos:  usize,
+}
+
+impl Script {
+    fn open(path: &str) -> Option<Self> {
+        let fd = open(path, O_RDONLY);
+        if fd < 0 { return None; }
+        let mut s = Script { text: [0; 4096], len: 0, pos: 0 };
+        while s.len < s.text.len() {
+            let n = read(fd, &mut s.text[s.len..]);
+            if n <= 0 { break; }
+            s.len += n as usize;
+        }
+        close(fd);
+        Some(s)
+    }
+
+    /// Copy the next non-blank, non-comment line into `buf`.
+    fn next_line(&mut self, buf: &mut [u8]) -> Option<usize> {
+        while self.pos < self.len {
+            let rest = &self.text[self.pos..self.len];
+            let end  = rest.iter().position(|&c| c == b'\n').unwrap_or(rest.len());
+            self.pos += end + 1;
+            let line = trim_bytes(&rest[..end]);
+            if line.is_empty() || line[0] == b'#' { continue; }
+            let n = line.len().min(buf.len() - 1);
+            buf[..n].copy_from_slice(&line[..n]);
+            return Some(n);
+        }
+        None
+    }
+}
+
 // ── Byte-slice helpers ────────────────────────────────────────────────────────
 
 fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
@@ -79,31 +124,106 @@ fn trim_bytes(s: &[u8]) -> &[u8] {
     &s[..end]
 }
 
+// ── Job control ───────────────────────────────────────────────────────────────
+
+/// Signals an interactive shell ignores and its jobs get back.
+const JOB_SIGNALS: [u32; 5] = [SIGINT, SIGQUIT, SIGTSTP, SIGTTIN, SIGTTOU];
+
+/// Turn on job control if stdin is our controlling terminal: wait until we
+/// are in its foreground, then put the shell in a group of its own and
+/// take the terminal.  Returns false for scripts and anything not on a
+/// terminal, which run every job in the shell's group.
+fn init_job_control() -> bool {
+    let sid = getsid(0);
+    if sid <= 0 || tcgetsid(0) != sid { return false; }
+    loop {
+        let fg = tcgetpgrp(0);
+        if fg < 0 { return false; }
+        if fg as u32 == getpgrp() { break; }
+        killpg(getpgrp(), SIGTTIN);
+    }
+    for sig in JOB_SIGNALS { sigaction(sig, SIG_IGN); }
+    // A session leader already leads its group and cannot move.
+    setpgid(0, 0);
+    tcsetpgrp(0, getpgrp());
+    true
+}
+
+/// In a freshly forked child: join job `pgid` (0: start one), take the
+/// terminal if it is a foreground job, and restore the signals the shell
+/// ignores.
+fn enter_job(jc: bool, pgid: u32, foreground: bool) {
+    if !jc { return; }
+    setpgid(0, pgid);
+    if foreground { tcsetpgrp(0, if pgid == 0 { getpid() } else { pgid }); }
+    for sig in JOB_SIGNALS { sigaction(sig, SIG_DFL); }
+}
+
+/// Wait for the `live` processes of job `pgid` to finish, or for one to
+/// stop (returns true).  Without job control `pgid` is a single pid.
+fn wait_job(jc: bool, pgid: u32, live: &mut u8) -> bool {
+    while *live > 0 {
+        let mut status = 0;
+        let r = if jc {
+            wait4(-(pgid as i32), &mut status, WUNTRACED)
+        } else {
+            wait4(pgid as i32, &mut status, 0)
+        };
+        if r < 0 { *live = 0; break; }
+        if wifstopped(status) { return true; }
+        *live -= 1;
+    }
+    false
+}
+
+/// Run job `pgid` in the foreground until it finishes or stops, then take
+/// the terminal back.  A stopped job goes into (or stays in) the table.
+fn foreground(jc: bool, jobs: &mut JobTable, pgid: u32, mut live: u8, name: &[u8]) {
+    if jc { tcsetpgrp(0, pgid); }
+    let stopped = wait_job(jc, pgid, &mut live);
+    if jc { tcsetpgrp(0, getpgrp()); }
+    jobs.remove(pgid);
+    if stopped {
+        let jn = jobs.add(pgid, live, name);
+        jobs.stopped[jn - 1] = true;
+        print_str("\n");
+        print_job(jobs, jn - 1);
+    }
+}
+
 // ── Job table ─────────────────────────────────────────────────────────────────
 
 const MAX_JOBS: usize = 8;
 
+/// Background and stopped jobs, by process group (the pid of a job's first
+/// process).
 struct JobTable {
-    pids:  [u32; MAX_JOBS],
-    names: [[u8; 32]; MAX_JOBS],
-    nlens: [u8; MAX_JOBS],
-    count: usize,
+    pgids:   [u32; MAX_JOBS],
+    live:    [u8; MAX_JOBS],
+    stopped: [bool; MAX_JOBS],
+    names:   [[u8; 32]; MAX_JOBS],
+    nlens:   [u8; MAX_JOBS],
+    count:   usize,
 }
 
 impl JobTable {
     const fn new() -> Self {
         Self {
-            pids:  [0; MAX_JOBS],
-            names: [[0u8; 32]; MAX_JOBS],
-            nlens: [0; MAX_JOBS],
-            count: 0,
+            pgids:   [0; MAX_JOBS],
+            live:    [0; MAX_JOBS],
+            stopped: [false; MAX_JOBS],
+            names:   [[0u8; 32]; MAX_JOBS],
+            nlens:   [0; MAX_JOBS],
+            count:   0,
         }
     }
 
-    fn add(&mut self, pid: u32, name: &[u8]) -> usize {
+    fn add(&mut self, pgid: u32, live: u8, name: &[u8]) -> usize {
         if self.count >= MAX_JOBS { return 0; }
         let idx = self.count;
-        self.pids[idx] = pid;
+        self.pgids[idx]   = pgid;
+        self.live[idx]    = live;
+        self.stopped[idx] = false;
         let n = name.len().min(31);
         self.names[idx][..n].copy_from_slice(&name[..n]);
         self.nlens[idx] = n as u8;
@@ -111,14 +231,16 @@ impl JobTable {
         idx + 1 // 1-based job number
     }
 
-    fn remove_pid(&mut self, pid: u32) {
+    fn remove(&mut self, pgid: u32) {
         for i in 0..self.count {
-            if self.pids[i] == pid {
+            if self.pgids[i] == pgid {
                 // Shift remaining jobs down.
                 for j in i..self.count - 1 {
-                    self.pids[j]  = self.pids[j + 1];
-                    self.names[j] = self.names[j + 1];
-                    self.nlens[j] = self.nlens[j + 1];
+                    self.pgids[j]   = self.pgids[j + 1];
+                    self.live[j]    = self.live[j + 1];
+                    self.stopped[j] = self.stopped[j + 1];
+                    self.names[j]   = self.names[j + 1];
+                    self.nlens[j]   = self.nlens[j + 1];
                 }
                 self.count -= 1;
                 return;
@@ -126,12 +248,27 @@ impl JobTable {
         }
     }
 
-    fn get_by_num(&self, n: usize) -> Option<u32> {
+    /// Index of job `n` (1-based; 0: the last one).
+    fn index(&self, n: usize) -> Option<usize> {
+        let n = if n == 0 { self.count } else { n };
         if n == 0 || n > self.count { return None; }
-        Some(self.pids[n - 1])
+        Some(n - 1)
     }
 }
 
+/// `[N] pgid  Running|Stopped  name`
+fn print_job(jobs: &JobTable, i: usize) {
+    let mut nbuf = [0u8; 12];
+    let mut pbuf = [0u8; 12];
+    print_str("[");
+    print_bytes(fmt_u32((i + 1) as u32, &mut nbuf));
+    print_str("] ");
+    print_bytes(fmt_u32(jobs.pgids[i], &mut pbuf));
+    print_str(if jobs.stopped[i] { "  Stopped  " } else { "  Running  " });
+    print_bytes(&jobs.names[i][..jobs.nlens[i] as usize]);
+    print_str("\n");
+}
+
 // ── Redirect parser ───────────────────────────────────────────────────────────
 
 fn parse_redirect(line: &[u8]) -> (&[u8], Option<(bool, &[u8])>) {
@@ -212,9 +349,10 @@ fn split_pipes<'a>(line: &'a [u8], segs: &mut [&'a [u8]; 8]) -> usize {
 
 // ── Pipeline executor ─────────────────────────────────────────────────────────
 // Runs a slice of command segments as a pipeline.
-// If `final_out_fd >= 0` it replaces stdout of the last stage.
+// If `final_out_fd >= 0` it replaces stdout of the last stage.  With job
+// control all stages share one process group, the first child's.
 
-fn run_pipeline(segs: &[&[u8]], final_out_fd: i32) {
+fn run_pipeline(jc: bool, jobs: &mut JobTable, segs: &[&[u8]], final_out_fd: i32) {
     let n = segs.len();
     if n == 0 { return; }
 
@@ -230,10 +368,12 @@ fn run_pipeline(segs: &[&[u8]], final_out_fd: i32) {
     }
 
     let mut child_pids = [0u32; 8];
+    let mut pgid = 0u32;
 
     for i in 0..n {
         let pid = fork();
         if pid == 0 {
+            enter_job(jc, pgid, true);
             // ── Child: wire stdin/stdout ──────────────────────────────────────
             if i > 0        { dup2(pipe_rd[i - 1], 0); }
             if i < n - 1    { dup2(pipe_wr[i],     1); }
@@ -262,6 +402,10 @@ fn run_pipeline(segs: &[&[u8]], final_out_fd: i32) {
             exit(127);
         } else if pid > 0 {
             child_pids[i] = pid as u32;
+            if jc {
+                if pgid == 0 { pgid = pid as u32; }
+                setpgid(pid as u32, pgid);
+            }
         } else {
             print_str("sh: fork failed\n");
         }
@@ -275,6 +419,14 @@ fn run_pipeline(segs: &[&[u8]], final_out_fd: i32) {
     if final_out_fd >= 0 { close(final_out_fd); }
 
     // Wait for all children.
+    if jc && pgid != 0 {
+        let live = child_pids.iter().filter(|&&p| p > 0).count() as u8;
+        let mut name = [0u8; 32];
+        let len = segs[0].len().min(name.len());
+        name[..len].copy_from_slice(&segs[0][..len]);
+        foreground(jc, jobs, pgid, live, trim_bytes(&name[..len]));
+        return;
+    }
     for i in 0..n {
         if child_pids[i] > 0 { waitpid(child_pids[i]); }
     }
@@ -325,11 +477,13 @@ fn print_help() {
     print_str("  clear                scroll screen\n");
     print_str("  jobs                 list background jobs\n");
     print_str("  fg [N]               bring job N to foreground\n");
+    print_str("  bg [N]               continue stopped job N in background\n");
+    print_str("  ulimit -c [N]        core file limit (KiB or 'unlimited')\n");
     print_str("  help                 this message\n");
     print_str("  exit                 quit the shell\n");
     print_str("\nExternal programs:\n");
     print_str("  ls cat grep wc head tail sort echo sleep kill touch\n");
-    print_str("  ps cp mkdir rm mv wget edit nc filemanager terminal\n");
+    print_str("  ps cp mkdir rm mv wget edit nc filemanager terminal strace\n");
     print_str("\nPipelines:   cmd1 | cmd2 | cmd3\n");
     print_str("Redirects:   cmd > file   cmd >> file\n");
     print_str("Variables:   export PATH=/bin    echo $HOME\n");
@@ -371,20 +525,39 @@ fn parse_u32(s: &[u8]) -> Option<u32> {
 
 #[unsafe(no_mangle)]
 pub extern "C" fn oxide_main() {
-    print_str("\nOxideOS Shell v0.3\n");
-    print_str("Type 'help' for available commands.\n\n");
+    let mut script = match arg(1).filter(|_| argc() > 1) {
+        Some(path) => match Script::open(path) {
+            Some(s) => Some(s),
+            None => {
+                print_str("sh: cannot open "); print_str(path); print_str("\n");
+                exit(127);
+            }
+        },
+        None => None,
+    };
+    if script.is_none() {
+        print_str("\nOxideOS Shell v0.3\n");
+        print_str("Type 'help' for available commands.\n\n");
+    }
 
     let mut line_buf   = [0u8; 256];
     let mut expand_buf = [0u8; 512];
     let mut cwd: &str  = "/";
     let mut jobs       = JobTable::new();
+    let jc             = script.is_none() && init_job_control();
 
     loop {
-        // Prompt.
-        print_str(cwd);
-        print_str(" $ ");
-
-        let len = readline(&mut line_buf);
+        let len = match script.as_mut() {
+            Some(s) => match s.next_line(&mut line_buf) {
+                Some(n) => n,
+                None    => exit(0),
+            },
+            None => {
+                print_str(cwd);
+                print_str(" $ ");
+                readline(&mut line_buf)
+            }
+        };
         if len == 0 { continue; }
 
         let raw_bytes = &line_buf[..len];
@@ -429,7 +602,7 @@ pub extern "C" fn oxide_main() {
 
         // ── Pipeline: 2 or more stages → run_pipeline ─────────────────────────
         if n_segs > 1 {
-            run_pipeline(&pipe_segs[..n_segs], redir_fd);
+            run_pipeline(jc, &mut jobs, &pipe_segs[..n_segs], redir_fd);
             line_buf[..len].fill(0);
             expand_buf[..exp_len].fill(0);
             continue;
@@ -472,43 +645,31 @@ pub extern "C" fn oxide_main() {
 
             "help" => print_help(),
 
-            // jobs — list background jobs
+            // jobs — list background and stopped jobs
             "jobs" => {
                 if jobs.count == 0 {
                     print_str("(no background jobs)\n");
                 } else {
-                    for i in 0..jobs.count {
-                        let mut nbuf = [0u8; 12];
-                        let jn = fmt_u32((i + 1) as u32, &mut nbuf);
-                        let mut pbuf = [0u8; 12];
-                        let pn = fmt_u32(jobs.pids[i], &mut pbuf);
-                        print_str("[");
-                        print_bytes(jn);
-                        print_str("] ");
-                        print_bytes(pn);
-                        print_str("  ");
-                        print_bytes(&jobs.names[i][..jobs.nlens[i] as usize]);
-                        print_str("\n");
-                    }
+                    for i in 0..jobs.count { print_job(&jobs, i); }
                 }
             }
 
-            // fg [N] — wait for background job N (default: last job)
+            // fg [N] — continue job N (default: last job) in the foreground
             "fg" => {
-                let jnum = if args.is_empty() {
-                    jobs.count
-                } else {
-                    parse_u32(args.as_bytes()).unwrap_or(0) as usize
-                };
-                match jobs.get_by_num(jnum) {
-                    Some(pid) => {
-                        let mut nbuf = [0u8; 12];
-                        let pn = fmt_u32(pid, &mut nbuf);
-                        print_str("fg: pid ");
-                        print_bytes(pn);
+                let jnum = parse_u32(args.as_bytes()).unwrap_or(0) as usize;
+                match jobs.index(jnum) {
+                    Some(i) => {
+                        let (pgid, live) = (jobs.pgids[i], jobs.live[i]);
+                        let mut name = [0u8; 32];
+                        let n = jobs.nlens[i] as usize;
+                        name[..n].copy_from_slice(&jobs.names[i][..n]);
+                        print_bytes(&name[..n]);
                         print_str("\n");
-                        waitpid(pid);
-                        jobs.remove_pid(pid);
+                        if jc {
+                            tcsetpgrp(0, pgid);
+                            killpg(pgid, SIGCONT);
+                        }
+                        foreground(jc, &mut jobs, pgid, live, &name[..n]);
                     }
                     None => {
                         print_str("fg: no such job\n");
@@ -516,6 +677,20 @@ pub extern "C" fn oxide_main() {
                 }
             }
 
+            // bg [N] — continue stopped job N (default: last job) in the background
+            "bg" => {
+                let jnum = parse_u32(args.as_bytes()).unwrap_or(0) as usize;
+                match jobs.index(jnum) {
+                    Some(i) if jc => {
+                        killpg(jobs.pgids[i], SIGCONT);
+                        jobs.stopped[i] = false;
+                        print_job(&jobs, i);
+                    }
+                    Some(_) => print_str("bg: no job control\n"),
+                    None    => print_str("bg: no such job\n"),
+                }
+            }
+
             // export VAR=val  or  export VAR (no value → empty string)
             "export" => {
                 if let Some(eq) = args.find('=') {
@@ -527,6 +702,36 @@ pub extern "C" fn oxide_main() {
                 }
             }
 
+            // ulimit -c [N|unlimited] — show or set the core file size limit
+            // (in 1 KiB blocks, as bash counts it).
+            "ulimit" => {
+                let value = args.strip_prefix("-c").map(str::trim);
+                match value {
+                    Some("") => match getrlimit(RLIMIT_CORE) {
+                        Ok((RLIM_INFINITY, _)) => print_str("unlimited\n"),
+                        Ok((soft, _)) => {
+                            let mut nbuf = [0u8; 12];
+                            print_bytes(fmt_u32((soft / 1024) as u32, &mut nbuf));
+                            print_str("\n");
+                        }
+                        Err(_) => print_str("ulimit: cannot read limit\n"),
+                    },
+                    Some(v) => {
+                        let soft = if v == "unlimited" {
+                            Some(RLIM_INFINITY)
+                        } else {
+                            parse_u32(v.as_bytes()).map(|kb| kb as u64 * 1024)
+                        };
+                        let hard = getrlimit(RLIMIT_CORE).map(|(_, h)| h).unwrap_or(RLIM_INFINITY);
+                        match soft {
+                            Some(soft) if setrlimit(RLIMIT_CORE, soft, hard) == 0 => {}
+                            _ => print_str("ulimit: invalid limit\n"),
+                        }
+                    }
+                    None => print_str("usage: ulimit -c [N|unlimited]\n"),
+                }
+            }
+
             _ if cmd.is_empty() => {
                 if redir_fd >= 0 { close(redir_fd); }
             }
@@ -535,14 +740,16 @@ pub extern "C" fn oxide_main() {
             prog => {
                 let pid = fork();
                 if pid == 0 {
+                    enter_job(jc, 0, !background);
                     if redir_fd >= 0 { dup2(redir_fd, 1); close(redir_fd); }
                     exec_args(prog, args);
                     print_str("sh: "); print_str(prog); print_str(": not found\n");
                     exit(127);
                 } else if pid > 0 {
+                    if jc { setpgid(pid as u32, pid as u32); }
                     if redir_fd >= 0 { close(redir_fd); }
                     if background {
-                        let jn = jobs.add(pid as u32, prog.as_bytes());
+                        let jn = jobs.add(pid as u32, 1, prog.as_bytes());
                         let mut nbuf1 = [0u8; 12];
                         let mut nbuf2 = [0u8; 12];
                         print_str("[");
@@ -551,7 +758,7 @@ pub extern "C" fn oxide_main() {
                         print_bytes(fmt_u32(pid as u32, &mut nbuf2));
                         print_str("\n");
                     } else {
-                        waitpid(pid as u32);
+                        foreground(jc, &mut jobs, pid as u32, 1, prog.as_bytes());
                     }
                 } else {
                     if redir_fd >= 0 { close(redir_fd); }
@@ -3,6 +3,11 @@
 //! Entry point is `submit` — called when the user presses Enter.
 
 use oxide_rt::{exit, fork, waitpid, exec, getpid, pipe, dup2, close};
+use oxide_rt::{open, read, write, ioctl, getchar, sleep_ms, setsid, wait4, Winsize, WNOHANG};
+use oxide_rt::{posix_openpt, grantpt, unlockpt, ptsname, O_RDWR, O_NONBLOCK};
+use oxide_rt::{wifsignaled, wtermsig, wexitstatus};
+use oxide_rt::ioctl::TIOCSWINSZ;
+use crate::draw::{redraw_full, draw_input_area};
 use crate::constants::*;
 use crate::fixstr::FixStr;
 use crate::fmt::{fmt_u32, fmt_i64};
@@ -139,6 +144,7 @@ pub fn run_builtin(term: &mut Terminal, cmd: &str, args: &str) -> bool {
 // ── External command execution ────────────────────────────────────────────────
 
 pub fn run_external(term: &mut Terminal, cmd: &str, background: bool) {
+    if !background && run_on_pty(term, cmd) { return; }
     let child = fork();
     if child < 0 { term.log("[error] fork failed", COL_ERROR); return; }
     if child == 0 { let _ = exec(cmd); exit(127); }
@@ -149,7 +155,10 @@ pub fn run_external(term: &mut Terminal, cmd: &str, background: bool) {
         term.log(line.as_str(), COL_SUCCESS);
         return;
     }
-    let code = waitpid(child as u32);
+    report_exit(term, cmd, waitpid(child as u32));
+}
+
+fn report_exit(term: &mut Terminal, cmd: &str, code: i64) {
     if code == 127 {
         let mut line = FixStr::<LINE_CAP>::new();
         line.push_str("[error] '"); line.push_str(cmd);
@@ -163,6 +172,110 @@ pub fn run_external(term: &mut Terminal, cmd: &str, background: bool) {
     }
 }
 
+// ── Foreground commands on a pseudo-terminal ──────────────────────────────────
+
+/// Run `cmd` in the foreground on a fresh pty, so it gets a session of its
+/// own: its output streams into the history as it arrives, keystrokes are
+/// forwarded to it, and Ctrl+C interrupts the command, not the terminal.
+/// Returns false if no pty could be set up (the caller falls back to a
+/// plain fork/exec).
+fn run_on_pty(term: &mut Terminal, cmd: &str) -> bool {
+    let master = posix_openpt(O_RDWR | O_NONBLOCK);
+    if master < 0 { return false; }
+    let mut name = [0u8; 16];
+    let slave = match (grantpt(master), unlockpt(master)) {
+        (0, 0) => ptsname(master, &mut name),
+        _      => None,
+    };
+    let Some(slave) = slave else { close(master); return false; };
+
+    let ws = Winsize {
+        ws_row: VISIBLE_LINES as u16, ws_col: ((WIN_W - 2 * PAD_X) / CHAR_W) as u16,
+        ws_xpixel: WIN_W as u16, ws_ypixel: HIST_H as u16,
+    };
+    ioctl(master, TIOCSWINSZ, &ws as *const Winsize as u64);
+
+    let child = fork();
+    if child < 0 { close(master); return false; }
+    if child == 0 {
+        close(master);
+        // A session of its own, so the slave becomes its controlling
+        // terminal with the command in the foreground.
+        setsid();
+        let fd = open(slave, O_RDWR);
+        if fd < 0 { exit(126); }
+        for std in 0..3 { dup2(fd, std); }
+        if fd > 2 { close(fd); }
+        let _ = exec(cmd);
+        exit(127);
+    }
+    // The partial output line (a prompt, or echo of what is being typed)
+    // is shown in the input area until its newline arrives.
+    let pid = getpid();
+    let mut esc = Esc::None;
+    let mut code = None;
+    let mut buf = [0u8; 256];
+    loop {
+        let n = read(master, &mut buf);
+        if n > 0 {
+            for &b in &buf[..n as usize] { feed(term, &mut esc, b); }
+            draw_input_area(term);
+        }
+        let key = getchar();
+        if let Some(c) = key { write(master, &[c]); }
+        if code.is_none() {
+            let mut status = 0;
+            if wait4(child as i32, &mut status, WNOHANG) == child {
+                code = Some(if wifsignaled(status) { 128 + wtermsig(status) as i64 }
+                            else { wexitstatus(status) as i64 });
+            }
+        }
+        // Once the child is gone, keep reading until the master runs dry.
+        if let (Some(code), true) = (code, n <= 0) {
+            if !term.input.is_empty() { feed(term, &mut esc, b'\n'); }
+            close(master);
+            report_exit(term, cmd, code);
+            return true;
+        }
+        if n <= 0 && key.is_none() {
+            redraw_full(term, pid);
+            term.dirty = false;
+            sleep_ms(10);
+        }
+    }
+}
+
+/// Where `feed` is in an escape sequence it is skipping.
+#[derive(Clone, Copy, PartialEq)]
+enum Esc { None, Esc, Csi }
+
+/// One byte of pty output: complete lines go to the history, the rest
+/// builds up the partial line; escape sequences are dropped.
+fn feed(term: &mut Terminal, esc: &mut Esc, b: u8) {
+    match *esc {
+        Esc::Esc => { *esc = if b == b'[' { Esc::Csi } else { Esc::None }; return; }
+        Esc::Csi => { if (0x40..=0x7E).contains(&b) { *esc = Esc::None; } return; }
+        Esc::None => {}
+    }
+    match b {
+        0x1B  => *esc = Esc::Esc,
+        b'\n' => {
+            let mut line = FixStr::<256>::new();
+            line.push_str(term.input.as_str());
+            term.log(line.as_str(), COL_DEFAULT);
+            term.input.clear();
+        }
+        8 | 127 => { if !term.input.is_empty() { term.input.remove(term.input.len - 1); } }
+        b'\t' => term.input.push(b' '),
+        0x20..=0x7E => {
+            if term.input.len + 1 >= LINE_CAP { feed(term, esc, b'\n'); }
+            term.input.push(b);
+        }
+        _ => {}
+    }
+    term.cursor = term.input.len;
+}
+
 // ── Pipe execution ────────────────────────────────────────────────────────────
 
 pub fn run_pipe(term: &mut Terminal, left_cmd: &str, right_cmd: &str) {